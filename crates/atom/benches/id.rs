@@ -0,0 +1,35 @@
+use std::convert::Infallible;
+use std::hint::black_box;
+
+use atom::{AtomId, CalculateRoot, ComputeHash};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+/// A stand-in for a store specific root, such as a Git commit id.
+struct Root([u8; 20]);
+
+impl CalculateRoot<[u8; 20]> for Root {
+    type Error = Infallible;
+
+    fn calculate_root(&self) -> Result<[u8; 20], Self::Error> {
+        Ok(self.0)
+    }
+}
+
+fn hash(c: &mut Criterion) {
+    let root = Root([0x5a; 20]);
+    let ids: Vec<AtomId<[u8; 20]>> = ["foo", "ひらがな", &"a".repeat(128)]
+        .into_iter()
+        .map(|id| AtomId::compute(&root, id.try_into().unwrap()).unwrap())
+        .collect();
+
+    let mut group = c.benchmark_group("atom_id");
+    for id in &ids {
+        group.bench_function(format!("hash/{}", id.id().len()), |b| {
+            b.iter(|| *black_box(id).compute_hash())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, hash);
+criterion_main!(benches);
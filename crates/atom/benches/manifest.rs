@@ -0,0 +1,43 @@
+use std::hint::black_box;
+
+use atom::{Atom, Manifest};
+use criterion::{Criterion, criterion_group, criterion_main};
+use semver::Version;
+
+fn manifest() -> Manifest {
+    Manifest {
+        atom: Atom {
+            id: "foo".try_into().unwrap(),
+            version: Version::new(0, 1, 0),
+            kind: None,
+            description: Some("a benchmark atom".into()),
+            keywords: Vec::new(),
+            license: None,
+            exclude: vec![],
+            include: vec![],
+            min_format: None,
+        },
+        deps: Default::default(),
+        artifacts: Default::default(),
+    }
+}
+
+fn serialize(c: &mut Criterion) {
+    let manifest = manifest();
+    c.bench_function("manifest/serialize", |b| {
+        b.iter(|| toml_edit::ser::to_string_pretty(black_box(&manifest)).unwrap())
+    });
+}
+
+fn deserialize(c: &mut Criterion) {
+    let content = toml_edit::ser::to_string_pretty(&manifest()).unwrap();
+    c.bench_function("manifest/get_atom", |b| {
+        b.iter(|| Manifest::get_atom(black_box(&content)).unwrap())
+    });
+    c.bench_function("manifest/from_str", |b| {
+        b.iter(|| black_box(&content).parse::<Manifest>().unwrap())
+    });
+}
+
+criterion_group!(benches, serialize, deserialize);
+criterion_main!(benches);
@@ -0,0 +1,64 @@
+use atom::publish::Builder;
+use atom::publish::git::GitPublisher;
+use atom::store::Init;
+use atom::store::git::synthetic::Synthetic;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use gix::actor::SignatureRef;
+use gix::config::{File, Source};
+use tempfile::TempDir;
+
+const SIZES: &[usize] = &[10, 100, 1_000, 10_000];
+
+/// Create a repository whose `HEAD` contains `n` synthetic atoms, each in a directory of its
+/// own, along with an initialized remote for it to publish to.
+fn synthetic_repo(n: usize) -> Result<(TempDir, TempDir), anyhow::Error> {
+    let sig = SignatureRef::default();
+    let repo_dir = tempfile::tempdir()?;
+    let remote_dir = tempfile::tempdir()?;
+    let repo = gix::init(repo_dir.as_ref())?;
+    let remote = gix::init_bare(remote_dir.as_ref())?;
+    let no_parents: Vec<gix::ObjectId> = vec![];
+    remote.commit_as(
+        sig,
+        sig,
+        "HEAD",
+        "init",
+        remote.empty_tree().id(),
+        no_parents,
+    )?;
+
+    let config_file = repo.git_dir().join("config");
+    let mut config = File::from_path_no_includes(config_file.clone(), Source::Local)?;
+    let mut origin = repo.remote_at(format!("file://{}", remote.git_dir().display()).as_str())?;
+    origin.save_as_to("origin", &mut config)?;
+    config.write_to(&mut std::fs::File::create(config_file)?)?;
+
+    let repo = gix::open(repo_dir.as_ref())?;
+    repo.find_remote("origin")?.ekala_init()?;
+
+    Synthetic::new(n).generate(&repo)?;
+
+    Ok((repo_dir, remote_dir))
+}
+
+fn validate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate");
+    group.sample_size(20);
+    for &n in SIZES {
+        let (dir, _remote) = synthetic_repo(n).unwrap();
+        let repo = gix::open(dir.as_ref()).unwrap();
+        let publisher = GitPublisher::new(&repo, "origin", "HEAD").unwrap();
+
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter(|| {
+                let (atoms, _) = publisher.build().unwrap();
+                assert_eq!(atoms.len(), n);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, validate);
+criterion_main!(benches);
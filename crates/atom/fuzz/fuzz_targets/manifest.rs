@@ -0,0 +1,15 @@
+//! Fuzz the parsing of Atom manifests, both of the `[atom]` table alone, as done when
+//! validating a repository, and of the whole manifest.
+//!
+//! ```console
+//! cargo +nightly fuzz run manifest corpus/manifest seeds/manifest
+//! ```
+#![no_main]
+
+use atom::Manifest;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = Manifest::get_atom(input);
+    let _ = input.parse::<Manifest>();
+});
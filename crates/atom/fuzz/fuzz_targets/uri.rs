@@ -0,0 +1,32 @@
+//! Fuzz the parsing of Atom URIs, and the rendering of those which parse.
+//!
+//! ```console
+//! cargo +nightly fuzz run uri corpus/uri seeds/uri
+//! ```
+#![no_main]
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use atom::uri::Uri;
+use libfuzzer_sys::fuzz_target;
+
+/// Fixed aliases, rather than those of whoever runs the fuzzer, so that failures reproduce.
+static ALIASES: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+    [
+        ("gh", "github.com"),
+        ("gl", "gitlab.com"),
+        ("bb", "bitbucket.org"),
+        ("pkgs", "gh:nixos/nixpkgs"),
+        ("work", "example.com/org"),
+    ]
+    .into_iter()
+    .map(|(alias, url)| (alias.to_owned(), url.to_owned()))
+    .collect()
+});
+
+fuzz_target!(|input: &str| {
+    if let Ok(uri) = Uri::parse_with(input, &ALIASES) {
+        let _ = uri.to_string();
+    }
+});
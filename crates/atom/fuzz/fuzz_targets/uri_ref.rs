@@ -0,0 +1,12 @@
+//! Fuzz the splitting of Atom URIs into their components, before any validation.
+//!
+//! ```console
+//! cargo +nightly fuzz run uri_ref corpus/uri_ref seeds/uri
+//! ```
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = format!("{:?}", atom::uri::parse_ref(input));
+});
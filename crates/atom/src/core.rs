@@ -5,6 +5,7 @@ use semver::Version;
 use serde::{Deserialize, Serialize};
 
 use super::id::Id;
+use crate::manifest::{FormatError, Kind, MANIFEST_FORMAT};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 /// Represents the deserialized form of an Atom, directly constructed from the TOML manifest.
@@ -18,9 +19,58 @@ pub struct Atom {
     /// The version of the Atom.
     pub version: Version,
 
+    #[serde(rename = "trait", default, skip_serializing_if = "Option::is_none")]
+    /// The kind of the Atom, declared by the optional `trait` key.
+    pub kind: Option<Kind>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     /// An optional description of the Atom.
     pub description: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// Keywords describing the Atom, e.g. to search for it by.
+    pub keywords: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The license of the Atom, as an SPDX license expression.
+    pub license: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// Globs of paths in the Atom's content, relative to it, left out of it when published.
+    pub exclude: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// Paths outside of the Atom's directory, relative to its manifest, included in its content
+    /// when published.
+    pub include: Vec<String>,
+
+    #[serde(
+        rename = "min-format",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    /// The oldest [manifest format](crate::MANIFEST_FORMAT) which understands the Atom,
+    /// declared by the optional `min-format` key.
+    pub min_format: Option<u32>,
+}
+
+impl Atom {
+    /// Check that this version of the crate understands the manifest format the Atom requires,
+    /// so it is refused, rather than misinterpreted, by clients too old for it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the Atom requires a manifest format newer than
+    /// [`crate::MANIFEST_FORMAT`].
+    pub fn supported(&self) -> Result<(), FormatError> {
+        match self.min_format {
+            Some(required) if required > MANIFEST_FORMAT => Err(FormatError {
+                id: self.id.clone(),
+                required,
+            }),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug)]
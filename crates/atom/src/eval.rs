@@ -0,0 +1,181 @@
+//! # Evaluation Cache
+//!
+//! Evaluating an Atom, e.g. to the hashes of its derivations, can be far more expensive than
+//! looking up the result of a previous evaluation. This module caches evaluation outputs,
+//! keyed by the hash of the [`AtomId`] and the Atom's version, so that repeated evaluations of
+//! an unchanged Atom can be skipped.
+//!
+//! As an unpublished Atom may change without its version changing, each entry additionally
+//! records a fingerprint of the Atom's content, and only a matching fingerprint is a hit.
+//! Outputs are opaque bytes to the cache.
+#[cfg(test)]
+mod tests;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use semver::Version;
+
+use crate::{AtomId, ComputeHash};
+
+/// The key an evaluation output is cached under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+    hash: String,
+    version: Version,
+    content: String,
+}
+
+impl Key {
+    /// Construct the key for the given version of an Atom, with the given content fingerprint.
+    pub fn new<R>(id: &AtomId<R>, version: &Version, content: String) -> Self
+    where
+        for<'id> AtomId<R>: ComputeHash<'id, R>,
+    {
+        Key {
+            hash: id.compute_hash().to_string(),
+            version: version.clone(),
+            content,
+        }
+    }
+
+    /// The path of the entry, relative to the root of a cache.
+    #[must_use]
+    pub fn path(&self) -> PathBuf {
+        Path::new(&self.hash)
+            .join(self.version.to_string())
+            .join(&self.content)
+    }
+
+    /// The hash of the [`AtomId`] this key belongs to.
+    #[must_use]
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    /// The version of the Atom this key belongs to.
+    #[must_use]
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// The fingerprint of the Atom's content this key belongs to.
+    #[must_use]
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+/// A cache of evaluation outputs on the local filesystem.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    dir: PathBuf,
+}
+
+impl EvalCache {
+    /// Open the cache rooted at the given directory, which is created on first write.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        EvalCache { dir: dir.into() }
+    }
+
+    /// Return the cached output for the given key, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if an existing entry cannot be read.
+    pub fn get(&self, key: &Key) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(key.path())) {
+            Ok(output) => Ok(Some(output)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Cache the output for the given key, replacing any previous entry for the same version
+    /// of the Atom.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the entry cannot be written.
+    pub fn put(&self, key: &Key, output: &[u8]) -> io::Result<()> {
+        let path = self.dir.join(key.path());
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
+        fs::create_dir_all(parent)?;
+
+        // write atomically, so concurrent evaluations never observe a partial entry
+        let mut tmp = tempfile::Builder::new()
+            .prefix(".")
+            .suffix(".tmp")
+            .tempfile_in(parent)?;
+        tmp.write_all(output)?;
+        tmp.persist(&path).map_err(|e| e.error)?;
+
+        // entries for outdated content of the same version are never hit again, while those
+        // still being written, by name, are left to their writers
+        for entry in fs::read_dir(parent)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name != key.content() && !name.as_encoded_bytes().starts_with(b".") {
+                match fs::remove_file(entry.path()) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {},
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Return the content directory of the Atom whose manifest is at the given path.
+#[must_use]
+pub fn content_dir(manifest: &Path) -> PathBuf {
+    crate::core::AtomPaths::new(manifest)
+        .content()
+        .to_path_buf()
+}
+
+/// Compute a fingerprint of the content of the directory at `dir`, from the relative paths and
+/// contents of the files under it. Symbolic links are never followed, but fingerprinted by
+/// their target, so that a link out of the directory neither escapes it nor goes unnoticed.
+///
+/// # Errors
+///
+/// This function will return an error if the directory cannot be walked, or a file or link read.
+pub fn fingerprint(dir: &Path) -> io::Result<String> {
+    fn walk(dir: &Path, files: &mut Vec<(PathBuf, bool)>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let kind = entry.file_type()?;
+            if kind.is_dir() {
+                walk(&entry.path(), files)?;
+            } else {
+                files.push((entry.path(), kind.is_symlink()));
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(dir, &mut files)?;
+    files.sort_unstable();
+
+    let mut hasher = blake3::Hasher::new();
+    for (file, link) in files {
+        let rel = file.strip_prefix(dir).unwrap_or(&file);
+        let content = if link {
+            fs::read_link(&file)?.into_os_string().into_encoded_bytes()
+        } else {
+            fs::read(&file)?
+        };
+        hasher.update(rel.as_os_str().as_encoded_bytes());
+        // a link is told apart from a file holding its target
+        hasher.update(&[u8::from(link)]);
+        hasher.update(&(content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
@@ -0,0 +1,90 @@
+use anyhow::Context;
+
+use super::*;
+
+fn key(content: &str) -> Key {
+    Key {
+        hash: "abc".into(),
+        version: Version::new(0, 1, 0),
+        content: content.into(),
+    }
+}
+
+#[test]
+fn cache_round_trip() -> Result<(), anyhow::Error> {
+    let dir = tempfile::tempdir()?;
+    let cache = EvalCache::new(dir.path());
+
+    assert_eq!(cache.get(&key("one"))?, None);
+    cache.put(&key("one"), b"{}")?;
+    assert_eq!(cache.get(&key("one"))?.as_deref(), Some(&b"{}"[..]));
+
+    // a new fingerprint for the same version replaces the outdated entry
+    cache.put(&key("two"), b"[]")?;
+    assert_eq!(cache.get(&key("one"))?, None);
+    assert_eq!(cache.get(&key("two"))?.as_deref(), Some(&b"[]"[..]));
+    Ok(())
+}
+
+#[test]
+fn fingerprint_tracks_content() -> Result<(), anyhow::Error> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("sub"))?;
+    fs::write(dir.path().join("sub/file"), "a")?;
+
+    let before = fingerprint(dir.path())?;
+    assert_eq!(before, fingerprint(dir.path())?);
+
+    fs::write(dir.path().join("sub/file"), "b")?;
+    assert_ne!(before, fingerprint(dir.path())?);
+    Ok(())
+}
+
+#[test]
+fn put_spares_pending_entries() -> Result<(), anyhow::Error> {
+    let dir = tempfile::tempdir()?;
+    let cache = EvalCache::new(dir.path());
+    cache.put(&key("one"), b"{}")?;
+
+    // an entry of another evaluation, still being written
+    let parent = dir.path().join(key("one").path());
+    let parent = parent.parent().context("no parent")?;
+    fs::write(parent.join(".pending.tmp"), "")?;
+
+    cache.put(&key("two"), b"[]")?;
+    assert_eq!(cache.get(&key("one"))?, None);
+    assert_eq!(cache.get(&key("two"))?.as_deref(), Some(&b"[]"[..]));
+    assert!(parent.join(".pending.tmp").exists());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn fingerprint_links_by_target() -> Result<(), anyhow::Error> {
+    use std::os::unix::fs::symlink;
+
+    let outside = tempfile::tempdir()?;
+    fs::write(outside.path().join("file"), "a")?;
+    let dir = tempfile::tempdir()?;
+    symlink(outside.path(), dir.path().join("link"))?;
+
+    // the target of the link is never walked
+    let before = fingerprint(dir.path())?;
+    fs::write(outside.path().join("file"), "b")?;
+    assert_eq!(before, fingerprint(dir.path())?);
+
+    // while where it points to is fingerprinted
+    fs::remove_file(dir.path().join("link"))?;
+    symlink(dir.path(), dir.path().join("link"))?;
+    let linked = fingerprint(dir.path())?;
+    assert_ne!(before, linked);
+
+    // and a link is told apart from a file holding its target
+    fs::remove_file(dir.path().join("link"))?;
+    fs::write(
+        dir.path().join("link"),
+        dir.path().as_os_str().as_encoded_bytes(),
+    )?;
+    assert_ne!(linked, fingerprint(dir.path())?);
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! # Fetching Errors
+//!
+//! This module contains the error types for errors that might occur during fetching.
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+/// The error representing a failure during fetching for any store implementation.
+pub enum FetchError {
+    #[cfg(feature = "git")]
+    /// A transparent wrapper for a [`git::Error`].
+    #[error(transparent)]
+    Git(#[from] git::Error),
+    #[cfg(feature = "s3")]
+    /// A transparent wrapper for a [`s3::Error`].
+    #[error(transparent)]
+    S3(#[from] s3::Error),
+}
+
+#[cfg(feature = "git")]
+pub mod git {
+    //! # Git Fetching Errors
+    use std::path::PathBuf;
+
+    /// An error representing a failure during fetching from a Git Ekala store.
+    #[derive(thiserror::Error, Debug)]
+    pub enum Error {
+        /// A transparent wrapper for a [`crate::store::git::Error`]
+        #[error(transparent)]
+        StoreError(#[from] crate::store::git::Error),
+        /// No version of the Atom published to the store satisfies the requested one.
+        #[error("No version of `{0}` published to the store satisfies `{1}`")]
+        Unpublished(String, String),
+        /// The directory to check the Atom out to already has content.
+        #[error("`{}` already exists and is not empty", .0.display())]
+        NotEmpty(PathBuf),
+        /// The Atom depends on other Atoms, but was published without a lock pinning them.
+        #[error("`{0}` was published without a lock, so its dependencies cannot be fetched")]
+        Unlocked(String),
+    }
+}
+
+#[cfg(feature = "s3")]
+pub mod s3 {
+    //! # S3 Fetching Errors
+    use std::path::PathBuf;
+
+    /// An error representing a failure during fetching from an S3 Ekala store.
+    #[derive(thiserror::Error, Debug)]
+    pub enum Error {
+        /// A transparent wrapper for a [`crate::store::s3::Error`]
+        #[error(transparent)]
+        Store(#[from] crate::store::s3::Error),
+        /// A transparent wrapper for a [`crate::store::git::archive::Error`]
+        #[error(transparent)]
+        Archive(#[from] crate::store::git::archive::Error),
+        /// No version of the Atom published to the store satisfies the requested one.
+        #[error("No version of `{0}` published to the store satisfies `{1}`")]
+        Unpublished(String, String),
+        /// The directory to check the Atom out to already has content.
+        #[error("`{}` already exists and is not empty", .0.display())]
+        NotEmpty(PathBuf),
+    }
+}
@@ -0,0 +1,327 @@
+//! # Atom Fetching from a Git Store
+//!
+//! Published versions are listed from the `refs/atoms/*` namespace of the remote store, and
+//! only the content ref of the resolved version is fetched into the local object database,
+//! without the history of the repository it was published from.
+//!
+//! A fetcher may also go through a read-through [`Proxy`], which serves the Atoms it holds
+//! locally, and only reaches the remote store on a miss, or once they must be revalidated.
+//!
+//! An Atom may also be fetched along with its entire locked closure, i.e. every Atom pinned by
+//! the lock it was published with, each checked out to a directory of its own, or linked to a
+//! single checkout in a [`SharedStore`], shared by every project on the machine.
+#[cfg(test)]
+mod test;
+
+use std::path::{Path, PathBuf};
+
+use gix::{ObjectId, Remote};
+use semver::Version;
+
+use super::error::git::Error;
+use super::{Fetch, Fetched};
+use crate::id::Id;
+use crate::store::git::info::{Info, Listed};
+use crate::store::git::proxy::Proxy;
+use crate::store::git::shared::SharedStore;
+use crate::store::git::{self, Snapshot};
+use crate::uri::Uri;
+use crate::{Lockfile, Manifest, ObjectSum};
+
+/// The Result type used for various methods during fetching from a Git store.
+pub type GitResult<T> = Result<T, Error>;
+
+/// The type representing a Git specific Atom fetcher.
+pub struct GitFetcher<'a> {
+    remote: Remote<'a>,
+    proxy: Option<Proxy>,
+    shared: Option<SharedStore>,
+    config: Option<&'a config::Config>,
+}
+
+impl<'a> GitFetcher<'a> {
+    /// Constructs a new [`GitFetcher`], fetching from the given remote store.
+    #[must_use]
+    pub fn new(remote: Remote<'a>) -> Self {
+        GitFetcher {
+            remote,
+            proxy: None,
+            shared: None,
+            config: None,
+        }
+    }
+
+    /// Query the refs of the remote store as `config` configures it, e.g. through the API of
+    /// the provider hosting it, rather than always over git.
+    #[must_use]
+    pub fn config(mut self, config: &'a config::Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Fetch through the given read-through proxy, caching the Atoms of the remote store.
+    #[must_use]
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Check the Atoms of a closure out to the given shared store, linking each to the
+    /// directory it is fetched to, rather than checking it out there.
+    #[must_use]
+    pub fn shared(mut self, shared: SharedStore) -> Self {
+        self.shared = Some(shared);
+        self
+    }
+
+    /// Return a reference to the remote store fetched from.
+    #[must_use]
+    pub fn remote(&self) -> &Remote<'a> {
+        &self.remote
+    }
+
+    /// The name of the remote, or its url if it has none, as understood by `git`.
+    fn location(&self) -> String {
+        match self.remote.name() {
+            Some(name) => name.as_bstr().to_string(),
+            None => self.url(),
+        }
+    }
+
+    /// List the Atom refs of the remote as they are now, as [`Snapshot::query`] does, if a
+    /// configuration was given, or over git otherwise.
+    fn snapshot(&self) -> Result<Snapshot, git::Error> {
+        match self.config {
+            Some(config) => Snapshot::query(self.remote.repo(), &self.location(), config),
+            None => Snapshot::take(self.remote.repo(), &self.location()),
+        }
+    }
+
+    /// The url of the remote, which identifies it to a proxy.
+    fn url(&self) -> String {
+        use gix::remote::Direction;
+
+        self.remote
+            .url(Direction::Fetch)
+            .map(|url| url.to_bstring().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Resolve the version of the Atom requested by `uri`, and fetch the tree of its content
+    /// into the local object database, without checking it out.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no published version satisfies the request, or
+    /// its content cannot be fetched.
+    pub fn content(&self, uri: &Uri) -> GitResult<(Version, ObjectId)> {
+        let version = self.resolve(uri)?;
+        let id = uri.id().to_string();
+        let tree = match &self.proxy {
+            Some(proxy) => proxy.fetch_content(self.remote.repo(), &self.url(), &id, &version)?,
+            None => git::fetch_content(&self.remote, &id, &version)?,
+        };
+        Ok((version, tree))
+    }
+
+    /// Resolve the version of the Atom requested by `uri`, and fetch only its spec, i.e. its
+    /// manifest and the lock it was published with, if any, without its content, e.g. to
+    /// describe the Atom. The spec is fetched from the remote, bypassing any proxy.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no published version satisfies the request, or
+    /// its spec cannot be fetched, or holds a missing or invalid manifest or lock.
+    pub fn spec(&self, uri: &Uri) -> GitResult<(Version, Manifest, Option<Lockfile>)> {
+        let version = self.resolve(uri)?;
+        let (manifest, lock) = git::fetch_spec(&self.remote, uri.id(), &version)?;
+        Ok((version, manifest, lock))
+    }
+
+    /// Describe the version of the Atom requested by `uri` as [`Info::describe`] does, along
+    /// with the id it is published under. Should the store redirect the Atom to the id it was
+    /// renamed to, the version is resolved among those of the new id instead. The store is
+    /// queried directly, bypassing any proxy.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the refs of the store cannot be listed, no
+    /// published version satisfies the request, or for any reason [`Info::describe`] would.
+    pub fn describe(&self, uri: &Uri) -> GitResult<(Id, Info)> {
+        let snapshot = self.snapshot()?;
+        let id = published_id(&snapshot, uri.id());
+        let version = super::select(&id, uri.version(), snapshot.resolvable())
+            .map_err(|(id, req)| Error::Unpublished(id, req))?;
+        let info = Info::describe(&self.remote, &snapshot, &id, &version)?;
+        Ok((id, info))
+    }
+
+    /// List every version of the Atom requested by `uri` which satisfies its requirement, if
+    /// any, including those yanked, along with the id it is published under, following any
+    /// redirect as [`Self::describe`] does.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the refs of the store cannot be listed.
+    pub fn versions(&self, uri: &Uri) -> GitResult<(Id, Vec<Listed>)> {
+        let snapshot = self.snapshot()?;
+        let id = published_id(&snapshot, uri.id());
+        let mut listed = snapshot.listed(&id);
+        if let Some(req) = uri.version() {
+            listed.retain(|listed| req.matches(&listed.version));
+        }
+        Ok((id, listed))
+    }
+
+    /// Fetch the Atom requested by `uri`, as [`Fetch::fetch`] does, along with every Atom
+    /// pinned by the lock it was published with, each checked out to its own directory of
+    /// `dest`, named `<id>@<version>`, and return them, the requested Atom first.
+    ///
+    /// The Atoms of the closure are fetched from the stores the lock pins them from, bypassing
+    /// any proxy, and verified to be published as the very atom commits it pins. With a
+    /// [shared store](Self::shared), each directory is a link to the Atom's checkout there.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `dest` has content, no published version satisfies
+    /// the request, the Atom has dependencies but was published without a lock, or any Atom of
+    /// the closure cannot be fetched or no longer matches the lock.
+    pub fn fetch_closure(&self, uri: &Uri, dest: &Path) -> GitResult<Vec<Fetched>> {
+        if super::occupied(dest) {
+            return Err(Error::NotEmpty(dest.to_path_buf()));
+        }
+
+        let (version, content) = self.content(uri)?;
+        let id = uri.id();
+        let lock = match git::fetch_spec(&self.remote, id, &version)? {
+            (_, Some(lock)) => lock,
+            (manifest, None) if manifest.deps.atoms.is_empty() => Default::default(),
+            (_, None) => return Err(Error::Unlocked(id.to_string())),
+        };
+
+        let path = self.place(&self.remote, None, (id, &version), content, dest)?;
+        let mut fetched = vec![Fetched {
+            id: id.clone(),
+            version,
+            content: content.into(),
+            path,
+        }];
+        fetched.extend(self.fetch_lock(&lock, dest)?);
+        Ok(fetched)
+    }
+
+    /// Fetch every Atom pinned by `lock`, each checked out to its own directory of `dest`,
+    /// named `<id>@<version>`, as [`Self::fetch_closure`] does, e.g. for an Atom which is not
+    /// published, but worked on from its repository.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any Atom of the lock cannot be fetched or no
+    /// longer matches the lock.
+    pub fn fetch_lock(&self, lock: &Lockfile, dest: &Path) -> GitResult<Vec<Fetched>> {
+        let repo = self.remote.repo();
+        let mut fetched = Vec::with_capacity(lock.atoms.len());
+        for atom in &lock.atoms {
+            let other;
+            let remote = match &atom.store {
+                Some(url) => {
+                    other = repo
+                        .remote_at(url.as_str())
+                        .map_err(|e| git::Error::from(Box::new(e)))?;
+                    &other
+                },
+                None => &self.remote,
+            };
+            let content = git::fetch_locked(remote, atom)?;
+            let path = self.place(remote, atom.root, (&atom.id, &atom.version), content, dest)?;
+            fetched.push(Fetched {
+                id: atom.id.clone(),
+                version: atom.version.clone(),
+                content: content.into(),
+                path,
+            });
+        }
+
+        Ok(fetched)
+    }
+
+    /// Check the content of the given version of an Atom, fetched from `remote`, whose root is
+    /// `root`, if known, out to its own directory of `dest`, or link that directory to its
+    /// checkout in the shared store, if any, and return the directory.
+    fn place(
+        &self,
+        remote: &Remote,
+        root: Option<ObjectSum>,
+        (id, version): (&Id, &Version),
+        content: ObjectId,
+        dest: &Path,
+    ) -> GitResult<PathBuf> {
+        use crate::store::Init;
+
+        let path = dest.join(format!("{id}@{version}"));
+        let Some(shared) = &self.shared else {
+            git::materialize(remote.repo(), content, &path)?;
+            return Ok(path);
+        };
+        let root = match root {
+            Some(root) => root,
+            None => ObjectSum::from(*remote.ekala_root()?),
+        };
+        shared.place(remote.repo(), (root, id), version, content, &path)?;
+        Ok(path)
+    }
+}
+
+/// The id the Atom `id` is published under in `snapshot`, i.e. the one it was renamed to, if
+/// the store redirects it, or `id` itself otherwise.
+fn published_id(snapshot: &Snapshot, id: &Id) -> Id {
+    match snapshot.renamed(id) {
+        Some(renamed) => {
+            tracing::warn!(%id, %renamed, "The Atom was renamed, refer to it by its new id instead");
+            renamed
+        },
+        None => id.clone(),
+    }
+}
+
+impl Fetch for GitFetcher<'_> {
+    type Error = Error;
+
+    fn resolve(&self, uri: &Uri) -> GitResult<Version> {
+        let unpublished = |(id, req): (String, String)| Error::Unpublished(id, req);
+        let Some(proxy) = &self.proxy else {
+            // yanked versions are passed over, though they remain in the store
+            let published = git::resolvable(self.remote.repo(), &self.location())?;
+            return super::select(uri.id(), uri.version(), published).map_err(unpublished);
+        };
+
+        let (upstream, id) = (self.url(), uri.id().to_string());
+        let revalidated = proxy.revalidate(&upstream, &id, false)?;
+        match super::select(uri.id(), uri.version(), proxy.resolvable(&upstream)?) {
+            // a miss is populated from the remote store, unless it was just revalidated
+            Err(_) if !revalidated => {
+                proxy.revalidate(&upstream, &id, true)?;
+                super::select(uri.id(), uri.version(), proxy.resolvable(&upstream)?)
+                    .map_err(unpublished)
+            },
+            selected => selected.map_err(unpublished),
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip_all, fields(uri = %uri))]
+    fn fetch(&self, uri: &Uri, dest: &Path) -> GitResult<Fetched> {
+        if super::occupied(dest) {
+            return Err(Error::NotEmpty(dest.to_path_buf()));
+        }
+
+        let (version, content) = self.content(uri)?;
+        git::materialize(self.remote.repo(), content, dest)?;
+
+        Ok(Fetched {
+            id: uri.id().clone(),
+            version,
+            content: content.into(),
+            path: dest.to_path_buf(),
+        })
+    }
+}
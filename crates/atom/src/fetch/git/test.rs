@@ -0,0 +1,230 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+
+use super::*;
+use crate::publish::git::test::MockAtom;
+
+#[tokio::test]
+async fn fetch_published_atom() -> Result<(), anyhow::Error> {
+    use crate::publish::Publish;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let fetcher = GitFetcher::new(remote);
+    let uri = Uri::from_str("foo@^0.1")?;
+    let dest = tempfile::tempdir()?;
+    let fetched = fetcher.fetch(&uri, dest.path())?;
+    assert_eq!(fetched.id.to_string(), "foo");
+    assert_eq!(fetched.version, Version::new(0, 1, 0));
+    assert_eq!(fetched.path, dest.path());
+    let manifest = file.path().file_name().context("no file name")?;
+    assert!(dest.path().join(manifest).is_file());
+
+    assert!(matches!(
+        fetcher.fetch(&uri, dest.path()),
+        Err(Error::NotEmpty(_))
+    ));
+    assert!(matches!(
+        fetcher.resolve(&Uri::from_str("foo@^0.2")?),
+        Err(Error::Unpublished(..))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn describe_published_atom() -> Result<(), anyhow::Error> {
+    use crate::publish::Publish;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::git::redirect::Redirect;
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let _foo = repo.mock("foo", "0.1.0", "some atom")?;
+    let _bar = repo.mock("bar", "0.1.0", "")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let fetcher = GitFetcher::new(remote.clone());
+    let (id, info) = fetcher.describe(&Uri::from_str("foo")?)?;
+    assert_eq!(id.to_string(), "foo");
+    assert_eq!(info.version(), &Version::new(0, 1, 0));
+    assert_eq!(
+        info.manifest().atom.description.as_deref(),
+        Some("some atom")
+    );
+    assert_eq!(info.format(), Some(crate::publish::ATOM_FORMAT_VERSION));
+    assert!(info.origin().is_some());
+    let refs: Vec<_> = info.refs().iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        refs,
+        [
+            "refs/atoms/foo/0.1.0/atom",
+            "refs/atoms/foo/0.1.0/spec",
+            "refs/atoms/foo/0.1.0/src"
+        ]
+    );
+    assert!(matches!(
+        fetcher.describe(&Uri::from_str("foo@^0.2")?),
+        Err(Error::Unpublished(..))
+    ));
+
+    let (from, to) = (Id::try_from("bar")?, Id::try_from("foo")?);
+    Redirect::plan(&repo, "origin", &from, &to)?.apply(&remote)?;
+    let (id, listed) = fetcher.versions(&Uri::from_str("bar")?)?;
+    assert_eq!(id, to);
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].version, Version::new(0, 1, 0));
+    assert_eq!(listed[0].commit, info.commit());
+    assert!(!listed[0].yanked);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_through_proxy() -> Result<(), anyhow::Error> {
+    use std::time::Duration;
+
+    use crate::publish::Publish;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let _first = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let dir = tempfile::tempdir()?;
+    let proxy = Proxy::open(dir.path(), Duration::from_secs(3600))?;
+    let fetcher = GitFetcher::new(remote.clone()).proxy(proxy);
+    let uri = Uri::from_str("foo")?;
+    let dest = tempfile::tempdir()?;
+    assert_eq!(
+        fetcher.fetch(&uri, dest.path())?.version,
+        Version::new(0, 1, 0)
+    );
+
+    // the proxy was populated on the miss, under the namespace of the remote store
+    let proxy = Proxy::open(dir.path(), Duration::from_secs(3600))?;
+    let url = remote
+        .url(gix::remote::Direction::Fetch)
+        .context("no url")?;
+    let upstream = url.to_bstring().to_string();
+    let held = proxy.resolvable(&upstream)?;
+    assert_eq!(held.len(), 1);
+
+    let _second = repo.mock("foo", "0.2.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    // fresh refs are served by the proxy, until a request misses
+    assert!(!proxy.revalidate(&upstream, "foo", false)?);
+    let fetcher = GitFetcher::new(remote).proxy(proxy);
+    assert_eq!(fetcher.resolve(&uri)?, Version::new(0, 1, 0));
+    assert_eq!(
+        fetcher.resolve(&Uri::from_str("foo@^0.2")?)?,
+        Version::new(0, 2, 0)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_locked_closure() -> Result<(), anyhow::Error> {
+    use crate::publish::Publish;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let fetcher = GitFetcher::new(remote);
+    let dest = tempfile::tempdir()?;
+    let fetched = fetcher.fetch_closure(&Uri::from_str("foo@^0.1")?, dest.path())?;
+    let [foo] = &fetched[..] else {
+        anyhow::bail!("expected a single fetched atom, got {fetched:?}");
+    };
+    assert_eq!(foo.path, dest.path().join("foo@0.1.0"));
+    let manifest = file.path().file_name().context("no file name")?;
+    assert!(foo.path.join(manifest).is_file());
+
+    // the content of a locked atom must be published as the atom commit the lock pins
+    let mut locked = crate::LockedAtom {
+        id: foo.id.clone(),
+        version: foo.version.clone(),
+        rev: foo.content,
+        store: None,
+        root: None,
+        renamed: None,
+        deps: Vec::new(),
+    };
+    assert!(matches!(
+        git::fetch_locked(fetcher.remote(), &locked),
+        Err(git::Error::LockedRev(..))
+    ));
+    let name = format!("refs/atoms/foo/{}/atom", git::encode_version(&foo.version));
+    locked.rev = repo.find_reference(name.as_str())?.id().detach().into();
+    let content = git::fetch_locked(fetcher.remote(), &locked)?;
+    assert_eq!(crate::ObjectSum::from(content), foo.content);
+
+    // a lock, e.g. of an atom worked on from its repository, is fetched on its own
+    let deps = tempfile::tempdir()?;
+    let lock = crate::Lockfile {
+        atoms: vec![locked],
+        ..Default::default()
+    };
+    let fetched = fetcher.fetch_lock(&lock, deps.path())?;
+    let [dep] = &fetched[..] else {
+        anyhow::bail!("expected a single fetched atom, got {fetched:?}");
+    };
+    assert_eq!(dep.path, deps.path().join("foo@0.1.0"));
+    assert_eq!(dep.content, foo.content);
+    assert!(dep.path.join(manifest).is_file());
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! # Atom Fetching
+//!
+//! This module provides the types and logic necessary to retrieve published Atoms from a store
+//! implementation, the counterpart of [`crate::publish`]. An Atom is requested by its
+//! [`Uri`], whose version requirement is resolved against the versions published to the store,
+//! and the content of the greatest satisfying version is checked out to a directory of the
+//! caller's choosing, from either a Git store or an S3-compatible object store.
+pub mod error;
+#[cfg(feature = "git")]
+pub mod git;
+#[cfg(feature = "s3")]
+pub mod s3;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use semver::{Version, VersionReq};
+
+use crate::ObjectSum;
+use crate::id::Id;
+use crate::uri::Uri;
+
+/// The record of an Atom fetched from a store, for reporting to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    /// The id of the Atom.
+    pub id: Id,
+    /// The version the requirement of the URI resolved to.
+    pub version: Version,
+    /// The hash of the Atom's content, as published.
+    pub content: ObjectSum,
+    /// The directory the content was checked out to.
+    pub path: PathBuf,
+}
+
+/// The trait responsible for exposing Atom fetching logic for a given store.
+pub trait Fetch {
+    /// The error type returned by the fetcher.
+    type Error;
+
+    /// Resolve the version of the Atom requested by `uri` to the greatest one published to the
+    /// store which satisfies it, or the greatest one of all if it requests none.
+    fn resolve(&self, uri: &Uri) -> Result<Version, Self::Error>;
+
+    /// Fetch the content of the Atom requested by `uri`, at the version it resolves to, and
+    /// check it out to `dest`, which must not exist, or be empty.
+    fn fetch(&self, uri: &Uri, dest: &Path) -> Result<Fetched, Self::Error>;
+}
+
+/// The greatest version of the Atom `id` among those `published`, which satisfies the version
+/// requirement `req`, if any, or the requirement otherwise, for reporting.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn select(
+    id: &Id,
+    req: Option<&VersionReq>,
+    mut published: BTreeMap<Id, BTreeSet<Version>>,
+) -> Result<Version, (String, String)> {
+    published
+        .remove(id)
+        .and_then(|versions| {
+            versions
+                .into_iter()
+                .rev()
+                .find(|v| req.map_or(true, |req| req.matches(v)))
+        })
+        .ok_or_else(|| {
+            let req = req.map_or("*".into(), ToString::to_string);
+            (id.to_string(), req)
+        })
+}
+
+/// Whether `dest` is a directory with content, which an Atom may not be checked out to.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn occupied(dest: &Path) -> bool {
+    std::fs::read_dir(dest).is_ok_and(|mut entries| entries.next().is_some())
+}
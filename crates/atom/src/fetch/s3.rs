@@ -0,0 +1,77 @@
+//! # Atom Fetching from an S3 Store
+//!
+//! Published versions are listed from the `refs/atoms/` keys of the store, and only the content
+//! tarball of the resolved version is downloaded, verified against its hash, and unpacked. No
+//! Git repository is needed.
+use std::path::Path;
+
+use semver::Version;
+
+use super::error::s3::Error;
+use super::{Fetch, Fetched};
+use crate::ObjectSum;
+use crate::publish::{ATOM, ATOM_REF_TOP_LEVEL};
+use crate::store::git::{archive, encode_version};
+use crate::store::s3::S3Store;
+use crate::uri::Uri;
+
+/// The Result type used for various methods during fetching from an S3 store.
+pub type S3Result<T> = Result<T, Error>;
+
+/// The type representing an S3 specific Atom fetcher.
+pub struct S3Fetcher<'a> {
+    store: &'a S3Store,
+}
+
+impl<'a> S3Fetcher<'a> {
+    /// Constructs a new [`S3Fetcher`], fetching from the given store.
+    #[must_use]
+    pub fn new(store: &'a S3Store) -> Self {
+        S3Fetcher { store }
+    }
+
+    /// Resolve the version of the Atom requested by `uri`, and download the tarball of its
+    /// content, without unpacking it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no published version satisfies the request, or
+    /// its content cannot be downloaded, or does not match its hash.
+    pub fn content(&self, uri: &Uri) -> S3Result<(Version, ObjectSum, Vec<u8>)> {
+        let version = self.resolve(uri)?;
+        let content_ref = format!(
+            "refs/{ATOM_REF_TOP_LEVEL}/{}/{}/{ATOM}",
+            uri.id(),
+            encode_version(&version)
+        );
+        let sum = self.store.get_sum(&content_ref)?;
+        let tarball = self.store.get_object(&sum)?;
+        Ok((version, sum, tarball))
+    }
+}
+
+impl Fetch for S3Fetcher<'_> {
+    type Error = Error;
+
+    fn resolve(&self, uri: &Uri) -> S3Result<Version> {
+        super::select(uri.id(), uri.version(), self.store.published()?)
+            .map_err(|(id, req)| Error::Unpublished(id, req))
+    }
+
+    #[tracing::instrument(level = "trace", skip_all, fields(uri = %uri))]
+    fn fetch(&self, uri: &Uri, dest: &Path) -> S3Result<Fetched> {
+        if super::occupied(dest) {
+            return Err(Error::NotEmpty(dest.to_path_buf()));
+        }
+
+        let (version, content, tarball) = self.content(uri)?;
+        archive::unpack(&tarball, dest)?;
+
+        Ok(Fetched {
+            id: uri.id().clone(),
+            version,
+            content,
+            path: dest.to_path_buf(),
+        })
+    }
+}
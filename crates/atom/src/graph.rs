@@ -0,0 +1,155 @@
+//! # Atom Graphs
+//!
+//! The graph of the dependencies among a set of Atoms, e.g. all those of a repository, where
+//! each Atom is a node, and each dependency of one Atom on another of the set an edge. The
+//! dependencies on no Atom of the set are recorded apart, as missing, so that the graph itself
+//! only ever refers to its own nodes.
+#[cfg(test)]
+mod tests;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use semver::Version;
+
+use crate::id::Id;
+
+/// The graph of the dependencies among a set of Atoms.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AtomGraph {
+    nodes: BTreeMap<Id, AtomNode>,
+    edges: BTreeMap<Id, BTreeSet<Id>>,
+    missing: Vec<MissingDep>,
+}
+
+/// An Atom of an [`AtomGraph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtomNode {
+    /// The version of the Atom.
+    pub version: Version,
+    /// The path of the manifest of the Atom.
+    pub path: PathBuf,
+}
+
+/// A dependency of an Atom of an [`AtomGraph`] on none of the Atoms of the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingDep {
+    /// The Atom declaring the dependency.
+    pub from: Id,
+    /// The name the dependency is declared by.
+    pub name: Id,
+    /// The path the dependency is declared at, if it is declared by path.
+    pub path: Option<PathBuf>,
+}
+
+impl AtomGraph {
+    /// Add an Atom to the graph, replacing any other with the same id.
+    pub fn add_atom(&mut self, id: Id, node: AtomNode) {
+        self.nodes.insert(id, node);
+    }
+
+    /// Add a dependency of the Atom `from` on the Atom `to`, which must both be in the graph
+    /// once it is complete.
+    pub fn add_dep(&mut self, from: Id, to: Id) {
+        self.edges.entry(from).or_default().insert(to);
+    }
+
+    /// Record a dependency on none of the Atoms of the graph.
+    pub fn add_missing(&mut self, missing: MissingDep) {
+        self.missing.push(missing);
+    }
+
+    /// The Atoms of the graph, by id.
+    #[must_use]
+    pub fn nodes(&self) -> &BTreeMap<Id, AtomNode> {
+        &self.nodes
+    }
+
+    /// Each dependency of the graph, as the id of the dependent and that of its dependency,
+    /// in order.
+    pub fn edges(&self) -> impl Iterator<Item = (&Id, &Id)> {
+        self.edges
+            .iter()
+            .flat_map(|(from, deps)| deps.iter().map(move |to| (from, to)))
+    }
+
+    /// The dependencies on none of the Atoms of the graph, in the order they were recorded.
+    #[must_use]
+    pub fn missing(&self) -> &[MissingDep] {
+        &self.missing
+    }
+
+    /// The cycles of the graph, as the ids of the Atoms of each set which depend on one
+    /// another, in order, including an Atom depending on itself. An acyclic graph has none.
+    #[must_use]
+    pub fn cycles(&self) -> Vec<Vec<Id>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: BTreeMap::new(),
+            low: BTreeMap::new(),
+            stack: Vec::new(),
+            on_stack: BTreeSet::new(),
+            cycles: Vec::new(),
+        };
+        for id in self.nodes.keys() {
+            if !tarjan.index.contains_key(id) {
+                tarjan.visit(id);
+            }
+        }
+
+        let mut cycles = tarjan.cycles;
+        for cycle in &mut cycles {
+            cycle.sort_unstable();
+        }
+        cycles.sort_unstable();
+        cycles
+    }
+}
+
+/// The state of Tarjan's algorithm, finding the strongly connected components of a graph.
+struct Tarjan<'a> {
+    graph: &'a AtomGraph,
+    index: BTreeMap<&'a Id, usize>,
+    low: BTreeMap<&'a Id, usize>,
+    stack: Vec<&'a Id>,
+    on_stack: BTreeSet<&'a Id>,
+    cycles: Vec<Vec<Id>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn visit(&mut self, id: &'a Id) {
+        let index = self.index.len();
+        self.index.insert(id, index);
+        self.low.insert(id, index);
+        self.stack.push(id);
+        self.on_stack.insert(id);
+
+        let graph = self.graph;
+        for dep in graph.edges.get(id).into_iter().flatten() {
+            if !self.index.contains_key(dep) {
+                self.visit(dep);
+                let low = self.low[id].min(self.low[dep]);
+                self.low.insert(id, low);
+            } else if self.on_stack.contains(dep) {
+                let low = self.low[id].min(self.index[dep]);
+                self.low.insert(id, low);
+            }
+        }
+
+        if self.low[id] != self.index[id] {
+            return;
+        }
+        let mut component = Vec::new();
+        while let Some(member) = self.stack.pop() {
+            self.on_stack.remove(member);
+            component.push(member.clone());
+            if member == id {
+                break;
+            }
+        }
+        let looped = graph.edges.get(id).is_some_and(|deps| deps.contains(id));
+        if component.len() > 1 || looped {
+            self.cycles.push(component);
+        }
+    }
+}
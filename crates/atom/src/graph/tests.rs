@@ -0,0 +1,54 @@
+use super::*;
+
+fn graph(deps: &[(&str, &str)]) -> Result<AtomGraph, anyhow::Error> {
+    let mut graph = AtomGraph::default();
+    for (from, to) in deps {
+        for id in [from, to] {
+            let node = AtomNode {
+                version: Version::new(0, 1, 0),
+                path: format!("{id}@.toml").into(),
+            };
+            graph.add_atom(Id::try_from(*id)?, node);
+        }
+        graph.add_dep(Id::try_from(*from)?, Id::try_from(*to)?);
+    }
+    Ok(graph)
+}
+
+fn ids(cycles: Vec<Vec<Id>>) -> Vec<Vec<String>> {
+    cycles
+        .into_iter()
+        .map(|cycle| cycle.iter().map(ToString::to_string).collect())
+        .collect()
+}
+
+#[test]
+fn acyclic() -> Result<(), anyhow::Error> {
+    let graph = graph(&[("a", "b"), ("b", "c"), ("a", "c")])?;
+    assert!(graph.cycles().is_empty());
+    assert_eq!(graph.nodes().len(), 3);
+    let edges: Vec<_> = graph
+        .edges()
+        .map(|(from, to)| format!("{from}->{to}"))
+        .collect();
+    assert_eq!(edges, ["a->b", "a->c", "b->c"]);
+    Ok(())
+}
+
+#[test]
+fn cycles() -> Result<(), anyhow::Error> {
+    let graph = graph(&[
+        ("a", "b"),
+        ("b", "c"),
+        ("c", "a"),
+        ("c", "d"),
+        ("e", "e"),
+        ("f", "g"),
+        ("g", "f"),
+    ])?;
+    assert_eq!(
+        ids(graph.cycles()),
+        [vec!["a", "b", "c"], vec!["e"], vec!["f", "g"]]
+    );
+    Ok(())
+}
@@ -7,6 +7,7 @@
 mod tests;
 
 use std::borrow::Borrow;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::ops::Deref;
 use std::str::FromStr;
@@ -33,7 +34,9 @@ pub enum Error {
     InvalidCharacters(String),
 }
 
+/// Computes the hash used to identify an Atom within a store, keyed by its root.
 pub trait ComputeHash<'id, T>: Borrow<[u8]> {
+    /// Compute the keyed blake3 hash of the Atom's id.
     fn compute_hash(&'id self) -> AtomHash<'id, T>;
 }
 
@@ -62,6 +65,7 @@ pub struct AtomId<R> {
     id: Id,
 }
 
+/// The hash of an [`AtomId`], along with a reference to the id it was computed from.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AtomHash<'id, T> {
     hash: [u8; 32],
@@ -170,6 +174,41 @@ impl Id {
             || !Id::is_valid_char(c)
     }
 
+    /// The skeleton of the id, which it shares with every id differing from it only by case or
+    /// by characters confusable with one another, per Unicode Technical Standard #39.
+    ///
+    /// Such ids map to distinct refs, yet are indistinguishable to a human, and may collide
+    /// on case-insensitive filesystems.
+    #[must_use]
+    pub fn skeleton(&self) -> String {
+        use unicode_security::skeleton;
+        // fold case between two passes, as some prototypes are themselves uppercase
+        let folded: String = skeleton(&self.0).flat_map(char::to_lowercase).collect();
+        skeleton(&folded).collect()
+    }
+
+    /// Whether the id is distinct from `other`, yet indistinguishable from it to a human.
+    #[must_use]
+    pub fn is_confusable_with(&self, other: &Id) -> bool {
+        self != other && self.skeleton() == other.skeleton()
+    }
+
+    /// Group the distinct ids which share a [skeleton](Id::skeleton), returning only the
+    /// groups of more than one id.
+    pub fn confusables<'a, I>(ids: I) -> Vec<Vec<&'a Id>>
+    where
+        I: IntoIterator<Item = &'a Id>,
+    {
+        let mut groups: BTreeMap<String, Vec<&Id>> = BTreeMap::new();
+        for id in ids {
+            let group = groups.entry(id.skeleton()).or_default();
+            if !group.contains(&id) {
+                group.push(id);
+            }
+        }
+        groups.into_values().filter(|g| g.len() > 1).collect()
+    }
+
     pub(super) fn is_valid_char(c: char) -> bool {
         matches!(
             GeneralCategory::of(c),
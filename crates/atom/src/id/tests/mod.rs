@@ -133,3 +133,23 @@ fn edge_cases() {
         "Zero-width space should be invalid in the middle"
     );
 }
+
+#[test]
+fn confusable_ids() -> Result<(), Error> {
+    let latin = Id::try_from("paypal")?;
+    // cyrillic `р`, `а` and `у`
+    let cyrillic = Id::try_from("\u{440}\u{430}\u{443}\u{440}\u{430}l")?;
+    let upper = Id::try_from("PayPal")?;
+    let other = Id::try_from("paypals")?;
+
+    assert!(latin.is_confusable_with(&cyrillic));
+    assert!(latin.is_confusable_with(&upper));
+    assert!(!latin.is_confusable_with(&latin));
+    assert!(!latin.is_confusable_with(&other));
+
+    let ids = [&latin, &other, &upper, &cyrillic, &latin];
+    let groups = Id::confusables(ids);
+    assert_eq!(groups.len(), 1, "Only the confusable ids should be grouped");
+    assert_eq!(groups[0].len(), 3, "Identical ids should be grouped once");
+    Ok(())
+}
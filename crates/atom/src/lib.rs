@@ -21,25 +21,45 @@
 //! ```
 //!
 //! Here the `atom` ref points to the Atom's contents in full. The `spec` ref points
-//! to a git tree object containing only the manifest and its lock file, which will be
-//! important for efficient resolution (not yet implemented). The refs under `src`
-//! points to the original commit from which the Atom's content references, ensuring
+//! to a git tree object containing only the manifest and its lock file, which can be
+//! fetched on its own, without the Atom's content, for efficient resolution, see
+//! [`store::git::fetch_specs`]. The refs
+//! under `src` points to the original commit from which the Atom's content references, ensuring
 //! it remains live, allowing trivially verification.
+//!
+//! Versions are encoded reversibly in ref names, so that any valid version yields a
+//! valid ref: the `+` preceding build metadata is written as `_`, and a version ending
+//! in `.lock` has a `_` appended, e.g. `refs/atoms/ひらがな/0.1.0_build.5/atom`.
 #![deny(missing_docs)]
 #![cfg_attr(not(feature = "git"), allow(dead_code))]
 
 mod core;
+mod graph;
 mod id;
+mod lock;
 mod manifest;
 
+pub mod eval;
+pub mod fetch;
+pub mod policy;
 pub mod publish;
+pub mod report;
 pub mod store;
 pub mod uri;
+#[cfg(feature = "git")]
+pub mod verify;
 pub use core::Atom;
 use std::sync::LazyLock;
 
-pub use id::{AtomId, CalculateRoot};
-pub use manifest::Manifest;
+pub use graph::{AtomGraph, AtomNode, MissingDep};
+pub use id::{AtomHash, AtomId, CalculateRoot, ComputeHash, Id};
+pub use lock::{Change, ChangeKind, LOCK_VERSION, LockedAtom, Lockfile, ObjectSum};
+pub use manifest::{
+    Artifact, AtomDep, Bump, Bumped, Denied, Dependencies, Digest, DigestError, Follow,
+    FormatError, INITIAL_VERSION, Kind, KindError, KindRegistry, LintError, Linter,
+    MANIFEST_FORMAT, Manifest, Pin, Scaffold, ScaffoldError, Shared, Src, Validator, VersionError,
+    Violation, WORKSPACE_FILE, Workspace, WorkspaceError,
+};
 const TOML: &str = "toml";
 const BASE32: base32::Alphabet = base32::Alphabet::Rfc4648HexLower { padding: false };
 static ATOM_EXT: LazyLock<String> = LazyLock::new(|| format!("@.{}", crate::TOML));
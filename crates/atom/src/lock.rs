@@ -0,0 +1,384 @@
+//! # Atom Lock File
+//!
+//! Provides the types for an Atom's lock file, which pins each of its dependencies to the
+//! exact published version it was resolved against. The lock sits beside the manifest, e.g.
+//! `foo.lock` beside `foo@.toml`, and is published with it in the Atom's spec tree.
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml_edit::{de, ser};
+
+use crate::id::Id;
+
+/// The version of the lock file format written by this crate.
+pub const LOCK_VERSION: u32 = 1;
+
+/// The lock file of an Atom, recording the graph of its resolved dependencies.
+///
+/// ```toml
+/// version = 1
+/// deps = ["foo"]
+///
+/// [[atom]]
+/// id = "foo"
+/// version = "0.1.0"
+/// rev = "sha1:ceebaca6d44c4cda555db3fbf687c0604c4818eb"
+/// store = "https://github.com/ekala-project/eka"
+/// deps = ["bar"]
+///
+/// [[atom]]
+/// id = "bar"
+/// version = "1.2.0"
+/// rev = "sha1:a87bff5ae43894a158dadf40938c775cb5b62d4b"
+/// root = "sha1:16b4b2a6ab9c2d9df8f4b8e4ab2ae2bd5e1ae75c"
+/// ```
+///
+/// Each Atom is pinned to a single version, so dependencies are referred to by id alone.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    /// The version of the lock file format.
+    pub version: u32,
+    /// The ids of the Atoms the locking Atom depends on directly.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deps: Vec<Id>,
+    /// The Atoms pinned by the lock, its direct and transitive dependencies alike.
+    #[serde(default, rename = "atom", skip_serializing_if = "Vec::is_empty")]
+    pub atoms: Vec<LockedAtom>,
+}
+
+/// A dependency on an Atom, pinned to an exact published version.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LockedAtom {
+    /// The id of the Atom.
+    pub id: Id,
+    /// The version of the Atom.
+    pub version: Version,
+    /// The id of the published Atom commit.
+    pub rev: ObjectSum,
+    /// The url of the store the Atom was resolved from, if not that of the locking Atom.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store: Option<String>,
+    /// The id of the root commit of the store the Atom was resolved from, so that it is
+    /// fetched from that store, or a mirror of it, rather than any other with the same url.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<ObjectSum>,
+    /// The id the Atom was renamed to, and is now published under, if it is depended on by a
+    /// former id the store redirects to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed: Option<Id>,
+    /// The ids of the Atoms this one depends on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deps: Vec<Id>,
+}
+
+/// A hash identifying an object, tagged with the algorithm which computed it, and written as
+/// `<algorithm>:<hex>`, e.g. `sha256:<hex>`, so that stores of any object format may be
+/// locked. A bare 40 character hex string, as written by earlier versions, is a sha1 hash.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub enum ObjectSum {
+    /// A sha1 hash, as used by most Git repositories.
+    Sha1([u8; 20]),
+    /// A sha256 hash, as used by Git repositories of the sha256 object format.
+    Sha256([u8; 32]),
+    /// A blake3 hash.
+    Blake3([u8; 32]),
+}
+
+/// Errors which occur when parsing an [`ObjectSum`].
+#[derive(Error, Debug)]
+pub enum ObjectSumError {
+    /// The hash does not name a supported algorithm.
+    #[error("Unsupported hash `{0}`, expected one of `sha1:`, `sha256:` or `blake3:<hex>`")]
+    Algorithm(String),
+    /// The hash is not a hex string of the length its algorithm produces.
+    #[error("`{0}` is not a valid hex encoded hash")]
+    Hex(String),
+}
+
+impl ObjectSum {
+    const BLAKE3: &str = "blake3";
+    const SHA1: &str = "sha1";
+    const SHA256: &str = "sha256";
+
+    /// The name of the algorithm which computed the hash.
+    #[must_use]
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            ObjectSum::Sha1(_) => ObjectSum::SHA1,
+            ObjectSum::Sha256(_) => ObjectSum::SHA256,
+            ObjectSum::Blake3(_) => ObjectSum::BLAKE3,
+        }
+    }
+
+    /// The raw bytes of the hash.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            ObjectSum::Sha1(bytes) => bytes,
+            ObjectSum::Sha256(bytes) | ObjectSum::Blake3(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for ObjectSum {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// The root of a store, as locked, so that the [`crate::AtomId`] of a locked Atom may be
+/// computed without reaching its store.
+impl crate::CalculateRoot<ObjectSum> for ObjectSum {
+    type Error = std::convert::Infallible;
+
+    fn calculate_root(&self) -> Result<ObjectSum, Self::Error> {
+        Ok(*self)
+    }
+}
+
+/// Decode a lowercase hex string of exactly `N` bytes, as written by [`ObjectSum`]'s `Display`.
+fn decode_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    fn nibble(digit: u8) -> Option<u8> {
+        match digit {
+            b'0'..=b'9' => Some(digit - b'0'),
+            b'a'..=b'f' => Some(digit - b'a' + 10),
+            _ => None,
+        }
+    }
+
+    if hex.len() != N * 2 {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+        *byte = (nibble(pair[0])? << 4) | nibble(pair[1])?;
+    }
+    Some(bytes)
+}
+
+impl fmt::Display for ObjectSum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.algorithm())?;
+        self.as_bytes()
+            .iter()
+            .try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl FromStr for ObjectSum {
+    type Err = ObjectSumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex_err = || ObjectSumError::Hex(s.to_owned());
+        let Some((algorithm, hex)) = s.split_once(':') else {
+            return decode_hex(s).map(ObjectSum::Sha1).ok_or_else(hex_err);
+        };
+        match algorithm {
+            ObjectSum::SHA1 => decode_hex(hex).map(ObjectSum::Sha1),
+            ObjectSum::SHA256 => decode_hex(hex).map(ObjectSum::Sha256),
+            ObjectSum::BLAKE3 => decode_hex(hex).map(ObjectSum::Blake3),
+            _ => return Err(ObjectSumError::Algorithm(s.to_owned())),
+        }
+        .ok_or_else(hex_err)
+    }
+}
+
+impl TryFrom<String> for ObjectSum {
+    type Error = ObjectSumError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ObjectSum> for String {
+    fn from(sum: ObjectSum) -> Self {
+        sum.to_string()
+    }
+}
+
+#[cfg(feature = "git")]
+impl From<gix::ObjectId> for ObjectSum {
+    fn from(id: gix::ObjectId) -> Self {
+        let bytes = id.as_bytes();
+        match <[u8; 20]>::try_from(bytes) {
+            Ok(sha1) => ObjectSum::Sha1(sha1),
+            Err(_) => ObjectSum::Sha256(bytes.try_into().expect("an object id is sha1 or sha256")),
+        }
+    }
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Lockfile {
+            version: LOCK_VERSION,
+            deps: Vec::new(),
+            atoms: Vec::new(),
+        }
+    }
+}
+
+/// How the Atom pinned for an id differs between two locks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The Atom is only pinned by the new lock.
+    Added,
+    /// The Atom is only pinned by the old lock.
+    Removed,
+    /// The Atom is pinned to a greater version by the new lock.
+    Upgraded,
+    /// The Atom is pinned to a lesser version by the new lock.
+    Downgraded,
+    /// The Atom is pinned to the same version, but a different commit or store.
+    Repinned,
+}
+
+/// A difference between two locks in the Atom pinned for a single id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<'a> {
+    /// The id of the Atom.
+    pub id: &'a str,
+    /// How the pinned Atom differs.
+    pub kind: ChangeKind,
+    /// The Atom pinned by the old lock, if any.
+    pub old: Option<&'a LockedAtom>,
+    /// The Atom pinned by the new lock, if any.
+    pub new: Option<&'a LockedAtom>,
+    /// Whether the locking Atom depends on this one directly, rather than transitively.
+    pub direct: bool,
+}
+
+impl LockedAtom {
+    /// The id the Atom is published under in its store, i.e. the one it was renamed to, if it
+    /// was, or else the one it is depended on by.
+    #[must_use]
+    pub fn published_id(&self) -> &Id {
+        self.renamed.as_ref().unwrap_or(&self.id)
+    }
+}
+
+impl Lockfile {
+    /// The path of the lock of the Atom whose manifest is at the given path.
+    #[must_use]
+    pub fn path(manifest: &Path) -> PathBuf {
+        crate::core::AtomPaths::new(manifest).lock().to_path_buf()
+    }
+
+    /// Render the lock as TOML, as it is written to disk.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the lock cannot be represented as TOML.
+    pub fn to_toml(&self) -> Result<String, ser::Error> {
+        ser::to_string(self)
+    }
+
+    /// The pinned Atom with the given id, if the lock has one.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&LockedAtom> {
+        self.atoms.iter().find(|atom| *atom.id == *id)
+    }
+
+    /// Whether the locking Atom depends on the Atom with the given id directly.
+    #[must_use]
+    pub fn is_direct(&self, id: &str) -> bool {
+        self.deps.iter().any(|dep| **dep == *id)
+    }
+
+    /// The shortest chain of dependencies by which the locking Atom depends on the Atom with
+    /// the given id, from one of its direct dependencies up to the Atom itself, if the lock
+    /// pins it.
+    #[must_use]
+    pub fn chain(&self, id: &str) -> Option<Vec<&Id>> {
+        use std::collections::{HashMap, VecDeque};
+
+        let mut parents: HashMap<&str, Option<&Id>> = HashMap::new();
+        let mut queue: VecDeque<_> = self.deps.iter().map(|dep| (dep, None)).collect();
+        while let Some((atom, parent)) = queue.pop_front() {
+            if parents.contains_key(atom.as_str()) {
+                continue;
+            }
+            parents.insert(atom.as_str(), parent);
+            if **atom == *id {
+                let mut chain = vec![atom];
+                let mut next = parent;
+                while let Some(dependent) = next {
+                    chain.push(dependent);
+                    next = parents[dependent.as_str()];
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+            let deps = self.get(atom).into_iter().flat_map(|a| &a.deps);
+            queue.extend(deps.map(|dep| (dep, Some(atom))));
+        }
+        None
+    }
+
+    /// The differences between this lock and a `new` one, direct dependencies first, and
+    /// otherwise ordered by id.
+    #[must_use]
+    pub fn diff<'a>(&'a self, new: &'a Lockfile) -> Vec<Change<'a>> {
+        use std::cmp::Ordering;
+
+        let mut changes: Vec<_> = new
+            .atoms
+            .iter()
+            .map(|atom| (atom.id.as_str(), self.get(&atom.id), Some(atom)))
+            .chain(
+                self.atoms
+                    .iter()
+                    .filter(|atom| new.get(&atom.id).is_none())
+                    .map(|atom| (atom.id.as_str(), Some(atom), None)),
+            )
+            .filter_map(|(id, old, new_atom)| {
+                let kind = match (old, new_atom) {
+                    (None, _) => ChangeKind::Added,
+                    (_, None) => ChangeKind::Removed,
+                    (Some(old), Some(atom)) => match atom.version.cmp(&old.version) {
+                        Ordering::Greater => ChangeKind::Upgraded,
+                        Ordering::Less => ChangeKind::Downgraded,
+                        Ordering::Equal
+                            if atom.rev != old.rev
+                                || atom.store != old.store
+                                || atom.root != old.root =>
+                        {
+                            ChangeKind::Repinned
+                        },
+                        Ordering::Equal => return None,
+                    },
+                };
+                let direct = match new_atom {
+                    Some(_) => new.is_direct(id),
+                    None => self.is_direct(id),
+                };
+                Some(Change {
+                    id,
+                    kind,
+                    old,
+                    new: new_atom,
+                    direct,
+                })
+            })
+            .collect();
+
+        changes.sort_by(|a, b| b.direct.cmp(&a.direct).then_with(|| a.id.cmp(b.id)));
+        changes
+    }
+}
+
+impl FromStr for Lockfile {
+    type Err = de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        de::from_str(s)
+    }
+}
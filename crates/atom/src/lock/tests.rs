@@ -0,0 +1,146 @@
+use super::*;
+
+const LOCK: &str = r#"
+version = 1
+deps = ["foo"]
+
+[[atom]]
+id = "foo"
+version = "0.1.0"
+rev = "ceebaca6d44c4cda555db3fbf687c0604c4818eb"
+store = "https://github.com/ekala-project/eka"
+deps = ["bar"]
+
+[[atom]]
+id = "bar"
+version = "1.2.0"
+rev = "sha1:a87bff5ae43894a158dadf40938c775cb5b62d4b"
+root = "sha1:16b4b2a6ab9c2d9df8f4b8e4ab2ae2bd5e1ae75c"
+"#;
+
+#[test]
+fn parse_lock() -> Result<(), anyhow::Error> {
+    let lock = Lockfile::from_str(LOCK)?;
+    assert_eq!(lock.version, LOCK_VERSION);
+    assert_eq!(lock.deps, vec![Id::from_str("foo")?]);
+
+    let foo = lock.get("foo").expect("foo is locked");
+    assert_eq!(foo.version, Version::new(0, 1, 0));
+    assert_eq!(foo.deps, vec![Id::from_str("bar")?]);
+
+    let bar = lock.get("bar").expect("bar is locked");
+    assert_eq!(bar.store, None);
+    assert_eq!(
+        bar.root.map(|root| root.to_string()).as_deref(),
+        Some("sha1:16b4b2a6ab9c2d9df8f4b8e4ab2ae2bd5e1ae75c")
+    );
+    assert!(bar.deps.is_empty());
+    assert!(lock.get("baz").is_none());
+
+    let written = lock.to_toml()?;
+    assert_eq!(Lockfile::from_str(&written)?, lock);
+
+    assert_eq!(
+        Lockfile::path(Path::new("atoms/foo@.toml")),
+        Path::new("atoms/foo.lock")
+    );
+    Ok(())
+}
+
+#[test]
+fn diff_locks() -> Result<(), anyhow::Error> {
+    let old = Lockfile::from_str(LOCK)?;
+    let mut new = old.clone();
+    new.atoms.retain(|atom| *atom.id != "bar");
+    new.atoms[0].version = Version::new(0, 2, 0);
+    new.atoms[0].deps = vec![Id::from_str("baz")?];
+    new.atoms.push(LockedAtom {
+        id: Id::from_str("baz")?,
+        version: Version::new(2, 0, 0),
+        rev: "sha1:9f17c8c816bd1de6f8aa9c037d1b529212ab2a02".parse()?,
+        store: None,
+        root: None,
+        renamed: None,
+        deps: Vec::new(),
+    });
+
+    let changes: Vec<_> = old
+        .diff(&new)
+        .into_iter()
+        .map(|change| (change.id, change.kind, change.direct))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            ("foo", ChangeKind::Upgraded, true),
+            ("bar", ChangeKind::Removed, false),
+            ("baz", ChangeKind::Added, false),
+        ]
+    );
+
+    assert!(old.diff(&old).is_empty());
+    Ok(())
+}
+
+#[test]
+fn dependency_chains() -> Result<(), anyhow::Error> {
+    let lock = Lockfile::from_str(LOCK)?;
+    let chain = |id| {
+        lock.chain(id)
+            .map(|chain| chain.iter().map(|id| id.as_str()).collect::<Vec<_>>())
+    };
+    assert_eq!(chain("foo"), Some(vec!["foo"]));
+    assert_eq!(chain("bar"), Some(vec!["foo", "bar"]));
+    assert_eq!(chain("baz"), None);
+    Ok(())
+}
+
+#[test]
+fn object_sums() -> Result<(), anyhow::Error> {
+    // locks written before hashes were tagged hold bare sha1 hex
+    let bare: ObjectSum = "ceebaca6d44c4cda555db3fbf687c0604c4818eb".parse()?;
+    assert_eq!(bare.algorithm(), "sha1");
+    assert_eq!(
+        bare.to_string(),
+        "sha1:ceebaca6d44c4cda555db3fbf687c0604c4818eb"
+    );
+
+    let sha256 = format!("sha256:{}", "ab".repeat(32));
+    assert_eq!(sha256.parse::<ObjectSum>()?.to_string(), sha256);
+    let blake3 = format!("blake3:{}", "0f".repeat(32));
+    assert!(matches!(blake3.parse::<ObjectSum>()?, ObjectSum::Blake3(_)));
+
+    assert!(matches!(
+        "md5:00".parse::<ObjectSum>(),
+        Err(ObjectSumError::Algorithm(_))
+    ));
+    assert!(matches!(
+        format!("sha256:{}", "ab".repeat(20)).parse::<ObjectSum>(),
+        Err(ObjectSumError::Hex(_))
+    ));
+    assert!("ceebaca6".parse::<ObjectSum>().is_err());
+
+    // only the lowercase hex sums are written as is accepted, so that each has a single spelling
+    for hex in [
+        "+eebaca6d44c4cda555db3fbf687c0604c4818eb",
+        "-eebaca6d44c4cda555db3fbf687c0604c4818eb",
+        "CEEBACA6D44C4CDA555DB3FBF687C0604C4818EB",
+        "ceebaca6d44c4cda555db3fbf687c0604c4818eg",
+        " eebaca6d44c4cda555db3fbf687c0604c4818eb",
+    ] {
+        assert!(matches!(
+            hex.parse::<ObjectSum>(),
+            Err(ObjectSumError::Hex(_))
+        ));
+        assert!(matches!(
+            format!("sha1:{hex}").parse::<ObjectSum>(),
+            Err(ObjectSumError::Hex(_))
+        ));
+    }
+    // a multi-byte character never splits into digits
+    assert!(matches!(
+        format!("sha1:{}é", "a".repeat(38)).parse::<ObjectSum>(),
+        Err(ObjectSumError::Hex(_))
+    ));
+    Ok(())
+}
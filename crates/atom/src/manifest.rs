@@ -1,16 +1,51 @@
 //! # Atom Manifest
 //!
 //! Provides the core types for working with an Atom's manifest format.
+#[cfg(test)]
+mod tests;
+
+mod artifact;
 mod depends;
+mod kind;
+mod lint;
+mod scaffold;
+mod version;
+mod workspace;
 
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
+pub use artifact::{Artifact, Digest, DigestError};
+pub(crate) use depends::rewrite_path_deps;
+pub use depends::{AtomDep, Dependencies, Follow, Pin, Src};
+pub use kind::{Kind, KindError, KindRegistry, Validator};
+pub use lint::{Denied, LintError, Linter, Violation};
+pub use scaffold::{INITIAL_VERSION, Scaffold, ScaffoldError};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use toml_edit::{DocumentMut, de};
+use toml_edit::{ImDocument, de};
+pub use version::{Bump, Bumped, VersionError};
+pub use workspace::{Shared, WORKSPACE_FILE, Workspace, WorkspaceError};
 
 use crate::Atom;
 
+/// The manifest format understood by this version of the crate. Atoms declaring a newer one
+/// by their `min-format` key are refused, rather than misinterpreted.
+pub const MANIFEST_FORMAT: u32 = 1;
+
+/// An Atom requires a manifest format newer than this version of the crate understands.
+#[derive(Error, Debug)]
+#[error(
+    "`{id}` requires manifest format {required}, but this version of eka only understands format \
+     {MANIFEST_FORMAT}; upgrade eka to use it"
+)]
+pub struct FormatError {
+    /// The Atom requiring the newer format.
+    pub id: crate::Id,
+    /// The manifest format it requires.
+    pub required: u32,
+}
+
 /// Errors which occur during manifest (de)serialization.
 #[derive(Error, Debug)]
 pub enum AtomError {
@@ -23,15 +58,42 @@ pub enum AtomError {
     /// The manifest is not valid TOML.
     #[error(transparent)]
     InvalidToml(#[from] toml_edit::TomlError),
+    /// The manifest declares an unknown kind, or is rejected by a hook of its kind.
+    #[error(transparent)]
+    InvalidKind(#[from] KindError),
+    /// The manifest violates a lint configured at the `error` level.
+    #[error(transparent)]
+    Denied(#[from] Denied),
+    /// The manifest breaks a rule of the organization's policy.
+    #[error(transparent)]
+    Breached(#[from] crate::policy::Breaches),
+    /// The manifest inherits a key its workspace does not declare.
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    /// The manifest requires a newer manifest format than this version understands.
+    #[error(transparent)]
+    Unsupported(#[from] FormatError),
 }
 
 type AtomResult<T> = Result<T, AtomError>;
 
 /// The type representing the required fields of an Atom's manifest.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct Manifest {
     /// The required \[atom] key of the TOML manifest.
     pub atom: Atom,
+    /// The dependencies of the Atom.
+    #[serde(default, skip_serializing_if = "Dependencies::is_empty")]
+    pub deps: Dependencies,
+    /// Auxiliary artifacts which may be attached to the published Atom, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub artifacts: BTreeMap<String, Artifact>,
+}
+
+/// The \[atom] key of a manifest, with any other keys ignored.
+#[derive(Deserialize)]
+struct AtomTable {
+    atom: Option<Atom>,
 }
 
 impl Manifest {
@@ -41,17 +103,43 @@ impl Manifest {
     /// # Errors
     ///
     /// This function will return an error if the content is invalid
-    /// TOML, or if the \[atom] key is missing.
+    /// TOML, if the \[atom] key is missing, or if it requires a newer manifest format than
+    /// [`MANIFEST_FORMAT`].
     pub fn get_atom(content: &str) -> AtomResult<Atom> {
-        let doc = content.parse::<DocumentMut>()?;
+        // parse once, borrowing the content, and deserialize straight from the parsed document
+        let doc = ImDocument::parse(content)?;
+        let AtomTable { atom } = AtomTable::deserialize(de::Deserializer::from(doc))?;
+
+        let atom = atom.ok_or(AtomError::Missing)?;
+        atom.supported()?;
+        Ok(atom)
+    }
 
-        if let Some(v) = doc.get("atom").map(ToString::to_string) {
-            let atom = de::from_str::<Atom>(&v)?;
-            Ok(atom)
-        } else {
-            Err(AtomError::Missing)
+    /// Build an Atom struct from the \[atom] key of the manifest of a member of `workspace`,
+    /// with the fields it inherits from the workspace in their place.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error for any reason [`Manifest::get_atom`] would, or if
+    /// the manifest inherits a field the workspace does not declare.
+    pub fn get_member_atom(content: &str, workspace: &Workspace) -> AtomResult<Atom> {
+        // the \[atom] key holds no paths, which would be relative to the manifest
+        match workspace.inherit(content, std::path::Path::new(""))? {
+            Some(inherited) => Manifest::get_atom(&inherited),
+            None => Manifest::get_atom(content),
         }
     }
+
+    /// Bump the version of the \[atom] key of a TOML manifest, returning the manifest with the
+    /// new version in place, and the rest of it, including its formatting, untouched.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the content is invalid TOML, or if it declares no
+    /// valid version of its own, e.g. as it inherits it from the workspace.
+    pub fn bump_version(content: &str, bump: &Bump) -> Result<Bumped, VersionError> {
+        version::bump(content, bump)
+    }
 }
 
 impl FromStr for Manifest {
@@ -0,0 +1,87 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// An auxiliary artifact which may be attached to a published Atom, such as a prebuilt
+/// tarball, declared in the \[artifacts] key of its manifest:
+///
+/// ```toml
+/// [artifacts.tarball]
+/// hash = "blake3:af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+/// ```
+///
+/// Artifacts are not part of the Atom's content, which remains reproducible from source, but
+/// anyone fetching one can verify it is exactly what the Atom's author declared.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Artifact {
+    /// The content hash the artifact must match.
+    pub hash: Digest,
+}
+
+/// The blake3 hash of an artifact's content, written as `blake3:<hex>`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct Digest([u8; blake3::OUT_LEN]);
+
+/// Errors which occur when parsing a [`Digest`].
+#[derive(Error, Debug)]
+pub enum DigestError {
+    /// The digest does not name a supported algorithm.
+    #[error("Unsupported hash `{0}`, expected `{}:<hex>`", Digest::ALGORITHM)]
+    Algorithm(String),
+    /// A transparent wrapper for a [`blake3::HexError`]
+    #[error(transparent)]
+    Hex(#[from] blake3::HexError),
+}
+
+impl Digest {
+    const ALGORITHM: &str = "blake3";
+
+    /// Compute the digest of the given content.
+    #[must_use]
+    pub fn of(content: &[u8]) -> Self {
+        Digest(*blake3::hash(content).as_bytes())
+    }
+
+    /// The digest of the content fed to the given hasher so far, for content which is
+    /// streamed rather than held in memory.
+    pub(crate) fn from_hasher(hasher: &blake3::Hasher) -> Self {
+        Digest(*hasher.finalize().as_bytes())
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hash = blake3::Hash::from_bytes(self.0);
+        write!(f, "{}:{}", Digest::ALGORITHM, hash.to_hex())
+    }
+}
+
+impl FromStr for Digest {
+    type Err = DigestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix(Digest::ALGORITHM)
+            .and_then(|s| s.strip_prefix(':'))
+            .ok_or_else(|| DigestError::Algorithm(s.to_owned()))?;
+
+        Ok(Digest(*blake3::Hash::from_hex(hex)?.as_bytes()))
+    }
+}
+
+impl TryFrom<String> for Digest {
+    type Error = DigestError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Digest> for String {
+    fn from(digest: Digest) -> Self {
+        digest.to_string()
+    }
+}
@@ -1,47 +1,225 @@
-use std::path::PathBuf;
+#[cfg(test)]
+mod tests;
 
-use semver::Version;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item, value};
 use url::Url;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
-enum AtomSrc {
-    Url(Url),
-    Path(PathBuf),
-}
+use crate::id::Id;
 
-/// atom dependencies
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+/// The dependencies of an Atom, declared in the \[deps] key of its manifest:
+///
+/// ```toml
+/// [deps.atoms.bar]
+/// version = "^0.2"
+/// path = "../bar"
+/// ```
+///
+/// An Atom of the same store may also be depended on through a channel, or a tag, of the
+/// store, in place of a version requirement, to follow the version it points to:
+///
+/// ```toml
+/// [deps.atoms.qux]
+/// channel = "stable"
+/// ```
+///
+/// An Atom of another store may list mirrors of it, tried in order whenever the store at its
+/// url cannot be reached:
+///
+/// ```toml
+/// [deps.atoms.baz]
+/// version = "^1"
+/// url = "https://example.com/store.git"
+/// mirrors = ["https://mirror.example.com/store.git"]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
 pub struct Dependencies {
-    version: Version,
-    src: AtomSrc,
+    /// Other Atoms, by id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub atoms: BTreeMap<Id, AtomDep>,
+    /// Legacy pins, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pins: BTreeMap<String, Pin>,
+    /// Sources fetched at build time, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub srcs: BTreeMap<String, Src>,
+}
+
+/// A dependency on another Atom.
+///
+/// A dependency with neither a `url` nor a `path` refers to an Atom published to the same
+/// store as the depending Atom, so a store's Atoms may depend on one another without
+/// repeating its url.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AtomDep {
+    /// The versions of the Atom which satisfy the dependency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<VersionReq>,
+    /// The url of the store the Atom is published to, if not that of the depending Atom.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+    /// The urls of mirrors of the store at `url`, holding the very same Atoms, which are
+    /// resolved against in order when it cannot be reached.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<Url>,
+    /// The path to the Atom in the same repository, relative to the directory of the
+    /// depending Atom's manifest. Published specs never contain one: the Atom at the path
+    /// must satisfy `version`, and is depended on by it alone once published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// The channel of the store to follow, e.g. `stable`, resolved to the version it points
+    /// to, which must also satisfy `version`, if given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// The tag of the store to follow, e.g. `release-2024`, resolved as a `channel` is, but
+    /// never moving once created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// A ref of the store an Atom dependency follows, in place of a version requirement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Follow<'a> {
+    /// A channel, which moves to newer versions as they are released on it.
+    Channel(&'a str),
+    /// A tag, which points to the same version for good.
+    Tag(&'a str),
+}
+
+impl fmt::Display for Follow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Follow::Channel(name) => write!(f, "channel `{name}`"),
+            Follow::Tag(name) => write!(f, "tag `{name}`"),
+        }
+    }
 }
 
 /// legacy pins
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
-pub struct Pins {
-    url: Url,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    /// The url the pinned source is fetched from.
+    pub url: Url,
 }
 
 /// sources fetched at build time
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
-pub struct Srcs {
-    url: Url,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Src {
+    /// The url the source is fetched from.
+    pub url: Url,
 }
 
-#[allow(dead_code)]
-impl AtomSrc {
-    pub(crate) fn url(self) -> Option<Url> {
-        match self {
-            AtomSrc::Url(url) => Some(url),
+impl Dependencies {
+    /// Whether no dependencies are declared at all.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty() && self.pins.is_empty() && self.srcs.is_empty()
+    }
+
+    /// The url of every dependency fetched from one, i.e. Atoms of other stores, and their
+    /// mirrors, pins and sources, along with the name it is declared by.
+    pub fn urls(&self) -> impl Iterator<Item = (String, &Url)> {
+        let atoms = self
+            .atoms
+            .iter()
+            .flat_map(|(dep, a)| a.sources().map(move |url| (dep.to_string(), url)));
+        atoms
+            .chain(self.pins.iter().map(|(dep, p)| (dep.clone(), &p.url)))
+            .chain(self.srcs.iter().map(|(dep, s)| (dep.clone(), &s.url)))
+    }
+}
+
+impl AtomDep {
+    /// Whether the Atom is published to the same store as the depending Atom, declared by
+    /// giving neither a url nor a path.
+    #[must_use]
+    pub fn is_same_store(&self) -> bool {
+        self.url.is_none() && self.path.is_none()
+    }
+
+    /// The urls the Atom may be fetched from, if it is published to another store: that of
+    /// the store, followed by those of its mirrors, in the order they are to be tried.
+    pub fn sources(&self) -> impl Iterator<Item = &Url> {
+        let mirrors = self.url.as_ref().map_or(&[][..], |_| &self.mirrors[..]);
+        self.url.iter().chain(mirrors)
+    }
+
+    /// The channel, or tag, the dependency follows, if any, or `None` if it declares both,
+    /// which is ambiguous.
+    #[must_use]
+    pub fn follows(&self) -> Option<Follow<'_>> {
+        match (&self.channel, &self.tag) {
+            (Some(channel), None) => Some(Follow::Channel(channel)),
+            (None, Some(tag)) => Some(Follow::Tag(tag)),
             _ => None,
         }
     }
 
-    pub(crate) fn path(self) -> Option<PathBuf> {
-        match self {
-            AtomSrc::Path(path) => Some(path),
-            _ => None,
+    /// Describe the versions which satisfy the dependency, e.g. in an error.
+    #[must_use]
+    pub fn requirement(&self) -> String {
+        let req = self.version.as_ref().map(ToString::to_string);
+        match (self.follows(), req) {
+            (Some(follow), Some(req)) => format!("{req} on the {follow}"),
+            (Some(follow), None) => follow.to_string(),
+            (None, req) => req.unwrap_or_else(|| "*".into()),
         }
     }
 }
+
+/// Rewrite each path dependency in the manifest `content` into a version requirement, as a
+/// published spec cannot refer to paths in the repository it was published from.
+///
+/// The Atom at a path is published to the same store as the depending Atom, so a dependency
+/// with neither a path nor a url refers to an Atom in that store, and a path dependency is
+/// published as just its version requirement. Each path dependency is checked by `resolve`,
+/// given its id, its path joined to `dir` and its requirement, which returns the version of
+/// the Atom found there; one without a requirement is given a caret requirement on it.
+///
+/// The rest of the manifest, including its formatting, is left untouched. Returns `None` if
+/// there is nothing to rewrite.
+pub(crate) fn rewrite_path_deps<E>(
+    content: &str,
+    dir: &Path,
+    mut resolve: impl FnMut(&str, &Path, Option<&VersionReq>) -> Result<Version, E>,
+) -> Result<Option<String>, E> {
+    let Ok(mut doc) = content.parse::<DocumentMut>() else {
+        return Ok(None);
+    };
+    let Some(atoms) = doc
+        .get_mut("deps")
+        .and_then(|deps| deps.get_mut("atoms"))
+        .and_then(Item::as_table_like_mut)
+    else {
+        return Ok(None);
+    };
+
+    let mut rewrote = false;
+    for (id, dep) in atoms.iter_mut() {
+        let Some(dep) = dep.as_table_like_mut() else {
+            continue;
+        };
+        let Some(path) = dep.remove("path") else {
+            continue;
+        };
+        rewrote = true;
+        let Some(path) = path.as_str() else {
+            continue;
+        };
+        let req = dep
+            .get("version")
+            .and_then(Item::as_str)
+            .and_then(|req| VersionReq::parse(req).ok());
+        let version = resolve(id.get(), &dir.join(path), req.as_ref())?;
+        if !dep.contains_key("version") {
+            dep.insert("version", value(format!("^{version}")));
+        }
+    }
+
+    Ok(rewrote.then(|| doc.to_string()))
+}
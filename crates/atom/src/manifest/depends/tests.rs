@@ -0,0 +1,59 @@
+use std::convert::Infallible;
+use std::str::FromStr;
+
+use super::*;
+
+const MANIFEST: &str = r#"[atom]
+id = "foo"
+version = "0.1.0"
+
+# siblings in the same repository
+[deps.atoms.bar]
+path = "../bar"
+
+[deps.atoms.baz]
+version = "~1.2"
+path = "../baz"
+
+[deps.atoms.qux]
+version = "^2"
+"#;
+
+#[test]
+fn rewrite_deps() -> Result<(), anyhow::Error> {
+    let mut visited = Vec::new();
+    let rewritten = rewrite_path_deps(MANIFEST, Path::new("foo"), |id, path, req| {
+        visited.push((id.to_owned(), path.to_path_buf(), req.cloned()));
+        Ok::<_, Infallible>(Version::new(0, 3, 1))
+    })?
+    .expect("path deps are rewritten");
+
+    assert_eq!(
+        visited,
+        vec![
+            ("bar".into(), PathBuf::from("foo/../bar"), None),
+            (
+                "baz".into(),
+                PathBuf::from("foo/../baz"),
+                Some(VersionReq::parse("~1.2")?)
+            ),
+        ]
+    );
+    assert!(rewritten.contains("# siblings in the same repository"));
+
+    let deps = crate::Manifest::from_str(&rewritten)?.deps;
+    let dep = |id: &str| deps.atoms.iter().find(|(k, _)| **k == *id).map(|(_, v)| v);
+
+    let bar = dep("bar").expect("bar is a dependency");
+    assert_eq!(bar.version, Some(VersionReq::parse("^0.3.1")?));
+    assert_eq!(bar.path, None);
+    let baz = dep("baz").expect("baz is a dependency");
+    assert_eq!(baz.version, Some(VersionReq::parse("~1.2")?));
+    assert_eq!(baz.path, None);
+
+    let unchanged = rewrite_path_deps(&rewritten, Path::new("foo"), |_, _, _| {
+        Ok::<_, Infallible>(Version::new(0, 0, 0))
+    })?;
+    assert_eq!(unchanged, None);
+    Ok(())
+}
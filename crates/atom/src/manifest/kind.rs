@@ -0,0 +1,190 @@
+//! # Atom Kinds
+//!
+//! An Atom may declare what it is with the `trait` key of its \[atom] table, e.g. a package, a
+//! deployment or a configuration, so that tooling can dispatch on it. The set of kinds is open:
+//! beyond the builtin ones, tooling may register its own in a [`KindRegistry`], along with
+//! hooks validating the manifests of Atoms declaring them.
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::Manifest;
+
+/// The kind of an Atom, as declared by the `trait` key of its manifest.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Kind {
+    /// Software to be built and installed.
+    Package,
+    /// A description of a running system or service.
+    Deployment,
+    /// Configuration to be consumed by other Atoms.
+    Config,
+    /// A kind defined by downstream tooling.
+    Custom(String),
+}
+
+/// A hook validating the manifest of an Atom of a given [`Kind`], returning the reason it is
+/// invalid, if it is.
+pub type Validator = fn(&Manifest) -> Result<(), String>;
+
+/// Errors which occur while parsing or validating the kind of an Atom.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum KindError {
+    /// The name of the kind is not lowercase alphanumeric words separated by `-`.
+    #[error("`{0}` is not a valid trait, expected lowercase alphanumerics and `-`")]
+    InvalidName(String),
+    /// The kind is not registered.
+    #[error("Unknown trait `{0}`")]
+    Unknown(Kind),
+    /// The manifest was rejected by one of the validators of its kind.
+    #[error("Invalid `{kind}` atom: {reason}")]
+    Invalid {
+        /// The kind of the Atom.
+        kind: Kind,
+        /// The reason given by the validator.
+        reason: String,
+    },
+}
+
+impl Kind {
+    /// The name of the kind, as written in a manifest.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Kind::Package => "package",
+            Kind::Deployment => "deployment",
+            Kind::Config => "config",
+            Kind::Custom(name) => name,
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = KindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "package" => Kind::Package,
+            "deployment" => Kind::Deployment,
+            "config" => Kind::Config,
+            _ => {
+                let valid = s.split('-').all(|w| {
+                    !w.is_empty()
+                        && w.bytes()
+                            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+                });
+                if !valid {
+                    return Err(KindError::InvalidName(s.to_owned()));
+                }
+                Kind::Custom(s.to_owned())
+            },
+        })
+    }
+}
+
+impl TryFrom<String> for Kind {
+    type Error = KindError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Kind> for String {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Custom(name) => name,
+            kind => kind.as_str().to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The set of known Atom kinds, and the hooks validating the manifests of Atoms of each.
+///
+/// The default registry knows only the builtin kinds, which impose no constraints of their own.
+#[derive(Debug, Clone)]
+pub struct KindRegistry {
+    kinds: BTreeMap<Kind, Vec<Validator>>,
+}
+
+impl Default for KindRegistry {
+    fn default() -> Self {
+        KindRegistry::empty()
+            .register(Kind::Package)
+            .register(Kind::Deployment)
+            .register(Kind::Config)
+    }
+}
+
+impl KindRegistry {
+    /// Construct a registry with no known kinds.
+    #[must_use]
+    pub fn empty() -> Self {
+        KindRegistry {
+            kinds: BTreeMap::new(),
+        }
+    }
+
+    /// Register a kind, if it is not already known.
+    #[must_use]
+    pub fn register(mut self, kind: Kind) -> Self {
+        self.kinds.entry(kind).or_default();
+        self
+    }
+
+    /// Register a hook validating the manifests of Atoms of the given kind, registering the
+    /// kind itself if it is not already known.
+    #[must_use]
+    pub fn hook(mut self, kind: Kind, validator: Validator) -> Self {
+        self.kinds.entry(kind).or_default().push(validator);
+        self
+    }
+
+    /// Whether the given kind is known to the registry.
+    #[must_use]
+    pub fn contains(&self, kind: &Kind) -> bool {
+        self.kinds.contains_key(kind)
+    }
+
+    /// Iterate over the kinds known to the registry.
+    pub fn kinds(&self) -> impl Iterator<Item = &Kind> {
+        self.kinds.keys()
+    }
+
+    /// Validate the manifest against the hooks registered for its kind. Manifests which
+    /// declare no kind are always valid.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the manifest declares an unknown kind, or any
+    /// hook of its kind rejects it.
+    pub fn validate(&self, manifest: &Manifest) -> Result<(), KindError> {
+        let Some(kind) = &manifest.atom.kind else {
+            return Ok(());
+        };
+        let validators = self
+            .kinds
+            .get(kind)
+            .ok_or_else(|| KindError::Unknown(kind.clone()))?;
+
+        validators.iter().try_for_each(|validate| {
+            validate(manifest).map_err(|reason| KindError::Invalid {
+                kind: kind.clone(),
+                reason,
+            })
+        })
+    }
+}
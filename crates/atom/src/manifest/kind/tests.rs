@@ -0,0 +1,58 @@
+use super::*;
+
+const MANIFEST: &str = r#"
+[atom]
+id = "foo"
+version = "0.1.0"
+trait = "deployment"
+"#;
+
+#[test]
+fn parse_kinds() -> Result<(), anyhow::Error> {
+    assert_eq!(Kind::from_str("package")?, Kind::Package);
+    assert_eq!(
+        Kind::from_str("build-cache2")?,
+        Kind::Custom("build-cache2".into())
+    );
+    for invalid in ["", "Package", "-cache", "build--cache", "build_cache"] {
+        assert!(Kind::from_str(invalid).is_err(), "{invalid:?}");
+    }
+
+    let manifest = Manifest::from_str(MANIFEST)?;
+    assert_eq!(manifest.atom.kind, Some(Kind::Deployment));
+    let roundtrip = toml_edit::ser::to_string_pretty(&manifest)?;
+    assert!(roundtrip.contains(r#"trait = "deployment""#));
+    Ok(())
+}
+
+#[test]
+fn registry_hooks() -> Result<(), anyhow::Error> {
+    fn described(manifest: &Manifest) -> Result<(), String> {
+        match manifest.atom.description {
+            Some(_) => Ok(()),
+            None => Err("a description is required".into()),
+        }
+    }
+
+    let manifest = Manifest::from_str(MANIFEST)?;
+    assert!(KindRegistry::default().validate(&manifest).is_ok());
+    assert_eq!(
+        KindRegistry::empty().validate(&manifest),
+        Err(KindError::Unknown(Kind::Deployment))
+    );
+
+    let registry = KindRegistry::default().hook(Kind::Deployment, described);
+    assert!(matches!(
+        registry.validate(&manifest),
+        Err(KindError::Invalid {
+            kind: Kind::Deployment,
+            ..
+        })
+    ));
+
+    let custom = Manifest::from_str(&MANIFEST.replace("deployment", "build-cache"))?;
+    assert!(KindRegistry::default().validate(&custom).is_err());
+    let registry = registry.register(Kind::Custom("build-cache".into()));
+    assert!(registry.validate(&custom).is_ok());
+    Ok(())
+}
@@ -0,0 +1,187 @@
+//! # Manifest Lints
+//!
+//! Beyond what the manifest format requires, a store may hold its Atoms to conventions of its
+//! own, e.g. that every Atom is described, or that the ids of an organization follow its naming
+//! scheme. Each lint is given a [`LintLevel`] in the `[lint]` section of the configuration, so
+//! that a [`Linter`] reports its violations, or refuses the manifests violating it outright.
+#[cfg(test)]
+mod tests;
+
+use config::{LintConfig, LintLevel, LintLevels};
+use regex::Regex;
+use semver::Version;
+use thiserror::Error;
+
+use crate::Atom;
+
+/// A manifest violating one of the configured lints.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The Atom declares no description.
+    #[error("`{0}` has no description")]
+    MissingDescription(String),
+    /// The description of the Atom is longer than allowed.
+    #[error("The description of `{id}` is {len} characters long, more than {max}")]
+    DescriptionTooLong {
+        /// The id of the Atom.
+        id: String,
+        /// The length of the description, in characters.
+        len: usize,
+        /// The maximum length allowed.
+        max: usize,
+    },
+    /// The Atom is at a version before 1.0.0.
+    #[error("`{0}` is at version {1}, before 1.0.0")]
+    UnstableVersion(String, Version),
+    /// The Atom declares more keywords than allowed.
+    #[error("`{id}` declares {count} keywords, more than {max}")]
+    TooManyKeywords {
+        /// The id of the Atom.
+        id: String,
+        /// The number of keywords declared.
+        count: usize,
+        /// The maximum number allowed.
+        max: usize,
+    },
+    /// The id of the Atom does not follow the convention for its prefix.
+    #[error("`{id}` does not match `{pattern}`, the convention for ids starting with `{prefix}`")]
+    IdConvention {
+        /// The id of the Atom.
+        id: String,
+        /// The prefix the convention applies to.
+        prefix: String,
+        /// The pattern of the convention.
+        pattern: String,
+    },
+}
+
+impl Violation {
+    /// The severity of the violation, as configured by `levels`.
+    #[must_use]
+    pub fn level(&self, levels: &LintLevels) -> LintLevel {
+        match self {
+            Violation::MissingDescription(_) => levels.missing_description,
+            Violation::DescriptionTooLong { .. } => levels.description_too_long,
+            Violation::UnstableVersion(..) => levels.unstable_version,
+            Violation::TooManyKeywords { .. } => levels.too_many_keywords,
+            Violation::IdConvention { .. } => levels.id_convention,
+        }
+    }
+}
+
+/// The violations of lints configured at the `error` level, refusing a manifest.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+pub struct Denied(pub Vec<Violation>);
+
+/// An error in the configuration of the lints.
+#[derive(Error, Debug)]
+pub enum LintError {
+    /// The naming convention for a prefix is not a valid regular expression.
+    #[error("The convention for ids starting with `{0}` is invalid: {1}")]
+    Convention(String, #[source] regex::Error),
+}
+
+/// Checks the manifests of Atoms against the configured lints.
+#[derive(Debug, Clone, Default)]
+pub struct Linter {
+    config: LintConfig,
+    /// The compiled naming conventions, ordered by prefix.
+    conventions: Vec<(String, Regex)>,
+}
+
+impl Linter {
+    /// Compile the lints of the given configuration.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any naming convention is not a valid regular
+    /// expression.
+    pub fn new(config: &LintConfig) -> Result<Self, LintError> {
+        let mut conventions = config
+            .id_conventions
+            .iter()
+            .map(|(prefix, pattern)| {
+                Regex::new(pattern)
+                    .map(|regex| (prefix.clone(), regex))
+                    .map_err(|e| LintError::Convention(prefix.clone(), e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        conventions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(Linter {
+            config: config.clone(),
+            conventions,
+        })
+    }
+
+    /// The violations of the lints not allowed by the configuration, along with their level.
+    ///
+    /// An id must match the pattern of every prefix it starts with. Patterns are not anchored,
+    /// unless they say so, e.g. with `^` and `$`.
+    #[must_use]
+    pub fn lint(&self, atom: &Atom) -> Vec<(LintLevel, Violation)> {
+        let id = atom.id.to_string();
+        let mut violations = Vec::new();
+
+        match &atom.description {
+            None => violations.push(Violation::MissingDescription(id.clone())),
+            Some(description) => {
+                let len = description.chars().count();
+                if let Some(max) = self.config.description_max_length.filter(|max| len > *max) {
+                    violations.push(Violation::DescriptionTooLong {
+                        id: id.clone(),
+                        len,
+                        max,
+                    });
+                }
+            },
+        }
+
+        if atom.version.major == 0 {
+            violations.push(Violation::UnstableVersion(id.clone(), atom.version.clone()));
+        }
+
+        let count = atom.keywords.len();
+        if let Some(max) = self.config.max_keywords.filter(|max| count > *max) {
+            violations.push(Violation::TooManyKeywords {
+                id: id.clone(),
+                count,
+                max,
+            });
+        }
+
+        for (prefix, regex) in &self.conventions {
+            if id.starts_with(prefix.as_str()) && !regex.is_match(&id) {
+                violations.push(Violation::IdConvention {
+                    id: id.clone(),
+                    prefix: prefix.clone(),
+                    pattern: regex.as_str().to_owned(),
+                });
+            }
+        }
+
+        violations
+            .into_iter()
+            .map(|violation| (violation.level(&self.config.levels), violation))
+            .filter(|(level, _)| *level != LintLevel::Allow)
+            .collect()
+    }
+
+    /// Check the manifest of an Atom, returning the violations to warn about.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error holding the violations of every lint configured at
+    /// the `error` level, if there are any.
+    pub fn check(&self, atom: &Atom) -> Result<Vec<Violation>, Denied> {
+        let (denied, warned): (Vec<_>, Vec<_>) = self
+            .lint(atom)
+            .into_iter()
+            .partition(|(level, _)| *level == LintLevel::Error);
+        if !denied.is_empty() {
+            return Err(Denied(denied.into_iter().map(|(_, v)| v).collect()));
+        }
+        Ok(warned.into_iter().map(|(_, v)| v).collect())
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use super::*;
+use crate::Manifest;
+
+const MANIFEST: &str = r#"
+[atom]
+id = "acmetools"
+version = "0.3.0"
+description = "Tools of the Acme organization"
+keywords = ["acme", "tools", "cli"]
+"#;
+
+#[test]
+fn lint_manifests() -> Result<(), anyhow::Error> {
+    let atom = Manifest::get_atom(MANIFEST)?;
+    assert_eq!(atom.keywords.len(), 3);
+
+    // without thresholds or conventions, the default levels allow everything else
+    assert!(Linter::default().lint(&atom).is_empty());
+
+    let config = LintConfig {
+        description_max_length: Some(16),
+        max_keywords: Some(2),
+        id_conventions: HashMap::from([
+            ("acme".into(), "^acme-[a-z]+$".into()),
+            ("other".into(), "^other-".into()),
+        ]),
+        levels: LintLevels {
+            missing_description: LintLevel::Warn,
+            unstable_version: LintLevel::Warn,
+            ..LintLevels::default()
+        },
+    };
+    let linter = Linter::new(&config)?;
+    let id = || "acmetools".to_owned();
+    assert_eq!(
+        linter.lint(&atom),
+        vec![
+            (
+                LintLevel::Warn,
+                Violation::DescriptionTooLong {
+                    id: id(),
+                    len: 30,
+                    max: 16
+                }
+            ),
+            (
+                LintLevel::Warn,
+                Violation::UnstableVersion(id(), Version::new(0, 3, 0))
+            ),
+            (
+                LintLevel::Warn,
+                Violation::TooManyKeywords {
+                    id: id(),
+                    count: 3,
+                    max: 2
+                }
+            ),
+            (
+                LintLevel::Error,
+                Violation::IdConvention {
+                    id: id(),
+                    prefix: "acme".into(),
+                    pattern: "^acme-[a-z]+$".into()
+                }
+            ),
+        ]
+    );
+    assert!(matches!(linter.check(&atom), Err(Denied(denied)) if denied.len() == 1));
+
+    let conforming = Manifest::get_atom("[atom]\nid = \"acme-tools\"\nversion = \"1.0.0\"\n")?;
+    assert_eq!(
+        linter.check(&conforming)?,
+        vec![Violation::MissingDescription("acme-tools".into())]
+    );
+    Ok(())
+}
+
+#[test]
+fn invalid_conventions() {
+    let config = LintConfig {
+        id_conventions: HashMap::from([("acme".into(), "(".into())]),
+        ..LintConfig::default()
+    };
+    assert!(matches!(
+        Linter::new(&config),
+        Err(LintError::Convention(prefix, _)) if prefix == "acme"
+    ));
+}
@@ -0,0 +1,297 @@
+//! # Atom Scaffolding
+//!
+//! A [`Scaffold`] lays out the directory of a new Atom, with a manifest named after its id,
+//! declaring its id, version, and optional description. It may be laid out from a template
+//! directory instead, whose files are copied with the `{{id}}`, `{{version}}` and
+//! `{{description}}` placeholders replaced, in their names as well as their content:
+//!
+//! ```console
+//! template/
+//! ├── {{id}}@.toml
+//! └── {{id}}/default.nix
+//! ```
+//!
+//! In TOML files, such as the manifest, the values replacing placeholders are escaped as the
+//! content of a string, so they are meant to be placed in one, e.g. `"{{description}}"`. File
+//! names may not render to anything but a single name, so that nothing is written outside of
+//! the new Atom's directory.
+//!
+//! A template need not hold a manifest, one is written as without a template otherwise. The
+//! new Atom may also be declared in the [`WORKSPACE_FILE`](super::WORKSPACE_FILE), so that the
+//! other Atoms of the repository can depend on it with `workspace = true`.
+#[cfg(test)]
+mod tests;
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use thiserror::Error;
+use toml_edit::{DocumentMut, Item, Table, value};
+
+use super::{AtomError, Dependencies, Manifest};
+use crate::id::Id;
+use crate::{ATOM_EXT, Atom};
+
+/// The version of a new Atom, unless given.
+pub const INITIAL_VERSION: Version = Version::new(0, 1, 0);
+
+/// The Atom a new directory is scaffolded for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scaffold {
+    /// The id of the new Atom.
+    pub id: Id,
+    /// The version of the new Atom.
+    pub version: Version,
+    /// The description of the new Atom, if any.
+    pub description: Option<String>,
+}
+
+/// An error encountered while scaffolding a new Atom.
+#[derive(Error, Debug)]
+pub enum ScaffoldError {
+    /// The directory to scaffold the Atom in already has content.
+    #[error("`{0}` already exists and is not empty")]
+    Exists(PathBuf),
+    /// The manifest laid out from the template is invalid.
+    #[error("The manifest `{0}` laid out from the template is invalid: {1}")]
+    Invalid(PathBuf, AtomError),
+    /// A file name of the template renders to something other than a single file name.
+    #[error("`{0}` is not a valid file name, as rendered from the template")]
+    UnsafeName(String),
+    /// The manifest laid out from the template declares another id.
+    #[error("The manifest laid out from the template declares `{0}`, rather than `{1}`")]
+    Mismatch(Id, Id),
+    /// The dependencies of the workspace are not declared as tables.
+    #[error("The workspace declares `deps.atoms` other than as a table")]
+    Malformed,
+    /// The workspace already declares a dependency on the Atom.
+    #[error("The workspace already declares `deps.atoms.{0}`")]
+    Declared(Id),
+    /// A transparent wrapper for a [`toml_edit::TomlError`]
+    #[error(transparent)]
+    Toml(#[from] toml_edit::TomlError),
+    /// A transparent wrapper for a [`toml_edit::ser::Error`]
+    #[error(transparent)]
+    Serialize(#[from] toml_edit::ser::Error),
+    /// A transparent wrapper for a [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl Scaffold {
+    /// Construct the scaffold of the Atom `id`, at its [`INITIAL_VERSION`].
+    #[must_use]
+    pub fn new(id: Id) -> Self {
+        Scaffold {
+            id,
+            version: INITIAL_VERSION,
+            description: None,
+        }
+    }
+
+    /// The file name of the manifest of the new Atom.
+    #[must_use]
+    pub fn manifest_name(&self) -> String {
+        format!("{}{}", self.id, ATOM_EXT.as_str())
+    }
+
+    /// Render the manifest of the new Atom.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the manifest cannot be serialized.
+    pub fn manifest(&self) -> Result<String, ScaffoldError> {
+        let manifest = Manifest {
+            atom: Atom {
+                id: self.id.clone(),
+                version: self.version.clone(),
+                kind: None,
+                description: self.description.clone(),
+                keywords: Vec::new(),
+                license: None,
+                exclude: vec![],
+                include: vec![],
+                min_format: None,
+            },
+            deps: Dependencies::default(),
+            artifacts: Default::default(),
+        };
+        Ok(toml_edit::ser::to_string_pretty(&manifest)?)
+    }
+
+    /// Replace the placeholders of a template in `text`.
+    #[must_use]
+    pub fn render(&self, text: &str) -> String {
+        self.render_with(text, str::to_owned)
+    }
+
+    /// Replace the placeholders of a template in the TOML document `text`, escaping their
+    /// values as the content of a string.
+    #[must_use]
+    pub fn render_toml(&self, text: &str) -> String {
+        self.render_with(text, escape_toml)
+    }
+
+    fn render_with(&self, text: &str, escape: impl Fn(&str) -> String) -> String {
+        text.replace("{{id}}", &escape(&self.id))
+            .replace("{{version}}", &escape(&self.version.to_string()))
+            .replace(
+                "{{description}}",
+                &escape(self.description.as_deref().unwrap_or_default()),
+            )
+    }
+
+    /// Lay out the new Atom in `dir`, which must not have any content yet, from `template` if
+    /// given, returning the path to each file written, its manifest first. Should it fail,
+    /// nothing written to `dir` is left behind.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `dir` has content, the files cannot be written,
+    /// or the manifest laid out from the template is invalid, or declares another id.
+    pub fn write(
+        &self,
+        dir: &Path,
+        template: Option<&Path>,
+    ) -> Result<Vec<PathBuf>, ScaffoldError> {
+        if dir
+            .read_dir()
+            .is_ok_and(|mut entries| entries.next().is_some())
+        {
+            return Err(ScaffoldError::Exists(dir.to_owned()));
+        }
+        let existed = dir.exists();
+        std::fs::create_dir_all(dir)?;
+
+        let written = self.lay_out(dir, template);
+        if written.is_err() {
+            // the directory is left as it was found, empty if it existed at all
+            let _ = std::fs::remove_dir_all(dir);
+            if existed {
+                let _ = std::fs::create_dir(dir);
+            }
+        }
+        written
+    }
+
+    /// Lay out the new Atom in the empty directory `dir`, as for [`Scaffold::write`].
+    fn lay_out(&self, dir: &Path, template: Option<&Path>) -> Result<Vec<PathBuf>, ScaffoldError> {
+        let mut written = Vec::new();
+        if let Some(template) = template {
+            self.copy(template, dir, &mut written)?;
+        }
+
+        let manifest = written
+            .iter()
+            .position(|path| path.to_string_lossy().ends_with(ATOM_EXT.as_str()));
+        match manifest {
+            Some(i) => {
+                let path = written.remove(i);
+                let atom = Manifest::get_atom(&std::fs::read_to_string(&path)?)
+                    .map_err(|e| ScaffoldError::Invalid(path.clone(), e))?;
+                if atom.id != self.id {
+                    return Err(ScaffoldError::Mismatch(atom.id, self.id.clone()));
+                }
+                written.insert(0, path);
+            },
+            None => {
+                let path = dir.join(self.manifest_name());
+                std::fs::write(&path, self.manifest()?)?;
+                written.insert(0, path);
+            },
+        }
+        Ok(written)
+    }
+
+    /// Copy the template directory `from` into `to`, rendering the names and content of its
+    /// files, and skipping the `.git` directory of a template checked out on its own.
+    fn copy(
+        &self,
+        from: &Path,
+        to: &Path,
+        written: &mut Vec<PathBuf>,
+    ) -> Result<(), ScaffoldError> {
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name == ".git" {
+                continue;
+            }
+            let name = self.render(&name.to_string_lossy());
+            if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+                return Err(ScaffoldError::UnsafeName(name));
+            }
+            let path = to.join(name);
+            if entry.file_type()?.is_dir() {
+                std::fs::create_dir_all(&path)?;
+                self.copy(&entry.path(), &path, written)?;
+                continue;
+            }
+            let toml = path.extension().is_some_and(|e| e == "toml");
+            let content = std::fs::read(entry.path())?;
+            match String::from_utf8(content) {
+                Ok(text) if toml => std::fs::write(&path, self.render_toml(&text))?,
+                Ok(text) => std::fs::write(&path, self.render(&text))?,
+                // binary files are copied as they are
+                Err(e) => std::fs::write(&path, e.into_bytes())?,
+            }
+            written.push(path);
+        }
+        Ok(())
+    }
+
+    /// Declare the new Atom as a dependency in the workspace manifest `content`, at `path`,
+    /// relative to the repository root, leaving the rest of the manifest untouched.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the workspace manifest is not valid TOML, or
+    /// already declares a dependency on the Atom.
+    pub fn register(&self, content: &str, path: &Path) -> Result<String, ScaffoldError> {
+        let mut doc = content.parse::<DocumentMut>()?;
+        let atoms = ["deps", "atoms"]
+            .iter()
+            .try_fold(doc.as_table_mut(), |table, key| {
+                let item = table.entry(key).or_insert_with(|| {
+                    let mut table = Table::new();
+                    table.set_implicit(true);
+                    Item::Table(table)
+                });
+                item.as_table_mut()
+            });
+        let Some(atoms) = atoms else {
+            return Err(ScaffoldError::Malformed);
+        };
+        if atoms.contains_key(&self.id) {
+            return Err(ScaffoldError::Declared(self.id.clone()));
+        }
+
+        let mut dep = Table::new();
+        dep.insert("version", value(format!("^{}", self.version)));
+        dep.insert("path", value(path.to_string_lossy().as_ref()));
+        atoms.insert(&self.id, Item::Table(dep));
+        Ok(doc.to_string())
+    }
+}
+
+/// Escape `value` as the content of a basic TOML string.
+fn escape_toml(value: &str) -> String {
+    use std::fmt::Write;
+
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04X}", u32::from(c));
+            },
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,129 @@
+use super::*;
+
+fn scaffold() -> Scaffold {
+    Scaffold {
+        description: Some("The foo atom".into()),
+        ..Scaffold::new("foo".parse().unwrap())
+    }
+}
+
+#[test]
+fn manifest_declares_the_atom() -> Result<(), anyhow::Error> {
+    let scaffold = scaffold();
+    let atom = Manifest::get_atom(&scaffold.manifest()?)?;
+    assert_eq!(atom.id, scaffold.id);
+    assert_eq!(atom.version, INITIAL_VERSION);
+    assert_eq!(atom.description.as_deref(), Some("The foo atom"));
+    assert_eq!(scaffold.manifest_name(), "foo@.toml");
+    Ok(())
+}
+
+#[test]
+fn write_from_template() -> Result<(), anyhow::Error> {
+    let template = tempfile::tempdir()?;
+    std::fs::create_dir(template.path().join("{{id}}"))?;
+    std::fs::write(
+        template.path().join("{{id}}/default.nix"),
+        "# {{description}}\n{ version = \"{{version}}\"; }\n",
+    )?;
+
+    let dir = tempfile::tempdir()?;
+    let dir = dir.path().join("foo");
+    let scaffold = scaffold();
+    let written = scaffold.write(&dir, Some(template.path()))?;
+    assert_eq!(
+        written,
+        [dir.join("foo@.toml"), dir.join("foo/default.nix")]
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("foo/default.nix"))?,
+        "# The foo atom\n{ version = \"0.1.0\"; }\n"
+    );
+
+    // the directory now has content
+    assert!(matches!(
+        scaffold.write(&dir, None),
+        Err(ScaffoldError::Exists(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn write_rejects_mismatched_template() -> Result<(), anyhow::Error> {
+    let template = tempfile::tempdir()?;
+    std::fs::write(
+        template.path().join("bar@.toml"),
+        "[atom]\nid = \"bar\"\nversion = \"{{version}}\"\n",
+    )?;
+
+    let dir = tempfile::tempdir()?;
+    assert!(matches!(
+        scaffold().write(&dir.path().join("foo"), Some(template.path())),
+        Err(ScaffoldError::Mismatch(..))
+    ));
+    // nothing is left behind, and a directory given empty is left empty
+    assert!(!dir.path().join("foo").exists());
+    assert!(matches!(
+        scaffold().write(dir.path(), Some(template.path())),
+        Err(ScaffoldError::Mismatch(..))
+    ));
+    assert_eq!(std::fs::read_dir(dir.path())?.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn render_escapes_values() -> Result<(), anyhow::Error> {
+    let template = tempfile::tempdir()?;
+    std::fs::write(
+        template.path().join("{{id}}@.toml"),
+        "[atom]\nid = \"{{id}}\"\nversion = \"{{version}}\"\ndescription = \"{{description}}\"\n",
+    )?;
+    let description = "a \"quoted\" \\ one\nlicense = \"MIT\"";
+    let scaffold = Scaffold {
+        description: Some(description.into()),
+        ..scaffold()
+    };
+
+    // a value placed in a TOML string stays within it
+    let dir = tempfile::tempdir()?;
+    let written = scaffold.write(dir.path(), Some(template.path()))?;
+    let atom = Manifest::get_atom(&std::fs::read_to_string(&written[0])?)?;
+    assert_eq!(atom.description.as_deref(), Some(description));
+    assert_eq!(atom.license, None);
+
+    // and a file name renders to a single name
+    std::fs::write(template.path().join("{{description}}"), "")?;
+    let escaping = Scaffold {
+        description: Some("../escaped".into()),
+        ..scaffold()
+    };
+    let dir = tempfile::tempdir()?;
+    assert!(matches!(
+        escaping.write(&dir.path().join("foo"), Some(template.path())),
+        Err(ScaffoldError::UnsafeName(_))
+    ));
+    assert!(!dir.path().join("escaped").exists());
+    Ok(())
+}
+
+#[test]
+fn register_in_workspace() -> Result<(), anyhow::Error> {
+    let workspace = "# shared\n[atom]\nlicense = \"MIT\"\n";
+    let scaffold = scaffold();
+    let registered = scaffold.register(workspace, Path::new("libs/foo"))?;
+    assert!(registered.starts_with(workspace));
+
+    let parsed = crate::Workspace::from_slice(registered.as_bytes())?;
+    let dep = &parsed.deps.atoms[&scaffold.id];
+    assert_eq!(
+        dep.version.as_ref().map(ToString::to_string).as_deref(),
+        Some("^0.1.0")
+    );
+    assert_eq!(dep.path.as_deref(), Some(Path::new("libs/foo")));
+
+    assert!(matches!(
+        scaffold.register(&registered, Path::new("libs/foo")),
+        Err(ScaffoldError::Declared(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use super::*;
+
+const MANIFEST: &str = "[atom]\nid = \"foo\"\nversion = \"0.1.0\"\n";
+
+#[test]
+fn min_format() -> Result<(), anyhow::Error> {
+    let atom = Manifest::get_atom(MANIFEST)?;
+    assert_eq!(atom.min_format, None);
+
+    let current = format!("{MANIFEST}min-format = {MANIFEST_FORMAT}\n");
+    let atom = Manifest::get_atom(&current)?;
+    assert_eq!(atom.min_format, Some(MANIFEST_FORMAT));
+    assert!(toml_edit::ser::to_string(&atom)?.contains("min-format"));
+
+    // a manifest requiring a newer format is refused rather than misinterpreted
+    let newer = format!("{MANIFEST}min-format = {}\n", MANIFEST_FORMAT + 1);
+    let Err(AtomError::Unsupported(e)) = Manifest::get_atom(&newer) else {
+        anyhow::bail!("a newer manifest format was accepted");
+    };
+    assert_eq!(e.required, MANIFEST_FORMAT + 1);
+    assert!(e.to_string().contains("upgrade eka"));
+    Ok(())
+}
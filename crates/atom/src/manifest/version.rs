@@ -0,0 +1,126 @@
+//! # Version Bumps
+//!
+//! Releasing a new version of an Atom means editing the `version` of the `[atom]` table of its
+//! manifest. A [`Bump`] computes the next version from the current one, and
+//! [`Manifest::bump_version`](super::Manifest::bump_version) writes it into the manifest in
+//! place, leaving the rest of it, including its formatting and comments, untouched.
+#[cfg(test)]
+mod tests;
+
+use semver::{BuildMetadata, Prerelease, Version};
+use thiserror::Error;
+use toml_edit::{DocumentMut, Item, value};
+
+/// How the version of an Atom is bumped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bump {
+    /// Bump the major version, resetting the minor and patch versions.
+    Major,
+    /// Bump the minor version, resetting the patch version.
+    Minor,
+    /// Bump the patch version.
+    Patch,
+    /// Set the version outright.
+    To(Version),
+}
+
+/// The outcome of bumping the version of a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bumped {
+    /// The version the manifest declared.
+    pub old: Version,
+    /// The version the manifest declares now.
+    pub new: Version,
+    /// The content of the manifest, with the new version in place of the old.
+    pub content: String,
+}
+
+/// An error encountered while bumping the version of a manifest.
+#[derive(Error, Debug)]
+pub enum VersionError {
+    /// The manifest declares no version in its \[atom] table.
+    #[error("Manifest declares no `atom.version`")]
+    Missing,
+    /// The manifest inherits its version from the workspace.
+    #[error(
+        "`atom.version` is inherited from the workspace, bump it in `{}` instead",
+        super::WORKSPACE_FILE
+    )]
+    Inherited,
+    /// The version declared is not a string.
+    #[error("`atom.version` is not a string")]
+    NotAString,
+    /// A transparent wrapper for a [`semver::Error`]
+    #[error(transparent)]
+    Invalid(#[from] semver::Error),
+    /// A transparent wrapper for a [`toml_edit::TomlError`]
+    #[error(transparent)]
+    Toml(#[from] toml_edit::TomlError),
+}
+
+impl Bump {
+    /// Compute the version following `version`.
+    ///
+    /// The build metadata of `version` is dropped. A pre-release is released, rather than
+    /// bumped past, when the bump would not change any other component, e.g. a patch bump of
+    /// `1.2.3-rc.1` yields `1.2.3`, much like `cargo set-version --bump`.
+    #[must_use]
+    pub fn apply(&self, version: &Version) -> Version {
+        let release = !version.pre.is_empty();
+        let mut next = Version {
+            pre: Prerelease::EMPTY,
+            build: BuildMetadata::EMPTY,
+            ..version.clone()
+        };
+        match self {
+            Bump::Major => {
+                if !(release && version.minor == 0 && version.patch == 0) {
+                    next.major += 1;
+                }
+                next.minor = 0;
+                next.patch = 0;
+            },
+            Bump::Minor => {
+                if !(release && version.patch == 0) {
+                    next.minor += 1;
+                }
+                next.patch = 0;
+            },
+            Bump::Patch => {
+                if !release {
+                    next.patch += 1;
+                }
+            },
+            Bump::To(version) => return version.clone(),
+        }
+        next
+    }
+}
+
+/// Bump the version of the \[atom] table of the manifest `content`.
+pub(super) fn bump(content: &str, bump: &Bump) -> Result<Bumped, VersionError> {
+    let mut doc = content.parse::<DocumentMut>()?;
+    let item = doc
+        .get_mut("atom")
+        .and_then(Item::as_table_like_mut)
+        .and_then(|atom| atom.get_mut("version"))
+        .ok_or(VersionError::Missing)?;
+    if item.is_table_like() {
+        return Err(VersionError::Inherited);
+    }
+    let old: Version = item.as_str().ok_or(VersionError::NotAString)?.parse()?;
+    let new = bump.apply(&old);
+
+    // keep the decor, i.e. the whitespace and comments, around the old value
+    let decor = item.as_value().map(|v| v.decor().clone());
+    *item = value(new.to_string());
+    if let (Some(decor), Some(v)) = (decor, item.as_value_mut()) {
+        *v.decor_mut() = decor;
+    }
+
+    Ok(Bumped {
+        old,
+        new,
+        content: doc.to_string(),
+    })
+}
@@ -0,0 +1,61 @@
+use super::*;
+use crate::Manifest;
+
+const MANIFEST: &str = r#"# the foo atom
+[atom]
+id = "foo"
+version = "0.1.2" # released by hand
+
+[deps.atoms.bar]
+version = "^0.2"
+"#;
+
+#[test]
+fn bump_components() {
+    let bumps = [
+        (Bump::Major, "1.2.3", "2.0.0"),
+        (Bump::Minor, "1.2.3", "1.3.0"),
+        (Bump::Patch, "1.2.3", "1.2.4"),
+        (Bump::Patch, "1.2.3+build.5", "1.2.4"),
+        // pre-releases are released rather than bumped past
+        (Bump::Patch, "1.2.3-rc.1", "1.2.3"),
+        (Bump::Minor, "1.3.0-rc.1", "1.3.0"),
+        (Bump::Minor, "1.2.3-rc.1", "1.3.0"),
+        (Bump::Major, "2.0.0-alpha", "2.0.0"),
+        (Bump::Major, "2.1.0-alpha", "3.0.0"),
+        (Bump::To("0.9.0".parse().unwrap()), "1.2.3", "0.9.0"),
+    ];
+    for (bump, old, new) in bumps {
+        assert_eq!(
+            bump.apply(&old.parse().unwrap()).to_string(),
+            new,
+            "{bump:?} {old}"
+        );
+    }
+}
+
+#[test]
+fn bump_preserves_formatting() -> Result<(), anyhow::Error> {
+    let bumped = Manifest::bump_version(MANIFEST, &Bump::Minor)?;
+    assert_eq!(bumped.old.to_string(), "0.1.2");
+    assert_eq!(bumped.new.to_string(), "0.2.0");
+    assert_eq!(
+        bumped.content,
+        MANIFEST.replace(r#"version = "0.1.2""#, r#"version = "0.2.0""#)
+    );
+    assert_eq!(Manifest::get_atom(&bumped.content)?.version, bumped.new);
+    Ok(())
+}
+
+#[test]
+fn bump_rejects_inherited_or_missing() {
+    let inherited = "[atom]\nid = \"foo\"\nversion.workspace = true\n";
+    assert!(matches!(
+        Manifest::bump_version(inherited, &Bump::Patch),
+        Err(VersionError::Inherited)
+    ));
+    assert!(matches!(
+        Manifest::bump_version("[atom]\nid = \"foo\"\n", &Bump::Patch),
+        Err(VersionError::Missing)
+    ));
+}
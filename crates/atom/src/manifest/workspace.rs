@@ -0,0 +1,221 @@
+//! # Workspace Manifest
+//!
+//! A repository holding many Atoms may commit a [`WORKSPACE_FILE`] at its root, declaring the
+//! metadata and dependencies its Atoms share, in the tables of a manifest they belong in:
+//!
+//! ```toml
+//! [atom]
+//! license = "MIT"
+//! keywords = ["ekala"]
+//!
+//! [deps.atoms.bar]
+//! version = "^0.2"
+//! path = "libs/bar"
+//! ```
+//!
+//! Much like a Cargo workspace, each member Atom then inherits any of them explicitly, by
+//! declaring `workspace = true` in its place:
+//!
+//! ```toml
+//! [atom]
+//! id = "foo"
+//! version = "0.1.0"
+//! license.workspace = true
+//!
+//! [deps.atoms.bar]
+//! workspace = true
+//! ```
+//!
+//! Keys declared alongside `workspace = true` in a dependency override those of the
+//! workspace, and a `path` declared by the workspace is relative to the repository root.
+//! Inherited keys are written into the manifest of an Atom as it is published, so that its
+//! published spec never depends on the workspace it was published from.
+#[cfg(test)]
+mod tests;
+
+use std::path::{Component, Path, PathBuf};
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml_edit::ser::ValueSerializer;
+use toml_edit::{DocumentMut, Item, Value};
+
+use super::Dependencies;
+
+/// The name of the workspace manifest, at the root of a repository.
+pub const WORKSPACE_FILE: &str = "ekala.toml";
+
+/// The workspace manifest, declaring what the Atoms of a repository share.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    /// The fields of the `[atom]` table members may inherit.
+    #[serde(default)]
+    pub atom: Shared,
+    /// The dependencies members may inherit, by the name they are declared by.
+    #[serde(default, skip_serializing_if = "Dependencies::is_empty")]
+    pub deps: Dependencies,
+}
+
+/// The fields of the `[atom]` table of a manifest which may be inherited from the workspace.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Shared {
+    /// The version of the Atoms, e.g. those released together.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
+    /// The description of the Atoms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The keywords describing the Atoms.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// The license of the Atoms, as an SPDX license expression.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+}
+
+/// An error encountered while inheriting keys from the workspace.
+#[derive(Error, Debug)]
+pub enum WorkspaceError {
+    /// A member inherits a key the workspace does not declare.
+    #[error("`{0}` is inherited from the workspace, which does not declare it")]
+    Undeclared(String),
+    /// A transparent wrapper for a [`toml_edit::ser::Error`]
+    #[error(transparent)]
+    Serialize(#[from] toml_edit::ser::Error),
+}
+
+impl Workspace {
+    /// Parse a workspace manifest from the raw contents of its file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the content is not a valid workspace manifest.
+    pub fn from_slice(content: &[u8]) -> Result<Self, toml_edit::de::Error> {
+        toml_edit::de::from_slice(content)
+    }
+
+    /// Whether the workspace declares nothing, so that no member can inherit anything.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self == &Workspace::default()
+    }
+
+    /// Write each key the member manifest `content` inherits from the workspace in its place,
+    /// given the directory `dir` of the manifest, relative to the repository root.
+    ///
+    /// The rest of the manifest, including its formatting, is left untouched. Returns `None`
+    /// if nothing is inherited, or the manifest is not valid TOML, which is reported once it
+    /// is parsed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the member inherits a key the workspace does not
+    /// declare.
+    pub fn inherit(&self, content: &str, dir: &Path) -> Result<Option<String>, WorkspaceError> {
+        let Ok(mut doc) = content.parse::<DocumentMut>() else {
+            return Ok(None);
+        };
+
+        let Shared {
+            version,
+            description,
+            keywords,
+            license,
+        } = &self.atom;
+
+        let mut inherited = false;
+        if let Some(atom) = doc.get_mut("atom").and_then(Item::as_table_like_mut) {
+            for (key, item) in atom.iter_mut().filter(|(_, item)| inherits(item)) {
+                let shared = match key.get() {
+                    "version" => version.as_ref().map(to_value),
+                    "description" => description.as_ref().map(to_value),
+                    "keywords" => (!keywords.is_empty()).then(|| to_value(keywords)),
+                    "license" => license.as_ref().map(to_value),
+                    _ => None,
+                };
+                let undeclared = || WorkspaceError::Undeclared(format!("atom.{}", key.get()));
+                *item = Item::Value(shared.transpose()?.ok_or_else(undeclared)?);
+                inherited = true;
+            }
+        }
+
+        let Some(deps) = doc.get_mut("deps").and_then(Item::as_table_like_mut) else {
+            return Ok(inherited.then(|| doc.to_string()));
+        };
+        for (kind, table) in deps.iter_mut() {
+            let Some(table) = table.as_table_like_mut() else {
+                continue;
+            };
+            for (name, item) in table.iter_mut().filter(|(_, item)| inherits(item)) {
+                let shared = match kind.get() {
+                    "atoms" => self
+                        .deps
+                        .atoms
+                        .iter()
+                        .find(|(id, _)| id.as_str() == name.get())
+                        .map(|(_, dep)| {
+                            let mut dep = dep.clone();
+                            dep.path = dep.path.map(|path| to_root(dir).join(path));
+                            to_value(&dep)
+                        }),
+                    "pins" => self.deps.pins.get(name.get()).map(to_value),
+                    "srcs" => self.deps.srcs.get(name.get()).map(to_value),
+                    _ => None,
+                };
+                let undeclared =
+                    || WorkspaceError::Undeclared(format!("deps.{}.{}", kind.get(), name.get()));
+                let Value::InlineTable(mut shared) = shared.transpose()?.ok_or_else(undeclared)?
+                else {
+                    continue;
+                };
+
+                // keys declared by the member override those of the workspace
+                if let Some(member) = item.as_table_like() {
+                    for (key, value) in member.iter().filter(|(key, _)| *key != "workspace") {
+                        if let Some(value) = value.as_value() {
+                            shared.insert(key, value.clone());
+                        }
+                    }
+                }
+                *item = match item {
+                    Item::Table(table) => {
+                        let position = table.position();
+                        let mut shared = shared.into_table();
+                        if let Some(position) = position {
+                            shared.set_position(position);
+                        }
+                        Item::Table(shared)
+                    },
+                    _ => Item::Value(shared.into()),
+                };
+                inherited = true;
+            }
+        }
+
+        Ok(inherited.then(|| doc.to_string()))
+    }
+}
+
+/// Whether the item declares that it is inherited from the workspace, by `workspace = true`.
+fn inherits(item: &Item) -> bool {
+    item.as_table_like()
+        .and_then(|table| table.get("workspace"))
+        .and_then(Item::as_bool)
+        .unwrap_or(false)
+}
+
+/// Serialize an inherited value, to write it into a member's manifest.
+fn to_value<T: Serialize>(value: &T) -> Result<Value, WorkspaceError> {
+    Ok(value.serialize(ValueSerializer::new())?)
+}
+
+/// The path leading from `dir`, relative to the repository root, back up to the root.
+fn to_root(dir: &Path) -> PathBuf {
+    dir.components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .map(|_| Component::ParentDir)
+        .collect()
+}
@@ -0,0 +1,81 @@
+use super::*;
+use crate::Manifest;
+use crate::id::Id;
+
+const WORKSPACE: &str = r#"[atom]
+license = "MIT"
+keywords = ["ekala", "nix"]
+
+[deps.atoms.bar]
+version = "^0.2"
+path = "libs/bar"
+
+[deps.srcs.nixpkgs]
+url = "https://github.com/NixOS/nixpkgs"
+"#;
+
+const MEMBER: &str = r#"[atom]
+id = "foo"
+version = "0.1.0"
+license.workspace = true
+keywords = { workspace = true }
+
+[deps.atoms.bar]
+workspace = true
+version = "^0.2.3"
+
+[deps.srcs]
+nixpkgs = { workspace = true }
+"#;
+
+#[test]
+fn inherit_from_workspace() -> Result<(), anyhow::Error> {
+    let workspace = Workspace::from_slice(WORKSPACE.as_bytes())?;
+    let inherited = workspace
+        .inherit(MEMBER, Path::new("apps/foo"))?
+        .expect("keys are inherited");
+
+    let manifest: Manifest = inherited.parse()?;
+    assert_eq!(manifest.atom.license.as_deref(), Some("MIT"));
+    assert_eq!(manifest.atom.keywords, ["ekala", "nix"]);
+
+    // the member's requirement overrides the workspace's, while the path is made relative to
+    // the member's manifest
+    let bar = &manifest.deps.atoms[&Id::try_from("bar")?];
+    assert_eq!(bar.version, Some("^0.2.3".parse()?));
+    assert_eq!(bar.path.as_deref(), Some(Path::new("../../libs/bar")));
+    assert_eq!(
+        manifest.deps.srcs["nixpkgs"].url.as_str(),
+        "https://github.com/NixOS/nixpkgs"
+    );
+    Ok(())
+}
+
+#[test]
+fn inherit_nothing() -> Result<(), anyhow::Error> {
+    let workspace = Workspace::from_slice(WORKSPACE.as_bytes())?;
+    let member = "[atom]\nid = \"foo\"\nversion = \"0.1.0\"\nlicense = \"Apache-2.0\"\n";
+    assert_eq!(workspace.inherit(member, Path::new("foo"))?, None);
+    Ok(())
+}
+
+#[test]
+fn inherit_undeclared() -> Result<(), anyhow::Error> {
+    let workspace = Workspace::from_slice(b"[atom]\nlicense = \"MIT\"\n")?;
+    let member = "[atom]\nid = \"foo\"\nversion.workspace = true\n";
+    assert!(matches!(
+        workspace.inherit(member, Path::new("foo")),
+        Err(WorkspaceError::Undeclared(key)) if key == "atom.version"
+    ));
+
+    let member =
+        "[atom]\nid = \"foo\"\nversion = \"0.1.0\"\n\n[deps.atoms.bar]\nworkspace = true\n";
+    assert!(matches!(
+        workspace.inherit(member, Path::new("foo")),
+        Err(WorkspaceError::Undeclared(key)) if key == "deps.atoms.bar"
+    ));
+
+    // a workspace may only declare what members can inherit
+    assert!(Workspace::from_slice(b"[atom]\nid = \"foo\"\n").is_err());
+    Ok(())
+}
@@ -0,0 +1,262 @@
+//! # Store Policy
+//!
+//! An Ekala store may declare a policy restricting who may publish which Atoms. It is a TOML
+//! document kept by the store itself, and is enforced by clients at publish time, and by the
+//! store's hooks at push time:
+//!
+//! ```toml
+//! [[protected]]
+//! id = "core"
+//! versions = ">=1"
+//! signers = ["release@example.com"]
+//! ```
+//!
+//! Here only `release@example.com` may publish versions of the `core` Atom from 1.0.0 onwards.
+//! Omitting `versions` protects every version, and omitting `signers` forbids everyone.
+//!
+//! Identities are only as trustworthy as what vouches for them, the authenticated pusher for
+//! hooks, but merely the configured `user.email` for clients. A protection may instead, or as
+//! well, bind its versions to signing keys, by their fingerprints as for
+//! `signing.allowed-keys`:
+//!
+//! ```toml
+//! [[protected]]
+//! id = "core"
+//! keys = ["SHA256:4lzuQ7Hdt1Kk0q4dQ6yK0ZDhhwW7bV5cpcqSd1b2P6M"]
+//! ```
+//!
+//! The Atom commits of its versions must then be signed by one of the keys, which the hooks
+//! verify, and without `signers`, anyone may push them.
+//!
+//! A store shared by several teams may also divide its namespace between them, by the prefix
+//! of the Atom ids each team owns:
+//!
+//! ```toml
+//! namespaces = "deny"
+//!
+//! [teams.web]
+//! members = ["alice@example.com", "bob@example.com"]
+//! prefixes = ["web-"]
+//! ```
+//!
+//! Publishing an owned Atom as anyone but a member of an owning team is then refused, or only
+//! warned about if `namespaces` is `"warn"`, the default. Ids no team owns are open to all.
+//!
+//! Since the policy decides who may publish, it may itself only be changed by its owners, and
+//! the same goes for the root of the store and the organization's policy:
+//!
+//! ```toml
+//! owners = ["admin@example.com"]
+//! ```
+//!
+//! A policy without owners can no longer be changed by a push at all, only on the store's host.
+//!
+//! The rules an organization holds its Atoms to, wherever they are published, are instead
+//! declared by an [`OrgPolicy`], committed alongside the Atoms themselves.
+mod org;
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeMap;
+
+pub use org::{Breach, Breaches, Bumps, Field, ORG_POLICY_FILE, OrgPolicy};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::id::Id;
+
+/// The name of the file holding a store's policy.
+pub const POLICY_FILE: &str = "policy.toml";
+
+/// The policy declared by an Ekala store.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Policy {
+    /// Atoms, or ranges of their versions, which only the declared signers may publish.
+    pub protected: Vec<Protected>,
+    /// The teams sharing the store, by name.
+    pub teams: BTreeMap<String, Team>,
+    /// How publishing an Atom owned by another team is handled.
+    pub namespaces: Enforcement,
+    /// The identities permitted to change the policy itself, the root of the store, and the
+    /// organization's policy at its `HEAD`.
+    pub owners: Vec<String>,
+}
+
+/// A team, along with the part of the store's namespace it owns.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Team {
+    /// The identities of the team's members.
+    pub members: Vec<String>,
+    /// The prefixes of the Atom ids owned by the team.
+    pub prefixes: Vec<String>,
+}
+
+/// How a violation of the policy is handled.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Enforcement {
+    /// Warn about the violation, but carry on.
+    #[default]
+    Warn,
+    /// Refuse to carry on.
+    Deny,
+}
+
+/// A problem with the ownership declarations of a [`Policy`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// A team has no members, so none of its Atoms can be published.
+    #[error("Team `{0}` has no members")]
+    NoMembers(String),
+    /// A team owns no part of the namespace.
+    #[error("Team `{0}` owns no prefixes")]
+    NoPrefixes(String),
+    /// A team owns the empty prefix, and with it every Atom id.
+    #[error("Team `{0}` owns the empty prefix, and with it every Atom")]
+    EmptyPrefix(String),
+    /// Two teams claim the same prefix.
+    #[error("Prefix `{prefix}` is claimed by both `{first}` and `{second}`")]
+    SharedPrefix {
+        /// The prefix claimed twice.
+        prefix: String,
+        /// The first team claiming it.
+        first: String,
+        /// The second team claiming it.
+        second: String,
+    },
+}
+
+/// A protected Atom, or range of its versions.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Protected {
+    /// The id of the protected Atom.
+    pub id: Id,
+    /// The protected versions, defaulting to all of them.
+    #[serde(default = "any_version")]
+    pub versions: VersionReq,
+    /// The identities permitted to publish the protected versions.
+    #[serde(default)]
+    pub signers: Vec<String>,
+    /// The fingerprints of the keys one of which must sign the Atom commits of the protected
+    /// versions.
+    #[serde(default)]
+    pub keys: Vec<String>,
+}
+
+fn any_version() -> VersionReq {
+    VersionReq::STAR
+}
+
+impl Policy {
+    /// Parse a policy from the raw contents of its file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the content is not a valid policy document.
+    pub fn from_slice(content: &[u8]) -> Result<Self, toml_edit::de::Error> {
+        toml_edit::de::from_slice(content)
+    }
+
+    /// Return the first protection forbidding `signer` from publishing the given version of an
+    /// Atom, if there is one.
+    #[must_use]
+    pub fn violation(
+        &self,
+        id: &Id,
+        version: &Version,
+        signer: Option<&str>,
+    ) -> Option<&Protected> {
+        self.protected
+            .iter()
+            .find(|p| p.covers(id, version) && !p.permits(signer))
+    }
+
+    /// Return the protections requiring the Atom commit of the given version of an Atom to be
+    /// signed by one of their keys.
+    #[must_use]
+    pub fn signed(&self, id: &Id, version: &Version) -> Vec<&Protected> {
+        self.protected
+            .iter()
+            .filter(|p| p.covers(id, version) && !p.keys.is_empty())
+            .collect()
+    }
+
+    /// Return the names of the teams owning the given Atom id if `signer` is a member of none
+    /// of them, i.e. if publishing it would trespass on another team's namespace.
+    #[must_use]
+    pub fn trespass(&self, id: &Id, signer: Option<&str>) -> Option<Vec<&str>> {
+        let owners: Vec<_> = self.teams.iter().filter(|(_, t)| t.owns(id)).collect();
+        let member = owners.iter().any(|(_, t)| t.has_member(signer));
+
+        (!owners.is_empty() && !member)
+            .then(|| owners.into_iter().map(|(name, _)| name.as_str()).collect())
+    }
+
+    /// Whether `signer` is one of the owners of the policy, and may change it.
+    #[must_use]
+    pub fn administers(&self, signer: Option<&str>) -> bool {
+        signer.is_some_and(|s| self.owners.iter().any(|o| o == s))
+    }
+
+    /// Validate the ownership declarations of the policy, returning any problems found.
+    #[must_use]
+    pub fn check(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let mut claimed: BTreeMap<&str, &str> = BTreeMap::new();
+
+        for (name, team) in &self.teams {
+            if team.members.is_empty() {
+                issues.push(Issue::NoMembers(name.clone()));
+            }
+            if team.prefixes.is_empty() {
+                issues.push(Issue::NoPrefixes(name.clone()));
+            }
+            for prefix in &team.prefixes {
+                if prefix.is_empty() {
+                    issues.push(Issue::EmptyPrefix(name.clone()));
+                } else if let Some(first) = claimed.insert(prefix, name) {
+                    issues.push(Issue::SharedPrefix {
+                        prefix: prefix.clone(),
+                        first: first.to_owned(),
+                        second: name.clone(),
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+impl Team {
+    /// Whether the given Atom id falls within the team's namespace.
+    #[must_use]
+    pub fn owns(&self, id: &Id) -> bool {
+        self.prefixes.iter().any(|p| id.starts_with(p.as_str()))
+    }
+
+    /// Whether `signer` is a member of the team.
+    #[must_use]
+    pub fn has_member(&self, signer: Option<&str>) -> bool {
+        signer.is_some_and(|s| self.members.iter().any(|m| m == s))
+    }
+}
+
+impl Protected {
+    /// Whether this protection applies to the given version of an Atom.
+    #[must_use]
+    pub fn covers(&self, id: &Id, version: &Version) -> bool {
+        &self.id == id && self.versions.matches(version)
+    }
+
+    /// Whether `signer` is one of the identities permitted to publish under this protection,
+    /// or it only binds its versions to keys.
+    #[must_use]
+    pub fn permits(&self, signer: Option<&str>) -> bool {
+        (self.signers.is_empty() && !self.keys.is_empty())
+            || signer.is_some_and(|s| self.signers.iter().any(|p| p == s))
+    }
+}
@@ -0,0 +1,266 @@
+//! # Organization Policy
+//!
+//! Beyond the policy a store declares for itself, an organization may commit an
+//! [`ORG_POLICY_FILE`] at the root of the repository its Atoms are published from, holding
+//! every Atom to the same rules, regardless of who publishes it:
+//!
+//! ```toml
+//! required = ["description", "license"]
+//! forbidden-licenses = ["AGPL-3.0-only"]
+//! allowed-hosts = ["github.com", "*.example.com"]
+//! bumps = "sequential"
+//! ```
+//!
+//! Here every manifest must declare a description and a license, which may not be the AGPL,
+//! and may only depend on Atoms and sources hosted on GitHub or under `example.com`. With
+//! `bumps = "sequential"`, each new version must directly follow a published one, e.g. 1.2.3
+//! may be followed by 1.2.4, 1.3.0 or 2.0.0, but not by 1.2.5. The first version published is
+//! unrestricted.
+use std::collections::BTreeSet;
+use std::fmt;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+use crate::Manifest;
+
+/// The name of the file holding an organization's policy, at the root of its repository.
+pub const ORG_POLICY_FILE: &str = "ekala-policy.toml";
+
+/// The policy an organization holds its Atoms to.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OrgPolicy {
+    /// The optional fields of the `[atom]` table every manifest must declare.
+    pub required: Vec<Field>,
+    /// The licenses no Atom may be published under, by their SPDX identifier.
+    pub forbidden_licenses: Vec<String>,
+    /// The hosts dependencies may be fetched from, allowing any if empty. A host starting with
+    /// `*.` allows any of its subdomains.
+    pub allowed_hosts: Vec<String>,
+    /// How the versions of an Atom may follow one another.
+    pub bumps: Bumps,
+}
+
+/// An optional field of the `[atom]` table of a manifest.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Field {
+    /// The `description` of the Atom.
+    Description,
+    /// The `license` of the Atom.
+    License,
+    /// The `keywords` of the Atom, of which there must be at least one.
+    Keywords,
+    /// The kind of the Atom, declared by its `trait`.
+    #[serde(rename = "trait")]
+    Kind,
+}
+
+/// How the versions of an Atom may follow one another.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bumps {
+    /// Any version not yet published may be published.
+    #[default]
+    Any,
+    /// Each new version must be the next patch, minor or major version of a published one.
+    Sequential,
+}
+
+/// A manifest breaking a rule of the [`OrgPolicy`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Breach {
+    /// The manifest does not declare a required field.
+    #[error("`{id}` does not declare the required `{field}`")]
+    MissingField {
+        /// The id of the Atom.
+        id: String,
+        /// The missing field.
+        field: Field,
+    },
+    /// The Atom is published under a forbidden license.
+    #[error("`{id}` is licensed under `{license}`, which is forbidden")]
+    ForbiddenLicense {
+        /// The id of the Atom.
+        id: String,
+        /// The license expression declared by the Atom.
+        license: String,
+    },
+    /// A dependency is fetched from a host which is not allowed.
+    #[error("`{id}` depends on `{dep}` from `{url}`, whose host is not allowed")]
+    DisallowedHost {
+        /// The id of the Atom.
+        id: String,
+        /// The name of the dependency.
+        dep: String,
+        /// The url the dependency is fetched from.
+        url: String,
+    },
+    /// The version does not directly follow a published one.
+    #[error("`{id}` {version} does not directly follow a published version")]
+    Unsequential {
+        /// The id of the Atom.
+        id: String,
+        /// The version of the Atom.
+        version: Version,
+    },
+}
+
+/// The rules of the [`OrgPolicy`] a manifest breaks, refusing it.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+pub struct Breaches(pub Vec<Breach>);
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Field::Description => "description",
+            Field::License => "license",
+            Field::Keywords => "keywords",
+            Field::Kind => "trait",
+        })
+    }
+}
+
+impl OrgPolicy {
+    /// Parse a policy from the raw contents of its file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the content is not a valid policy document.
+    pub fn from_slice(content: &[u8]) -> Result<Self, toml_edit::de::Error> {
+        toml_edit::de::from_slice(content)
+    }
+
+    /// Whether the policy has no rules, so that no manifest can break it.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self == &OrgPolicy::default()
+    }
+
+    /// Whether the given SPDX license expression names a forbidden license.
+    #[must_use]
+    pub fn forbids(&self, license: &str) -> bool {
+        license
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .any(|term| {
+                self.forbidden_licenses
+                    .iter()
+                    .any(|l| l.eq_ignore_ascii_case(term))
+            })
+    }
+
+    /// Whether dependencies may be fetched from the given url.
+    #[must_use]
+    pub fn allows(&self, url: &Url) -> bool {
+        if self.allowed_hosts.is_empty() {
+            return true;
+        }
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        self.allowed_hosts
+            .iter()
+            .any(|allowed| match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                None => allowed.eq_ignore_ascii_case(host),
+            })
+    }
+
+    /// Check a manifest against the policy, given the versions of its Atom already published,
+    /// which a version bump rule may require it to follow.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error holding every rule the manifest breaks, if any.
+    pub fn check(
+        &self,
+        manifest: &Manifest,
+        published: Option<&BTreeSet<Version>>,
+    ) -> Result<(), Breaches> {
+        let atom = &manifest.atom;
+        let id = atom.id.to_string();
+        let mut breaches = Vec::new();
+
+        for &field in &self.required {
+            let declared = match field {
+                Field::Description => atom.description.is_some(),
+                Field::License => atom.license.is_some(),
+                Field::Keywords => !atom.keywords.is_empty(),
+                Field::Kind => atom.kind.is_some(),
+            };
+            if !declared {
+                breaches.push(Breach::MissingField {
+                    id: id.clone(),
+                    field,
+                });
+            }
+        }
+
+        if let Some(license) = atom.license.as_ref().filter(|l| self.forbids(l)) {
+            breaches.push(Breach::ForbiddenLicense {
+                id: id.clone(),
+                license: license.clone(),
+            });
+        }
+
+        for (dep, url) in manifest.deps.urls() {
+            if !self.allows(url) {
+                breaches.push(Breach::DisallowedHost {
+                    id: id.clone(),
+                    dep,
+                    url: url.to_string(),
+                });
+            }
+        }
+
+        let sequential = || published.map_or(true, |p| follows(&atom.version, p));
+        if self.bumps == Bumps::Sequential && !sequential() {
+            breaches.push(Breach::Unsequential {
+                id,
+                version: atom.version.clone(),
+            });
+        }
+
+        if breaches.is_empty() {
+            Ok(())
+        } else {
+            Err(Breaches(breaches))
+        }
+    }
+}
+
+/// Whether `version` is published, or directly follows one of the `published` versions.
+///
+/// Pre-releases and build metadata are disregarded, so that a release may be preceded by any
+/// number of pre-releases, and the first of them is held to the same rule as the release.
+fn follows(version: &Version, published: &BTreeSet<Version>) -> bool {
+    let release = |v: &Version| Version::new(v.major, v.minor, v.patch);
+    let released: BTreeSet<_> = published.iter().map(release).collect();
+    let version = release(version);
+
+    let Some(latest) = released.last() else {
+        return true;
+    };
+    if released.contains(&version) {
+        return true;
+    }
+
+    let line = released
+        .iter()
+        .rev()
+        .find(|v| v.major == version.major && v.minor == version.minor);
+    let major = released.iter().rev().find(|v| v.major == version.major);
+    match (line, major) {
+        (Some(v), _) => version.patch == v.patch + 1,
+        (None, Some(v)) => version.minor == v.minor + 1 && version.patch == 0,
+        (None, None) => {
+            version.major == latest.major + 1 && version.minor == 0 && version.patch == 0
+        },
+    }
+}
@@ -0,0 +1,194 @@
+use super::*;
+
+const POLICY: &str = r#"
+owners = ["admin@example.com"]
+
+[[protected]]
+id = "core"
+versions = ">=1"
+signers = ["release@example.com"]
+
+[[protected]]
+id = "frozen"
+
+[[protected]]
+id = "signed"
+versions = ">=1"
+keys = ["SHA256:key"]
+"#;
+
+#[test]
+fn protected_versions() -> Result<(), anyhow::Error> {
+    let policy = Policy::from_slice(POLICY.as_bytes())?;
+    let core = Id::try_from("core")?;
+    let frozen = Id::try_from("frozen")?;
+    let v0 = Version::new(0, 9, 0);
+    let v1 = Version::new(1, 2, 0);
+
+    let release = Some("release@example.com");
+    let other = Some("other@example.com");
+
+    assert!(policy.violation(&core, &v0, None).is_none());
+    assert!(policy.violation(&core, &v1, None).is_some());
+    assert!(policy.violation(&core, &v1, other).is_some());
+    assert!(policy.violation(&core, &v1, release).is_none());
+    assert!(policy.violation(&frozen, &v0, release).is_some());
+
+    // versions bound to keys may be published by anyone, as long as they are signed
+    let signed = Id::try_from("signed")?;
+    assert!(policy.violation(&signed, &v1, None).is_none());
+    assert_eq!(policy.signed(&signed, &v1)[0].keys, ["SHA256:key"]);
+    assert!(policy.signed(&signed, &v0).is_empty());
+    assert!(policy.signed(&core, &v1).is_empty());
+
+    assert!(policy.administers(Some("admin@example.com")));
+    assert!(!policy.administers(release));
+    assert!(!policy.administers(None));
+    Ok(())
+}
+
+const TEAMS: &str = r#"
+namespaces = "deny"
+
+[teams.web]
+members = ["alice@example.com"]
+prefixes = ["web-"]
+
+[teams.infra]
+members = ["bob@example.com"]
+prefixes = ["infra-", "web-"]
+
+[teams.ghost]
+prefixes = [""]
+"#;
+
+#[test]
+fn team_namespaces() -> Result<(), anyhow::Error> {
+    let policy = Policy::from_slice(TEAMS.as_bytes())?;
+    let web = Id::try_from("web-app")?;
+    let infra = Id::try_from("infra-dns")?;
+    let free = Id::try_from("free")?;
+
+    assert_eq!(policy.namespaces, Enforcement::Deny);
+    assert!(policy.trespass(&web, Some("alice@example.com")).is_none());
+    assert!(policy.trespass(&infra, Some("bob@example.com")).is_none());
+    assert_eq!(
+        policy.trespass(&infra, Some("alice@example.com")),
+        Some(vec!["ghost", "infra"])
+    );
+    assert!(policy.trespass(&free, None).is_some());
+
+    assert_eq!(
+        policy.check(),
+        vec![
+            Issue::NoMembers("ghost".into()),
+            Issue::EmptyPrefix("ghost".into()),
+            Issue::SharedPrefix {
+                prefix: "web-".into(),
+                first: "infra".into(),
+                second: "web".into(),
+            },
+        ]
+    );
+    Ok(())
+}
+
+const ORG: &str = r#"
+required = ["description", "license"]
+forbidden-licenses = ["AGPL-3.0-only"]
+allowed-hosts = ["github.com", "*.example.com"]
+bumps = "sequential"
+"#;
+
+#[test]
+fn org_policy() -> Result<(), anyhow::Error> {
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    use crate::Manifest;
+
+    let policy = OrgPolicy::from_slice(ORG.as_bytes())?;
+    assert!(OrgPolicy::from_slice(b"unknown = true").is_err());
+    assert!(policy.forbids("MIT OR (AGPL-3.0-only AND Apache-2.0)"));
+    assert!(!policy.forbids("AGPL-3.0-or-later"));
+
+    let published: BTreeSet<_> = ["1.1.0", "1.2.3", "2.0.0-rc.1"]
+        .into_iter()
+        .map(Version::parse)
+        .collect::<Result<_, _>>()?;
+    let manifest = |version: &str, license: &str, url: &str| {
+        Manifest::from_str(&format!(
+            r#"
+            [atom]
+            id = "foo"
+            version = "{version}"
+            description = "some atom"
+            license = "{license}"
+
+            [deps.srcs.bar]
+            url = "{url}"
+            "#
+        ))
+    };
+
+    for (version, allowed) in [
+        ("1.2.3", true),
+        ("1.2.4", true),
+        ("1.2.5", false),
+        ("1.1.1", true),
+        ("1.3.0", true),
+        ("1.3.1", false),
+        ("2.0.0", true),
+        ("3.0.0", true),
+        ("4.0.0", false),
+        ("1.0.0", false),
+    ] {
+        let manifest = manifest(version, "MIT", "https://github.com/owner/bar")?;
+        assert_eq!(
+            policy.check(&manifest, Some(&published)).is_ok(),
+            allowed,
+            "{version}"
+        );
+    }
+    let first = manifest("9.0.0", "MIT", "https://github.com/owner/bar")?;
+    assert!(policy.check(&first, None).is_ok());
+
+    let manifest = manifest("1.2.4", "AGPL-3.0-only", "https://example.com/bar")?;
+    let Err(Breaches(breaches)) = policy.check(&manifest, Some(&published)) else {
+        anyhow::bail!("the manifest breaks the policy");
+    };
+    assert_eq!(
+        breaches,
+        vec![
+            Breach::ForbiddenLicense {
+                id: "foo".into(),
+                license: "AGPL-3.0-only".into(),
+            },
+            Breach::DisallowedHost {
+                id: "foo".into(),
+                dep: "bar".into(),
+                url: "https://example.com/bar".into(),
+            },
+        ]
+    );
+    assert!(policy.allows(&"https://git.example.com/bar".parse()?));
+
+    let bare = Manifest::from_str("[atom]\nid = \"foo\"\nversion = \"0.1.0\"")?;
+    let Err(Breaches(breaches)) = policy.check(&bare, None) else {
+        anyhow::bail!("the manifest breaks the policy");
+    };
+    assert_eq!(
+        breaches,
+        vec![
+            Breach::MissingField {
+                id: "foo".into(),
+                field: Field::Description,
+            },
+            Breach::MissingField {
+                id: "foo".into(),
+                field: Field::License,
+            },
+        ]
+    );
+    Ok(())
+}
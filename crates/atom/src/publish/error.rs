@@ -10,6 +10,33 @@ pub enum PublishError {
     /// A transparent wrapper for a [`GitError`].
     #[error(transparent)]
     Git(#[from] git::Error),
+    #[cfg(feature = "s3")]
+    /// A transparent wrapper for a [`s3::Error`].
+    #[error(transparent)]
+    S3(#[from] s3::Error),
+}
+
+#[cfg(feature = "s3")]
+pub mod s3 {
+    //! # S3 Publishing Errors
+
+    /// An error representing a failure during publishing to an S3 Ekala store.
+    #[derive(thiserror::Error, Debug)]
+    pub enum Error {
+        /// A transparent wrapper for a [`super::git::Error`]
+        #[error(transparent)]
+        Git(#[from] super::git::Error),
+        /// A transparent wrapper for a [`crate::store::s3::Error`]
+        #[error(transparent)]
+        Store(#[from] crate::store::s3::Error),
+        /// A transparent wrapper for a [`crate::store::git::archive::Error`]
+        #[error(transparent)]
+        Archive(#[from] crate::store::git::archive::Error),
+        /// A ref of the version already holds another value, as it is published concurrently,
+        /// or an earlier publish of it, from another revision, was interrupted.
+        #[error("`{0}` already holds `{1}`, the version is being published from elsewhere")]
+        Conflict(String, String),
+    }
 }
 
 #[cfg(feature = "git")]
@@ -65,11 +92,44 @@ pub mod git {
             atom: Root,
         },
         /// The remote is not initialized as an Ekala store.
-        #[error("Remote is not initialized")]
-        NotInitialized,
+        #[error("Remote is not initialized: {0}")]
+        NotInitialized(crate::store::git::Error),
         /// The Atom manifest is invalid, and this Atom will be ignored.
         #[error("Ignoring invalid Atom manifest")]
         Invalid(#[source] crate::manifest::AtomError, Box<PathBuf>),
+        /// The Atom's content contains a path which is not valid UTF-8.
+        #[error("The Atom at `{}` contains a non UTF-8 path: `{}`", .0.display(), .1)]
+        NonUtf8Path(PathBuf, gix::bstr::BString),
+        /// A path dependency of the Atom does not point to an Atom in the published revision.
+        #[error("`{}` depends on `{}`, which is not an Atom in the given revision", .0.display(), .1.display())]
+        PathDependency(PathBuf, PathBuf),
+        /// A path dependency of the Atom points to an Atom with a different id.
+        #[error("`{}` depends on `{dep}` at `{}`, but found `{found}` there", .atom.display(), .path.display())]
+        MismatchedPathDependency {
+            /// The manifest of the depending Atom.
+            atom: PathBuf,
+            /// The id the dependency is declared under.
+            dep: String,
+            /// The path of the dependency, relative to the repository root.
+            path: PathBuf,
+            /// The id of the Atom found at the path.
+            found: String,
+        },
+        /// The Atom a path dependency points to does not satisfy its version requirement.
+        #[error("`{}` requires `{dep}` {req}, but the Atom at its path is version {version}", .atom.display())]
+        UnsatisfiedPathDependency {
+            /// The manifest of the depending Atom.
+            atom: PathBuf,
+            /// The id the dependency is declared under.
+            dep: String,
+            /// The version requirement of the dependency.
+            req: semver::VersionReq,
+            /// The version of the Atom found at the path.
+            version: semver::Version,
+        },
+        /// The path dependencies of the Atoms to publish form a cycle.
+        #[error("The path dependencies of these Atoms form a cycle: {0}")]
+        Cycle(String),
         /// The path given does not point to an Atom.
         #[error("The given path does not point to an Atom")]
         NotAnAtom(PathBuf),
@@ -82,39 +142,59 @@ pub mod git {
         /// A transparent wrapper for a [`crate::store::git::Error`]
         #[error(transparent)]
         StoreError(#[from] crate::store::git::Error),
+        /// A transparent wrapper for a [`crate::store::git::sign::Error`]
+        #[error(transparent)]
+        Sign(#[from] crate::store::git::sign::Error),
+        /// A transparent wrapper for a [`crate::manifest::LintError`]
+        #[error(transparent)]
+        LintError(#[from] crate::manifest::LintError),
         /// No Atoms found under the given directory.
         #[error("Failed to find any Atoms under the current directory")]
         NotFound,
-        /// Atoms with the same Unicode ID were found in the given revision.
+        /// Atoms with the same, or confusable, Unicode IDs were found in the given revision.
         #[error("Duplicate Atoms detected in the given revision, refusing to publish")]
-        Duplicates,
-    }
-
-    #[cfg(feature = "git")]
-    impl Error {
-        const INCONSISTENT_ROOT_SUGGESTION: &str =
-            "You may need to reinitalize the remote if the issue persists";
-
-        /// Warn the user about specific error conditions encountered during publishing.
-        pub fn warn(&self) {
-            match self {
-                Error::InconsistentRoot { remote, atom } => {
-                    tracing::warn!(
-                        message = %self,
-                        atom_root = %**atom,
-                        remote_root = %**remote,
-                        suggest = Error::INCONSISTENT_ROOT_SUGGESTION
-                    );
-                },
-                Error::Invalid(e, path) => {
-                    tracing::warn!(message = %self, path = %path.display(), message = format!("\n{}", e));
-                },
-                Error::NotAnAtom(path) => {
-                    tracing::warn!(message = %self, path = %path.display());
-                },
-                Error::Failed => (),
-                _ => tracing::warn!(message = %self),
-            }
-        }
+        Duplicates(crate::publish::Warnings),
+        /// Invalid Atom manifests were found while validating in strict mode.
+        #[error("Found {} invalid Atom manifest(s), refusing to publish in strict mode", .0.len())]
+        Strict(crate::publish::Warnings),
+        /// The Atom version is protected by the store's policy.
+        #[error("Atom `{id}` version {version} is protected by the store's policy")]
+        Protected {
+            /// The id of the protected Atom.
+            id: String,
+            /// The protected version.
+            version: semver::Version,
+        },
+        /// The Atom is owned by teams the publisher is not a member of.
+        #[error("Atom `{id}` is owned by {owners}, and the publisher is not a member")]
+        Trespass {
+            /// The id of the owned Atom.
+            id: String,
+            /// The names of the owning teams.
+            owners: String,
+        },
+        /// Another publisher published the same version of the Atom, with other content, while
+        /// this one was publishing it.
+        #[error(
+            "Someone else published `{id}@{version}` concurrently, check what was published with \
+             `eka verify`, and bump the version before publishing again"
+        )]
+        Concurrent {
+            /// The id of the Atom.
+            id: String,
+            /// The version published by both.
+            version: semver::Version,
+        },
+        /// The Atom would no longer be published as planned, e.g. as the revision changed.
+        #[error("`{id}@{version}` would no longer be published as planned, plan it again")]
+        StalePlan {
+            /// The id of the Atom.
+            id: String,
+            /// The planned version.
+            version: semver::Version,
+        },
+        /// The signature of a publish plan could not be verified.
+        #[error("The signature of the publish plan is invalid: {0}")]
+        InvalidPlanSignature(String),
     }
 }
@@ -0,0 +1,119 @@
+//! # Incremental Publishing
+//!
+//! Validating every manifest of a large repository, and re-creating the tree of every Atom
+//! published, is wasted work when little changed since the last publish. In incremental mode,
+//! the publisher keeps a [`Cache`] at [`CACHE_FILE`], under the git directory, recording the
+//! manifests found valid and the Atoms published, by the ids of the objects they were made of,
+//! so that both are short-circuited for as long as those objects are unchanged.
+//!
+//! The entries only hold under the settings they were recorded with, i.e. the lints and kinds
+//! manifests are checked against, and the workspace manifest and organization policy of the
+//! published revision, so the cache is discarded as soon as any of them change.
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use gix::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::id::Id;
+
+/// The file the cache is kept in, relative to the git directory.
+pub(super) const CACHE_FILE: &str = "ekala/cache";
+
+/// What was validated and published incrementally, by the objects it was made of.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub(super) struct Cache {
+    /// The fingerprint of the settings the entries were recorded with.
+    #[serde(default)]
+    settings: String,
+    /// The ids of the Atoms of the manifests found valid, by the id of the manifest.
+    #[serde(default)]
+    valid: BTreeMap<String, String>,
+    /// The Atoms published, by the id of their source.
+    #[serde(default)]
+    published: BTreeMap<String, Published>,
+}
+
+/// An Atom published, as recorded in the [`Cache`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(super) struct Published {
+    /// The id of the Atom.
+    pub(super) id: String,
+    /// The full name of the ref the Atom's commit was published under.
+    pub(super) name: String,
+    /// The id of the Atom's commit.
+    pub(super) commit: String,
+    /// The ids of the manifests of the Atoms depended on by path, by their path, as their
+    /// versions end up in the published spec.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(super) deps: BTreeMap<String, String>,
+}
+
+impl Cache {
+    /// Load the cache of the repository at `git_dir`, starting afresh if there is none yet, it
+    /// is unreadable, or was recorded with other `settings`.
+    pub(super) fn load(git_dir: &Path, settings: String) -> Self {
+        let cached = match std::fs::read_to_string(git_dir.join(CACHE_FILE)) {
+            Ok(content) => toml_edit::de::from_str::<Cache>(&content)
+                .inspect_err(|e| tracing::warn!(%e, "Discarding the unreadable publish cache"))
+                .ok(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                tracing::warn!(%e, "Could not read the publish cache");
+                None
+            },
+        };
+        match cached {
+            Some(cache) if cache.settings == settings => cache,
+            cached => {
+                if cached.is_some() {
+                    tracing::debug!("The publish settings changed, discarding the cache");
+                }
+                Cache {
+                    settings,
+                    ..Cache::default()
+                }
+            },
+        }
+    }
+
+    /// Persist the cache of the repository at `git_dir`, replacing the previous one at once, so
+    /// that it is never read half written.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the cache cannot be written.
+    pub(super) fn save(&self, git_dir: &Path) -> io::Result<()> {
+        let path = git_dir.join(CACHE_FILE);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = toml_edit::ser::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// The id of the Atom of the manifest `manifest`, if it was found valid before.
+    pub(super) fn valid(&self, manifest: ObjectId) -> Option<Id> {
+        let id = self.valid.get(&manifest.to_string())?;
+        Id::try_from(id.as_str()).ok()
+    }
+
+    /// Record the manifest `manifest`, of the Atom `id`, as valid.
+    pub(super) fn record_valid(&mut self, manifest: ObjectId, id: &Id) {
+        self.valid.insert(manifest.to_string(), id.to_string());
+    }
+
+    /// The Atom published from the source `source`, if there is one.
+    pub(super) fn published(&self, source: ObjectId) -> Option<&Published> {
+        self.published.get(&source.to_string())
+    }
+
+    /// Record the Atom published from the source `source`.
+    pub(super) fn record_published(&mut self, source: ObjectId, published: Published) {
+        self.published.insert(source.to_string(), published);
+    }
+}
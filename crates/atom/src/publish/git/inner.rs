@@ -1,35 +1,69 @@
-use std::io::{self, Read};
-use std::os::unix::ffi::OsStrExt;
+use std::borrow::Cow;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use gix::ObjectId;
 use gix::actor::Signature;
 use gix::diff::object::Commit as AtomCommit;
 use gix::object::tree::Entry;
 use gix::objs::WriteTo;
 use gix::objs::tree::Entry as AtomEntry;
 use gix::worktree::object::Tree as AtomTree;
-use gix::{ObjectId, Reference};
 
-use super::{AtomContext, AtomRef, GitContext, GitResult, RefKind};
+use super::{AtomContext, AtomRef, GitContext, GitResult, Prepared, RefKind};
 use crate::core::AtomPaths;
+use crate::manifest::AtomError;
 use crate::publish::error::git::Error;
 use crate::publish::{ATOM, ATOM_FORMAT_VERSION, ATOM_MANIFEST, ATOM_ORIGIN, EMPTY_SIG};
-use crate::store::git;
+use crate::store::git::exclude::Rewritten;
+use crate::store::git::transaction::RefTransaction;
+use crate::store::git::{self, Push};
 use crate::{Atom, AtomId, Manifest};
 impl<'a> GitContext<'a> {
     /// Method to verify the manifest of an entry
     pub(super) fn verify_manifest(&self, obj: &Object, path: &Path) -> GitResult<Atom> {
-        let content = read_blob(obj, |reader| {
-            let mut content = String::new();
-            reader.read_to_string(&mut content)?;
-            Ok(content)
-        })?;
+        let content = std::str::from_utf8(&obj.data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let invalid = |e: AtomError| Error::Invalid(e, Box::new(path.into()));
+        let content = &self.inherit(content, path).map_err(invalid)?;
+        let atom = Manifest::get_atom(content).map_err(invalid)?;
+        self.linter.check(&atom).map_err(|e| invalid(e.into()))?;
+
+        // only an Atom declaring a kind needs its full manifest, for the hooks of its kind,
+        // unless the organization's policy has rules of its own
+        if atom.kind.is_some() || !self.org_policy.is_empty() {
+            let manifest = Manifest::from_str(content).map_err(|e| invalid(e.into()))?;
+            if atom.kind.is_some() {
+                self.kinds
+                    .validate(&manifest)
+                    .map_err(|e| invalid(e.into()))?;
+            }
+            self.org_policy
+                .check(&manifest, self.published.get(&atom.id))
+                .map_err(|e| invalid(e.into()))?;
+        }
+
+        Ok(atom)
+    }
 
-        Manifest::get_atom(&content).map_err(|e| Error::Invalid(e, Box::new(path.into())))
+    /// Write each key the manifest `content` at `path` inherits from the workspace in its
+    /// place, borrowing the content as is if it inherits none.
+    fn inherit<'c>(&self, content: &'c str, path: &Path) -> Result<Cow<'c, str>, AtomError> {
+        // a manifest not mentioning the workspace at all need not be parsed to be sure
+        if !content.contains("workspace") {
+            return Ok(Cow::Borrowed(content));
+        }
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Ok(match self.workspace.inherit(content, dir)? {
+            Some(inherited) => Cow::Owned(inherited),
+            None => Cow::Borrowed(content),
+        })
     }
 
     /// Compute the [`ObjectId`] of the given proto-object in memory
-    fn compute_hash(&self, obj: &dyn WriteTo) -> GitResult<ObjectId> {
+    pub(super) fn compute_hash(&self, obj: &dyn WriteTo) -> GitResult<ObjectId> {
         use gix::objs;
 
         let mut buf = Vec::with_capacity(obj.size() as usize);
@@ -41,6 +75,17 @@ impl<'a> GitContext<'a> {
         Ok(oid)
     }
 
+    /// Write the trees of an Atom's content, as rewritten by the paths its manifest excludes
+    /// and includes.
+    pub(super) fn write_content(&self, content: Option<&Rewritten>) -> GitResult<()> {
+        if let Some(content) = content {
+            for tree in content.trees.iter().chain([&content.tree]) {
+                self.write_object(tree)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Helper function to write an object to the repository
     fn write_object(&self, obj: impl WriteTo) -> GitResult<gix::ObjectId> {
         Ok(self.repo.write_object(obj).map(gix::Id::detach)?)
@@ -54,7 +99,8 @@ impl<'a> GitContext<'a> {
     /// [`gix::object::tree::Tree::lookup_entry`] fails.
     pub fn tree_search(&self, path: &Path) -> GitResult<Option<Entry<'a>>> {
         let mut buf = self.buf.borrow_mut();
-        let search = path.components().map(|c| c.as_os_str().as_bytes());
+        let path = git::try_to_tree_path(path)?;
+        let search = path.split_str("/").filter(|c| !c.is_empty());
         Ok(self.tree.clone().lookup_entry(search, &mut buf)?)
     }
 
@@ -80,7 +126,14 @@ impl<'a> GitContext<'a> {
             .tree_search(paths.content())?
             .and_then(|e| e.mode().is_tree().then_some(e));
 
-        self.verify_manifest(&entry.object()?, paths.spec())
+        if let Some(content) = &content {
+            if let Some(bad) = git::find_non_utf8_path(self.repo, content.object_id())? {
+                return Err(Error::NonUtf8Path(path.into(), bad));
+            }
+        }
+
+        let manifest = entry.object()?;
+        self.verify_manifest(&manifest, paths.spec())
             .and_then(|spec| {
                 let id = AtomId::compute(&self.commit, spec.id.clone())?;
                 if self.root != *id.root() {
@@ -89,15 +142,178 @@ impl<'a> GitContext<'a> {
                         atom: *id.root(),
                     });
                 };
+                let rewritten = self.rewrite_manifest(&manifest.data, paths.spec())?;
+                let dir = git::to_tree_path(path.parent().unwrap_or(Path::new("")));
+                let source = content.as_ref().map(Entry::object_id);
+                let content_tree = git::include::rewrite(
+                    &self.repo.objects,
+                    self.repo.object_hash(),
+                    &self.tree.id,
+                    &dir.to_str_lossy(),
+                    source.as_deref(),
+                    &spec,
+                )?;
                 let entries = match (lock, content) {
                     (None, None) => smallvec![entry],
                     (None, Some(content)) => smallvec![entry, content],
                     (Some(lock), None) => smallvec![entry, lock],
                     (Some(lock), Some(content)) => smallvec![entry, content, lock],
                 };
-                Ok((FoundAtom { spec, id, entries }, paths))
+                let found = FoundAtom {
+                    spec,
+                    id,
+                    entries,
+                    rewritten,
+                    content: content_tree,
+                };
+                Ok((found, paths))
             })
     }
+
+    /// Write the keys the manifest at `path` inherits from the workspace in their place, and
+    /// rewrite its path dependencies into version requirements, returning the id and content of
+    /// the manifest to publish instead, if it differs.
+    fn rewrite_manifest(&self, data: &[u8], path: &Path) -> GitResult<Option<(ObjectId, Vec<u8>)>> {
+        let Ok(content) = std::str::from_utf8(data) else {
+            return Ok(None);
+        };
+        let inherited = self
+            .inherit(content, path)
+            .map_err(|e| Error::Invalid(e, Box::new(path.into())))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let rewritten = crate::manifest::rewrite_path_deps(&inherited, dir, |id, dep, req| {
+            let atom = self
+                .atom_at(dep)
+                .ok_or_else(|| Error::PathDependency(path.into(), dep.into()))?;
+            if *atom.id != *id {
+                return Err(Error::MismatchedPathDependency {
+                    atom: path.into(),
+                    dep: id.into(),
+                    path: dep.into(),
+                    found: atom.id.to_string(),
+                });
+            }
+            if let Some(req) = req.filter(|req| !req.matches(&atom.version)) {
+                return Err(Error::UnsatisfiedPathDependency {
+                    atom: path.into(),
+                    dep: id.into(),
+                    req: req.clone(),
+                    version: atom.version,
+                });
+            }
+            Ok(atom.version)
+        })?;
+
+        let rewritten = match (rewritten, inherited) {
+            (Some(content), _) | (None, Cow::Owned(content)) => content,
+            (None, Cow::Borrowed(_)) => return Ok(None),
+        };
+        let data = rewritten.into_bytes();
+        let id = gix::objs::compute_hash(self.repo.object_hash(), gix::object::Kind::Blob, &data);
+        Ok(Some((id, data)))
+    }
+
+    /// The id of what the source of the Atom at `path` is made of, i.e. its manifest, content,
+    /// lock and the paths it includes, by which it is cached, if publishing incrementally.
+    pub(super) fn source(&self, path: &Path) -> GitResult<Option<ObjectId>> {
+        if self.cache.is_none() {
+            return Ok(None);
+        }
+        let paths = AtomPaths::new(path);
+        let mut source = String::new();
+        for (part, path) in [
+            ("spec", paths.spec()),
+            ("content", paths.content()),
+            ("lock", paths.lock()),
+        ] {
+            if let Some(entry) = self.tree_search(path)? {
+                let (mode, id) = (entry.mode(), entry.object_id());
+                source.push_str(&format!("{part} {mode:?} {id}\n"));
+            }
+        }
+        // the paths the Atom includes from outside of its directory are part of its source, too
+        if let Some(spec) = self.tree_search(paths.spec())? {
+            let dir = git::to_tree_path(path.parent().unwrap_or(Path::new("")));
+            for include in includes(&spec.object()?.data) {
+                let Some(resolved) = git::include::resolve(&dir.to_str_lossy(), &include) else {
+                    continue;
+                };
+                if let Some(entry) = self.tree_search(Path::new(&resolved))? {
+                    let (mode, id) = (entry.mode(), entry.object_id());
+                    source.push_str(&format!("include {resolved} {mode:?} {id}\n"));
+                }
+            }
+        }
+        let kind = gix::object::Kind::Blob;
+        let id = gix::objs::compute_hash(self.repo.object_hash(), kind, source.as_bytes());
+        Ok(Some(id))
+    }
+
+    /// The id of the Atom published from `source` before, if it still is, and the manifests
+    /// of the Atoms it depends on by path are unchanged, so that it is published already.
+    pub(super) fn unchanged(&self, source: ObjectId) -> Option<Id> {
+        let cache = self.cache.as_ref()?.borrow();
+        let published = cache.published(source)?;
+        let commit = ObjectId::from_hex(published.commit.as_bytes()).ok()?;
+        let current = self
+            .repo
+            .try_find_reference(published.name.as_str())
+            .ok()??;
+        if current.target().try_id() != Some(commit.as_ref()) {
+            return None;
+        }
+        for (path, id) in &published.deps {
+            let entry = self.tree_search(Path::new(path)).ok()??;
+            if entry.object_id().to_string() != *id {
+                return None;
+            }
+        }
+        tracing::debug!(id = %published.id, "Unchanged since last published");
+        Id::try_from(published.id.as_str()).ok()
+    }
+
+    /// The Atom at `path`, relative to the repository root, if there is one.
+    fn atom_at(&self, path: &Path) -> Option<Atom> {
+        let spec = self.path_dep(path)?;
+        let entry = self.tree_search(&spec).ok()??;
+        let object = entry.object().ok()?;
+        let content = std::str::from_utf8(&object.data).ok()?;
+        Manifest::get_atom(&self.inherit(content, &spec).ok()?).ok()
+    }
+
+    /// Normalize the path of a dependency on the Atom at `path`, relative to the repository
+    /// root, to the path of its manifest.
+    pub(super) fn path_dep(&self, path: &Path) -> Option<PathBuf> {
+        use crate::store::NormalizeStorePath;
+
+        let path = self.repo.normalize_lexical(path).ok()?;
+        Some(AtomPaths::new(path).spec().to_path_buf())
+    }
+
+    /// The manifest at `spec`, relative to the repository root, with the keys it inherits from
+    /// its workspace in their place, if there is a valid one.
+    pub(super) fn manifest_at(&self, spec: &Path) -> GitResult<Option<Manifest>> {
+        let Some(entry) = self.tree_search(spec)? else {
+            return Ok(None);
+        };
+        let object = entry.object()?;
+        Ok(std::str::from_utf8(&object.data)
+            .ok()
+            .and_then(|content| self.inherit(content, spec).ok())
+            .and_then(|content| Manifest::from_str(&content).ok()))
+    }
+
+    /// The manifests of the Atoms the Atom with the manifest at `spec` depends on by path.
+    pub(super) fn path_deps(&self, spec: &Path) -> GitResult<Vec<PathBuf>> {
+        let manifest = self.manifest_at(spec)?;
+        let dir = spec.parent().unwrap_or(Path::new(""));
+
+        Ok(manifest
+            .into_iter()
+            .flat_map(|manifest| manifest.deps.atoms.into_values())
+            .filter_map(|dep| self.path_dep(&dir.join(dep.path?)))
+            .collect())
+    }
 }
 
 use semver::Version;
@@ -116,15 +332,15 @@ use std::fmt;
 
 impl<'a> fmt::Display for AtomRef<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let version = git::encode_version(self.version);
         match self.kind {
-            RefKind::Content => write!(f, "{}/{}/{}", self.prefix, self.version, ATOM),
-            RefKind::Origin => write!(f, "{}/{}/{}", self.prefix, self.version, ATOM_ORIGIN),
-            RefKind::Spec => write!(f, "{}/{}/{}", self.prefix, self.version, ATOM_MANIFEST),
+            RefKind::Content => write!(f, "{}/{}/{}", self.prefix, version, ATOM),
+            RefKind::Origin => write!(f, "{}/{}/{}", self.prefix, version, ATOM_ORIGIN),
+            RefKind::Spec => write!(f, "{}/{}/{}", self.prefix, version, ATOM_MANIFEST),
         }
     }
 }
 
-use super::AtomEntries;
 use crate::publish::MaybeSkipped;
 
 impl<'a> AtomContext<'a> {
@@ -132,6 +348,15 @@ impl<'a> AtomContext<'a> {
         AtomRef::new(kind, &self.ref_prefix, &self.atom.spec.version)
     }
 
+    /// Check that every ref of the Atom is a name the store will accept, before any of its
+    /// objects are written.
+    pub(super) fn validate_refs(&self) -> GitResult<()> {
+        for kind in [RefKind::Content, RefKind::Spec, RefKind::Origin] {
+            git::validate_ref_name(&format!("refs/{}", self.refs(kind)))?;
+        }
+        Ok(())
+    }
+
     fn ref_exists(&self, tree: &AtomTree, atom_ref: &AtomRef) -> bool {
         let id = self.git.compute_hash(tree);
         if let Ok(id) = id {
@@ -142,120 +367,270 @@ impl<'a> AtomContext<'a> {
         }
     }
 
-    /// Method to write the atom tree object
-    pub(super) fn write_atom_tree(
-        &self,
-        entries: &AtomEntries,
-    ) -> GitResult<MaybeSkipped<AtomTreeId>> {
-        use {Err as Skipped, Ok as Wrote};
+    /// The entries of the atom tree, with the manifest and content as they are published
+    fn entries(&self) -> Vec<AtomEntry> {
+        let mut entries: Vec<_> = self.atom.entries.iter().map(atom_entry).collect();
 
-        let mut entries: Vec<_> = entries.iter().map(atom_entry).collect();
+        // the manifest is always the first entry
+        if let (Some(manifest), Some((id, _))) = (entries.first_mut(), &self.atom.rewritten) {
+            manifest.oid = *id;
+        }
+
+        if let Some(content) = &self.atom.content {
+            match entries.iter_mut().find(|e| e.mode.is_tree()) {
+                Some(entry) => entry.oid = content.id,
+                // an Atom without content of its own is published with what it includes
+                None => {
+                    let name = self.paths.content().file_name().unwrap_or_default();
+                    entries.push(AtomEntry {
+                        mode: gix::objs::tree::EntryKind::Tree.into(),
+                        filename: git::to_tree_path(Path::new(name)).into_owned(),
+                        oid: content.id,
+                    });
+                },
+            }
+        }
+
+        entries
+    }
+
+    /// Construct the in-memory atom tree object from its entries
+    fn atom_tree(&self) -> AtomTree {
+        let mut entries = self.entries();
 
         //git expects tree entries to be sorted
         if entries.len() > 1 {
             entries.sort_unstable();
         }
 
-        let tree = AtomTree { entries };
+        AtomTree { entries }
+    }
+
+    /// Whether this exact atom has already been published locally, without writing anything
+    pub(super) fn exists(&self) -> bool {
+        let tree = self.atom_tree();
+        self.ref_exists(&tree, &self.refs(RefKind::Content))
+    }
+
+    /// Method to write the atom tree object
+    pub(super) fn write_atom_tree(&self) -> GitResult<MaybeSkipped<AtomTreeId>> {
+        use {Err as Skipped, Ok as Wrote};
+
+        let tree = self.atom_tree();
 
         if self.ref_exists(&tree, &self.refs(RefKind::Content)) {
             return Ok(Skipped(self.atom.spec.id.clone()));
         }
 
+        if let Some((_, manifest)) = &self.atom.rewritten {
+            self.git.repo.write_blob(manifest)?;
+        }
+        self.git.write_content(self.atom.content.as_ref())?;
         let id = self.git.write_object(tree)?;
         Ok(Wrote(AtomTreeId(id)))
     }
 
     /// Method to write atom commits
     pub(super) fn write_atom_commit(&self, AtomTreeId(id): AtomTreeId) -> GitResult<CommittedAtom> {
-        let sig = Signature {
-            email: EMPTY_SIG.into(),
-            name: EMPTY_SIG.into(),
-            time: gix::date::Time {
-                seconds: 0,
-                offset: 0,
-                sign: gix::date::time::Sign::Plus,
-            },
-        };
-        let commit = AtomCommit {
-            tree: id,
-            parents: smallvec::smallvec![],
-            author: sig.clone(),
-            committer: sig,
-            encoding: None,
-            message: format!("{}: {}", self.atom.spec.id, self.atom.spec.version).into(),
-            extra_headers: [
-                (ATOM_ORIGIN.into(), self.git.commit.id.to_string().into()),
-                (
-                    "path".into(),
-                    self.paths
-                        .content()
-                        .parent()
-                        .unwrap_or(Path::new("/"))
-                        .to_string_lossy()
-                        .to_string()
-                        .into(),
-                ),
-                ("format".into(), ATOM_FORMAT_VERSION.into()),
-            ]
-            .into(),
-        };
+        let mut commit = self.atom_commit(id);
+        if let Some(signer) = &self.git.signing {
+            signer.sign_commit(&mut commit, self.git.repo.object_hash())?;
+        }
         let id = self.git.write_object(commit.clone())?;
         Ok(CommittedAtom { commit, id })
     }
+
+    /// Method to write the tree of the atom's manifest and lock, without its content
+    pub(super) fn write_spec_tree(&self) -> GitResult<ObjectId> {
+        // filter out the content tree
+        let mut entries: Vec<_> = self
+            .entries()
+            .into_iter()
+            .filter(|e| e.mode.is_blob())
+            .collect();
+
+        if entries.len() > 1 {
+            entries.sort_unstable();
+        }
+
+        self.git.write_object(AtomTree { entries })
+    }
+
+    /// Re-create the atom tree and the id of the atom commit, exactly as they would be
+    /// published, without writing either
+    pub(super) fn recreate(&self) -> GitResult<(AtomTree, ObjectId)> {
+        let tree = self.atom_tree();
+        let commit = self.atom_commit(self.git.compute_hash(&tree)?);
+        Ok((tree, self.git.compute_hash(&commit)?))
+    }
+
+    /// Hold on to what is left to publish the atom, once its objects are written, apart from
+    /// the repository they were written with
+    pub(super) fn prepared(
+        self,
+        source: Option<ObjectId>,
+        written: Option<(CommittedAtom, ObjectId)>,
+    ) -> Prepared {
+        Prepared {
+            spec: self.atom.spec,
+            id: self.atom.id,
+            paths: self.paths,
+            ref_prefix: self.ref_prefix,
+            source,
+            written,
+        }
+    }
+
+    /// Construct the atom commit for the given atom tree
+    fn atom_commit(&self, tree: ObjectId) -> AtomCommit {
+        let path = self.paths.content().parent().unwrap_or(Path::new("/"));
+        atom_commit(&self.atom.spec, tree, self.git.commit.id, path)
+    }
 }
 
-/// Method to write a single reference to the repository
-fn write_ref<'a>(
-    atom: &'a AtomContext,
+/// Construct the reproducible commit of an Atom, whose content is the given tree, published
+/// from the `origin` commit with its content under `path`.
+pub(crate) fn atom_commit(
+    atom: &Atom,
+    tree: ObjectId,
+    origin: ObjectId,
+    path: &Path,
+) -> AtomCommit {
+    let sig = Signature {
+        email: EMPTY_SIG.into(),
+        name: EMPTY_SIG.into(),
+        time: gix::date::Time {
+            seconds: 0,
+            offset: 0,
+            sign: gix::date::time::Sign::Plus,
+        },
+    };
+    AtomCommit {
+        tree,
+        parents: smallvec::smallvec![],
+        author: sig.clone(),
+        committer: sig,
+        encoding: None,
+        message: format!("{}: {}", atom.id, atom.version).into(),
+        extra_headers: [
+            (ATOM_ORIGIN.into(), origin.to_string().into()),
+            ("path".into(), git::to_tree_path(path).into_owned()),
+            ("format".into(), ATOM_FORMAT_VERSION.into()),
+        ]
+        .into(),
+    }
+}
+
+/// Add the creation of a single reference of the atom to the transaction
+fn write_ref(
+    tx: &mut RefTransaction,
+    atom: &Prepared,
     id: ObjectId,
     atom_ref: AtomRef,
-) -> GitResult<Reference<'a>> {
-    use gix::refs::transaction::PreviousValue;
-
+) -> GitResult<()> {
     tracing::debug!("writing atom ref: {}", atom_ref);
 
-    let AtomContext { atom, git, .. } = atom;
+    let Prepared { spec, .. } = atom;
 
-    Ok(git.repo.reference(
-        format!("refs/{atom_ref}"),
+    tx.create(
+        &format!("refs/{atom_ref}"),
         id,
-        PreviousValue::MustNotExist,
-        format!(
-            "publish: {}: {}-{}",
-            atom.spec.id, atom.spec.version, atom_ref
-        ),
-    )?)
+        format!("publish: {}: {}-{}", spec.id, spec.version, atom_ref),
+    )?;
+    Ok(())
 }
-use super::{CommittedAtom, FoundAtom};
 
-impl<'a> CommittedAtom {
-    /// Method to write references for the committed atom
-    pub(super) fn write_refs(&'a self, atom: &'a AtomContext) -> GitResult<AtomReferences> {
-        let Self { id, .. } = self;
+impl Prepared {
+    fn refs(&self, kind: RefKind) -> AtomRef {
+        AtomRef::new(kind, &self.ref_prefix, &self.spec.version)
+    }
 
-        // filter out the content tree
-        let mut entries: Vec<_> = atom
-            .atom
-            .entries
-            .clone()
+    /// Record the Atom as published from its source in the cache, once its refs were written,
+    /// along with the manifests of the Atoms it depends on by path.
+    pub(super) fn remember(&self, git: &GitContext) {
+        let (Some(cache), Some(source)) = (&git.cache, self.source) else {
+            return;
+        };
+        let name = format!("refs/{}", self.refs(RefKind::Content));
+        let Ok(Some(r)) = git.repo.try_find_reference(name.as_str()) else {
+            return;
+        };
+        let Some(commit) = r.target().try_id().map(ToString::to_string) else {
+            return;
+        };
+        let deps = git
+            .path_deps(self.paths.spec())
+            .unwrap_or_default()
             .into_iter()
-            .filter_map(|e| e.mode().is_blob().then_some(atom_entry(&e)))
+            .filter_map(|path| {
+                let id = git.tree_search(&path).ok()??.object_id().to_string();
+                Some((path.to_string_lossy().into_owned(), id))
+            })
             .collect();
+        let published = super::cache::Published {
+            id: self.spec.id.to_string(),
+            name,
+            commit,
+            deps,
+        };
+        cache.borrow_mut().record_published(source, published);
+    }
+}
 
-        if entries.len() > 1 {
-            entries.sort_unstable();
-        }
+use super::{CommittedAtom, FoundAtom};
 
-        let spec_tree = AtomTree { entries };
-        let spec = atom.git.repo.write_object(spec_tree)?.detach();
-        let src = atom.git.commit.id;
+impl<'a> CommittedAtom {
+    /// Method to write references for the committed atom, and the tree of its manifest and
+    /// lock, `spec`, all at once, so that none are left behind if any of them cannot be written
+    ///
+    /// Should someone else have written the refs of the same version concurrently, the Atom is
+    /// skipped if they point to the very same Atom, and an error is returned otherwise.
+    pub(super) fn write_refs(
+        &'a self,
+        atom: &'a Prepared,
+        spec: ObjectId,
+        git: &'a GitContext,
+    ) -> GitResult<MaybeSkipped<AtomReferences<'a>>> {
+        use {Err as Skipped, Ok as Wrote};
 
-        Ok(AtomReferences {
-            spec: write_ref(atom, spec, atom.refs(RefKind::Spec))?,
-            content: write_ref(atom, *id, atom.refs(RefKind::Content))?,
-            origin: write_ref(atom, src, atom.refs(RefKind::Origin))?,
-        })
+        let Self { id, .. } = self;
+        let src = git.commit.id;
+
+        let mut tx = RefTransaction::new(git.repo);
+        write_ref(&mut tx, atom, spec, atom.refs(RefKind::Spec))?;
+        write_ref(&mut tx, atom, *id, atom.refs(RefKind::Content))?;
+        write_ref(&mut tx, atom, src, atom.refs(RefKind::Origin))?;
+
+        let written = match tx.commit() {
+            Ok(written) => written,
+            Err(e) if e.is_contended() => {
+                let spec = &atom.spec;
+                let content = format!("refs/{}", atom.refs(RefKind::Content));
+                return match git.repo.try_find_reference(content.as_str()) {
+                    Ok(Some(r)) if r.target().try_id() == Some(id.as_ref()) => {
+                        tracing::info!(
+                            id = %spec.id,
+                            version = %spec.version,
+                            "Already published identically, concurrently"
+                        );
+                        Ok(Skipped(spec.id.clone()))
+                    },
+                    _ => Err(Error::Concurrent {
+                        id: spec.id.to_string(),
+                        version: spec.version.clone(),
+                    }),
+                };
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        let [spec, content, origin] = <[_; 3]>::try_from(written)
+            .expect("a reference is written for each edit of the transaction");
+        Ok(Wrote(AtomReferences {
+            spec,
+            content,
+            origin,
+        }))
     }
 }
 
@@ -266,17 +641,57 @@ impl<'a> AtomReferences<'a> {
     ///
     /// Currently the implementation just calls the `git` binary.
     /// Once `gix` is further along we can use it directly.
-    pub(super) fn push(self, atom: &'a AtomContext) -> GitContent {
-        let remote = atom.git.remote_str.to_owned();
-        let mut tasks = atom.git.push_tasks.borrow_mut();
+    pub(super) fn push(self, atom: &Prepared, git: &GitContext) -> GitContent {
+        let remote = git.remote_str.to_owned();
+        let git_dir = git.repo.git_dir().to_string_lossy().to_string();
+        let pack = pack_config(&git.pack);
+        let mut tasks = git.push_tasks.borrow_mut();
 
         for r in [&self.content, &self.spec, &self.origin] {
+            let target = r.target().try_id().map(ToOwned::to_owned);
             let r = r.name().as_bstr().to_string();
-            let remote = remote.clone();
+            // what the ref pointed to on the remote when the publish was planned, if at all
+            let planned = git.snapshot.as_ref().map(|snapshot| snapshot.get(&r));
+            let mut push = pack
+                .iter()
+                .fold(Push::refs([r.as_str()]), |push, (key, value)| {
+                    push.config(key, value)
+                });
+            if planned.is_some() {
+                // only create the ref if the remote still lacks it
+                push = push.lease(&r, None);
+            }
+            let (git_dir, remote) = (git_dir.clone(), remote.clone());
+            let spec = &atom.spec;
+            let (id, version) = (spec.id.to_string(), spec.version.clone());
             let task = async move {
-                let result = git::run_git_command(&["push", &remote, format!("{r}:{r}").as_str()])?;
-
-                Ok(result)
+                #[cfg(feature = "fault-injection")]
+                if git::fault::trip(Path::new(&git_dir), git::fault::Fault::RejectPush) {
+                    return Err(git::fault::rejected(&r).into());
+                }
+                match planned {
+                    Some(Some(planned)) if Some(planned) == target => {
+                        tracing::info!(%id, %version, r#ref = r, "Already published identically");
+                        return Ok(Vec::new());
+                    },
+                    Some(Some(_)) => {
+                        if let Some(settled) = replan(&git_dir, &remote, &r, target, &id, &version)
+                        {
+                            return settled;
+                        }
+                    },
+                    Some(None) | None => {},
+                }
+                match push.run(&git_dir, &remote) {
+                    Ok(stderr) => Ok(git::parse_notices(&stderr)
+                        .into_iter()
+                        .map(|notice| Warning::Notice {
+                            id: id.clone(),
+                            notice,
+                        })
+                        .collect()),
+                    Err(e) => settle_rejection(e, &git_dir, &remote, &r, target, id, version),
+                }
             };
             tasks.spawn(task);
         }
@@ -291,14 +706,101 @@ impl<'a> AtomReferences<'a> {
     }
 }
 
+/// Settle the failed push of the ref `name`, which points to `target` locally, telling apart
+/// a remote which already has the very same ref, as someone else published the same Atom, from
+/// one where someone else published another Atom under the same version, concurrently.
+fn settle_rejection(
+    e: io::Error,
+    git_dir: &str,
+    remote: &str,
+    name: &str,
+    target: Option<ObjectId>,
+    id: String,
+    version: Version,
+) -> GitResult<Warnings> {
+    use git::Rejection;
+
+    match git::classify_rejection(&e) {
+        Some(Rejection::PermissionDenied) => {
+            Err(git::Error::PermissionDenied(remote.to_owned()).into())
+        },
+        Some(Rejection::Exists | Rejection::Locked) => {
+            match git::advertised(Path::new(git_dir), remote, name) {
+                Ok(found) if found.is_some() && found == target => {
+                    tracing::info!(%id, %version, r#ref = name, "Already published identically");
+                    Ok(Vec::new())
+                },
+                Ok(_) => Err(Error::Concurrent { id, version }),
+                Err(_) => Err(e.into()),
+            }
+        },
+        Some(Rejection::Declined) | None => Err(e.into()),
+    }
+}
+
+/// Plan the push of the ref `name`, which points to `target` locally, again against the
+/// remote as it is now, as it already had another ref of that name when the publish was
+/// planned. Returns `None` if the remote no longer has it, so that it is pushed after all.
+fn replan(
+    git_dir: &str,
+    remote: &str,
+    name: &str,
+    target: Option<ObjectId>,
+    id: &str,
+    version: &Version,
+) -> Option<GitResult<Warnings>> {
+    match git::advertised(Path::new(git_dir), remote, name) {
+        Ok(None) => None,
+        Ok(found) if found == target => {
+            tracing::info!(%id, %version, r#ref = name, "Already published identically");
+            Some(Ok(Vec::new()))
+        },
+        Ok(_) => Some(Err(Error::Concurrent {
+            id: id.to_owned(),
+            version: version.clone(),
+        })),
+        Err(e) => Some(Err(e.into())),
+    }
+}
+
+/// Translate the packing settings into git configuration overrides for a push.
+fn pack_config(pack: &config::PackConfig) -> Vec<(&'static str, String)> {
+    let config = [
+        ("pack.compression", pack.compression.map(|c| c.to_string())),
+        ("pack.window", pack.window.map(|w| w.to_string())),
+        ("pack.depth", pack.depth.map(|d| d.to_string())),
+        ("pack.allowPackReuse", pack.reuse.map(|r| r.to_string())),
+    ];
+
+    config
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+}
+
 use gix::Object;
-/// Helper function to read a blob from an object
-fn read_blob<F, R>(obj: &Object, mut f: F) -> GitResult<R>
-where
-    F: FnMut(&mut dyn Read) -> io::Result<R>,
-{
-    let mut reader = obj.data.as_slice();
-    Ok(f(&mut reader)?)
+
+/// The paths the manifest `data` includes, read without validating the rest of it.
+fn includes(data: &[u8]) -> Vec<String> {
+    let Some(doc) = std::str::from_utf8(data)
+        .ok()
+        .and_then(|content| toml_edit::ImDocument::parse(content).ok())
+    else {
+        return Vec::new();
+    };
+    let include = doc
+        .as_table()
+        .get("atom")
+        .and_then(|atom| atom.get("include"));
+    include
+        .and_then(toml_edit::Item::as_array)
+        .map(|paths| {
+            paths
+                .iter()
+                .filter_map(|p| p.as_str().map(ToOwned::to_owned))
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 /// Helper function to create an atom entry from found entries
@@ -323,3 +825,182 @@ impl CommittedAtom {
         &self.id
     }
 }
+
+use std::collections::hash_map::Entry as MapEntry;
+use std::collections::{HashMap, VecDeque};
+
+use gix::bstr::{BStr, BString, ByteSlice, ByteVec};
+use gix::objs::tree::EntryRef;
+use gix::traverse::tree::Visit;
+use gix::traverse::tree::visit::Action;
+
+use crate::id::Id;
+use crate::publish::{ValidAtoms, Warning, Warnings};
+/// A tree visitor which verifies Atom manifests as soon as they are encountered, so that only
+/// the valid Atoms are held in memory, rather than a record of every entry in the tree.
+pub(super) struct AtomVisitor<'a, 'c> {
+    git: &'c GitContext<'a>,
+    queue: VecDeque<BString>,
+    path: BString,
+    atoms: ValidAtoms,
+    /// The paths of the valid Atoms, case folded, if the filesystem ignores case.
+    folded: Option<HashMap<String, PathBuf>>,
+    /// The ids of the valid Atoms, by their skeleton.
+    skeletons: HashMap<String, Id>,
+    /// The Atoms skipped as invalid.
+    invalid: Warnings,
+    /// The Atoms which could not be told apart from one another.
+    duplicates: Warnings,
+}
+
+impl<'a, 'c> AtomVisitor<'a, 'c> {
+    pub(super) fn new(git: &'c GitContext<'a>) -> Self {
+        AtomVisitor {
+            git,
+            queue: VecDeque::new(),
+            path: BString::default(),
+            atoms: ValidAtoms::new(),
+            folded: git.ignore_case.then(HashMap::new),
+            skeletons: HashMap::new(),
+            invalid: Warnings::new(),
+            duplicates: Warnings::new(),
+        }
+    }
+
+    /// Consume the visitor, returning the valid Atoms and the warnings for those skipped as
+    /// invalid.
+    pub(super) fn finish(self) -> GitResult<(ValidAtoms, Warnings)> {
+        if !self.duplicates.is_empty() {
+            return Err(Error::Duplicates(self.duplicates));
+        }
+        Ok((self.atoms, self.invalid))
+    }
+
+    /// Whether the path differs from that of another Atom only by case, so that both could not
+    /// be checked out together on a case-insensitive filesystem.
+    fn collides(&mut self, path: &Path) -> bool {
+        let Some(folded) = &mut self.folded else {
+            return false;
+        };
+        match folded.entry(path.to_string_lossy().to_lowercase()) {
+            MapEntry::Occupied(other) => {
+                let collision = Warning::CaseCollision(other.get().clone(), path.to_path_buf());
+                self.duplicates.push(collision);
+                true
+            },
+            MapEntry::Vacant(slot) => {
+                slot.insert(path.to_path_buf());
+                false
+            },
+        }
+    }
+
+    /// Whether the id is indistinguishable to a human from that of another Atom, differing
+    /// from it only by case or by confusable characters.
+    fn confusable(&mut self, id: &Id, path: &Path) -> bool {
+        match self.skeletons.entry(id.skeleton()) {
+            MapEntry::Occupied(other) => {
+                self.duplicates.push(Warning::Confusable {
+                    fst_id: other.get().to_string(),
+                    fst: self.atoms[other.get()].clone(),
+                    snd_id: id.to_string(),
+                    snd: path.to_path_buf(),
+                });
+                true
+            },
+            MapEntry::Vacant(slot) => {
+                slot.insert(id.clone());
+                false
+            },
+        }
+    }
+
+    fn push_element(&mut self, name: &BStr) {
+        if !self.path.is_empty() {
+            self.path.push(b'/');
+        }
+        self.path.push_str(name);
+    }
+}
+
+impl Visit for AtomVisitor<'_, '_> {
+    fn pop_front_tracked_path_and_set_current(&mut self) {
+        self.path = self
+            .queue
+            .pop_front()
+            .expect("every call is matched with push_back_tracked_path_component");
+    }
+
+    fn push_back_tracked_path_component(&mut self, component: &BStr) {
+        self.push_element(component);
+        self.queue.push_back(self.path.clone());
+    }
+
+    fn push_path_component(&mut self, component: &BStr) {
+        self.push_element(component);
+    }
+
+    fn pop_path_component(&mut self) {
+        match self.path.rfind_byte(b'/') {
+            Some(pos) => self.path.truncate(pos),
+            None => self.path.clear(),
+        }
+    }
+
+    fn visit_tree(&mut self, _entry: &EntryRef<'_>) -> Action {
+        Action::Continue
+    }
+
+    fn visit_nontree(&mut self, entry: &EntryRef<'_>) -> Action {
+        if !entry.mode.is_blob() || !entry.filename.ends_with(crate::ATOM_EXT.as_bytes()) {
+            return Action::Continue;
+        }
+
+        if self.path.to_str().is_err() {
+            self.invalid
+                .push(Warning::NonUtf8Path(self.path.to_string()));
+            return Action::Continue;
+        }
+        let path = gix::path::from_bstr(self.path.as_bstr()).into_owned();
+        if self.collides(&path) {
+            return Action::Cancel;
+        }
+
+        let oid = entry.oid.to_owned();
+        let cached = self.git.cache.as_ref().and_then(|c| c.borrow().valid(oid));
+        let verified = match cached {
+            Some(id) => Ok(id),
+            None => {
+                let Ok(obj) = self.git.repo.find_object(oid) else {
+                    return Action::Continue;
+                };
+                self.git.verify_manifest(&obj, &path).map(|atom| {
+                    if let Some(cache) = &self.git.cache {
+                        cache.borrow_mut().record_valid(oid, &atom.id);
+                    }
+                    atom.id
+                })
+            },
+        };
+
+        match verified {
+            Ok(id) => {
+                if let Some(duplicate) = self.atoms.get(&id) {
+                    self.duplicates.push(Warning::DuplicateId {
+                        id: id.to_string(),
+                        fst: duplicate.clone(),
+                        snd: path,
+                    });
+                    return Action::Cancel;
+                }
+                if self.confusable(&id, &path) {
+                    return Action::Cancel;
+                }
+                self.atoms.insert(id, path);
+            },
+            Err(e) => self.invalid.push(Warning::Skipped(e)),
+        }
+
+        Action::Continue
+    }
+}
@@ -10,23 +10,37 @@
 //!
 //! A hexadecimal representation of the source commit is also stored in the reproducible
 //! Atom commit header, ensuring it is tied to its source in an unforgable manner.
+//!
+//! Atom commits may also be [signed](crate::store::git::sign), in which case they are
+//! reproducible but for their signature.
 #[cfg(test)]
-mod test;
+pub(crate) mod test;
 
+mod cache;
 mod inner;
+pub mod plan;
 
 use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use config::PackConfig;
 use gix::{Commit, ObjectId, Repository, Tree};
 use tokio::task::JoinSet;
 
+use self::cache::Cache;
 use super::error::git::Error;
-use super::{Content, PublishOutcome, Record};
+use super::{Content, MaybeSkipped, PublishOutcome, Record, Warning, Warnings};
 use crate::core::AtomPaths;
+use crate::id::Id;
+use crate::manifest::{Linter, Workspace};
+use crate::policy::{Bumps, OrgPolicy, Policy};
 use crate::store::NormalizeStorePath;
-use crate::store::git::Root;
-use crate::{Atom, AtomId};
+use crate::store::git::sign::Signer;
+use crate::store::git::{Root, Snapshot};
+use crate::{Atom, AtomGraph, AtomId, AtomNode, KindRegistry, MissingDep};
 
 type GitAtomId = AtomId<Root>;
 /// The Outcome of an Atom publish attempt to a Git store.
@@ -35,6 +49,8 @@ pub type GitOutcome = PublishOutcome<Root>;
 pub type GitResult<T> = Result<T, Error>;
 type GitRecord = Record<Root>;
 
+pub(crate) use inner::atom_commit;
+
 #[derive(Debug)]
 /// Holds the shared context needed for publishing Atoms.
 pub struct GitContext<'a> {
@@ -49,9 +65,73 @@ pub struct GitContext<'a> {
     /// The reported root commit according to the remote.
     root: Root,
     /// A [`JoinSet`] of push tasks to avoid blocking on them.
-    push_tasks: RefCell<JoinSet<Result<Vec<u8>, Error>>>,
+    push_tasks: RefCell<JoinSet<Result<Warnings, Error>>>,
     /// Path buf for efficient tree searches
     buf: RefCell<Vec<u8>>,
+    /// Whether invalid manifests found during validation are fatal.
+    strict: bool,
+    /// The directory relative paths are normalized from.
+    cwd: &'a Path,
+    /// The policy declared by the store.
+    policy: Policy,
+    /// Whether to publish protected Atom versions regardless of the policy.
+    allow_protected: bool,
+    /// The identity of the publisher, checked against the policy.
+    signer: Option<String>,
+    /// The key Atom commits are signed with, if they are signed.
+    signing: Option<Signer>,
+    /// How Atom content is packed when pushed.
+    pack: PackConfig,
+    /// The known Atom kinds, validating manifests which declare one.
+    kinds: KindRegistry,
+    /// The lints manifests are checked against.
+    linter: Linter,
+    /// The policy committed by the organization at the root of the published revision.
+    org_policy: OrgPolicy,
+    /// The versions of each Atom already published, if the organization's policy restricts
+    /// version bumps.
+    published: BTreeMap<Id, BTreeSet<Version>>,
+    /// The workspace manifest committed at the root of the published revision, which member
+    /// manifests inherit keys from.
+    workspace: Workspace,
+    /// What was validated and published before, if publishing incrementally.
+    cache: Option<RefCell<Cache>>,
+    /// The Atom refs of the remote as they were when the publish was planned, which pushes
+    /// are conditioned on.
+    snapshot: Option<Arc<Snapshot>>,
+    /// Whether the repository's filesystem ignores case, per `core.ignoreCase`.
+    ignore_case: bool,
+    /// Whether paths are already relative to the repository root, and normalized lexically.
+    lexical: bool,
+    /// How many Atoms are prepared at once, each on a thread of its own.
+    jobs: NonZeroUsize,
+    /// The warnings collected so far, until taken by the caller.
+    warnings: RefCell<Warnings>,
+}
+
+/// The settings of a [`GitContext`], from which a context of its own is set up on each thread
+/// Atoms are prepared on, as a context is bound to a repository which may not be shared
+/// between threads.
+struct Fork<'a> {
+    commit: ObjectId,
+    remote_str: &'a str,
+    root: Root,
+    strict: bool,
+    cwd: &'a Path,
+    policy: Policy,
+    allow_protected: bool,
+    signer: Option<String>,
+    signing: Option<Signer>,
+    pack: PackConfig,
+    kinds: KindRegistry,
+    linter: Linter,
+    org_policy: OrgPolicy,
+    published: BTreeMap<Id, BTreeSet<Version>>,
+    workspace: Workspace,
+    cache: Option<Cache>,
+    snapshot: Option<Arc<Snapshot>>,
+    ignore_case: bool,
+    lexical: bool,
 }
 
 struct AtomContext<'a> {
@@ -61,10 +141,29 @@ struct AtomContext<'a> {
     git: &'a GitContext<'a>,
 }
 
+/// An Atom whose objects were written to the repository, so that all that is left to publish
+/// it is writing and pushing its refs, which is done for one Atom at a time.
+struct Prepared {
+    spec: Atom,
+    id: GitAtomId,
+    paths: AtomPaths<PathBuf>,
+    ref_prefix: String,
+    /// The id of what the source of the Atom is made of, if publishing incrementally.
+    source: Option<ObjectId>,
+    /// The Atom commit, and the tree of its manifest and lock, unless the very same Atom was
+    /// published before.
+    written: Option<(CommittedAtom, ObjectId)>,
+}
+
 struct FoundAtom<'a> {
     spec: Atom,
     id: GitAtomId,
     entries: AtomEntries<'a>,
+    /// The id and content of the manifest as published, if it differs from the source's.
+    rewritten: Option<(ObjectId, Vec<u8>)>,
+    /// The content as published, if it differs from the source's, as the manifest excludes
+    /// paths from it, or includes others.
+    content: Option<crate::store::git::exclude::Rewritten>,
 }
 
 use gix::diff::object::Commit as AtomCommit;
@@ -123,44 +222,229 @@ pub struct GitContent {
     ref_prefix: String,
 }
 
+/// A preview of an Atom that would be published, computed without writing any objects
+/// or references to the repository.
+#[derive(Debug)]
+pub struct GitPlan {
+    id: GitAtomId,
+    version: Version,
+    path: PathBuf,
+    ref_prefix: String,
+    /// The id of the Atom commit, as it would be published.
+    commit: ObjectId,
+    /// The id of the tree of the Atom commit.
+    tree: ObjectId,
+    exists: bool,
+}
+
+/// An Atom found in the published revision, and checked against the policy and lints, ready to
+/// be published to a store other than a Git remote.
+#[cfg(feature = "s3")]
+pub(super) struct Staged<'a> {
+    pub(super) spec: Atom,
+    pub(super) id: GitAtomId,
+    /// The tree of the Atom's content, if it has any.
+    pub(super) content: Option<ObjectId>,
+    /// The manifest as published, with its path dependencies rewritten.
+    pub(super) manifest: Vec<u8>,
+    pub(super) lock: Option<Vec<u8>>,
+    /// The commit the Atom is published from.
+    pub(super) origin: ObjectId,
+    /// The path of the Atom's manifest, relative to the repository root.
+    pub(super) path: PathBuf,
+    pub(super) repo: &'a Repository,
+}
+
 use super::{Builder, ValidAtoms};
 
 /// The type representing a Git specific Atom publisher.
+#[derive(Clone)]
 pub struct GitPublisher<'a> {
     repo: &'a Repository,
     remote: &'a str,
     spec: &'a str,
     root: Root,
+    strict: bool,
+    cwd: &'a Path,
+    policy: Policy,
+    allow_protected: bool,
+    pack: PackConfig,
+    kinds: KindRegistry,
+    linter: Linter,
+    lexical: bool,
+    incremental: bool,
+    published: Option<BTreeMap<Id, BTreeSet<Version>>>,
+    snapshot: Option<Arc<Snapshot>>,
+    signing: Option<Signer>,
+    jobs: NonZeroUsize,
 }
 
 impl<'a> GitPublisher<'a> {
-    /// Constructs a new [`GitPublisher`].
+    /// Constructs a new [`GitPublisher`], fetching the root and policy of the remote store.
     pub fn new(repo: &'a Repository, remote: &'a str, spec: &'a str) -> GitResult<Self> {
-        use crate::store::Init;
-        let root = repo
-            .find_remote(remote)
-            .map_err(Box::new)?
-            .ekala_root()
-            .map_err(|e| {
-                e.warn();
-                Error::NotInitialized
-            })?;
+        use crate::store::{Init, QueryPolicy};
+        let store = repo.find_remote(remote).map_err(Box::new)?;
+        let root = store.ekala_root().map_err(Error::NotInitialized)?;
+        let policy = store.ekala_policy()?.unwrap_or_default();
 
         Ok(GitPublisher {
             repo,
             remote,
             spec,
             root,
+            strict: false,
+            cwd: repo.current_dir(),
+            policy,
+            allow_protected: false,
+            pack: PackConfig::default(),
+            kinds: KindRegistry::default(),
+            linter: Linter::default(),
+            lexical: false,
+            incremental: false,
+            published: None,
+            snapshot: None,
+            signing: None,
+            jobs: NonZeroUsize::MIN,
         })
     }
-}
 
-fn calculate_capacity(record_count: usize) -> usize {
-    let log_count = (record_count as f64).log2();
-    let base_multiplier = 20.0;
-    let scaling_factor = (log_count - 10.0).max(0.0).powf(2.0);
-    let multiplier = base_multiplier + scaling_factor * 10.0;
-    (log_count * multiplier).ceil() as usize
+    /// Constructs a new [`GitPublisher`] for a store other than a Git remote, which reported
+    /// the given root. The store declares no policy, and nothing is pushed to a remote.
+    #[cfg(feature = "s3")]
+    pub(super) fn with_root(repo: &'a Repository, root: Root, spec: &'a str) -> Self {
+        GitPublisher {
+            repo,
+            remote: "",
+            spec,
+            root,
+            strict: false,
+            cwd: repo.current_dir(),
+            policy: Policy::default(),
+            allow_protected: false,
+            pack: PackConfig::default(),
+            kinds: KindRegistry::default(),
+            linter: Linter::default(),
+            lexical: false,
+            incremental: false,
+            published: None,
+            snapshot: None,
+            signing: None,
+            jobs: NonZeroUsize::MIN,
+        }
+    }
+
+    /// Check version bumps against the given versions of each Atom already published, rather
+    /// than those listed from the remote.
+    #[cfg(feature = "s3")]
+    #[must_use]
+    pub(super) fn published(mut self, published: BTreeMap<Id, BTreeSet<Version>>) -> Self {
+        self.published = Some(published);
+        self
+    }
+
+    /// Publish Atom versions protected by the store's policy, even if the publisher is not
+    /// one of their permitted signers.
+    #[must_use]
+    pub fn allow_protected(mut self, allow: bool) -> Self {
+        self.allow_protected = allow;
+        self
+    }
+
+    /// Control how Atom content is packed when pushed, e.g. to save bandwidth on a slow
+    /// link at the expense of processing time.
+    #[must_use]
+    pub fn pack(mut self, pack: PackConfig) -> Self {
+        self.pack = pack.resolve();
+        self
+    }
+
+    /// Validate Atom manifests against the given registry of kinds, rather than only the
+    /// builtin ones. Manifests declaring an unknown kind are invalid.
+    #[must_use]
+    pub fn kinds(mut self, kinds: KindRegistry) -> Self {
+        self.kinds = kinds;
+        self
+    }
+
+    /// Check manifests against the given lints, refusing those violating any at the `error`
+    /// level as invalid, and warning about the others when their Atom is published.
+    #[must_use]
+    pub fn lints(mut self, linter: Linter) -> Self {
+        self.linter = linter;
+        self
+    }
+
+    /// Interpret Atom paths as already relative to the repository root, e.g. paths read from
+    /// its tree, normalizing them lexically instead of resolving them on the filesystem. The
+    /// paths then need not exist in the working directory, nor need there be one at all.
+    #[must_use]
+    pub fn lexical(mut self, lexical: bool) -> Self {
+        self.lexical = lexical;
+        self
+    }
+
+    /// Validate and publish incrementally, skipping manifests found valid, and Atoms published,
+    /// before, as long as the objects they are made of are unchanged, as recorded in a cache
+    /// kept under the git directory.
+    #[must_use]
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Push each ref only if the remote still lacks it, as it did when the given snapshot of
+    /// its refs was taken, e.g. while planning the publish. The Atoms whose refs changed in
+    /// the meantime are checked against the remote again, and skipped if someone else
+    /// published the very same Atom, or refused as published concurrently otherwise.
+    ///
+    /// The versions already published are also read from the snapshot, rather than listed
+    /// from the remote again.
+    #[must_use]
+    pub fn snapshot(mut self, snapshot: Snapshot) -> Self {
+        self.snapshot = Some(Arc::new(snapshot));
+        self
+    }
+
+    /// Sign each Atom commit written with the given signer, so that the store's consumers can
+    /// authenticate the publisher. The Atoms remain reproducible, but for their signature.
+    #[must_use]
+    pub fn sign(mut self, signer: Signer) -> Self {
+        self.signing = Some(signer);
+        self
+    }
+
+    /// Prepare up to `jobs` Atoms at once, each on a thread of its own, i.e. find and check
+    /// them, and write their objects. Their refs are still written, and pushed, one Atom at a
+    /// time, in the order the Atoms were given.
+    #[must_use]
+    pub fn jobs(mut self, jobs: NonZeroUsize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Interpret relative Atom paths from the given directory, rather than the current
+    /// working directory of the process.
+    #[must_use]
+    pub fn current_dir(mut self, cwd: &'a Path) -> Self {
+        self.cwd = cwd;
+        self
+    }
+
+    /// Publish from the given revision, rather than the one the publisher was constructed
+    /// with, e.g. to publish from several revisions without querying the store again for each.
+    #[must_use]
+    pub fn spec(mut self, spec: &'a str) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    /// Treat any invalid Atom manifest encountered during validation as a hard failure,
+    /// rather than warning and skipping it.
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
 }
 
 use super::StateValidator;
@@ -169,44 +453,24 @@ impl<'a> StateValidator<Root> for GitPublisher<'a> {
     type Error = Error;
     type Publisher = GitContext<'a>;
 
+    #[tracing::instrument(level = "trace", skip_all)]
     fn validate(publisher: &Self::Publisher) -> Result<ValidAtoms, Self::Error> {
-        use gix::traverse::tree::Recorder;
-        let mut record = Recorder::default();
-
-        publisher
-            .tree()
-            .traverse()
-            .breadthfirst(&mut record)
-            .map_err(|_| Error::NotFound)?;
-
-        let cap = calculate_capacity(record.records.len());
-        let mut atoms: HashMap<Id, PathBuf> = HashMap::with_capacity(cap);
-
-        for entry in record.records {
-            if entry.mode.is_blob() && entry.filepath.ends_with(crate::ATOM_EXT.as_ref()) {
-                if let Ok(obj) = publisher.repo.find_object(entry.oid) {
-                    let path = PathBuf::from(entry.filepath.to_string());
-                    match publisher.verify_manifest(&obj, &path) {
-                        Ok(atom) => {
-                            if let Some(duplicate) = atoms.get(&atom.id) {
-                                tracing::warn!(
-                                    message = "Two atoms share the same ID",
-                                    duplicate.id = %atom.id,
-                                    fst = %path.display(),
-                                    snd = %duplicate.display(),
-                                );
-                                return Err(Error::Duplicates);
-                            }
-                            atoms.insert(atom.id, path);
-                        },
-                        Err(e) => e.warn(),
-                    }
-                }
-            }
-        }
+        use inner::AtomVisitor;
+        let mut visitor = AtomVisitor::new(publisher);
+
+        // a cancelled traversal is reported by the visitor as a duplicate
+        let traversal = publisher.tree().traverse().breadthfirst(&mut visitor);
+        let (atoms, invalid) = visitor.finish()?;
+        traversal.map_err(|_| Error::NotFound)?;
+        publisher.save_cache();
 
         tracing::trace!(repo.atoms.valid.count = atoms.len());
 
+        if publisher.strict && !invalid.is_empty() {
+            return Err(Error::Strict(invalid));
+        }
+        publisher.warnings.borrow_mut().extend(invalid);
+
         Ok(atoms)
     }
 }
@@ -216,7 +480,7 @@ impl<'a> Builder<'a, Root> for GitPublisher<'a> {
     type Publisher = GitContext<'a>;
 
     fn build(&self) -> Result<(ValidAtoms, Self::Publisher), Self::Error> {
-        let publisher = GitContext::set(self.repo, self.remote, self.spec, self.root)?;
+        let publisher = GitContext::set(self)?;
         let atoms = GitPublisher::validate(&publisher)?;
         Ok((atoms, publisher))
     }
@@ -254,10 +518,62 @@ impl GitContent {
     }
 }
 
-use std::collections::HashMap;
+impl GitPlan {
+    /// Return a reference to the [`AtomId`] of the planned Atom.
+    #[must_use]
+    pub fn id(&self) -> &GitAtomId {
+        &self.id
+    }
+
+    /// Return a reference to the version which would be published.
+    #[must_use]
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Return a reference to the path of the Atom's manifest.
+    #[must_use]
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return a reference to the ref prefix the Atom would be published under.
+    #[must_use]
+    pub fn ref_prefix(&self) -> &String {
+        &self.ref_prefix
+    }
+
+    /// Return the id of the Atom commit which would be published, as it is reproducible.
+    #[must_use]
+    pub fn commit(&self) -> ObjectId {
+        self.commit
+    }
+
+    /// Return the id of the tree of the Atom commit which would be published. Unlike the
+    /// commit, it is the same whichever revision the Atom is published from, and whether the
+    /// commit is signed or not, so it tells whether a published Atom has the same content.
+    #[must_use]
+    pub fn tree(&self) -> ObjectId {
+        self.tree
+    }
+
+    /// Return the full names of the refs the Atom would be published under.
+    #[must_use]
+    pub fn refs(&self) -> [String; 3] {
+        use super::{ATOM, ATOM_MANIFEST, ATOM_ORIGIN};
+        let version = crate::store::git::encode_version(&self.version);
+        [ATOM, ATOM_MANIFEST, ATOM_ORIGIN]
+            .map(|kind| format!("refs/{}/{version}/{kind}", self.ref_prefix))
+    }
+
+    /// Whether this version already exists, meaning it would be safely skipped.
+    #[must_use]
+    pub fn exists(&self) -> bool {
+        self.exists
+    }
+}
 
 use super::Publish;
-use crate::id::Id;
 
 impl<'a> super::private::Sealed for GitContext<'a> {}
 
@@ -274,7 +590,9 @@ impl<'a> Publish<Root> for GitContext<'a> {
     /// - First attempts to interpret each path as relative to the caller's current location inside
     ///   the repository.
     /// - If normalization fails (e.g., in a bare repository), falls back to treating the path as
-    ///   already relative to the repo root.
+    ///   already relative to the repo root, normalized lexically.
+    /// - In lexical mode, paths are always treated as relative to the repo root, and normalized
+    ///   without consulting the file system, so they need only exist in the published tree.
     /// - The normalized path is used to search the Git history, not the file system.
     ///
     /// # Publishing Process
@@ -286,6 +604,12 @@ impl<'a> Publish<Root> for GitContext<'a> {
     ///    - If successful, the atom is added to the repository.
     ///    - If any error occurs during publishing, the atom is skipped, and an error is logged.
     ///
+    /// # Parallelism
+    /// Up to [`GitPublisher::jobs`] atoms are prepared at once, i.e. found, checked against
+    /// the policy and lints, and have their objects written, each on a thread of its own. Their
+    /// refs are then written, and their pushes started, one atom at a time, in the order given,
+    /// so that ref transactions never race one another.
+    ///
     /// # Error Handling
     /// - The function aims to process all provided paths, even if some fail.
     /// - Errors and skipped atoms are collected as results but do not halt the overall process.
@@ -299,39 +623,85 @@ impl<'a> Publish<Root> for GitContext<'a> {
     where
         C: IntoIterator<Item = PathBuf>,
     {
-        use crate::store::git;
-        paths
+        let paths: Vec<_> = paths
             .into_iter()
-            .map(|path| {
-                let path = match self.repo.normalize(&path) {
-                    Ok(path) => path,
-                    Err(git::Error::NoWorkDir) => path,
-                    Err(e) => return Err(e.into()),
-                };
-                self.publish_atom(&path)
-            })
+            .map(|path| self.normalize_path(path))
+            .collect();
+        if self.jobs.get() == 1 || paths.len() < 2 {
+            return paths
+                .into_iter()
+                .map(|path| self.publish_atom(path?))
+                .collect();
+        }
+
+        self.prepare_each(paths)
+            .into_iter()
+            .map(|prepared| self.finish(prepared?))
             .collect()
     }
 
+    #[tracing::instrument(level = "trace", skip_all, fields(path = %path.as_ref().display()))]
     fn publish_atom<P: AsRef<Path>>(&self, path: P) -> GitResult<GitOutcome> {
-        use {Err as Skipped, Ok as Published};
-
-        let atom = AtomContext::set(path.as_ref(), self)?;
-
-        let tree_id = match atom.write_atom_tree(&atom.atom.entries)? {
-            Ok(t) => t,
-            Skipped(id) => return Ok(Skipped(id)),
-        };
+        self.finish(self.prepare(path.as_ref())?)
+    }
+}
 
-        let refs = atom
-            .write_atom_commit(tree_id)?
-            .write_refs(&atom)?
-            .push(&atom);
+impl<'a> Fork<'a> {
+    fn new(git: &GitContext<'a>) -> Self {
+        Fork {
+            commit: git.commit.id,
+            remote_str: git.remote_str,
+            root: git.root,
+            strict: git.strict,
+            cwd: git.cwd,
+            policy: git.policy.clone(),
+            allow_protected: git.allow_protected,
+            signer: git.signer.clone(),
+            signing: git.signing.clone(),
+            pack: git.pack,
+            kinds: git.kinds.clone(),
+            linter: git.linter.clone(),
+            org_policy: git.org_policy.clone(),
+            published: git.published.clone(),
+            workspace: git.workspace.clone(),
+            cache: git.cache.as_ref().map(|cache| cache.borrow().clone()),
+            snapshot: git.snapshot.clone(),
+            ignore_case: git.ignore_case,
+            lexical: git.lexical,
+        }
+    }
 
-        Ok(Published(GitRecord {
-            id: atom.atom.id.clone(),
-            content: Content::Git(refs),
-        }))
+    /// Set up a context of its own from the settings, bound to `repo`, which prepares the
+    /// Atoms given to it on the calling thread.
+    fn context<'b>(&'b self, repo: &'b Repository) -> GitResult<GitContext<'b>> {
+        let commit = repo.find_commit(self.commit)?;
+        Ok(GitContext {
+            repo,
+            tree: commit.tree()?,
+            commit,
+            remote_str: self.remote_str,
+            root: self.root,
+            push_tasks: RefCell::new(JoinSet::new()),
+            buf: RefCell::new(Vec::with_capacity(64)),
+            strict: self.strict,
+            cwd: self.cwd,
+            policy: self.policy.clone(),
+            allow_protected: self.allow_protected,
+            signer: self.signer.clone(),
+            signing: self.signing.clone(),
+            pack: self.pack,
+            kinds: self.kinds.clone(),
+            linter: self.linter.clone(),
+            org_policy: self.org_policy.clone(),
+            published: self.published.clone(),
+            workspace: self.workspace.clone(),
+            cache: self.cache.clone().map(RefCell::new),
+            snapshot: self.snapshot.clone(),
+            ignore_case: self.ignore_case,
+            lexical: self.lexical,
+            jobs: NonZeroUsize::MIN,
+            warnings: RefCell::default(),
+        })
     }
 }
 
@@ -339,24 +709,44 @@ impl<'a> AtomContext<'a> {
     fn set(path: &'a Path, git: &'a GitContext) -> GitResult<Self> {
         let (atom, paths) = git.find_and_verify_atom(path)?;
         let ref_prefix = format!("{}/{}", super::ATOM_REF_TOP_LEVEL, atom.id.id());
-        Ok(Self {
+        let atom = Self {
             paths,
             atom,
             ref_prefix,
             git,
-        })
+        };
+        atom.validate_refs()?;
+        Ok(atom)
     }
 }
 
 impl<'a> GitContext<'a> {
-    fn set(
-        repo: &'a Repository,
-        remote_str: &'a str,
-        refspec: &str,
-        root: Root,
-    ) -> GitResult<Self> {
+    fn set(publisher: &GitPublisher<'a>) -> GitResult<Self> {
+        use crate::store::git;
+
+        let &GitPublisher {
+            repo,
+            remote: remote_str,
+            spec: refspec,
+            root,
+            strict,
+            cwd,
+            ref policy,
+            allow_protected,
+            pack,
+            ref kinds,
+            ref linter,
+            lexical,
+            incremental,
+            ref published,
+            ref snapshot,
+            ref signing,
+            jobs,
+        } = publisher;
         // short-circuit publishing if the passed remote doesn't exist
-        let _remote = repo.find_remote(remote_str).map_err(Box::new)?;
+        if !remote_str.is_empty() {
+            let _remote = repo.find_remote(remote_str).map_err(Box::new)?;
+        }
         let commit = repo
             .rev_parse_single(refspec)
             .map(|s| repo.find_commit(s))
@@ -364,7 +754,32 @@ impl<'a> GitContext<'a> {
 
         let tree = commit.tree()?;
 
+        let org_policy = git::read_org_policy(repo, commit.id)?.unwrap_or_default();
+        let workspace = git::read_workspace(repo, commit.id)?.unwrap_or_default();
+        // only a version bump rule needs the versions already published
+        let published = match (org_policy.bumps, published) {
+            (Bumps::Sequential, Some(published)) => published.clone(),
+            (Bumps::Sequential, None) => match snapshot {
+                Some(snapshot) => snapshot.published(),
+                None => git::published(repo, remote_str)?,
+            },
+            (Bumps::Any, _) => BTreeMap::new(),
+        };
+
+        let cache = incremental.then(|| {
+            let settings = (root, &org_policy, &workspace, linter, kinds, &published);
+            RefCell::new(Cache::load(repo.common_dir(), fingerprint(repo, &settings)))
+        });
+
         let push_tasks = RefCell::new(JoinSet::new());
+        let ignore_case = repo
+            .config_snapshot()
+            .boolean("core.ignoreCase")
+            .unwrap_or(false);
+        let signer = repo
+            .committer()
+            .and_then(Result::ok)
+            .map(|sig| sig.email.to_string());
 
         Ok(Self {
             repo,
@@ -374,23 +789,457 @@ impl<'a> GitContext<'a> {
             remote_str,
             push_tasks,
             buf: RefCell::new(Vec::with_capacity(64)),
+            strict,
+            cwd,
+            policy: policy.clone(),
+            allow_protected,
+            signer,
+            signing: signing.clone(),
+            pack,
+            kinds: kinds.clone(),
+            linter: linter.clone(),
+            org_policy,
+            published,
+            workspace,
+            cache,
+            snapshot: snapshot.clone(),
+            ignore_case,
+            lexical,
+            jobs,
+            warnings: RefCell::default(),
         })
     }
 
+    /// Find the Atom at `path`, check it against the policy and lints, and write its objects,
+    /// unless it was published from the very same source before, as far as the cache knows.
+    #[tracing::instrument(level = "trace", skip_all, fields(path = %path.display()))]
+    fn prepare(&self, path: &Path) -> GitResult<MaybeSkipped<Prepared>> {
+        use {Err as Skipped, Ok as Wrote};
+
+        let source = self.source(path)?;
+        if let Some(id) = source.and_then(|source| self.unchanged(source)) {
+            return Ok(Skipped(id));
+        }
+
+        let atom = AtomContext::set(path, self)?;
+
+        let written = match atom.write_atom_tree()? {
+            Wrote(tree_id) => {
+                self.check_policy(&atom.atom.spec)?;
+                self.warn_lints(&atom.atom.spec);
+                let commit = atom.write_atom_commit(tree_id)?;
+                Some((commit, atom.write_spec_tree()?))
+            },
+            Skipped(_) => None,
+        };
+
+        Ok(Wrote(atom.prepared(source, written)))
+    }
+
+    /// Prepare the Atoms at the given paths on as many threads at once as given to
+    /// [`GitPublisher::jobs`], each with a context of its own, returning the outcome for each in
+    /// the order given.
+    ///
+    /// The warnings collected on each thread are collected into this context. Should a thread
+    /// fail to set up its context, the Atoms left are prepared on the calling thread instead.
+    fn prepare_each(
+        &self,
+        paths: Vec<GitResult<PathBuf>>,
+    ) -> Vec<GitResult<MaybeSkipped<Prepared>>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut outcomes: Vec<_> = paths.iter().map(|_| None).collect();
+        let found: Vec<_> = paths
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, path)| match path {
+                Ok(path) => Some((i, path)),
+                Err(e) => {
+                    outcomes[i] = Some(Err(e));
+                    None
+                },
+            })
+            .collect();
+
+        let fork = Fork::new(self);
+        let repo = self.repo.clone().into_sync();
+        let next = AtomicUsize::new(0);
+        let prepared = std::thread::scope(|s| {
+            let workers: Vec<_> = (0..self.jobs.get().min(found.len()))
+                .map(|_| {
+                    s.spawn(|| {
+                        let repo = repo.to_thread_local();
+                        let git = match fork.context(&repo) {
+                            Ok(git) => git,
+                            Err(e) => {
+                                tracing::warn!(%e, "Could not prepare Atoms on another thread");
+                                return (Vec::new(), Warnings::new());
+                            },
+                        };
+                        let mut prepared = Vec::new();
+                        loop {
+                            let n = next.fetch_add(1, Ordering::Relaxed);
+                            let Some((i, path)) = found.get(n) else {
+                                break;
+                            };
+                            prepared.push((*i, git.prepare(path)));
+                        }
+                        (prepared, git.take_warnings())
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|w| w.join().expect("preparing an Atom never panics"))
+                .collect::<Vec<_>>()
+        });
+
+        for (prepared, warnings) in prepared {
+            self.warnings.borrow_mut().extend(warnings);
+            for (i, outcome) in prepared {
+                outcomes[i] = Some(outcome);
+            }
+        }
+        // only the Atoms of a thread which could not set up its context are left
+        for (i, path) in found {
+            if outcomes[i].is_none() {
+                outcomes[i] = Some(self.prepare(&path));
+            }
+        }
+
+        outcomes
+            .into_iter()
+            .map(|outcome| outcome.expect("every Atom is prepared"))
+            .collect()
+    }
+
+    /// Write the refs of a prepared Atom, all at once, and start pushing them, remembering the
+    /// Atom as published from its source, if publishing incrementally.
+    fn finish(&self, prepared: MaybeSkipped<Prepared>) -> GitResult<GitOutcome> {
+        use {Err as Skipped, Ok as Published};
+
+        let atom = match prepared {
+            Ok(atom) => atom,
+            Skipped(id) => return Ok(Skipped(id)),
+        };
+        let Some((commit, spec)) = &atom.written else {
+            atom.remember(self);
+            return Ok(Skipped(atom.spec.id.clone()));
+        };
+
+        let refs = match commit.write_refs(&atom, *spec, self)? {
+            Ok(refs) => {
+                atom.remember(self);
+                refs.push(&atom, self)
+            },
+            Skipped(id) => return Ok(Skipped(id)),
+        };
+
+        Ok(Published(GitRecord {
+            id: atom.id.clone(),
+            content: Content::Git(refs),
+        }))
+    }
+
+    /// Take the warnings collected so far, e.g. while validating and publishing, leaving none
+    /// behind.
+    pub fn take_warnings(&self) -> Warnings {
+        self.warnings.take()
+    }
+
+    /// Refuse to publish a version of an Atom protected by the store's policy, unless the
+    /// publisher is one of its permitted signers, or protection was explicitly overridden.
+    ///
+    /// Publishing an Atom owned by a team the publisher is not a member of is either warned
+    /// about or refused, as the policy demands.
+    fn check_policy(&self, atom: &Atom) -> GitResult<()> {
+        use crate::policy::Enforcement;
+
+        let signer = self.signer.as_deref();
+
+        if let Some(owners) = self.policy.trespass(&atom.id, signer) {
+            let owners = owners.join(", ");
+            match self.policy.namespaces {
+                Enforcement::Warn => self.warnings.borrow_mut().push(Warning::Trespass {
+                    id: atom.id.to_string(),
+                    owners,
+                }),
+                Enforcement::Deny => {
+                    return Err(Error::Trespass {
+                        id: atom.id.to_string(),
+                        owners,
+                    });
+                },
+            }
+        }
+
+        // versions bound to keys are only accepted by the store's hooks once signed by one
+        let unsigned = || {
+            self.signing
+                .is_none()
+                .then(|| self.policy.signed(&atom.id, &atom.version).first().copied())
+                .flatten()
+        };
+        let violation = self.policy.violation(&atom.id, &atom.version, signer);
+        let Some(protected) = violation.or_else(unsigned) else {
+            return Ok(());
+        };
+
+        if self.allow_protected {
+            self.warnings.borrow_mut().push(Warning::Protected {
+                id: atom.id.to_string(),
+                version: atom.version.clone(),
+                protected: protected.versions.clone(),
+            });
+            return Ok(());
+        }
+
+        Err(Error::Protected {
+            id: atom.id.to_string(),
+            version: atom.version.clone(),
+        })
+    }
+
+    /// Warn about the lints the manifest of an Atom violates. Those it violates at the `error`
+    /// level already invalidated it during validation.
+    fn warn_lints(&self, atom: &Atom) {
+        let warned = self.linter.check(atom).unwrap_or_default();
+        self.warnings
+            .borrow_mut()
+            .extend(warned.into_iter().map(Warning::Lint));
+    }
+
+    /// Normalize a user supplied path, falling back to treating it as relative to the
+    /// repository root when there is no working directory, e.g. in a bare repository.
+    ///
+    /// In lexical mode, the path is always treated as relative to the repository root, and
+    /// the filesystem is never consulted.
+    pub(super) fn normalize_path(&self, path: PathBuf) -> GitResult<PathBuf> {
+        use crate::store::git;
+        if self.lexical {
+            return Ok(self.repo.normalize_lexical(&path)?);
+        }
+        match self.repo.normalize_from(self.cwd, &path) {
+            Ok(path) => Ok(path),
+            Err(git::Error::NoWorkDir) => Ok(self.repo.normalize_lexical(&path)?),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Find the Atom at the given path in the published revision, and check it against the
+    /// policy and lints, exactly as [`Publish::publish_atom`] would, yielding everything a
+    /// store other than a Git remote needs to publish it, without writing anything but the
+    /// trees of its content its manifest excludes paths from, or includes paths in.
+    #[cfg(feature = "s3")]
+    pub(super) fn stage(&self, path: &Path) -> GitResult<Staged<'a>> {
+        let atom = AtomContext::set(path, self)?;
+        self.check_policy(&atom.atom.spec)?;
+        self.warn_lints(&atom.atom.spec);
+
+        let FoundAtom {
+            spec,
+            id,
+            entries,
+            rewritten,
+            content,
+        } = atom.atom;
+        // the manifest is always the first entry, followed by the content and lock, if any
+        self.write_content(content.as_ref())?;
+        let content = match content {
+            Some(content) => Some(content.id),
+            None => entries
+                .iter()
+                .find(|e| e.mode().is_tree())
+                .map(Entry::object_id),
+        };
+        let lock = entries.iter().skip(1).find(|e| e.mode().is_blob());
+        let manifest = match rewritten {
+            Some((_, manifest)) => manifest,
+            None => entries[0].object()?.detach().data,
+        };
+
+        Ok(Staged {
+            spec,
+            id,
+            content,
+            manifest,
+            lock: lock
+                .map(|e| e.object())
+                .transpose()?
+                .map(|o| o.detach().data),
+            origin: self.commit.id,
+            path: atom.paths.spec().to_path_buf(),
+            repo: self.repo,
+        })
+    }
+
+    /// Compute a [`GitPlan`] for each of the given paths without publishing anything.
+    ///
+    /// Paths are normalized exactly as in [`Publish::publish`], so the plan accurately
+    /// reflects what a subsequent call to it would do.
+    pub fn plan<C>(&self, paths: C) -> Vec<GitResult<GitPlan>>
+    where
+        C: IntoIterator<Item = PathBuf>,
+    {
+        paths
+            .into_iter()
+            .map(|path| {
+                let path = self.normalize_path(path)?;
+                let atom = AtomContext::set(&path, self)?;
+                let (tree, commit) = atom.recreate()?;
+                Ok(GitPlan {
+                    commit,
+                    tree: self.compute_hash(&tree)?,
+                    exists: atom.exists(),
+                    id: atom.atom.id.clone(),
+                    version: atom.atom.spec.version.clone(),
+                    path: atom.paths.spec().to_path_buf(),
+                    ref_prefix: atom.ref_prefix.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Group the Atoms at the given paths into levels, such that every Atom depends by path
+    /// only on Atoms in earlier levels. Publishing a level at a time then never publishes an
+    /// Atom before those it depends on. Dependencies on Atoms not among the paths are ignored.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a path cannot be normalized, or the path
+    /// dependencies among the Atoms form a cycle.
+    pub fn levels(&self, paths: Vec<PathBuf>) -> GitResult<Vec<Vec<PathBuf>>> {
+        use std::collections::BTreeMap;
+
+        // each Atom by the path of its manifest, with the manifests of its path dependencies
+        let mut pending = BTreeMap::new();
+        for path in paths {
+            let spec = AtomPaths::new(self.normalize_path(path.clone())?)
+                .spec()
+                .to_path_buf();
+            let deps = self.path_deps(&spec)?;
+            pending.insert(spec, (path, deps));
+        }
+
+        let mut levels = Vec::new();
+        while !pending.is_empty() {
+            let ready: Vec<_> = pending
+                .iter()
+                .filter(|(_, (_, deps))| deps.iter().all(|dep| !pending.contains_key(dep)))
+                .map(|(spec, _)| spec.clone())
+                .collect();
+
+            if ready.is_empty() {
+                let cycle: Vec<_> = pending.keys().map(|p| p.display().to_string()).collect();
+                return Err(Error::Cycle(cycle.join(", ")));
+            }
+
+            let level = ready
+                .iter()
+                .filter_map(|spec| pending.remove(spec).map(|(path, _)| path))
+                .collect();
+            levels.push(level);
+        }
+
+        Ok(levels)
+    }
+
+    /// The graph of the dependencies among the given Atoms, e.g. those found by
+    /// [`Builder::build`], by their paths, as of the revision published from. An Atom depends
+    /// on another by path, or by id for a dependency on its own store, i.e. one declared with
+    /// neither a url nor a path. Dependencies by path on none of the Atoms are recorded as
+    /// missing, while those by id on none of them are left out, as they may be published to
+    /// the store from elsewhere.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a path cannot be normalized, or the tree of the
+    /// revision cannot be searched.
+    pub fn graph(&self, atoms: &HashMap<Id, PathBuf>) -> GitResult<AtomGraph> {
+        let mut specs = HashMap::with_capacity(atoms.len());
+        for (id, path) in atoms {
+            let spec = AtomPaths::new(self.normalize_path(path.clone())?)
+                .spec()
+                .to_path_buf();
+            specs.insert(spec, id);
+        }
+
+        let mut graph = AtomGraph::default();
+        let mut deps = Vec::new();
+        for (spec, id) in &specs {
+            // Atoms found by the publisher hold valid manifests
+            let Some(manifest) = self.manifest_at(spec)? else {
+                continue;
+            };
+            graph.add_atom(
+                (*id).clone(),
+                AtomNode {
+                    version: manifest.atom.version,
+                    path: atoms[*id].clone(),
+                },
+            );
+            deps.push((spec, *id, manifest.deps.atoms));
+        }
+
+        for (spec, id, declared) in deps {
+            let dir = spec.parent().unwrap_or(Path::new(""));
+            for (name, dep) in declared {
+                match &dep.path {
+                    Some(path) => {
+                        let found = self
+                            .path_dep(&dir.join(path))
+                            .and_then(|spec| specs.get(&spec));
+                        match found {
+                            Some(to) => graph.add_dep(id.clone(), (*to).clone()),
+                            None => graph.add_missing(MissingDep {
+                                from: id.clone(),
+                                name,
+                                path: Some(path.clone()),
+                            }),
+                        }
+                    },
+                    None if dep.is_same_store() && graph.nodes().contains_key(&name) => {
+                        graph.add_dep(id.clone(), name);
+                    },
+                    None => {},
+                }
+            }
+        }
+
+        Ok(graph)
+    }
+
     /// A method used to await the results of the concurrently running Git pushes,
     /// which were offloaded to a seperate thread of execution of Tokio's runtime.
     ///
     /// An errors that occurred will be collected into a [`Vec`].
+    #[tracing::instrument(level = "trace", skip_all)]
     pub async fn await_pushes(&self, errors: &mut Vec<Error>) {
+        self.await_pushes_with(errors, |_| {}).await;
+    }
+
+    /// Await the results of the concurrently running Git pushes, as with
+    /// [`GitContext::await_pushes`], calling `pushed` with whether each push succeeded as
+    /// soon as it completes, e.g. to report the throughput of pushes.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn await_pushes_with<F>(&self, errors: &mut Vec<Error>, mut pushed: F)
+    where
+        F: FnMut(bool),
+    {
         use tokio::sync::Mutex;
 
         let tasks = Mutex::new(self.push_tasks.borrow_mut());
 
         while let Some(task) = tasks.lock().await.join_next().await {
+            pushed(matches!(task, Ok(Ok(_))));
             match task {
-                Ok(Ok(output)) => {
-                    if !output.is_empty() {
-                        tracing::info!(output = %String::from_utf8_lossy(&output));
+                Ok(Ok(notices)) => {
+                    let mut warnings = self.warnings.borrow_mut();
+                    for notice in notices {
+                        // each ref of an Atom is pushed on its own, often eliciting the same
+                        if !warnings.iter().any(|w| w.to_string() == notice.to_string()) {
+                            warnings.push(notice);
+                        }
                     }
                 },
                 Ok(Err(e)) => {
@@ -401,6 +1250,18 @@ impl<'a> GitContext<'a> {
                 },
             }
         }
+        self.save_cache();
+    }
+
+    /// Persist the cache, if publishing incrementally. The cache is merely an optimization, so
+    /// failing to persist it is only warned about.
+    fn save_cache(&self) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+        if let Err(e) = cache.borrow().save(self.repo.common_dir()) {
+            tracing::warn!(%e, "Could not persist the publish cache");
+        }
     }
 
     /// Return a reference to the git tree object of the commit the Atom originates from.
@@ -408,3 +1269,124 @@ impl<'a> GitContext<'a> {
         self.tree.clone()
     }
 }
+
+/// List the commits at which each version of the Atom at `path` was introduced, in the
+/// first-parent history of the revision `spec`, oldest first.
+///
+/// Only versions of the Atom with the id it has in `spec` are considered, and a version is
+/// attributed to the first commit it appears in, even if it reappears later. Commits in which
+/// the Atom is missing, or its manifest invalid, are passed over.
+///
+/// # Errors
+///
+/// This function will return an error if the revision cannot be resolved, or its history read.
+pub fn version_history(
+    repo: &Repository,
+    spec: &str,
+    path: &Path,
+) -> GitResult<Vec<(ObjectId, Version)>> {
+    use std::collections::HashSet;
+
+    use gix::bstr::ByteSlice;
+
+    use crate::Manifest;
+    use crate::store::git;
+
+    let paths = AtomPaths::new(path);
+    let manifest = git::try_to_tree_path(paths.spec())?;
+    let search: Vec<_> = manifest.split_str("/").filter(|c| !c.is_empty()).collect();
+
+    let tip = repo
+        .rev_parse_single(spec)
+        .map(|s| repo.find_commit(s))
+        .map_err(Box::new)??;
+    let mut walk = tip.ancestors().first_parent_only().all()?;
+
+    let mut id = None;
+    let mut history = Vec::new();
+    let mut buf = Vec::new();
+    while let Some(Ok(info)) = walk.next() {
+        let tree = repo.find_commit(info.id)?.tree()?;
+        let Some(entry) = tree.lookup_entry(search.iter().copied(), &mut buf)? else {
+            continue;
+        };
+        if !entry.mode().is_blob() {
+            continue;
+        }
+        let blob = entry.object()?;
+        let content = std::str::from_utf8(&blob.data).ok();
+        let Some(atom) = content.and_then(|c| Manifest::get_atom(c).ok()) else {
+            continue;
+        };
+
+        // the history is walked from the tip, whose id is the one the Atom is known by
+        if *id.get_or_insert_with(|| atom.id.clone()) == atom.id {
+            history.push((info.id, atom.version));
+        }
+    }
+
+    history.reverse();
+    let mut seen = HashSet::new();
+    history.retain(|(_, version)| seen.insert(version.clone()));
+    Ok(history)
+}
+
+/// Fingerprint the settings manifests are validated, and Atoms published, under, along with the
+/// version of the publisher itself, as nothing cached under other settings holds.
+fn fingerprint(repo: &Repository, settings: &dyn std::fmt::Debug) -> String {
+    let settings = format!("{} {settings:?}", env!("CARGO_PKG_VERSION"));
+    gix::objs::compute_hash(
+        repo.object_hash(),
+        gix::object::Kind::Blob,
+        settings.as_bytes(),
+    )
+    .to_string()
+}
+
+/// Re-create the tree and the id of the commit of the Atom whose manifest is at `path`,
+/// relative to the repository root, in the commit `origin`, exactly as they are published from
+/// it, without writing anything.
+///
+/// The Atom is known to have been published already, so only its manifest is validated, and
+/// neither the policy of the store, nor that of the organization, is applied to it.
+///
+/// # Errors
+///
+/// This function will return an error if `origin` cannot be read, or there is no valid Atom
+/// at `path` in it.
+pub(crate) fn recreate(
+    repo: &Repository,
+    origin: ObjectId,
+    path: &Path,
+) -> GitResult<(gix::objs::Tree, ObjectId)> {
+    use crate::CalculateRoot;
+
+    let commit = repo.find_commit(origin)?;
+    let git = GitContext {
+        repo,
+        tree: commit.tree()?,
+        root: commit.calculate_root()?,
+        commit,
+        remote_str: "",
+        push_tasks: RefCell::new(JoinSet::new()),
+        buf: RefCell::new(Vec::with_capacity(64)),
+        strict: false,
+        cwd: repo.current_dir(),
+        policy: Policy::default(),
+        allow_protected: true,
+        signer: None,
+        signing: None,
+        pack: PackConfig::default(),
+        kinds: KindRegistry::default(),
+        linter: Linter::default(),
+        org_policy: OrgPolicy::default(),
+        published: BTreeMap::new(),
+        workspace: git::read_workspace(repo, origin)?.unwrap_or_default(),
+        cache: None,
+        snapshot: None,
+        ignore_case: false,
+        lexical: true,
+        warnings: RefCell::default(),
+    };
+    AtomContext::set(path, &git)?.recreate()
+}
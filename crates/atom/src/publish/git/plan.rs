@@ -0,0 +1,154 @@
+//! # Publish Plans
+//!
+//! Publishing may be split in two steps: planning, which validates the Atoms and computes
+//! their commits without writing anything, and executing the plan, which publishes exactly
+//! the Atoms it lists, e.g. once a human reviewed and approved it, possibly on another
+//! machine.
+//!
+//! A [`PublishPlan`] records the revision the Atoms are published from, and for each of them,
+//! its version, the id of its Atom commit and the refs it is published under. Since Atom
+//! commits are reproducible, executing the plan re-creates each commit first, and refuses to
+//! publish if any of them differ from the plan.
+//!
+//! As a signature makes a commit differ from the one planned, a plan also records whether its
+//! Atom commits are signed when it is executed. They are then signed exactly as the plan says,
+//! so that the commits published are those planned, but for their signature.
+//!
+//! A plan is signed with the signing key configured in Git, by a commit created with
+//! `git commit-tree -S`, referencing nothing but the id of the plan's contents, which is
+//! embedded in the plan as its signature. Verifying the plan then writes the commit back into
+//! the repository, checks it with `git verify-commit`, and that it covers the plan's contents.
+use std::path::PathBuf;
+
+use gix::{ObjectId, Repository};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use super::{GitPlan, GitResult};
+use crate::publish::error::git::Error;
+use crate::store::git::run_git_command;
+
+/// The format version of the plans written, which must match to execute a plan.
+pub const PLAN_FORMAT: u32 = 2;
+
+/// The prefix of the message of the commit signing a plan, followed by the id of its contents.
+const SIGNED_PLAN: &str = "eka publish plan";
+
+/// A plan of the Atoms to publish, computed without writing anything.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PublishPlan {
+    /// The format version of the plan.
+    pub format: u32,
+    /// The remote the Atoms are published to.
+    pub remote: String,
+    /// The id of the commit the Atoms are published from.
+    pub revision: String,
+    /// The Atoms to publish, in the order they are published.
+    pub atoms: Vec<PlannedAtom>,
+    /// Whether the Atom commits are signed with the signing key configured when the plan is
+    /// executed.
+    #[serde(default)]
+    pub signed: bool,
+    /// The raw commit signing the plan, if it was signed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// An Atom to publish, as recorded in a [`PublishPlan`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlannedAtom {
+    /// The id of the Atom.
+    pub id: String,
+    /// The version published.
+    pub version: Version,
+    /// The path of the Atom's manifest, relative to the repository root.
+    pub path: PathBuf,
+    /// The id of the Atom commit published.
+    pub commit: String,
+    /// The full names of the refs the Atom is published under.
+    pub refs: Vec<String>,
+    /// The level the Atom is published at, after every Atom it depends on by path.
+    #[serde(default)]
+    pub level: usize,
+}
+
+impl PlannedAtom {
+    /// Record the planned Atom, to be published at the given level.
+    #[must_use]
+    pub fn new(plan: &GitPlan, level: usize) -> Self {
+        PlannedAtom {
+            id: plan.id().id().to_string(),
+            version: plan.version().clone(),
+            path: plan.path().clone(),
+            commit: plan.commit().to_string(),
+            refs: plan.refs().into(),
+            level,
+        }
+    }
+
+    /// Check that the Atom would still be published exactly as planned.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the Atom's id, version or commit changed.
+    pub fn check(&self, plan: &GitPlan) -> GitResult<()> {
+        if self.id == plan.id().id().to_string()
+            && &self.version == plan.version()
+            && self.commit == plan.commit().to_string()
+        {
+            Ok(())
+        } else {
+            Err(Error::StalePlan {
+                id: self.id.clone(),
+                version: self.version.clone(),
+            })
+        }
+    }
+}
+
+/// Sign the contents of a plan with the signing key configured in Git, returning the raw
+/// commit holding the signature.
+///
+/// # Errors
+///
+/// This function will return an error if Git fails to create the signed commit, e.g. as no
+/// signing key is configured.
+pub fn sign(repo: &Repository, payload: &[u8]) -> GitResult<String> {
+    let git_dir = repo.git_dir().to_string_lossy().to_string();
+    let digest = digest(repo, payload);
+    let tree = ObjectId::empty_tree(repo.object_hash()).to_string();
+    let message = format!("{SIGNED_PLAN} {digest}");
+    let out = run_git_command(&["-C", &git_dir, "commit-tree", "-S", "-m", &message, &tree])?;
+    let id = ObjectId::from_hex(String::from_utf8_lossy(&out).trim().as_bytes())
+        .map_err(|e| Error::InvalidPlanSignature(e.to_string()))?;
+    let commit = repo.find_object(id)?;
+    Ok(String::from_utf8_lossy(&commit.data).into_owned())
+}
+
+/// Verify that `signature` is a valid signature, by a key Git trusts, of the contents of a
+/// plan.
+///
+/// # Errors
+///
+/// This function will return an error if the signature is malformed, was made for other
+/// contents, or Git fails to verify it.
+pub fn verify(repo: &Repository, payload: &[u8], signature: &str) -> GitResult<()> {
+    let invalid = |reason: &str| Error::InvalidPlanSignature(reason.to_owned());
+    let commit = gix::objs::CommitRef::from_bytes(signature.as_bytes())
+        .map_err(|_| invalid("not a signed commit"))?;
+    let expected = format!("{SIGNED_PLAN} {}", digest(repo, payload));
+    if commit.message.to_string().trim() != expected {
+        return Err(invalid("it was made for other contents"));
+    }
+
+    let id = repo.write_buf(gix::object::Kind::Commit, signature.as_bytes())?;
+    let git_dir = repo.git_dir().to_string_lossy().to_string();
+    run_git_command(&["-C", &git_dir, "verify-commit", &id.to_string()])
+        .map_err(|e| Error::InvalidPlanSignature(e.to_string()))?;
+    Ok(())
+}
+
+/// The id of the contents of a plan, as they would be stored in Git.
+fn digest(repo: &Repository, payload: &[u8]) -> ObjectId {
+    gix::objs::compute_hash(repo.object_hash(), gix::object::Kind::Blob, payload)
+}
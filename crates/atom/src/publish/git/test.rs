@@ -1,5 +1,4 @@
 use std::io::Write;
-use std::os::unix::fs::MetadataExt;
 use std::str::FromStr;
 
 use anyhow::Context;
@@ -10,7 +9,7 @@ use tempfile::{Builder, NamedTempFile};
 use crate::publish::{Content, Publish, Record};
 use crate::store::git;
 
-trait MockAtom {
+pub(crate) trait MockAtom {
     fn mock(
         &self,
         id: &str,
@@ -27,7 +26,7 @@ impl MockAtom for gix::Repository {
         description: &str,
     ) -> Result<(NamedTempFile, ObjectId), anyhow::Error> {
         use gix::objs::Tree;
-        use gix::objs::tree::{Entry, EntryMode};
+        use gix::objs::tree::{Entry, EntryKind};
         use semver::Version;
         use toml_edit::ser;
 
@@ -41,8 +40,16 @@ impl MockAtom for gix::Repository {
             atom: Atom {
                 id: id.try_into()?,
                 version: Version::from_str(version)?,
+                kind: None,
                 description: (!description.is_empty()).then_some(description.into()),
+                keywords: Vec::new(),
+                license: None,
+                exclude: vec![],
+                include: vec![],
+                min_format: None,
             },
+            deps: Default::default(),
+            artifacts: Default::default(),
         };
 
         let buf = ser::to_string_pretty(&manifest)?;
@@ -50,11 +57,10 @@ impl MockAtom for gix::Repository {
 
         let path = atom_file.as_ref().to_path_buf();
 
-        let mode = atom_file.as_file().metadata()?.mode();
         let filename = path.strip_prefix(work_dir)?.display().to_string().into();
         let oid = self.write_blob(buf.as_bytes())?.detach();
         let entry = Entry {
-            mode: EntryMode(mode as u16),
+            mode: EntryKind::Blob.into(),
             filename,
             oid,
         };
@@ -85,7 +91,7 @@ impl MockAtom for gix::Repository {
 async fn publish_atom() -> Result<(), anyhow::Error> {
     use crate::id::Id;
     use crate::publish::git::{Builder, GitPublisher};
-    use crate::store::{Init, QueryStore};
+    use crate::store::{Init, QueryAtoms, QueryStore};
     let (repo, _remote) = git::test::init_repo_and_remote()?;
     let repo = gix::open(repo.as_ref())?;
     let remote = repo.find_remote("origin")?;
@@ -134,5 +140,1132 @@ async fn publish_atom() -> Result<(), anyhow::Error> {
     assert_eq!(content_tree.data, origin_tree.detach().data);
     assert_eq!(content_tree.data, spec_tree.detach().data);
 
+    let atoms = remote.published_atoms()?;
+    let versions: Vec<_> = atoms[&Id::try_from(id)?].iter().collect();
+    assert_eq!(versions, [&semver::Version::new(0, 1, 0)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn publish_in_parallel() -> Result<(), anyhow::Error> {
+    use std::num::NonZeroUsize;
+    use std::path::PathBuf;
+
+    use crate::id::Id;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryAtoms, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let ids = ["foo", "bar", "baz", "qux"];
+    let mut files = Vec::new();
+    for id in ids {
+        files.push(repo.mock(id, "0.1.0", "some atom")?.0);
+    }
+    let (atoms, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?
+        .jobs(NonZeroUsize::new(3).context("zero jobs")?)
+        .build()?;
+
+    // the outcomes are in the order given, including that of a path which is not an Atom
+    let mut paths: Vec<_> = ids
+        .iter()
+        .map(|id| atoms[&Id::try_from(*id).unwrap()].clone())
+        .collect();
+    paths.insert(2, PathBuf::from("missing"));
+    let outcomes = publisher.publish(paths.clone());
+    assert_eq!(outcomes.len(), 5);
+    assert!(outcomes[2].is_err());
+    for (outcome, id) in outcomes.iter().filter(|o| o.is_ok()).zip(ids) {
+        match outcome {
+            Ok(Ok(record)) => assert_eq!(record.id.id().to_string(), id),
+            _ => return Err(anyhow::anyhow!("{id} was not published")),
+        }
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+    assert_eq!(remote.published_atoms()?.len(), ids.len());
+
+    // the Atoms published already are skipped
+    paths.remove(2);
+    for outcome in publisher.publish(paths) {
+        assert!(matches!(outcome, Ok(Err(_))));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn fetch_published_spec() -> Result<(), anyhow::Error> {
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (_file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let version = semver::Version::new(0, 1, 0);
+    let (manifest, lock) = git::fetch_spec(&remote, "foo", &version)?;
+    assert_eq!(manifest.atom.id.to_string(), "foo");
+    assert_eq!(manifest.atom.description.as_deref(), Some("some atom"));
+    assert_eq!(lock, None);
+
+    let missing = semver::Version::new(0, 2, 0);
+    assert!(git::fetch_spec(&remote, "foo", &missing).is_err());
+
+    let id = crate::Id::try_from("foo")?;
+    let specs = git::fetch_specs(&remote, &[(&id, &version), (&id, &version)])?;
+    assert_eq!(specs.len(), 2);
+    for (spec, lock) in specs {
+        assert_eq!(spec.atom.id, id);
+        assert_eq!(lock, None);
+    }
+    assert!(matches!(
+        git::fetch_specs(&remote, &[(&id, &version), (&id, &missing)]),
+        Err(git::Error::NoRef(..))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn verify_published_atoms() -> Result<(), anyhow::Error> {
+    use std::num::NonZeroUsize;
+
+    use crate::publish::ATOM;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::git::transaction::RefTransaction;
+    use crate::store::git::verify::{self, Error};
+    use crate::store::{Init, QueryStore};
+    let (repo, store) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (_file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let store = gix::ThreadSafeRepository::open(store.as_ref())?;
+    let jobs = NonZeroUsize::new(2).context("no jobs")?;
+    let outcomes = verify::verify_all(&store, jobs)?;
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].0.id.to_string(), "foo");
+    assert!(outcomes[0].1.is_ok());
+
+    // a version pointing to the Atom commit of another is not reproducible
+    let commit = outcomes[0].0.refs[ATOM];
+    let local = store.to_thread_local();
+    let mut tx = RefTransaction::new(&local);
+    tx.create("refs/atoms/bar/0.1.0/atom", commit, "test: atom")?;
+    tx.commit()?;
+
+    let outcomes = verify::verify_all(&store, jobs)?;
+    assert_eq!(outcomes.len(), 2);
+    assert!(matches!(outcomes[0].1, Err(Error::NotReproducible(_))));
+    assert!(outcomes[1].1.is_ok());
+    Ok(())
+}
+
+#[tokio::test]
+async fn lock_same_store_deps() -> Result<(), anyhow::Error> {
+    use crate::ObjectSum;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (_file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let manifest = |req: &str| {
+        crate::Manifest::from_str(&format!(
+            "[atom]\nid = \"bar\"\nversion = \"0.1.0\"\n\n[deps.atoms.foo]\nversion = \"{req}\"\n"
+        ))
+    };
+
+    let none = crate::Lockfile::default();
+    let locked = git::lock_same_store(&remote, &manifest("^0.1")?, &none)?;
+    let [foo] = &locked[..] else {
+        anyhow::bail!("expected a single locked atom, got {locked:?}");
+    };
+    let version = semver::Version::new(0, 1, 0);
+    let name = format!("refs/atoms/foo/{}/atom", git::encode_version(&version));
+    let rev = repo.find_reference(name.as_str())?.id().detach();
+    assert_eq!(foo.version, version);
+    assert_eq!(foo.rev, ObjectSum::from(rev));
+    assert_eq!(foo.store, None);
+    assert_eq!(foo.root, Some(ObjectSum::from(*remote.ekala_root()?)));
+
+    assert!(matches!(
+        git::lock_same_store(&remote, &manifest("^0.2")?, &none),
+        Err(git::Error::Unsatisfied(..))
+    ));
+
+    let lock = git::resolve(&remote, &manifest("^0.1")?, &none)?;
+    assert_eq!(lock.deps, vec![foo.id.clone()]);
+    assert_eq!(lock.atoms, locked);
+
+    // a yanked version is no longer selected, unless it is locked already
+    git::yank::Yank::plan(&repo, "origin", "foo", &version, false)?.apply(&remote)?;
+    assert!(matches!(
+        git::resolve(&remote, &manifest("^0.1")?, &none),
+        Err(git::Error::Unsatisfied(..))
+    ));
+    assert_eq!(git::resolve(&remote, &manifest("^0.1")?, &lock)?, lock);
+    Ok(())
+}
+
+#[tokio::test]
+async fn lock_from_mirror() -> Result<(), anyhow::Error> {
+    use crate::ObjectSum;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, remote_dir) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (_file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let mirror_dir = tempfile::tempdir()?;
+    let status = std::process::Command::new("git")
+        .args(["clone", "--mirror", "--quiet"])
+        .arg(remote_dir.path())
+        .arg(mirror_dir.path())
+        .status()?;
+    anyhow::ensure!(status.success(), "failed to mirror the store");
+
+    let primary = format!("file://{}", remote_dir.path().display());
+    let mirror = format!("file://{}", mirror_dir.path().display());
+    let gone = format!("file://{}", remote_dir.path().join("gone").display());
+    let manifest = |url: &str, mirror: &str| {
+        crate::Manifest::from_str(&format!(
+            "[atom]\nid = \"bar\"\nversion = \"0.1.0\"\n\n[deps.atoms.foo]\nversion = \
+             \"^0.1\"\nurl = \"{url}\"\nmirrors = [\"{mirror}\"]\n"
+        ))
+    };
+    let root = Some(ObjectSum::from(*remote.ekala_root()?));
+
+    // the store itself is preferred whenever it can be reached
+    let none = crate::Lockfile::default();
+    let locked = git::lock_other_stores(&repo, &manifest(&primary, &gone)?, &none)?;
+    let [foo] = &locked[..] else {
+        anyhow::bail!("expected a single locked atom, got {locked:?}");
+    };
+    assert_eq!(foo.store.as_deref(), Some(primary.as_str()));
+    assert_eq!(foo.root, root);
+
+    let mirrored = git::lock_other_stores(&repo, &manifest(&gone, &mirror)?, &none)?;
+    let [mirrored] = &mirrored[..] else {
+        anyhow::bail!("expected a single locked atom, got {mirrored:?}");
+    };
+    assert_eq!(mirrored.store.as_deref(), Some(mirror.as_str()));
+    assert_eq!((mirrored.rev, mirrored.root), (foo.rev, root));
+
+    // a mirror must match the store the lock pins the Atom from
+    let mut forged = foo.clone();
+    forged.root = Some(forged.rev);
+    let lock = crate::Lockfile {
+        atoms: vec![forged],
+        ..Default::default()
+    };
+    assert!(matches!(
+        git::lock_other_stores(&repo, &manifest(&gone, &mirror)?, &lock),
+        Err(git::Error::MirrorMismatch(..))
+    ));
+    assert!(git::lock_other_stores(&repo, &manifest(&gone, &gone)?, &none).is_err());
+
+    // the atoms of a lock are verified against the very store and commit it pins
+    let tree = git::verify_locked(&remote, &mirrored)?;
+    assert_eq!(git::verify_locked(&remote, foo)?, tree);
+    let own = crate::LockedAtom {
+        store: None,
+        ..foo.clone()
+    };
+    assert_eq!(git::verify_locked(&remote, &own)?, tree);
+    assert!(matches!(
+        git::verify_locked(&remote, &lock.atoms[0]),
+        Err(git::Error::LockedRoot(_))
+    ));
+    let moved = crate::LockedAtom {
+        rev: ObjectSum::from(gix::ObjectId::null(repo.object_hash())),
+        ..foo.clone()
+    };
+    assert!(matches!(
+        git::verify_locked(&remote, &moved),
+        Err(git::Error::LockedRev(..))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn publish_through_dyn_store() -> Result<(), anyhow::Error> {
+    use crate::store::git::backend::GitBackend;
+    use crate::store::{BoxError, EkalaStore, QueryStore};
+    fn boxed(e: BoxError) -> anyhow::Error {
+        anyhow::anyhow!(e)
+    }
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let path = file
+        .path()
+        .strip_prefix(repo.work_dir().context("No workdir")?)?
+        .to_path_buf();
+
+    let store: Box<dyn EkalaStore> = Box::new(GitBackend::new(repo.clone(), "origin".into()));
+    assert_eq!(store.name(), "origin");
+    assert_eq!(store.root_ref(), git::V1_ROOT);
+    assert!(!store.status().map_err(boxed)?.is_initialized());
+    store.init().map_err(boxed)?;
+    let status = store.status().map_err(boxed)?;
+    assert!(status.matches());
+    assert_eq!(status.remote, Some(store.root().map_err(boxed)?));
+    assert!(store.policy().map_err(boxed)?.is_none());
+
+    let outcomes = store
+        .publish("HEAD", vec![path.clone()])
+        .await
+        .map_err(boxed)?;
+    let [Ok(Ok(id))] = &outcomes[..] else {
+        anyhow::bail!("expected a single published atom");
+    };
+    assert_eq!(id.as_str(), "foo");
+    let published = store.published().map_err(boxed)?;
+    assert_eq!(
+        published.keys().map(|id| id.as_str()).collect::<Vec<_>>(),
+        ["foo"]
+    );
+    let atom = store.get_ref("refs/atoms/foo/0.1.0/atom").map_err(boxed)?;
+    assert_eq!(
+        atom,
+        repo.find_reference("refs/atoms/foo/0.1.0/atom")?
+            .id()
+            .to_string()
+    );
+
+    // publishing the same version again is safely skipped
+    let outcomes = store.publish("HEAD", vec![path]).await.map_err(boxed)?;
+    assert!(matches!(&outcomes[..], [Ok(Err(id))] if id.as_str() == "foo"));
+    Ok(())
+}
+
+#[test]
+fn migrate_legacy_refs() -> Result<(), anyhow::Error> {
+    use crate::store::git::migrate;
+    use crate::store::git::verify::Verifier;
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (_file, src) = repo.mock("foo", "0.1.0", "a legacy atom")?;
+    let tree = repo.find_commit(src)?.tree_id()?.detach();
+    let legacy = "refs/atom/foo-0.1.0";
+    repo.commit(legacy, "foo: 0.1.0", tree, vec![src])?;
+    git::run_git_command(&[
+        "-C",
+        repo.git_dir().to_string_lossy().as_ref(),
+        "push",
+        "origin",
+        format!("{legacy}:{legacy}").as_str(),
+    ])?;
+
+    let found = migrate::fetch_legacy(&repo, "origin")?;
+    let [(name, id)] = found.as_slice() else {
+        return Err(anyhow::anyhow!("expected a single legacy ref"));
+    };
+    assert_eq!(name, legacy);
+
+    let migration = migrate::prepare(&repo, name, *id)?;
+    assert_eq!(migration.origin, src);
+    migration.verify(&Verifier::new(&repo, None)?)?;
+    assert!(!migration.apply(&repo, "origin", true)?);
+
+    let (atom_ref, _) = &migration.refs()[0];
+    let atom: ObjectId = remote.get_ref(atom_ref.as_str())?;
+    assert_eq!(atom, migration.atom);
+    assert!(migrate::fetch_legacy(&repo, "origin")?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn confusable_ids() -> Result<(), anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    use crate::publish::Warning;
+    use crate::publish::error::git::Error;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    // the second id is spelled with cyrillic `р`, `а` and `у`
+    let mut entries = Vec::new();
+    for (name, id) in [
+        ("a", "paypal"),
+        ("b", "\u{440}\u{430}\u{443}\u{440}\u{430}l"),
+    ] {
+        let manifest = format!("[atom]\nid = \"{id}\"\nversion = \"0.1.0\"\n");
+        entries.push(Entry {
+            mode: EntryKind::Blob.into(),
+            filename: format!("{name}{}", crate::ATOM_EXT.as_str()).into(),
+            oid: repo.write_blob(manifest.as_bytes())?.detach(),
+        });
+    }
+    let tree = repo.write_object(Tree { entries })?;
+    let head = repo.head_id()?;
+    let head_ref = repo.head_ref()?.context("detached HEAD")?;
+    repo.commit(head_ref.name().as_bstr(), "confusable", tree, vec![head])?;
+
+    let result = GitPublisher::new(&repo, "origin", "HEAD")?.build();
+    let Err(Error::Duplicates(warnings)) = result else {
+        panic!("confusable ids were published");
+    };
+    assert!(matches!(
+        warnings.as_slice(),
+        [Warning::Confusable { fst_id, snd_id, .. }] if fst_id == "paypal" && snd_id != "paypal"
+    ));
+    Ok(())
+}
+
+#[test]
+fn version_history() -> Result<(), anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+    use semver::Version;
+
+    use crate::store::QueryStore;
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let filename = format!("a{}", crate::ATOM_EXT.as_str());
+    let mut commits = Vec::new();
+    for version in ["0.1.0", "0.1.0", "0.2.0", "0.1.0"] {
+        let manifest = format!("[atom]\nid = \"a\"\nversion = \"{version}\"\n");
+        let entry = Entry {
+            mode: EntryKind::Blob.into(),
+            filename: filename.as_str().into(),
+            oid: repo.write_blob(manifest.as_bytes())?.detach(),
+        };
+        let tree = repo.write_object(Tree {
+            entries: vec![entry],
+        })?;
+        let head = repo.head_id()?;
+        let head_ref = repo.head_ref()?.context("detached HEAD")?;
+        let commit = repo.commit(head_ref.name().as_bstr(), version, tree, vec![head])?;
+        commits.push(commit.detach());
+    }
+
+    // unchanged and reverted versions are attributed to the commit introducing them
+    let history = super::version_history(&repo, "HEAD", std::path::Path::new(&filename))?;
+    assert_eq!(
+        history,
+        [
+            (commits[0], Version::parse("0.1.0")?),
+            (commits[2], Version::parse("0.2.0")?)
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn path_dependencies() -> Result<(), anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    use crate::id::Id;
+    use crate::publish::error::git::Error;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let commit = |foo: &str| -> Result<(), anyhow::Error> {
+        let mut entries = Vec::new();
+        for (name, manifest) in [
+            ("bar", "[atom]\nid = \"bar\"\nversion = \"0.1.0\"\n"),
+            ("foo", foo),
+        ] {
+            entries.push(Entry {
+                mode: EntryKind::Blob.into(),
+                filename: format!("{name}{}", crate::ATOM_EXT.as_str()).into(),
+                oid: repo.write_blob(manifest.as_bytes())?.detach(),
+            });
+        }
+        let tree = repo.write_object(Tree { entries })?;
+        let head = repo.head_id()?;
+        let head_ref = repo.head_ref()?.context("detached HEAD")?;
+        repo.commit(head_ref.name().as_bstr(), "path deps", tree, vec![head])?;
+        Ok(())
+    };
+
+    commit(
+        r#"[atom]
+id = "foo"
+version = "0.1.0"
+
+[deps.atoms.bar]
+path = "bar"
+"#,
+    )?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    let path = |id: &str| -> Result<_, anyhow::Error> {
+        paths
+            .get(&Id::try_from(id)?)
+            .cloned()
+            .context("no such atom")
+    };
+
+    // dependencies are published first
+    let levels = publisher.levels(vec![path("foo")?, path("bar")?])?;
+    assert_eq!(levels, vec![vec![path("bar")?], vec![path("foo")?]]);
+    for paths in levels {
+        for outcome in publisher.publish(paths) {
+            assert!(matches!(outcome, Ok(Ok(_))));
+        }
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let (manifest, _) = git::fetch_spec(&remote, "foo", &semver::Version::new(0, 1, 0))?;
+    let bar = manifest.deps.atoms.values().next().context("no deps")?;
+    assert_eq!(bar.version, Some(semver::VersionReq::parse("^0.1.0")?));
+    assert_eq!(bar.path, None);
+
+    // the Atom at the path must satisfy the declared requirement
+    commit(
+        r#"[atom]
+id = "foo"
+version = "0.2.0"
+
+[deps.atoms.bar]
+version = "^0.2"
+path = "bar"
+"#,
+    )?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    let foo = paths.get(&Id::try_from("foo")?).context("no such atom")?;
+    assert!(matches!(
+        publisher.publish_atom(foo),
+        Err(Error::UnsatisfiedPathDependency { .. })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn workspace_inheritance() -> Result<(), anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    use crate::id::Id;
+    use crate::manifest::{AtomError, WORKSPACE_FILE, WorkspaceError};
+    use crate::publish::error::git::Error;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let workspace = r#"[atom]
+license = "MIT"
+
+[deps.atoms.bar]
+version = "^0.1"
+path = "bar"
+"#;
+    let foo = r#"[atom]
+id = "foo"
+version = "0.1.0"
+license.workspace = true
+
+[deps.atoms.bar]
+workspace = true
+"#;
+    let baz = "[atom]\nid = \"baz\"\nversion = \"0.1.0\"\ndescription.workspace = true\n";
+    let mut entries = vec![Entry {
+        mode: EntryKind::Blob.into(),
+        filename: WORKSPACE_FILE.into(),
+        oid: repo.write_blob(workspace.as_bytes())?.detach(),
+    }];
+    for (name, manifest) in [
+        ("bar", "[atom]\nid = \"bar\"\nversion = \"0.1.0\"\n"),
+        ("baz", baz),
+        ("foo", foo),
+    ] {
+        entries.push(Entry {
+            mode: EntryKind::Blob.into(),
+            filename: format!("{name}{}", crate::ATOM_EXT.as_str()).into(),
+            oid: repo.write_blob(manifest.as_bytes())?.detach(),
+        });
+    }
+    entries.sort();
+    let tree = repo.write_object(Tree { entries })?;
+    let head = repo.head_id()?;
+    let head_ref = repo.head_ref()?.context("detached HEAD")?;
+    repo.commit(head_ref.name().as_bstr(), "workspace", tree, vec![head])?;
+
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    let path = |id: &str| -> Result<_, anyhow::Error> {
+        paths
+            .get(&Id::try_from(id)?)
+            .cloned()
+            .context("no such atom")
+    };
+
+    // an Atom inheriting what the workspace does not declare is invalid
+    assert!(paths.get(&Id::try_from("baz")?).is_none());
+    assert!(matches!(
+        publisher.publish_atom("baz@.toml"),
+        Err(Error::Invalid(
+            AtomError::Workspace(WorkspaceError::Undeclared(_)),
+            _
+        ))
+    ));
+
+    // inherited path dependencies are published first, too
+    let levels = publisher.levels(vec![path("foo")?, path("bar")?])?;
+    assert_eq!(levels, vec![vec![path("bar")?], vec![path("foo")?]]);
+    for paths in levels {
+        for outcome in publisher.publish(paths) {
+            assert!(matches!(outcome, Ok(Ok(_))));
+        }
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    // the published spec holds what was inherited, independently of the workspace
+    let (manifest, _) = git::fetch_spec(&remote, "foo", &semver::Version::new(0, 1, 0))?;
+    assert_eq!(manifest.atom.license.as_deref(), Some("MIT"));
+    let bar = manifest.deps.atoms.values().next().context("no deps")?;
+    assert_eq!(bar.version, Some(semver::VersionReq::parse("^0.1")?));
+    assert_eq!(bar.path, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn exclude_content() -> Result<(), anyhow::Error> {
+    use std::num::NonZeroUsize;
+
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::git::verify;
+    use crate::store::{Init, QueryStore};
+    let (repo, store) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let blob = |name: &str| -> Result<Entry, anyhow::Error> {
+        Ok(Entry {
+            mode: EntryKind::Blob.into(),
+            filename: name.into(),
+            oid: repo.write_blob(name.as_bytes())?.detach(),
+        })
+    };
+    let tree = |name: &str, mut entries: Vec<Entry>| -> Result<Entry, anyhow::Error> {
+        entries.sort();
+        Ok(Entry {
+            mode: EntryKind::Tree.into(),
+            filename: name.into(),
+            oid: repo.write_object(Tree { entries })?.detach(),
+        })
+    };
+    let manifest = r#"[atom]
+id = "foo"
+version = "0.1.0"
+exclude = ["fixtures", "*.png"]
+"#;
+    let content = tree(
+        "foo",
+        vec![
+            blob("lib.nix")?,
+            blob("logo.png")?,
+            tree("fixtures", vec![blob("data.json")?])?,
+            tree("assets", vec![blob("icon.png")?])?,
+            tree("src", vec![blob("main.nix")?, blob("fixtures")?])?,
+        ],
+    )?;
+    let mut entries = vec![
+        content,
+        Entry {
+            mode: EntryKind::Blob.into(),
+            filename: format!("foo{}", crate::ATOM_EXT.as_str()).into(),
+            oid: repo.write_blob(manifest.as_bytes())?.detach(),
+        },
+    ];
+    entries.sort();
+    let root = repo.write_object(Tree { entries })?;
+    let head = repo.head_id()?;
+    let head_ref = repo.head_ref()?.context("detached HEAD")?;
+    repo.commit(head_ref.name().as_bstr(), "exclude", root, vec![head])?;
+
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    // the excluded paths, and the directories left empty, are left out of the content
+    let atom = repo
+        .find_reference("refs/atoms/foo/0.1.0/atom")?
+        .into_fully_peeled_id()?;
+    let tree = repo.find_commit(atom)?.tree()?;
+    let content = tree
+        .find_entry("foo")
+        .context("no content")?
+        .object()?
+        .into_tree();
+    let names: Vec<_> = content
+        .decode()?
+        .entries
+        .iter()
+        .map(|e| e.filename.to_string())
+        .collect();
+    assert_eq!(names, ["lib.nix", "src"]);
+    let src = content
+        .find_entry("src")
+        .context("no src")?
+        .object()?
+        .into_tree();
+    assert_eq!(src.decode()?.entries.len(), 1);
+
+    // the filtered content is recomputed from the source by the store
+    let store = gix::ThreadSafeRepository::open(store.as_ref())?;
+    let jobs = NonZeroUsize::new(1).context("no jobs")?;
+    let outcomes = verify::verify_all(&store, jobs)?;
+    assert_eq!(outcomes.len(), 1);
+    assert!(outcomes[0].1.is_ok());
+    Ok(())
+}
+
+#[tokio::test]
+async fn include_content() -> Result<(), anyhow::Error> {
+    use std::num::NonZeroUsize;
+
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    use crate::id::Id;
+    use crate::publish::error::git::Error;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::git::verify;
+    use crate::store::{Init, QueryStore};
+    let (repo, store) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let blob = |name: &str, data: &str| -> Result<Entry, anyhow::Error> {
+        Ok(Entry {
+            mode: EntryKind::Blob.into(),
+            filename: name.into(),
+            oid: repo.write_blob(data.as_bytes())?.detach(),
+        })
+    };
+    let tree = |name: &str, mut entries: Vec<Entry>| -> Result<Entry, anyhow::Error> {
+        entries.sort();
+        Ok(Entry {
+            mode: EntryKind::Tree.into(),
+            filename: name.into(),
+            oid: repo.write_object(Tree { entries })?.detach(),
+        })
+    };
+    let manifest = |id: &str, include: &str| {
+        format!("[atom]\nid = \"{id}\"\nversion = \"0.1.0\"\ninclude = {include}\n")
+    };
+    let spec = |id: &str| format!("{id}{}", crate::ATOM_EXT.as_str());
+    let atoms = tree(
+        "atoms",
+        vec![
+            blob(
+                &spec("foo"),
+                &manifest("foo", r#"["../common/lib.nix", "../LICENSE"]"#),
+            )?,
+            tree("foo", vec![blob("default.nix", "{}")?])?,
+            blob(&spec("bar"), &manifest("bar", r#"["../common"]"#))?,
+            blob(&spec("baz"), &manifest("baz", r#"["../../outside"]"#))?,
+        ],
+    )?;
+    let common = tree("common", vec![blob("lib.nix", "{ lib }")?])?;
+    let mut entries = vec![atoms, common, blob("LICENSE", "MIT")?];
+    entries.sort();
+    let root = repo.write_object(Tree { entries })?;
+    let head = repo.head_id()?;
+    let head_ref = repo.head_ref()?.context("detached HEAD")?;
+    repo.commit(head_ref.name().as_bstr(), "include", root, vec![head])?;
+
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    let path = |id: &str| -> Result<_, anyhow::Error> {
+        paths
+            .get(&Id::try_from(id)?)
+            .cloned()
+            .context("no such atom")
+    };
+
+    // a path outside of the repository cannot be included
+    assert!(matches!(
+        publisher.publish_atom(path("baz")?),
+        Err(Error::StoreError(git::Error::IncludeOutside(_)))
+    ));
+    for outcome in publisher.publish(vec![path("foo")?, path("bar")?]) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    // included paths are grafted under their path in the repository, even into an Atom with
+    // no content of its own
+    let content = |id: &str, path: &str| -> Result<_, anyhow::Error> {
+        let atom = repo
+            .find_reference(format!("refs/atoms/{id}/0.1.0/atom").as_str())?
+            .into_fully_peeled_id()?;
+        let tree = repo.find_commit(atom)?.tree()?;
+        let mut buf = Vec::new();
+        let found = tree.lookup_entry_by_path(format!("{id}/{path}"), &mut buf)?;
+        Ok(found.map(|e| e.object_id()))
+    };
+    let lib = content("foo", "_include/common/lib.nix")?;
+    assert!(lib.is_some());
+    assert!(content("foo", "_include/LICENSE")?.is_some());
+    assert!(content("foo", "default.nix")?.is_some());
+    assert_eq!(content("bar", "_include/common/lib.nix")?, lib);
+
+    // the grafted content is recomputed from the source by the store
+    let store = gix::ThreadSafeRepository::open(store.as_ref())?;
+    let jobs = NonZeroUsize::new(1).context("no jobs")?;
+    let outcomes = verify::verify_all(&store, jobs)?;
+    assert_eq!(outcomes.len(), 2);
+    assert!(outcomes.iter().all(|(_, outcome)| outcome.is_ok()));
+    Ok(())
+}
+
+#[test]
+fn custom_kinds() -> Result<(), anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    use crate::id::Id;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    use crate::{Kind, KindRegistry};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let manifest = r#"[atom]
+id = "foo"
+version = "0.1.0"
+trait = "service"
+"#;
+    let entries = vec![Entry {
+        mode: EntryKind::Blob.into(),
+        filename: format!("foo{}", crate::ATOM_EXT.as_str()).into(),
+        oid: repo.write_blob(manifest.as_bytes())?.detach(),
+    }];
+    let root = repo.write_object(Tree { entries })?;
+    let head = repo.head_id()?;
+    let head_ref = repo.head_ref()?.context("detached HEAD")?;
+    repo.commit(head_ref.name().as_bstr(), "service", root, vec![head])?;
+
+    // only the builtin kinds are known by default
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    assert!(paths.is_empty());
+    assert_eq!(publisher.take_warnings().len(), 1);
+
+    // a registered kind may be declared
+    let kinds = KindRegistry::default().register(Kind::from_str("service")?);
+    let (paths, _) = GitPublisher::new(&repo, "origin", "HEAD")?
+        .kinds(kinds)
+        .build()?;
+    assert!(paths.contains_key(&Id::try_from("foo")?));
+    Ok(())
+}
+
+#[tokio::test]
+async fn incremental_publish() -> Result<(), anyhow::Error> {
+    use crate::id::Id;
+    use crate::publish::git::cache::{CACHE_FILE, Cache};
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let id = Id::try_from("foo")?;
+    repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?
+        .incremental(true)
+        .build()?;
+    let path = paths.get(&id).context("no such atom")?;
+    assert!(matches!(publisher.publish_atom(path), Ok(Ok(_))));
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    // the manifest is recorded as valid, and the Atom as published from its source
+    let manifest = publisher.tree_search(path)?.context("no manifest")?;
+    let source = publisher.source(path)?.context("not incremental")?;
+    let cache: Cache = toml_edit::de::from_str(&std::fs::read_to_string(
+        repo.common_dir().join(CACHE_FILE),
+    )?)?;
+    assert_eq!(cache.valid(manifest.object_id()), Some(id.clone()));
+    assert!(cache.published(source).is_some());
+
+    // the unchanged Atom is skipped before it is even looked at again
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?
+        .incremental(true)
+        .build()?;
+    assert_eq!(paths.get(&id), Some(path));
+    assert_eq!(publisher.unchanged(source), Some(id.clone()));
+    assert!(matches!(publisher.publish_atom(path), Ok(Err(skipped)) if skipped == id));
+
+    // unless the Atom is no longer published under the recorded ref
+    let published = cache.published(source).context("not published")?;
+    repo.find_reference(published.name.as_str())?.delete()?;
+    assert_eq!(publisher.unchanged(source), None);
+
+    // nor does the cache hold under other settings
+    let lints = crate::Linter::new(&config::LintConfig {
+        levels: config::LintLevels {
+            unstable_version: config::LintLevel::Warn,
+            ..Default::default()
+        },
+        ..Default::default()
+    })?;
+    let (_, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?
+        .incremental(true)
+        .lints(lints)
+        .build()?;
+    assert_eq!(publisher.unchanged(source), None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn snapshot_publish() -> Result<(), anyhow::Error> {
+    use crate::id::Id;
+    use crate::publish::error::git::Error;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let git_dir = repo.git_dir().to_string_lossy().to_string();
+    let git = |args: &[&str]| git::run_git_command(&[&["-C", git_dir.as_str()][..], args].concat());
+    let prefix = format!("refs/{}/foo", crate::publish::ATOM_REF_TOP_LEVEL);
+    let taken = format!("{prefix}/0.1.0/atom");
+
+    repo.mock("foo", "0.1.0", "some atom")?;
+    let snapshot = git::Snapshot::take(&repo, "origin")?;
+    assert!(snapshot.published().is_empty());
+
+    // someone else publishes another Atom under the same version after the publish was planned
+    git(&["push", "origin", &format!("HEAD:{taken}")])?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?
+        .snapshot(snapshot)
+        .build()?;
+    let path = paths.get(&Id::try_from("foo")?).context("no such atom")?;
+    assert!(matches!(publisher.publish_atom(path), Ok(Ok(_))));
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0], Error::Concurrent { id, .. } if id == "foo"));
+
+    // a snapshot holding a ref the remote no longer has is planned again, only for that ref,
+    // while the refs it holds identically are not pushed again
+    let snapshot = git::Snapshot::take(&repo, "origin")?;
+    assert!(snapshot.get(&taken).is_some());
+    git(&["push", "origin", &format!(":{taken}")])?;
+    let names: Vec<_> = repo
+        .references()?
+        .prefixed(prefix.as_str())?
+        .filter_map(Result::ok)
+        .map(|r| r.name().as_bstr().to_string())
+        .collect();
+    for name in names {
+        repo.find_reference(name.as_str())?.delete()?;
+    }
+
+    let (_, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?
+        .snapshot(snapshot)
+        .build()?;
+    assert!(matches!(publisher.publish_atom(path), Ok(Ok(_))));
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+    let local = repo.find_reference(taken.as_str())?.id().detach();
+    assert_eq!(
+        git::advertised(repo.git_dir(), "origin", &taken)?,
+        Some(local)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn planned_commits() -> Result<(), anyhow::Error> {
+    use crate::id::Id;
+    use crate::publish::git::plan::PlannedAtom;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    let path = paths.get(&Id::try_from("foo")?).context("no such atom")?;
+
+    // the plan is computed without writing anything
+    let plan = publisher.plan([path.clone()]).remove(0)?;
+    let planned = PlannedAtom::new(&plan, 0);
+    let [atom, ..] = plan.refs();
+    assert!(repo.try_find_reference(atom.as_str())?.is_none());
+
+    assert!(matches!(publisher.publish_atom(path), Ok(Ok(_))));
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    // the Atom commit is published exactly as planned
+    let published = repo.find_reference(atom.as_str())?.id().detach();
+    assert_eq!(published, plan.commit());
+    planned.check(&publisher.plan([path.clone()]).remove(0)?)?;
+
+    // while a plan of another version is stale
+    let mut stale = planned.clone();
+    stale.version = semver::Version::new(0, 2, 0);
+    assert!(matches!(
+        stale.check(&plan),
+        Err(crate::publish::error::git::Error::StalePlan { .. })
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "fault-injection")]
+#[tokio::test]
+async fn injected_faults() -> Result<(), anyhow::Error> {
+    use crate::id::Id;
+    use crate::publish::error::git::Error;
+    use crate::publish::git::{Builder, GitPublisher};
+    use crate::store::git::fault::{Fault, Injection};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    // the remote hangs up once, and answers again when asked a second time
+    let armed = Injection::new(Fault::Disconnect).times(1).arm(&repo);
+    match remote.get_ref("HEAD") {
+        Err(git::Error::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::ConnectionReset),
+        other => panic!("expected a disconnect, got {other:?}"),
+    }
+    remote.get_ref("HEAD")?;
+    assert_eq!(armed.tripped(), 1);
+    drop(armed);
+
+    let (_file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    let path = paths.get(&Id::try_from("foo")?).context("no such atom")?;
+    let prefix = format!("refs/{}/foo", crate::publish::ATOM_REF_TOP_LEVEL);
+    let local_refs = || -> Result<usize, anyhow::Error> {
+        Ok(repo.references()?.prefixed(prefix.as_str())?.count())
+    };
+
+    // contention on one of the Atom's refs leaves none of them written, and is reported as a
+    // concurrent publish
+    let armed = Injection::new(Fault::LockContention).arm(&repo);
+    assert!(matches!(
+        publisher.publish_atom(path),
+        Err(Error::Concurrent { .. })
+    ));
+    assert_eq!(armed.tripped(), 1);
+    assert_eq!(local_refs()?, 0);
+    drop(armed);
+
+    // a push rejected after the refs were written locally is reported, and only it fails
+    let armed = Injection::new(Fault::RejectPush)
+        .after(1)
+        .times(1)
+        .arm(&repo);
+    assert!(matches!(publisher.publish_atom(path), Ok(Ok(_))));
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains("remote rejected"));
+    assert_eq!(armed.tripped(), 1);
+    assert_eq!(local_refs()?, 3);
     Ok(())
 }
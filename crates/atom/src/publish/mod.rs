@@ -1,20 +1,26 @@
 //! # Atom Publishing
 //!
 //! This module provides the types and logic necessary to efficienctly publish Atoms
-//! to a store implementation. Currently, only a Git store is implemented, but future
-//! work will likely include more alternate backends, e.g. an S3 bucket.
+//! to a store implementation. Atoms are always published from a Git repository, either to
+//! one of its remotes, or to an S3-compatible object store.
 pub mod error;
 #[cfg(feature = "git")]
 pub mod git;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod warning;
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 #[cfg(feature = "git")]
 use git::GitContent;
+#[cfg(feature = "s3")]
+use s3::S3Content;
 
 use crate::AtomId;
 use crate::id::Id;
+pub use crate::publish::warning::{Warning, Warnings};
 
 /// The results of Atom publishing, for reporting to the user.
 pub struct Record<R> {
@@ -22,17 +28,6 @@ pub struct Record<R> {
     content: Content,
 }
 
-/// Basic statistics collected during a publishing request.
-#[derive(Default)]
-pub struct Stats {
-    /// How many Atoms were actually published.
-    pub published: u32,
-    /// How many Atoms were safely skipped because they already existed.
-    pub skipped: u32,
-    /// How many Atoms failed to publish due to some error condition.
-    pub failed: u32,
-}
-
 /// A Result is used over an Option here mainly so we can report which
 /// Atom was skipped, but it does not represent a true failure condition
 type MaybeSkipped<T> = Result<T, Id>;
@@ -49,6 +44,9 @@ pub enum Content {
     #[cfg(feature = "git")]
     /// Content specific to the Git implementation.
     Git(GitContent),
+    #[cfg(feature = "s3")]
+    /// Content specific to the S3 implementation.
+    S3(S3Content),
 }
 
 /// A [`Builder`] produces a [`Publish`] implementation, which has no other constructor.
@@ -135,8 +133,8 @@ impl<R> Record<R> {
 }
 
 const EMPTY_SIG: &str = "";
-const ATOM: &str = "atom";
-const ATOM_FORMAT_VERSION: &str = "1";
-const ATOM_REF_TOP_LEVEL: &str = "atoms";
-const ATOM_MANIFEST: &str = "spec";
-const ATOM_ORIGIN: &str = "src";
+pub(crate) const ATOM: &str = "atom";
+pub(crate) const ATOM_FORMAT_VERSION: &str = "1";
+pub(crate) const ATOM_REF_TOP_LEVEL: &str = "atoms";
+pub(crate) const ATOM_MANIFEST: &str = "spec";
+pub(crate) const ATOM_ORIGIN: &str = "src";
@@ -0,0 +1,257 @@
+//! # Atom Publishing for an S3 Store
+//!
+//! Atoms are found and validated in the Git repository they are published from, exactly as
+//! for a Git store, but are then uploaded to an [`S3Store`] rather than pushed: the content as
+//! a deterministic tarball, and the manifest and lock as they are, each stored under its own
+//! hash, followed by the refs of the version pointing to them.
+//!
+//! The refs are only ever created, never replaced: each is written on the condition that it does
+//! not exist yet, so that of concurrent publishes of the same version, the first to write a ref
+//! claims the version, while the others fail with a conflict, or are skipped if they publish the
+//! very same content. A ref left behind by an interrupted publish of the same revision is reused.
+//!
+//! The policy and hooks of a Git store have no counterpart in an object store, so only the
+//! organization's policy committed to the repository applies.
+use std::path::{Path, PathBuf};
+
+use gix::{ObjectId, Repository};
+
+use super::error::s3::Error;
+use super::git::{GitContext, GitPublisher};
+use super::{
+    ATOM, ATOM_MANIFEST, ATOM_ORIGIN, ATOM_REF_TOP_LEVEL, Builder, Content, Publish,
+    PublishOutcome, Record, ValidAtoms,
+};
+use crate::ObjectSum;
+use crate::manifest::{KindRegistry, Linter};
+use crate::store::QueryStore;
+use crate::store::git::{Root, archive, encode_version};
+use crate::store::s3::{S3Remote, S3Store};
+
+/// The Outcome of an Atom publish attempt to an S3 store.
+pub type S3Outcome = PublishOutcome<Root>;
+/// The Result type used for various methods during publishing to an S3 store.
+pub type S3Result<T> = Result<T, Error>;
+
+/// The name of the ref recording the hash of an Atom's lock, if it has one.
+const ATOM_LOCK: &str = "lock";
+
+/// The type representing an S3 specific Atom publisher.
+#[derive(Clone)]
+pub struct S3Publisher<'a> {
+    git: GitPublisher<'a>,
+    store: &'a S3Store,
+}
+
+/// Holds the shared context needed for publishing Atoms to an S3 store.
+pub struct S3Context<'a> {
+    git: GitContext<'a>,
+    store: &'a S3Store,
+}
+
+/// The S3 specific content which will be returned for presenting to the user after an Atom
+/// is successfully published.
+#[derive(Debug)]
+pub struct S3Content {
+    content: ObjectSum,
+    path: PathBuf,
+    ref_prefix: String,
+}
+
+impl<'a> S3Publisher<'a> {
+    /// Constructs a new [`S3Publisher`], publishing from `repo` to `store`, which must be
+    /// initialized with the root of its history.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the store is not initialized, or its Atoms cannot
+    /// be listed.
+    pub fn new(repo: &'a Repository, store: &'a S3Store, spec: &'a str) -> S3Result<Self> {
+        use crate::store::Init;
+
+        let root = S3Remote::new(repo, store).ekala_root()?;
+        let git = GitPublisher::with_root(repo, root, spec).published(store.published()?);
+        Ok(S3Publisher { git, store })
+    }
+
+    /// Check manifests against the given lints, refusing those violating any at the `error`
+    /// level as invalid, and warning about the others when their Atom is published.
+    #[must_use]
+    pub fn lints(mut self, linter: Linter) -> Self {
+        self.git = self.git.lints(linter);
+        self
+    }
+
+    /// Validate Atom manifests against the given registry of kinds, as for
+    /// [`GitPublisher::kinds`].
+    #[must_use]
+    pub fn kinds(mut self, kinds: KindRegistry) -> Self {
+        self.git = self.git.kinds(kinds);
+        self
+    }
+
+    /// Interpret Atom paths as already relative to the repository root, normalizing them
+    /// lexically, as for [`GitPublisher::lexical`].
+    #[must_use]
+    pub fn lexical(mut self, lexical: bool) -> Self {
+        self.git = self.git.lexical(lexical);
+        self
+    }
+
+    /// Interpret relative Atom paths from the given directory, rather than the current
+    /// working directory of the process.
+    #[must_use]
+    pub fn current_dir(mut self, cwd: &'a Path) -> Self {
+        self.git = self.git.current_dir(cwd);
+        self
+    }
+
+    /// Treat any invalid Atom manifest encountered during validation as a hard failure,
+    /// rather than warning and skipping it.
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.git = self.git.strict(strict);
+        self
+    }
+}
+
+impl<'a> Builder<'a, Root> for S3Publisher<'a> {
+    type Error = Error;
+    type Publisher = S3Context<'a>;
+
+    fn build(&self) -> Result<(ValidAtoms, Self::Publisher), Self::Error> {
+        let (atoms, git) = self.git.build()?;
+        let publisher = S3Context {
+            git,
+            store: self.store,
+        };
+        Ok((atoms, publisher))
+    }
+}
+
+impl S3Context<'_> {
+    /// Take the warnings collected so far, e.g. while validating and publishing, leaving none
+    /// behind.
+    pub fn take_warnings(&self) -> super::Warnings {
+        self.git.take_warnings()
+    }
+
+    /// Group the Atoms at the given paths into levels, such that every Atom depends by path
+    /// only on Atoms in earlier levels, as for [`GitContext::levels`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a path cannot be normalized, or the path
+    /// dependencies among the Atoms form a cycle.
+    pub fn levels(&self, paths: Vec<PathBuf>) -> S3Result<Vec<Vec<PathBuf>>> {
+        Ok(self.git.levels(paths)?)
+    }
+
+    /// Create the ref `name` with the given value, or accept it if it already holds it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the ref holds another value, or a request fails.
+    fn claim(&self, name: &str, value: &str) -> S3Result<()> {
+        if self.store.create(name, value.as_bytes())? {
+            return Ok(());
+        }
+        match self.store.get_ref(name)? {
+            existing if existing == value => Ok(()),
+            existing => Err(Error::Conflict(name.to_owned(), existing)),
+        }
+    }
+}
+
+impl S3Content {
+    /// Return the hash of the tarball of the Atom's content.
+    #[must_use]
+    pub fn content(&self) -> &ObjectSum {
+        &self.content
+    }
+
+    /// Return a reference to the path to the Atom.
+    #[must_use]
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return a reference to the atom ref prefix.
+    #[must_use]
+    pub fn ref_prefix(&self) -> &String {
+        &self.ref_prefix
+    }
+}
+
+impl super::private::Sealed for S3Context<'_> {}
+
+impl Publish<Root> for S3Context<'_> {
+    type Error = Error;
+
+    /// Publishes atoms, normalizing their paths exactly as [`GitContext`] does.
+    fn publish<C>(&self, paths: C) -> Vec<S3Result<S3Outcome>>
+    where
+        C: IntoIterator<Item = PathBuf>,
+    {
+        paths
+            .into_iter()
+            .map(|path| self.publish_atom(self.git.normalize_path(path)?))
+            .collect()
+    }
+
+    #[tracing::instrument(level = "trace", skip_all, fields(path = %path.as_ref().display()))]
+    fn publish_atom<P: AsRef<Path>>(&self, path: P) -> S3Result<S3Outcome> {
+        use {Err as Skipped, Ok as Published};
+
+        let atom = self.git.stage(path.as_ref())?;
+        let ref_prefix = format!("{ATOM_REF_TOP_LEVEL}/{}", atom.id.id());
+        let refs = format!("refs/{ref_prefix}/{}", encode_version(&atom.spec.version));
+        let content_ref = format!("{refs}/{ATOM}");
+        if self.store.exists(&content_ref)? {
+            return Ok(Skipped(atom.spec.id));
+        }
+
+        // an Atom without content is published as an empty archive
+        let tree = match atom.content {
+            Some(tree) => tree,
+            None => ObjectId::empty_tree(atom.repo.object_hash()),
+        };
+        let mut tarball = Vec::new();
+        archive::export(atom.repo, tree, archive::Format::Tar, &mut tarball)?;
+
+        let content = self.store.put_object(&tarball)?;
+        let manifest = self.store.put_object(&atom.manifest)?;
+        // the origin is claimed first, so that refs left behind by an interrupted publish of the
+        // same revision are reused, while those of any other are never mixed with these
+        self.claim(&format!("{refs}/{ATOM_ORIGIN}"), &atom.origin.to_string())?;
+        self.claim(&format!("{refs}/{ATOM_MANIFEST}"), &manifest.to_string())?;
+        let lock_ref = format!("{refs}/{ATOM_LOCK}");
+        match &atom.lock {
+            Some(lock) => {
+                let lock = self.store.put_object(lock)?;
+                self.claim(&lock_ref, &lock.to_string())?;
+            },
+            // a lock left behind by an interrupted publish must not be taken for this one's
+            None => self.store.delete(&lock_ref)?,
+        }
+        // the version is listed as published once its content ref exists, so it comes last
+        let sum = content.to_string();
+        if !self.store.create(&content_ref, sum.as_bytes())? {
+            let existing = self.store.get_ref(&content_ref)?;
+            if existing == sum {
+                return Ok(Skipped(atom.spec.id));
+            }
+            return Err(Error::Conflict(content_ref, existing));
+        }
+        tracing::debug!(message = "published atom", store = %self.store.url(), refs = %refs);
+
+        Ok(Published(Record {
+            id: atom.id,
+            content: Content::S3(S3Content {
+                content,
+                path: atom.path,
+                ref_prefix,
+            }),
+        }))
+    }
+}
@@ -0,0 +1,100 @@
+//! # Publishing Warnings
+//!
+//! Conditions encountered during publishing which are worth reporting, but which did not
+//! prevent it, are collected as typed [`Warning`]s and returned to the caller, rather than
+//! being logged as a side effect, so that the caller decides how, and whether, to render them.
+use std::path::PathBuf;
+
+use semver::{Version, VersionReq};
+use thiserror::Error;
+
+/// A buffer of the warnings collected while publishing, in the order they were encountered.
+pub type Warnings = Vec<Warning>;
+
+/// A condition worth reporting to the user, which did not prevent publishing.
+#[derive(Error, Debug)]
+pub enum Warning {
+    /// An Atom was skipped, as it could not be published.
+    #[cfg(feature = "git")]
+    #[error(transparent)]
+    Skipped(super::error::git::Error),
+    /// An Atom was skipped, as its path is not valid UTF-8.
+    #[error("Ignoring Atom with a path which is not valid UTF-8: `{0}`")]
+    NonUtf8Path(String),
+    /// The paths of two Atoms differ only by case, so they cannot be checked out together on
+    /// a case-insensitive filesystem.
+    #[error("The paths of two Atoms differ only by case: `{}` and `{}`", .0.display(), .1.display())]
+    CaseCollision(PathBuf, PathBuf),
+    /// Two Atoms share the same id.
+    #[error("Two Atoms share the id `{id}`: `{}` and `{}`", .fst.display(), .snd.display())]
+    DuplicateId {
+        /// The shared id.
+        id: String,
+        /// The path of the Atom found first.
+        fst: PathBuf,
+        /// The path of the Atom found second.
+        snd: PathBuf,
+    },
+    /// The ids of two Atoms are indistinguishable to a human.
+    #[error("The ids of two Atoms are confusable: `{fst_id}` at `{}` and `{snd_id}` at `{}`", .fst.display(), .snd.display())]
+    Confusable {
+        /// The id of the Atom found first.
+        fst_id: String,
+        /// The path of the Atom found first.
+        fst: PathBuf,
+        /// The id of the Atom found second.
+        snd_id: String,
+        /// The path of the Atom found second.
+        snd: PathBuf,
+    },
+    /// An Atom owned by another team is published, as the store's policy only warns about it.
+    #[error("Publishing `{id}`, which is owned by {owners}")]
+    Trespass {
+        /// The id of the Atom.
+        id: String,
+        /// The names of the owning teams.
+        owners: String,
+    },
+    /// An Atom violating a lint configured at the `warn` level is published.
+    #[error(transparent)]
+    Lint(crate::manifest::Violation),
+    /// The store responded to the push of an Atom with a message, e.g. about its size.
+    #[cfg(feature = "git")]
+    #[error("The store responded to the push of `{id}`: {notice}")]
+    Notice {
+        /// The id of the Atom.
+        id: String,
+        /// The message of the store.
+        notice: crate::store::git::Notice,
+    },
+    /// A protected Atom version is published, as protection was explicitly overridden.
+    #[error("Publishing `{id}` version {version}, protected as `{protected}` by the store")]
+    Protected {
+        /// The id of the Atom.
+        id: String,
+        /// The protected version.
+        version: Version,
+        /// The protected versions, as declared by the store's policy.
+        protected: VersionReq,
+    },
+}
+
+impl Warning {
+    /// Return the stable code identifying the kind of warning.
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "git")]
+            Warning::Skipped(_) => "skipped",
+            Warning::NonUtf8Path(_) => "non-utf8-path",
+            Warning::CaseCollision(..) => "case-collision",
+            Warning::DuplicateId { .. } => "duplicate-id",
+            Warning::Confusable { .. } => "confusable",
+            Warning::Trespass { .. } => "trespass",
+            Warning::Lint(_) => "lint",
+            #[cfg(feature = "git")]
+            Warning::Notice { .. } => "notice",
+            Warning::Protected { .. } => "protected",
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! # Reports
+//!
+//! A command acting on many Atoms at once, e.g. publishing, verifying or checking them, has
+//! more to say than its results: how many Atoms ended each way, and the conditions worth
+//! raising along the way, from mere notes to outright errors. A [`Report`] collects both, as
+//! tallies and typed [`Diagnostic`]s, so that every such command reports them alike, and the
+//! caller decides how, and whether, to render them.
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use crate::publish::Warning;
+
+/// How serious a [`Diagnostic`] is, in increasing order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing, but calls for no action.
+    Note,
+    /// Did not prevent the command, but likely calls for action.
+    Warning,
+    /// Prevented the command, at least in part.
+    Error,
+}
+
+/// A condition raised while acting on some Atoms, identified by a stable code, e.g. for
+/// filtering by machines, along with a message for humans.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: &'static str,
+    message: String,
+    subject: Option<String>,
+}
+
+/// The tallies of the outcomes of a command, and the diagnostics it raised, in the order they
+/// were encountered.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Report {
+    tallies: Vec<(&'static str, usize)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Severity {
+    /// The name of the severity, as rendered for machines.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Diagnostic {
+    /// Constructs a new [`Diagnostic`] of the given severity, identified by `code`.
+    pub fn new(severity: Severity, code: &'static str, message: impl fmt::Display) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            message: message.to_string(),
+            subject: None,
+        }
+    }
+
+    /// Constructs a new note, identified by `code`.
+    pub fn note(code: &'static str, message: impl fmt::Display) -> Self {
+        Diagnostic::new(Severity::Note, code, message)
+    }
+
+    /// Constructs a new warning, identified by `code`.
+    pub fn warning(code: &'static str, message: impl fmt::Display) -> Self {
+        Diagnostic::new(Severity::Warning, code, message)
+    }
+
+    /// Constructs a new error, identified by `code`.
+    pub fn error(code: &'static str, message: impl fmt::Display) -> Self {
+        Diagnostic::new(Severity::Error, code, message)
+    }
+
+    /// Name what the diagnostic is about, e.g. the id of an Atom or the path of a manifest.
+    #[must_use]
+    pub fn about(mut self, subject: impl fmt::Display) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// Return how serious the diagnostic is.
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Return the stable code identifying the kind of diagnostic.
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Return the message of the diagnostic, for humans.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Return what the diagnostic is about, if it names anything.
+    #[must_use]
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Report {
+    /// Count one more outcome of the given kind, e.g. `published`.
+    pub fn count(&mut self, outcome: &'static str) {
+        match self.tallies.iter_mut().find(|(kind, _)| *kind == outcome) {
+            Some((_, n)) => *n += 1,
+            None => self.tallies.push((outcome, 1)),
+        }
+    }
+
+    /// Return how many outcomes of the given kind were counted.
+    #[must_use]
+    pub fn tally(&self, outcome: &str) -> usize {
+        self.tallies
+            .iter()
+            .find_map(|(kind, n)| (*kind == outcome).then_some(*n))
+            .unwrap_or_default()
+    }
+
+    /// Return the tallies of each kind of outcome counted, in the order they were first
+    /// counted.
+    #[must_use]
+    pub fn tallies(&self) -> &[(&'static str, usize)] {
+        &self.tallies
+    }
+
+    /// Raise a diagnostic.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Return the diagnostics raised, in the order they were raised.
+    #[must_use]
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Return how many diagnostics of the given severity were raised.
+    #[must_use]
+    pub fn raised(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+
+    /// Return the severity of the most serious diagnostic raised, if any was.
+    #[must_use]
+    pub fn worst(&self) -> Option<Severity> {
+        self.diagnostics.iter().map(|d| d.severity).max()
+    }
+}
+
+impl Extend<Diagnostic> for Report {
+    fn extend<T: IntoIterator<Item = Diagnostic>>(&mut self, iter: T) {
+        self.diagnostics.extend(iter);
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        let diagnostic = Diagnostic::warning(warning.code(), warning);
+        match warning {
+            Warning::Trespass { id, .. } | Warning::Protected { id, .. } => diagnostic.about(id),
+            #[cfg(feature = "git")]
+            Warning::Notice { id, .. } => diagnostic.about(id),
+            _ => diagnostic,
+        }
+    }
+}
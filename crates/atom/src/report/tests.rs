@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use super::*;
+
+#[test]
+fn tallies_in_order() {
+    let mut report = Report::default();
+    for outcome in ["published", "skipped", "published", "failed"] {
+        report.count(outcome);
+    }
+    assert_eq!(
+        report.tallies(),
+        [("published", 2), ("skipped", 1), ("failed", 1)]
+    );
+    assert_eq!(report.tally("skipped"), 1);
+    assert_eq!(report.tally("verified"), 0);
+}
+
+#[test]
+fn worst_severity() {
+    let mut report = Report::default();
+    assert_eq!(report.worst(), None);
+    report.push(Diagnostic::note("unlocked", "not locked to any hash").about("nixpkgs"));
+    assert_eq!(report.worst(), Some(Severity::Note));
+    report.extend([
+        Diagnostic::error("policy", "no members"),
+        Diagnostic::warning("lint", "no description"),
+    ]);
+    assert_eq!(report.worst(), Some(Severity::Error));
+    assert_eq!(report.raised(Severity::Warning), 1);
+    assert_eq!(report.diagnostics()[0].subject(), Some("nixpkgs"));
+}
+
+#[test]
+fn from_warning() {
+    let warning = Warning::DuplicateId {
+        id: "foo".into(),
+        fst: PathBuf::from("a/foo@.toml"),
+        snd: PathBuf::from("b/foo@.toml"),
+    };
+    let diagnostic = Diagnostic::from(&warning);
+    assert_eq!(diagnostic.severity(), Severity::Warning);
+    assert_eq!(diagnostic.code(), "duplicate-id");
+    assert_eq!(diagnostic.message(), warning.to_string());
+
+    let warning = Warning::Trespass {
+        id: "foo".into(),
+        owners: "core".into(),
+    };
+    assert_eq!(Diagnostic::from(&warning).subject(), Some("foo"));
+}
@@ -1,9 +1,20 @@
 //! # Atom Store Interface
 #[cfg(feature = "git")]
+pub(crate) mod auth;
+#[cfg(feature = "git")]
 pub mod git;
+#[cfg(feature = "s3")]
+pub mod s3;
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
 
 use bstr::BStr;
+use semver::Version;
+
+use crate::id::Id;
+use crate::policy::Policy;
 
 /// A trait representing the methods required to initialize an Ekala store.
 pub trait Init<R, O> {
@@ -15,6 +26,52 @@ pub trait Init<R, O> {
     fn ekala_init(&self) -> Result<(), Self::Error>;
     /// Returns the root as reported by the remote store, or an error if it is inconsistent.
     fn ekala_root(&self) -> Result<R, Self::Error>;
+    /// Inspect whether the Ekala store is initialized, and with which root, without
+    /// modifying it.
+    fn ekala_status(&self) -> Result<InitStatus<R>, Self::Error>;
+}
+
+/// The initialization state of an Ekala store, as reported by [`Init::ekala_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitStatus<R> {
+    /// The root the store is initialized with, if it is initialized.
+    pub remote: Option<R>,
+    /// The root of the store's current history, which initializing it would record.
+    pub head: R,
+    /// The root of the local history.
+    pub local: R,
+}
+
+impl<R: PartialEq> InitStatus<R> {
+    /// Whether the store is initialized.
+    #[must_use]
+    pub fn is_initialized(&self) -> bool {
+        self.remote.is_some()
+    }
+
+    /// Whether the store is initialized with the root of the local history, so that Atoms
+    /// may be published to it from here.
+    #[must_use]
+    pub fn matches(&self) -> bool {
+        self.remote.as_ref() == Some(&self.local)
+    }
+}
+
+/// A trait for retrieving the [`Policy`] declared by an Ekala store.
+pub trait QueryPolicy {
+    /// The error type returned by the methods of this trait.
+    type Error;
+    /// Fetch the store's policy, or `None` if it has not declared one.
+    fn ekala_policy(&self) -> Result<Option<Policy>, Self::Error>;
+}
+
+/// A trait for enumerating the Atoms published to an Ekala store, rather than querying the
+/// refs of a single Atom.
+pub trait QueryAtoms {
+    /// The error type returned by the methods of this trait.
+    type Error;
+    /// List the Atoms published to the store, along with each of their published versions.
+    fn published_atoms(&self) -> Result<BTreeMap<Id, BTreeSet<Version>>, Self::Error>;
 }
 
 /// A trait containing a path normalization method, to normalize paths in an Ekala store
@@ -37,6 +94,76 @@ pub trait NormalizeStorePath {
     ///   - Treated as if the repository root is the filesystem root.
     ///   - The leading slash is ignored, and the path is considered relative to the repo root.
     fn normalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Self::Error>;
+
+    /// Normalizes a given path to be relative to the store root, exactly like
+    /// [`NormalizeStorePath::normalize`], except relative paths are interpreted from the
+    /// given `cwd` instead of the process's current working directory.
+    fn normalize_from<P: AsRef<Path>>(&self, cwd: &Path, path: P) -> Result<PathBuf, Self::Error>;
+
+    /// Normalizes a path which is already relative to the store root, such as one read from
+    /// the store's own tree, purely lexically.
+    ///
+    /// Unlike [`NormalizeStorePath::normalize`], the filesystem is never consulted, so the path
+    /// need not exist in the working directory, nor need there be one at all. Absolute paths
+    /// are treated as relative to the store root, and paths escaping it are rejected.
+    fn normalize_lexical<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Self::Error>;
+}
+
+/// A boxed error of a store backend, as returned through an [`EkalaStore`] trait object.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A boxed future, as returned through an [`EkalaStore`] trait object.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// The outcome of publishing an Atom through an [`EkalaStore`]: the id of the Atom if it was
+/// published, or an error holding it if it was safely skipped, as it was published already.
+pub type Published = Result<Id, Id>;
+
+/// An object-safe interface to an Ekala store, unifying [`Init`], [`QueryAtoms`],
+/// [`QueryPolicy`], the querying of its refs, and the publishing of Atoms to it, so that a
+/// backend can be selected at runtime, e.g. from the scheme of a url or the configuration, and
+/// used through a `Box<dyn EkalaStore>` without knowing which it is.
+///
+/// The roots of a store, and the targets of its refs, are rendered as text, as their types
+/// differ from one backend to another.
+pub trait EkalaStore {
+    /// The name of the store, i.e. the remote or url it is reached by.
+    fn name(&self) -> &str;
+    /// The ref recording the root of the store.
+    fn root_ref(&self) -> &str;
+    /// Initialize the store, as by [`Init::ekala_init`].
+    fn init(&self) -> Result<(), BoxError>;
+    /// The root of the store, as by [`Init::ekala_root`].
+    fn root(&self) -> Result<String, BoxError>;
+    /// The initialization state of the store, as by [`Init::ekala_status`].
+    fn status(&self) -> Result<InitStatus<String>, BoxError>;
+    /// The Atoms published to the store, with their versions, as by
+    /// [`QueryAtoms::published_atoms`].
+    fn published(&self) -> Result<BTreeMap<Id, BTreeSet<Version>>, BoxError>;
+    /// The policy declared by the store, as by [`QueryPolicy::ekala_policy`].
+    fn policy(&self) -> Result<Option<Policy>, BoxError>;
+    /// The target of the ref `name` of the store.
+    fn get_ref(&self, name: &str) -> Result<String, BoxError>;
+    /// Publish the Atoms at `paths`, as of the revision `spec`, with the default settings of
+    /// the backend, resolving to the outcome of each once all of them reached the store. The
+    /// errors not specific to any one Atom, e.g. of a push, follow their outcomes.
+    fn publish<'a>(
+        &'a self,
+        spec: &'a str,
+        paths: Vec<PathBuf>,
+    ) -> BoxFuture<'a, Result<Vec<Result<Published, BoxError>>, BoxError>>;
+}
+
+impl<R> InitStatus<R> {
+    /// Map each root of the status, e.g. to render it as text, as an [`EkalaStore`] reports
+    /// them.
+    pub fn map<T>(self, f: impl Fn(R) -> T) -> InitStatus<T> {
+        InitStatus {
+            remote: self.remote.map(&f),
+            head: f(self.head),
+            local: f(self.local),
+        }
+    }
 }
 
 pub(crate) trait QueryStore<Id> {
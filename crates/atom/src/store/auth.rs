@@ -0,0 +1,66 @@
+//! # Authorizing Requests
+//!
+//! Private stores, and the APIs of the providers hosting them, may require credentials, which
+//! are configured per url prefix under `[auth]`, as a bearer token, basic authentication, or a
+//! client certificate for mutual TLS:
+//!
+//! ```toml
+//! [auth."https://github.example.com/api/v3"]
+//! token = "..."
+//! client-cert = "/etc/eka/client.pem"
+//! client-key = "/etc/eka/client.key"
+//! ```
+//!
+//! They are applied to the `curl` command making a request, the secrets being passed in its
+//! environment and expanded by `curl` itself, with `--variable`, so that they never appear on
+//! its command line. This requires `curl` 8.3 or later.
+#[cfg(test)]
+mod test;
+
+use std::path::Path;
+use std::process::Command;
+
+use config::Authorization;
+
+/// The variable a bearer token is passed to `curl` in.
+const TOKEN_VAR: &str = "EKA_AUTH_TOKEN";
+
+/// The variable the user name of basic authentication is passed to `curl` in.
+const USER_VAR: &str = "EKA_AUTH_USER";
+
+/// The variable the password of basic authentication is passed to `curl` in.
+const PASSWORD_VAR: &str = "EKA_AUTH_PASSWORD";
+
+/// Authorize the request made by `cmd` as given, and present the client certificate and key
+/// of `identity`, if any.
+pub(crate) fn apply(
+    cmd: &mut Command,
+    authorization: Option<Authorization<'_>>,
+    identity: Option<(&Path, &Path)>,
+) {
+    match authorization {
+        Some(Authorization::Bearer(token)) => {
+            cmd.env(TOKEN_VAR, token)
+                .args(["--variable", &format!("%{TOKEN_VAR}")])
+                .args([
+                    "--expand-header",
+                    &format!("Authorization: Bearer {{{{{TOKEN_VAR}}}}}"),
+                ]);
+        },
+        Some(Authorization::Basic(user, password)) => {
+            // an empty password is sent as such, rather than prompted for
+            cmd.env(USER_VAR, user)
+                .env(PASSWORD_VAR, password.unwrap_or_default())
+                .args(["--variable", &format!("%{USER_VAR}")])
+                .args(["--variable", &format!("%{PASSWORD_VAR}")])
+                .args([
+                    "--expand-user",
+                    &format!("{{{{{USER_VAR}}}}}:{{{{{PASSWORD_VAR}}}}}"),
+                ]);
+        },
+        None => {},
+    }
+    if let Some((cert, key)) = identity {
+        cmd.arg("--cert").arg(cert).arg("--key").arg(key);
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+
+use super::*;
+
+/// Whether the `curl` binary supports `--variable`, i.e. is version 8.3 or later.
+fn curl_expands_variables() -> bool {
+    let Ok(output) = Command::new("curl").arg("--version").output() else {
+        return false;
+    };
+    let version = String::from_utf8_lossy(&output.stdout);
+    let version: Vec<u32> = version
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .split('.')
+        .filter_map(|part| part.parse().ok())
+        .collect();
+    version[..] >= [8, 3][..]
+}
+
+/// Make the request of `cmd` to a server accepting a single one, returning it as received.
+fn received(mut cmd: Command) -> Result<String, anyhow::Error> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/refs", listener.local_addr()?);
+    let server = std::thread::spawn(move || -> std::io::Result<String> {
+        let (mut stream, _) = listener.accept()?;
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+        Ok(String::from_utf8_lossy(&request).into_owned())
+    });
+
+    let output = cmd.args(["--silent", "--show-error"]).arg(&url).output()?;
+    let request = server
+        .join()
+        .map_err(|_| anyhow::anyhow!("the server panicked"))??;
+    anyhow::ensure!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(request)
+}
+
+#[test]
+fn authorize_requests() -> Result<(), anyhow::Error> {
+    if !curl_expands_variables() {
+        return Ok(());
+    }
+    let secret = |cmd: &Command| {
+        cmd.get_args()
+            .any(|arg| arg.to_string_lossy().contains("secret"))
+    };
+
+    let mut cmd = Command::new("curl");
+    apply(&mut cmd, Some(Authorization::Bearer("secret")), None);
+    assert!(!secret(&cmd));
+    assert!(received(cmd)?.contains("Authorization: Bearer secret\r\n"));
+
+    // `user:secret`, base64 encoded
+    let mut cmd = Command::new("curl");
+    apply(
+        &mut cmd,
+        Some(Authorization::Basic("user", Some("secret"))),
+        None,
+    );
+    assert!(!secret(&cmd));
+    assert!(received(cmd)?.contains("Authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+
+    // `user:`, base64 encoded
+    let mut cmd = Command::new("curl");
+    apply(&mut cmd, Some(Authorization::Basic("user", None)), None);
+    assert!(received(cmd)?.contains("Authorization: Basic dXNlcjo=\r\n"));
+
+    let mut cmd = Command::new("curl");
+    apply(&mut cmd, None, None);
+    assert!(!received(cmd)?.contains("Authorization:"));
+    Ok(())
+}
+
+#[test]
+fn present_client_identity() {
+    let mut cmd = Command::new("curl");
+    apply(
+        &mut cmd,
+        None,
+        Some((
+            Path::new("/etc/eka/client.pem"),
+            Path::new("/etc/eka/client.key"),
+        )),
+    );
+    let args: Vec<_> = cmd.get_args().map(|arg| arg.to_string_lossy()).collect();
+    assert_eq!(
+        args,
+        [
+            "--cert",
+            "/etc/eka/client.pem",
+            "--key",
+            "/etc/eka/client.key"
+        ]
+    );
+}
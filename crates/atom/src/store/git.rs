@@ -5,19 +5,45 @@
 //! In particular, the implementation to initialize ([`Init`]) a Git repository as an Ekala store
 //! is contained here, as well as the type representing the [`Root`] of history used for an
 //! [`crate::AtomId`].
+pub mod api;
+pub mod archive;
+pub mod artifact;
+pub mod backend;
+pub mod channel;
+pub mod content;
+pub mod eval;
+pub mod exclude;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
+pub mod gc;
+pub mod include;
+pub mod info;
+pub mod migrate;
+pub mod mirror;
+pub mod proxy;
+pub mod redirect;
+pub mod shared;
+pub mod sign;
+pub mod synthetic;
 #[cfg(test)]
 pub(crate) mod test;
+pub mod transaction;
+pub mod verify;
+pub mod watch;
+pub mod yank;
 
-use std::sync::OnceLock;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 
-use bstr::BStr;
+use bstr::{BStr, BString, ByteSlice};
 use gix::discover::upwards::Options;
 use gix::sec::Trust;
 use gix::sec::trust::Mapping;
 use gix::{Commit, ObjectId, ThreadSafeRepository};
+use semver::Version;
 use thiserror::Error as ThisError;
 
-use crate::id::CalculateRoot;
+use crate::id::{CalculateRoot, Id};
 
 /// An error encountered during initialization or other git store operations.
 #[derive(ThisError, Debug)]
@@ -34,18 +60,39 @@ pub enum Error {
     /// The calculated root does not match what was reported by the remote.
     #[error("The calculated root does not match the reported one")]
     RootInconsistent,
+    /// The remote is already initialized, with a root other than its history's.
+    #[error("The remote is already initialized with a different root: {}", *.0)]
+    AlreadyInitialized(Root),
+    /// The remote refused the push, as the pusher lacks permission to write to it.
+    #[error("Permission to push to `{0}` was denied")]
+    PermissionDenied(String),
     /// A transparent wrapper for a [`gix::revision::walk::Error`]
     #[error(transparent)]
     WalkFailure(#[from] gix::revision::walk::Error),
     /// A transparent wrapper for a [`std::io::Error`]
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    /// The path lies outside of the repository root.
+    #[error("`{}` is outside of the repository root", .0.display())]
+    OutsideRoot(PathBuf),
     /// A transparent wrapper for a [`std::path::StripPrefixError`]
     #[error(transparent)]
     NormalizationFailed(#[from] std::path::StripPrefixError),
+    /// No remote was given, and several are configured without one being the default.
+    #[error("No default remote is configured, choose one of: {}", .0.join(", "))]
+    AmbiguousRemote(Vec<String>),
     /// A transparent wrapper for a [`Box<gix::remote::find::existing::Error>`]
     #[error(transparent)]
     NoRemote(#[from] Box<gix::remote::find::existing::Error>),
+    /// A transparent wrapper for a [`Box<gix::remote::init::Error>`]
+    #[error(transparent)]
+    RemoteInit(#[from] Box<gix::remote::init::Error>),
+    /// A transparent wrapper for a [`Box<gix::open::Error>`]
+    #[error(transparent)]
+    Open(#[from] Box<gix::open::Error>),
+    /// A transparent wrapper for a [`Box<gix::init::Error>`]
+    #[error(transparent)]
+    Init(#[from] Box<gix::init::Error>),
     /// A transparent wrapper for a [`Box<gix::remote::connect::Error>`]
     #[error(transparent)]
     Connect(#[from] Box<gix::remote::connect::Error>),
@@ -58,27 +105,101 @@ pub enum Error {
     /// A transparent wrapper for a [`Box<gix::object::find::existing::with_conversion::Error>`]
     #[error(transparent)]
     NoCommit(#[from] Box<gix::object::find::existing::with_conversion::Error>),
+    /// A transparent wrapper for a [`Box<gix::object::find::existing::Error>`]
+    #[error(transparent)]
+    NoObject(#[from] Box<gix::object::find::existing::Error>),
     /// A transparent wrapper for a [`Box<gix::refspec::parse::Error>`]
     #[error(transparent)]
     AddRefFailed(#[from] Box<gix::refspec::parse::Error>),
     /// A transparent wrapper for a [`Box<gix::reference::edit::Error>`]
     #[error(transparent)]
     WriteRef(#[from] Box<gix::reference::edit::Error>),
+    /// A transparent wrapper for a [`Box<gix::object::write::Error>`]
+    #[error(transparent)]
+    WriteObject(#[from] Box<gix::object::write::Error>),
+    /// A transparent wrapper for a [`Box<gix::object::commit::Error>`]
+    #[error(transparent)]
+    NoTree(#[from] Box<gix::object::commit::Error>),
+    /// A transparent wrapper for a [`Box<gix::reference::head_id::Error>`]
+    #[error(transparent)]
+    NoHead(#[from] Box<gix::reference::head_id::Error>),
+    /// A transparent wrapper for a [`gix::objs::decode::Error`]
+    #[error(transparent)]
+    Decode(#[from] gix::objs::decode::Error),
+    /// A transparent wrapper for a [`gix::objs::find::existing_object::Error`]
+    #[error(transparent)]
+    FindObject(#[from] gix::objs::find::existing_object::Error),
+    /// A path in the tree is not valid UTF-8, and cannot be represented on every platform.
+    #[error("`{0}` is not a valid UTF-8 path")]
+    NonUtf8Path(BString),
+    /// The ref name is longer than a store is expected to accept.
+    #[error("`{}` is longer than {} bytes", .0, REF_MAX)]
+    RefTooLong(String),
+    /// The ref name contains a sequence git forbids in ref names.
+    #[error("`{0}` is not a valid ref name: {1}")]
+    InvalidRefName(String, String),
+    /// The spec tree of a published Atom contains no manifest.
+    #[error("`{0}` contains no Atom manifest")]
+    NoManifest(String),
+    /// The manifest or lock in the spec tree of a published Atom could not be parsed.
+    #[error("`{0}` holds an invalid manifest or lock: {1}")]
+    InvalidSpec(String, toml_edit::de::Error),
+    /// No version of a dependency published to the store satisfies its requirement.
+    #[error("No version of `{0}` published to the store satisfies `{1}`")]
+    Unsatisfied(String, String),
+    /// A transparent wrapper for a [`crate::FormatError`]
+    #[error(transparent)]
+    Unsupported(#[from] crate::FormatError),
+    /// A locked version of an Atom is published as another atom commit than the lock pins.
+    #[error("`{0}@{1}` is published as another atom commit than the one the lock pins")]
+    LockedRev(String, Version),
+    /// A mirror holds other Atoms than the store it mirrors.
+    #[error("`{1}` does not hold the same `{0}` as the store it mirrors")]
+    MirrorMismatch(String, String),
+    /// A locked Atom was fetched from another store than the one the lock pins it from.
+    #[error("`{0}` is no longer served by the store the lock pins it from")]
+    LockedRoot(String),
+    /// Two stores are initialized with different roots, so one cannot mirror the other.
+    #[error("`{0}` and `{1}` are not initialized with the same root")]
+    RootMismatch(String, String),
+    /// An Atom cannot be redirected to itself.
+    #[error("`{0}` cannot be redirected to itself")]
+    SelfRedirect(String),
+    /// A dependency follows both a channel and a tag.
+    #[error("`{0}` is declared with both a `channel` and a `tag`, only one may be followed")]
+    Ambiguous(String),
+    /// A dependency requires a version of an Atom other than the one already resolved.
+    #[error("`{0}` requires `{1}` at `{2}`, but `{3}` was already resolved")]
+    Conflict(String, String, String, Version),
+    /// The policy declared by the store could not be parsed.
+    #[error("The store's policy is invalid: {0}")]
+    InvalidPolicy(#[from] toml_edit::de::Error),
+    /// The policy committed by the organization could not be parsed.
+    #[error("`{}` is invalid: {}", crate::policy::ORG_POLICY_FILE, .0)]
+    InvalidOrgPolicy(#[source] toml_edit::de::Error),
+    /// The workspace manifest committed at the root of the repository could not be parsed.
+    #[error("`{}` is invalid: {}", crate::manifest::WORKSPACE_FILE, .0)]
+    InvalidWorkspace(#[source] toml_edit::de::Error),
+    /// A path an Atom includes lies outside of the repository.
+    #[error("`{0}` lies outside of the repository, and cannot be included")]
+    IncludeOutside(String),
+    /// A path an Atom includes does not exist in the revision it is published from.
+    #[error("`{0}` does not exist in the published revision, and cannot be included")]
+    IncludeMissing(String),
+    /// The content of an Atom including paths has an entry of its own where they are grafted.
+    #[error(
+        "The Atom's content has an entry named `{}` of its own, where included paths go",
+        include::INCLUDED
+    )]
+    IncludeConflict,
+    /// A tree entry would be written outside of the directory it belongs to, or over another.
+    #[error("`{0}` is not a safe path to write a tree entry to")]
+    UnsafePath(BString),
+    /// The path is not in the tree it was looked up in.
+    #[error("`{}` does not exist in the Atom's content", .0.display())]
+    NotInTree(PathBuf),
 }
 
-impl Error {
-    pub(crate) fn warn(self) -> Self {
-        tracing::warn!(message = %self);
-        self
-    }
-}
-
-/// Provide a lazyily instantiated static reference to the git repository.
-static REPO: OnceLock<Option<ThreadSafeRepository>> = OnceLock::new();
-
-use std::borrow::Cow;
-static DEFAULT_REMOTE: OnceLock<Cow<str>> = OnceLock::new();
-
 /// The wrapper type for the underlying type which will be used to represent
 /// the "root" identifier for an [`crate::AtomId`]. For git, this is a [`gix::ObjectId`]
 /// representing the original commit made in the repositories history.
@@ -87,34 +208,23 @@ static DEFAULT_REMOTE: OnceLock<Cow<str>> = OnceLock::new();
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Root(ObjectId);
 
-/// Return a static reference the the local Git repository.
-pub fn repo() -> Result<Option<&'static ThreadSafeRepository>, Box<gix::discover::Error>> {
-    let mut error = None;
-    let repo = REPO.get_or_init(|| match get_repo() {
-        Ok(repo) => Some(repo),
-        Err(e) => {
-            error = Some(e);
-            None
-        },
-    });
-    if let Some(e) = error {
-        Err(e)
-    } else {
-        Ok(repo.as_ref())
-    }
-}
-
 use std::io;
 /// Run's the git binary, returning the output or the err, depending on the return value.
 ///
 /// Note: We rely on this only for operations that are not yet implemented in GitOxide.
 ///       Once push is implemented upstream, we can, and should, remove this.
 pub fn run_git_command(args: &[&str]) -> io::Result<Vec<u8>> {
+    run_git_command_verbose(args).map(|(stdout, _)| stdout)
+}
+
+/// Run's the git binary like [`run_git_command`], also returning what it wrote to its standard
+/// error when it succeeds, e.g. the messages a remote responded to a push with.
+pub fn run_git_command_verbose(args: &[&str]) -> io::Result<(Vec<u8>, Vec<u8>)> {
     use std::process::Command;
     let output = Command::new("git").args(args).output()?;
 
     if output.status.success() {
-        Ok(output.stdout)
+        Ok((output.stdout, output.stderr))
     } else {
         Err(io::Error::new(
             io::ErrorKind::Other,
@@ -123,30 +233,1219 @@ pub fn run_git_command(args: &[&str]) -> io::Result<Vec<u8>> {
     }
 }
 
-fn get_repo() -> Result<ThreadSafeRepository, Box<gix::discover::Error>> {
+/// A push of refs to a remote, through the git binary, as `gix` cannot push yet.
+///
+/// Every push of the store goes through here, so that how refs are pushed is decided in one
+/// place: a ref is never forced, and is only updated where a lease allows it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Push {
+    config: Vec<String>,
+    atomic: bool,
+    leases: Vec<String>,
+    refspecs: Vec<String>,
+}
+
+impl Push {
+    /// Push the given refspecs.
+    pub(crate) fn new<I: IntoIterator<Item = String>>(refspecs: I) -> Self {
+        Push {
+            refspecs: refspecs.into_iter().collect(),
+            ..Push::default()
+        }
+    }
+
+    /// Push each ref under its own name.
+    pub(crate) fn refs<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Self {
+        Push::new(names.into_iter().map(|name| format!("{name}:{name}")))
+    }
+
+    /// Set a Git configuration value for the push only.
+    pub(crate) fn config(mut self, key: &str, value: impl std::fmt::Display) -> Self {
+        self.config
+            .extend(["-c".to_owned(), format!("{key}={value}")]);
+        self
+    }
+
+    /// Push all the refs or none, if the remote supports it.
+    pub(crate) fn atomic(mut self) -> Self {
+        self.atomic = true;
+        self
+    }
+
+    /// Only update the ref `name` if the remote has it pointing to `expected`, or lacks it if
+    /// `expected` is `None`.
+    pub(crate) fn lease(mut self, name: &str, expected: Option<ObjectId>) -> Self {
+        let expected = expected.map(|id| id.to_string()).unwrap_or_default();
+        self.leases
+            .push(format!("--force-with-lease={name}:{expected}"));
+        self
+    }
+
+    /// Push to `remote` from the repository at `git_dir`, returning what Git wrote to its
+    /// standard error, e.g. the messages the remote responded with.
+    pub(crate) fn run(&self, git_dir: &str, remote: &str) -> io::Result<Vec<u8>> {
+        let mut args = vec!["-C", git_dir];
+        args.extend(self.config.iter().map(String::as_str));
+        args.push("push");
+        if self.atomic {
+            args.push("--atomic");
+        }
+        args.extend(self.leases.iter().map(String::as_str));
+        args.push(remote);
+        args.extend(self.refspecs.iter().map(String::as_str));
+        // FIXME: use gix for push once it supports it
+        run_git_command_verbose(&args).map(|(_, stderr)| stderr)
+    }
+}
+
+/// Discover the Git repository containing the given directory, searching upwards through its
+/// parents.
+pub fn discover<P: AsRef<Path>>(dir: P) -> Result<ThreadSafeRepository, Box<gix::discover::Error>> {
     let opts = Options {
         required_trust: Trust::Full,
         ..Default::default()
     };
-    ThreadSafeRepository::discover_opts(".", opts, Mapping::default()).map_err(Box::new)
+    ThreadSafeRepository::discover_opts(dir.as_ref(), opts, Mapping::default()).map_err(Box::new)
+}
+
+/// The name of the remote preferred for publishing when no other is configured.
+pub const EKALA_REMOTE: &str = "ekala";
+
+/// Why a remote was selected by [`select_remote`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteSource {
+    /// The remote was given explicitly.
+    Given,
+    /// The remote is configured as the default for publishing.
+    Configured,
+    /// The remote is the `branch.<name>.pushRemote` of the checked out branch.
+    BranchPushRemote,
+    /// The remote is the repository's `remote.pushDefault`.
+    PushDefault,
+    /// The remote is the `branch.<name>.remote` of the checked out branch.
+    BranchRemote,
+    /// The remote is named [`EKALA_REMOTE`].
+    Ekala,
+    /// The remote is the only one configured.
+    Only,
+    /// The remote is `origin`, by convention.
+    Origin,
 }
 
-/// Return a static reference to the default remote configured for pushing
-pub fn default_remote() -> &'static str {
+impl std::fmt::Display for RemoteSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RemoteSource::Given => "given explicitly",
+            RemoteSource::Configured => "configured as the default",
+            RemoteSource::BranchPushRemote => "push remote of the current branch",
+            RemoteSource::PushDefault => "`remote.pushDefault` of the repository",
+            RemoteSource::BranchRemote => "upstream remote of the current branch",
+            RemoteSource::Ekala => "named `ekala`",
+            RemoteSource::Only => "the only remote",
+            RemoteSource::Origin => "`origin` by convention",
+        })
+    }
+}
+
+/// Select the remote to publish to, in order of precedence: the `given` one, the `configured`
+/// default, the push remote git would use for the checked out branch, a remote named
+/// [`EKALA_REMOTE`], the only remote, or `origin`.
+///
+/// # Errors
+///
+/// This function will return an error listing the configured remotes if there are several, and
+/// none of them is selected by the above.
+pub fn select_remote(
+    repo: &Repository,
+    given: Option<&str>,
+    configured: Option<&str>,
+) -> Result<(String, RemoteSource), Error> {
+    let config = repo.config_snapshot();
+    let branch = repo
+        .head_name()
+        .ok()
+        .flatten()
+        .map(|name| name.shorten().to_string());
+    let branch_key = |key: &str| {
+        let key = format!("branch.{}.{key}", branch.as_ref()?);
+        config.string(key.as_str()).map(|v| v.to_string())
+    };
+    let names: Vec<_> = repo
+        .remote_names()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    let (name, source) = given
+        .map(|name| (name.to_owned(), RemoteSource::Given))
+        .or_else(|| configured.map(|name| (name.to_owned(), RemoteSource::Configured)))
+        .or_else(|| branch_key("pushRemote").map(|name| (name, RemoteSource::BranchPushRemote)))
+        .or_else(|| {
+            let name = config.string("remote.pushDefault")?;
+            Some((name.to_string(), RemoteSource::PushDefault))
+        })
+        .or_else(|| branch_key("remote").map(|name| (name, RemoteSource::BranchRemote)))
+        .or_else(|| {
+            let ekala = names.iter().find(|name| *name == EKALA_REMOTE)?;
+            Some((ekala.clone(), RemoteSource::Ekala))
+        })
+        .or_else(|| match names.as_slice() {
+            [only] => Some((only.clone(), RemoteSource::Only)),
+            // with no remotes at all, report the missing `origin` when it is looked up
+            [] => Some(("origin".into(), RemoteSource::Origin)),
+            _ => names
+                .iter()
+                .any(|name| name == "origin")
+                .then(|| ("origin".into(), RemoteSource::Origin)),
+        })
+        .ok_or_else(|| Error::AmbiguousRemote(names.clone()))?;
+
+    tracing::debug!(remote = %name, reason = %source, "Selected remote");
+    Ok((name, source))
+}
+
+/// Return the name of the remote to publish to when none is given or configured, as selected
+/// by [`select_remote`].
+///
+/// # Errors
+///
+/// This function will return an error listing the configured remotes if there are several, and
+/// none of them is the default.
+pub fn default_remote(repo: &Repository) -> Result<String, Error> {
+    select_remote(repo, None, None).map(|(name, _)| name)
+}
+
+/// Expand the given revisions into those to publish from, in order. A range, e.g.
+/// `v1.0..v2.0`, expands to each of the commits in it, oldest first, while any other revision
+/// is kept as is.
+///
+/// # Errors
+///
+/// This function will return an error if the commits of a range cannot be listed.
+pub fn expand_revisions<S: AsRef<str>>(
+    repo: &Repository,
+    specs: &[S],
+) -> Result<Vec<String>, Error> {
+    let git_dir = repo.git_dir().to_string_lossy().to_string();
+    let mut revisions = Vec::with_capacity(specs.len());
+    for spec in specs.iter().map(AsRef::as_ref) {
+        if !spec.contains("..") {
+            revisions.push(spec.to_owned());
+            continue;
+        }
+        // FIXME: use gix to walk ranges once it supports hiding commits
+        let listing = run_git_command(&[
+            "-C",
+            &git_dir,
+            "rev-list",
+            "--reverse",
+            "--topo-order",
+            "--end-of-options",
+            spec,
+        ])?;
+        revisions.extend(
+            String::from_utf8_lossy(&listing)
+                .lines()
+                .map(ToOwned::to_owned),
+        );
+    }
+    Ok(revisions)
+}
+
+/// Convert a path relative to the root of the repository into the path of the corresponding
+/// tree entry, which is separated by `/` on every platform.
+///
+/// # Errors
+///
+/// This function will return an error if the path is not valid UTF-8 on a platform which
+/// requires tree paths to be.
+pub fn try_to_tree_path(path: &Path) -> Result<Cow<'_, BStr>, Error> {
+    let bytes = gix::path::try_into_bstr(path)
+        .map_err(|_| Error::NonUtf8Path(path.to_string_lossy().as_bytes().into()))?;
+    Ok(gix::path::to_unix_separators_on_windows(bytes))
+}
+
+/// Like [`try_to_tree_path`], for paths which are already known to be valid, e.g. because they
+/// were read from a tree in the first place.
+pub(crate) fn to_tree_path(path: &Path) -> Cow<'_, BStr> {
+    gix::path::to_unix_separators_on_windows(gix::path::into_bstr(path))
+}
+
+/// The longest ref name, in bytes, a store is expected to accept.
+///
+/// Git itself imposes no limit, but loose refs are stored as files, whose names are limited to
+/// 255 bytes on most filesystems, and hosts commonly reject longer names outright.
+pub const REF_MAX: usize = 255;
+
+/// Check that `name` is a full ref name a store will accept: no longer than [`REF_MAX`] bytes,
+/// and free of the sequences git forbids, such as `..` or a component ending in `.lock`.
+///
+/// # Errors
+///
+/// This function will return an error describing why the name would be rejected.
+pub fn validate_ref_name(name: &str) -> Result<(), Error> {
+    if name.len() > REF_MAX {
+        return Err(Error::RefTooLong(name.to_owned()));
+    }
+    let invalid = |reason: String| Error::InvalidRefName(name.to_owned(), reason);
+    gix::refs::FullName::try_from(name).map_err(|e| invalid(e.to_string()))?;
+    // gix only checks the full name for the suffix, but git forbids it in any component
+    if name.split('/').any(|c| c.ends_with(".lock")) {
+        return Err(invalid("a component ends with `.lock`".into()));
+    }
+    Ok(())
+}
+
+/// Parse the hex representation of an object id, e.g. from a commit header or the output of
+/// git, accepting only ids of the repository's object format.
+///
+/// Nothing in a store assumes a particular object format: every id is either computed with
+/// the repository's [`gix::Repository::object_hash`] or parsed with this function, so that an
+/// id of one format is never mistaken for an object of another.
+#[must_use]
+pub fn parse_object_id(repo: &Repository, hex: &[u8]) -> Option<ObjectId> {
+    ObjectId::from_hex(hex)
+        .ok()
+        .filter(|id| id.kind() == repo.object_hash())
+}
+
+/// Encode a version as the component of the ref names it is published under.
+///
+/// The encoding is reversible, as `_` never occurs in a valid version: the `+` preceding build
+/// metadata, which is awkward in refspecs and URLs, is written as `_`, and a version ending in
+/// `.lock`, which git forbids, has a `_` appended.
+#[must_use]
+pub fn encode_version(version: &Version) -> String {
+    let mut encoded = version.to_string().replace('+', "_");
+    if encoded.ends_with(".lock") {
+        encoded.push('_');
+    }
+    encoded
+}
+
+/// Decode a version from the component of a ref name written by [`encode_version`].
+///
+/// # Errors
+///
+/// This function will return an error if the component does not encode a valid version.
+pub fn decode_version(component: &str) -> Result<Version, semver::Error> {
+    let component = component
+        .strip_suffix('_')
+        .filter(|c| c.ends_with(".lock"))
+        .unwrap_or(component);
+    Version::parse(&component.replace('_', "+"))
+}
+
+/// Find the first path under the tree with the given id which is not valid UTF-8, if any.
+///
+/// Such paths are representable on unix, but not on every platform, nor in the manifests and
+/// headers which refer to them, so they are rejected rather than silently mangled.
+pub fn find_non_utf8_path(repo: &Repository, tree: ObjectId) -> Result<Option<BString>, Error> {
+    fn walk(repo: &Repository, tree: ObjectId, prefix: &BStr) -> Result<Option<BString>, Error> {
+        let tree = repo.find_tree(tree).map_err(Box::new)?;
+        for entry in tree.decode()?.entries {
+            let mut path = prefix.to_owned();
+            if !path.is_empty() {
+                path.push(b'/');
+            }
+            path.extend_from_slice(entry.filename);
+
+            if entry.filename.to_str().is_err() {
+                return Ok(Some(path));
+            }
+            if entry.mode.is_tree() {
+                if let Some(path) = walk(repo, entry.oid.to_owned(), path.as_bstr())? {
+                    return Ok(Some(path));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    walk(repo, tree, BStr::new(""))
+}
+
+/// List the Atoms published to `remote`, along with each of their published versions.
+///
+/// # Errors
+///
+/// This function will return an error if the refs of the remote cannot be listed.
+pub fn published(
+    repo: &Repository,
+    remote: &str,
+) -> Result<BTreeMap<Id, BTreeSet<Version>>, Error> {
+    Ok(Snapshot::take(repo, remote)?.published())
+}
+
+/// List the Atoms published to `remote` along with each of their versions resolution may
+/// select, i.e. those not [yanked](yank).
+///
+/// # Errors
+///
+/// This function will return an error if the refs of the remote cannot be listed.
+pub fn resolvable(
+    repo: &Repository,
+    remote: &str,
+) -> Result<BTreeMap<Id, BTreeSet<Version>>, Error> {
+    Ok(Snapshot::take(repo, remote)?.resolvable())
+}
+
+/// The fetch url of `remote`, given by name or as a url.
+fn remote_url(repo: &Repository, remote: &str) -> Option<gix::Url> {
     use gix::remote::Direction;
-    DEFAULT_REMOTE
-        .get_or_init(|| {
-            repo()
-                .ok()
-                .flatten()
-                .and_then(|repo| {
-                    repo.to_thread_local()
-                        .remote_default_name(Direction::Push)
-                        .map(|s| s.to_string().into())
-                })
-                .unwrap_or("origin".into())
+
+    match repo.try_find_remote(remote) {
+        Some(found) => found.ok()?.url(Direction::Fetch).cloned(),
+        None => gix::url::parse(remote.into()).ok(),
+    }
+}
+
+/// The Atom refs of a remote store, as listed at a point in time.
+///
+/// A snapshot taken while planning a batch publish serves as its optimistic concurrency token:
+/// each ref is then only pushed if the remote still lacks it, as it did when the snapshot was
+/// taken, so that an Atom published by someone else in the meantime is never overwritten, and
+/// only the Atoms whose refs changed are planned again, rather than the whole batch failing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    refs: BTreeMap<String, ObjectId>,
+}
+
+impl Snapshot {
+    /// List the Atom refs of `remote` as they are now.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the refs of the remote cannot be listed.
+    pub fn take(repo: &Repository, remote: &str) -> Result<Self, Error> {
+        use crate::publish::ATOM_REF_TOP_LEVEL;
+
+        let git_dir = repo.git_dir().to_string_lossy().to_string();
+        let pattern = format!("refs/{ATOM_REF_TOP_LEVEL}/*");
+        let listing = run_git_command(&["-C", &git_dir, "ls-remote", remote, &pattern])?;
+
+        let refs = String::from_utf8_lossy(&listing)
+            .lines()
+            .filter_map(|line| {
+                let (id, name) = line.split_once('\t')?;
+                let id = ObjectId::from_hex(id.as_bytes()).ok()?;
+                Some((name.to_owned(), id))
+            })
+            .collect();
+
+        Ok(Snapshot { refs })
+    }
+
+    /// List the Atom refs of `remote` as they are now, to read metadata only, e.g. to describe
+    /// an Atom: through the [API](api) of the provider hosting it, if there is one, and
+    /// `fetch.api` is enabled in `config`, authorized by the credentials it configures for the
+    /// API, or over git as [`Self::take`] does otherwise, or should the API fail.
+    ///
+    /// The API may serve a listing lagging briefly behind the remote, so a snapshot serving as
+    /// an optimistic concurrency token is always taken over git instead.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the refs of the remote cannot be listed over git,
+    /// once the API failed or was not used.
+    pub fn query(repo: &Repository, remote: &str, config: &config::Config) -> Result<Self, Error> {
+        let api = config
+            .fetch()
+            .api
+            .then(|| remote_url(repo, remote))
+            .flatten()
+            .and_then(|url| api::Api::detect(&url, config.aliases()));
+        if let Some(api) = api {
+            match api.list(config.auth(api.base())) {
+                Ok(refs) => return Ok(Snapshot { refs }),
+                Err(e @ api::Error::RateLimited(_)) => tracing::warn!(
+                    %remote,
+                    reason = %e,
+                    "Listing refs over git instead, configure a token for the API to raise its limit"
+                ),
+                Err(e) => {
+                    tracing::debug!(%remote, reason = %e, "Listing refs over git instead");
+                },
+            }
+        }
+        Self::take(repo, remote)
+    }
+
+    /// Return the object the ref `name` pointed to when the snapshot was taken, if it existed.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<ObjectId> {
+        self.refs.get(name).copied()
+    }
+
+    /// Iterate over the refs as they were when the snapshot was taken, by full name.
+    pub fn refs(&self) -> impl Iterator<Item = (&str, ObjectId)> {
+        self.refs.iter().map(|(name, id)| (name.as_str(), *id))
+    }
+
+    /// The Atoms published when the snapshot was taken, along with each of their versions.
+    #[must_use]
+    pub fn published(&self) -> BTreeMap<Id, BTreeSet<Version>> {
+        self.versions(crate::publish::ATOM)
+    }
+
+    /// The Atoms published when the snapshot was taken, along with each of their versions
+    /// resolution may select, i.e. those not [yanked](yank).
+    #[must_use]
+    pub fn resolvable(&self) -> BTreeMap<Id, BTreeSet<Version>> {
+        let yanked = self.yanked();
+        let mut atoms = self.published();
+        for (id, versions) in &mut atoms {
+            if let Some(yanked) = yanked.get(id) {
+                versions.retain(|version| !yanked.contains(version));
+            }
+        }
+        atoms.retain(|_, versions| !versions.is_empty());
+        atoms
+    }
+
+    /// The Atoms with versions [yanked](yank) when the snapshot was taken, along with each of
+    /// their yanked versions.
+    #[must_use]
+    pub fn yanked(&self) -> BTreeMap<Id, BTreeSet<Version>> {
+        self.versions(yank::YANKED)
+    }
+
+    /// The versions of each Atom with a ref of the given kind, e.g. `atom`.
+    fn versions(&self, kind: &str) -> BTreeMap<Id, BTreeSet<Version>> {
+        use std::str::FromStr;
+
+        use crate::publish::ATOM_REF_TOP_LEVEL;
+
+        let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/");
+        let mut atoms: BTreeMap<Id, BTreeSet<Version>> = BTreeMap::new();
+        for name in self.refs.keys() {
+            let Some(path) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+            let [id, version, found] = path.split('/').collect::<Vec<_>>()[..] else {
+                continue;
+            };
+            if found != kind {
+                continue;
+            }
+            if let (Ok(id), Ok(version)) = (Id::from_str(id), decode_version(version)) {
+                atoms.entry(id).or_default().insert(version);
+            }
+        }
+        atoms
+    }
+}
+
+/// Find the Atoms published to `remote` whose ids are indistinguishable to a human from one
+/// another, differing only by case or by confusable characters.
+///
+/// # Errors
+///
+/// This function will return an error if the refs of the remote cannot be listed.
+pub fn confusable_ids(repo: &Repository, remote: &str) -> Result<Vec<Vec<Id>>, Error> {
+    let atoms = published(repo, remote)?;
+    Ok(Id::confusables(atoms.keys())
+        .into_iter()
+        .map(|group| group.into_iter().cloned().collect())
+        .collect())
+}
+
+/// Fetch only the spec of the given version of an Atom from `remote`, i.e. the tree holding
+/// its manifest and lock, over a single connection, and parse them. The Atom's content is not
+/// transferred, making this cheap enough to query many Atoms, e.g. during resolution.
+///
+/// # Errors
+///
+/// This function will return an error if the version is not published to `remote`, cannot be
+/// fetched, or its manifest or lock is missing or invalid.
+pub fn fetch_spec(
+    remote: &gix::Remote,
+    id: &str,
+    version: &Version,
+) -> Result<(crate::Manifest, Option<crate::Lockfile>), Error> {
+    let name = artifact::spec_ref(id, version);
+    validate_ref_name(&name)?;
+    let spec: ObjectId = remote.get_ref(name.as_str())?;
+    let spec = read_spec(remote.repo(), spec, &name)?;
+
+    tracing::debug!(remote = remote.symbol(), %id, %version, "Fetched spec");
+    Ok(spec)
+}
+
+/// Fetch only the specs of the given versions of Atoms from `remote`, as [`fetch_spec`] does,
+/// and parse them, in order. The specs the repository lacks are all fetched over a single
+/// connection, and those it holds already, e.g. from an earlier resolution, are not fetched
+/// again.
+///
+/// # Errors
+///
+/// This function will return an error if the refs of `remote` cannot be listed, any of the
+/// versions is not published to it, or any spec cannot be fetched, or holds a missing or
+/// invalid manifest or lock.
+pub fn fetch_specs(
+    remote: &gix::Remote,
+    atoms: &[(&Id, &Version)],
+) -> Result<Vec<(crate::Manifest, Option<crate::Lockfile>)>, Error> {
+    let snapshot = Snapshot::take(remote.repo(), remote.symbol())?;
+    fetch_specs_in(remote, &snapshot, atoms)
+}
+
+/// Fetch the specs of the given versions of Atoms as [`fetch_specs`] does, as listed by the
+/// given snapshot of the refs of `remote`.
+fn fetch_specs_in(
+    remote: &gix::Remote,
+    snapshot: &Snapshot,
+    atoms: &[(&Id, &Version)],
+) -> Result<Vec<(crate::Manifest, Option<crate::Lockfile>)>, Error> {
+    let repo = remote.repo();
+    let mut specs = Vec::with_capacity(atoms.len());
+    for (id, version) in atoms {
+        let name = artifact::spec_ref(id, version);
+        validate_ref_name(&name)?;
+        let spec = snapshot
+            .get(&name)
+            .ok_or_else(|| Error::NoRef(name.clone(), remote.symbol().to_owned()))?;
+        specs.push((name, spec));
+    }
+
+    let missing: Vec<_> = specs
+        .iter()
+        .filter(|(_, spec)| !repo.has_object(spec))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        remote.get_refs(&missing)?;
+        tracing::debug!(
+            remote = remote.symbol(),
+            count = missing.len(),
+            "Fetched specs"
+        );
+    }
+
+    specs
+        .iter()
+        .map(|(name, spec)| read_spec(repo, *spec, name))
+        .collect()
+}
+
+/// Fetch the given version of an Atom from `remote`, returning the id of its content tree.
+///
+/// # Errors
+///
+/// This function will return an error if the version is not published to `remote`, or cannot
+/// be fetched.
+pub fn fetch_content(remote: &gix::Remote, id: &str, version: &Version) -> Result<ObjectId, Error> {
+    use crate::publish::{ATOM, ATOM_REF_TOP_LEVEL};
+
+    let name = format!(
+        "refs/{ATOM_REF_TOP_LEVEL}/{id}/{}/{ATOM}",
+        encode_version(version)
+    );
+    validate_ref_name(&name)?;
+    let commit: ObjectId = remote.get_ref(name.as_str())?;
+    let tree = remote
+        .repo()
+        .find_commit(commit)
+        .map_err(Box::new)?
+        .tree_id()?
+        .detach();
+
+    tracing::debug!(remote = remote.symbol(), %id, %version, "Fetched content");
+    Ok(tree)
+}
+
+/// Fetch the version of an Atom pinned by a lock from `remote`, verifying that it is still
+/// published as the atom commit the lock pins, and return the id of its content tree.
+///
+/// # Errors
+///
+/// This function will return an error if the version is not published to `remote`, cannot be
+/// fetched, or is published as another atom commit than the one the lock pins.
+pub fn fetch_locked(remote: &gix::Remote, atom: &crate::LockedAtom) -> Result<ObjectId, Error> {
+    let name = atom_ref(atom.published_id(), &atom.version);
+    validate_ref_name(&name)?;
+    let commit: ObjectId = remote.get_ref(name.as_str())?;
+    if crate::ObjectSum::from(commit) != atom.rev {
+        return Err(Error::LockedRev(atom.id.to_string(), atom.version.clone()));
+    }
+    let tree = remote
+        .repo()
+        .find_commit(commit)
+        .map_err(Box::new)?
+        .tree_id()?
+        .detach();
+
+    tracing::debug!(
+        remote = remote.symbol(),
+        id = %atom.id,
+        version = %atom.version,
+        "Fetched locked content"
+    );
+    Ok(tree)
+}
+
+/// Verify that the Atom pinned by a lock is still fetchable, exactly as pinned, and return the
+/// id of its content tree. An Atom of the locking Atom's own store is fetched from `remote`,
+/// any other from the store it was resolved from, which must still have the root the lock
+/// pins, so that a store replaced under the same url is caught.
+///
+/// # Errors
+///
+/// This function will return an error if the store cannot be reached, no longer has the root
+/// the lock pins, or for any reason [`fetch_locked`] would.
+pub fn verify_locked(remote: &gix::Remote, atom: &crate::LockedAtom) -> Result<ObjectId, Error> {
+    use crate::store::Init;
+
+    let other;
+    let remote = match &atom.store {
+        Some(url) => {
+            other = remote
+                .repo()
+                .remote_at(url.as_str())
+                .map_err(|e| Error::from(Box::new(e)))?;
+            &other
+        },
+        None => remote,
+    };
+    if let Some(root) = atom.root {
+        if crate::ObjectSum::from(*remote.ekala_root()?) != root {
+            return Err(Error::LockedRoot(atom.id.to_string()));
+        }
+    }
+    fetch_locked(remote, atom)
+}
+
+/// Lock the dependencies of `manifest` on other Atoms in its own store, i.e. those declared
+/// with neither a url nor a path, against `remote`, the store it is published to. Each is
+/// pinned to the greatest published version satisfying its requirement, which is not yanked,
+/// or to the version the [channel, or tag](channel) it follows points to, along with the root
+/// of the store, so consumers fetch it from the right place. Their own dependencies are not
+/// locked.
+///
+/// As with Cargo, a yanked version is never selected by a new resolution, but remains
+/// selectable for an Atom it is already pinned to by the `locked` lock, e.g. the lock being
+/// updated, so that yanking a version does not break the builds depending on it.
+///
+/// # Errors
+///
+/// This function will return an error if `manifest` requires a newer manifest format than
+/// this version understands, `remote` is not an Ekala store, its refs cannot be listed, or no
+/// published version of a dependency satisfies its requirement.
+pub fn lock_same_store(
+    remote: &gix::Remote,
+    manifest: &crate::Manifest,
+    locked: &crate::Lockfile,
+) -> Result<Vec<crate::LockedAtom>, Error> {
+    use crate::store::Init;
+
+    manifest.atom.supported()?;
+    if !manifest
+        .deps
+        .atoms
+        .values()
+        .any(crate::AtomDep::is_same_store)
+    {
+        return Ok(Vec::new());
+    }
+    let root = crate::ObjectSum::from(*remote.ekala_root()?);
+    let snapshot = Snapshot::take(remote.repo(), remote.symbol())?;
+    lock_deps(remote, &snapshot, root, manifest, locked)
+}
+
+/// Lock the dependencies of `manifest` as [`lock_same_store`] does, against the given snapshot
+/// of the refs of `remote`, whose root is `root`.
+fn lock_deps(
+    remote: &gix::Remote,
+    snapshot: &Snapshot,
+    root: crate::ObjectSum,
+    manifest: &crate::Manifest,
+    locked: &crate::Lockfile,
+) -> Result<Vec<crate::LockedAtom>, Error> {
+    let deps: Vec<_> = manifest
+        .deps
+        .atoms
+        .iter()
+        .filter(|(_, dep)| dep.is_same_store())
+        .collect();
+    if deps.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pinned = Vec::with_capacity(deps.len());
+    for (id, dep) in deps {
+        let (renamed, version) = select(snapshot, id, dep, locked)?;
+        let published = renamed.as_ref().unwrap_or(id);
+        let rev: ObjectId = remote.get_ref(atom_ref(published, &version).as_str())?;
+        pinned.push(crate::LockedAtom {
+            id: id.clone(),
+            version,
+            rev: rev.into(),
+            store: None,
+            root: Some(root),
+            renamed,
+            deps: Vec::new(),
+        });
+    }
+
+    Ok(pinned)
+}
+
+/// Select the version of the store, as listed by `snapshot`, the dependency `dep` on the Atom
+/// `id` is to be pinned to, as described by [`lock_same_store`], along with the id the Atom
+/// was renamed to, if the store [redirects](redirect) `id` to another Atom, whose versions are
+/// then selected from instead.
+fn select(
+    snapshot: &Snapshot,
+    id: &Id,
+    dep: &crate::AtomDep,
+    locked: &crate::Lockfile,
+) -> Result<(Option<Id>, Version), Error> {
+    if dep.channel.is_some() && dep.tag.is_some() {
+        return Err(Error::Ambiguous(id.to_string()));
+    }
+    let renamed = snapshot.renamed(id);
+    if let Some(renamed) = &renamed {
+        tracing::warn!(
+            %id,
+            %renamed,
+            "The Atom was renamed, depend on it by its new id instead"
+        );
+    }
+    let published = renamed.as_ref().unwrap_or(id);
+    let yanked = snapshot.yanked();
+    let satisfies = |v: &Version| dep.version.as_ref().map_or(true, |req| req.matches(v));
+    // a yanked version is only kept where it is locked already
+    let selectable = |v: &Version| {
+        !yanked
+            .get(published)
+            .is_some_and(|yanked| yanked.contains(v))
+            || locked.get(id).is_some_and(|atom| atom.version == *v)
+    };
+    let version = match dep.follows() {
+        Some(follow) => {
+            validate_ref_name(&channel::ref_name(published, follow))?;
+            snapshot
+                .pointed(published, follow)
+                .filter(|v| satisfies(v) && selectable(v))
+        },
+        None => snapshot.published().remove(published).and_then(|versions| {
+            versions
+                .into_iter()
+                .rev()
+                .find(|v| satisfies(v) && selectable(v))
+        }),
+    };
+    version
+        .map(|version| (renamed, version))
+        .ok_or_else(|| Error::Unsatisfied(id.to_string(), dep.requirement()))
+}
+
+/// The name of the ref the atom commit of version `version` of the Atom `id` is published as.
+fn atom_ref(id: &Id, version: &Version) -> String {
+    use crate::publish::{ATOM, ATOM_REF_TOP_LEVEL};
+
+    format!(
+        "refs/{ATOM_REF_TOP_LEVEL}/{id}/{}/{ATOM}",
+        encode_version(version)
+    )
+}
+
+/// Lock the dependencies of `manifest` on Atoms of other stores, i.e. those declared with a
+/// url, as [`lock_same_store`] locks those on Atoms of its own, recording the url each was
+/// pinned from in the lock. The store at the url of a dependency is tried first, then each of
+/// its mirrors in order, until one can be reached. Their own dependencies are not locked.
+///
+/// The identity of an Atom, its [`crate::AtomId`], is rooted in the root of its store, which a
+/// mirror must then share. So, when a mirror is resolved against, its root is verified against
+/// the one the `locked` lock pins for the Atom, as is the atom commit of the version it pins,
+/// should that version be selected again. An Atom the lock does not pin yet is trusted to the
+/// mirror, whose root is recorded for later resolutions to verify.
+///
+/// # Errors
+///
+/// This function will return an error if `manifest` requires a newer manifest format than
+/// this version understands, neither the store of a dependency nor any of its mirrors can be
+/// reached, no published version of a dependency satisfies its requirement, or a mirror does
+/// not match the store it mirrors.
+pub fn lock_other_stores(
+    repo: &Repository,
+    manifest: &crate::Manifest,
+    locked: &crate::Lockfile,
+) -> Result<Vec<crate::LockedAtom>, Error> {
+    use crate::store::Init;
+
+    manifest.atom.supported()?;
+    let mut pinned = Vec::new();
+    for (id, dep) in &manifest.deps.atoms {
+        if dep.path.is_some() {
+            continue;
+        }
+
+        let mut reached = None;
+        for (i, url) in dep.sources().enumerate() {
+            let store = repo
+                .remote_at(url.as_str())
+                .map_err(|e| Error::from(Box::new(e)))
+                .and_then(|remote| {
+                    let root = crate::ObjectSum::from(*remote.ekala_root()?);
+                    let snapshot = Snapshot::take(repo, url.as_str())?;
+                    Ok((remote, root, snapshot))
+                });
+            match store {
+                Ok(store) => {
+                    reached = Some(Ok((i > 0, url, store)));
+                    break;
+                },
+                Err(e) => {
+                    tracing::warn!(%id, %url, reason = %e, "Store could not be reached");
+                    reached = Some(Err(e));
+                },
+            }
+        }
+        let Some(reached) = reached else {
+            continue;
+        };
+        let (mirror, url, (remote, root, snapshot)) = reached?;
+
+        let (renamed, version) = select(&snapshot, id, dep, locked)?;
+        let published = renamed.as_ref().unwrap_or(id);
+        let rev: ObjectId = remote.get_ref(atom_ref(published, &version).as_str())?;
+        let rev = crate::ObjectSum::from(rev);
+        if mirror {
+            let known = locked.get(id).filter(|atom| atom.store.is_some());
+            let mismatch = known.is_some_and(|atom| {
+                atom.root.is_some_and(|known| known != root)
+                    || atom.version == version && atom.rev != rev
+            });
+            if mismatch {
+                return Err(Error::MirrorMismatch(id.to_string(), url.to_string()));
+            }
+            tracing::warn!(%id, %url, "Resolved against a mirror");
+        }
+        pinned.push(crate::LockedAtom {
+            id: id.clone(),
+            version,
+            rev,
+            store: Some(url.to_string()),
+            root: Some(root),
+            renamed,
+            deps: Vec::new(),
+        });
+    }
+
+    Ok(pinned)
+}
+
+/// Resolve the dependencies of `manifest` on other Atoms against `remote`, the store it is
+/// published to, transitively, into a lock. Each Atom is pinned, as by [`lock_same_store`], to
+/// the greatest published version satisfying the requirement it is first found by, nearest
+/// dependencies first, and the specs of its pinned version are read to resolve its own
+/// dependencies in turn. The specs of the Atoms pinned at each depth are fetched all at once,
+/// as by [`fetch_specs`], without their content. The yanked versions the `locked` lock pins
+/// remain selectable, as with [`lock_same_store`].
+///
+/// Versions once pinned are never revisited, so a requirement not satisfied by the version
+/// already pinned for its Atom is a conflict, even if another version would satisfy every
+/// requirement. Atoms of other stores are locked, from the store or one of its mirrors, as by
+/// [`lock_other_stores`], but their own dependencies are not resolved.
+///
+/// # Errors
+///
+/// This function will return an error if the dependencies of any Atom cannot be locked, its
+/// spec cannot be fetched or requires a newer manifest format than this version understands,
+/// or its requirements conflict with one another.
+pub fn resolve(
+    remote: &gix::Remote,
+    manifest: &crate::Manifest,
+    locked: &crate::Lockfile,
+) -> Result<crate::Lockfile, Error> {
+    use crate::store::Init;
+
+    manifest.atom.supported()?;
+    if manifest.deps.atoms.values().all(|dep| dep.path.is_some()) {
+        return Ok(crate::Lockfile::default());
+    }
+    let root = crate::ObjectSum::from(*remote.ekala_root()?);
+    let snapshot = Snapshot::take(remote.repo(), remote.symbol())?;
+    let lock_all = |manifest: &crate::Manifest| -> Result<Vec<crate::LockedAtom>, Error> {
+        let mut deps = lock_deps(remote, &snapshot, root, manifest, locked)?;
+        deps.extend(lock_other_stores(remote.repo(), manifest, locked)?);
+        Ok(deps)
+    };
+
+    let direct = lock_all(manifest)?;
+    let mut lock = crate::Lockfile {
+        deps: direct.iter().map(|atom| atom.id.clone()).collect(),
+        ..Default::default()
+    };
+
+    // the specs of Atoms of other stores are not read
+    let (mut pending, other): (Vec<_>, Vec<_>) =
+        direct.into_iter().partition(|atom| atom.store.is_none());
+    lock.atoms.extend(other);
+    while !pending.is_empty() {
+        // the specs of the Atoms pinned by each round are fetched all at once
+        let mut round = std::mem::take(&mut pending);
+        let atoms: Vec<_> = round
+            .iter()
+            .map(|atom| (atom.published_id(), &atom.version))
+            .collect();
+        let mut specs = fetch_specs_in(remote, &snapshot, &atoms)?;
+        while let (Some(mut atom), Some((spec, _))) = (round.pop(), specs.pop()) {
+            spec.atom.supported()?;
+            for dep in lock_all(&spec)? {
+                let pinned = lock
+                    .get(&dep.id)
+                    .or_else(|| round.iter().find(|pinned| pinned.id == dep.id))
+                    .or_else(|| pending.iter().find(|pending| pending.id == dep.id));
+                if let Some(pinned) = pinned {
+                    // a dependency following a channel, or tag, requires the version it points to
+                    let declared = spec.deps.atoms.get(&dep.id);
+                    let conflicts = declared.is_some_and(|d| match (d.follows(), &d.version) {
+                        (Some(_), _) => dep.version != pinned.version,
+                        (None, Some(req)) => !req.matches(&pinned.version),
+                        (None, None) => false,
+                    });
+                    if conflicts {
+                        return Err(Error::Conflict(
+                            atom.id.to_string(),
+                            dep.id.to_string(),
+                            declared.map_or("*".into(), crate::AtomDep::requirement),
+                            pinned.version.clone(),
+                        ));
+                    }
+                }
+                atom.deps.push(dep.id.clone());
+                if pinned.is_some() || dep.id == atom.id {
+                    continue;
+                }
+                if dep.store.is_none() {
+                    pending.push(dep);
+                } else {
+                    lock.atoms.push(dep);
+                }
+            }
+            lock.atoms.push(atom);
+        }
+    }
+
+    lock.atoms.sort();
+    Ok(lock)
+}
+
+/// Parse the manifest and lock, if any, held by the spec tree `spec`, published as `name`.
+fn read_spec(
+    repo: &Repository,
+    spec: ObjectId,
+    name: &str,
+) -> Result<(crate::Manifest, Option<crate::Lockfile>), Error> {
+    use crate::core::AtomPaths;
+
+    let tree = repo.find_tree(spec).map_err(Box::new)?;
+    let entries: Vec<_> = tree
+        .decode()?
+        .entries
+        .iter()
+        .filter(|e| e.mode.is_blob())
+        .map(|e| (e.filename.to_str_lossy().into_owned(), e.oid.to_owned()))
+        .collect();
+
+    let (manifest, oid) = entries
+        .iter()
+        .find(|(file, _)| file.ends_with(crate::ATOM_EXT.as_str()))
+        .ok_or_else(|| Error::NoManifest(name.to_owned()))?;
+    let lock = AtomPaths::new(manifest)
+        .lock()
+        .to_string_lossy()
+        .into_owned();
+    let lock = entries
+        .iter()
+        .find_map(|(file, oid)| (*file == lock).then_some(oid));
+
+    let read = |oid: &ObjectId| -> Result<String, Error> {
+        let blob = repo.find_blob(*oid).map_err(Box::new)?;
+        Ok(String::from_utf8_lossy(&blob.data).into_owned())
+    };
+    let invalid = |e| Error::InvalidSpec(name.to_owned(), e);
+
+    let manifest = read(oid)?.parse().map_err(invalid)?;
+    let lock = lock
+        .map(|oid| read(oid)?.parse().map_err(invalid))
+        .transpose()?;
+
+    Ok((manifest, lock))
+}
+
+/// Write out the tree with the given id to the `dest` directory, e.g. to work on an Atom's
+/// content in isolation from the rest of its repository. Submodules are skipped.
+///
+/// The tree may come from an untrusted store, so every entry must name a single component
+/// which stays within `dest`, as [`validate_entry_name`] checks, and no entry is ever written
+/// over, or through, an existing one, e.g. a symlink of the same name.
+pub fn materialize(repo: &Repository, tree: ObjectId, dest: &Path) -> Result<(), Error> {
+    use std::collections::HashSet;
+    use std::fs;
+
+    fs::create_dir_all(dest)?;
+
+    let tree = repo.find_tree(tree).map_err(Box::new)?;
+    let mut seen = HashSet::new();
+    let entries = tree
+        .decode()?
+        .entries
+        .iter()
+        .map(|e| {
+            validate_entry_name(e.filename)?;
+            if !seen.insert(e.filename) {
+                return Err(Error::UnsafePath(e.filename.to_owned()));
+            }
+            let name = gix::path::try_from_bstr(e.filename)
+                .map_err(|_| Error::NonUtf8Path(e.filename.to_owned()))?;
+            Ok((e.mode, name.into_owned(), e.oid.to_owned()))
         })
-        .as_ref()
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    for (mode, name, oid) in entries {
+        write_entry(repo, mode, oid, &dest.join(name))?;
+    }
+
+    Ok(())
+}
+
+/// Write the directory `dest` with `write`, into a temporary directory beside it, which is only
+/// renamed to `dest` once complete, so that no other process ever sees it partially written.
+/// The temporary directory is removed should `write` fail. Should another process write `dest`
+/// first, its directory is kept.
+///
+/// # Errors
+///
+/// This function will return an error if `write` does, or the directory cannot be renamed to
+/// `dest`, which does not exist either.
+pub fn write_atomically<E>(dest: &Path, write: impl FnOnce(&Path) -> Result<(), E>) -> Result<(), E>
+where
+    E: From<io::Error>,
+{
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+    if let Err(e) = write(&tmp) {
+        match std::fs::remove_dir_all(&tmp) {
+            Err(cleanup) if cleanup.kind() != io::ErrorKind::NotFound => {
+                tracing::warn!(dir = %tmp.display(), reason = %cleanup, "Failed to clean up");
+            },
+            _ => {},
+        }
+        return Err(e);
+    }
+    if let Err(e) = std::fs::rename(&tmp, dest) {
+        std::fs::remove_dir_all(&tmp)?;
+        if !dest.exists() {
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// Like [`materialize`], but write out only the entry at `path` within the tree, at the same
+/// path under `dest`, so that only the part of a large Atom which is needed hits the disk. The
+/// rest of the tree is never read.
+///
+/// # Errors
+///
+/// This function will return an error if `path` is not a relative path to an entry of the
+/// tree, or the entry cannot be written.
+pub fn materialize_path(
+    repo: &Repository,
+    tree: ObjectId,
+    path: &Path,
+    dest: &Path,
+) -> Result<(), Error> {
+    use std::path::Component;
+
+    let not_found = || Error::NotInTree(path.to_owned());
+    if !path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(not_found());
+    }
+
+    let tree_path = try_to_tree_path(path)?;
+    let mut buf = Vec::new();
+    let entry = repo
+        .find_tree(tree)
+        .map_err(Box::new)?
+        .lookup_entry(
+            tree_path
+                .split_str("/")
+                .filter(|c| !c.is_empty() && *c != b"."),
+            &mut buf,
+        )
+        .map_err(Box::new)?
+        .ok_or_else(not_found)?;
+
+    let dest = dest.join(path);
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_entry(repo, entry.mode(), entry.object_id(), &dest)
+}
+
+/// Check that `name`, the name of an entry of a tree, is a single component which stays within
+/// the directory it is written to, as `gix-validate` does: it may not be empty, `.`, `..`, or
+/// `.git` in any case, nor contain a separator or a NUL byte.
+///
+/// # Errors
+///
+/// This function will return an error if the name is not safe to write to.
+pub fn validate_entry_name(name: &BStr) -> Result<(), Error> {
+    let unsafe_name = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.eq_ignore_ascii_case(b".git")
+        || name.find_byteset(b"/\\\0").is_some();
+    if unsafe_name {
+        return Err(Error::UnsafePath(name.to_owned()));
+    }
+    Ok(())
+}
+
+/// Write out the tree entry with the given mode and id to `path`, which must not exist yet, so
+/// that nothing is ever written through a symlink written before.
+fn write_entry(
+    repo: &Repository,
+    mode: gix::objs::tree::EntryMode,
+    oid: ObjectId,
+    path: &Path,
+) -> Result<(), Error> {
+    use std::fs;
+
+    match fs::symlink_metadata(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+        Err(e) => return Err(e.into()),
+        Ok(_) => return Err(Error::UnsafePath(to_tree_path(path).into_owned())),
+    }
+
+    if mode.is_tree() {
+        materialize(repo, oid, path)?;
+    } else if mode.is_link() {
+        let target = repo.find_blob(oid).map_err(Box::new)?;
+        let target = gix::path::try_from_bstr(BStr::new(&target.data))
+            .map_err(|_| Error::NonUtf8Path(target.data.as_bstr().to_owned()))?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target, path)?;
+        #[cfg(not(unix))]
+        fs::write(path, gix::path::into_bstr(target).as_ref())?;
+    } else if mode.is_blob() {
+        fs::write(path, &repo.find_blob(oid).map_err(Box::new)?.data)?;
+        #[cfg(unix)]
+        if mode.is_executable() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        }
+    }
+
+    Ok(())
 }
 
 use std::ops::Deref;
@@ -199,6 +1498,10 @@ impl NormalizeStorePath for Repository {
     type Error = Error;
 
     fn normalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        self.normalize_from(self.current_dir(), path)
+    }
+
+    fn normalize_from<P: AsRef<Path>>(&self, cwd: &Path, path: P) -> Result<PathBuf, Error> {
         use std::fs;
 
         use path_clean::PathClean;
@@ -206,8 +1509,7 @@ impl NormalizeStorePath for Repository {
 
         let rel_repo_root = self.work_dir().ok_or(Error::NoWorkDir)?;
         let repo_root = fs::canonicalize(rel_repo_root)?;
-        let current = self.current_dir();
-        let rel = current.join(path).clean();
+        let rel = cwd.join(path).clean();
 
         rel.strip_prefix(&repo_root)
             .map_or_else(
@@ -235,6 +1537,51 @@ impl NormalizeStorePath for Repository {
                 Error::NormalizationFailed(e)
             })
     }
+
+    fn normalize_lexical<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        use std::path::Component;
+
+        use path_clean::PathClean;
+        let path = path.as_ref();
+
+        let normalized: PathBuf = path
+            .clean()
+            .components()
+            .filter(|c| {
+                !matches!(
+                    c,
+                    Component::Prefix(_) | Component::RootDir | Component::CurDir
+                )
+            })
+            .collect();
+
+        if normalized.starts_with("..") {
+            return Err(Error::OutsideRoot(path.to_path_buf()));
+        }
+        Ok(normalized)
+    }
+}
+
+impl Error {
+    /// Whether writing a ref failed as someone else wrote it, or is writing it, concurrently,
+    /// rather than for any other reason.
+    #[must_use]
+    pub fn is_contended(&self) -> bool {
+        use gix::reference::edit::Error as Edit;
+        use gix::refs::file::transaction::prepare::Error as Prepare;
+
+        matches!(
+            self,
+            Error::WriteRef(e) if matches!(
+                **e,
+                Edit::FileTransactionPrepare(
+                    Prepare::LockAcquire { .. }
+                        | Prepare::MustNotExist { .. }
+                        | Prepare::ReferenceOutOfDate { .. }
+                )
+            )
+        )
+    }
 }
 
 impl AsRef<[u8]> for Root {
@@ -267,9 +1614,10 @@ impl<'repo> EkalaRemote for gix::Remote<'repo> {
     }
 }
 
-const V1_ROOT: &str = "refs/tags/ekala/root/v1";
+/// The tag recording the root of an Ekala store's history, created by [`Init::ekala_init`].
+pub const V1_ROOT: &str = "refs/tags/ekala/root/v1";
 
-use super::Init;
+use super::{Init, InitStatus};
 impl<'repo> Init<Root, ObjectId> for gix::Remote<'repo> {
     type Error = Error;
 
@@ -308,6 +1656,8 @@ impl<'repo> Init<Root, ObjectId> for gix::Remote<'repo> {
     }
 
     /// Initialize the repository by calculating the root, according to the latest HEAD.
+    ///
+    /// Initializing a store already initialized with the same root is a no-op.
     fn ekala_init(&self) -> Result<(), Error> {
         use gix::refs::transaction::PreviousValue;
 
@@ -318,24 +1668,289 @@ impl<'repo> Init<Root, ObjectId> for gix::Remote<'repo> {
         let repo = self.repo();
         let root = *repo.find_commit(head).map_err(Box::new)?.calculate_root()?;
 
+        match self.get_ref(V1_ROOT) {
+            Ok(id) if id == root => {
+                tracing::info!(remote = name, message = "Already initialized");
+                return Ok(());
+            },
+            Ok(id) => return Err(Error::AlreadyInitialized(Root(id))),
+            Err(Error::NoRef(..) | Error::Refs(_)) => {},
+            Err(e) => return Err(e),
+        }
+
         let root_ref = repo
-            .reference(V1_ROOT, root, PreviousValue::MustNotExist, "init: root")
+            .reference(
+                V1_ROOT,
+                root,
+                PreviousValue::MustNotExistOrEqual(root.into()),
+                "init: root",
+            )
             .map_err(Box::new)?
             .name()
             .as_bstr()
             .to_string();
 
-        // FIXME: use gix for push once it supports it
-        run_git_command(&[
-            "-C",
-            repo.git_dir().to_string_lossy().as_ref(),
-            "push",
-            name,
-            format!("{root_ref}:{root_ref}").as_str(),
-        ])?;
+        Push::refs([root_ref.as_str()])
+            .run(repo.git_dir().to_string_lossy().as_ref(), name)
+            .map_err(|e| {
+                if is_permission_denied(&e) {
+                    Error::PermissionDenied(name.to_owned())
+                } else {
+                    e.into()
+                }
+            })?;
         tracing::info!(remote = name, message = "Successfully initialized");
         Ok(())
     }
+
+    fn ekala_status(&self) -> Result<InitStatus<Root>, Error> {
+        use crate::CalculateRoot;
+
+        let repo = self.repo();
+        let root_of = |id: ObjectId| repo.find_commit(id).map_err(Box::new)?.calculate_root();
+
+        let remote = match self.get_ref(V1_ROOT) {
+            Ok(id) => Some(root_of(id)?),
+            Err(Error::NoRef(..) | Error::Refs(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let head = root_of(self.sync()?)?;
+        let local = root_of(repo.head_id().map_err(Box::new)?.detach())?;
+
+        Ok(InitStatus {
+            remote,
+            head,
+            local,
+        })
+    }
+}
+
+/// Whether a failed push was refused for lack of permission, judging by git's output.
+fn is_permission_denied(e: &io::Error) -> bool {
+    let message = e.to_string().to_lowercase();
+    ["permission denied", "access denied", "error: 403"]
+        .iter()
+        .any(|needle| message.contains(needle))
+}
+
+/// The reason a remote refused to update a ref during a push.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// The ref already exists on the remote, pointing elsewhere, e.g. as someone else pushed it
+    /// first.
+    Exists,
+    /// The ref is locked on the remote, e.g. as someone else is pushing it at the same time.
+    Locked,
+    /// A hook of the remote declined the update.
+    Declined,
+    /// The pusher lacks permission to write to the remote.
+    PermissionDenied,
+}
+
+/// Classify the reason a failed push was refused, judging by git's output, if it was refused
+/// by the remote at all, rather than failing for another reason, e.g. a network error.
+#[must_use]
+pub fn classify_rejection(e: &io::Error) -> Option<Rejection> {
+    if is_permission_denied(e) {
+        return Some(Rejection::PermissionDenied);
+    }
+    let message = e.to_string().to_lowercase();
+    let mentions = |needles: &[&str]| needles.iter().any(|needle| message.contains(needle));
+    if mentions(&[
+        "(fetch first)",
+        "(non-fast-forward)",
+        "(already exists)",
+        "(stale info)",
+    ]) {
+        Some(Rejection::Exists)
+    } else if mentions(&[
+        "failed to lock",
+        "cannot lock ref",
+        "unable to lock",
+        "incorrect old value",
+    ]) {
+        Some(Rejection::Locked)
+    } else if mentions(&["hook declined", "[remote rejected]"]) {
+        Some(Rejection::Declined)
+    } else {
+        None
+    }
+}
+
+/// A message a remote responded to a successful push with, e.g. from one of its hooks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Notice {
+    /// An object pushed is larger than the remote recommends, or allows.
+    LargeObject(String),
+    /// The repository is nearing, or exceeds, a storage quota of the remote.
+    Quota(String),
+    /// A warning of the remote, or one of its hooks.
+    Warning(String),
+    /// Any other message of the remote, or one of its hooks.
+    Message(String),
+}
+
+impl std::fmt::Display for Notice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Notice::LargeObject(m) | Notice::Quota(m) | Notice::Warning(m) | Notice::Message(m) => {
+                f.write_str(m)
+            },
+        }
+    }
+}
+
+/// Parse the messages a remote responded to a push with, out of what git wrote to its standard
+/// error, passing over the progress it reported.
+#[must_use]
+pub fn parse_notices(stderr: &[u8]) -> Vec<Notice> {
+    const PROGRESS: &[&str] = &[
+        "counting objects",
+        "compressing objects",
+        "resolving deltas",
+        "enumerating objects",
+        "total ",
+    ];
+
+    let mut notices: Vec<Notice> = Vec::new();
+    for line in String::from_utf8_lossy(stderr).lines() {
+        // progress is redrawn in place, so only its last state is kept
+        let line = line.rsplit('\r').next().unwrap_or_default();
+        let Some(message) = line.strip_prefix("remote:").map(str::trim) else {
+            continue;
+        };
+        let lower = message.to_lowercase();
+        if message.is_empty() || PROGRESS.iter().any(|p| lower.starts_with(p)) {
+            continue;
+        }
+        let mentions = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+        let message = message.to_owned();
+        let notice = if mentions(&["large file", "larger than", "file size", "exceeds the size"]) {
+            Notice::LargeObject(message)
+        } else if mentions(&["quota", "size limit", "storage limit", "repository size"]) {
+            Notice::Quota(message)
+        } else if mentions(&["warning:", "error:"]) {
+            Notice::Warning(message)
+        } else {
+            Notice::Message(message)
+        };
+        if !notices.contains(&notice) {
+            notices.push(notice);
+        }
+    }
+    notices
+}
+
+/// Return the object a ref points to on `remote`, as it advertises it now, if it exists.
+///
+/// # Errors
+///
+/// This function will return an error if the refs of the remote cannot be listed.
+pub fn advertised(git_dir: &Path, remote: &str, name: &str) -> io::Result<Option<ObjectId>> {
+    let git_dir = git_dir.to_string_lossy();
+    let listing = run_git_command(&["-C", &git_dir, "ls-remote", remote, name])?;
+    Ok(String::from_utf8_lossy(&listing).lines().find_map(|line| {
+        let (id, found) = line.split_once('\t')?;
+        let id = ObjectId::from_hex(id.as_bytes()).ok()?;
+        (found == name).then_some(id)
+    }))
+}
+
+/// The ref under which a store declares its [`Policy`], pointing to a commit with a
+/// [`POLICY_FILE`] at the root of its tree.
+pub const POLICY_REF: &str = "refs/ekala/policy";
+
+use crate::manifest::{WORKSPACE_FILE, Workspace};
+use crate::policy::{ORG_POLICY_FILE, OrgPolicy, POLICY_FILE, Policy};
+impl<'repo> super::QueryPolicy for gix::Remote<'repo> {
+    type Error = Error;
+
+    /// Fetch the policy from the commit [`POLICY_REF`] points to on the remote, if it exists.
+    fn ekala_policy(&self) -> Result<Option<Policy>, Self::Error> {
+        let id = match self.get_ref(POLICY_REF) {
+            Ok(id) => id,
+            // a missing ref either yields no match, or no mapping at all for the refspec
+            Err(Error::NoRef(..) | Error::Refs(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let policy = read_policy(self.repo(), id)?;
+        if policy.is_none() {
+            tracing::warn!(
+                message = "Ignoring policy ref without a policy file",
+                remote = self.symbol(),
+                policy_ref = POLICY_REF,
+            );
+        }
+        Ok(policy)
+    }
+}
+
+impl<'repo> super::QueryAtoms for gix::Remote<'repo> {
+    type Error = Error;
+
+    /// List the Atoms from the `atom` refs of the remote, as by [`published`].
+    fn published_atoms(&self) -> Result<BTreeMap<Id, BTreeSet<Version>>, Self::Error> {
+        published(self.repo(), self.symbol())
+    }
+}
+
+/// Read the [`POLICY_FILE`] from the tree of the given commit, if it contains one.
+fn read_policy(repo: &Repository, commit: ObjectId) -> Result<Option<Policy>, Error> {
+    let Some(data) = read_root_file(repo, commit, POLICY_FILE)? else {
+        return Ok(None);
+    };
+    Ok(Some(Policy::from_slice(&data)?))
+}
+
+/// Read the [`ORG_POLICY_FILE`] from the tree of the given commit, if it contains one.
+///
+/// # Errors
+///
+/// This function will return an error if the commit cannot be read, or the policy is invalid.
+pub fn read_org_policy(repo: &Repository, commit: ObjectId) -> Result<Option<OrgPolicy>, Error> {
+    let Some(data) = read_root_file(repo, commit, ORG_POLICY_FILE)? else {
+        return Ok(None);
+    };
+    OrgPolicy::from_slice(&data)
+        .map(Some)
+        .map_err(Error::InvalidOrgPolicy)
+}
+
+/// Read the [`WORKSPACE_FILE`] from the tree of the given commit, if it contains one.
+///
+/// # Errors
+///
+/// This function will return an error if the commit cannot be read, or the workspace manifest
+/// is invalid.
+pub fn read_workspace(repo: &Repository, commit: ObjectId) -> Result<Option<Workspace>, Error> {
+    let Some(data) = read_root_file(repo, commit, WORKSPACE_FILE)? else {
+        return Ok(None);
+    };
+    Workspace::from_slice(&data)
+        .map(Some)
+        .map_err(Error::InvalidWorkspace)
+}
+
+/// Read the file with the given name from the root of the tree of the given commit, if it
+/// contains one.
+fn read_root_file(
+    repo: &Repository,
+    commit: ObjectId,
+    name: &str,
+) -> Result<Option<Vec<u8>>, Error> {
+    let tree = repo
+        .find_commit(commit)
+        .map_err(Box::new)?
+        .tree()
+        .map_err(Box::new)?;
+
+    let Some(entry) = tree.find_entry(name) else {
+        return Ok(None);
+    };
+
+    let mut blob = repo.find_blob(entry.oid()).map_err(Box::new)?;
+    Ok(Some(blob.take_data()))
 }
 
 type ProgressRange = std::ops::RangeInclusive<prodash::progress::key::Level>;
@@ -398,6 +2013,12 @@ impl<'repo> super::QueryStore<ObjectId> for gix::Remote<'repo> {
             .prepare_fetch(sync_progress, Options::default())
             .map_err(Box::new)?;
 
+        #[cfg(feature = "fault-injection")]
+        if fault::trip(self.repo().git_dir(), fault::Fault::Disconnect) {
+            handle.shutdown_and_wait();
+            return Err(fault::disconnected().into());
+        }
+
         let outcome = sync
             .receive(init_progress, &AtomicBool::new(false))
             .map_err(Box::new)?;
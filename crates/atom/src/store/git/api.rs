@@ -0,0 +1,304 @@
+//! # Listing Refs over Provider APIs
+//!
+//! Listing the refs of a remote store over git takes a full handshake, which dominates
+//! commands that only read metadata, e.g. `eka list` or `eka info`. For stores hosted by a
+//! provider with a REST API listing refs, they are listed with a single request to it instead:
+//!
+//! ```console
+//! GET https://api.github.com/repos/<owner>/<repo>/git/matching-refs/atoms/
+//! ```
+//!
+//! The provider is selected automatically from the host of the store's url, as the `gh:` alias
+//! expands to, unless `fetch.api` is disabled. An alias describing its host may also hint at
+//! the API it offers, e.g. for a GitHub Enterprise host, or to always list it over git:
+//!
+//! ```toml
+//! [aliases.work]
+//! host = "github.example.com"
+//! api = "rest"
+//! ```
+//!
+//! Requests are made with the `curl` binary, and authorized by the credentials configured in
+//! `[auth]` for the API, [applied](crate::store::auth) as for any other request, or otherwise, for
+//! `github.com` only, by `GITHUB_TOKEN` or `GH_TOKEN`, which `curl` reads from its environment,
+//! so that it never appears on its command line. The pages of a listing are only followed on
+//! the origin of the API, so that no credentials are sent elsewhere. Should a request fail for
+//! any reason, e.g. as the store is private and no token is given, or the rate limit of the API
+//! is exhausted, the refs are listed over git instead, and the API is not queried again for the
+//! rest of the process once it is exhausted.
+//!
+//! GitLab's API only lists branches and tags, not the refs Atoms are published under, so
+//! stores hosted by GitLab, as the `gl:` alias expands to, are always listed over git.
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use config::{Alias, Aliases, AuthConfig, Authorization, HostApi};
+use gix::ObjectId;
+use serde::Deserialize;
+use thiserror::Error as ThisError;
+
+use crate::publish::ATOM_REF_TOP_LEVEL;
+
+/// The url GitHub's API is served from, for repositories hosted at `github.com`.
+const GITHUB_API: &str = "https://api.github.com";
+
+/// The environment variables a token for [`GITHUB_API`] is read from, in order, unless
+/// credentials are configured for it.
+const TOKEN_VARS: [&str; 2] = ["GITHUB_TOKEN", "GH_TOKEN"];
+
+/// The number of refs requested per page of a listing, the most the API allows.
+const PER_PAGE: u32 = 100;
+
+/// When the rate limit of the API, found exhausted during this process, is reset, in unix time.
+static EXHAUSTED_UNTIL: AtomicU64 = AtomicU64::new(0);
+
+/// An error encountered while listing refs through a provider's API.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// The `curl` binary could not be run, or failed before receiving a response.
+    #[error("The request to `{0}` failed: {1}")]
+    Request(String, String),
+    /// The API responded to a request with an error.
+    #[error("The request to `{0}` failed with status {1}")]
+    Status(String, u16),
+    /// The rate limit of the API is exhausted.
+    #[error("The rate limit of the API is exhausted until {0} (unix time)")]
+    RateLimited(u64),
+    /// The response of the API could not be parsed.
+    #[error("The response of `{0}` is invalid: {1}")]
+    Invalid(String, String),
+}
+
+/// A provider whose REST API lists the refs of the repositories it hosts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    /// GitHub, at `github.com`, or a GitHub Enterprise host.
+    GitHub,
+}
+
+/// The REST API of the provider hosting a repository, as detected from its url.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Api {
+    provider: Provider,
+    base: String,
+    repo: String,
+}
+
+#[derive(Deserialize)]
+struct MatchingRef {
+    #[serde(rename = "ref")]
+    name: String,
+    object: RefObject,
+}
+
+#[derive(Deserialize)]
+struct RefObject {
+    sha: String,
+}
+
+impl Api {
+    /// Detect the API of the provider hosting the repository at `url`, if it is hosted by a
+    /// known provider, or one of the given aliases hints at, and its url names a repository of
+    /// it, as `<owner>/<repo>`.
+    #[must_use]
+    pub fn detect(url: &gix::Url, aliases: &Aliases) -> Option<Self> {
+        let host = url.host()?;
+        let hint = aliases
+            .values()
+            .filter_map(Alias::host)
+            .find(|alias| alias.name() == host)
+            .and_then(|alias| alias.api);
+        let base = match hint {
+            Some(HostApi::Git) => return None,
+            _ if host == "github.com" => GITHUB_API.to_owned(),
+            Some(HostApi::Rest) => format!("https://{host}/api/v3"),
+            None => return None,
+        };
+        let path = url.path.to_string();
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let [owner, repo] = path.split('/').collect::<Vec<_>>()[..] else {
+            return None;
+        };
+        if owner.is_empty() || repo.is_empty() {
+            return None;
+        }
+        Some(Api {
+            provider: Provider::GitHub,
+            base,
+            repo: format!("{owner}/{repo}"),
+        })
+    }
+
+    /// Return the provider of the API.
+    #[must_use]
+    pub fn provider(&self) -> Provider {
+        self.provider
+    }
+
+    /// Return the url the API is served from.
+    #[must_use]
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Whether `url` is served from the origin of the API, so that it may be sent the same
+    /// credentials.
+    #[must_use]
+    pub(super) fn serves(&self, url: &str) -> bool {
+        let base = origin(&self.base);
+        origin(url).is_some_and(|url| Some(url) == base)
+    }
+
+    /// List the Atom refs of the repository through the API, by full name, along with the
+    /// object each points to, authorized by the credentials `auth` configured for the API, if
+    /// any.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the rate limit of the API was exhausted, any
+    /// request fails, or its response cannot be parsed.
+    pub fn list(&self, auth: Option<&AuthConfig>) -> Result<BTreeMap<String, ObjectId>, Error> {
+        let until = EXHAUSTED_UNTIL.load(Ordering::Relaxed);
+        if unix_time() < until {
+            return Err(Error::RateLimited(until));
+        }
+        // a token for github.com is never sent to another host, e.g. an Enterprise one
+        let token = (self.base == GITHUB_API)
+            .then(|| TOKEN_VARS.iter().find_map(|var| std::env::var(var).ok()))
+            .flatten();
+        let authorization = auth
+            .and_then(AuthConfig::authorization)
+            .or(token.as_deref().map(Authorization::Bearer));
+        let identity = auth.and_then(AuthConfig::identity);
+
+        let mut refs = BTreeMap::new();
+        let mut next = Some(format!(
+            "{}/repos/{}/git/matching-refs/{ATOM_REF_TOP_LEVEL}/?per_page={PER_PAGE}",
+            self.base, self.repo
+        ));
+        while let Some(url) = next.take() {
+            let response = request(&url, authorization, identity)?;
+            match response.status {
+                200..=299 => {},
+                403 | 429 if response.remaining.as_deref() == Some("0") => {
+                    // without a reset time, the limit is assumed to be reset within the hour
+                    let reset = response.reset.and_then(|r| r.parse().ok());
+                    let reset = reset.unwrap_or_else(|| unix_time() + 3600);
+                    EXHAUSTED_UNTIL.store(reset, Ordering::Relaxed);
+                    return Err(Error::RateLimited(reset));
+                },
+                status => return Err(Error::Status(url, status)),
+            }
+            let page: Vec<MatchingRef> = serde_json::from_slice(&response.body)
+                .map_err(|e| Error::Invalid(url.clone(), e.to_string()))?;
+            for MatchingRef { name, object } in page {
+                let id = ObjectId::from_hex(object.sha.as_bytes())
+                    .map_err(|e| Error::Invalid(url.clone(), e.to_string()))?;
+                refs.insert(name, id);
+            }
+            next = match response.link.as_deref().and_then(next_page) {
+                Some(page) if !self.serves(&page) => {
+                    let reason = format!("the next page `{page}` is not served by the API");
+                    return Err(Error::Invalid(url, reason));
+                },
+                page => page,
+            };
+        }
+
+        tracing::debug!(repo = %self.repo, count = refs.len(), "Listed refs through the API");
+        Ok(refs)
+    }
+}
+
+/// The trailer `curl` writes to stderr after each response: its status, the headers reporting
+/// the rate limit, and the header linking to the next page of a listing.
+const WRITE_OUT: &str = concat!(
+    "%{stderr}\\n%{http_code}",
+    "\\t%header{x-ratelimit-remaining}",
+    "\\t%header{x-ratelimit-reset}",
+    "\\t%header{link}",
+);
+
+/// A response of the API.
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+    remaining: Option<String>,
+    reset: Option<String>,
+    link: Option<String>,
+}
+
+/// Send a `GET` request to `url`, authorized as given, presenting the client certificate and
+/// key of `identity`, if any.
+fn request(
+    url: &str,
+    authorization: Option<Authorization<'_>>,
+    identity: Option<(&Path, &Path)>,
+) -> Result<Response, Error> {
+    let failed = |e: &dyn std::fmt::Display| Error::Request(url.to_owned(), e.to_string());
+
+    let mut cmd = Command::new("curl");
+    cmd.args(["--silent", "--show-error", "--globoff"])
+        .args(["--header", "Accept: application/vnd.github+json"])
+        .args(["--header", "X-GitHub-Api-Version: 2022-11-28"])
+        .args(["--write-out", WRITE_OUT]);
+    crate::store::auth::apply(&mut cmd, authorization, identity);
+    let output = cmd
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| failed(&e))?;
+
+    // the trailer is written last to stderr, after any error of curl's own
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let (message, trailer) = stderr.rsplit_once('\n').unwrap_or(("", &stderr));
+    let mut fields = trailer.split('\t').map(str::trim);
+    let status = fields.next().and_then(|status| status.parse().ok());
+    let mut field = || {
+        fields
+            .next()
+            .filter(|f| !f.is_empty())
+            .map(ToOwned::to_owned)
+    };
+    let (remaining, reset, link) = (field(), field(), field());
+    match status {
+        Some(status) if output.status.success() => Ok(Response {
+            status,
+            body: output.stdout,
+            remaining,
+            reset,
+            link,
+        }),
+        _ => Err(failed(&message.trim())),
+    }
+}
+
+/// The url of the next page of a listing, from the `Link` header of a response.
+fn next_page(link: &str) -> Option<String> {
+    link.split(',').find_map(|entry| {
+        let (url, rel) = entry.split_once(';')?;
+        rel.contains("rel=\"next\"").then(|| {
+            url.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_owned()
+        })
+    })
+}
+
+/// The origin of `url`, i.e. its scheme and authority.
+fn origin(url: &str) -> Option<&str> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    Some(&url[..scheme.len() + "://".len() + authority.len()])
+}
+
+/// The current time, in unix time.
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,489 @@
+//! # Deterministic Archives
+//!
+//! An Atom's content may be distributed as an archive, e.g. to consumers without git, and
+//! attached to the Atom as an artifact. For the hash of such an archive to be declared ahead of
+//! time, and checked by anyone, the same content must always produce the same bytes. [`export`]
+//! writes the entries in the order of the content tree, with zeroed timestamps and owners, and
+//! modes derived only from the kind of each entry, regardless of who exports it, or where.
+//!
+//! Archives are written uncompressed, and may be piped through a deterministic compressor,
+//! such as `zstd`, as needed. A tar archive written by [`export`] can be checked out again with
+//! [`unpack`], e.g. when an Atom's content is fetched from a store as an archive, not through git.
+use std::io::{self, Write};
+use std::path::Path;
+
+use gix::{ObjectId, Repository};
+use thiserror::Error as ThisError;
+
+use super::content::{self, Entry, Mode};
+use crate::Digest;
+
+/// The format of an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// A POSIX ustar archive.
+    Tar,
+    /// A zip archive, with every entry stored uncompressed.
+    Zip,
+}
+
+impl Format {
+    /// The format of an archive written to `path`, by its extension.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "tar" => Some(Format::Tar),
+            "zip" => Some(Format::Zip),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Tar => "tar",
+            Format::Zip => "zip",
+        }
+    }
+}
+
+/// An error encountered while exporting an archive.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// The path of an entry, or the target of a link, is too long for the archive format.
+    #[error("`{0}` is too long to be archived")]
+    PathTooLong(String),
+    /// The content is too large for the archive format.
+    #[error("The content is too large to be archived as a {}", .0.name())]
+    TooLarge(Format),
+    /// The archive is truncated, or holds an entry of a kind never exported.
+    #[error("The archive is malformed")]
+    Malformed,
+    /// The path of an entry of the archive escapes the directory it is unpacked to.
+    #[error("`{0}` escapes the directory the archive is unpacked to")]
+    Escapes(String),
+    /// A transparent wrapper for a [`super::Error`]
+    #[error(transparent)]
+    Store(#[from] super::Error),
+    /// A transparent wrapper for a [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+type ArchiveResult<T> = Result<T, Error>;
+
+/// Write the tree with the given id, e.g. an Atom's content, to `out` as an archive of the
+/// given format, returning the digest of the archive, as declared for artifacts.
+///
+/// # Errors
+///
+/// This function will return an error if the tree cannot be read, cannot be represented in the
+/// archive format, or the archive cannot be written.
+pub fn export(
+    repo: &Repository,
+    tree: ObjectId,
+    format: Format,
+    out: impl Write,
+) -> ArchiveResult<Digest> {
+    let mut out = Hashing {
+        inner: out,
+        hasher: blake3::Hasher::new(),
+        written: 0,
+    };
+    let entries = content::entries(repo, tree);
+    match format {
+        Format::Tar => write_tar(entries, &mut out)?,
+        Format::Zip => write_zip(entries, &mut out)?,
+    }
+    out.flush()?;
+
+    Ok(Digest::from_hasher(&out.hasher))
+}
+
+/// Unpack a tar archive, as written by [`export`], into `dest`.
+///
+/// Only the kinds of entries [`export`] writes are understood, i.e. directories, regular and
+/// executable files, and symlinks. Entries whose path escapes `dest`, whether directly or
+/// through a symlink, including any already under `dest`, are refused, and so are symlinks
+/// resolving outside of it once every entry is unpacked.
+///
+/// # Errors
+///
+/// This function will return an error if the archive is malformed, holds an entry escaping
+/// `dest`, or an entry cannot be written.
+pub fn unpack(data: &[u8], dest: &Path) -> ArchiveResult<()> {
+    use std::fs;
+    use std::path::{Component, PathBuf};
+
+    fs::create_dir_all(dest)?;
+    let is_link = |path: &Path| {
+        fs::symlink_metadata(dest.join(path)).is_ok_and(|m| m.file_type().is_symlink())
+    };
+
+    let mut links: Vec<(PathBuf, String)> = Vec::new();
+    let mut rest = data;
+    loop {
+        let header = rest.get(..BLOCK).ok_or(Error::Malformed)?;
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let field = |at: usize, len: usize| {
+            let field = &header[at..at + len];
+            let end = field.iter().position(|&b| b == 0).unwrap_or(len);
+            std::str::from_utf8(&field[..end]).map_err(|_| Error::Malformed)
+        };
+        let octal = |at: usize, len: usize| {
+            u64::from_str_radix(field(at, len)?.trim(), 8).map_err(|_| Error::Malformed)
+        };
+
+        let (prefix, name) = (field(345, 155)?, field(0, 100)?);
+        let path = match prefix {
+            "" => name.to_owned(),
+            prefix => format!("{prefix}/{name}"),
+        };
+        let relative = PathBuf::from(path.trim_end_matches('/'));
+        // the filesystem is asked, rather than the archive, so that links are found whatever
+        // the case of their name, and whoever put them there
+        let escapes = !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+            || relative
+                .ancestors()
+                .any(|a| !a.as_os_str().is_empty() && is_link(a));
+        if escapes {
+            return Err(Error::Escapes(path));
+        }
+
+        let size = usize::try_from(octal(124, 12)?).map_err(|_| Error::Malformed)?;
+        let padded = size.div_ceil(BLOCK) * BLOCK;
+        let content = rest.get(BLOCK..BLOCK + size).ok_or(Error::Malformed)?;
+        let target = dest.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match header[156] {
+            b'5' => fs::create_dir_all(&target)?,
+            b'0' | 0 => {
+                fs::write(&target, content)?;
+                #[cfg(unix)]
+                if octal(100, 8)? & 0o111 != 0 {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(&target, fs::Permissions::from_mode(0o755))?;
+                }
+            },
+            b'2' => {
+                let link = field(157, 100)?;
+                #[cfg(unix)]
+                std::os::unix::fs::symlink(link, &target)?;
+                #[cfg(not(unix))]
+                fs::write(&target, link)?;
+                links.push((relative, link.to_owned()));
+            },
+            _ => return Err(Error::Malformed),
+        }
+
+        rest = rest.get(BLOCK + padded..).ok_or(Error::Malformed)?;
+    }
+
+    // links may point through others unpacked after them, so are only resolved at the end
+    let mut escaped = None;
+    for (link, target) in &links {
+        if !resolves_within(dest, link, Path::new(target))? {
+            // a link left behind would still lead outside of `dest`
+            fs::remove_file(dest.join(link))?;
+            escaped.get_or_insert_with(|| link.to_string_lossy().into_owned());
+        }
+    }
+    match escaped {
+        Some(link) => Err(Error::Escapes(link)),
+        None => Ok(()),
+    }
+}
+
+/// Whether the symlink at `link`, relative to `dest`, to `target` resolves to a path within
+/// `dest`, following any symlinks found under `dest` on the way.
+fn resolves_within(dest: &Path, link: &Path, target: &Path) -> io::Result<bool> {
+    use std::collections::VecDeque;
+    use std::ffi::OsString;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// The most symlinks followed while resolving a link, as for `ELOOP`.
+    const MAX_LINKS: usize = 40;
+
+    let steps = |target: &Path| -> Option<Vec<OsString>> {
+        (!target.has_root()).then(|| target.iter().map(ToOwned::to_owned).collect())
+    };
+
+    let Some(steps) = steps(target) else {
+        return Ok(false);
+    };
+    let mut pending = VecDeque::from(steps);
+    let mut resolved: Vec<OsString> = link
+        .parent()
+        .map(|p| p.iter().map(ToOwned::to_owned).collect())
+        .unwrap_or_default();
+    let mut followed = 0;
+    while let Some(step) = pending.pop_front() {
+        if step == "." {
+            continue;
+        }
+        if step == ".." {
+            if resolved.pop().is_none() {
+                return Ok(false);
+            }
+            continue;
+        }
+
+        resolved.push(step);
+        let path = dest.join(resolved.iter().collect::<PathBuf>());
+        if fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink()) {
+            followed += 1;
+            let target = fs::read_link(&path)?;
+            let Some(steps) = steps(&target).filter(|_| followed <= MAX_LINKS) else {
+                return Ok(false);
+            };
+            resolved.pop();
+            for step in steps.into_iter().rev() {
+                pending.push_front(step);
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// A writer hashing and counting everything written through it.
+struct Hashing<W> {
+    inner: W,
+    hasher: blake3::Hasher,
+    written: u64,
+}
+
+impl<W: Write> Write for Hashing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The permissions of an entry of the given kind, the only ones an archive records.
+fn permissions(mode: Mode) -> u32 {
+    match mode {
+        Mode::Dir | Mode::Executable => 0o755,
+        Mode::File => 0o644,
+        Mode::Symlink => 0o777,
+    }
+}
+
+const BLOCK: usize = 512;
+
+/// Write the entries as a ustar archive.
+fn write_tar(
+    entries: impl Iterator<Item = Result<Entry, super::Error>>,
+    out: &mut impl Write,
+) -> ArchiveResult<()> {
+    for entry in entries {
+        let entry = entry?;
+        out.write_all(&tar_header(&entry)?)?;
+        if entry.mode == Mode::File || entry.mode == Mode::Executable {
+            out.write_all(&entry.data)?;
+            let padding = (BLOCK - entry.data.len() % BLOCK) % BLOCK;
+            out.write_all(&[0; BLOCK][..padding])?;
+        }
+    }
+    // the end of the archive is marked by two empty blocks
+    out.write_all(&[0; 2 * BLOCK])?;
+    Ok(())
+}
+
+/// The ustar header of an entry, with every field not derived from the entry zeroed.
+fn tar_header(entry: &Entry) -> ArchiveResult<[u8; BLOCK]> {
+    fn put(header: &mut [u8], at: usize, value: &[u8]) {
+        header[at..at + value.len()].copy_from_slice(value);
+    }
+    fn octal(header: &mut [u8], at: usize, len: usize, value: u64) -> ArchiveResult<()> {
+        let value = format!("{value:0width$o}", width = len - 1);
+        if value.len() >= len {
+            return Err(Error::TooLarge(Format::Tar));
+        }
+        put(header, at, value.as_bytes());
+        Ok(())
+    }
+
+    let path = match entry.mode {
+        Mode::Dir => format!("{}/", entry.path),
+        _ => entry.path.clone(),
+    };
+    let too_long = || Error::PathTooLong(path.clone());
+    // paths longer than the name field are split into a prefix and a name at a `/`
+    let (prefix, name) = if path.len() <= 100 {
+        ("", path.as_str())
+    } else {
+        path.trim_end_matches('/')
+            .match_indices('/')
+            .map(|(i, _)| (&path[..i], &path[i + 1..]))
+            .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100)
+            .ok_or_else(too_long)?
+    };
+
+    let (kind, size) = match entry.mode {
+        Mode::Dir => (b'5', 0),
+        Mode::Symlink => (b'2', 0),
+        Mode::File | Mode::Executable => (b'0', entry.data.len() as u64),
+    };
+
+    let mut header = [0; BLOCK];
+    put(&mut header, 0, name.as_bytes());
+    octal(&mut header, 100, 8, permissions(entry.mode).into())?;
+    // uid, gid and mtime
+    octal(&mut header, 108, 8, 0)?;
+    octal(&mut header, 116, 8, 0)?;
+    octal(&mut header, 124, 12, size)?;
+    octal(&mut header, 136, 12, 0)?;
+    header[156] = kind;
+    if entry.mode == Mode::Symlink {
+        if entry.data.len() > 100 {
+            return Err(Error::PathTooLong(
+                String::from_utf8_lossy(&entry.data).into_owned(),
+            ));
+        }
+        put(&mut header, 157, &entry.data);
+    }
+    put(&mut header, 257, b"ustar\x0000");
+    // device numbers
+    octal(&mut header, 329, 8, 0)?;
+    octal(&mut header, 337, 8, 0)?;
+    put(&mut header, 345, prefix.as_bytes());
+
+    // the checksum is computed as if its own field held spaces
+    put(&mut header, 148, &[b' '; 8]);
+    let checksum: u32 = header.iter().copied().map(u32::from).sum();
+    put(&mut header, 148, format!("{checksum:06o}\0 ").as_bytes());
+
+    Ok(header)
+}
+
+/// The date of every entry of a zip archive, January 1st 1980, the earliest one it can record.
+const ZIP_DATE: u16 = (1 << 5) | 1;
+/// The flag marking names as UTF-8.
+const ZIP_UTF8: u16 = 1 << 11;
+/// The zip version required to extract the archive, 2.0.
+const ZIP_VERSION: u16 = 20;
+/// The archive was made by unix, so that the upper half of the attributes is read as a mode.
+const ZIP_MADE_BY: u16 = (3 << 8) | ZIP_VERSION;
+
+/// An entry of the central directory of a zip archive.
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+    attributes: u32,
+}
+
+/// Write the entries as a zip archive, storing each uncompressed.
+fn write_zip<W: Write>(
+    entries: impl Iterator<Item = Result<Entry, super::Error>>,
+    out: &mut Hashing<W>,
+) -> ArchiveResult<()> {
+    let too_large = || Error::TooLarge(Format::Zip);
+    let to_u16 = |n: usize| u16::try_from(n).map_err(|_| too_large());
+    let to_u32 = |n: u64| u32::try_from(n).map_err(|_| too_large());
+
+    let mut central = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let (name, kind) = match entry.mode {
+            Mode::Dir => (format!("{}/", entry.path), 0o040_000),
+            Mode::Symlink => (entry.path, 0o120_000),
+            Mode::File | Mode::Executable => (entry.path, 0o100_000),
+        };
+        // the unix mode is kept in the upper half, and the directory flag of DOS in the lower
+        let dos = if entry.mode == Mode::Dir { 0x10 } else { 0 };
+        let zip = ZipEntry {
+            crc: crc32(&entry.data),
+            size: to_u32(entry.data.len() as u64)?,
+            offset: to_u32(out.written)?,
+            attributes: ((kind | permissions(entry.mode)) << 16) | dos,
+            name,
+        };
+
+        out.write_all(&0x0403_4b50_u32.to_le_bytes())?;
+        for field in [ZIP_VERSION, ZIP_UTF8, 0, 0, ZIP_DATE] {
+            out.write_all(&field.to_le_bytes())?;
+        }
+        for field in [zip.crc, zip.size, zip.size] {
+            out.write_all(&field.to_le_bytes())?;
+        }
+        out.write_all(&to_u16(zip.name.len())?.to_le_bytes())?;
+        out.write_all(&0_u16.to_le_bytes())?;
+        out.write_all(zip.name.as_bytes())?;
+        out.write_all(&entry.data)?;
+
+        central.push(zip);
+    }
+
+    let start = out.written;
+    for zip in &central {
+        out.write_all(&0x0201_4b50_u32.to_le_bytes())?;
+        for field in [ZIP_MADE_BY, ZIP_VERSION, ZIP_UTF8, 0, 0, ZIP_DATE] {
+            out.write_all(&field.to_le_bytes())?;
+        }
+        for field in [zip.crc, zip.size, zip.size] {
+            out.write_all(&field.to_le_bytes())?;
+        }
+        for field in [to_u16(zip.name.len())?, 0, 0, 0, 0] {
+            out.write_all(&field.to_le_bytes())?;
+        }
+        for field in [zip.attributes, zip.offset] {
+            out.write_all(&field.to_le_bytes())?;
+        }
+        out.write_all(zip.name.as_bytes())?;
+    }
+    let size = to_u32(out.written - start)?;
+    let start = to_u32(start)?;
+
+    let count = to_u16(central.len())?;
+    out.write_all(&0x0605_4b50_u32.to_le_bytes())?;
+    for field in [0, 0, count, count] {
+        out.write_all(&field.to_le_bytes())?;
+    }
+    for field in [size, start] {
+        out.write_all(&field.to_le_bytes())?;
+    }
+    out.write_all(&0_u16.to_le_bytes())?;
+    Ok(())
+}
+
+/// The lookup table of the CRC-32 checksum used by zip.
+const CRC32: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC32[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
@@ -0,0 +1,195 @@
+//! # Atom Artifacts
+//!
+//! A published Atom may have auxiliary artifacts attached to it, such as a prebuilt tarball or
+//! an evaluation cache. Each is stored as a blob, under a ref beside the Atom's own:
+//!
+//! ```console
+//! refs/atoms/<id>/_artifacts/<version>/<name>
+//! ```
+//!
+//! Only artifacts declared with a content hash in the \[artifacts] key of the published
+//! manifest may be attached, and their content is verified against it whenever they are
+//! attached or fetched. The Atom's content itself is unaffected, and remains reproducible.
+use std::str::FromStr;
+
+use gix::objs::{Find, FindExt};
+use gix::{ObjectId, Repository, oid};
+use semver::Version;
+use thiserror::Error as ThisError;
+
+use super::{EkalaRemote, Push};
+use crate::id::Id;
+use crate::publish::{ATOM_MANIFEST, ATOM_REF_TOP_LEVEL};
+use crate::store::QueryStore;
+use crate::{ATOM_EXT, Digest, Manifest};
+
+/// The path component under an Atom's refs which artifacts are attached beneath.
+pub const ARTIFACTS: &str = "_artifacts";
+
+/// An error encountered while attaching or fetching an artifact.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// The artifact is not declared in the Atom's published manifest.
+    #[error("`{0}` is not declared in the manifest's `[artifacts]`")]
+    Undeclared(String),
+    /// The content of the artifact does not match the hash declared for it.
+    #[error("`{name}` does not match its declared hash: expected {expected}, found {found}")]
+    Mismatch {
+        /// The name of the artifact.
+        name: String,
+        /// The hash declared in the manifest.
+        expected: Digest,
+        /// The hash of the artifact's content.
+        found: Digest,
+    },
+    /// The Atom's spec tree contains no valid manifest.
+    #[error("The published spec contains no valid manifest")]
+    NoManifest,
+    /// The Atom id or the artifact name is invalid.
+    #[error(transparent)]
+    InvalidName(#[from] crate::id::Error),
+    /// A transparent wrapper for a [`gix::objs::find::existing_object::Error`]
+    #[error(transparent)]
+    Find(#[from] gix::objs::find::existing_object::Error),
+    /// A transparent wrapper for a [`Box<gix::object::write::Error>`]
+    #[error(transparent)]
+    Write(#[from] Box<gix::object::write::Error>),
+    /// A transparent wrapper for a [`Box<gix::reference::edit::Error>`]
+    #[error(transparent)]
+    WriteRef(#[from] Box<gix::reference::edit::Error>),
+    /// A transparent wrapper for a [`super::Error`]
+    #[error(transparent)]
+    Store(#[from] super::Error),
+    /// A transparent wrapper for a [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+type ArtifactResult<T> = Result<T, Error>;
+
+/// The full name of the ref an artifact of the given Atom version is attached under.
+#[must_use]
+pub fn artifact_ref(id: &str, version: &Version, name: &str) -> String {
+    let version = super::encode_version(version);
+    format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{ARTIFACTS}/{version}/{name}")
+}
+
+/// The full name of the spec ref of the given Atom version.
+pub(super) fn spec_ref(id: &str, version: &Version) -> String {
+    let version = super::encode_version(version);
+    format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{version}/{ATOM_MANIFEST}")
+}
+
+/// Look up the hash declared for the named artifact in the manifest of the given spec tree.
+pub(super) fn declared(objects: &impl Find, spec: &oid, name: &str) -> ArtifactResult<Digest> {
+    let mut buf = Vec::new();
+    let tree = objects.find_tree(spec, &mut buf)?;
+    let entry = tree
+        .entries
+        .iter()
+        .find(|e| e.mode.is_blob() && e.filename.ends_with(ATOM_EXT.as_bytes()))
+        .ok_or(Error::NoManifest)?;
+    let oid = entry.oid.to_owned();
+
+    let mut buf = Vec::new();
+    let blob = objects.find_blob(&oid, &mut buf)?;
+    let manifest = std::str::from_utf8(blob.data)
+        .ok()
+        .and_then(|content| Manifest::from_str(content).ok())
+        .ok_or(Error::NoManifest)?;
+
+    manifest
+        .artifacts
+        .get(name)
+        .map(|a| a.hash)
+        .ok_or_else(|| Error::Undeclared(name.to_owned()))
+}
+
+/// Verify `content` against the hash declared for it.
+pub(super) fn verify(name: &str, expected: Digest, content: &[u8]) -> ArtifactResult<()> {
+    let found = Digest::of(content);
+    if found != expected {
+        return Err(Error::Mismatch {
+            name: name.to_owned(),
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+/// Attach an artifact to a version of an Atom published from this repository, and push it to
+/// the given remote, returning the name of its ref.
+///
+/// # Errors
+///
+/// This function will return an error if the Atom version has not been published, the
+/// artifact is not declared in its manifest, or `content` does not match the declared hash.
+pub fn attach(
+    repo: &Repository,
+    remote: &str,
+    id: &str,
+    version: &Version,
+    name: &str,
+    content: &[u8],
+) -> ArtifactResult<String> {
+    use gix::refs::transaction::PreviousValue;
+
+    Id::from_str(id)?;
+    Id::from_str(name)?;
+
+    let spec = spec_ref(id, version);
+    let spec = repo
+        .find_reference(spec.as_str())
+        .ok()
+        .and_then(|r| r.into_fully_peeled_id().ok())
+        .ok_or_else(|| super::Error::NoRef(spec, remote.to_owned()))?;
+
+    verify(name, declared(&repo.objects, &spec, name)?, content)?;
+
+    let artifact = artifact_ref(id, version, name);
+    super::validate_ref_name(&artifact)?;
+    let blob = repo.write_blob(content).map_err(Box::new)?;
+    repo.reference(
+        artifact.as_str(),
+        blob,
+        PreviousValue::MustNotExistOrEqual(blob.detach().into()),
+        format!("artifact: {id}: {version}: {name}"),
+    )
+    .map_err(Box::new)?;
+
+    Push::refs([artifact.as_str()]).run(repo.git_dir().to_string_lossy().as_ref(), remote)?;
+
+    tracing::info!(%artifact, message = "Attached artifact");
+    Ok(artifact)
+}
+
+/// Fetch an artifact of an Atom version from the given remote, verified against the hash
+/// declared in the Atom's published manifest.
+///
+/// # Errors
+///
+/// This function will return an error if either the artifact or the Atom's spec cannot be
+/// fetched, or the artifact does not match its declared hash.
+pub fn fetch(
+    remote: &gix::Remote,
+    id: &str,
+    version: &Version,
+    name: &str,
+) -> ArtifactResult<Vec<u8>> {
+    Id::from_str(id)?;
+    Id::from_str(name)?;
+
+    let spec: ObjectId = remote.get_ref(spec_ref(id, version).as_str())?;
+    let artifact: ObjectId = remote.get_ref(artifact_ref(id, version, name).as_str())?;
+
+    let objects = &remote.repo().objects;
+    let expected = declared(objects, &spec, name)?;
+
+    let mut buf = Vec::new();
+    let content = objects.find_blob(&artifact, &mut buf)?.data.to_vec();
+    verify(name, expected, &content)?;
+
+    tracing::debug!(remote = remote.symbol(), %id, %version, name, "Fetched artifact");
+    Ok(content)
+}
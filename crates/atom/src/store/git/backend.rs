@@ -0,0 +1,107 @@
+//! # Git Store Backend
+//!
+//! A remote of a Git repository, as an [`EkalaStore`], so that it may be used through a trait
+//! object alongside the other backends. The remote is found again for each operation, which
+//! only reads the repository's configuration, so the backend owns nothing but the repository
+//! and the name of the remote.
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use gix::Repository;
+use semver::Version;
+
+use super::{Error, V1_ROOT};
+use crate::id::Id;
+use crate::policy::Policy;
+use crate::store::{
+    BoxError, BoxFuture, EkalaStore, Init, InitStatus, Published, QueryAtoms, QueryPolicy,
+    QueryStore,
+};
+
+/// A remote of a Git repository, as an [`EkalaStore`].
+pub struct GitBackend {
+    repo: Repository,
+    remote: String,
+}
+
+impl GitBackend {
+    /// Constructs a new [`GitBackend`] for the remote named `remote` of `repo`.
+    #[must_use]
+    pub fn new(repo: Repository, remote: String) -> Self {
+        GitBackend { repo, remote }
+    }
+
+    /// The repository the Atoms of the store are published from.
+    #[must_use]
+    pub fn repo(&self) -> &Repository {
+        &self.repo
+    }
+
+    fn remote(&self) -> Result<gix::Remote<'_>, Error> {
+        self.repo
+            .find_remote(self.remote.as_str())
+            .map_err(|e| Error::NoRemote(Box::new(e)))
+    }
+}
+
+impl EkalaStore for GitBackend {
+    fn name(&self) -> &str {
+        &self.remote
+    }
+
+    fn root_ref(&self) -> &str {
+        V1_ROOT
+    }
+
+    fn init(&self) -> Result<(), BoxError> {
+        Ok(self.remote()?.ekala_init()?)
+    }
+
+    fn root(&self) -> Result<String, BoxError> {
+        Ok(self.remote()?.ekala_root()?.to_string())
+    }
+
+    fn status(&self) -> Result<InitStatus<String>, BoxError> {
+        let status = self.remote()?.ekala_status()?;
+        Ok(status.map(|root| root.to_string()))
+    }
+
+    fn published(&self) -> Result<BTreeMap<Id, BTreeSet<Version>>, BoxError> {
+        Ok(self.remote()?.published_atoms()?)
+    }
+
+    fn policy(&self) -> Result<Option<Policy>, BoxError> {
+        Ok(self.remote()?.ekala_policy()?)
+    }
+
+    fn get_ref(&self, name: &str) -> Result<String, BoxError> {
+        Ok(self.remote()?.get_ref(name)?.to_string())
+    }
+
+    fn publish<'a>(
+        &'a self,
+        spec: &'a str,
+        paths: Vec<PathBuf>,
+    ) -> BoxFuture<'a, Result<Vec<Result<Published, BoxError>>, BoxError>> {
+        use crate::publish::git::GitPublisher;
+        use crate::publish::{Builder, Publish};
+
+        Box::pin(async move {
+            let (_, publisher) = GitPublisher::new(&self.repo, &self.remote, spec)?.build()?;
+            let mut outcomes: Vec<Result<Published, BoxError>> = publisher
+                .publish(paths)
+                .into_iter()
+                .map(|outcome| match outcome {
+                    Ok(Ok(record)) => Ok(Ok(record.id().id().clone())),
+                    Ok(Err(skipped)) => Ok(Err(skipped)),
+                    Err(e) => Err(e.into()),
+                })
+                .collect();
+
+            let mut errors = Vec::new();
+            publisher.await_pushes(&mut errors).await;
+            outcomes.extend(errors.into_iter().map(|e| Err(e.into())));
+            Ok::<_, BoxError>(outcomes)
+        })
+    }
+}
@@ -0,0 +1,68 @@
+//! # Channels and Tags
+//!
+//! A channel names the version of an Atom its consumers should currently use, e.g. `stable`,
+//! and moves to newer versions as they are released on it, while a tag names a version for
+//! good, e.g. `release-2024`. Both are refs beside the versions of the Atom, pointing to the
+//! Atom commit of the version they name:
+//!
+//! ```console
+//! refs/atoms/<id>/_channels/<name>
+//! refs/atoms/<id>/_tags/<name>
+//! ```
+//!
+//! A version is released on a channel, or tagged, by pushing its Atom commit to the ref:
+//!
+//! ```console
+//! git push <store> refs/atoms/<id>/<version>/atom:refs/atoms/<id>/_channels/<name>
+//! ```
+//!
+//! The store's hooks only accept a channel, or tag, pointing to a published version of its
+//! Atom which is not yanked, and never accept a tag being moved or deleted. A dependency
+//! following either is resolved to the version it points to, and locked to it.
+use semver::Version;
+
+use super::Snapshot;
+use crate::id::Id;
+use crate::manifest::Follow;
+use crate::publish::{ATOM, ATOM_REF_TOP_LEVEL};
+
+/// The kind of the refs of the channels of an Atom.
+pub const CHANNELS: &str = "_channels";
+
+/// The kind of the refs of the tags of an Atom.
+pub const TAGS: &str = "_tags";
+
+/// The full name of the ref of the channel, or tag, of the Atom `id`.
+#[must_use]
+pub fn ref_name(id: &str, follow: Follow) -> String {
+    let (kind, name) = match follow {
+        Follow::Channel(name) => (CHANNELS, name),
+        Follow::Tag(name) => (TAGS, name),
+    };
+    format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{kind}/{name}")
+}
+
+impl Snapshot {
+    /// Return the version of the Atom `id` its channel, or tag, pointed to when the snapshot
+    /// was taken, if it existed, and pointed to a version which is not yanked.
+    #[must_use]
+    pub fn followed(&self, id: &Id, follow: Follow) -> Option<Version> {
+        let version = self.pointed(id, follow)?;
+        self.resolvable()
+            .get(id)
+            .is_some_and(|versions| versions.contains(&version))
+            .then_some(version)
+    }
+
+    /// Return the published version of the Atom `id` its channel, or tag, pointed to when the
+    /// snapshot was taken, whether it is yanked or not.
+    pub(super) fn pointed(&self, id: &Id, follow: Follow) -> Option<Version> {
+        let commit = self.get(&ref_name(id, follow))?;
+        let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/");
+        self.refs().find_map(|(name, target)| {
+            let version = name.strip_prefix(&prefix)?.strip_suffix(ATOM)?;
+            let version = super::decode_version(version.strip_suffix('/')?).ok()?;
+            (target == commit).then_some(version)
+        })
+    }
+}
@@ -0,0 +1,126 @@
+//! # Content Streaming
+//!
+//! Tools embedding Atoms, e.g. to pack them into archives, upload them, or feed them to an
+//! in-memory build system, have no use for a checkout. [`entries`] walks an Atom's content
+//! tree and yields each of its entries, with its path, mode and data, without touching the
+//! filesystem. Blobs are only read as the walk reaches them, so consumers may stop early, or
+//! stream arbitrarily large Atoms through a bounded amount of memory.
+use std::io::Read;
+
+use gix::bstr::ByteSlice;
+use gix::{ObjectId, Repository};
+
+use super::Error;
+
+/// The kind of an entry of an Atom's content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// A directory, yielded before any of its entries.
+    Dir,
+    /// A regular file.
+    File,
+    /// An executable file.
+    Executable,
+    /// A symbolic link, whose data is its target.
+    Symlink,
+}
+
+/// A single entry of an Atom's content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The path of the entry, relative to the root of the content, separated by `/`.
+    pub path: String,
+    /// The kind of the entry.
+    pub mode: Mode,
+    /// The content of a file, or the target of a link, and empty for directories.
+    pub data: Vec<u8>,
+}
+
+impl Entry {
+    /// A reader over the data of the entry.
+    #[must_use]
+    pub fn reader(&self) -> impl Read + '_ {
+        self.data.as_slice()
+    }
+}
+
+/// An iterator over the entries of a content tree, in depth-first order, as sorted by git.
+///
+/// Submodules are skipped, as their content is not part of the tree.
+pub struct Entries<'repo> {
+    repo: &'repo Repository,
+    /// The entries of each directory entered, yet to be visited, in reverse order.
+    pending: Vec<(String, gix::objs::tree::EntryMode, ObjectId)>,
+}
+
+/// Stream the entries of the tree with the given id, e.g. an Atom's content.
+#[must_use]
+pub fn entries(repo: &Repository, tree: ObjectId) -> Entries<'_> {
+    let mut entries = Entries {
+        repo,
+        pending: Vec::new(),
+    };
+    entries.enter("", tree);
+    entries
+}
+
+impl Entries<'_> {
+    /// Queue the entries of the tree at `prefix` to be visited next.
+    fn enter(&mut self, prefix: &str, tree: ObjectId) -> Option<Error> {
+        let tree = match self.repo.find_tree(tree) {
+            Ok(tree) => tree,
+            Err(e) => return Some(Box::new(e).into()),
+        };
+        let decoded = match tree.decode() {
+            Ok(decoded) => decoded,
+            Err(e) => return Some(e.into()),
+        };
+        let start = self.pending.len();
+        for e in &decoded.entries {
+            let Ok(name) = e.filename.to_str() else {
+                return Some(Error::NonUtf8Path(e.filename.to_owned()));
+            };
+            let path = if prefix.is_empty() {
+                name.to_owned()
+            } else {
+                format!("{prefix}/{name}")
+            };
+            self.pending.push((path, e.mode, e.oid.to_owned()));
+        }
+        self.pending[start..].reverse();
+        None
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Result<Entry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, mode, oid) = self.pending.pop()?;
+            let mode = if mode.is_tree() {
+                if let Some(e) = self.enter(&path, oid) {
+                    return Some(Err(e));
+                }
+                Mode::Dir
+            } else if mode.is_link() {
+                Mode::Symlink
+            } else if mode.is_executable() {
+                Mode::Executable
+            } else if mode.is_blob() {
+                Mode::File
+            } else {
+                continue;
+            };
+
+            let data = match mode {
+                Mode::Dir => Vec::new(),
+                _ => match self.repo.find_blob(oid) {
+                    Ok(mut blob) => blob.take_data(),
+                    Err(e) => return Some(Err(Box::new(e).into())),
+                },
+            };
+            return Some(Ok(Entry { path, mode, data }));
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! # Shared Evaluation Cache
+//!
+//! Evaluation outputs may also be shared through a Git store, so that CI runs and other
+//! contributors can skip evaluating Atoms someone already has. Each output is stored as a
+//! blob under a ref mirroring the layout of the local [`crate::eval::EvalCache`]:
+//!
+//! ```console
+//! refs/ekala/eval/<hash>/<version>/<content>
+//! ```
+//!
+//! Outputs are shared once and never replaced, so a store guarded by the hooks of
+//! [`super::verify`] only accepts them for Atoms published to it, from those permitted to
+//! publish them.
+use gix::{ObjectId, Repository};
+
+use super::{EkalaRemote, Error, Push};
+use crate::eval::Key;
+use crate::store::QueryStore;
+
+/// The ref prefix evaluation outputs are shared under.
+pub const EVAL_REF_PREFIX: &str = "refs/ekala/eval";
+
+/// The full name of the ref the output for the given key is shared under.
+#[must_use]
+pub fn eval_ref(key: &Key) -> String {
+    format!(
+        "{EVAL_REF_PREFIX}/{}/{}/{}",
+        key.hash(),
+        super::encode_version(key.version()),
+        key.content()
+    )
+}
+
+/// Fetch the output for the given key from the remote, if it has been shared.
+///
+/// # Errors
+///
+/// This function will return an error if the remote cannot be reached, or the output read.
+pub fn fetch(remote: &gix::Remote, key: &Key) -> Result<Option<Vec<u8>>, Error> {
+    let id: ObjectId = match remote.get_ref(eval_ref(key).as_str()) {
+        Ok(id) => id,
+        Err(Error::NoRef(..) | Error::Refs(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let blob = remote.repo().find_blob(id).map_err(Box::new)?;
+    tracing::debug!(remote = remote.symbol(), key = %eval_ref(key), "Fetched evaluation");
+    Ok(Some(blob.data.clone()))
+}
+
+/// Share the output for the given key by pushing it to the remote.
+///
+/// # Errors
+///
+/// This function will return an error if the output cannot be written, or pushed, e.g. because
+/// a different output was already shared for the key.
+pub fn share(repo: &Repository, remote: &str, key: &Key, output: &[u8]) -> Result<(), Error> {
+    use gix::refs::transaction::PreviousValue;
+
+    let name = eval_ref(key);
+    let blob = repo.write_blob(output).map_err(Box::new)?;
+    repo.reference(
+        name.as_str(),
+        blob,
+        PreviousValue::Any,
+        format!("eval: {}", key.version()),
+    )
+    .map_err(Box::new)?;
+
+    Push::refs([name.as_str()]).run(repo.git_dir().to_string_lossy().as_ref(), remote)?;
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! # Excluding Content
+//!
+//! An Atom's content is the whole directory next to its manifest, which may hold files it has
+//! no use for once published, e.g. test fixtures or large assets. They are left out by globs in
+//! the `exclude` key of its manifest, matched against paths relative to its content directory:
+//!
+//! ```toml
+//! [atom]
+//! exclude = ["tests/fixtures", "*.png"]
+//! ```
+//!
+//! As in a `.gitignore`, a glob without a `/` matches an entry of that name at any depth, `*`
+//! never matches a `/`, while `**` does, and excluding a directory excludes everything in it.
+//! Directories left empty are dropped.
+//!
+//! The content is filtered from the trees of the commit the Atom is published from, rather than
+//! from a checkout, so that the very same trees are recomputed from it when the Atom is verified,
+//! by clients and the store's hooks alike.
+use gix::bstr::{BStr, BString, ByteSlice, ByteVec};
+use gix::glob::wildmatch::Mode;
+use gix::objs::{Find, FindExt, Tree, WriteTo};
+use gix::{ObjectId, oid};
+
+use super::Error;
+
+/// The content of an Atom as published, where it differs from the directory it is published
+/// from, as its manifest excludes paths from it, or [includes](super::include) others.
+#[derive(Debug, Clone)]
+pub struct Rewritten {
+    /// The id of the root tree of the content.
+    pub id: ObjectId,
+    /// The root tree of the content.
+    pub tree: Tree,
+    /// The other trees of the content which differ from the source's, to be written along
+    /// with the Atom.
+    pub trees: Vec<Tree>,
+}
+
+/// Leave the paths matching any of the `globs` out of the content tree `tree`, computing the
+/// trees which differ from the source's in memory, without writing them.
+///
+/// Returns `None` if no path matches, so that the content is published as is.
+///
+/// # Errors
+///
+/// This function will return an error if any tree of the content cannot be found, or cannot be
+/// encoded.
+pub fn filter(
+    objects: &impl Find,
+    hash: gix::hash::Kind,
+    tree: &oid,
+    globs: &[String],
+) -> Result<Option<Rewritten>, Error> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let mut trees = Vec::new();
+    let Some(tree) = walk(objects, hash, tree, BStr::new(""), globs, &mut trees)? else {
+        return Ok(None);
+    };
+    Ok(Some(Rewritten {
+        id: hash_tree(hash, &tree)?,
+        tree,
+        trees,
+    }))
+}
+
+/// Filter the tree `tree` at `prefix`, returning the filtered tree, if it differs.
+fn walk(
+    objects: &impl Find,
+    hash: gix::hash::Kind,
+    tree: &oid,
+    prefix: &BStr,
+    globs: &[String],
+    trees: &mut Vec<Tree>,
+) -> Result<Option<Tree>, Error> {
+    let mut buf = Vec::new();
+    let source: Tree = objects.find_tree(tree, &mut buf)?.into();
+
+    let mut changed = false;
+    let mut entries = Vec::with_capacity(source.entries.len());
+    for mut entry in source.entries {
+        let mut path = BString::from(prefix);
+        if !path.is_empty() {
+            path.push_byte(b'/');
+        }
+        path.push_str(&entry.filename);
+
+        if excluded(globs, path.as_bstr(), entry.filename.as_bstr()) {
+            changed = true;
+            continue;
+        }
+        if entry.mode.is_tree() {
+            if let Some(tree) = walk(objects, hash, &entry.oid, path.as_bstr(), globs, trees)? {
+                changed = true;
+                if tree.entries.is_empty() {
+                    continue;
+                }
+                entry.oid = hash_tree(hash, &tree)?;
+                trees.push(tree);
+            }
+        }
+        entries.push(entry);
+    }
+
+    Ok(changed.then_some(Tree { entries }))
+}
+
+/// Compute the id of the given tree in memory.
+pub(super) fn hash_tree(hash: gix::hash::Kind, tree: &Tree) -> Result<ObjectId, Error> {
+    let mut buf = Vec::with_capacity(tree.size() as usize);
+    tree.write_to(&mut buf)?;
+    Ok(gix::objs::compute_hash(hash, gix::object::Kind::Tree, &buf))
+}
+
+/// Whether the entry `name`, at `path` relative to the content, matches any of the `globs`.
+fn excluded(globs: &[String], path: &BStr, name: &BStr) -> bool {
+    globs.iter().any(|glob| {
+        let glob = glob.trim_matches('/');
+        let target = if glob.contains('/') { path } else { name };
+        gix::glob::wildmatch(glob.into(), target, Mode::NO_MATCH_SLASH_LITERAL)
+    })
+}
@@ -0,0 +1,205 @@
+//! # Fault Injection
+//!
+//! Publishing must cope with a store misbehaving halfway through: a push rejected after the
+//! local refs were written, a connection dropped in the middle of a fetch, or a ref locked by
+//! a concurrent writer. Such failures are rare and hard to provoke against a real store, so
+//! with the `fault-injection` feature, an [`Injection`] armed for a repository makes the next
+//! matching operations on it fail, deterministically:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use atom::store::git::fault::{Fault, Injection};
+//!
+//! let repo = gix::open(".")?;
+//! // let the first push through, then reject the next two
+//! let armed = Injection::new(Fault::RejectPush)
+//!     .after(1)
+//!     .times(2)
+//!     .arm(&repo);
+//! // ... publish, and observe how the failures are handled
+//! assert!(armed.tripped() <= 2);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Injections are keyed by the git directory of the repository, so tests running concurrently
+//! against different repositories never observe each other's faults. An injection is disarmed
+//! as soon as it is dropped.
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use gix::Repository;
+
+/// The injections currently armed, across every repository.
+static ARMED: LazyLock<Mutex<Vec<Slot>>> = LazyLock::new(Mutex::default);
+
+/// A failure which can be injected into the operations of the git store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The remote rejects a push of an Atom's refs, as git reports it, after the refs were
+    /// written locally.
+    RejectPush,
+    /// The connection to the remote is lost after it advertised its refs, but before any
+    /// objects were received.
+    Disconnect,
+    /// A ref written by a transaction is locked by another writer, failing the transaction
+    /// exactly as real contention would.
+    LockContention,
+}
+
+/// An injection of a [`Fault`], built up before being armed for a repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Injection {
+    fault: Fault,
+    after: usize,
+    times: Option<usize>,
+}
+
+/// An [`Injection`] armed for a repository, which is disarmed when dropped.
+#[derive(Debug)]
+#[must_use = "the injection is disarmed as soon as it is dropped"]
+pub struct Armed {
+    id: u64,
+}
+
+/// The state of an armed injection.
+struct Slot {
+    id: u64,
+    git_dir: PathBuf,
+    injection: Injection,
+    /// The number of matching operations seen so far, whether failed or not.
+    seen: usize,
+    /// The number of matching operations failed so far.
+    tripped: usize,
+}
+
+impl Injection {
+    /// Inject `fault` into every matching operation, until configured otherwise.
+    #[must_use]
+    pub fn new(fault: Fault) -> Self {
+        Injection {
+            fault,
+            after: 0,
+            times: None,
+        }
+    }
+
+    /// Let the first `after` matching operations succeed before failing any.
+    #[must_use]
+    pub fn after(mut self, after: usize) -> Self {
+        self.after = after;
+        self
+    }
+
+    /// Fail no more than `times` matching operations, letting those following succeed again,
+    /// e.g. to test that a retry recovers.
+    #[must_use]
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Arm the injection for the operations on `repo`, or any other handle to the same git
+    /// directory.
+    pub fn arm(self, repo: &Repository) -> Armed {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        lock().push(Slot {
+            id,
+            git_dir: repo.git_dir().to_path_buf(),
+            injection: self,
+            seen: 0,
+            tripped: 0,
+        });
+        tracing::debug!(fault = ?self.fault, git_dir = %repo.git_dir().display(), "Armed a fault");
+        Armed { id }
+    }
+}
+
+impl Armed {
+    /// Return the number of operations failed by the injection so far.
+    #[must_use]
+    pub fn tripped(&self) -> usize {
+        lock()
+            .iter()
+            .find(|slot| slot.id == self.id)
+            .map_or(0, |slot| slot.tripped)
+    }
+}
+
+impl Drop for Armed {
+    fn drop(&mut self) {
+        lock().retain(|slot| slot.id != self.id);
+    }
+}
+
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::RejectPush => write!(f, "push rejection"),
+            Fault::Disconnect => write!(f, "disconnect"),
+            Fault::LockContention => write!(f, "ref lock contention"),
+        }
+    }
+}
+
+/// Whether an operation on the repository at `git_dir` which may suffer `fault` is to fail,
+/// counting it against every injection of the fault armed for the repository.
+pub(crate) fn trip(git_dir: &Path, fault: Fault) -> bool {
+    let mut tripped = false;
+    for slot in lock()
+        .iter_mut()
+        .filter(|slot| slot.injection.fault == fault && slot.git_dir == git_dir)
+    {
+        slot.seen += 1;
+        let Injection { after, times, .. } = slot.injection;
+        if slot.seen > after && times.map_or(true, |times| slot.tripped < times) {
+            slot.tripped += 1;
+            tripped = true;
+        }
+    }
+    if tripped {
+        tracing::warn!(%fault, git_dir = %git_dir.display(), "Injecting a fault");
+    }
+    tripped
+}
+
+/// The error git reports when the remote rejects the push of `name`.
+pub(crate) fn rejected(name: &str) -> std::io::Error {
+    std::io::Error::other(format!(
+        " ! [remote rejected] {name} -> {name} (injected fault)\nerror: failed to push some refs"
+    ))
+}
+
+/// The error reported when the remote hangs up in the middle of a fetch.
+pub(crate) fn disconnected() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::ConnectionReset,
+        "the remote end hung up unexpectedly (injected fault)",
+    )
+}
+
+/// Lock the loose ref `name` of `repo`, as a concurrent writer would, until the returned lock
+/// is dropped.
+pub(crate) fn hold_lock(repo: &Repository, name: &str) -> Option<gix::lock::File> {
+    use gix::lock::acquire::Fail;
+
+    let path = repo.common_dir().join(name);
+    gix::lock::File::acquire_to_update_resource(
+        path,
+        Fail::Immediately,
+        Some(repo.common_dir().to_path_buf()),
+    )
+    .map_err(|e| tracing::warn!(%e, "Could not inject ref lock contention"))
+    .ok()
+}
+
+/// Lock the shared list of armed injections, which is never left inconsistent by a panic.
+fn lock() -> std::sync::MutexGuard<'static, Vec<Slot>> {
+    ARMED
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
@@ -0,0 +1,150 @@
+//! # Source Retention
+//!
+//! Every published Atom version keeps a `src` ref to the commit it was published from, so that
+//! its content can be verified against that source at any time. In a store publishing often,
+//! those refs keep the entire history of the repository alive, however old. A [`Retention`]
+//! policy selects which of them are worth keeping, and [`prune`] deletes the others, trading
+//! the verifiability of the affected versions for space.
+//!
+//! Only the `src` refs are ever pruned: the content and spec of every version remain, so that
+//! published Atoms stay resolvable. Pruning happens in the repository the store lives in, as
+//! the store's hooks reject the deletion of Atom refs pushed to it, and the objects are only
+//! reclaimed by a subsequent `git gc`.
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use gix::{ObjectId, Repository};
+use semver::Version;
+
+use super::transaction::RefTransaction;
+use super::{Error, decode_version, run_git_command};
+use crate::id::Id;
+use crate::publish::{ATOM_ORIGIN, ATOM_REF_TOP_LEVEL};
+
+/// Which sources of published Atom versions to keep.
+///
+/// With no rule set, every source is kept. With both set, a source is kept as long as either
+/// rule keeps it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// Keep the sources of this many of the latest versions of each Atom.
+    pub latest: Option<usize>,
+    /// Keep the sources whose commit is younger than this.
+    pub max_age: Option<Duration>,
+}
+
+impl Retention {
+    /// Whether the policy keeps every source, i.e. has no rule set.
+    #[must_use]
+    pub fn keeps_all(&self) -> bool {
+        self.latest.is_none() && self.max_age.is_none()
+    }
+}
+
+/// The source of a published Atom version, as referenced by its `src` ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// The id of the Atom.
+    pub id: Id,
+    /// The published version.
+    pub version: Version,
+    /// The full name of the `src` ref.
+    pub name: String,
+    /// The commit the version was published from.
+    pub origin: ObjectId,
+    /// The commit time of the origin, in seconds since the unix epoch.
+    pub time: i64,
+}
+
+/// List the sources of the Atom versions published in the repository itself.
+///
+/// # Errors
+///
+/// This function will return an error if the refs of the repository cannot be listed.
+pub fn sources(repo: &Repository) -> Result<Vec<Source>, Error> {
+    let git_dir = repo.git_dir().to_string_lossy().to_string();
+    let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/");
+    let listing = run_git_command(&[
+        "-C",
+        &git_dir,
+        "for-each-ref",
+        "--format=%(objectname) %(committerdate:unix) %(refname)",
+        &prefix,
+    ])?;
+
+    Ok(String::from_utf8_lossy(&listing)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let (origin, time, name) = (fields.next()?, fields.next()?, fields.next()?);
+            let path = name.strip_prefix(&prefix)?;
+            let [id, version, ATOM_ORIGIN] = path.split('/').collect::<Vec<_>>()[..] else {
+                return None;
+            };
+            Some(Source {
+                id: Id::from_str(id).ok()?,
+                version: decode_version(version).ok()?,
+                name: name.to_owned(),
+                origin: super::parse_object_id(repo, origin.as_bytes())?,
+                time: time.parse().ok()?,
+            })
+        })
+        .collect())
+}
+
+/// Select the sources the `retention` policy does not keep as of `now`, in seconds since the
+/// unix epoch, ordered by Atom id and version.
+#[must_use]
+pub fn prunable(sources: Vec<Source>, retention: Retention, now: i64) -> Vec<Source> {
+    if retention.keeps_all() {
+        return Vec::new();
+    }
+
+    let mut atoms: BTreeMap<Id, Vec<Source>> = BTreeMap::new();
+    for source in sources {
+        atoms.entry(source.id.clone()).or_default().push(source);
+    }
+
+    let young = |source: &Source| {
+        retention.max_age.is_some_and(|age| {
+            now.saturating_sub(source.time) < i64::try_from(age.as_secs()).unwrap_or(i64::MAX)
+        })
+    };
+
+    atoms
+        .into_values()
+        .flat_map(|mut versions| {
+            // latest first
+            versions.sort_by(|a, b| b.version.cmp(&a.version));
+            let latest = retention.latest.unwrap_or(0);
+            let mut prunable: Vec<_> = versions
+                .into_iter()
+                .skip(latest)
+                .filter(|source| !young(source))
+                .collect();
+            prunable.reverse();
+            prunable
+        })
+        .collect()
+}
+
+/// Delete the `src` refs of the given sources, all at once, so that none are deleted if any of
+/// them has moved since it was listed.
+///
+/// The affected versions can no longer be verified against their source, once `git gc` has
+/// reclaimed it.
+///
+/// # Errors
+///
+/// This function will return an error if any of the refs cannot be deleted.
+pub fn prune(repo: &Repository, sources: &[Source]) -> Result<(), Error> {
+    let mut tx = RefTransaction::new(repo);
+    for source in sources {
+        tx.delete(&source.name, source.origin)?;
+    }
+    if !tx.is_empty() {
+        tx.commit()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! # Including Content
+//!
+//! An Atom may need a few files living outside of its directory, e.g. a license, or a Nix
+//! library shared by several Atoms of the repository. They are included by the `include` key of
+//! its manifest, as paths relative to the directory of the manifest:
+//!
+//! ```toml
+//! [atom]
+//! include = ["../common/lib.nix", "../LICENSE"]
+//! ```
+//!
+//! Each is grafted into the Atom's content under [`INCLUDED`], at its path relative to the root
+//! of the repository, e.g. `_include/common/lib.nix`, so that where it ends up only depends on
+//! the revision the Atom is published from. A path may name a file or a whole directory, but not
+//! lie outside of the repository, and the content may not have an entry of its own named
+//! [`INCLUDED`]. Included paths are never [excluded](super::exclude).
+//!
+//! As with excluded paths, the included entries are read from the trees of the commit the Atom
+//! is published from, so that its content is recomputed from it exactly when it is verified.
+use std::collections::BTreeMap;
+
+use gix::objs::tree::{Entry, EntryKind, EntryMode};
+use gix::objs::{Find, FindExt, Tree};
+use gix::{ObjectId, oid};
+
+use super::Error;
+use super::exclude::{Rewritten, filter, hash_tree};
+use crate::Atom;
+
+/// The entry of an Atom's content the paths it includes are grafted under.
+pub const INCLUDED: &str = "_include";
+
+/// An included path, or a directory leading to some.
+enum Node {
+    Entry(EntryMode, ObjectId),
+    Dir(BTreeMap<String, Node>),
+}
+
+/// Resolve the path `include`, relative to the directory `dir` of a manifest, to a path relative
+/// to the root of the repository, or return `None` if it lies outside of it.
+#[must_use]
+pub fn resolve(dir: &str, include: &str) -> Option<String> {
+    if include.starts_with('/') {
+        return None;
+    }
+    let mut parts = Vec::new();
+    for part in dir.split('/').chain(include.split('/')) {
+        match part {
+            "" | "." => {},
+            ".." => {
+                parts.pop()?;
+            },
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Rewrite the content tree `content` of `atom`, if it has one, as it is published from the
+/// directory `dir` of its manifest in the revision with the root tree `root`, leaving out the
+/// paths it excludes, then grafting in those it includes.
+///
+/// Returns `None` if the content is published as is.
+///
+/// # Errors
+///
+/// This function will return an error if [`super::exclude::filter`] or [`graft`] does.
+pub fn rewrite(
+    objects: &impl Find,
+    hash: gix::hash::Kind,
+    root: &oid,
+    dir: &str,
+    content: Option<&oid>,
+    atom: &Atom,
+) -> Result<Option<Rewritten>, Error> {
+    let excluded = match content {
+        Some(content) => filter(objects, hash, content, &atom.exclude)?,
+        None => None,
+    };
+    graft(objects, hash, root, dir, content, excluded, &atom.include)
+}
+
+/// Graft the paths in `includes`, relative to the directory `dir` of a manifest, from the root
+/// tree `root` of the revision the Atom is published from, into its content, computing the trees
+/// which differ from the source's in memory, without writing them.
+///
+/// The content is either the tree `content`, if the Atom has one, or the content already
+/// rewritten, as `excluded`, which is returned as is if nothing is included.
+///
+/// # Errors
+///
+/// This function will return an error if an included path lies outside of the repository, does
+/// not exist in `root`, or the content has an entry named [`INCLUDED`] of its own, or any tree
+/// cannot be found, or cannot be encoded.
+pub fn graft(
+    objects: &impl Find,
+    hash: gix::hash::Kind,
+    root: &oid,
+    dir: &str,
+    content: Option<&oid>,
+    excluded: Option<Rewritten>,
+    includes: &[String],
+) -> Result<Option<Rewritten>, Error> {
+    if includes.is_empty() {
+        return Ok(excluded);
+    }
+
+    let mut included = BTreeMap::new();
+    for include in includes {
+        let path = resolve(dir, include).ok_or_else(|| Error::IncludeOutside(include.clone()))?;
+        let (mode, id) =
+            lookup(objects, root, &path)?.ok_or_else(|| Error::IncludeMissing(path.clone()))?;
+        insert(&mut included, &path, mode, id);
+    }
+
+    let (mut tree, mut trees) = match (excluded, content) {
+        (Some(Rewritten { tree, trees, .. }), _) => (tree, trees),
+        (None, Some(content)) => {
+            let mut buf = Vec::new();
+            (objects.find_tree(content, &mut buf)?.into(), Vec::new())
+        },
+        (None, None) => (Tree::empty(), Vec::new()),
+    };
+    if tree.entries.iter().any(|e| e.filename == INCLUDED) {
+        return Err(Error::IncludeConflict);
+    }
+    let oid = write(hash, included, &mut trees)?;
+    tree.entries.push(Entry {
+        mode: EntryKind::Tree.into(),
+        filename: INCLUDED.into(),
+        oid,
+    });
+    tree.entries.sort();
+
+    Ok(Some(Rewritten {
+        id: hash_tree(hash, &tree)?,
+        tree,
+        trees,
+    }))
+}
+
+/// Look up the entry at `path` in the tree `root`, returning its mode and id, if it exists.
+fn lookup(
+    objects: &impl Find,
+    root: &oid,
+    path: &str,
+) -> Result<Option<(EntryMode, ObjectId)>, Error> {
+    let mut found = (EntryMode::from(EntryKind::Tree), root.to_owned());
+    for part in path.split('/') {
+        if !found.0.is_tree() {
+            return Ok(None);
+        }
+        let mut buf = Vec::new();
+        let tree = objects.find_tree(&found.1, &mut buf)?;
+        match tree.entries.iter().find(|e| e.filename == part) {
+            Some(entry) => found = (entry.mode, entry.oid.to_owned()),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(found))
+}
+
+/// Insert the entry at `path` into the directory `dir`, unless a directory leading to it is
+/// already included as a whole.
+fn insert(dir: &mut BTreeMap<String, Node>, path: &str, mode: EntryMode, id: ObjectId) {
+    match path.split_once('/') {
+        None => {
+            dir.insert(path.to_owned(), Node::Entry(mode, id));
+        },
+        Some((name, rest)) => {
+            let node = dir
+                .entry(name.to_owned())
+                .or_insert_with(|| Node::Dir(BTreeMap::new()));
+            if let Node::Dir(dir) = node {
+                insert(dir, rest, mode, id);
+            }
+        },
+    }
+}
+
+/// Compute the trees of the directory `dir` in memory, returning the id of its own.
+fn write(
+    hash: gix::hash::Kind,
+    dir: BTreeMap<String, Node>,
+    trees: &mut Vec<Tree>,
+) -> Result<ObjectId, Error> {
+    let mut entries = Vec::with_capacity(dir.len());
+    for (name, node) in dir {
+        let (mode, oid) = match node {
+            Node::Entry(mode, id) => (mode, id),
+            Node::Dir(dir) => (EntryKind::Tree.into(), write(hash, dir, trees)?),
+        };
+        entries.push(Entry {
+            mode,
+            filename: name.into(),
+            oid,
+        });
+    }
+    // git orders the entries of a tree as if the names of trees ended with a `/`
+    entries.sort();
+
+    let tree = Tree { entries };
+    let id = hash_tree(hash, &tree)?;
+    trees.push(tree);
+    Ok(id)
+}
@@ -0,0 +1,160 @@
+//! # Atom Info
+//!
+//! Describes a published version of an Atom from its store, without checking it out: the
+//! fields of its manifest, as read from its spec, what its Atom commit records about itself,
+//! i.e. the commit it originates from and the format it was published in, and the full name
+//! of each of its refs, e.g. to inspect an Atom before depending on it.
+use gix::ObjectId;
+use semver::Version;
+
+use super::{Error, Snapshot, atom_ref, encode_version, fetch_specs_in, validate_ref_name};
+use crate::id::Id;
+use crate::publish::{ATOM_ORIGIN, ATOM_REF_TOP_LEVEL};
+use crate::store::QueryStore;
+use crate::{Lockfile, Manifest};
+
+/// A published version of an Atom, as described by its store.
+#[derive(Debug)]
+pub struct Info {
+    version: Version,
+    manifest: Manifest,
+    lock: Option<Lockfile>,
+    commit: ObjectId,
+    origin: Option<ObjectId>,
+    format: Option<String>,
+    refs: Vec<(String, ObjectId)>,
+}
+
+/// A version of an Atom published to a store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listed {
+    /// The version.
+    pub version: Version,
+    /// The Atom commit the version is published as.
+    pub commit: ObjectId,
+    /// Whether the version is yanked, so that resolution passes over it.
+    pub yanked: bool,
+}
+
+impl Info {
+    /// Describe the given version of the Atom `id`, published to `remote` as listed by
+    /// `snapshot`, fetching its spec, and its Atom commit, unless the repository holds them.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the version is not published to `remote`, cannot
+    /// be fetched, or its spec holds a missing or invalid manifest or lock.
+    pub fn describe(
+        remote: &gix::Remote,
+        snapshot: &Snapshot,
+        id: &Id,
+        version: &Version,
+    ) -> Result<Self, Error> {
+        let name = atom_ref(id, version);
+        validate_ref_name(&name)?;
+        let commit = snapshot
+            .get(&name)
+            .ok_or_else(|| Error::NoRef(name.clone(), remote.symbol().to_owned()))?;
+        let (manifest, lock) = fetch_specs_in(remote, snapshot, &[(id, version)])?.remove(0);
+
+        let repo = remote.repo();
+        if !repo.has_object(commit) {
+            remote.get_ref(name.as_str())?;
+        }
+        let atom = repo.find_commit(commit).map_err(Box::new)?;
+        let decoded = atom.decode()?;
+        let header = |key: &str| decoded.extra_headers().find(key).map(|v| v.to_string());
+        let origin =
+            header(ATOM_ORIGIN).and_then(|origin| super::parse_object_id(repo, origin.as_bytes()));
+
+        Ok(Info {
+            version: version.clone(),
+            manifest,
+            lock,
+            commit,
+            origin,
+            format: header("format"),
+            refs: refs_of(snapshot, id, version),
+        })
+    }
+
+    /// Return the version described.
+    #[must_use]
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Return the manifest the version is published with.
+    #[must_use]
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Return the lock the version is published with, if any.
+    #[must_use]
+    pub fn lock(&self) -> Option<&Lockfile> {
+        self.lock.as_ref()
+    }
+
+    /// Return the id of the Atom commit the version is published as.
+    #[must_use]
+    pub fn commit(&self) -> ObjectId {
+        self.commit
+    }
+
+    /// Return the id of the commit the Atom commit records it originates from, if it does.
+    #[must_use]
+    pub fn origin(&self) -> Option<ObjectId> {
+        self.origin
+    }
+
+    /// Return the format the version was published in, as its Atom commit records it.
+    #[must_use]
+    pub fn format(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
+    /// Return the full name of each ref of the version, along with the object it points to,
+    /// in order, including its artifacts.
+    #[must_use]
+    pub fn refs(&self) -> &[(String, ObjectId)] {
+        &self.refs
+    }
+}
+
+impl Snapshot {
+    /// List every version of the Atom `id` published when the snapshot was taken, in order,
+    /// including those yanked.
+    #[must_use]
+    pub fn listed(&self, id: &Id) -> Vec<Listed> {
+        let yanked = self.yanked().remove(id).unwrap_or_default();
+        self.published()
+            .remove(id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|version| {
+                let commit = self.get(&atom_ref(id, &version))?;
+                let yanked = yanked.contains(&version);
+                Some(Listed {
+                    version,
+                    commit,
+                    yanked,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The refs of the given version of the Atom `id` in `snapshot`, by full name, in order.
+fn refs_of(snapshot: &Snapshot, id: &Id, version: &Version) -> Vec<(String, ObjectId)> {
+    use super::artifact::ARTIFACTS;
+
+    let version = encode_version(version);
+    let own = format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{version}/");
+    let artifacts = format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{ARTIFACTS}/{version}/");
+    snapshot
+        .refs()
+        .filter(|(name, _)| name.starts_with(&own) || name.starts_with(&artifacts))
+        .map(|(name, target)| (name.to_owned(), target))
+        .collect()
+}
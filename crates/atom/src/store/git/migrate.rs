@@ -0,0 +1,264 @@
+//! # Legacy Ref Migration
+//!
+//! Early versions of the format published each Atom as a single ref, named after its path in
+//! the source repository and its version:
+//!
+//! ```console
+//! refs/atom/<path>-<version>
+//! ```
+//!
+//! pointing to a commit of the Atom's tree, on top of the commit it was published from. This
+//! module rewrites such refs into the current `refs/atoms/<id>/<version>/{atom,spec,src}`
+//! layout, with a reproducible Atom commit carrying the headers the format requires, verifies
+//! the result as the store would, and pushes it.
+use std::path::{Path, PathBuf};
+
+use gix::objs::FindExt;
+use gix::objs::tree::{Entry, Tree};
+use gix::{ObjectId, Repository};
+use semver::Version;
+use thiserror::Error as ThisError;
+
+use super::verify::{self, RefUpdate, Verifier};
+use super::{Push, run_git_command};
+use crate::id::Id;
+use crate::publish::git::atom_commit;
+use crate::publish::{ATOM, ATOM_MANIFEST, ATOM_ORIGIN, ATOM_REF_TOP_LEVEL};
+use crate::{ATOM_EXT, Manifest};
+
+/// The namespace refs were published under in the legacy layout.
+pub const LEGACY_REF_PREFIX: &str = "refs/atom/";
+
+/// The local namespace legacy refs are fetched into, keeping their objects alive.
+const LEGACY_NAMESPACE: &str = "refs/ekala/legacy/";
+
+/// An error encountered while migrating a legacy ref.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// The legacy commit's tree contains no valid manifest.
+    #[error("`{0}` contains no valid Atom manifest")]
+    NoManifest(String),
+    /// The commit the legacy ref was published from cannot be determined.
+    #[error("`{0}` records no source commit")]
+    NoOrigin(String),
+    /// A ref of the current layout already exists, pointing elsewhere.
+    #[error("`{0}` already exists with different content")]
+    Conflict(String),
+    /// A transparent wrapper for a [`gix::objs::find::existing_object::Error`]
+    #[error(transparent)]
+    Find(#[from] gix::objs::find::existing_object::Error),
+    /// A transparent wrapper for a [`Box<gix::object::write::Error>`]
+    #[error(transparent)]
+    Write(#[from] Box<gix::object::write::Error>),
+    /// A transparent wrapper for a [`Box<gix::reference::edit::Error>`]
+    #[error(transparent)]
+    WriteRef(#[from] Box<gix::reference::edit::Error>),
+    /// A transparent wrapper for a [`verify::Error`]
+    #[error(transparent)]
+    Verify(#[from] verify::Error),
+    /// A transparent wrapper for a [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+type MigrateResult<T> = Result<T, Error>;
+
+/// A legacy ref, rewritten into the objects of the current layout.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// The full name of the legacy ref.
+    pub legacy: String,
+    /// The id of the Atom.
+    pub id: Id,
+    /// The version of the Atom.
+    pub version: Version,
+    /// The reproducible Atom commit.
+    pub atom: ObjectId,
+    /// The tree holding the Atom's manifest and lock.
+    pub spec: ObjectId,
+    /// The commit the Atom was published from.
+    pub origin: ObjectId,
+}
+
+impl Migration {
+    /// The refs of the current layout, and the objects they point to.
+    #[must_use]
+    pub fn refs(&self) -> [(String, ObjectId); 3] {
+        let version = super::encode_version(&self.version);
+        let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/{}/{version}", self.id);
+        [
+            (format!("{prefix}/{ATOM}"), self.atom),
+            (format!("{prefix}/{ATOM_MANIFEST}"), self.spec),
+            (format!("{prefix}/{ATOM_ORIGIN}"), self.origin),
+        ]
+    }
+
+    /// Verify the rewritten refs exactly as a store's hooks would verify them on push.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error describing why the store would reject them.
+    pub fn verify(&self, verifier: &Verifier) -> MigrateResult<()> {
+        for (name, new) in self.refs() {
+            verifier.verify(&RefUpdate {
+                old: ObjectId::null(new.kind()),
+                new,
+                name,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write the rewritten refs to the repository and push them to `remote`, deleting the
+    /// legacy ref from it if `prune` is set. Returns whether the refs already existed locally.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a ref of the current layout exists with different
+    /// content, or the push fails.
+    pub fn apply(&self, repo: &Repository, remote: &str, prune: bool) -> MigrateResult<bool> {
+        use gix::refs::transaction::PreviousValue;
+
+        let refs = self.refs();
+        let mut existed = true;
+        for (name, id) in &refs {
+            let current = repo
+                .find_reference(name.as_str())
+                .ok()
+                .and_then(|r| r.into_fully_peeled_id().ok());
+            match current {
+                Some(current) if current == *id => continue,
+                Some(_) => return Err(Error::Conflict(name.clone())),
+                None => existed = false,
+            }
+
+            repo.reference(
+                name.as_str(),
+                *id,
+                PreviousValue::MustNotExist,
+                format!("migrate: {}: {}", self.legacy, name),
+            )
+            .map_err(Box::new)?;
+        }
+
+        let git_dir = repo.git_dir().to_string_lossy().to_string();
+        let mut refspecs: Vec<_> = refs
+            .iter()
+            .map(|(name, _)| format!("{name}:{name}"))
+            .collect();
+        if prune {
+            refspecs.push(format!(":{}", self.legacy));
+        }
+        Push::new(refspecs).run(&git_dir, remote)?;
+
+        tracing::info!(legacy = %self.legacy, id = %self.id, version = %self.version, "Migrated");
+        Ok(existed)
+    }
+}
+
+/// List the legacy refs of `remote`, fetching them along with their objects.
+///
+/// # Errors
+///
+/// This function will return an error if the remote cannot be listed or fetched from.
+pub fn fetch_legacy(repo: &Repository, remote: &str) -> MigrateResult<Vec<(String, ObjectId)>> {
+    let git_dir = repo.git_dir().to_string_lossy().to_string();
+    let pattern = format!("{LEGACY_REF_PREFIX}*");
+    let listing = run_git_command(&["-C", &git_dir, "ls-remote", remote, &pattern])?;
+
+    let legacy: Vec<_> = String::from_utf8_lossy(&listing)
+        .lines()
+        .filter_map(|line| {
+            let (id, name) = line.split_once('\t')?;
+            let id = super::parse_object_id(repo, id.as_bytes())?;
+            // skip the peeled entries of annotated tags
+            (!name.ends_with("^{}")).then(|| (name.to_owned(), id))
+        })
+        .collect();
+
+    if !legacy.is_empty() {
+        let refspec = format!("+{pattern}:{LEGACY_NAMESPACE}*");
+        run_git_command(&["-C", &git_dir, "fetch", "--no-tags", remote, &refspec])?;
+    }
+
+    Ok(legacy)
+}
+
+/// Rewrite a fetched legacy ref into the objects of the current layout.
+///
+/// The Atom's id and version are read from the manifest in the legacy commit's tree. Its source
+/// is taken from the commit's `src` header, if it has one, or else its first parent.
+///
+/// # Errors
+///
+/// This function will return an error if the legacy commit has no valid manifest or source, or
+/// the new objects cannot be written.
+pub fn prepare(repo: &Repository, legacy: &str, id: ObjectId) -> MigrateResult<Migration> {
+    let objects = &repo.objects;
+
+    let mut buf = Vec::new();
+    let commit = objects.find_commit(&id, &mut buf)?;
+    let tree = commit.tree();
+    let path = commit.extra_headers().find("path").map(|p| p.to_string());
+    let origin = commit
+        .extra_headers()
+        .find(ATOM_ORIGIN)
+        .and_then(|o| super::parse_object_id(repo, o))
+        .or_else(|| commit.parents().next())
+        .ok_or_else(|| Error::NoOrigin(legacy.to_owned()))?;
+
+    let mut buf = Vec::new();
+    let entries: Vec<Entry> = objects
+        .find_tree(&tree, &mut buf)?
+        .entries
+        .iter()
+        .filter(|e| e.mode.is_blob())
+        .map(|e| Entry {
+            mode: e.mode,
+            filename: e.filename.into(),
+            oid: e.oid.to_owned(),
+        })
+        .collect();
+
+    let no_manifest = || Error::NoManifest(legacy.to_owned());
+    let manifest = entries
+        .iter()
+        .find(|e| e.filename.ends_with(ATOM_EXT.as_bytes()))
+        .ok_or_else(no_manifest)?;
+    let mut buf = Vec::new();
+    let blob = objects.find_blob(&manifest.oid, &mut buf)?;
+    let atom = std::str::from_utf8(blob.data)
+        .ok()
+        .and_then(|content| Manifest::get_atom(content).ok())
+        .ok_or_else(no_manifest)?;
+
+    let path = path.map_or_else(|| legacy_path(legacy, &atom.version), PathBuf::from);
+    let commit = atom_commit(&atom, tree, origin, &path);
+    let atom_id = repo.write_object(commit).map_err(Box::new)?.detach();
+
+    // entries of a tree are already sorted, as git expects
+    let spec = repo
+        .write_object(Tree { entries })
+        .map_err(Box::new)?
+        .detach();
+
+    Ok(Migration {
+        legacy: legacy.to_owned(),
+        id: atom.id,
+        version: atom.version,
+        atom: atom_id,
+        spec,
+        origin,
+    })
+}
+
+/// Derive the directory an Atom's content was published from, from the name of its legacy
+/// ref, for commits which do not record it.
+fn legacy_path(legacy: &str, version: &Version) -> PathBuf {
+    let path = legacy.strip_prefix(LEGACY_REF_PREFIX).unwrap_or(legacy);
+    let path = path.strip_suffix(&format!("-{version}")).unwrap_or(path);
+    Path::new(path)
+        .parent()
+        .unwrap_or(Path::new("/"))
+        .to_path_buf()
+}
@@ -0,0 +1,248 @@
+//! # Mirroring Stores
+//!
+//! An organization may mirror a public store into an internal one, e.g. to keep resolving its
+//! Atoms should the public one be unreachable. Every ref of the source under `refs/atoms/` is
+//! fetched, and pushed to the mirror under the same name, so that the mirror serves the very
+//! same Atom commits, and the locks pinning them hold against either store.
+//!
+//! Both stores must be initialized with the same root, as the ids of their Atoms are derived
+//! from it. The refs of each version, i.e. its own refs and those of its artifacts, are pushed
+//! first, and the refs pointing to versions, i.e. the markers of yanks, channels, tags and
+//! redirects, only then, as the mirror's hooks only accept them once the versions they point
+//! to are published. Every ref is only written if it still points where it did when the sync
+//! was planned.
+//!
+//! Published refs are immutable, so a ref of the mirror pointing elsewhere than the source's,
+//! other than a channel or a redirect, is a conflict, which is left alone. With `prune`, the
+//! refs of the mirror the source no longer has are deleted, all at once, as the hooks only
+//! accept the deletion of a version's refs along with the marker of its yank.
+use gix::{ObjectId, Repository};
+
+use super::artifact::ARTIFACTS;
+use super::channel::{CHANNELS, TAGS};
+use super::redirect::REDIRECT;
+use super::yank::YANKED;
+use super::{Error, Push, Snapshot, run_git_command};
+use crate::publish::ATOM_REF_TOP_LEVEL;
+use crate::store::Init;
+
+/// The local namespace the refs of the source are fetched into, keeping their objects alive.
+const MIRROR_NAMESPACE: &str = "refs/ekala/mirror/";
+
+/// The most refs written by a single push, keeping its command line within the platform's
+/// limits.
+const PUSH_BATCH: usize = 256;
+
+/// How a ref of the mirror changes to match the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The ref is created, as the mirror lacks it.
+    Create,
+    /// The ref, a channel or a redirect, is moved to where the source's points.
+    Update,
+    /// The ref is deleted, as the source no longer has it.
+    Delete,
+    /// The ref is immutable, but points elsewhere than the source's, and is left alone.
+    Conflict,
+}
+
+/// A ref of the mirror which differs from the source's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    /// The full name of the ref.
+    pub name: String,
+    /// The object the ref of the mirror points to, if it exists.
+    pub old: Option<ObjectId>,
+    /// The object the ref of the source points to, if it exists.
+    pub new: Option<ObjectId>,
+}
+
+/// The sync of the Atom refs of a store to its mirror, as planned against the refs of both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mirror {
+    source: String,
+    dest: String,
+    updates: Vec<Update>,
+    unchanged: usize,
+}
+
+impl Update {
+    /// How the ref changes, judging by its name and its targets in both stores.
+    #[must_use]
+    pub fn change(&self) -> Change {
+        match (self.old, self.new) {
+            (None, _) => Change::Create,
+            (Some(_), None) => Change::Delete,
+            (Some(_), Some(_)) if is_mutable(&self.name) => Change::Update,
+            (Some(_), Some(_)) => Change::Conflict,
+        }
+    }
+}
+
+impl Mirror {
+    /// Plan to sync the Atom refs of the remote `source` to the remote `dest`, against the refs
+    /// of both as they are now, deleting the refs `source` no longer has from `dest` if `prune`
+    /// is set.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if either remote is not an initialized store, both
+    /// are initialized with different roots, or the refs of either cannot be listed.
+    pub fn plan(repo: &Repository, source: &str, dest: &str, prune: bool) -> Result<Self, Error> {
+        let root = |name: &str| -> Result<_, Error> {
+            let remote = repo.find_remote(name).map_err(Box::new)?;
+            remote.ekala_root()
+        };
+        if root(source)? != root(dest)? {
+            return Err(Error::RootMismatch(source.to_owned(), dest.to_owned()));
+        }
+
+        let (from, to) = (Snapshot::take(repo, source)?, Snapshot::take(repo, dest)?);
+        let mut updates = Vec::new();
+        let mut unchanged = 0;
+        for (name, new) in from.refs() {
+            match to.get(name) {
+                Some(old) if old == new => unchanged += 1,
+                old => updates.push(Update {
+                    name: name.to_owned(),
+                    old,
+                    new: Some(new),
+                }),
+            }
+        }
+        if prune {
+            updates.extend(to.refs().filter(|(name, _)| from.get(name).is_none()).map(
+                |(name, old)| Update {
+                    name: name.to_owned(),
+                    old: Some(old),
+                    new: None,
+                },
+            ));
+        }
+
+        Ok(Mirror {
+            source: source.to_owned(),
+            dest: dest.to_owned(),
+            updates,
+            unchanged,
+        })
+    }
+
+    /// Return the remote synced from.
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Return the remote synced to.
+    #[must_use]
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    /// Return the refs of the mirror which differ from the source's, in order.
+    #[must_use]
+    pub fn updates(&self) -> &[Update] {
+        &self.updates
+    }
+
+    /// Return how many refs of the mirror already match the source's.
+    #[must_use]
+    pub fn unchanged(&self) -> usize {
+        self.unchanged
+    }
+
+    /// Fetch the refs of the source, and push them to the mirror, apart from conflicts, then
+    /// delete those the source no longer has, if the sync was planned with `prune`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the source cannot be fetched from, or a push is
+    /// rejected, e.g. as any of the refs of the mirror changed since the sync was planned.
+    pub fn apply(&self, repo: &Repository) -> Result<(), Error> {
+        let git_dir = repo.git_dir().to_string_lossy().to_string();
+        let (versions, pointers): (Vec<_>, Vec<_>) = self
+            .updates
+            .iter()
+            .filter(|update| matches!(update.change(), Change::Create | Change::Update))
+            .partition(|update| is_version(&update.name));
+        let deletions: Vec<_> = self
+            .updates
+            .iter()
+            .filter(|update| update.change() == Change::Delete)
+            .collect();
+
+        if !versions.is_empty() || !pointers.is_empty() {
+            let refspec = format!("+refs/{ATOM_REF_TOP_LEVEL}/*:{MIRROR_NAMESPACE}*");
+            run_git_command(&[
+                "-C",
+                &git_dir,
+                "fetch",
+                "--no-tags",
+                "--prune",
+                &self.source,
+                &refspec,
+            ])?;
+        }
+        for batch in versions
+            .chunks(PUSH_BATCH)
+            .chain(pointers.chunks(PUSH_BATCH))
+        {
+            self.push(&git_dir, batch, false)?;
+        }
+        if !deletions.is_empty() {
+            self.push(&git_dir, &deletions, true)?;
+        }
+
+        tracing::info!(
+            source = %self.source,
+            dest = %self.dest,
+            updated = versions.len() + pointers.len(),
+            deleted = deletions.len(),
+            "Mirrored"
+        );
+        Ok(())
+    }
+
+    /// Push the given updates to the mirror, each only if its ref still points where it did
+    /// when the sync was planned.
+    fn push(&self, git_dir: &str, updates: &[&Update], atomic: bool) -> Result<(), Error> {
+        let refspecs = updates.iter().map(|update| {
+            let new = update.new.map(|id| id.to_string()).unwrap_or_default();
+            format!("{new}:{}", update.name)
+        });
+        let mut push = updates.iter().fold(Push::new(refspecs), |push, update| {
+            push.lease(&update.name, update.old)
+        });
+        if atomic {
+            push = push.atomic();
+        }
+        push.run(git_dir, &self.dest)?;
+        Ok(())
+    }
+}
+
+/// The path of the Atom ref `name` below the Atom namespace, split into its components.
+fn components(name: &str) -> Vec<&str> {
+    name.strip_prefix("refs/")
+        .and_then(|name| name.strip_prefix(ATOM_REF_TOP_LEVEL))
+        .and_then(|name| name.strip_prefix('/'))
+        .map(|path| path.split('/').collect())
+        .unwrap_or_default()
+}
+
+/// Whether the Atom ref `name` belongs to a version, as one of its own refs or those of its
+/// artifacts, rather than pointing to one.
+fn is_version(name: &str) -> bool {
+    match components(name)[..] {
+        [_, ARTIFACTS, _, _] => true,
+        [_, CHANNELS | TAGS, _] => false,
+        [_, _, kind] => kind != YANKED,
+        _ => false,
+    }
+}
+
+/// Whether the Atom ref `name` may be moved, i.e. it is a channel or a redirect.
+fn is_mutable(name: &str) -> bool {
+    matches!(components(name)[..], [_, CHANNELS, _] | [_, REDIRECT])
+}
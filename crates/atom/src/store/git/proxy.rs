@@ -0,0 +1,227 @@
+//! # Read-through Atom Proxy
+//!
+//! Large build farms fetching the same Atoms over and over put a heavy load on the central Git
+//! servers of their stores. A [`Proxy`] is a local bare repository caching the Atoms of any
+//! number of upstream stores: a fetch through it is served from the proxy when it holds the
+//! requested Atom, and only reaches the upstream store on a miss, or when the Atom's refs were
+//! last revalidated longer ago than the proxy's time to live.
+//!
+//! The refs of each upstream store are kept apart in a [Git namespace] of their own, named
+//! after its url, so that stores publishing the same Atom ids never clash:
+//!
+//! ```console
+//! refs/namespaces/<upstream>/refs/atoms/<id>/<version>/atom
+//! ```
+//!
+//! Only the `atom` and `spec` refs of a version, and its yank marker, are cached. Its `src` ref
+//! is not, as it would pull the entire history of the repository the Atom was published from.
+//! The proxy may also be served by `git daemon`, or any other Git server, with `GIT_NAMESPACE`
+//! set to the namespace of an upstream store, to serve it as that store.
+//!
+//! [Git namespace]: https://git-scm.com/docs/gitnamespaces
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use gix::{ObjectId, Repository};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use super::yank::YANKED;
+use super::{Error, Snapshot, encode_version, run_git_command, validate_ref_name};
+use crate::id::Id;
+use crate::publish::{ATOM, ATOM_MANIFEST, ATOM_REF_TOP_LEVEL};
+
+/// The file recording when the refs of each Atom were last revalidated, relative to the git
+/// directory of the proxy.
+pub const PROXY_STATE: &str = "ekala/proxy";
+
+/// A local bare repository caching the Atoms of upstream stores.
+pub struct Proxy {
+    repo: Repository,
+    ttl: Duration,
+}
+
+/// When the refs of each Atom were last revalidated, in seconds since the Unix epoch, by the
+/// namespace of their upstream store, and then by the id of the Atom.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+struct State {
+    #[serde(default)]
+    revalidated: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl State {
+    fn load(git_dir: &Path) -> Self {
+        match std::fs::read_to_string(git_dir.join(PROXY_STATE)) {
+            Ok(content) => toml_edit::de::from_str(&content)
+                .inspect_err(|e| tracing::warn!(%e, "Discarding the unreadable proxy state"))
+                .unwrap_or_default(),
+            Err(_) => State::default(),
+        }
+    }
+
+    /// Persist the state, replacing the previous one at once, as several processes may share
+    /// the proxy.
+    fn save(&self, git_dir: &Path) -> io::Result<()> {
+        let path = git_dir.join(PROXY_STATE);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = toml_edit::ser::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+/// The seconds elapsed since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+impl Proxy {
+    /// Open the proxy at `path`, initializing a bare repository there if there is none yet,
+    /// revalidating the refs of an Atom once they are older than `ttl`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the repository cannot be opened, or initialized.
+    pub fn open(path: &Path, ttl: Duration) -> Result<Self, Error> {
+        let repo = match gix::open(path) {
+            Ok(repo) => repo,
+            Err(gix::open::Error::NotARepository { .. }) => {
+                std::fs::create_dir_all(path)?;
+                gix::init_bare(path).map_err(Box::new)?
+            },
+            Err(e) => return Err(Box::new(e).into()),
+        };
+        Ok(Proxy { repo, ttl })
+    }
+
+    /// Return the path to the git directory of the proxy.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.repo.git_dir()
+    }
+
+    /// Return the namespace the refs of the upstream store at `upstream` are kept in.
+    #[must_use]
+    pub fn namespace(upstream: &str) -> String {
+        let hash = blake3::hash(upstream.trim_end_matches('/').as_bytes());
+        hash.to_hex()[..16].to_owned()
+    }
+
+    /// The full name, in the proxy, of the ref of the given kind of an Atom version of
+    /// `upstream`.
+    fn ref_name(upstream: &str, id: &str, version: &Version, kind: &str) -> String {
+        let namespace = Proxy::namespace(upstream);
+        let version = encode_version(version);
+        format!("refs/namespaces/{namespace}/refs/{ATOM_REF_TOP_LEVEL}/{id}/{version}/{kind}")
+    }
+
+    /// Revalidate the refs of the Atom `id` against `upstream`, if they were last revalidated
+    /// longer ago than the time to live, or ever, or `force` is set, returning whether they
+    /// were.
+    ///
+    /// Versions published upstream since are added, and those deleted upstream removed, along
+    /// with the yank markers of each version.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `id` is not a valid ref component, or the refs of
+    /// the upstream store cannot be fetched.
+    pub fn revalidate(&self, upstream: &str, id: &str, force: bool) -> Result<bool, Error> {
+        let namespace = Proxy::namespace(upstream);
+        let git_dir = self.path();
+        let mut state = State::load(git_dir);
+        let last = state
+            .revalidated
+            .get(&namespace)
+            .and_then(|ids| ids.get(id));
+        let fresh = last.is_some_and(|last| now().saturating_sub(*last) < self.ttl.as_secs());
+        if fresh && !force {
+            return Ok(false);
+        }
+
+        let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/{id}");
+        validate_ref_name(&prefix)?;
+        let refspecs: Vec<_> = [ATOM, ATOM_MANIFEST, YANKED]
+            .iter()
+            .map(|kind| format!("+{prefix}/*/{kind}:refs/namespaces/{namespace}/{prefix}/*/{kind}"))
+            .collect();
+        let git_dir = git_dir.to_string_lossy().to_string();
+        let mut args = vec!["-C", &git_dir, "fetch", "--prune", "--no-tags", upstream];
+        args.extend(refspecs.iter().map(String::as_str));
+        run_git_command(&args)?;
+        tracing::debug!(%upstream, %id, "Revalidated the proxied Atom");
+
+        state
+            .revalidated
+            .entry(namespace)
+            .or_default()
+            .insert(id.to_owned(), now());
+        if let Err(e) = state.save(self.path()) {
+            tracing::warn!(%e, "Could not persist the proxy state");
+        }
+        Ok(true)
+    }
+
+    /// List the Atoms of `upstream` held by the proxy, along with each of their versions
+    /// resolution may select, i.e. those not yanked.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the refs of the proxy cannot be read.
+    pub fn resolvable(&self, upstream: &str) -> Result<BTreeMap<Id, BTreeSet<Version>>, Error> {
+        let namespace = format!("refs/namespaces/{}/", Proxy::namespace(upstream));
+        let refs = self
+            .repo
+            .references()
+            .map_err(io::Error::other)?
+            .prefixed(namespace.as_str())
+            .map_err(io::Error::other)?
+            .filter_map(Result::ok)
+            .filter_map(|r| {
+                let name = r.name().as_bstr().to_string();
+                let id = r.target().try_id()?.to_owned();
+                Some((name.strip_prefix(&namespace)?.to_owned(), id))
+            })
+            .collect();
+        Ok(Snapshot { refs }.resolvable())
+    }
+
+    /// Fetch the content of the given version of the Atom `id` of `upstream` from the proxy
+    /// into `repo`, returning the id of its tree.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the proxy does not hold the version, or it cannot
+    /// be fetched.
+    pub fn fetch_content(
+        &self,
+        repo: &Repository,
+        upstream: &str,
+        id: &str,
+        version: &Version,
+    ) -> Result<ObjectId, Error> {
+        use crate::store::QueryStore;
+
+        let name = Proxy::ref_name(upstream, id, version, ATOM);
+        validate_ref_name(&name)?;
+        let url = self.path().to_string_lossy();
+        let remote = repo.remote_at(url.as_ref()).map_err(Box::new)?;
+        let commit: ObjectId = remote.get_ref(name.as_str())?;
+        let tree = repo
+            .find_commit(commit)
+            .map_err(Box::new)?
+            .tree_id()?
+            .detach();
+
+        tracing::debug!(%upstream, %id, %version, "Fetched content through the proxy");
+        Ok(tree)
+    }
+}
@@ -0,0 +1,164 @@
+//! # Renaming Atoms
+//!
+//! An Atom is renamed by publishing it under its new id, which would leave every dependent
+//! still naming it by its former id behind. A redirect beside the versions of the former id
+//! points them to the new one instead, as a ref pointing to the Atom commit of a published
+//! version of the new id:
+//!
+//! ```console
+//! refs/atoms/<id>/_redirect
+//! ```
+//!
+//! The store's hooks only accept a redirect pointing to a published version of another Atom
+//! which is not yanked. Resolution then selects the versions of a dependency on the former id
+//! among those of the new one, warning that the Atom was renamed, and locks it as renamed, so
+//! that dependents keep working until they are updated. The versions published under the
+//! former id remain in the store, and the locks already pinning them remain valid.
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use gix::{ObjectId, Repository};
+use semver::Version;
+
+use super::{EkalaRemote, Error, Push, Snapshot, atom_ref, validate_ref_name};
+use crate::id::Id;
+use crate::publish::{ATOM, ATOM_REF_TOP_LEVEL};
+
+/// The kind of ref redirecting a renamed Atom to its new id.
+pub const REDIRECT: &str = "_redirect";
+
+/// The full name of the ref redirecting the Atom `id` to the one it was renamed to.
+#[must_use]
+pub fn ref_name(id: &str) -> String {
+    format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{REDIRECT}")
+}
+
+impl Snapshot {
+    /// Return the id the Atom `id` was renamed to when the snapshot was taken, following each
+    /// redirect in turn, if it was, unless the redirects loop back on themselves.
+    #[must_use]
+    pub fn renamed(&self, id: &Id) -> Option<Id> {
+        let mut seen = BTreeSet::new();
+        let mut current = id.clone();
+        while let Some(next) = self.redirect(&current) {
+            if !seen.insert(current) {
+                return None;
+            }
+            current = next;
+        }
+        (current != *id).then_some(current)
+    }
+
+    /// Return the id of the other Atom the redirect of the Atom `id` points to a published
+    /// version of, if it has one.
+    fn redirect(&self, id: &Id) -> Option<Id> {
+        let commit = self.get(&ref_name(id))?;
+        let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/");
+        self.refs().find_map(|(name, target)| {
+            let [to, _, ATOM] = name.strip_prefix(&prefix)?.split('/').collect::<Vec<_>>()[..]
+            else {
+                return None;
+            };
+            let to = Id::from_str(to).ok()?;
+            (target == commit && to != *id).then_some(to)
+        })
+    }
+}
+
+/// The redirect of a renamed Atom to its new id, as planned against the refs of its store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    from: Id,
+    to: Id,
+    /// The latest version of the new id, whose Atom commit the redirect points to.
+    version: Version,
+    commit: ObjectId,
+    /// The commit the redirect pointed to already, if any.
+    previous: Option<ObjectId>,
+}
+
+impl Redirect {
+    /// Plan to redirect the Atom `from` to the Atom `to` in `remote`, pointing to the latest
+    /// version of `to` which is not yanked, against the refs of the remote as they are now.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if both ids are the same, the refs of the remote
+    /// cannot be listed, or no version of `to` which is not yanked is published to it.
+    pub fn plan(repo: &Repository, remote: &str, from: &Id, to: &Id) -> Result<Self, Error> {
+        if from == to {
+            return Err(Error::SelfRedirect(from.to_string()));
+        }
+        let name = ref_name(from);
+        validate_ref_name(&name)?;
+        let snapshot = Snapshot::take(repo, remote)?;
+        let version = snapshot
+            .resolvable()
+            .remove(to)
+            .and_then(|versions| versions.last().cloned())
+            .ok_or_else(|| {
+                Error::NoRef(format!("refs/{ATOM_REF_TOP_LEVEL}/{to}"), remote.into())
+            })?;
+        let atom = atom_ref(to, &version);
+        let commit = snapshot
+            .get(&atom)
+            .ok_or_else(|| Error::NoRef(atom, remote.to_owned()))?;
+
+        Ok(Redirect {
+            from: from.clone(),
+            to: to.clone(),
+            version,
+            commit,
+            previous: snapshot.get(&name),
+        })
+    }
+
+    /// Return the former id of the Atom.
+    #[must_use]
+    pub fn from(&self) -> &Id {
+        &self.from
+    }
+
+    /// Return the id the Atom was renamed to.
+    #[must_use]
+    pub fn to(&self) -> &Id {
+        &self.to
+    }
+
+    /// Return the version of the new id the redirect points to.
+    #[must_use]
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Whether the redirect points where it did already when it was planned.
+    #[must_use]
+    pub fn is_unchanged(&self) -> bool {
+        self.previous == Some(self.commit)
+    }
+
+    /// Write the redirect to `remote`, unless it is unchanged, only if it still points where it
+    /// did when the redirect was planned.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the Atom commit cannot be fetched, or the push is
+    /// rejected, e.g. as the redirect changed since it was planned.
+    pub fn apply(&self, remote: &gix::Remote) -> Result<(), Error> {
+        use crate::store::QueryStore;
+
+        if self.is_unchanged() {
+            return Ok(());
+        }
+        // the redirect may only be pushed along with the commit it points to
+        remote.get_ref(atom_ref(&self.to, &self.version).as_str())?;
+
+        let git_dir = remote.repo().git_dir().to_string_lossy().to_string();
+        let name = ref_name(&self.from);
+        Push::new([format!("{}:{name}", self.commit)])
+            .lease(&name, self.previous)
+            .run(&git_dir, remote.symbol())?;
+        tracing::info!(from = %self.from, to = %self.to, "Redirected");
+        Ok(())
+    }
+}
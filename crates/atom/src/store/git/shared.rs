@@ -0,0 +1,209 @@
+//! # Shared Atom Store
+//!
+//! Projects depending on the same Atoms would each hold a checkout of their own if every fetch
+//! wrote to the project's directory. A [`SharedStore`] is a local directory holding a single
+//! checkout of each version of an Atom, shared by every project on the machine, keyed by the
+//! hash of its [`AtomId`] and its version:
+//!
+//! ```console
+//! <store>/<atom-hash>/<version>
+//! ```
+//!
+//! Each project is then given a view of the Atoms it needs, as a symbolic link to the checkout
+//! in the store. As the checkouts outlive any one fetch,
+//! and any project may write through its views, a checkout is verified against the content
+//! it was written from each time it is reused, and written anew if it no longer matches.
+#[cfg(test)]
+mod test;
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use gix::{ObjectId, Repository};
+use semver::Version;
+
+use super::Error;
+use super::content::{self, Mode};
+use crate::ObjectSum;
+use crate::id::{AtomId, ComputeHash, Id};
+
+/// A local directory holding a single checkout of each version of an Atom, shared by every
+/// project on the machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedStore {
+    path: PathBuf,
+}
+
+impl SharedStore {
+    /// Constructs a new [`SharedStore`] at the given directory, which is created once an Atom
+    /// is first placed in it.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        SharedStore { path: path.into() }
+    }
+
+    /// The directory of the store.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The directory the given version of the Atom `id`, of the store whose root is `root`, is
+    /// checked out to in the store.
+    #[must_use]
+    pub fn entry(&self, root: ObjectSum, id: &Id, version: &Version) -> PathBuf {
+        let id = AtomId::compute(&root, id.clone()).unwrap_or_else(|e| match e {});
+        self.path
+            .join(id.compute_hash().to_string())
+            .join(version.to_string())
+    }
+
+    /// Place the content tree `content` of the given version of the Atom `id` in the store,
+    /// unless the store holds it already, and link `view` to it, returning the directory it is
+    /// checked out to in the store. A checkout already held is verified against `content`
+    /// first, and written anew if it no longer matches.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `view` already exists, or the content cannot be
+    /// checked out, verified or linked to.
+    pub fn place(
+        &self,
+        repo: &Repository,
+        (root, id): (ObjectSum, &Id),
+        version: &Version,
+        content: ObjectId,
+        view: &Path,
+    ) -> Result<PathBuf, Error> {
+        let entry = self.entry(root, id, version);
+        if entry.exists() {
+            if verify(repo, content, &entry)? {
+                tracing::debug!(
+                    %id,
+                    %version,
+                    path = %entry.display(),
+                    "Reusing a shared checkout"
+                );
+            } else {
+                tracing::warn!(
+                    %id,
+                    %version,
+                    path = %entry.display(),
+                    "Replacing a shared checkout which no longer matches its content"
+                );
+                replace(repo, content, &entry)?;
+            }
+        } else {
+            checkout(repo, content, &entry)?;
+        }
+
+        if let Some(dir) = view.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        link(&entry, view)?;
+        Ok(entry)
+    }
+}
+
+/// Check `content` out to `entry`, through a temporary directory beside it, so that no other
+/// process ever sees a partial checkout. Should another process place the same checkout
+/// first, its checkout is kept.
+fn checkout(repo: &Repository, content: ObjectId, entry: &Path) -> Result<(), Error> {
+    super::write_atomically(entry, |tmp| super::materialize(repo, content, tmp))
+}
+
+/// Check `content` out anew over the stale checkout at `entry`, which is only moved aside once
+/// its replacement is complete, and dropped once the replacement is renamed in its place. Should
+/// the checkout fail, the stale checkout is left as it is.
+fn replace(repo: &Repository, content: ObjectId, entry: &Path) -> Result<(), Error> {
+    let mut stale = entry.as_os_str().to_owned();
+    stale.push(format!(".{}.stale", std::process::id()));
+    let stale = PathBuf::from(stale);
+
+    super::write_atomically(entry, |tmp| {
+        super::materialize(repo, content, tmp)?;
+        // another process replacing the same checkout may have moved it aside already
+        match std::fs::rename(entry, &stale) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    })?;
+    match std::fs::remove_dir_all(&stale) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            tracing::warn!(dir = %stale.display(), reason = %e, "Failed to clean up");
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Whether the directory `dir` holds exactly the content tree `content`, with no entry
+/// missing, changed or added.
+///
+/// # Errors
+///
+/// This function will return an error if the content tree cannot be read.
+pub fn verify(repo: &Repository, content: ObjectId, dir: &Path) -> Result<bool, Error> {
+    let mut expected = 0;
+    for entry in content::entries(repo, content) {
+        let entry = entry?;
+        let path = dir.join(&entry.path);
+        if !matches(&path, entry.mode, &entry.data) {
+            return Ok(false);
+        }
+        expected += 1;
+    }
+    Ok(count(dir).is_ok_and(|found| found == expected))
+}
+
+/// Whether the file at `path` is an entry of the given mode, holding `data`.
+fn matches(path: &Path, mode: Mode, data: &[u8]) -> bool {
+    let Ok(meta) = path.symlink_metadata() else {
+        return false;
+    };
+    match mode {
+        Mode::Dir => meta.is_dir(),
+        #[cfg(unix)]
+        Mode::Symlink => {
+            use std::os::unix::ffi::OsStrExt;
+            meta.is_symlink()
+                && std::fs::read_link(path)
+                    .is_ok_and(|target| target.as_os_str().as_bytes() == data)
+        },
+        // links are written out as files holding their target elsewhere
+        #[cfg(not(unix))]
+        Mode::Symlink => meta.is_file() && std::fs::read(path).is_ok_and(|target| target == data),
+        Mode::File | Mode::Executable => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let executable = meta.permissions().mode() & 0o111 != 0;
+                if executable != (mode == Mode::Executable) {
+                    return false;
+                }
+            }
+            meta.is_file() && std::fs::read(path).is_ok_and(|content| content == data)
+        },
+    }
+}
+
+/// The number of entries under `dir`, at any depth, without following links.
+fn count(dir: &Path) -> io::Result<usize> {
+    let mut found = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        found += 1;
+        if entry.file_type()?.is_dir() {
+            found += count(&entry.path())?;
+        }
+    }
+    Ok(found)
+}
+
+/// Link `view` to the checkout at `entry`.
+fn link(entry: &Path, view: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_dir(entry, view);
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(entry, view)
+}
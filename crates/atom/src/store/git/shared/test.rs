@@ -0,0 +1,139 @@
+use anyhow::Context;
+use gix::objs::Tree;
+use gix::objs::tree::{Entry, EntryKind};
+
+use super::*;
+
+/// Write a content tree holding a file, and another in a directory.
+fn content(repo: &Repository) -> Result<ObjectId, anyhow::Error> {
+    let file = |name: &str, oid: ObjectId| Entry {
+        mode: EntryKind::Blob.into(),
+        filename: name.into(),
+        oid,
+    };
+    let readme = repo.write_blob(b"hello")?.detach();
+    let lib = repo.write_blob(b"world")?.detach();
+    let src = repo.write_object(Tree {
+        entries: vec![file("lib.nix", lib)],
+    })?;
+    let tree = repo.write_object(Tree {
+        entries: vec![
+            file("README", readme),
+            Entry {
+                mode: EntryKind::Tree.into(),
+                filename: "src".into(),
+                oid: src.detach(),
+            },
+        ],
+    })?;
+    Ok(tree.detach())
+}
+
+#[test]
+fn place_shared_checkouts() -> Result<(), anyhow::Error> {
+    let dir = tempfile::tempdir()?;
+    let repo = gix::init(dir.path().join("repo"))?;
+    let content = content(&repo)?;
+    let store = SharedStore::new(dir.path().join("store"));
+    let root = ObjectSum::from(content);
+    let id = Id::try_from("foo")?;
+    let version = Version::new(0, 1, 0);
+
+    let view = |project: &str| dir.path().join(project).join("foo@0.1.0");
+    let entry = store.place(&repo, (root, &id), &version, content, &view("a"))?;
+    assert_eq!(entry, store.entry(root, &id, &version));
+    assert!(entry.starts_with(store.path()));
+    assert_eq!(std::fs::read(view("a").join("README"))?, b"hello");
+    assert!(view("a").symlink_metadata()?.is_symlink());
+
+    // a second project is given a view of the very same checkout
+    assert_eq!(
+        store.place(&repo, (root, &id), &version, content, &view("b"))?,
+        entry
+    );
+    assert_eq!(std::fs::read(view("b").join("src/lib.nix"))?, b"world");
+
+    // a checkout which no longer matches its content is written anew
+    std::fs::write(view("b").join("README"), b"changed")?;
+    assert!(!verify(&repo, content, &entry)?);
+    store.place(&repo, (root, &id), &version, content, &view("c"))?;
+    assert!(verify(&repo, content, &entry)?);
+    assert_eq!(std::fs::read(view("a").join("README"))?, b"hello");
+
+    std::fs::write(entry.join("extra"), b"")?;
+    assert!(!verify(&repo, content, &entry)?);
+    Ok(())
+}
+
+#[test]
+fn failed_checkouts_leave_no_trace() -> Result<(), anyhow::Error> {
+    let dir = tempfile::tempdir()?;
+    let repo = gix::init(dir.path().join("repo"))?;
+    let store = SharedStore::new(dir.path().join("store"));
+    let id = Id::try_from("foo")?;
+    let version = Version::new(0, 1, 0);
+
+    // a tree whose file is written out before its directory is refused for an unsafe entry
+    let unsafe_dir = repo.write_object(Tree {
+        entries: vec![Entry {
+            mode: EntryKind::Blob.into(),
+            filename: "..".into(),
+            oid: repo.write_blob(b"")?.detach(),
+        }],
+    })?;
+    let broken = repo
+        .write_object(Tree {
+            entries: vec![
+                Entry {
+                    mode: EntryKind::Blob.into(),
+                    filename: "README".into(),
+                    oid: repo.write_blob(b"hello")?.detach(),
+                },
+                Entry {
+                    mode: EntryKind::Tree.into(),
+                    filename: "src".into(),
+                    oid: unsafe_dir.detach(),
+                },
+            ],
+        })?
+        .detach();
+    let root = ObjectSum::from(broken);
+    let entry = store.entry(root, &id, &version);
+    let parent = entry.parent().context("no parent")?;
+    let listing = || -> Result<Vec<_>, anyhow::Error> {
+        let mut names = std::fs::read_dir(parent)?
+            .map(|e| Ok(e?.file_name()))
+            .collect::<Result<Vec<_>, io::Error>>()?;
+        names.sort();
+        Ok(names)
+    };
+
+    // no partial checkout is left behind
+    let view = dir.path().join("a/foo@0.1.0");
+    assert!(
+        store
+            .place(&repo, (root, &id), &version, broken, &view)
+            .is_err()
+    );
+    assert!(!entry.exists());
+    assert!(listing()?.is_empty());
+    assert!(!view.exists());
+
+    // nor is a stale checkout dropped for a replacement which failed
+    let content = content(&repo)?;
+    std::fs::create_dir_all(&entry)?;
+    std::fs::write(entry.join("README"), b"stale")?;
+    assert!(
+        store
+            .place(&repo, (root, &id), &version, broken, &view)
+            .is_err()
+    );
+    assert_eq!(std::fs::read(entry.join("README"))?, b"stale");
+    assert_eq!(listing()?, ["0.1.0"]);
+
+    // while one which succeeds is renamed over it
+    store.place(&repo, (root, &id), &version, content, &view)?;
+    assert!(verify(&repo, content, &entry)?);
+    assert_eq!(listing()?, ["0.1.0"]);
+    Ok(())
+}
@@ -0,0 +1,326 @@
+//! # Signed Atom Commits
+//!
+//! Atom commits are written with an empty author and committer, so that they are reproducible
+//! from their source, which leaves the consumers of a store no way to authenticate who
+//! published an Atom. A publisher may instead sign its Atom commits, with an OpenPGP key
+//! through `gpg`, or an SSH key through `ssh-keygen`, exactly as `git commit -S` would: the
+//! signature covers the commit as it would be written unsigned, and is stored in its `gpgsig`
+//! header, so that `git verify-commit` checks it as well.
+//!
+//! Since the signature is the only part of an Atom commit which is not reproducible, the
+//! commit re-created from its source is compared against the signed commit with its signature
+//! removed, i.e. its [`Signed::payload`]. Signatures are then checked against an allowlist of
+//! key fingerprints with [`Signed::verify`], rather than the keys trusted by Git, as a store's
+//! consumers rarely share the publisher's keyring.
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use config::{SigningConfig, SigningFormat};
+use gix::objs::{Commit, CommitRef, WriteTo};
+use gix::{ObjectId, Repository};
+use tempfile::NamedTempFile;
+use thiserror::Error as ThisError;
+
+/// The header the signature of a commit is stored in, in repositories using SHA-1.
+pub const SIGNATURE: &str = "gpgsig";
+
+/// The header the signature of a commit is stored in, in repositories using SHA-256.
+pub const SIGNATURE_SHA256: &str = "gpgsig-sha256";
+
+/// The namespace of the SSH signatures of commits, as used by Git.
+const SSH_NAMESPACE: &str = "git";
+
+/// An error encountered while signing an Atom commit, or verifying its signature.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// No key to sign with is configured.
+    #[error("No signing key is configured, set `signing.key`, or `user.signingKey` in Git")]
+    NoKey,
+    /// The signature format configured in Git is not supported.
+    #[error("Signing with `gpg.format = {0}` is not supported, use `openpgp` or `ssh`")]
+    Unsupported(String),
+    /// The signing program did not report a signature.
+    #[error("Failed to sign the Atom commit: {0}")]
+    Sign(String),
+    /// The signing or verifying program failed.
+    #[error("`{program}` failed: {message}")]
+    Failed {
+        /// The program run.
+        program: String,
+        /// What it wrote to its standard error.
+        message: String,
+    },
+    /// The signature is not valid for the commit.
+    #[error("The signature is invalid: {0}")]
+    Invalid(String),
+    /// A transparent wrapper for a [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Signs Atom commits with a configured key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signer {
+    format: SigningFormat,
+    key: String,
+    program: String,
+}
+
+/// A signed commit, split into its signature and the payload it signs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signed {
+    /// The commit as it was written before it was signed.
+    pub payload: Vec<u8>,
+    /// The armored signature of the payload.
+    pub signature: String,
+}
+
+impl Signer {
+    /// Construct a signer signing with the given key, in the given format, with the default
+    /// program of the format.
+    #[must_use]
+    pub fn new(format: SigningFormat, key: impl Into<String>) -> Self {
+        Signer {
+            format,
+            key: key.into(),
+            program: default_program(format).to_owned(),
+        }
+    }
+
+    /// Construct the signer configured by `config`, falling back to the signing configuration
+    /// of Git, i.e. `gpg.format`, `user.signingKey`, and the program of the format.
+    ///
+    /// An OpenPGP key defaults to the committer's email, as it does for `git commit -S`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no key is configured, or Git is configured with
+    /// an unsupported format.
+    pub fn from_config(repo: &Repository, config: &SigningConfig) -> Result<Self, Error> {
+        let git = repo.config_snapshot();
+        let configured = git.string("gpg.format").map(|f| f.to_string());
+        let format = match (config.format, configured.as_deref()) {
+            (Some(format), _) => format,
+            (None, None | Some("openpgp")) => SigningFormat::Openpgp,
+            (None, Some("ssh")) => SigningFormat::Ssh,
+            (None, Some(format)) => return Err(Error::Unsupported(format.to_owned())),
+        };
+        let key = config
+            .key
+            .clone()
+            .or_else(|| git.string("user.signingKey").map(|k| k.to_string()))
+            .or_else(|| match format {
+                SigningFormat::Openpgp => repo
+                    .committer()
+                    .and_then(Result::ok)
+                    .map(|sig| sig.email.to_string()),
+                SigningFormat::Ssh => None,
+            })
+            .ok_or(Error::NoKey)?;
+
+        Ok(Signer {
+            format,
+            key,
+            program: program(repo, format),
+        })
+    }
+
+    /// Sign `payload`, returning the armored signature.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the signing program cannot be run, or fails.
+    pub fn sign(&self, payload: &[u8]) -> Result<String, Error> {
+        match self.format {
+            SigningFormat::Openpgp => {
+                let args = ["--status-fd=2", "-bsau", &self.key];
+                let (signature, status) = pipe(&self.program, &args, payload)?;
+                if !status.contains("[GNUPG:] SIG_CREATED ") {
+                    return Err(Error::Sign(status));
+                }
+                Ok(signature)
+            },
+            SigningFormat::Ssh => {
+                // a literal public key is written to a file, the agent holding its private key
+                let literal = match self.key.strip_prefix("key::") {
+                    Some(literal) => Some(temp_file(".pub", literal.as_bytes())?),
+                    None => None,
+                };
+                let key = match &literal {
+                    Some(file) => file.path().to_owned(),
+                    None => expand_home(&self.key),
+                };
+                let key = key.to_string_lossy().into_owned();
+                let args = ["-Y", "sign", "-n", SSH_NAMESPACE, "-f", &key];
+                Ok(pipe(&self.program, &args, payload)?.0)
+            },
+        }
+    }
+
+    /// Sign an Atom commit, storing the signature in its signature header, as the last one.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error for any reason [`Signer::sign`] would.
+    pub fn sign_commit(&self, commit: &mut Commit, hash: gix::hash::Kind) -> Result<(), Error> {
+        let mut payload = Vec::new();
+        commit.write_to(&mut payload)?;
+        let signature = self.sign(&payload)?;
+        let header = if hash == gix::hash::Kind::Sha1 {
+            SIGNATURE
+        } else {
+            SIGNATURE_SHA256
+        };
+        commit
+            .extra_headers
+            .push((header.into(), signature.trim_end().into()));
+        Ok(())
+    }
+}
+
+impl Signed {
+    /// Split a signed commit into its signature and the payload it signs, or return `None` if
+    /// the commit is not signed.
+    #[must_use]
+    pub fn split(commit: CommitRef<'_>) -> Option<Self> {
+        let mut commit = Commit::from(commit);
+        let signed = commit
+            .extra_headers
+            .iter()
+            .position(|(k, _)| k == SIGNATURE || k == SIGNATURE_SHA256)?;
+        let (_, signature) = commit.extra_headers.remove(signed);
+
+        let mut payload = Vec::new();
+        commit.write_to(&mut payload).ok()?;
+        Some(Signed {
+            payload,
+            signature: signature.to_string(),
+        })
+    }
+
+    /// Return the id the commit had before it was signed.
+    #[must_use]
+    pub fn payload_id(&self, repo: &Repository) -> ObjectId {
+        gix::objs::compute_hash(repo.object_hash(), gix::object::Kind::Commit, &self.payload)
+    }
+
+    /// Verify the signature, returning the fingerprint of the key it was made with, if it is
+    /// one of the `allowed` fingerprints.
+    ///
+    /// OpenPGP signatures are checked with `gpg`, which must hold the key in its keyring, and
+    /// are accepted by the fingerprint of the signing key or of its primary key. SSH signatures
+    /// are checked with `ssh-keygen`, and accepted by the `SHA256:` fingerprint of their key.
+    /// Either program is taken from the Git configuration of `repo`, as it is for signing.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the signature is not valid for the payload, or
+    /// was made with a key which is not allowed.
+    pub fn verify(&self, repo: &Repository, allowed: &[String]) -> Result<String, Error> {
+        let signature = temp_file(".sig", self.signature.as_bytes())?;
+        let path = signature.path().to_string_lossy().into_owned();
+        let fingerprints = if self.signature.contains("BEGIN SSH SIGNATURE") {
+            let args = ["-Y", "check-novalidate", "-n", SSH_NAMESPACE, "-s", &path];
+            let program = program(repo, SigningFormat::Ssh);
+            let (out, err) = pipe(&program, &args, &self.payload)?;
+            // the verdict is reported on either output, depending on the version
+            [out, err]
+                .iter()
+                .flat_map(|out| out.split_whitespace())
+                .filter(|word| word.starts_with("SHA256:"))
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>()
+        } else {
+            let args = ["--status-fd=1", "--verify", &path, "-"];
+            let program = program(repo, SigningFormat::Openpgp);
+            let (status, _) = pipe(&program, &args, &self.payload)?;
+            // VALIDSIG <fingerprint> <date> <timestamp> <expiry> <version> <reserved>
+            //          <algorithm> <hash> <class> <primary key fingerprint>
+            status
+                .lines()
+                .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+                .flat_map(|fields| {
+                    let fields = fields.split_whitespace().enumerate();
+                    fields.filter_map(|(i, field)| (i == 0 || i == 9).then_some(field))
+                })
+                .map(ToOwned::to_owned)
+                .collect()
+        };
+
+        let Some(first) = fingerprints.first() else {
+            return Err(Error::Invalid("no valid signature was found".to_owned()));
+        };
+        fingerprints
+            .iter()
+            .find(|fingerprint| allowed.iter().any(|a| a.eq_ignore_ascii_case(fingerprint)))
+            .cloned()
+            .ok_or_else(|| Error::Invalid(format!("`{first}` is not an allowed key")))
+    }
+}
+
+/// Run `program` with `input` on its standard input, returning what it wrote to its standard
+/// output and error if it succeeds.
+fn pipe(program: &str, args: &[&str], input: &[u8]) -> Result<(String, String), Error> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    if output.status.success() {
+        Ok((stdout, stderr))
+    } else {
+        Err(Error::Failed {
+            program: program.to_owned(),
+            message: stderr.trim().to_owned(),
+        })
+    }
+}
+
+/// Expand a leading `~/` in a path to the home directory, as Git does for `user.signingKey`.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The program signing and verifying signatures in the given format, as Git configures it.
+fn program(repo: &Repository, format: SigningFormat) -> String {
+    let git = repo.config_snapshot();
+    let program = match format {
+        SigningFormat::Openpgp => git
+            .string("gpg.openpgp.program")
+            .or_else(|| git.string("gpg.program")),
+        SigningFormat::Ssh => git.string("gpg.ssh.program"),
+    };
+    program.map_or_else(|| default_program(format).to_owned(), |p| p.to_string())
+}
+
+/// The program signing and verifying signatures in the given format, unless Git configures
+/// another.
+fn default_program(format: SigningFormat) -> &'static str {
+    match format {
+        SigningFormat::Openpgp => "gpg",
+        SigningFormat::Ssh => "ssh-keygen",
+    }
+}
+
+/// Write `content` to a new file in the temporary directory, only readable by the current
+/// user, and removed once dropped.
+fn temp_file(suffix: &str, content: &[u8]) -> io::Result<NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix("eka-")
+        .suffix(suffix)
+        .tempfile()?;
+    file.write_all(content)?;
+    file.flush()?;
+    Ok(file)
+}
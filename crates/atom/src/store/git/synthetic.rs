@@ -0,0 +1,361 @@
+//! # Synthetic Repositories
+//!
+//! Validating and publishing Atoms must hold up in monorepos of thousands of Atoms, nested
+//! deep in the tree, and among mistakes such as duplicate ids or broken manifests. A
+//! [`Synthetic`] repository is generated with any number of such Atoms, for performance and
+//! correctness tests alike:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use atom::store::git::synthetic::Synthetic;
+//!
+//! let repo = gix::open(".")?;
+//! let generated = Synthetic::new(5_000).depth(3).broken(10).generate(&repo)?;
+//! assert_eq!(generated.valid(), 4_990);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Generation is deterministic: the same parameters and seed always yield the same commit,
+//! so a failure found with a generated repository can be reproduced exactly.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use gix::objs::Tree;
+use gix::objs::tree::{Entry, EntryKind};
+use gix::{ObjectId, Repository};
+use thiserror::Error as ThisError;
+
+/// The number of directories Atoms are spread over at each level of nesting.
+const FANOUT: u64 = 8;
+
+/// An error encountered while generating a synthetic repository.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// More Atoms are requested to be broken or duplicates than there are Atoms, leaving none
+    /// valid to be duplicated.
+    #[error("Cannot break or duplicate {faulty} of {atoms} atoms, at least one must be valid")]
+    TooFaulty {
+        /// The number of Atoms requested.
+        atoms: usize,
+        /// The number of Atoms requested to be broken or duplicates.
+        faulty: usize,
+    },
+    /// A transparent wrapper for a [`gix::object::write::Error`]
+    #[error(transparent)]
+    WriteObject(#[from] Box<gix::object::write::Error>),
+    /// A transparent wrapper for a [`gix::commit::Error`]
+    #[error(transparent)]
+    Commit(#[from] Box<gix::commit::Error>),
+}
+
+/// The parameters of a synthetic repository, built up before generating it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Synthetic {
+    atoms: usize,
+    depth: usize,
+    duplicates: usize,
+    broken: usize,
+    seed: u64,
+}
+
+/// A synthetic repository, as generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generated {
+    commit: ObjectId,
+    atoms: usize,
+    duplicates: Vec<PathBuf>,
+    broken: Vec<PathBuf>,
+}
+
+/// The ways an Atom's manifest may be broken.
+#[derive(Clone, Copy)]
+enum Broken {
+    /// The manifest is not valid TOML.
+    Syntax,
+    /// The manifest declares no version.
+    NoVersion,
+    /// The manifest declares a version which is not valid semver.
+    BadVersion,
+}
+
+/// What becomes of each Atom generated.
+#[derive(Clone, Copy)]
+enum Role {
+    Valid,
+    Duplicate,
+    Broken(Broken),
+}
+
+/// A directory of the generated tree, holding nested directories, and Atoms by their trees.
+#[derive(Default)]
+struct Dir {
+    dirs: BTreeMap<String, Dir>,
+    atoms: BTreeMap<String, ObjectId>,
+}
+
+/// A small, fast pseudo-random number generator (SplitMix64), so that generation does not
+/// depend on the platform, or on any other crate, to be deterministic.
+struct Rng(u64);
+
+impl Synthetic {
+    /// Describe a repository of `atoms` Atoms, all valid and at the root of the tree, until
+    /// configured otherwise.
+    #[must_use]
+    pub fn new(atoms: usize) -> Self {
+        Synthetic {
+            atoms,
+            depth: 0,
+            duplicates: 0,
+            broken: 0,
+            seed: 0,
+        }
+    }
+
+    /// Nest each Atom in up to `depth` directories, chosen at random, rather than at the root.
+    #[must_use]
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Give `duplicates` Atoms, chosen at random, the id of another, valid Atom.
+    #[must_use]
+    pub fn duplicates(mut self, duplicates: usize) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Break the manifests of `broken` Atoms, chosen at random, in one of several ways.
+    #[must_use]
+    pub fn broken(mut self, broken: usize) -> Self {
+        self.broken = broken;
+        self
+    }
+
+    /// Seed the random choices of the generator, yielding a different repository for the same
+    /// parameters.
+    #[must_use]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Write the objects of the repository to `repo`, and commit them to its `HEAD`, which
+    /// must not exist yet.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no Atom would be left valid, or the objects or
+    /// the commit cannot be written.
+    pub fn generate(&self, repo: &Repository) -> Result<Generated, Error> {
+        use gix::actor::SignatureRef;
+
+        let faulty = self.duplicates + self.broken;
+        if self.atoms > 0 && faulty >= self.atoms {
+            return Err(Error::TooFaulty {
+                atoms: self.atoms,
+                faulty,
+            });
+        }
+
+        let mut rng = Rng(self.seed);
+        let roles = self.roles(&mut rng);
+        let valid: Vec<_> = (0..self.atoms)
+            .filter(|&i| matches!(roles[i], Role::Valid))
+            .collect();
+        let width = self.atoms.to_string().len();
+
+        let mut root = Dir::default();
+        let mut generated = Generated {
+            commit: ObjectId::null(repo.object_hash()),
+            atoms: self.atoms,
+            duplicates: Vec::new(),
+            broken: Vec::new(),
+        };
+        for (i, &role) in roles.iter().enumerate() {
+            let name = format!("a{i:0width$}");
+            let depth = rng.below(self.depth as u64 + 1);
+            let dirs: Vec<_> = (0..depth)
+                .map(|_| format!("d{}", rng.below(FANOUT)))
+                .collect();
+
+            let id = match role {
+                Role::Duplicate => {
+                    let other = valid[rng.below(valid.len() as u64) as usize];
+                    format!("a{other:0width$}")
+                },
+                _ => name.clone(),
+            };
+            let manifest = match role {
+                Role::Broken(Broken::Syntax) => format!("[atom\nid = \"{id}\"\n"),
+                Role::Broken(Broken::NoVersion) => format!("[atom]\nid = \"{id}\"\n"),
+                Role::Broken(Broken::BadVersion) => {
+                    format!("[atom]\nid = \"{id}\"\nversion = \"{i}\"\n")
+                },
+                Role::Valid | Role::Duplicate => {
+                    format!("[atom]\nid = \"{id}\"\nversion = \"0.1.0\"\n")
+                },
+            };
+            let manifest_name = format!("{id}{}", crate::ATOM_EXT.as_str());
+
+            let path: PathBuf = dirs.iter().chain([&name, &manifest_name]).collect();
+            match role {
+                Role::Valid => {},
+                Role::Duplicate => generated.duplicates.push(path),
+                Role::Broken(_) => generated.broken.push(path),
+            }
+
+            let tree = Tree {
+                entries: sorted(vec![
+                    blob(
+                        manifest_name,
+                        repo.write_blob(manifest).map_err(Box::new)?.detach(),
+                    ),
+                    blob(
+                        "default.nix".into(),
+                        repo.write_blob(format!("# {name}\n{{ }}\n"))
+                            .map_err(Box::new)?
+                            .detach(),
+                    ),
+                ]),
+            };
+            let dir = dirs
+                .into_iter()
+                .fold(&mut root, |dir, name| dir.dirs.entry(name).or_default());
+            dir.atoms
+                .insert(name, repo.write_object(tree).map_err(Box::new)?.detach());
+        }
+
+        let tree = root.write(repo)?;
+        let sig = SignatureRef::default();
+        let no_parents: Vec<ObjectId> = Vec::new();
+        generated.commit = repo
+            .commit_as(sig, sig, "HEAD", "synthetic atoms", tree, no_parents)
+            .map_err(Box::new)?
+            .detach();
+
+        tracing::debug!(
+            commit = %generated.commit,
+            atoms = self.atoms,
+            duplicates = self.duplicates,
+            broken = self.broken,
+            "Generated a synthetic repository"
+        );
+        Ok(generated)
+    }
+
+    /// Decide at random which Atoms are broken, or duplicates, leaving the others valid.
+    fn roles(&self, rng: &mut Rng) -> Vec<Role> {
+        let kinds = [Broken::Syntax, Broken::NoVersion, Broken::BadVersion];
+
+        // a partial Fisher-Yates shuffle picks the faulty Atoms
+        let mut order: Vec<_> = (0..self.atoms).collect();
+        let faulty = (self.duplicates + self.broken).min(self.atoms);
+        for i in 0..faulty {
+            let j = i + rng.below((self.atoms - i) as u64) as usize;
+            order.swap(i, j);
+        }
+
+        let mut roles = vec![Role::Valid; self.atoms];
+        for (n, &i) in order[..faulty].iter().enumerate() {
+            roles[i] = if n < self.broken {
+                Role::Broken(kinds[n % kinds.len()])
+            } else {
+                Role::Duplicate
+            };
+        }
+        roles
+    }
+}
+
+impl Generated {
+    /// Return the id of the commit holding the generated Atoms.
+    #[must_use]
+    pub fn commit(&self) -> ObjectId {
+        self.commit
+    }
+
+    /// Return the number of Atoms generated, whether valid or not.
+    #[must_use]
+    pub fn atoms(&self) -> usize {
+        self.atoms
+    }
+
+    /// Return the number of valid Atoms generated, with a unique id and a valid manifest.
+    #[must_use]
+    pub fn valid(&self) -> usize {
+        self.atoms - self.duplicates.len() - self.broken.len()
+    }
+
+    /// Return the paths of the manifests of the Atoms duplicating the id of another.
+    #[must_use]
+    pub fn duplicates(&self) -> &[PathBuf] {
+        &self.duplicates
+    }
+
+    /// Return the paths of the broken manifests.
+    #[must_use]
+    pub fn broken(&self) -> &[PathBuf] {
+        &self.broken
+    }
+}
+
+impl Dir {
+    /// Write the tree of the directory, and those of the directories it holds, returning its id.
+    fn write(self, repo: &Repository) -> Result<ObjectId, Error> {
+        let mut entries: Vec<_> = self
+            .atoms
+            .into_iter()
+            .map(|(name, oid)| tree(name, oid))
+            .collect();
+        for (name, dir) in self.dirs {
+            entries.push(tree(name, dir.write(repo)?));
+        }
+        let tree = Tree {
+            entries: sorted(entries),
+        };
+        Ok(repo.write_object(tree).map_err(Box::new)?.detach())
+    }
+}
+
+impl Rng {
+    /// The next number of the sequence, uniformly distributed over all `u64`.
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `n`, or zero if `n` is.
+    fn below(&mut self, n: u64) -> u64 {
+        match n {
+            0 => 0,
+            n => self.next_u64() % n,
+        }
+    }
+}
+
+fn blob(name: String, oid: ObjectId) -> Entry {
+    Entry {
+        mode: EntryKind::Blob.into(),
+        filename: name.into(),
+        oid,
+    }
+}
+
+fn tree(name: String, oid: ObjectId) -> Entry {
+    Entry {
+        mode: EntryKind::Tree.into(),
+        filename: name.into(),
+        oid,
+    }
+}
+
+/// Sort tree entries as git expects them to be.
+fn sorted(mut entries: Vec<Entry>) -> Vec<Entry> {
+    entries.sort();
+    entries
+}
@@ -45,6 +45,28 @@ fn init_repo() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[test]
+fn init_status() -> Result<(), anyhow::Error> {
+    use crate::store::QueryStore;
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let status = remote.ekala_status()?;
+    assert!(!status.is_initialized());
+    assert_eq!(status.head, status.local);
+
+    remote.ekala_init()?;
+    let status = remote.ekala_status()?;
+    assert!(status.matches());
+    assert_eq!(status.remote, Some(status.head));
+
+    // initializing again with the same root is a no-op
+    remote.ekala_init()?;
+    Ok(())
+}
+
 #[test]
 fn uninitialized_repo() -> Result<(), anyhow::Error> {
     let (dir, _remote) = init_repo_and_remote()?;
@@ -53,3 +75,1758 @@ fn uninitialized_repo() -> Result<(), anyhow::Error> {
     assert!(remote.ekala_root().is_err());
     Ok(())
 }
+
+#[test]
+fn select_remotes() -> Result<(), anyhow::Error> {
+    let (dir, _remote) = init_repo_and_remote()?;
+    let git_dir = dir.path().to_string_lossy().to_string();
+    let git = |args: &[&str]| run_git_command(&[&["-C", git_dir.as_str()][..], args].concat());
+    let select = |given: Option<&str>, configured: Option<&str>| -> anyhow::Result<_> {
+        Ok(select_remote(&gix::open(dir.as_ref())?, given, configured)?)
+    };
+    assert_eq!(default_remote(&gix::open(dir.as_ref())?)?, "origin");
+
+    git(&["remote", "rename", "origin", "a"])?;
+    assert_eq!(select(None, None)?, ("a".into(), RemoteSource::Only));
+
+    git(&["remote", "add", "b", "file:///dev/null"])?;
+    let err = select(None, None).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(Error::AmbiguousRemote(names)) if names == ["a", "b"]
+    ));
+
+    git(&["remote", "add", EKALA_REMOTE, "file:///dev/null"])?;
+    assert_eq!(
+        select(None, None)?,
+        (EKALA_REMOTE.into(), RemoteSource::Ekala)
+    );
+
+    git(&["config", "remote.pushDefault", "b"])?;
+    assert_eq!(select(None, None)?, ("b".into(), RemoteSource::PushDefault));
+
+    git(&["symbolic-ref", "HEAD", "refs/heads/main"])?;
+    git(&["config", "branch.main.pushRemote", "a"])?;
+    assert_eq!(
+        select(None, None)?,
+        ("a".into(), RemoteSource::BranchPushRemote)
+    );
+
+    assert_eq!(
+        select(None, Some("b"))?,
+        ("b".into(), RemoteSource::Configured)
+    );
+    assert_eq!(
+        select(Some(EKALA_REMOTE), Some("b"))?,
+        (EKALA_REMOTE.into(), RemoteSource::Given)
+    );
+    Ok(())
+}
+
+#[test]
+fn expand_revision_ranges() -> Result<(), anyhow::Error> {
+    use crate::store::QueryStore;
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+    let head = repo.rev_parse_single("HEAD")?.to_string();
+
+    assert_eq!(expand_revisions(&repo, &["HEAD"])?, ["HEAD"]);
+    assert_eq!(
+        expand_revisions(&repo, &["HEAD~1..HEAD", "HEAD~1"])?,
+        [head, "HEAD~1".into()]
+    );
+    assert!(expand_revisions(&repo, &["HEAD..HEAD~1"])?.is_empty());
+    Ok(())
+}
+
+// absolute paths are relative to the current drive on windows
+#[cfg(unix)]
+#[test]
+fn normalize_from_dir() -> Result<(), anyhow::Error> {
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let cwd = std::fs::canonicalize(dir.as_ref())?.join("foo");
+    assert_eq!(repo.normalize_from(&cwd, "bar")?, Path::new("foo/bar"));
+    assert_eq!(repo.normalize_from(&cwd, "../baz")?, Path::new("baz"));
+    assert_eq!(repo.normalize_from(&cwd, "/qux")?, Path::new("qux"));
+    assert!(repo.normalize_from(&cwd, "../../outside").is_err());
+    Ok(())
+}
+
+#[test]
+fn normalize_lexically() -> Result<(), anyhow::Error> {
+    let (_dir, remote) = init_repo_and_remote()?;
+    // a bare repository has no working directory to resolve paths on
+    let repo = gix::open(remote.as_ref())?;
+    assert_eq!(
+        repo.normalize_lexical("foo/./bar/../baz")?,
+        Path::new("foo/baz")
+    );
+    assert_eq!(repo.normalize_lexical("/qux")?, Path::new("qux"));
+    assert_eq!(
+        repo.normalize_lexical("deleted/atom")?,
+        Path::new("deleted/atom")
+    );
+    assert!(matches!(
+        repo.normalize_lexical("foo/../../outside"),
+        Err(Error::OutsideRoot(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn version_encoding() -> Result<(), anyhow::Error> {
+    for (version, encoded) in [
+        ("1.0.0", "1.0.0"),
+        ("1.0.0-rc.1", "1.0.0-rc.1"),
+        ("1.0.0+abc.5", "1.0.0_abc.5"),
+        ("1.0.0-x.lock", "1.0.0-x.lock_"),
+        ("1.0.0-rc+build.lock", "1.0.0-rc_build.lock_"),
+    ] {
+        let version = Version::parse(version)?;
+        assert_eq!(encode_version(&version), encoded);
+        assert_eq!(decode_version(encoded)?, version);
+        validate_ref_name(&format!("refs/atoms/foo/{encoded}/atom"))?;
+    }
+    assert!(decode_version("1.0.0_").is_err());
+    Ok(())
+}
+
+#[test]
+fn object_formats() -> Result<(), anyhow::Error> {
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let head = repo.head_id()?.detach();
+
+    let hex = head.to_hex().to_string();
+    assert_eq!(parse_object_id(&repo, hex.as_bytes()), Some(head));
+    // the id of a sha256 object is never mistaken for one of a sha1 repository
+    let sha256 = "a".repeat(64);
+    assert_eq!(parse_object_id(&repo, sha256.as_bytes()), None);
+    assert_eq!(parse_object_id(&repo, &hex.as_bytes()[1..]), None);
+    Ok(())
+}
+
+#[test]
+fn validate_ref_names() {
+    let version = "1.0.0-rc.1+build.5";
+    assert!(validate_ref_name(&format!("refs/atoms/foo/{version}/atom")).is_ok());
+    assert!(matches!(
+        validate_ref_name("refs/atoms/foo/1.0.0-x.lock/atom"),
+        Err(Error::InvalidRefName(..))
+    ));
+    assert!(matches!(
+        validate_ref_name("refs/atoms/foo/1.0.0/atom.lock"),
+        Err(Error::InvalidRefName(..))
+    ));
+    assert!(matches!(
+        validate_ref_name("refs/atoms/foo/1..0/atom"),
+        Err(Error::InvalidRefName(..))
+    ));
+    let long = format!("refs/atoms/{}/1.0.0/atom", "a".repeat(REF_MAX));
+    assert!(matches!(
+        validate_ref_name(&long),
+        Err(Error::RefTooLong(_))
+    ));
+}
+
+#[test]
+fn classify_rejections() {
+    use git::Rejection;
+    let classify = |msg: &str| git::classify_rejection(&std::io::Error::other(msg));
+    let rejected = |reason: &str| {
+        format!(
+            " ! [rejected]        refs/atoms/foo/0.1.0/atom -> refs/atoms/foo/0.1.0/atom \
+             ({reason})\nerror: failed to push some refs"
+        )
+    };
+    assert_eq!(
+        classify(&rejected("already exists")),
+        Some(Rejection::Exists)
+    );
+    assert_eq!(classify(&rejected("fetch first")), Some(Rejection::Exists));
+    assert_eq!(
+        classify(" ! [remote rejected] refs/atoms/foo/0.1.0/atom (failed to lock)"),
+        Some(Rejection::Locked)
+    );
+    assert_eq!(
+        classify(" ! [remote rejected] refs/atoms/foo/0.1.0/atom (pre-receive hook declined)"),
+        Some(Rejection::Declined)
+    );
+    assert_eq!(classify("fatal: unable to access remote"), None);
+}
+
+#[test]
+fn parse_push_notices() {
+    use git::Notice;
+    let stderr = b"remote: Resolving deltas:  50% (1/2)\rremote: Resolving deltas: 100% (2/2)\n\
+        remote: warning: File big.bin is 52.00 MB; this is larger than GitHub's recommended \
+        maximum file size of 50.00 MB\n\
+        remote: warning: File big.bin is 52.00 MB; this is larger than GitHub's recommended \
+        maximum file size of 50.00 MB\n\
+        remote: \n\
+        remote: This repository is at 95% of its storage quota\n\
+        remote: checked by the policy hook\n\
+        To ../store.git\n";
+    assert_eq!(
+        git::parse_notices(stderr),
+        [
+            Notice::LargeObject(
+                "warning: File big.bin is 52.00 MB; this is larger than GitHub's recommended \
+                 maximum file size of 50.00 MB"
+                    .into()
+            ),
+            Notice::Quota("This repository is at 95% of its storage quota".into()),
+            Notice::Message("checked by the policy hook".into()),
+        ]
+    );
+}
+
+#[test]
+fn verify_ref_updates() -> Result<(), anyhow::Error> {
+    use verify::{Error, RefUpdate, Verifier};
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let verifier = Verifier::new(&repo, None)?;
+    let null = ObjectId::null(repo.object_hash());
+
+    let update: RefUpdate = format!("{null} {} refs/heads/main", repo.empty_tree().id()).parse()?;
+    assert!(verifier.verify(&update).is_ok());
+
+    let deleted: RefUpdate = format!("{null} {null} refs/atoms/foo/0.1.0/atom").parse()?;
+    assert!(matches!(verifier.verify(&deleted), Err(Error::Deleted(_))));
+
+    let invalid: RefUpdate = format!("{null} {null} refs/atoms/foo/bar").parse()?;
+    assert!(matches!(
+        verifier.verify(&invalid),
+        Err(Error::InvalidRef(_))
+    ));
+
+    // build metadata must be encoded, so a version has only one ref
+    let verbatim: RefUpdate = format!("{null} {null} refs/atoms/foo/0.1.0+abc/atom").parse()?;
+    assert!(matches!(
+        verifier.verify(&verbatim),
+        Err(Error::InvalidRef(_))
+    ));
+
+    assert!("not an update".parse::<RefUpdate>().is_err());
+    Ok(())
+}
+
+#[test]
+fn verify_admin_refs() -> Result<(), anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+    use verify::{Error, RefUpdate, Verifier};
+
+    use crate::policy::{ORG_POLICY_FILE, POLICY_FILE};
+
+    let (_dir, remote_dir) = init_repo_and_remote()?;
+    let store = gix::open(remote_dir.as_ref())?;
+    let sig = gix::actor::SignatureRef::default();
+    let Some(head) = store.head_name()? else {
+        anyhow::bail!("the store has no branch at HEAD");
+    };
+    let head = head.as_bstr().to_string();
+    let main = store.head_id()?.detach();
+    let null = ObjectId::null(store.object_hash());
+    let update = |old, new, name: &str| RefUpdate {
+        old,
+        new,
+        name: name.to_owned(),
+    };
+    let file = |name: &str, content: &str| -> Result<ObjectId, anyhow::Error> {
+        let oid = store.write_blob(content.as_bytes())?.detach();
+        let entry = Entry {
+            mode: EntryKind::Blob.into(),
+            filename: name.into(),
+            oid,
+        };
+        Ok(store
+            .write_object(Tree {
+                entries: vec![entry],
+            })?
+            .detach())
+    };
+
+    // anyone may set up the policy of a store which has none yet
+    let open = Verifier::new(&store, None)?;
+    let tree = file(POLICY_FILE, "owners = [\"admin@example.com\"]\n")?;
+    let no_parents: Vec<ObjectId> = vec![];
+    let policy = store
+        .commit_as(sig, sig, POLICY_REF, "policy", tree, no_parents)?
+        .detach();
+    assert!(open.verify(&update(null, policy, POLICY_REF)).is_ok());
+
+    let tree = file(ORG_POLICY_FILE, "required = [\"license\"]\n")?;
+    let org = store
+        .commit_as(sig, sig, "refs/heads/org", "org policy", tree, [main])?
+        .detach();
+    let tree = store.empty_tree().id;
+    let unchanged = store
+        .commit_as(sig, sig, "refs/heads/unchanged", "unchanged", tree, [main])?
+        .detach();
+
+    // once it has one, only its owners may change what the checks rely on
+    let eve = Verifier::new(&store, Some("eve@example.com".into()))?;
+    let admin = Verifier::new(&store, Some("admin@example.com".into()))?;
+    for (old, new, name) in [
+        (policy, main, POLICY_REF),
+        (null, main, V1_ROOT),
+        (main, org, head.as_str()),
+        (org, null, head.as_str()),
+    ] {
+        assert!(matches!(
+            eve.verify(&update(old, new, name)),
+            Err(Error::Unauthorized(_))
+        ));
+        assert!(admin.verify(&update(old, new, name)).is_ok());
+    }
+    assert!(eve.verify(&update(main, unchanged, &head)).is_ok());
+    assert!(eve.verify(&update(main, org, "refs/heads/org")).is_ok());
+    Ok(())
+}
+
+#[test]
+fn ref_transactions() -> Result<(), anyhow::Error> {
+    use transaction::RefTransaction;
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let id = repo.head_id()?.detach();
+
+    let mut tx = RefTransaction::new(&repo);
+    tx.create("refs/atoms/foo/0.1.0/atom", id, "test: atom")?
+        .create("refs/atoms/foo/0.1.0/src", id, "test: src")?;
+    let refs = tx.commit()?;
+    assert_eq!(refs.len(), 2);
+    assert_eq!(refs[1].name().as_bstr(), "refs/atoms/foo/0.1.0/src");
+
+    // one edit failing leaves every other reference unwritten
+    let mut tx = RefTransaction::new(&repo);
+    tx.create("refs/atoms/foo/0.2.0/atom", id, "test: atom")?
+        .create("refs/atoms/foo/0.1.0/src", id, "test: src")?;
+    assert!(tx.commit().is_err());
+    assert!(
+        repo.try_find_reference("refs/atoms/foo/0.2.0/atom")?
+            .is_none()
+    );
+
+    let mut tx = RefTransaction::new(&repo);
+    assert!(matches!(
+        tx.create("refs/atoms/foo/1..0/atom", id, "test: atom"),
+        Err(Error::InvalidRefName(..))
+    ));
+    assert!(tx.is_empty());
+    Ok(())
+}
+
+#[test]
+fn watch_cursor() -> Result<(), anyhow::Error> {
+    use watch::{Cursor, Event};
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("watch/origin.toml");
+    let (a, b) = (
+        ObjectId::null(gix::hash::Kind::Sha1),
+        ObjectId::empty_tree(gix::hash::Kind::Sha1),
+    );
+    let (foo, bar) = ("refs/atoms/foo/0.1.0/atom", "refs/atoms/bar/0.1.0/atom");
+
+    let mut cursor = Cursor::load(&path)?;
+    let events = cursor.advance(BTreeMap::from([(foo.to_owned(), a)]));
+    assert_eq!(
+        events,
+        [Event::Added {
+            name: foo.into(),
+            id: a.to_string()
+        }]
+    );
+    cursor.save(&path)?;
+
+    // a persisted cursor resumes where it left off
+    let mut cursor = Cursor::load(&path)?;
+    assert!(
+        cursor
+            .advance(BTreeMap::from([(foo.to_owned(), a)]))
+            .is_empty()
+    );
+    let events = cursor.advance(BTreeMap::from([(bar.to_owned(), a), (foo.to_owned(), b)]));
+    assert_eq!(
+        events,
+        [
+            Event::Added {
+                name: bar.into(),
+                id: a.to_string()
+            },
+            Event::Changed {
+                name: foo.into(),
+                old: a.to_string(),
+                new: b.to_string()
+            },
+        ]
+    );
+    let events = cursor.advance(BTreeMap::new());
+    assert_eq!(events.len(), 2);
+    assert!(matches!(&events[1], Event::Removed { name, .. } if name == foo));
+    Ok(())
+}
+
+#[test]
+fn source_retention() -> Result<(), anyhow::Error> {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use gc::{Retention, Source};
+    use transaction::RefTransaction;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let sig = gix::actor::SignatureRef::default();
+    let no_parents: Vec<ObjectId> = vec![];
+    let origin = repo
+        .commit_as(sig, sig, "HEAD", "init", repo.empty_tree().id(), no_parents)?
+        .detach();
+
+    let mut tx = RefTransaction::new(&repo);
+    for version in ["0.1.0", "0.2.0", "1.0.0"] {
+        for kind in ["atom", "src"] {
+            let name = format!("refs/atoms/foo/{version}/{kind}");
+            tx.create(&name, origin, "test: publish")?;
+        }
+    }
+    tx.commit()?;
+
+    let sources = gc::sources(&repo)?;
+    assert_eq!(sources.len(), 3);
+    assert!(sources.iter().all(|s| s.origin == origin && s.time == 0));
+
+    let source = |version: &str, time: i64| Source {
+        id: Id::from_str("foo").unwrap(),
+        version: Version::parse(version).unwrap(),
+        name: format!("refs/atoms/foo/{version}/src"),
+        origin,
+        time,
+    };
+    let versions = |sources: Vec<Source>| {
+        sources
+            .into_iter()
+            .map(|s| s.version.to_string())
+            .collect::<Vec<_>>()
+    };
+    let history = vec![
+        source("0.1.0", 0),
+        source("1.0.0", 0),
+        source("0.2.0", 9 * DAY),
+    ];
+    let now = 10 * DAY;
+
+    assert!(gc::prunable(history.clone(), Retention::default(), now).is_empty());
+    let latest = Retention {
+        latest: Some(1),
+        max_age: None,
+    };
+    assert_eq!(
+        versions(gc::prunable(history.clone(), latest, now)),
+        ["0.1.0", "0.2.0"]
+    );
+    // either rule keeps a source
+    let both = Retention {
+        max_age: Some(Duration::from_secs(2 * 24 * 60 * 60)),
+        ..latest
+    };
+    assert_eq!(versions(gc::prunable(history, both, now)), ["0.1.0"]);
+
+    gc::prune(&repo, &[source("0.1.0", 0)])?;
+    assert!(
+        repo.try_find_reference("refs/atoms/foo/0.1.0/src")?
+            .is_none()
+    );
+    assert!(
+        repo.try_find_reference("refs/atoms/foo/0.1.0/atom")?
+            .is_some()
+    );
+    assert_eq!(gc::sources(&repo)?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn yank_versions() -> Result<(), anyhow::Error> {
+    use anyhow::Context;
+    use transaction::RefTransaction;
+    use verify::{RefUpdate, Verifier};
+    use yank::Yank;
+
+    let (dir, remote_dir) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let store = gix::open(remote_dir.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    let origin = store.head_id()?.detach();
+    let init = store
+        .find_commit(origin)?
+        .parent_ids()
+        .next()
+        .context("no parent")?
+        .detach();
+
+    // two versions published from the same commit, and one from another
+    let mut tx = RefTransaction::new(&store);
+    for (version, src) in [("0.1.0", origin), ("0.2.0", origin), ("0.3.0", init)] {
+        for (kind, target) in [("atom", origin), ("spec", origin), ("src", src)] {
+            let name = format!("refs/atoms/foo/{version}/{kind}");
+            tx.create(&name, target, "test: publish")?;
+        }
+    }
+    tx.commit()?;
+    let v = |version: &str| Version::parse(version).unwrap();
+    let versions = |atoms: BTreeMap<Id, BTreeSet<Version>>| -> Vec<String> {
+        atoms
+            .into_values()
+            .flatten()
+            .map(|v| v.to_string())
+            .collect()
+    };
+    let exists = |name: &str| store.try_find_reference(name).map(|r| r.is_some());
+
+    let yank = Yank::plan(&repo, "origin", "foo", &v("0.1.0"), false)?;
+    assert!(!yank.was_yanked() && !yank.is_deletion());
+    yank.apply(&remote)?;
+    assert!(exists("refs/atoms/foo/0.1.0/_yanked")?);
+    // a yanked version is no longer resolved, but remains published
+    assert_eq!(versions(resolvable(&repo, "origin")?), ["0.2.0", "0.3.0"]);
+    assert_eq!(versions(published(&repo, "origin")?).len(), 3);
+
+    // only the refs of yanked versions may be deleted, and only by a marker of the version
+    let verifier = Verifier::new(&store, None)?;
+    let null = ObjectId::null(store.object_hash());
+    let update = |old, new, name: &str| RefUpdate {
+        old,
+        new,
+        name: name.to_owned(),
+    };
+    assert!(
+        verifier
+            .verify(&update(origin, null, "refs/atoms/foo/0.1.0/atom"))
+            .is_ok()
+    );
+    assert!(matches!(
+        verifier.verify(&update(origin, null, "refs/atoms/foo/0.2.0/atom")),
+        Err(verify::Error::Deleted(_))
+    ));
+    assert!(matches!(
+        verifier.verify(&update(null, init, "refs/atoms/foo/0.2.0/_yanked")),
+        Err(verify::Error::InvalidYank(_))
+    ));
+    assert!(
+        verifier
+            .verify(&update(null, origin, "refs/atoms/foo/0.2.0/_yanked"))
+            .is_ok()
+    );
+
+    // the source of a deleted version is kept, while another version was published from it
+    let yank = Yank::plan(&repo, "origin", "foo", &v("0.1.0"), true)?;
+    assert!(yank.was_yanked());
+    assert_eq!(yank.kept(), Some("refs/atoms/foo/0.1.0/src"));
+    assert_eq!(
+        yank.deleted().collect::<Vec<_>>(),
+        [
+            "refs/atoms/foo/0.1.0/atom",
+            "refs/atoms/foo/0.1.0/spec",
+            "refs/atoms/foo/0.1.0/_yanked",
+        ]
+    );
+    yank.apply(&remote)?;
+    assert!(!exists("refs/atoms/foo/0.1.0/atom")?);
+    assert!(exists("refs/atoms/foo/0.1.0/src")?);
+
+    // a version not yet yanked is marked before it is deleted
+    let yank = Yank::plan(&repo, "origin", "foo", &v("0.3.0"), true)?;
+    assert!(!yank.was_yanked() && yank.kept().is_none());
+    yank.apply(&remote)?;
+    for kind in ["atom", "spec", "src", "_yanked"] {
+        assert!(!exists(&format!("refs/atoms/foo/0.3.0/{kind}"))?);
+    }
+    assert_eq!(versions(published(&repo, "origin")?), ["0.2.0"]);
+
+    assert!(matches!(
+        Yank::plan(&repo, "origin", "foo", &v("9.9.9"), false),
+        Err(Error::NoRef(..))
+    ));
+    Ok(())
+}
+
+#[test]
+fn follow_channels_and_tags() -> Result<(), anyhow::Error> {
+    use std::str::FromStr;
+
+    use transaction::RefTransaction;
+    use verify::{RefUpdate, Verifier};
+
+    use crate::Atom;
+    use crate::manifest::Follow;
+    use crate::publish::git::atom_commit;
+
+    let (dir, remote_dir) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let store = gix::open(remote_dir.as_ref())?;
+    let origin = store.head_id()?.detach();
+    let tree = store.empty_tree().id;
+
+    let id = Id::from_str("foo")?;
+    let mut commits = Vec::new();
+    let mut tx = RefTransaction::new(&store);
+    for version in ["0.1.0", "0.2.0"] {
+        let atom = Atom {
+            id: id.clone(),
+            version: Version::parse(version)?,
+            kind: None,
+            description: None,
+            keywords: Vec::new(),
+            license: None,
+            exclude: vec![],
+            include: vec![],
+            min_format: None,
+        };
+        let commit = atom_commit(&atom, tree, origin, Path::new("foo"));
+        let commit = store.write_object(commit)?.detach();
+        tx.create(
+            &format!("refs/atoms/foo/{version}/atom"),
+            commit,
+            "test: publish",
+        )?;
+        commits.push(commit);
+    }
+    let stable = channel::ref_name(&id, Follow::Channel("stable"));
+    let tag = channel::ref_name(&id, Follow::Tag("first"));
+    tx.create(&stable, commits[1], "test: release")?;
+    tx.create(&tag, commits[0], "test: tag")?;
+    tx.commit()?;
+
+    let snapshot = Snapshot::take(&repo, "origin")?;
+    let followed = |follow| snapshot.followed(&id, follow);
+    assert_eq!(
+        followed(Follow::Channel("stable")),
+        Some(Version::new(0, 2, 0))
+    );
+    assert_eq!(followed(Follow::Tag("first")), Some(Version::new(0, 1, 0)));
+    assert_eq!(followed(Follow::Channel("beta")), None);
+
+    // channels move freely between published versions, while tags never move
+    let verifier = Verifier::new(&store, None)?;
+    let null = ObjectId::null(store.object_hash());
+    let update = |old, new, name: &str| RefUpdate {
+        old,
+        new,
+        name: name.to_owned(),
+    };
+    assert!(
+        verifier
+            .verify(&update(commits[1], commits[0], &stable))
+            .is_ok()
+    );
+    assert!(verifier.verify(&update(commits[1], null, &stable)).is_ok());
+    assert!(matches!(
+        verifier.verify(&update(commits[1], origin, &stable)),
+        Err(verify::Error::InvalidFollow(_))
+    ));
+    assert!(matches!(
+        verifier.verify(&update(commits[0], commits[1], &tag)),
+        Err(verify::Error::Retagged(_))
+    ));
+    assert!(matches!(
+        verifier.verify(&update(commits[0], null, &tag)),
+        Err(verify::Error::Retagged(_))
+    ));
+
+    // only the owners of an Atom may move its channels, or delete them
+    commit_policy(
+        &store,
+        "namespaces = \"deny\"\n[teams.core]\nmembers = [\"alice\"]\nprefixes = [\"foo\"]\n",
+    )?;
+    let outsider = Verifier::new(&store, Some("eve".into()))?;
+    for new in [commits[0], null] {
+        assert!(matches!(
+            outsider.verify(&update(commits[1], new, &stable)),
+            Err(verify::Error::Trespass { .. })
+        ));
+    }
+    let owner = Verifier::new(&store, Some("alice".into()))?;
+    assert!(owner.verify(&update(commits[1], null, &stable)).is_ok());
+
+    // a yanked version is no longer followed, nor may it be released
+    let mut tx = RefTransaction::new(&store);
+    tx.create("refs/atoms/foo/0.2.0/_yanked", commits[1], "test: yank")?;
+    tx.commit()?;
+    let snapshot = Snapshot::take(&repo, "origin")?;
+    assert_eq!(snapshot.followed(&id, Follow::Channel("stable")), None);
+    let verifier = Verifier::new(&store, None)?;
+    assert!(matches!(
+        verifier.verify(&update(null, commits[1], "refs/atoms/foo/_channels/beta")),
+        Err(verify::Error::InvalidFollow(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn redirect_renamed_atoms() -> Result<(), anyhow::Error> {
+    use std::str::FromStr;
+
+    use redirect::Redirect;
+    use transaction::RefTransaction;
+    use verify::{RefUpdate, Verifier};
+
+    use crate::Atom;
+    use crate::publish::git::atom_commit;
+    use crate::store::Init;
+
+    let (dir, remote_dir) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let store = gix::open(remote_dir.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    let origin = store.head_id()?.detach();
+    let tree = store.empty_tree().id;
+
+    // `foo` was renamed to `bar`, which was published since
+    let (foo, bar) = (Id::from_str("foo")?, Id::from_str("bar")?);
+    let mut commits = BTreeMap::new();
+    let mut tx = RefTransaction::new(&store);
+    for (id, version) in [(&foo, "0.1.0"), (&bar, "0.2.0"), (&bar, "0.3.0")] {
+        let atom = Atom {
+            id: id.clone(),
+            version: Version::parse(version)?,
+            kind: None,
+            description: None,
+            keywords: Vec::new(),
+            license: None,
+            exclude: vec![],
+            include: vec![],
+            min_format: None,
+        };
+        let commit = atom_commit(&atom, tree, origin, Path::new("foo"));
+        let commit = store.write_object(commit)?.detach();
+        let name = format!("refs/atoms/{id}/{version}/atom");
+        tx.create(&name, commit, "test: publish")?;
+        commits.insert(format!("{id}@{version}"), commit);
+    }
+    tx.commit()?;
+
+    assert!(matches!(
+        Redirect::plan(&repo, "origin", &foo, &foo),
+        Err(Error::SelfRedirect(_))
+    ));
+    let redirect = Redirect::plan(&repo, "origin", &foo, &bar)?;
+    assert_eq!(redirect.version(), &Version::new(0, 3, 0));
+    assert!(!redirect.is_unchanged());
+    redirect.apply(&remote)?;
+    let snapshot = Snapshot::take(&repo, "origin")?;
+    assert_eq!(snapshot.renamed(&foo), Some(bar.clone()));
+    assert_eq!(snapshot.renamed(&bar), None);
+    assert!(Redirect::plan(&repo, "origin", &foo, &bar)?.is_unchanged());
+
+    // dependents on the former id are locked to a version of the new one
+    let manifest = crate::Manifest::from_str(
+        "[atom]\nid = \"baz\"\nversion = \"0.1.0\"\n\n[deps.atoms.foo]\nversion = \"^0.2\"\n",
+    )?;
+    let locked = lock_same_store(&remote, &manifest, &crate::Lockfile::default())?;
+    let [locked] = &locked[..] else {
+        anyhow::bail!("expected a single locked atom, got {locked:?}");
+    };
+    assert_eq!((&locked.id, locked.published_id()), (&foo, &bar));
+    assert_eq!(locked.version, Version::new(0, 2, 0));
+    assert_eq!(locked.rev, crate::ObjectSum::from(commits["bar@0.2.0"]));
+    fetch_locked(&remote, locked)?;
+
+    // a redirect must point to a published version of another Atom
+    let verifier = Verifier::new(&store, None)?;
+    let null = ObjectId::null(store.object_hash());
+    let name = redirect::ref_name(&foo);
+    let update = |old, new| RefUpdate {
+        old,
+        new,
+        name: name.clone(),
+    };
+    let [own, old, new] = [
+        &commits["foo@0.1.0"],
+        &commits["bar@0.2.0"],
+        &commits["bar@0.3.0"],
+    ];
+    assert!(verifier.verify(&update(*new, *old)).is_ok());
+    assert!(verifier.verify(&update(*new, null)).is_ok());
+    for invalid in [*own, origin] {
+        assert!(matches!(
+            verifier.verify(&update(null, invalid)),
+            Err(verify::Error::InvalidRedirect(_))
+        ));
+    }
+
+    // redirects looping back on themselves are not followed
+    let mut tx = RefTransaction::new(&store);
+    tx.create(&redirect::ref_name(&bar), *own, "test: redirect")?;
+    tx.commit()?;
+    assert_eq!(Snapshot::take(&repo, "origin")?.renamed(&foo), None);
+    Ok(())
+}
+
+#[test]
+fn verify_shared_evals() -> Result<(), anyhow::Error> {
+    use std::str::FromStr;
+
+    use transaction::RefTransaction;
+    use verify::{RefUpdate, Verifier};
+
+    use crate::eval::Key;
+    use crate::publish::git::atom_commit;
+    use crate::store::Init;
+    use crate::{Atom, AtomId};
+
+    let (dir, remote_dir) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let store = gix::open(remote_dir.as_ref())?;
+    repo.find_remote("origin")?.ekala_init()?;
+    let origin = store.head_id()?.detach();
+
+    let version = Version::new(0, 1, 0);
+    let atom = Atom {
+        id: Id::from_str("foo")?,
+        version: version.clone(),
+        kind: None,
+        description: None,
+        keywords: Vec::new(),
+        license: None,
+        exclude: vec![],
+        include: vec![],
+        min_format: None,
+    };
+    let commit = atom_commit(&atom, store.empty_tree().id, origin, Path::new("foo"));
+    let commit = store.write_object(commit)?.detach();
+    let mut tx = RefTransaction::new(&store);
+    tx.create("refs/atoms/foo/0.1.0/atom", commit, "test: publish")?;
+    tx.commit()?;
+
+    let verifier = Verifier::new(&store, None)?;
+    let null = ObjectId::null(store.object_hash());
+    let output = store.write_blob(b"{}")?.detach();
+    let shared = |id: &str| -> Result<String, anyhow::Error> {
+        let id = AtomId::compute(&store.head_commit()?, Id::from_str(id)?)?;
+        Ok(eval::eval_ref(&Key::new(&id, &version, "content".into())))
+    };
+    let update = |old, new, name: String| RefUpdate { old, new, name };
+
+    assert!(
+        verifier
+            .verify(&update(null, output, shared("foo")?))
+            .is_ok()
+    );
+    // outputs are shared once, as blobs, and only for Atoms published to the store
+    assert!(matches!(
+        verifier.verify(&update(output, output, shared("foo")?)),
+        Err(verify::Error::Immutable(_))
+    ));
+    assert!(matches!(
+        verifier.verify(&update(null, commit, shared("foo")?)),
+        Err(verify::Error::Find(_))
+    ));
+    assert!(matches!(
+        verifier.verify(&update(null, output, shared("bar")?)),
+        Err(verify::Error::UnknownEval(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn verify_signed_protections() -> Result<(), anyhow::Error> {
+    use std::str::FromStr;
+
+    use verify::{RefUpdate, Verifier};
+
+    use crate::Atom;
+    use crate::publish::git::atom_commit;
+    use crate::store::Init;
+
+    let (dir, remote_dir) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let store = gix::open(remote_dir.as_ref())?;
+    repo.find_remote("origin")?.ekala_init()?;
+    let origin = store.head_id()?.detach();
+
+    commit_policy(
+        &store,
+        "[[protected]]\nid = \"foo\"\nkeys = [\"SHA256:key\"]\n",
+    )?;
+
+    let verifier = Verifier::new(&store, None)?;
+    let null = ObjectId::null(store.object_hash());
+    let publish = |id: &str| -> Result<_, anyhow::Error> {
+        let atom = Atom {
+            id: Id::from_str(id)?,
+            version: Version::new(0, 1, 0),
+            kind: None,
+            description: None,
+            keywords: Vec::new(),
+            license: None,
+            exclude: vec![],
+            include: vec![],
+            min_format: None,
+        };
+        let commit = atom_commit(&atom, store.empty_tree().id, origin, Path::new(id));
+        let commit = store.write_object(commit)?.detach();
+        Ok(verifier.verify(&RefUpdate {
+            old: null,
+            new: commit,
+            name: format!("refs/atoms/{id}/0.1.0/atom"),
+        }))
+    };
+
+    // anyone may publish a version bound to keys, but only signed by one of them
+    assert!(matches!(
+        publish("foo")?,
+        Err(verify::Error::Unsigned { .. })
+    ));
+    assert!(publish("bar")?.is_ok());
+    Ok(())
+}
+
+#[test]
+fn inspect_atom_headers() -> Result<(), anyhow::Error> {
+    use std::str::FromStr;
+
+    use crate::Atom;
+    use crate::id::Id;
+    use crate::publish::git::atom_commit;
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let atom = Atom {
+        id: Id::from_str("foo")?,
+        version: semver::Version::new(0, 1, 0),
+        kind: None,
+        description: None,
+        keywords: Vec::new(),
+        license: None,
+        exclude: vec![],
+        include: vec![],
+        min_format: None,
+    };
+    let tree = repo.empty_tree().id;
+    let origin = repo.write_blob(b"origin")?.detach();
+
+    let mut commit = atom_commit(&atom, tree, origin, Path::new("dir"));
+    let valid = repo.write_object(commit.clone())?.detach();
+    let headers = verify::inspect_headers(&repo, &valid)?;
+    assert_eq!(headers.get("path"), Some("dir"));
+    assert!(headers.issues.is_empty());
+
+    commit.extra_headers[2].1 = "0".into();
+    let tampered = repo.write_object(commit)?.detach();
+    let headers = verify::inspect_headers(&repo, &tampered)?;
+    // both the format itself and the reproducibility of the commit are reported
+    assert_eq!(headers.issues.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn artifact_digests() -> Result<(), anyhow::Error> {
+    use crate::Digest;
+
+    let digest = Digest::of(b"artifact");
+    assert_eq!(digest.to_string().parse::<Digest>()?, digest);
+    assert!("sha256:00".parse::<Digest>().is_err());
+    assert_eq!(
+        artifact::artifact_ref("foo", &semver::Version::new(0, 1, 0), "tarball"),
+        "refs/atoms/foo/_artifacts/0.1.0/tarball"
+    );
+    Ok(())
+}
+
+#[test]
+fn non_utf8_paths() -> Result<(), anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let blob = repo.write_blob(b"content")?.detach();
+    let entry = |kind: EntryKind, name: &[u8], oid| Entry {
+        mode: kind.into(),
+        filename: name.into(),
+        oid,
+    };
+
+    let valid = repo
+        .write_object(Tree {
+            entries: vec![entry(EntryKind::Blob, b"ok", blob)],
+        })?
+        .detach();
+    let invalid = repo
+        .write_object(Tree {
+            entries: vec![
+                entry(EntryKind::Blob, b"ok", blob),
+                entry(EntryKind::Blob, b"\xff", blob),
+            ],
+        })?
+        .detach();
+    let nested = repo
+        .write_object(Tree {
+            entries: vec![entry(EntryKind::Tree, b"dir", invalid)],
+        })?
+        .detach();
+
+    assert_eq!(&*try_to_tree_path(Path::new("dir/ok"))?, "dir/ok");
+    assert_eq!(find_non_utf8_path(&repo, valid)?, None);
+    assert_eq!(
+        find_non_utf8_path(&repo, nested)?,
+        Some(BString::from(&b"dir/\xff"[..]))
+    );
+    Ok(())
+}
+
+#[test]
+fn content_streaming() -> Result<(), anyhow::Error> {
+    use std::io::Read;
+
+    use content::Mode;
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let blob = repo.write_blob(b"content")?.detach();
+    let link = repo.write_blob(b"../b")?.detach();
+    let entry = |kind: EntryKind, name: &str, oid| Entry {
+        mode: kind.into(),
+        filename: name.into(),
+        oid,
+    };
+
+    let nested = repo
+        .write_object(Tree {
+            entries: vec![
+                entry(EntryKind::BlobExecutable, "b", blob),
+                entry(EntryKind::Link, "c", link),
+            ],
+        })?
+        .detach();
+    let tree = repo
+        .write_object(Tree {
+            entries: vec![
+                entry(EntryKind::Blob, "a", blob),
+                entry(EntryKind::Tree, "dir", nested),
+                entry(EntryKind::Blob, "z", blob),
+            ],
+        })?
+        .detach();
+
+    let entries = content::entries(&repo, tree).collect::<Result<Vec<_>, _>>()?;
+    let listing: Vec<_> = entries.iter().map(|e| (e.path.as_str(), e.mode)).collect();
+    assert_eq!(
+        listing,
+        [
+            ("a", Mode::File),
+            ("dir", Mode::Dir),
+            ("dir/b", Mode::Executable),
+            ("dir/c", Mode::Symlink),
+            ("z", Mode::File),
+        ]
+    );
+
+    let mut data = String::new();
+    entries[2].reader().read_to_string(&mut data)?;
+    assert_eq!(data, "content");
+    assert_eq!(entries[3].data, b"../b");
+    assert!(entries[1].data.is_empty());
+    Ok(())
+}
+
+#[test]
+fn deterministic_archives() -> Result<(), anyhow::Error> {
+    use archive::Format;
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let blob = repo.write_blob(b"content")?.detach();
+    let entry = |kind: EntryKind, name: &str, oid| Entry {
+        mode: kind.into(),
+        filename: name.into(),
+        oid,
+    };
+    let nested = repo
+        .write_object(Tree {
+            entries: vec![entry(EntryKind::BlobExecutable, "run", blob)],
+        })?
+        .detach();
+    let tree = repo
+        .write_object(Tree {
+            entries: vec![
+                entry(EntryKind::Blob, "a", blob),
+                entry(EntryKind::Tree, "bin", nested),
+            ],
+        })?
+        .detach();
+
+    let mut tar = Vec::new();
+    let digest = archive::export(&repo, tree, Format::Tar, &mut tar)?;
+    assert_eq!(digest, crate::Digest::of(&tar));
+    // a header and a block of data for each file, a header for the directory, and the end
+    assert_eq!(tar.len(), 512 * (2 + 1 + 2 + 2));
+    assert_eq!(&tar[..2], b"a\0");
+    assert_eq!(&tar[257..265], b"ustar\x0000");
+    assert_eq!(&tar[1024..1029], b"bin/\0");
+
+    let mut again = Vec::new();
+    assert_eq!(
+        archive::export(&repo, tree, Format::Tar, &mut again)?,
+        digest
+    );
+    assert_eq!(tar, again);
+
+    let mut zip = Vec::new();
+    archive::export(&repo, tree, Format::Zip, &mut zip)?;
+    assert_eq!(&zip[..4], b"PK\x03\x04");
+    // the end of the central directory records its three entries
+    let end = &zip[zip.len() - 22..];
+    assert_eq!(&end[..4], b"PK\x05\x06");
+    assert_eq!(&end[8..12], [3, 0, 3, 0]);
+
+    assert_eq!(Format::from_path(Path::new("atom.zip")), Some(Format::Zip));
+    assert_eq!(Format::from_path(Path::new("atom.tar.zst")), None);
+    Ok(())
+}
+
+#[test]
+fn unpack_archives() -> Result<(), anyhow::Error> {
+    use archive::Format;
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let blob = repo.write_blob(b"content")?.detach();
+    let target = repo.write_blob(b"../a")?.detach();
+    let entry = |kind: EntryKind, name: &str, oid| Entry {
+        mode: kind.into(),
+        filename: name.into(),
+        oid,
+    };
+    let nested = repo
+        .write_object(Tree {
+            entries: vec![
+                entry(EntryKind::Link, "link", target),
+                entry(EntryKind::BlobExecutable, "run", blob),
+            ],
+        })?
+        .detach();
+    let tree = repo
+        .write_object(Tree {
+            entries: vec![
+                entry(EntryKind::Blob, "a", blob),
+                entry(EntryKind::Tree, "bin", nested),
+            ],
+        })?
+        .detach();
+
+    let mut tar = Vec::new();
+    archive::export(&repo, tree, Format::Tar, &mut tar)?;
+    let dest = tempfile::tempdir()?;
+    archive::unpack(&tar, dest.path())?;
+    assert_eq!(std::fs::read(dest.path().join("a"))?, b"content");
+    assert_eq!(std::fs::read(dest.path().join("bin/run"))?, b"content");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dest.path().join("bin/run"))?
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o111, 0o111);
+        assert_eq!(
+            std::fs::read_link(dest.path().join("bin/link"))?,
+            Path::new("../a")
+        );
+    }
+
+    // an entry may not escape the destination
+    let mut evil = tar[..512].to_vec();
+    evil[..5].copy_from_slice(b"../x\0");
+    let escaped = archive::unpack(&evil, tempfile::tempdir()?.path());
+    assert!(matches!(escaped, Err(archive::Error::Escapes(_))));
+    assert!(matches!(
+        archive::unpack(&tar[..1000], tempfile::tempdir()?.path()),
+        Err(archive::Error::Malformed)
+    ));
+
+    // nor may a symlink resolve outside of it, even through another one
+    let link = |target: &str| repo.write_blob(target.as_bytes()).map(gix::Id::detach);
+    let up = repo
+        .write_object(Tree {
+            entries: vec![entry(EntryKind::Link, "b", link("../x")?)],
+        })?
+        .detach();
+    for entries in [
+        vec![entry(EntryKind::Link, "up", link("../outside")?)],
+        vec![
+            entry(EntryKind::Link, "a", link("d/b/../..")?),
+            entry(EntryKind::Tree, "d", up),
+            entry(EntryKind::Tree, "x", nested),
+        ],
+    ] {
+        let tree = repo.write_object(Tree { entries })?.detach();
+        let mut tar = Vec::new();
+        archive::export(&repo, tree, Format::Tar, &mut tar)?;
+        let escaped = archive::unpack(&tar, tempfile::tempdir()?.path());
+        assert!(matches!(escaped, Err(archive::Error::Escapes(_))));
+    }
+
+    // and entries are never written through a symlink already in the destination
+    #[cfg(unix)]
+    {
+        let (dest, outside) = (tempfile::tempdir()?, tempfile::tempdir()?);
+        std::os::unix::fs::symlink(outside.path(), dest.path().join("bin"))?;
+        let escaped = archive::unpack(&tar, dest.path());
+        assert!(matches!(escaped, Err(archive::Error::Escapes(_))));
+        assert!(!outside.path().join("run").exists());
+    }
+    Ok(())
+}
+
+#[test]
+fn sparse_materialization() -> Result<(), anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let blob = repo.write_blob(b"content")?.detach();
+    let entry = |kind: EntryKind, name: &str, oid| Entry {
+        mode: kind.into(),
+        filename: name.into(),
+        oid,
+    };
+
+    let modules = repo
+        .write_object(Tree {
+            entries: vec![entry(EntryKind::Blob, "mod.nix", blob)],
+        })?
+        .detach();
+    let tree = repo
+        .write_object(Tree {
+            entries: vec![
+                entry(EntryKind::Blob, "assets.bin", blob),
+                entry(EntryKind::Tree, "modules", modules),
+            ],
+        })?
+        .detach();
+
+    let dest = tempfile::tempdir()?;
+    materialize_path(&repo, tree, Path::new("modules/"), dest.path())?;
+    assert!(dest.path().join("modules/mod.nix").is_file());
+    assert!(!dest.path().join("assets.bin").exists());
+
+    materialize_path(&repo, tree, Path::new("./assets.bin"), dest.path())?;
+    assert_eq!(std::fs::read(dest.path().join("assets.bin"))?, b"content");
+
+    for missing in ["vendor", "../modules", "modules/mod.nix/x"] {
+        assert!(matches!(
+            materialize_path(&repo, tree, Path::new(missing), dest.path()),
+            Err(Error::NotInTree(_))
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn materialize_rejects_unsafe_entries() -> Result<(), anyhow::Error> {
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let blob = repo.write_blob(b"evil")?.detach();
+    let link = repo.write_blob(b"../outside")?.detach();
+    // trees are encoded by hand, as gix would refuse to encode some of these names
+    let raw_tree = |entries: &[(&str, &str, ObjectId)]| -> Result<ObjectId, anyhow::Error> {
+        let mut buf = Vec::new();
+        for (mode, name, oid) in entries {
+            buf.extend_from_slice(format!("{mode} {name}\0").as_bytes());
+            buf.extend_from_slice(oid.as_bytes());
+        }
+        Ok(repo.write_buf(gix::object::Kind::Tree, &buf)?.detach())
+    };
+    let inner = raw_tree(&[("100644", "evil", blob)])?;
+
+    let malicious = [
+        raw_tree(&[("40000", "..", inner)])?,
+        raw_tree(&[("40000", ".", inner)])?,
+        raw_tree(&[("40000", ".GIT", inner)])?,
+        raw_tree(&[("100644", "a/b", blob)])?,
+        raw_tree(&[("100644", "a\\b", blob)])?,
+        raw_tree(&[("100644", "x", blob), ("100644", "x", blob)])?,
+        raw_tree(&[("120000", "link", link), ("40000", "link", inner)])?,
+    ];
+    for tree in malicious {
+        let root = tempfile::tempdir()?;
+        let dest = root.path().join("checkout");
+        assert!(matches!(
+            materialize(&repo, tree, &dest),
+            Err(Error::UnsafePath(_))
+        ));
+        assert!(!root.path().join("evil").exists());
+        assert!(!root.path().join("outside").exists());
+    }
+
+    let safe = raw_tree(&[("100644", "evil", blob)])?;
+    let dest = tempfile::tempdir()?;
+    materialize(&repo, safe, dest.path())?;
+    assert!(dest.path().join("evil").is_file());
+    Ok(())
+}
+
+#[test]
+fn write_directories_atomically() -> Result<(), anyhow::Error> {
+    let parent = tempfile::tempdir()?;
+    let dest = parent.path().join("checkout");
+    let names = || -> std::io::Result<Vec<_>> {
+        std::fs::read_dir(parent.path())?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect()
+    };
+
+    // a failed write leaves nothing behind
+    let failed = write_atomically(&dest, |tmp| -> Result<(), Error> {
+        std::fs::create_dir_all(tmp)?;
+        std::fs::write(tmp.join("partial"), b"")?;
+        Err(Error::NotInTree("missing".into()))
+    });
+    assert!(matches!(failed, Err(Error::NotInTree(_))));
+    assert!(names()?.is_empty());
+
+    write_atomically(&dest, |tmp| -> Result<(), Error> {
+        std::fs::create_dir_all(tmp)?;
+        assert!(!dest.exists());
+        Ok(std::fs::write(tmp.join("first"), b"")?)
+    })?;
+    assert!(dest.join("first").is_file());
+
+    // a directory written first is kept
+    write_atomically(&dest, |tmp| -> Result<(), Error> {
+        std::fs::create_dir_all(tmp)?;
+        Ok(std::fs::write(tmp.join("second"), b"")?)
+    })?;
+    assert!(dest.join("first").is_file());
+    assert!(!dest.join("second").exists());
+    assert_eq!(names()?, ["checkout"]);
+    Ok(())
+}
+
+#[test]
+fn synthetic_repos() -> Result<(), anyhow::Error> {
+    use synthetic::{Error as SynthError, Synthetic};
+
+    use crate::publish::Builder;
+    use crate::publish::error::git::Error as PublishError;
+    use crate::publish::git::GitPublisher;
+
+    let generate = |synthetic: Synthetic| -> Result<_, anyhow::Error> {
+        let (dir, remote) = init_repo_and_remote()?;
+        let repo = gix::open(dir.as_ref())?;
+        repo.find_remote("origin")?.ekala_init()?;
+        let generated = synthetic.generate(&repo)?;
+        Ok((dir, remote, repo, generated))
+    };
+
+    let synthetic = Synthetic::new(60).depth(3).broken(6);
+    let (_dir, _remote, repo, generated) = generate(synthetic)?;
+    assert_eq!(generated.valid(), 54);
+    assert_eq!(generated.broken().len(), 6);
+    let (atoms, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?
+        .lexical(true)
+        .build()?;
+    assert_eq!(atoms.len(), generated.valid());
+    assert_eq!(publisher.take_warnings().len(), generated.broken().len());
+
+    // the same parameters always yield the same commit, and another seed another one
+    let (_dir, _remote, _, again) = generate(synthetic)?;
+    assert_eq!(again, generated);
+    let (_dir, _remote, _, other) = generate(synthetic.seed(1))?;
+    assert_ne!(other.commit(), generated.commit());
+
+    let (_dir, _remote, repo, generated) = generate(Synthetic::new(20).duplicates(2))?;
+    assert_eq!(generated.duplicates().len(), 2);
+    let publisher = GitPublisher::new(&repo, "origin", "HEAD")?.lexical(true);
+    assert!(matches!(
+        publisher.build(),
+        Err(PublishError::Duplicates(_))
+    ));
+
+    assert!(matches!(
+        Synthetic::new(2).broken(1).duplicates(1).generate(&repo),
+        Err(SynthError::TooFaulty { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn detect_provider_api() -> Result<(), anyhow::Error> {
+    use std::collections::HashMap;
+
+    use api::{Api, Provider};
+    use config::{Alias, HostAlias, HostApi};
+
+    let host = |host: &str, api| {
+        Alias::Host(HostAlias {
+            host: host.into(),
+            api: Some(api),
+            ssh_user: None,
+        })
+    };
+    let aliases = HashMap::from([
+        ("gl".to_owned(), host("gitlab.com", HostApi::Git)),
+        ("ghe".to_owned(), host("github.example.com", HostApi::Rest)),
+        ("plain".to_owned(), "example.com".into()),
+    ]);
+    let detect = |url: &str| -> Result<_, anyhow::Error> {
+        Ok(Api::detect(&gix::url::parse(url.into())?, &aliases))
+    };
+    for url in [
+        "https://github.com/ekala-project/atoms",
+        "https://github.com/ekala-project/atoms.git",
+        "git@github.com:ekala-project/atoms.git",
+    ] {
+        let api = detect(url)?.expect("a repository hosted by GitHub");
+        assert_eq!(api.provider(), Provider::GitHub);
+        assert_eq!(api.base(), "https://api.github.com");
+    }
+    // pages are only followed, and credentials only sent, on the origin of the API
+    let api = detect("https://github.com/ekala-project/atoms")?.expect("a GitHub repository");
+    assert!(api.serves("https://api.github.com/repositories/1/git/matching-refs/atoms/?page=2"));
+    for url in [
+        "http://api.github.com/repos",
+        "https://api.github.com.example.com/repos",
+        "https://api.github.com@example.com/repos",
+        "api.github.com/repos",
+    ] {
+        assert!(!api.serves(url), "{url}");
+    }
+    // a host an alias hints offers GitHub's API, as GitHub Enterprise does
+    let api = detect("https://github.example.com/org/atoms")?.expect("an API hinted at");
+    assert_eq!(api.base(), "https://github.example.com/api/v3");
+    assert!(api.serves("https://github.example.com/api/v3/repos"));
+    // GitLab's API does not list arbitrary refs
+    assert_eq!(detect("https://gitlab.com/ekala-project/atoms")?, None);
+    // a host no alias hints at
+    assert_eq!(detect("https://example.com/ekala-project/atoms")?, None);
+    // a url naming no repository
+    assert_eq!(detect("https://github.com/ekala-project")?, None);
+    Ok(())
+}
+
+#[test]
+fn query_without_api_lists_over_git() -> Result<(), anyhow::Error> {
+    let (dir, _remote) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    repo.find_remote("origin")?.ekala_init()?;
+    assert_eq!(
+        Snapshot::query(&repo, "origin", &config::Config::default())?,
+        Snapshot::take(&repo, "origin")?
+    );
+    Ok(())
+}
+
+#[test]
+fn mirror_atom_refs() -> Result<(), anyhow::Error> {
+    use std::str::FromStr;
+
+    use mirror::{Change, Mirror, Update};
+    use transaction::RefTransaction;
+
+    use crate::Atom;
+    use crate::manifest::Follow;
+    use crate::publish::git::atom_commit;
+    use crate::store::Init;
+
+    let (dir, remote_dir) = init_repo_and_remote()?;
+    let repo = gix::open(dir.as_ref())?;
+    let store = gix::open(remote_dir.as_ref())?;
+    repo.find_remote("origin")?.ekala_init()?;
+
+    // the mirror shares the history of the store it mirrors
+    let mirror_dir = tempfile::tempdir()?;
+    let git_dir = repo.git_dir().to_string_lossy().to_string();
+    let source = format!("file://{}", store.git_dir().display());
+    let path = mirror_dir.path().to_string_lossy().to_string();
+    let url = format!("file://{path}");
+    run_git_command(&["clone", "--bare", &source, &path])?;
+    run_git_command(&["-C", &git_dir, "remote", "add", "mirror", &url])?;
+    let repo = gix::open(dir.as_ref())?;
+    repo.find_remote("mirror")?.ekala_init()?;
+    let mirrored = gix::open(mirror_dir.path())?;
+
+    let id = Id::from_str("foo")?;
+    let atom = Atom {
+        id: id.clone(),
+        version: Version::new(0, 1, 0),
+        kind: None,
+        description: None,
+        keywords: Vec::new(),
+        license: None,
+        exclude: vec![],
+        include: vec![],
+        min_format: None,
+    };
+    let (origin, tree) = (store.head_id()?.detach(), store.empty_tree().id);
+    let commit = atom_commit(&atom, tree, origin, Path::new("foo"));
+    let commit = store.write_object(commit)?.detach();
+    let stable = channel::ref_name(&id, Follow::Channel("stable"));
+    let mut tx = RefTransaction::new(&store);
+    tx.create("refs/atoms/foo/0.1.0/atom", commit, "test: publish")?;
+    tx.create(&stable, commit, "test: release")?;
+    tx.commit()?;
+
+    let sync = Mirror::plan(&repo, "origin", "mirror", false)?;
+    assert_eq!(sync.updates().len(), 2);
+    assert!(sync.updates().iter().all(|u| u.change() == Change::Create));
+    sync.apply(&repo)?;
+    assert_eq!(
+        Snapshot::take(&repo, "mirror")?,
+        Snapshot::take(&repo, "origin")?
+    );
+    let sync = Mirror::plan(&repo, "origin", "mirror", false)?;
+    assert_eq!((sync.updates().len(), sync.unchanged()), (0, 2));
+
+    // refs the source no longer has are only deleted when pruning
+    let mut tx = RefTransaction::new(&mirrored);
+    let head = mirrored.head_id()?.detach();
+    tx.create("refs/atoms/bar/0.1.0/atom", head, "test: publish")?;
+    tx.commit()?;
+    assert!(
+        Mirror::plan(&repo, "origin", "mirror", false)?
+            .updates()
+            .is_empty()
+    );
+    let sync = Mirror::plan(&repo, "origin", "mirror", true)?;
+    let [update] = sync.updates() else {
+        anyhow::bail!("expected a single update, got {:?}", sync.updates());
+    };
+    assert_eq!(update.change(), Change::Delete);
+    sync.apply(&repo)?;
+    assert_eq!(
+        Snapshot::take(&repo, "mirror")?,
+        Snapshot::take(&repo, "origin")?
+    );
+
+    // only channels and redirects may be moved
+    let update = |name: &str| Update {
+        name: name.to_owned(),
+        old: Some(commit),
+        new: Some(origin),
+    };
+    assert_eq!(update(&stable).change(), Change::Update);
+    assert_eq!(update(&redirect::ref_name(&id)).change(), Change::Update);
+    assert_eq!(
+        update("refs/atoms/foo/0.1.0/atom").change(),
+        Change::Conflict
+    );
+    Ok(())
+}
+
+/// Commit `policy` as the policy of the store in `repo`.
+fn commit_policy(repo: &gix::Repository, policy: &str) -> Result<ObjectId, anyhow::Error> {
+    use gix::objs::Tree;
+    use gix::objs::tree::{Entry, EntryKind};
+
+    let oid = repo.write_blob(policy.as_bytes())?.detach();
+    let tree = repo
+        .write_object(Tree {
+            entries: vec![Entry {
+                mode: EntryKind::Blob.into(),
+                filename: crate::policy::POLICY_FILE.into(),
+                oid,
+            }],
+        })?
+        .detach();
+    let sig = gix::actor::SignatureRef::default();
+    let no_parents: Vec<ObjectId> = vec![];
+    Ok(repo
+        .commit_as(sig, sig, POLICY_REF, "policy", tree, no_parents)?
+        .detach())
+}
+
+/// Sign an Atom commit as configured in the repository at `dir`, returning the id of the
+/// commit unsigned, and the commit split again into its signature and payload.
+fn sign_round_trip(
+    dir: &std::path::Path,
+    signing: &config::SigningConfig,
+) -> Result<(gix::Repository, ObjectId, sign::Signed), anyhow::Error> {
+    use gix::objs::{CommitRef, WriteTo};
+
+    let repo = gix::open(dir)?;
+    let mut commit = gix::objs::Commit {
+        tree: ObjectId::empty_tree(repo.object_hash()),
+        parents: Default::default(),
+        author: Default::default(),
+        committer: Default::default(),
+        encoding: None,
+        message: "atom".into(),
+        extra_headers: vec![],
+    };
+    let mut unsigned = Vec::new();
+    commit.write_to(&mut unsigned)?;
+    let unsigned =
+        gix::objs::compute_hash(repo.object_hash(), gix::object::Kind::Commit, &unsigned);
+
+    sign::Signer::from_config(&repo, signing)?.sign_commit(&mut commit, repo.object_hash())?;
+    let mut buf = Vec::new();
+    commit.write_to(&mut buf)?;
+    let signed = sign::Signed::split(CommitRef::from_bytes(&buf)?)
+        .ok_or_else(|| anyhow::anyhow!("the commit is not signed"))?;
+    Ok((repo, unsigned, signed))
+}
+
+/// Write an executable shell script running `body`, to stand in for a configured program.
+fn program_script(path: &std::path::Path, body: &str) -> Result<(), anyhow::Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::write(path, format!("#!/bin/sh\n{body}\n"))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[test]
+fn sign_and_verify_ssh() -> Result<(), anyhow::Error> {
+    use config::{SigningConfig, SigningFormat};
+    use sign::Error;
+
+    if std::process::Command::new("ssh-keygen").output().is_err() {
+        return Ok(());
+    }
+    let (dir, _remote) = init_repo_and_remote()?;
+    let keys = tempfile::tempdir()?;
+    let key = keys.path().join("key");
+    let key = key.to_string_lossy();
+    let out = std::process::Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", "eka", "-f", &key])
+        .output()?;
+    anyhow::ensure!(out.status.success(), "ssh-keygen failed");
+    let out = std::process::Command::new("ssh-keygen")
+        .args(["-l", "-f", &format!("{key}.pub")])
+        .output()?;
+    let listing = String::from_utf8(out.stdout)?;
+    let fingerprint = listing
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| anyhow::anyhow!("no fingerprint in `{listing}`"))?
+        .to_owned();
+
+    // the configured program is used for signing and verifying alike
+    let used = keys.path().join("used");
+    let script = keys.path().join("ssh-program");
+    program_script(
+        &script,
+        &format!("echo >> '{}'\nexec ssh-keygen \"$@\"", used.display()),
+    )?;
+    let git_dir = dir.path().to_string_lossy();
+    let program = script.to_string_lossy();
+    run_git_command(&["-C", &git_dir, "config", "gpg.ssh.program", &program])?;
+
+    let signing = SigningConfig {
+        format: Some(SigningFormat::Ssh),
+        key: Some(format!("{key}.pub")),
+        ..Default::default()
+    };
+    let (repo, unsigned, signed) = sign_round_trip(dir.path(), &signing)?;
+    assert_eq!(signed.payload_id(&repo), unsigned);
+    assert!(signed.signature.contains("BEGIN SSH SIGNATURE"));
+    assert_eq!(signed.verify(&repo, &[fingerprint.clone()])?, fingerprint);
+    assert_eq!(std::fs::read_to_string(&used)?.lines().count(), 2);
+
+    assert!(matches!(
+        signed.verify(&repo, &["SHA256:other".to_owned()]),
+        Err(Error::Invalid(_))
+    ));
+    let mut tampered = signed.clone();
+    tampered.payload.extend_from_slice(b"\n");
+    assert!(tampered.verify(&repo, &[fingerprint]).is_err());
+    Ok(())
+}
+
+#[test]
+fn sign_and_verify_openpgp() -> Result<(), anyhow::Error> {
+    use config::{SigningConfig, SigningFormat};
+    use sign::Error;
+
+    if std::process::Command::new("gpg")
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        return Ok(());
+    }
+    let (dir, _remote) = init_repo_and_remote()?;
+    let home = tempfile::tempdir()?;
+    let gpg = |args: &[&str]| -> anyhow::Result<String> {
+        let out = std::process::Command::new("gpg")
+            .arg("--homedir")
+            .arg(home.path())
+            .args(["--batch", "--pinentry-mode", "loopback", "--passphrase", ""])
+            .args(args)
+            .output()?;
+        anyhow::ensure!(
+            out.status.success(),
+            "{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        Ok(String::from_utf8(out.stdout)?)
+    };
+    gpg(&[
+        "--quick-gen-key",
+        "eka <eka@example.com>",
+        "ed25519",
+        "sign",
+        "never",
+    ])?;
+    let listing = gpg(&["--with-colons", "--list-keys"])?;
+    let fingerprint = listing
+        .lines()
+        .find_map(|line| line.strip_prefix("fpr:"))
+        .and_then(|fields| fields.split(':').find(|f| !f.is_empty()))
+        .ok_or_else(|| anyhow::anyhow!("no fingerprint in `{listing}`"))?
+        .to_owned();
+
+    // the keyring is only known to the configured program
+    let script = home.path().join("gpg-program");
+    program_script(
+        &script,
+        &format!(
+            "exec gpg --homedir '{}' --batch \"$@\"",
+            home.path().display()
+        ),
+    )?;
+    let git_dir = dir.path().to_string_lossy();
+    let program = script.to_string_lossy();
+    run_git_command(&["-C", &git_dir, "config", "gpg.program", &program])?;
+
+    let signing = SigningConfig {
+        format: Some(SigningFormat::Openpgp),
+        key: Some("eka@example.com".to_owned()),
+        ..Default::default()
+    };
+    let (repo, unsigned, signed) = sign_round_trip(dir.path(), &signing)?;
+    assert_eq!(signed.payload_id(&repo), unsigned);
+    assert!(signed.signature.contains("BEGIN PGP SIGNATURE"));
+    assert_eq!(
+        signed.verify(&repo, &[fingerprint.to_lowercase()])?,
+        fingerprint
+    );
+
+    assert!(matches!(
+        signed.verify(&repo, &["0".repeat(40)]),
+        Err(Error::Invalid(_))
+    ));
+    let mut tampered = signed.clone();
+    tampered.payload.extend_from_slice(b"\n");
+    assert!(tampered.verify(&repo, &[fingerprint]).is_err());
+    Ok(())
+}
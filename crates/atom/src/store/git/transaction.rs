@@ -0,0 +1,139 @@
+//! # Reference Transactions
+//!
+//! Several references often belong together, e.g. the content, spec and origin refs of a
+//! published Atom, and a repository holding only some of them is inconsistent. A
+//! [`RefTransaction`] collects the edits to such references, and applies them to the
+//! repository at once, so that either every one of them is written, or none are.
+use gix::bstr::BString;
+use gix::refs::FullName;
+use gix::refs::transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog};
+use gix::{ObjectId, Reference, Repository};
+
+use super::Error;
+
+/// A set of reference edits, applied to a repository in a single transaction.
+#[derive(Debug)]
+pub struct RefTransaction<'a> {
+    repo: &'a Repository,
+    edits: Vec<RefEdit>,
+}
+
+impl<'a> RefTransaction<'a> {
+    /// Begin an empty transaction for the given repository.
+    #[must_use]
+    pub fn new(repo: &'a Repository) -> Self {
+        RefTransaction {
+            repo,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Create the reference `name`, pointing to `target`, failing the whole transaction if it
+    /// already exists.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `name` is not a valid full ref name.
+    pub fn create(
+        &mut self,
+        name: &str,
+        target: ObjectId,
+        message: impl Into<BString>,
+    ) -> Result<&mut Self, Error> {
+        self.update(name, target, PreviousValue::MustNotExist, message)
+    }
+
+    /// Point the reference `name` to `target`, failing the whole transaction unless its
+    /// previous value is as `expected`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `name` is not a valid full ref name.
+    pub fn update(
+        &mut self,
+        name: &str,
+        target: ObjectId,
+        expected: PreviousValue,
+        message: impl Into<BString>,
+    ) -> Result<&mut Self, Error> {
+        let name = FullName::try_from(name)
+            .map_err(|e| Error::InvalidRefName(name.to_owned(), e.to_string()))?;
+        self.edits.push(RefEdit {
+            change: Change::Update {
+                log: LogChange {
+                    mode: RefLog::AndReference,
+                    force_create_reflog: false,
+                    message: message.into(),
+                },
+                expected,
+                new: target.into(),
+            },
+            name,
+            deref: false,
+        });
+        Ok(self)
+    }
+
+    /// Delete the reference `name`, failing the whole transaction unless it points to
+    /// `target`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `name` is not a valid full ref name.
+    pub fn delete(&mut self, name: &str, target: ObjectId) -> Result<&mut Self, Error> {
+        let name = FullName::try_from(name)
+            .map_err(|e| Error::InvalidRefName(name.to_owned(), e.to_string()))?;
+        self.edits.push(RefEdit {
+            change: Change::Delete {
+                expected: PreviousValue::MustExistAndMatch(target.into()),
+                log: RefLog::AndReference,
+            },
+            name,
+            deref: false,
+        });
+        Ok(self)
+    }
+
+    /// Whether the transaction holds no edits.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Apply every edit to the repository at once, returning the written references in the
+    /// order they were added to the transaction, leaving out those deleted.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error, having written none of the references, if any of
+    /// them cannot be written, e.g. as its previous value is not as expected.
+    pub fn commit(self) -> Result<Vec<Reference<'a>>, Error> {
+        use gix::prelude::ReferenceExt;
+
+        // contend for the last ref, so that those before it are locked, then rolled back
+        #[cfg(feature = "fault-injection")]
+        let _contended = self
+            .edits
+            .last()
+            .filter(|_| {
+                super::fault::trip(self.repo.git_dir(), super::fault::Fault::LockContention)
+            })
+            .and_then(|edit| super::fault::hold_lock(self.repo, &edit.name.as_bstr().to_string()));
+
+        let edits = self.repo.edit_references(self.edits).map_err(Box::new)?;
+        Ok(edits
+            .into_iter()
+            .filter_map(|edit| match edit.change {
+                Change::Update { new, .. } => Some(
+                    gix::refs::Reference {
+                        name: edit.name,
+                        target: new,
+                        peeled: None,
+                    }
+                    .attach(self.repo),
+                ),
+                Change::Delete { .. } => None,
+            })
+            .collect())
+    }
+}
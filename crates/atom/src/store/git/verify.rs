@@ -0,0 +1,1195 @@
+//! # Server-Side Atom Verification
+//!
+//! Clients verify their Atoms before publishing, but a store has no guarantee every push came
+//! from a well-behaved client. The [`Verifier`] re-checks incoming Atom refs from the store's
+//! side, and is meant to be driven by a `pre-receive` or `update` hook, so that malformed or
+//! unauthorized Atoms are rejected before they ever become visible.
+//!
+//! During a push, git keeps the incoming objects in a quarantine directory until every hook has
+//! accepted them. It is announced to hooks through `GIT_QUARANTINE_PATH`, and is searched before
+//! the repository's own object database.
+//!
+//! The refs the checks themselves rely on, i.e. the store's policy, its root, and the
+//! organization's policy committed at its `HEAD`, may only be changed by the owners of the
+//! store's policy, once it has one.
+//!
+//! Evaluation outputs shared under [`EVAL_REF_PREFIX`] are written once, by those permitted to
+//! publish the Atom they belong to, and only the owners of the policy may remove them.
+//!
+//! Atoms already in a store can be re-verified at rest with [`verify_all`], e.g. from a nightly
+//! job, which additionally recomputes each Atom's content from its source.
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+
+use gix::objs::tree::EntryRef;
+use gix::objs::{Find, FindExt};
+use gix::{ObjectId, Repository, ThreadSafeRepository, oid};
+use semver::Version;
+use thiserror::Error as ThisError;
+
+use super::artifact::ARTIFACTS;
+use super::channel::{CHANNELS, TAGS};
+use super::eval::EVAL_REF_PREFIX;
+use super::redirect::REDIRECT;
+use super::yank::YANKED;
+use super::{POLICY_REF, V1_ROOT, include};
+use crate::id::Id;
+use crate::policy::{Breaches, Enforcement, ORG_POLICY_FILE, OrgPolicy, Policy};
+use crate::publish::{ATOM, ATOM_FORMAT_VERSION, ATOM_MANIFEST, ATOM_ORIGIN, ATOM_REF_TOP_LEVEL};
+use crate::{ATOM_EXT, AtomId, ComputeHash, Manifest, ObjectSum};
+
+/// The kind of ref an update targets, by its final path component.
+enum Kind<'a> {
+    Atom(&'a str),
+    Artifact(&'a str),
+}
+
+/// The environment variable git uses to announce the quarantined objects of a push to hooks.
+const QUARANTINE: &str = "GIT_QUARANTINE_PATH";
+
+/// The reasons an incoming ref update may be rejected.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// The update line given by git could not be parsed.
+    #[error("Malformed ref update: `{0}`")]
+    Malformed(String),
+    /// The update's object ids are not of the repository's object format.
+    #[error("`{0}` is updated with object ids of a different format than the store's")]
+    ObjectFormat(String),
+    /// The ref is under the Atom namespace, but is not a valid Atom ref.
+    #[error("`{0}` is not a valid Atom ref")]
+    InvalidRef(String),
+    /// Published Atoms are immutable, and may never be moved.
+    #[error("`{0}` already exists, and published Atoms are immutable")]
+    Immutable(String),
+    /// Published Atoms may only be deleted once yanked.
+    #[error("`{0}` may not be deleted, unless its version is yanked first")]
+    Deleted(String),
+    /// The yank marker does not point to the Atom commit of the version it marks.
+    #[error("`{0}` does not mark a published Atom version")]
+    InvalidYank(String),
+    /// The channel, or tag, does not point to a published version of its Atom which is not
+    /// yanked.
+    #[error("`{0}` does not point to a published version of its Atom which is not yanked")]
+    InvalidFollow(String),
+    /// Tags name a version for good, and may never be moved or deleted.
+    #[error("`{0}` is a tag, which may never be moved or deleted")]
+    Retagged(String),
+    /// The redirect does not point to a published version of another Atom which is not
+    /// yanked.
+    #[error("`{0}` does not point to a published version of another Atom which is not yanked")]
+    InvalidRedirect(String),
+    /// The Atom commit was not written by a compatible publisher.
+    #[error("`{name}` has an unsupported format: `{found}`")]
+    Format {
+        /// The name of the ref.
+        name: String,
+        /// The format header found, if any.
+        found: String,
+    },
+    /// The Atom commit's fields are not the fixed values required for it to be reproducible.
+    #[error("`{0}` is not a reproducible Atom commit")]
+    NotReproducible(String),
+    /// The Atom's source commit does not descend from the store's root.
+    #[error("`{0}` does not descend from the root of the store")]
+    InconsistentRoot(String),
+    /// The store has not been initialized, so the Atom's root cannot be verified.
+    #[error("The store has no root, see `eka init`")]
+    NoRoot,
+    /// The Atom's manifest is missing or does not agree with the ref it was pushed to.
+    #[error("`{0}` does not contain a manifest matching its ref")]
+    ManifestMismatch(String),
+    /// A published Atom version lacks one of the refs it is published with.
+    #[error("`{0}` is missing")]
+    Incomplete(String),
+    /// The Atom's content differs from that of the source it claims to be published from.
+    #[error("`{0}` does not match the content of its source")]
+    ContentMismatch(String),
+    /// The Atom's id is indistinguishable to a human from that of another Atom in the store.
+    #[error("The Atom id `{id}` is confusable with `{existing}`, already in the store")]
+    Confusable {
+        /// The id of the Atom.
+        id: String,
+        /// The id of the Atom already in the store.
+        existing: String,
+    },
+    /// The pusher may not publish this version of the Atom.
+    #[error("The Atom `{id}` is protected at version {version}")]
+    Protected {
+        /// The id of the Atom.
+        id: String,
+        /// The protected version.
+        version: Version,
+    },
+    /// The Atom commit of a version bound to keys by the store's policy is not signed by one.
+    #[error("The Atom `{id}` is protected at version {version}, but {reason}")]
+    Unsigned {
+        /// The id of the Atom.
+        id: String,
+        /// The protected version.
+        version: Version,
+        /// Why the signature of the Atom commit was not accepted.
+        reason: String,
+    },
+    /// The pusher is not a member of any team owning the Atom's namespace.
+    #[error("The Atom `{id}` is owned by: {owners}")]
+    Trespass {
+        /// The id of the Atom.
+        id: String,
+        /// The teams owning the Atom's namespace.
+        owners: String,
+    },
+    /// The pusher is not an owner of the store's policy, so may not change what it relies on.
+    #[error("`{0}` may only be changed by the owners of the store's policy")]
+    Unauthorized(String),
+    /// The shared evaluation output does not belong to an Atom published to the store.
+    #[error("`{0}` does not belong to an Atom published to the store")]
+    UnknownEval(String),
+    /// The Atom's manifest breaks a rule of the organization's policy.
+    #[error(transparent)]
+    Breached(#[from] Breaches),
+    /// A transparent wrapper for a [`gix::objs::find::existing_object::Error`]
+    #[error(transparent)]
+    Find(#[from] gix::objs::find::existing_object::Error),
+    /// A transparent wrapper for a [`gix::traverse::commit::simple::Error`]
+    #[error(transparent)]
+    Walk(#[from] gix::traverse::commit::simple::Error),
+    /// A transparent wrapper for a [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A transparent wrapper for a [`super::Error`]
+    #[error(transparent)]
+    Store(#[from] super::Error),
+    /// A transparent wrapper for a [`super::artifact::Error`]
+    #[error(transparent)]
+    Artifact(#[from] super::artifact::Error),
+}
+
+type VerifyResult<T> = Result<T, Error>;
+
+/// A single ref update, as reported to hooks by git.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefUpdate {
+    /// The object the ref pointed to before the push, null if it did not exist.
+    pub old: ObjectId,
+    /// The object the ref will point to after the push, null if it is being deleted.
+    pub new: ObjectId,
+    /// The full name of the ref.
+    pub name: String,
+}
+
+impl FromStr for RefUpdate {
+    type Err = Error;
+
+    /// Parse a line of the form `<old> <new> <ref>`, as given on the stdin of a `pre-receive`
+    /// hook.
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let malformed = || Error::Malformed(line.to_owned());
+        let mut parts = line.split_whitespace();
+        let (Some(old), Some(new), Some(name), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(malformed());
+        };
+
+        Ok(RefUpdate {
+            old: ObjectId::from_hex(old.as_bytes()).map_err(|_| malformed())?,
+            new: ObjectId::from_hex(new.as_bytes()).map_err(|_| malformed())?,
+            name: name.to_owned(),
+        })
+    }
+}
+
+/// An Atom version published to a store, with the refs the store holds for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Published {
+    /// The id of the Atom.
+    pub id: Id,
+    /// The published version.
+    pub version: Version,
+    /// The target of each of the version's refs, by their final path component.
+    pub refs: BTreeMap<String, ObjectId>,
+    /// The target of each of the version's artifact refs, by artifact name.
+    pub artifacts: BTreeMap<String, ObjectId>,
+}
+
+impl Published {
+    /// The full name of the version's ref of the given kind.
+    #[must_use]
+    pub fn ref_name(&self, kind: &str) -> String {
+        format!(
+            "refs/{ATOM_REF_TOP_LEVEL}/{}/{}/{kind}",
+            self.id,
+            super::encode_version(&self.version)
+        )
+    }
+}
+
+/// The objects visible to a hook: those in quarantine, followed by the repository's own.
+struct Objects<'repo> {
+    quarantine: Option<gix::odb::Handle>,
+    repo: &'repo Repository,
+}
+
+impl Find for Objects<'_> {
+    fn try_find<'a>(
+        &self,
+        id: &oid,
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<Option<gix::objs::Data<'a>>, gix::objs::find::Error> {
+        use gix::objs::Exists;
+        match &self.quarantine {
+            Some(q) if q.exists(id) => q.try_find(id, buffer),
+            _ => self.repo.objects.try_find(id, buffer),
+        }
+    }
+}
+
+/// Verifies incoming Atom refs against the format and the policy of the store.
+pub struct Verifier<'repo> {
+    objects: Objects<'repo>,
+    root: Option<ObjectId>,
+    policy: Policy,
+    has_policy: bool,
+    org_policy: OrgPolicy,
+    head: Option<String>,
+    pusher: Option<String>,
+}
+
+impl<'repo> Verifier<'repo> {
+    /// Create a verifier for the store in `repo`, checking the policy against the given identity
+    /// of the pusher. Manifests are also checked against the organization's policy committed at
+    /// the `HEAD` of the store, if any.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the quarantined objects cannot be opened, or the
+    /// store's policy, or the organization's, cannot be read.
+    pub fn new(repo: &'repo Repository, pusher: Option<String>) -> VerifyResult<Self> {
+        let quarantine = std::env::var_os(QUARANTINE).map(gix::odb::at).transpose()?;
+
+        let peel = |name: &str| {
+            repo.find_reference(name)
+                .ok()
+                .and_then(|mut r| r.peel_to_id_in_place().ok())
+                .map(gix::Id::detach)
+        };
+
+        let root = peel(V1_ROOT);
+        let policy = match peel(POLICY_REF) {
+            Some(id) => super::read_policy(repo, id)?,
+            None => None,
+        };
+        let has_policy = policy.is_some();
+        let policy = policy.unwrap_or_default();
+        let org_policy = match peel("HEAD") {
+            Some(id) => super::read_org_policy(repo, id)?.unwrap_or_default(),
+            None => OrgPolicy::default(),
+        };
+        let head = repo
+            .head_name()
+            .ok()
+            .flatten()
+            .map(|n| n.as_bstr().to_string());
+
+        Ok(Verifier {
+            objects: Objects { quarantine, repo },
+            root,
+            policy,
+            has_policy,
+            org_policy,
+            head,
+            pusher,
+        })
+    }
+
+    /// Verify Atom sources against the given root, rather than the one the repository holds,
+    /// e.g. when verifying refs before pushing them to a store from a client.
+    #[must_use]
+    pub fn root(mut self, root: ObjectId) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    /// Verify a single ref update. Outside of the Atom namespace, only shared evaluation
+    /// outputs, and changes to the store's policy, its root, or the organization's policy at
+    /// its `HEAD` are checked.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error describing why the update must be rejected.
+    pub fn verify(&self, update: &RefUpdate) -> VerifyResult<()> {
+        let name = update.name.as_str();
+        if let Some(path) = name
+            .strip_prefix(EVAL_REF_PREFIX)
+            .and_then(|n| n.strip_prefix('/'))
+        {
+            return self.verify_eval(update, path);
+        }
+        let Some(path) = name
+            .strip_prefix("refs/")
+            .and_then(|n| n.strip_prefix(ATOM_REF_TOP_LEVEL))
+            .and_then(|n| n.strip_prefix('/'))
+        else {
+            return self.verify_admin(update);
+        };
+
+        let invalid = || Error::InvalidRef(name.to_owned());
+        let (id, version, kind) = match path.split('/').collect::<Vec<_>>()[..] {
+            [id, kind @ (CHANNELS | TAGS), _] => return self.verify_follow(update, id, kind),
+            [id, REDIRECT] => return self.verify_redirect(update, id),
+            [id, ARTIFACTS, version, artifact] => (id, version, Kind::Artifact(artifact)),
+            [id, version, kind] => (id, version, Kind::Atom(kind)),
+            _ => return Err(invalid()),
+        };
+        let id = Id::from_str(id).map_err(|_| invalid())?;
+        // only the canonical encoding is accepted, so each version has exactly one ref
+        let version = super::decode_version(version)
+            .ok()
+            .filter(|v| super::encode_version(v) == version)
+            .ok_or_else(invalid)?;
+
+        if update.new.is_null() {
+            // the refs of a version may only be deleted along with the marker of its yank
+            if !self.is_yanked(&id, &version) {
+                return Err(Error::Deleted(name.to_owned()));
+            }
+            return self.check_policy(&id, &version);
+        }
+        let format = self.objects.repo.object_hash();
+        if update.old.kind() != format || update.new.kind() != format {
+            return Err(Error::ObjectFormat(name.to_owned()));
+        }
+        if !update.old.is_null() {
+            return Err(Error::Immutable(name.to_owned()));
+        }
+
+        match kind {
+            Kind::Atom(ATOM) => {
+                self.verify_atom(name, &update.new, &id, &version)?;
+                self.check_signature(&update.new, &id, &version)?;
+                self.check_confusable(&id)?;
+            },
+            Kind::Atom(ATOM_ORIGIN) => self.verify_origin(name, &update.new)?,
+            Kind::Atom(ATOM_MANIFEST) => self.verify_spec(name, &update.new, &id, &version)?,
+            Kind::Atom(YANKED) => self.verify_yank(name, &update.new, &id, &version)?,
+            Kind::Artifact(artifact) => {
+                self.verify_artifact(&update.new, &id, &version, artifact)?
+            },
+            Kind::Atom(_) => return Err(invalid()),
+        }
+
+        self.check_policy(&id, &version)
+    }
+
+    /// Verify an update of a ref outside of the Atom namespace, which must be made by an owner
+    /// of the store's policy if it changes the policy, the root of the store, or the
+    /// organization's policy at its `HEAD`.
+    fn verify_admin(&self, update: &RefUpdate) -> VerifyResult<()> {
+        let name = update.name.as_str();
+        let guarded = match name {
+            POLICY_REF | V1_ROOT => true,
+            _ if self.head.as_deref() == Some(name) => {
+                self.org_policy_file(&update.old)? != self.org_policy_file(&update.new)?
+            },
+            _ => false,
+        };
+
+        if guarded {
+            self.check_admin(name)?;
+        }
+        Ok(())
+    }
+
+    /// The blob of the organization's policy in the tree of the given commit, if any.
+    fn org_policy_file(&self, commit: &oid) -> VerifyResult<Option<ObjectId>> {
+        // git refuses to point a branch at anything but a commit, so nothing else has a policy
+        let tree = {
+            let mut buf = Vec::new();
+            match self.objects.find_commit(commit, &mut buf) {
+                Ok(commit) => commit.tree(),
+                Err(_) => return Ok(None),
+            }
+        };
+        let mut buf = Vec::new();
+        Ok(self
+            .objects
+            .find_tree(&tree, &mut buf)?
+            .entries
+            .iter()
+            .find(|e| e.mode.is_blob() && e.filename == ORG_POLICY_FILE)
+            .map(|e| e.oid.to_owned()))
+    }
+
+    /// Verify an update of a shared evaluation output, which must be a blob shared for a version
+    /// of an Atom published to the store, by those permitted to publish that version. Outputs
+    /// are never replaced, and may only be removed by the owners of the store's policy.
+    fn verify_eval(&self, update: &RefUpdate, path: &str) -> VerifyResult<()> {
+        let name = update.name.as_str();
+        let invalid = || Error::InvalidRef(name.to_owned());
+        let [hash, version, _] = path.split('/').collect::<Vec<_>>()[..] else {
+            return Err(invalid());
+        };
+        let version = super::decode_version(version)
+            .ok()
+            .filter(|v| super::encode_version(v) == version)
+            .ok_or_else(invalid)?;
+
+        if update.new.is_null() {
+            return self.check_admin(name);
+        }
+        let format = self.objects.repo.object_hash();
+        if update.old.kind() != format || update.new.kind() != format {
+            return Err(Error::ObjectFormat(name.to_owned()));
+        }
+        if !update.old.is_null() {
+            return Err(Error::Immutable(name.to_owned()));
+        }
+        let mut buf = Vec::new();
+        self.objects.find_blob(&update.new, &mut buf)?;
+
+        // the Atom is only named by the hash of its id, so it is found among those published
+        let root = ObjectSum::from(self.root.ok_or(Error::NoRoot)?);
+        let id = self
+            .atom_ids()?
+            .into_iter()
+            .find(|id| {
+                let id = AtomId::compute(&root, id.clone()).unwrap_or_else(|e| match e {});
+                id.compute_hash().to_string() == hash
+            })
+            .ok_or_else(|| Error::UnknownEval(name.to_owned()))?;
+
+        self.check_policy(&id, &version)
+    }
+
+    /// Verify an update of the channel, or tag, of the Atom `id`, which must point to the Atom
+    /// commit of one of its published versions which is not yanked. Channels may be moved and
+    /// deleted freely by the owners of the Atom `id`, but tags may only be created.
+    fn verify_follow(&self, update: &RefUpdate, id: &str, kind: &str) -> VerifyResult<()> {
+        let name = update.name.as_str();
+        let id = Id::from_str(id).map_err(|_| Error::InvalidRef(name.to_owned()))?;
+
+        let retagged = kind == TAGS && !update.old.is_null();
+        if update.new.is_null() {
+            if retagged {
+                return Err(Error::Retagged(name.to_owned()));
+            }
+            return self.check_owner(&id);
+        }
+        let format = self.objects.repo.object_hash();
+        if update.old.kind() != format || update.new.kind() != format {
+            return Err(Error::ObjectFormat(name.to_owned()));
+        }
+        if retagged {
+            return Err(Error::Retagged(name.to_owned()));
+        }
+
+        // the version is named by the message of the Atom commit, and confirmed by its ref
+        let mut buf = Vec::new();
+        let commit = self.objects.find_commit(&update.new, &mut buf)?;
+        let prefix = format!("{id}: ");
+        let version = std::str::from_utf8(&commit.message)
+            .ok()
+            .and_then(|m| m.strip_prefix(&prefix))
+            .and_then(|v| Version::parse(v).ok())
+            .filter(|v| self.find_ref(&id, v, ATOM).is_some_and(|c| c == update.new))
+            .filter(|v| !self.is_yanked(&id, v))
+            .ok_or_else(|| Error::InvalidFollow(name.to_owned()))?;
+
+        self.check_policy(&id, &version)
+    }
+
+    /// Verify an update of the redirect of the Atom `id` to the one it was renamed to, which
+    /// must point to the Atom commit of a published version of another Atom which is not
+    /// yanked. Redirects may be moved and deleted freely by the owners of the Atom `id`.
+    fn verify_redirect(&self, update: &RefUpdate, id: &str) -> VerifyResult<()> {
+        let name = update.name.as_str();
+        let id = Id::from_str(id).map_err(|_| Error::InvalidRef(name.to_owned()))?;
+        if update.new.is_null() {
+            return self.check_owner(&id);
+        }
+        let format = self.objects.repo.object_hash();
+        if update.old.kind() != format || update.new.kind() != format {
+            return Err(Error::ObjectFormat(name.to_owned()));
+        }
+
+        // the Atom and version are named by the message of the Atom commit, and confirmed by
+        // its ref
+        let invalid = || Error::InvalidRedirect(name.to_owned());
+        let mut buf = Vec::new();
+        let commit = self.objects.find_commit(&update.new, &mut buf)?;
+        let (to, version) = std::str::from_utf8(&commit.message)
+            .ok()
+            .and_then(|m| m.split_once(": "))
+            .ok_or_else(invalid)?;
+        let to = Id::from_str(to)
+            .ok()
+            .filter(|to| *to != id)
+            .ok_or_else(invalid)?;
+        Version::parse(version)
+            .ok()
+            .filter(|v| self.find_ref(&to, v, ATOM).is_some_and(|c| c == update.new))
+            .filter(|v| !self.is_yanked(&to, v))
+            .ok_or_else(invalid)?;
+
+        self.check_owner(&id)
+    }
+
+    /// The target of the ref of the given kind of an Atom version in the store, if it exists.
+    fn find_ref(&self, id: &Id, version: &Version, kind: &str) -> Option<ObjectId> {
+        let version = super::encode_version(version);
+        let name = format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{version}/{kind}");
+        self.objects
+            .repo
+            .find_reference(name.as_str())
+            .ok()
+            .and_then(|r| r.into_fully_peeled_id().ok())
+            .map(gix::Id::detach)
+    }
+
+    /// Whether the Atom version is marked yanked in the store.
+    fn is_yanked(&self, id: &Id, version: &Version) -> bool {
+        self.find_ref(id, version, YANKED).is_some()
+    }
+
+    /// Check that a yank marker points to the Atom commit of the published version it marks.
+    fn verify_yank(&self, name: &str, new: &oid, id: &Id, version: &Version) -> VerifyResult<()> {
+        match self.find_ref(id, version, ATOM) {
+            Some(commit) if &*commit == new => Ok(()),
+            _ => Err(Error::InvalidYank(name.to_owned())),
+        }
+    }
+
+    /// Check that an artifact is declared by the published Atom, and matches its hash.
+    fn verify_artifact(
+        &self,
+        new: &oid,
+        id: &Id,
+        version: &Version,
+        name: &str,
+    ) -> VerifyResult<()> {
+        use super::artifact;
+
+        Id::from_str(name).map_err(artifact::Error::from)?;
+
+        let spec = artifact::spec_ref(id, version);
+        let spec = self
+            .objects
+            .repo
+            .find_reference(spec.as_str())
+            .ok()
+            .and_then(|r| r.into_fully_peeled_id().ok())
+            .ok_or_else(|| artifact::Error::Undeclared(name.to_owned()))?;
+
+        let expected = artifact::declared(&self.objects, &spec, name)?;
+        let mut buf = Vec::new();
+        let blob = self.objects.find_blob(new, &mut buf)?;
+        artifact::verify(name, expected, blob.data)?;
+
+        Ok(())
+    }
+
+    /// Check that an Atom commit has the exact shape written by the publisher.
+    fn verify_atom(&self, name: &str, new: &oid, id: &Id, version: &Version) -> VerifyResult<()> {
+        let mut buf = Vec::new();
+        let commit = self.objects.find_commit(new, &mut buf)?;
+
+        let header = |key: &str| {
+            commit
+                .extra_headers()
+                .find(key)
+                .map(|v| v.to_string())
+                .unwrap_or_default()
+        };
+
+        let format = header("format");
+        if format != ATOM_FORMAT_VERSION {
+            return Err(Error::Format {
+                name: name.to_owned(),
+                found: format,
+            });
+        }
+
+        let blank = |s: gix::actor::SignatureRef| {
+            s.name.is_empty() && s.email.is_empty() && s.time.seconds == 0
+        };
+        let message = format!("{id}: {version}");
+        if !commit.parents.is_empty()
+            || !blank(commit.author())
+            || !blank(commit.committer())
+            || commit.message[..] != *message.as_bytes()
+            || super::parse_object_id(self.objects.repo, header(ATOM_ORIGIN).as_bytes()).is_none()
+        {
+            return Err(Error::NotReproducible(name.to_owned()));
+        }
+
+        Ok(())
+    }
+
+    /// Check that an Atom's source commit shares the store's root.
+    fn verify_origin(&self, name: &str, new: &oid) -> VerifyResult<()> {
+        use gix::traverse::commit::Simple;
+
+        let root = self.root.ok_or(Error::NoRoot)?;
+
+        let mut buf = Vec::new();
+        self.objects.find_commit(new, &mut buf)?;
+
+        for info in Simple::new(Some(new), &self.objects) {
+            if info?.id == root {
+                return Ok(());
+            }
+        }
+
+        Err(Error::InconsistentRoot(name.to_owned()))
+    }
+
+    /// Check that the manifest in an Atom's spec tree agrees with the ref it was pushed to.
+    fn verify_spec(&self, name: &str, new: &oid, id: &Id, version: &Version) -> VerifyResult<()> {
+        let mismatch = || Error::ManifestMismatch(name.to_owned());
+
+        let mut buf = Vec::new();
+        let tree = self.objects.find_tree(new, &mut buf)?;
+        let entry = tree
+            .entries
+            .iter()
+            .find(|e| e.mode.is_blob() && e.filename.ends_with(ATOM_EXT.as_bytes()))
+            .ok_or_else(mismatch)?;
+        let oid = entry.oid.to_owned();
+
+        let mut buf = Vec::new();
+        let blob = self.objects.find_blob(&oid, &mut buf)?;
+        let content = std::str::from_utf8(blob.data).map_err(|_| mismatch())?;
+        let atom = Manifest::get_atom(content).map_err(|_| mismatch())?;
+
+        if &atom.id != id || &atom.version != version {
+            return Err(mismatch());
+        }
+
+        if !self.org_policy.is_empty() {
+            let manifest = Manifest::from_str(content).map_err(|_| mismatch())?;
+            self.org_policy
+                .check(&manifest, Some(&self.versions(id)?))?;
+        }
+
+        Ok(())
+    }
+
+    /// The versions of an Atom already in the store.
+    fn versions(&self, id: &Id) -> VerifyResult<BTreeSet<Version>> {
+        use std::io;
+
+        let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/");
+        let refs = self.objects.repo.references().map_err(io::Error::other)?;
+        let mut versions = BTreeSet::new();
+        for r in refs.prefixed(prefix.as_str()).map_err(io::Error::other)? {
+            let r = r.map_err(io::Error::other)?;
+            let name = r.name().as_bstr().to_string();
+            if let Some(version) = name
+                .strip_prefix(&prefix)
+                .and_then(|n| n.split('/').next())
+                .and_then(|v| super::decode_version(v).ok())
+            {
+                versions.insert(version);
+            }
+        }
+        Ok(versions)
+    }
+
+    /// Check that no other Atom in the store has an id differing from the given one only by
+    /// case or by confusable characters.
+    fn check_confusable(&self, id: &Id) -> VerifyResult<()> {
+        let skeleton = id.skeleton();
+        for other in self.atom_ids()? {
+            if &other != id && other.skeleton() == skeleton {
+                return Err(Error::Confusable {
+                    id: id.to_string(),
+                    existing: other.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The ids of the Atoms with refs in the store.
+    fn atom_ids(&self) -> VerifyResult<BTreeSet<Id>> {
+        use std::io;
+
+        let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/");
+        let refs = self.objects.repo.references().map_err(io::Error::other)?;
+        let mut ids = BTreeSet::new();
+        for r in refs.prefixed(prefix.as_str()).map_err(io::Error::other)? {
+            let r = r.map_err(io::Error::other)?;
+            let name = r.name().as_bstr().to_string();
+            if let Some(id) = name
+                .strip_prefix(&prefix)
+                .and_then(|n| n.split('/').next())
+                .and_then(|n| Id::from_str(n).ok())
+            {
+                ids.insert(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Verify an Atom version already in the store, as its refs were verified when pushed, and
+    /// recompute its content from its source, unless the source was pruned.
+    ///
+    /// Neither the store's policy, which depends on who pushed the version, nor confusable ids,
+    /// which are reported by [`super::confusable_ids`] for the whole store at once, are checked.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error describing the first problem found with the version.
+    pub fn verify_published(&self, atom: &Published) -> VerifyResult<()> {
+        let Published { id, version, .. } = atom;
+        let find = |kind: &str| {
+            let name = atom.ref_name(kind);
+            match atom.refs.get(kind) {
+                Some(target) => Ok((name, *target)),
+                None => Err(Error::Incomplete(name)),
+            }
+        };
+
+        let (name, commit) = find(ATOM)?;
+        self.verify_atom(&name, &commit, id, version)?;
+        let (spec_name, spec) = find(ATOM_MANIFEST)?;
+        self.verify_spec(&spec_name, &spec, id, version)?;
+        if let Ok((origin_name, origin)) = find(ATOM_ORIGIN) {
+            self.verify_origin(&origin_name, &origin)?;
+            self.verify_content(&name, &commit, &origin)?;
+        }
+        for (artifact, target) in &atom.artifacts {
+            self.verify_artifact(target, id, version, artifact)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that an Atom's content is that of the directory it was published from in its
+    /// source commit, other than its manifest, whose path dependencies may have been rewritten,
+    /// the paths its manifest excludes, and those it includes from outside of it.
+    fn verify_content(&self, name: &str, new: &oid, origin: &oid) -> VerifyResult<()> {
+        let mismatch = || Error::ContentMismatch(name.to_owned());
+        let format = self.objects.repo.object_hash();
+
+        let mut buf = Vec::new();
+        let commit = self.objects.find_commit(new, &mut buf)?;
+        let header = |key: &str| {
+            commit
+                .extra_headers()
+                .find(key)
+                .map(|v| v.to_string())
+                .unwrap_or_default()
+        };
+        if header(ATOM_ORIGIN) != origin.to_string() {
+            return Err(mismatch());
+        }
+
+        let root = {
+            let mut buf = Vec::new();
+            self.objects.find_commit(origin, &mut buf)?.tree()
+        };
+        let path = header("path");
+        let mut dir = root;
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            let mut buf = Vec::new();
+            dir = self
+                .objects
+                .find_tree(&dir, &mut buf)?
+                .entries
+                .iter()
+                .find(|e| e.mode.is_tree() && e.filename == component)
+                .map(|e| e.oid.to_owned())
+                .ok_or_else(mismatch)?;
+        }
+
+        let mut buf = Vec::new();
+        let source = self.objects.find_tree(&dir, &mut buf)?;
+        let mut buf = Vec::new();
+        let entries = self.objects.find_tree(&commit.tree(), &mut buf)?.entries;
+        let is_manifest =
+            |e: &EntryRef| e.mode.is_blob() && e.filename.ends_with(ATOM_EXT.as_bytes());
+        let atom = match entries.iter().find(|e| is_manifest(*e)) {
+            Some(manifest) => {
+                let mut buf = Vec::new();
+                let blob = self.objects.find_blob(manifest.oid, &mut buf)?;
+                let content = std::str::from_utf8(blob.data).map_err(|_| mismatch())?;
+                Some(Manifest::get_atom(content).map_err(|_| mismatch())?)
+            },
+            None => None,
+        };
+        for entry in &entries {
+            // the manifest is published with its path dependencies rewritten
+            if is_manifest(entry) {
+                continue;
+            }
+            let found = source
+                .entries
+                .iter()
+                .find(|e| e.filename == entry.filename && e.mode == entry.mode)
+                .map(|e| e.oid);
+            // the content is published without the paths its manifest excludes, and with those
+            // it includes, even if it has none of its own
+            let rewritten = match &atom {
+                Some(atom) if entry.mode.is_tree() => {
+                    include::rewrite(&self.objects, format, &root, &path, found, atom)?
+                },
+                _ => None,
+            };
+            let expected = match (rewritten, found) {
+                (Some(rewritten), _) => rewritten.id,
+                (None, Some(found)) => found.to_owned(),
+                (None, None) => return Err(mismatch()),
+            };
+            if expected != entry.oid {
+                return Err(mismatch());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply the store's policy to the pusher of the given Atom.
+    fn check_policy(&self, id: &Id, version: &Version) -> VerifyResult<()> {
+        self.check_owner(id)?;
+
+        let pusher = self.pusher.as_deref();
+        if self.policy.violation(id, version, pusher).is_some() {
+            return Err(Error::Protected {
+                id: id.to_string(),
+                version: version.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check that the Atom commit of a version the store's policy binds to keys is signed by
+    /// one of them, for each protection doing so.
+    fn check_signature(&self, new: &oid, id: &Id, version: &Version) -> VerifyResult<()> {
+        use super::sign::Signed;
+
+        let protections = self.policy.signed(id, version);
+        if protections.is_empty() {
+            return Ok(());
+        }
+        let unsigned = |reason: String| Error::Unsigned {
+            id: id.to_string(),
+            version: version.clone(),
+            reason,
+        };
+
+        let mut buf = Vec::new();
+        let commit = self.objects.find_commit(new, &mut buf)?;
+        let signed = Signed::split(commit)
+            .ok_or_else(|| unsigned("its Atom commit is not signed".to_owned()))?;
+        for protected in protections {
+            signed
+                .verify(self.objects.repo, &protected.keys)
+                .map_err(|e| unsigned(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Check that the pusher is an owner of the store's policy, if it has one, before the ref
+    /// `name` it relies on is changed.
+    fn check_admin(&self, name: &str) -> VerifyResult<()> {
+        if self.has_policy && !self.policy.administers(self.pusher.as_deref()) {
+            return Err(Error::Unauthorized(name.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Check that the pusher is a member of a team owning the namespace of the Atom `id`, if
+    /// the store's policy enforces it.
+    fn check_owner(&self, id: &Id) -> VerifyResult<()> {
+        if let Some(owners) = self.policy.trespass(id, self.pusher.as_deref()) {
+            let owners = owners.join(", ");
+            match self.policy.namespaces {
+                Enforcement::Warn => tracing::warn!(
+                    message = "Accepting an Atom owned by another team",
+                    atom.id = %id,
+                    %owners,
+                ),
+                Enforcement::Deny => {
+                    return Err(Error::Trespass {
+                        id: id.to_string(),
+                        owners,
+                    });
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// List the Atom versions published to the store in `repo`, ordered by id and version.
+///
+/// # Errors
+///
+/// This function will return an error if the refs of the repository cannot be read.
+pub fn published(repo: &Repository) -> VerifyResult<Vec<Published>> {
+    use std::io;
+
+    let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/");
+    let refs = repo.references().map_err(io::Error::other)?;
+    let mut atoms: BTreeMap<(Id, Version), Published> = BTreeMap::new();
+    for r in refs.prefixed(prefix.as_str()).map_err(io::Error::other)? {
+        let mut r = r.map_err(io::Error::other)?;
+        let name = r.name().as_bstr().to_string();
+        let Some(path) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        // refs of any other shape are rejected by the hooks, and are no published Atom
+        let (id, version, kind) = match path.split('/').collect::<Vec<_>>()[..] {
+            [id, ARTIFACTS, version, artifact] => (id, version, Kind::Artifact(artifact)),
+            [id, version, kind] => (id, version, Kind::Atom(kind)),
+            _ => continue,
+        };
+        let (Ok(id), Ok(version)) = (Id::from_str(id), super::decode_version(version)) else {
+            continue;
+        };
+
+        let target = r.peel_to_id_in_place().map_err(io::Error::other)?.detach();
+        let atom = atoms
+            .entry((id.clone(), version.clone()))
+            .or_insert_with(|| Published {
+                id,
+                version,
+                refs: BTreeMap::new(),
+                artifacts: BTreeMap::new(),
+            });
+        match kind {
+            Kind::Atom(kind) => atom.refs.insert(kind.to_owned(), target),
+            Kind::Artifact(artifact) => atom.artifacts.insert(artifact.to_owned(), target),
+        };
+    }
+
+    Ok(atoms.into_values().collect())
+}
+
+/// Verify every Atom version published to the store in `repo` with
+/// [`Verifier::verify_published`], on up to `jobs` threads at once, reporting progress on
+/// stderr.
+///
+/// # Errors
+///
+/// This function will return an error if the published Atoms cannot be listed, or the store's
+/// policy cannot be read. The outcome of verifying each version is returned alongside it, in
+/// the order of [`published`].
+pub fn verify_all(
+    repo: &ThreadSafeRepository,
+    jobs: NonZeroUsize,
+) -> VerifyResult<Vec<(Published, VerifyResult<()>)>> {
+    use prodash::{Count, Progress};
+
+    let atoms = published(&repo.to_thread_local())?;
+
+    let tree = prodash::tree::Root::new();
+    // rendered at the level of the progress of other store operations
+    let mut progress = tree.add_child("verify").add_child("atoms");
+    progress.init(Some(atoms.len()), Some(prodash::unit::label("atoms")));
+    let handle = super::setup_line_renderer(&tree);
+
+    let outcomes = verify_each(repo, atoms, jobs, |_, _| progress.inc());
+    handle.shutdown_and_wait();
+    outcomes
+}
+
+/// Verify each of the given Atom versions, as listed by [`published`], on up to `jobs` threads
+/// at once, calling `observe` with the outcome of each as soon as it is verified, rather than
+/// reporting progress on stderr.
+///
+/// # Errors
+///
+/// This function will return an error if the store's policy cannot be read. The outcome of
+/// verifying each version is returned alongside it, in the order given.
+pub fn verify_each<F>(
+    repo: &ThreadSafeRepository,
+    atoms: Vec<Published>,
+    jobs: NonZeroUsize,
+    observe: F,
+) -> VerifyResult<Vec<(Published, VerifyResult<()>)>>
+where
+    F: Fn(&Published, &VerifyResult<()>) + Sync,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let next = AtomicUsize::new(0);
+    let outcomes = std::thread::scope(|s| {
+        let workers: Vec<_> = (0..jobs.get().min(atoms.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let repo = repo.to_thread_local();
+                    let verifier = Verifier::new(&repo, None)?;
+                    let mut outcomes = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(atom) = atoms.get(i) else {
+                            break;
+                        };
+                        let outcome = verifier.verify_published(atom);
+                        observe(atom, &outcome);
+                        outcomes.push((i, outcome));
+                    }
+                    VerifyResult::Ok(outcomes)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().expect("verification never panics"))
+            .collect::<VerifyResult<Vec<_>>>()
+    });
+
+    let mut outcomes: Vec<_> = outcomes?.into_iter().flatten().collect();
+    outcomes.sort_unstable_by_key(|(i, _)| *i);
+    Ok(atoms
+        .into_iter()
+        .zip(outcomes)
+        .map(|(atom, (_, outcome))| (atom, outcome))
+        .collect())
+}
+
+/// The extra headers of an Atom commit, and how they deviate from those the current publisher
+/// would write.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers {
+    /// Each header and its value, in the order they appear in the commit.
+    pub entries: Vec<(String, String)>,
+    /// A description of each deviation from the commit the current publisher would write.
+    pub issues: Vec<String>,
+}
+
+impl Headers {
+    /// The value of the given header, if the commit has it.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find_map(|(k, v)| (k == key).then_some(v.as_str()))
+    }
+}
+
+/// Read the extra headers of the Atom commit `id`, and check them against those the current
+/// publisher would write for it, e.g. to debug stores written by older or third-party
+/// publishers.
+///
+/// The Atom's id and version are taken from the commit's message, and the commit is expected
+/// to be exactly reproducible from them, its tree, and its headers, but for its signature.
+///
+/// # Errors
+///
+/// This function will return an error if the commit cannot be read.
+pub fn inspect_headers(repo: &Repository, id: &oid) -> VerifyResult<Headers> {
+    use std::path::Path;
+
+    use gix::bstr::ByteSlice;
+    use gix::objs::WriteTo;
+
+    use super::sign::{SIGNATURE, SIGNATURE_SHA256, Signed};
+    use crate::Atom;
+    use crate::publish::git::atom_commit;
+
+    const PATH: &str = "path";
+    const FORMAT: &str = "format";
+
+    let mut buf = Vec::new();
+    let commit = repo.objects.find_commit(id, &mut buf)?;
+    let entries: Vec<_> = commit
+        .extra_headers
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let header = |key: &str| {
+        entries
+            .iter()
+            .find_map(|(k, v)| (k == key).then_some(v.as_str()))
+    };
+
+    let mut issues = Vec::new();
+    for (key, _) in &entries {
+        if ![ATOM_ORIGIN, PATH, FORMAT, SIGNATURE, SIGNATURE_SHA256].contains(&key.as_str()) {
+            issues.push(format!("unexpected header `{key}`"));
+        }
+    }
+
+    match header(FORMAT) {
+        Some(ATOM_FORMAT_VERSION) => {},
+        Some(format) => issues.push(format!(
+            "`{FORMAT}` is `{format}`, expected `{ATOM_FORMAT_VERSION}`"
+        )),
+        None => issues.push(format!("`{FORMAT}` is missing")),
+    }
+
+    let origin = match header(ATOM_ORIGIN).map(|o| super::parse_object_id(repo, o.as_bytes())) {
+        Some(Some(origin)) => Some(origin),
+        Some(None) => {
+            issues.push(format!("`{ATOM_ORIGIN}` is not an object id"));
+            None
+        },
+        None => {
+            issues.push(format!("`{ATOM_ORIGIN}` is missing"));
+            None
+        },
+    };
+
+    // the path of an Atom at the root of its source is empty
+    let path = match header(PATH) {
+        Some(path)
+            if path.is_empty()
+                || path
+                    .split('/')
+                    .all(|c| !c.is_empty() && c != "." && c != "..") =>
+        {
+            Some(path)
+        },
+        Some(path) => {
+            issues.push(format!(
+                "`{PATH}` `{path}` is not a normalized relative path"
+            ));
+            None
+        },
+        None => {
+            issues.push(format!("`{PATH}` is missing"));
+            None
+        },
+    };
+
+    let message = commit.message.to_str_lossy();
+    let atom = message.split_once(": ").and_then(|(id, version)| {
+        Some(Atom {
+            id: Id::from_str(id).ok()?,
+            version: Version::parse(version).ok()?,
+            kind: None,
+            description: None,
+            keywords: Vec::new(),
+            license: None,
+            exclude: vec![],
+            include: vec![],
+            min_format: None,
+        })
+    });
+    if atom.is_none() {
+        issues.push("the message is not of the form `<id>: <version>`".into());
+    }
+
+    if let (Some(atom), Some(origin), Some(path)) = (atom, origin, path) {
+        let expected = atom_commit(&atom, commit.tree(), origin, Path::new(path));
+        let mut data = Vec::new();
+        expected.write_to(&mut data)?;
+        let expected = gix::objs::compute_hash(repo.object_hash(), expected.kind(), &data);
+        // a signed commit is reproducible but for its signature
+        let unsigned = Signed::split(commit.clone()).map(|signed| signed.payload_id(repo));
+        if expected.as_ref() != unsigned.as_deref().unwrap_or(id) {
+            issues.push(format!(
+                "the commit is not reproducible from its headers, expected {expected}"
+            ));
+        }
+    }
+
+    Ok(Headers { entries, issues })
+}
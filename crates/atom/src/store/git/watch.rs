@@ -0,0 +1,158 @@
+//! # Store Watching
+//!
+//! Long-running consumers of a store, such as bots and mirrors, need only learn which Atom
+//! refs were added, moved or removed since they last looked. A [`Cursor`] records the Atom
+//! refs of a store as of the last poll, and may be persisted between runs, so that each
+//! [`poll`] reports just the [`Event`]s since, rather than the entire ref list.
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use gix::{ObjectId, Repository};
+use serde::{Deserialize, Serialize};
+
+use super::{Error, run_git_command};
+use crate::publish::ATOM_REF_TOP_LEVEL;
+
+/// The Atom refs of a store as of the last poll, by name.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cursor {
+    /// The hex id each ref pointed to.
+    #[serde(default)]
+    refs: BTreeMap<String, String>,
+}
+
+/// A change to an Atom ref of a store, since the last poll.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The ref was created.
+    Added {
+        /// The full name of the ref.
+        name: String,
+        /// The id the ref points to.
+        id: String,
+    },
+    /// The ref was moved.
+    Changed {
+        /// The full name of the ref.
+        name: String,
+        /// The id the ref pointed to.
+        old: String,
+        /// The id the ref points to.
+        new: String,
+    },
+    /// The ref was deleted.
+    Removed {
+        /// The full name of the ref.
+        name: String,
+        /// The id the ref pointed to.
+        old: String,
+    },
+}
+
+impl Event {
+    /// The full name of the ref the event is about.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Event::Added { name, .. }
+            | Event::Changed { name, .. }
+            | Event::Removed { name, .. } => name,
+        }
+    }
+}
+
+impl Cursor {
+    /// Load a cursor persisted at `path`, starting from scratch if there is none yet.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file exists, but cannot be read or parsed.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => toml_edit::de::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Cursor::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Persist the cursor at `path`, creating its parent directories as needed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = toml_edit::ser::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, content)
+    }
+
+    /// Advance the cursor to the given refs, returning how they changed, ordered by name.
+    pub fn advance(&mut self, refs: BTreeMap<String, ObjectId>) -> Vec<Event> {
+        let refs = refs
+            .into_iter()
+            .map(|(name, id)| (name, id.to_string()))
+            .collect();
+        let old = std::mem::replace(&mut self.refs, refs);
+
+        let mut events: Vec<_> = self
+            .refs
+            .iter()
+            .filter_map(|(name, id)| match old.get(name) {
+                None => Some(Event::Added {
+                    name: name.clone(),
+                    id: id.clone(),
+                }),
+                Some(prev) if prev != id => Some(Event::Changed {
+                    name: name.clone(),
+                    old: prev.clone(),
+                    new: id.clone(),
+                }),
+                Some(_) => None,
+            })
+            .chain(
+                old.into_iter()
+                    .filter(|(name, _)| !self.refs.contains_key(name))
+                    .map(|(name, old)| Event::Removed { name, old }),
+            )
+            .collect();
+        events.sort_by(|a, b| a.name().cmp(b.name()));
+        events
+    }
+}
+
+/// List the Atom refs of `remote`, by name.
+///
+/// # Errors
+///
+/// This function will return an error if the refs of the remote cannot be listed.
+pub fn atom_refs(repo: &Repository, remote: &str) -> Result<BTreeMap<String, ObjectId>, Error> {
+    let git_dir = repo.git_dir().to_string_lossy().to_string();
+    let pattern = format!("refs/{ATOM_REF_TOP_LEVEL}/*");
+    let listing = run_git_command(&["-C", &git_dir, "ls-remote", remote, &pattern])?;
+
+    Ok(String::from_utf8_lossy(&listing)
+        .lines()
+        .filter_map(|line| {
+            let (id, name) = line.split_once('\t')?;
+            let id = super::parse_object_id(repo, id.as_bytes())?;
+            Some((name.to_owned(), id))
+        })
+        .collect())
+}
+
+/// Report how the Atom refs of `remote` changed since the `cursor` was last advanced, and
+/// advance it.
+///
+/// # Errors
+///
+/// This function will return an error if the refs of the remote cannot be listed, in which
+/// case the cursor is left untouched.
+pub fn poll(repo: &Repository, remote: &str, cursor: &mut Cursor) -> Result<Vec<Event>, Error> {
+    let refs = atom_refs(repo, remote)?;
+    Ok(cursor.advance(refs))
+}
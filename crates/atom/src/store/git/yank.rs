@@ -0,0 +1,219 @@
+//! # Yanking Published Atoms
+//!
+//! Published Atoms are immutable, so a broken version cannot be fixed in place. Yanking it
+//! instead marks it with a [`YANKED`] ref beside its own, pointing to its Atom commit:
+//!
+//! ```console
+//! refs/atoms/<id>/<version>/_yanked
+//! ```
+//!
+//! Resolution then passes the version over, while it remains in the store, and may never be
+//! published again.
+//!
+//! A version which must go entirely, e.g. as it leaked a secret, may be deleted outright
+//! instead. The store's hooks only accept the deletion of the refs of a version already marked
+//! yanked, so it is always marked first, and its refs, its artifacts and the marker are then
+//! deleted all at once. The `src` ref of the version is only deleted if no other version still
+//! published was published from the same commit.
+use gix::{ObjectId, Repository};
+use semver::Version;
+
+use super::artifact::ARTIFACTS;
+use super::{EkalaRemote, Error, Push, Snapshot, encode_version, validate_ref_name};
+use crate::publish::{ATOM, ATOM_MANIFEST, ATOM_ORIGIN, ATOM_REF_TOP_LEVEL};
+
+/// The kind of ref marking a published Atom version as yanked.
+pub const YANKED: &str = "_yanked";
+
+/// The yank of a published Atom version, as planned against the refs of its store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Yank {
+    id: String,
+    version: Version,
+    /// The Atom commit of the version, which the marker points to.
+    commit: ObjectId,
+    /// Whether the version was already marked yanked.
+    yanked: bool,
+    /// The refs to delete, with their targets, if the version is deleted outright.
+    delete: Vec<(String, ObjectId)>,
+    /// The `src` ref kept, as another version was published from the same commit.
+    kept: Option<String>,
+}
+
+/// The full name of the ref of the given kind of an Atom version.
+fn ref_name(id: &str, version: &Version, kind: &str) -> String {
+    let version = encode_version(version);
+    format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{version}/{kind}")
+}
+
+impl Yank {
+    /// Plan to yank the given version of an Atom published to `remote`, or with `force`, to
+    /// delete it outright, against the refs of the remote as they are now.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the refs of the remote cannot be listed, or the
+    /// version is not published to it.
+    pub fn plan(
+        repo: &Repository,
+        remote: &str,
+        id: &str,
+        version: &Version,
+        force: bool,
+    ) -> Result<Self, Error> {
+        let atom = ref_name(id, version, ATOM);
+        validate_ref_name(&atom)?;
+        let snapshot = Snapshot::take(repo, remote)?;
+        let commit = snapshot
+            .get(&atom)
+            .ok_or_else(|| Error::NoRef(atom.clone(), remote.to_owned()))?;
+        let marker = ref_name(id, version, YANKED);
+        let yanked = snapshot.get(&marker).is_some();
+
+        let mut yank = Yank {
+            id: id.to_owned(),
+            version: version.clone(),
+            commit,
+            yanked,
+            delete: Vec::new(),
+            kept: None,
+        };
+        if !force {
+            return Ok(yank);
+        }
+
+        let src = ref_name(id, version, ATOM_ORIGIN);
+        let origin = snapshot.get(&src);
+        if origin.is_some_and(|origin| shares_origin(&snapshot, &src, origin)) {
+            yank.kept = Some(src.clone());
+        }
+
+        let artifacts = format!(
+            "refs/{ATOM_REF_TOP_LEVEL}/{id}/{ARTIFACTS}/{}/",
+            encode_version(version)
+        );
+        for kind in [ATOM, ATOM_MANIFEST, ATOM_ORIGIN] {
+            let name = ref_name(id, version, kind);
+            if yank.kept.as_ref() == Some(&name) {
+                continue;
+            }
+            if let Some(target) = snapshot.get(&name) {
+                yank.delete.push((name, target));
+            }
+        }
+        yank.delete.extend(
+            snapshot
+                .refs()
+                .filter(|(name, _)| name.starts_with(&artifacts))
+                .map(|(name, target)| (name.to_owned(), target)),
+        );
+        // the marker is pushed first, if the version was not yanked already
+        let target = snapshot.get(&marker).unwrap_or(commit);
+        yank.delete.push((marker, target));
+
+        Ok(yank)
+    }
+
+    /// Return the id of the Atom yanked.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Return the version yanked.
+    #[must_use]
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Whether the version was already marked yanked when the yank was planned.
+    #[must_use]
+    pub fn was_yanked(&self) -> bool {
+        self.yanked
+    }
+
+    /// Whether the version is deleted outright, rather than only marked yanked.
+    #[must_use]
+    pub fn is_deletion(&self) -> bool {
+        !self.delete.is_empty()
+    }
+
+    /// Iterate over the full names of the refs deleted, if the version is deleted outright.
+    pub fn deleted(&self) -> impl Iterator<Item = &str> {
+        self.delete.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Return the `src` ref kept, as another version still published was published from the
+    /// same commit, if the version is deleted outright.
+    #[must_use]
+    pub fn kept(&self) -> Option<&str> {
+        self.kept.as_deref()
+    }
+
+    /// Mark the version yanked in `remote`, unless it already was, and then delete its refs,
+    /// if it is deleted outright.
+    ///
+    /// Each ref is only written, or deleted, if it still points where it did when the yank
+    /// was planned, and the refs are deleted all at once, so that none are deleted if any of
+    /// them cannot be.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the Atom commit cannot be fetched, or a push is
+    /// rejected, e.g. as any of the refs changed since the yank was planned.
+    pub fn apply(&self, remote: &gix::Remote) -> Result<(), Error> {
+        use crate::store::QueryStore;
+
+        let git_dir = remote.repo().git_dir().to_string_lossy().to_string();
+        let symbol = remote.symbol().to_owned();
+        // push each ref only if it still points to the expected target, or none at all
+        let push = |refs: &[(String, Option<ObjectId>, String)]| {
+            let refspecs = refs.iter().map(|(_, _, refspec)| refspec.clone());
+            refs.iter()
+                .fold(Push::new(refspecs).atomic(), |push, (name, expected, _)| {
+                    push.lease(name, *expected)
+                })
+                .run(&git_dir, &symbol)
+        };
+
+        if !self.yanked {
+            let atom = ref_name(&self.id, &self.version, ATOM);
+            // the marker may only be pushed along with the commit it points to
+            remote.get_ref(atom.as_str())?;
+            let marker = ref_name(&self.id, &self.version, YANKED);
+            let refspec = format!("{}:{marker}", self.commit);
+            push(&[(marker, None, refspec)])?;
+            tracing::info!(id = %self.id, version = %self.version, "Yanked");
+        }
+
+        if self.is_deletion() {
+            let refs: Vec<_> = self
+                .delete
+                .iter()
+                .map(|(name, target)| (name.clone(), Some(*target), format!(":{name}")))
+                .collect();
+            push(&refs)?;
+            tracing::info!(id = %self.id, version = %self.version, "Deleted");
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether another Atom version still published, i.e. with an `atom` ref, was published from
+/// the commit `origin`, which the `src` ref `src` points to.
+fn shares_origin(snapshot: &Snapshot, src: &str, origin: ObjectId) -> bool {
+    let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/");
+    snapshot.refs().any(|(name, target)| {
+        let Some(path) = name.strip_prefix(&prefix) else {
+            return false;
+        };
+        match path.split('/').collect::<Vec<_>>()[..] {
+            [id, version, ATOM_ORIGIN] if name != src && target == origin => {
+                let atom = format!("{prefix}{id}/{version}/{ATOM}");
+                snapshot.get(&atom).is_some()
+            },
+            _ => false,
+        }
+    })
+}
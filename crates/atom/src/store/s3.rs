@@ -0,0 +1,776 @@
+//! # S3 Object Store
+//!
+//! An Ekala store may also be kept in any S3-compatible object store, e.g. to serve consumers
+//! without git from a bucket behind a CDN. Atoms are still published from a Git repository, but
+//! their content is uploaded as a deterministic tarball, and their manifest and lock as they
+//! are, each stored under its own hash, so that identical objects are only ever stored once:
+//!
+//! ```text
+//! <prefix>/objects/blake3/<hash>                   content tarballs, manifests and locks
+//! <prefix>/refs/ekala/root                         the root commit of the published history
+//! <prefix>/refs/atoms/<id>/<version>/spec          the hash of the manifest
+//! <prefix>/refs/atoms/<id>/<version>/lock          the hash of the lock, if there is one
+//! <prefix>/refs/atoms/<id>/<version>/src           the commit the Atom was published from
+//! <prefix>/refs/atoms/<id>/<version>/atom          the hash of the content tarball
+//! ```
+//!
+//! The `atom` ref is written last, so that a version is only ever listed once every object it
+//! refers to has been uploaded. Refs are written with `If-None-Match: *`, so that of two
+//! concurrent publishes of the same version, only one ever writes them.
+//!
+//! Requests are made with the `curl` binary, version 8.3 or later, and signed with AWS
+//! Signature Version 4 from the standard `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+//! `AWS_SESSION_TOKEN` variables, which `curl` reads from the environment, so that they never
+//! appear on its command line. Without credentials, requests are sent unsigned, e.g. to read
+//! from a public bucket. Requests go to `AWS_ENDPOINT_URL` if set, addressing buckets by path,
+//! or to the AWS endpoint of the bucket in `AWS_REGION` otherwise.
+//!
+//! Credentials configured in `[auth]` for the endpoint are [applied](super::auth) as well: its
+//! client certificate is always presented, while its token or basic authentication only
+//! authorize requests which are not signed, e.g. to a bucket served behind a proxy.
+#[cfg(test)]
+mod test;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bstr::{BStr, ByteSlice};
+use config::AuthConfig;
+use gix::{ObjectId, Repository};
+use semver::Version;
+use thiserror::Error as ThisError;
+
+use super::git::{self, Root};
+use super::{BoxError, BoxFuture, EkalaStore, Init, InitStatus, Published, QueryAtoms, QueryStore};
+use crate::ObjectSum;
+use crate::id::Id;
+use crate::policy::Policy;
+
+/// The ref recording the root of the history an object store publishes Atoms from.
+pub const ROOT_REF: &str = "refs/ekala/root";
+
+/// The directory under the store's prefix holding its objects, by algorithm and hash.
+const OBJECTS: &str = "objects";
+
+/// The region requests are signed for when none is configured.
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// An error encountered while operating on an S3 object store.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// The url does not name an S3 bucket.
+    #[error("`{0}` is not an S3 url, expected `s3://<bucket>/<prefix>`")]
+    InvalidUrl(String),
+    /// The `curl` binary could not be run, or failed before receiving a response.
+    #[error("The request for `{0}` failed: {1}")]
+    Request(String, String),
+    /// The store responded to a request with an error.
+    #[error("The request for `{key}` failed with status {status}: {message}")]
+    Status {
+        /// The key of the object requested.
+        key: String,
+        /// The HTTP status of the response.
+        status: u16,
+        /// The message of the store, or the body of its response.
+        message: String,
+    },
+    /// No object exists under the key.
+    #[error("`{0}` does not exist in the store")]
+    NotFound(String),
+    /// The object does not hash to the name it is stored under.
+    #[error("`{0}` does not hold the object it is named after")]
+    Corrupt(String),
+    /// The ref does not hold a valid commit id or object hash.
+    #[error("`{0}` holds an invalid value: `{1}`")]
+    InvalidRef(String, String),
+    /// The store is not initialized.
+    #[error("The store `{0}` is not initialized")]
+    NotInitialized(String),
+    /// The store is already initialized, with a root other than that of the local history.
+    #[error("The store is already initialized with a different root: {0}")]
+    AlreadyInitialized(String),
+    /// The store was used without a Git repository, which its Atoms are published from.
+    #[error("No Git repository was found to publish to `{0}` from")]
+    NoRepository(String),
+    /// A transparent wrapper for a [`super::git::Error`]
+    #[error(transparent)]
+    Git(#[from] git::Error),
+}
+
+type S3Result<T> = Result<T, Error>;
+
+/// The location of an Ekala store in an S3 bucket, written as `s3://<bucket>/<prefix>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct S3Url {
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Url {
+    /// The name of the bucket.
+    #[must_use]
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// The prefix of every key of the store within the bucket, without slashes at either end.
+    #[must_use]
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+}
+
+impl FromStr for S3Url {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidUrl(s.to_owned());
+        let rest = s.strip_prefix("s3://").ok_or_else(invalid)?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(invalid());
+        }
+        Ok(S3Url {
+            bucket: bucket.to_owned(),
+            prefix: prefix.trim_matches('/').to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for S3Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}", self.bucket)?;
+        if !self.prefix.is_empty() {
+            write!(f, "/{}", self.prefix)?;
+        }
+        Ok(())
+    }
+}
+
+/// The methods of the requests made to a store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Get,
+    Head,
+    Put,
+    /// A `PUT` which only succeeds if no object exists under the key yet.
+    Create,
+    Delete,
+}
+
+/// An Ekala store kept in an S3-compatible object store.
+#[derive(Debug, Clone)]
+pub struct S3Store {
+    url: S3Url,
+    /// The url of the bucket, which every key is appended to.
+    base: String,
+    /// The url objects of the bucket are listed from.
+    listing: String,
+    region: String,
+    /// Whether requests are signed, as credentials are configured.
+    signed: bool,
+    /// Whether the credentials are temporary, and require a session token.
+    session: bool,
+    /// The credentials configured for the endpoint, if any.
+    auth: Option<AuthConfig>,
+}
+
+impl S3Store {
+    /// Constructs a new [`S3Store`] at the given location, configured from the environment.
+    #[must_use]
+    pub fn new(url: S3Url) -> Self {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let region = var("AWS_REGION")
+            .or_else(|| var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| DEFAULT_REGION.to_owned());
+        let (base, listing) = match var("AWS_ENDPOINT_URL") {
+            Some(endpoint) => {
+                let base = format!("{}/{}", endpoint.trim_end_matches('/'), url.bucket);
+                (base.clone(), base)
+            },
+            None => {
+                let base = format!("https://{}.s3.{region}.amazonaws.com", url.bucket);
+                (base.clone(), format!("{base}/"))
+            },
+        };
+        let signed = var("AWS_ACCESS_KEY_ID").is_some() && var("AWS_SECRET_ACCESS_KEY").is_some();
+        let session = signed && var("AWS_SESSION_TOKEN").is_some();
+
+        S3Store {
+            url,
+            base,
+            listing,
+            region,
+            signed,
+            session,
+            auth: None,
+        }
+    }
+
+    /// Apply the given credentials, configured for the [endpoint](Self::endpoint), to every
+    /// request to the store.
+    #[must_use]
+    pub fn auth(mut self, auth: Option<AuthConfig>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// The url of the bucket requests are made to.
+    #[must_use]
+    pub fn endpoint(&self) -> &str {
+        &self.base
+    }
+
+    /// The location of the store.
+    #[must_use]
+    pub fn url(&self) -> &S3Url {
+        &self.url
+    }
+
+    /// The full key in the bucket of the given key of the store.
+    fn key(&self, key: &str) -> String {
+        match self.url.prefix.as_str() {
+            "" => key.to_owned(),
+            prefix => format!("{prefix}/{key}"),
+        }
+    }
+
+    /// Make a request with `curl`, returning the status and body of the response.
+    fn request(
+        &self,
+        method: Method,
+        key: &str,
+        url: &str,
+        body: Option<&[u8]>,
+    ) -> S3Result<(u16, Vec<u8>)> {
+        use std::process::{Command, Stdio};
+
+        let failed = |e: &dyn fmt::Display| Error::Request(key.to_owned(), e.to_string());
+
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--globoff"])
+            .args(["--write-out", "%{stderr}%{http_code}"]);
+        match method {
+            Method::Get => {},
+            Method::Head => {
+                cmd.arg("--head");
+            },
+            Method::Put | Method::Create => {
+                cmd.args(["--request", "PUT", "--data-binary", "@-"])
+                    .args(["--header", "Content-Type: application/octet-stream"]);
+                if method == Method::Create {
+                    cmd.args(["--header", "If-None-Match: *"]);
+                }
+            },
+            Method::Delete => {
+                cmd.args(["--request", "DELETE"]);
+            },
+        }
+        if self.signed {
+            // the credentials are expanded by curl, and never appear on its command line
+            cmd.args(["--variable", "%AWS_ACCESS_KEY_ID"])
+                .args(["--variable", "%AWS_SECRET_ACCESS_KEY"])
+                .args([
+                    "--expand-user",
+                    "{{AWS_ACCESS_KEY_ID}}:{{AWS_SECRET_ACCESS_KEY}}",
+                ])
+                .args(["--aws-sigv4", &format!("aws:amz:{}:s3", self.region)]);
+        }
+        if self.session {
+            cmd.args(["--variable", "%AWS_SESSION_TOKEN"]).args([
+                "--expand-header",
+                "x-amz-security-token: {{AWS_SESSION_TOKEN}}",
+            ]);
+        }
+        if let Some(auth) = &self.auth {
+            // a signed request is already authorized by its signature
+            let authorization = auth.authorization().filter(|_| !self.signed);
+            super::auth::apply(&mut cmd, authorization, auth.identity());
+        }
+        cmd.arg(url)
+            .stdin(if body.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = cmd.spawn().map_err(|e| failed(&e))?;
+        if let (Some(body), Some(mut stdin)) = (body, child.stdin.take()) {
+            stdin.write_all(body).map_err(|e| failed(&e))?;
+        }
+        let output = child.wait_with_output().map_err(|e| failed(&e))?;
+
+        // the status is written last to stderr, after any error of curl's own
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim_end();
+        let (message, status) = stderr.rsplit_once('\n').unwrap_or(("", stderr));
+        match status.trim().parse() {
+            Ok(status) if output.status.success() => Ok((status, output.stdout)),
+            _ => Err(failed(&message.trim())),
+        }
+    }
+
+    /// The error for an unsuccessful response to a request for `key`.
+    fn status(key: &str, status: u16, body: &[u8]) -> Error {
+        let body = String::from_utf8_lossy(body);
+        let message = elements(&body, "Message")
+            .first()
+            .map_or_else(|| body.trim().to_owned(), |m| unescape(m));
+        Error::Status {
+            key: key.to_owned(),
+            status,
+            message,
+        }
+    }
+
+    /// Get the object under the given key of the store, if it exists.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails.
+    pub fn get(&self, key: &str) -> S3Result<Option<Vec<u8>>> {
+        let full = self.key(key);
+        let url = format!("{}/{}", self.base, encode(&full, false));
+        match self.request(Method::Get, &full, &url, None)? {
+            (200..=299, body) => Ok(Some(body)),
+            (404, _) => Ok(None),
+            (status, body) => Err(Self::status(&full, status, &body)),
+        }
+    }
+
+    /// Whether an object exists under the given key of the store.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails.
+    pub fn exists(&self, key: &str) -> S3Result<bool> {
+        let full = self.key(key);
+        let url = format!("{}/{}", self.base, encode(&full, false));
+        match self.request(Method::Head, &full, &url, None)? {
+            (200..=299, _) => Ok(true),
+            (404, _) => Ok(false),
+            (status, body) => Err(Self::status(&full, status, &body)),
+        }
+    }
+
+    /// Put the given data under the given key of the store, replacing any object already there.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails.
+    pub fn put(&self, key: &str, data: &[u8]) -> S3Result<()> {
+        let full = self.key(key);
+        let url = format!("{}/{}", self.base, encode(&full, false));
+        match self.request(Method::Put, &full, &url, Some(data))? {
+            (200..=299, _) => Ok(()),
+            (status, body) => Err(Self::status(&full, status, &body)),
+        }
+    }
+
+    /// Put the given data under the given key of the store, unless an object already exists
+    /// there, returning whether it was written.
+    ///
+    /// The check is made by the store itself, with `If-None-Match: *`, so that of concurrent
+    /// writers of the same key, only one succeeds.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails.
+    pub fn create(&self, key: &str, data: &[u8]) -> S3Result<bool> {
+        let full = self.key(key);
+        let url = format!("{}/{}", self.base, encode(&full, false));
+        match self.request(Method::Create, &full, &url, Some(data))? {
+            (200..=299, _) => Ok(true),
+            (412, _) => Ok(false),
+            (status, body) => Err(Self::status(&full, status, &body)),
+        }
+    }
+
+    /// Delete the object under the given key of the store, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails.
+    pub fn delete(&self, key: &str) -> S3Result<()> {
+        let full = self.key(key);
+        let url = format!("{}/{}", self.base, encode(&full, false));
+        match self.request(Method::Delete, &full, &url, None)? {
+            (200..=299 | 404, _) => Ok(()),
+            (status, body) => Err(Self::status(&full, status, &body)),
+        }
+    }
+
+    /// List the keys of the store starting with the given prefix, relative to the store.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any request fails.
+    pub fn list(&self, prefix: &str) -> S3Result<Vec<String>> {
+        let full = self.key(prefix);
+        let root = self.key("");
+
+        let mut keys = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}?list-type=2&prefix={}",
+                self.listing,
+                encode(&full, true)
+            );
+            if let Some(token) = &token {
+                url.push_str("&continuation-token=");
+                url.push_str(&encode(token, true));
+            }
+            let body = match self.request(Method::Get, &full, &url, None)? {
+                (200..=299, body) => String::from_utf8_lossy(&body).into_owned(),
+                (status, body) => return Err(Self::status(&full, status, &body)),
+            };
+
+            keys.extend(
+                elements(&body, "Key")
+                    .into_iter()
+                    .filter_map(|key| unescape(key).strip_prefix(&root).map(ToOwned::to_owned)),
+            );
+            token = elements(&body, "NextContinuationToken")
+                .first()
+                .map(|t| unescape(t));
+            let truncated = elements(&body, "IsTruncated").first() == Some(&"true");
+            if !truncated || token.is_none() {
+                return Ok(keys);
+            }
+        }
+    }
+
+    /// Upload the given data as an object of the store, unless it already holds it, returning
+    /// the hash it is stored under.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a request fails.
+    pub fn put_object(&self, data: &[u8]) -> S3Result<ObjectSum> {
+        let sum = ObjectSum::Blake3(*blake3::hash(data).as_bytes());
+        let key = object_key(&sum);
+        if !self.exists(&key)? {
+            self.put(&key, data)?;
+        }
+        Ok(sum)
+    }
+
+    /// Download the object stored under the given hash, verifying that it matches.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the object does not exist, the request fails, or
+    /// the object does not match its hash.
+    pub fn get_object(&self, sum: &ObjectSum) -> S3Result<Vec<u8>> {
+        let key = object_key(sum);
+        let data = self
+            .get(&key)?
+            .ok_or_else(|| Error::NotFound(self.key(&key)))?;
+        if ObjectSum::Blake3(*blake3::hash(&data).as_bytes()) != *sum {
+            return Err(Error::Corrupt(self.key(&key)));
+        }
+        Ok(data)
+    }
+
+    /// Read the hash of an object recorded by the given ref.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the ref does not exist, or holds no valid hash.
+    pub fn get_sum(&self, name: &str) -> S3Result<ObjectSum> {
+        let value = self.get_ref(name)?;
+        value
+            .parse()
+            .map_err(|_| Error::InvalidRef(self.key(name), value))
+    }
+
+    /// List the Atoms published to the store, along with each of their published versions.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the refs of the store cannot be listed.
+    pub fn published(&self) -> S3Result<BTreeMap<Id, BTreeSet<Version>>> {
+        use crate::publish::{ATOM, ATOM_REF_TOP_LEVEL};
+
+        let prefix = format!("refs/{ATOM_REF_TOP_LEVEL}/");
+        let mut atoms: BTreeMap<Id, BTreeSet<Version>> = BTreeMap::new();
+        for key in self.list(&prefix)? {
+            let Some(path) = key.strip_prefix(&prefix) else {
+                continue;
+            };
+            let [id, version, ATOM] = path.split('/').collect::<Vec<_>>()[..] else {
+                continue;
+            };
+            if let (Ok(id), Ok(version)) = (Id::from_str(id), git::decode_version(version)) {
+                atoms.entry(id).or_default().insert(version);
+            }
+        }
+
+        Ok(atoms)
+    }
+}
+
+impl QueryAtoms for S3Store {
+    type Error = Error;
+
+    /// List the Atoms from the `atom` refs of the store, as by [`S3Store::published`].
+    fn published_atoms(&self) -> S3Result<BTreeMap<Id, BTreeSet<Version>>> {
+        self.published()
+    }
+}
+
+impl QueryStore<String> for S3Store {
+    type Error = Error;
+
+    fn get_refs<Spec>(
+        &self,
+        targets: impl IntoIterator<Item = Spec>,
+    ) -> S3Result<impl IntoIterator<Item = String>>
+    where
+        Spec: AsRef<BStr>,
+    {
+        targets
+            .into_iter()
+            .map(|target| self.get_ref(target))
+            .collect::<S3Result<Vec<_>>>()
+    }
+
+    /// Read the value of a ref, which is stored as the text of an object.
+    fn get_ref<Spec>(&self, target: Spec) -> S3Result<String>
+    where
+        Spec: AsRef<BStr>,
+    {
+        let name = target.as_ref().to_str_lossy();
+        let data = self
+            .get(&name)?
+            .ok_or_else(|| Error::NotFound(self.key(&name)))?;
+        Ok(String::from_utf8_lossy(&data).trim().to_owned())
+    }
+}
+
+/// The key of the object stored under the given hash.
+fn object_key(sum: &ObjectSum) -> String {
+    format!("{OBJECTS}/{}", sum.to_string().replacen(':', "/", 1))
+}
+
+/// Percent-encode `s` as S3 expects it in a url, leaving slashes in paths as they are.
+fn encode(s: &str, query: bool) -> String {
+    use std::fmt::Write;
+
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(char::from(byte));
+            },
+            b'/' if !query => encoded.push('/'),
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            },
+        }
+    }
+    encoded
+}
+
+/// The text of every element named `tag` in an XML document, in order, which is all that is
+/// needed of the responses of a store.
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+    xml.split(open.as_str())
+        .skip(1)
+        .filter_map(|rest| rest.split_once(close.as_str()).map(|(text, _)| text))
+        .collect()
+}
+
+/// Replace the predefined entities of XML in the text of an element.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// An [`S3Store`] along with the Git repository its Atoms are published from, which holds the
+/// history the root of the store is calculated from.
+pub struct S3Remote<'a> {
+    repo: &'a Repository,
+    store: &'a S3Store,
+}
+
+impl<'a> S3Remote<'a> {
+    /// Constructs a new [`S3Remote`], publishing to `store` from `repo`.
+    #[must_use]
+    pub fn new(repo: &'a Repository, store: &'a S3Store) -> Self {
+        S3Remote { repo, store }
+    }
+
+    /// The root of the history of the given commit.
+    fn root_of(&self, id: ObjectId) -> S3Result<Root> {
+        use crate::CalculateRoot;
+
+        let commit = self
+            .repo
+            .find_commit(id)
+            .map_err(|e| git::Error::from(Box::new(e)))?;
+        Ok(commit.calculate_root()?)
+    }
+
+    /// The root recorded by the store, if it is initialized.
+    fn recorded(&self) -> S3Result<Option<ObjectId>> {
+        let value = match self.store.get_ref(ROOT_REF) {
+            Ok(value) => value,
+            Err(Error::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        ObjectId::from_hex(value.as_bytes())
+            .map(Some)
+            .map_err(|_| Error::InvalidRef(self.store.key(ROOT_REF), value))
+    }
+}
+
+impl Init<Root, ObjectId> for S3Remote<'_> {
+    type Error = Error;
+
+    /// Returns the root recorded by the store, which must be a commit of the local history.
+    fn ekala_root(&self) -> S3Result<Root> {
+        let id = self
+            .recorded()?
+            .ok_or_else(|| Error::NotInitialized(self.store.url.to_string()))?;
+        self.root_of(id)
+    }
+
+    /// An object store has no history of its own, so this is the local `HEAD`, whose root
+    /// initializing the store records.
+    fn sync(&self) -> S3Result<ObjectId> {
+        let head = self
+            .repo
+            .head_id()
+            .map_err(|e| git::Error::from(Box::new(e)))?;
+        Ok(head.detach())
+    }
+
+    /// Initialize the store by recording the root of the local history.
+    ///
+    /// Initializing a store already initialized with the same root is a no-op.
+    fn ekala_init(&self) -> S3Result<()> {
+        let url = self.store.url.to_string();
+        let root = self.root_of(self.sync()?)?;
+
+        match self.recorded()? {
+            Some(id) if id == *root => {
+                tracing::info!(store = %url, message = "Already initialized");
+                return Ok(());
+            },
+            Some(id) => return Err(Error::AlreadyInitialized(id.to_string())),
+            None => {},
+        }
+
+        self.store.put(ROOT_REF, root.to_string().as_bytes())?;
+        tracing::info!(store = %url, message = "Successfully initialized");
+        Ok(())
+    }
+
+    fn ekala_status(&self) -> S3Result<InitStatus<Root>> {
+        let remote = self.recorded()?.map(|id| self.root_of(id)).transpose()?;
+        let local = self.root_of(self.sync()?)?;
+
+        Ok(InitStatus {
+            remote,
+            head: local,
+            local,
+        })
+    }
+}
+
+/// An [`S3Store`] as an [`EkalaStore`], along with the Git repository its Atoms are published
+/// from, if there is one. Without one, the store may only be queried.
+pub struct S3Backend<'a> {
+    repo: Option<Repository>,
+    store: &'a S3Store,
+    name: String,
+}
+
+impl<'a> S3Backend<'a> {
+    /// Constructs a new [`S3Backend`] for `store`, publishing to it from `repo`, if given.
+    #[must_use]
+    pub fn new(repo: Option<Repository>, store: &'a S3Store) -> Self {
+        let name = store.url.to_string();
+        S3Backend { repo, store, name }
+    }
+
+    /// The store and the repository its Atoms are published from, as an [`S3Remote`].
+    fn remote(&self) -> S3Result<S3Remote<'_>> {
+        let repo = self
+            .repo
+            .as_ref()
+            .ok_or_else(|| Error::NoRepository(self.name.clone()))?;
+        Ok(S3Remote::new(repo, self.store))
+    }
+}
+
+impl EkalaStore for S3Backend<'_> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn root_ref(&self) -> &str {
+        ROOT_REF
+    }
+
+    fn init(&self) -> Result<(), BoxError> {
+        Ok(self.remote()?.ekala_init()?)
+    }
+
+    fn root(&self) -> Result<String, BoxError> {
+        Ok(self.remote()?.ekala_root()?.to_string())
+    }
+
+    fn status(&self) -> Result<InitStatus<String>, BoxError> {
+        let status = self.remote()?.ekala_status()?;
+        Ok(status.map(|root| root.to_string()))
+    }
+
+    fn published(&self) -> Result<BTreeMap<Id, BTreeSet<Version>>, BoxError> {
+        Ok(self.store.published_atoms()?)
+    }
+
+    /// An object store declares no policy of its own.
+    fn policy(&self) -> Result<Option<Policy>, BoxError> {
+        Ok(None)
+    }
+
+    fn get_ref(&self, name: &str) -> Result<String, BoxError> {
+        Ok(self.store.get_ref(name)?)
+    }
+
+    fn publish<'b>(
+        &'b self,
+        spec: &'b str,
+        paths: Vec<PathBuf>,
+    ) -> BoxFuture<'b, Result<Vec<Result<Published, BoxError>>, BoxError>> {
+        use crate::publish::s3::S3Publisher;
+        use crate::publish::{Builder, Publish};
+
+        Box::pin(async move {
+            let remote = self.remote()?;
+            let (_, publisher) = S3Publisher::new(remote.repo, self.store, spec)?.build()?;
+            let outcomes: Vec<Result<Published, BoxError>> = publisher
+                .publish(paths)
+                .into_iter()
+                .map(|outcome| match outcome {
+                    Ok(Ok(record)) => Ok(Ok(record.id().id().clone())),
+                    Ok(Err(skipped)) => Ok(Err(skipped)),
+                    Err(e) => Err(e.into()),
+                })
+                .collect();
+            Ok::<_, BoxError>(outcomes)
+        })
+    }
+}
@@ -0,0 +1,51 @@
+use super::*;
+
+#[test]
+fn s3_urls() -> Result<(), anyhow::Error> {
+    let url: S3Url = "s3://bucket/some/prefix/".parse()?;
+    assert_eq!(url.bucket(), "bucket");
+    assert_eq!(url.prefix(), "some/prefix");
+    assert_eq!(url.to_string(), "s3://bucket/some/prefix");
+
+    let bare: S3Url = "s3://bucket".parse()?;
+    assert_eq!(bare.prefix(), "");
+    assert_eq!(bare.to_string(), "s3://bucket");
+
+    for invalid in ["s3://", "s3:///prefix", "https://bucket/prefix"] {
+        assert!(matches!(
+            invalid.parse::<S3Url>(),
+            Err(Error::InvalidUrl(_))
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn keys_and_listings() -> Result<(), anyhow::Error> {
+    assert_eq!(
+        encode("refs/atoms/ä b/1.0.0", false),
+        "refs/atoms/%C3%A4%20b/1.0.0"
+    );
+    assert_eq!(encode("refs/atoms/", true), "refs%2Fatoms%2F");
+
+    let sum: ObjectSum = format!("blake3:{}", "ab".repeat(32)).parse()?;
+    assert_eq!(
+        object_key(&sum),
+        format!("objects/blake3/{}", "ab".repeat(32))
+    );
+
+    let listing = concat!(
+        "<ListBucketResult><IsTruncated>true</IsTruncated>",
+        "<Contents><Key>p/refs/atoms/a&amp;b/1.0.0/atom</Key></Contents>",
+        "<Contents><Key>p/refs/atoms/c/2.0.0/atom</Key></Contents>",
+        "<NextContinuationToken>t</NextContinuationToken></ListBucketResult>",
+    );
+    let keys: Vec<_> = elements(listing, "Key").into_iter().map(unescape).collect();
+    assert_eq!(
+        keys,
+        ["p/refs/atoms/a&b/1.0.0/atom", "p/refs/atoms/c/2.0.0/atom"]
+    );
+    assert_eq!(elements(listing, "IsTruncated"), ["true"]);
+    assert_eq!(elements(listing, "Missing"), Vec::<&str>::new());
+    Ok(())
+}
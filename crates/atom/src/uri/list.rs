@@ -0,0 +1,162 @@
+//! # Atom URI Lists
+//!
+//! Fleets of Atoms may be managed declaratively, outside of any manifest, by listing their URIs
+//! in a file for batch operations to consume. Plain text lists hold one URI per line, each
+//! optionally followed by overrides of the form `key=value`, with `#` starting a comment:
+//!
+//! ```text
+//! # the atoms mirrored nightly
+//! gh:owner/repo::foo@^1
+//! gh:owner/repo::bar version=^2.1   # pinned until bar 3 is vetted
+//! ::baz url=work:tools
+//! ```
+//!
+//! Lists ending in `.toml` hold the same entries as tables:
+//!
+//! ```toml
+//! [[atom]]
+//! uri = "gh:owner/repo::bar"
+//! version = "^2.1"
+//! ```
+//!
+//! The `version` override replaces the version requested by the URI, and the `url` override,
+//! which may use an alias, the url of its store.
+use std::collections::HashMap;
+use std::path::Path;
+
+use config::Alias;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::{Aliases, Ref, Uri, UriError};
+
+/// The extension of lists written in TOML, rather than plain text.
+const TOML_EXT: &str = "toml";
+
+/// Errors which occur when reading an Atom URI list.
+#[derive(Error, Debug)]
+pub enum ListError {
+    /// A transparent wrapper for a [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A transparent wrapper for a [`toml_edit::de::Error`]
+    #[error(transparent)]
+    Toml(#[from] toml_edit::de::Error),
+    /// An entry of the list is not a valid Atom URI.
+    #[error("Invalid Atom URI on line {line}: {source}")]
+    Uri {
+        /// The line of the entry, or its position in a TOML list, counting from one.
+        line: usize,
+        /// The reason the URI is invalid.
+        source: UriError,
+    },
+    /// An entry of the list overrides an unknown key.
+    #[error("Unknown override `{key}` on line {line}, expected `version` or `url`")]
+    Override {
+        /// The line of the entry, or its position in a TOML list, counting from one.
+        line: usize,
+        /// The overridden key.
+        key: String,
+    },
+}
+
+/// A single entry of a list, before it is resolved.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    uri: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TomlList {
+    #[serde(default)]
+    atom: Vec<Entry>,
+}
+
+/// Read the Atom URIs listed in the file at `path`, in the order they are listed, expanding
+/// aliases from the given map.
+///
+/// # Errors
+///
+/// This function will return an error if the file cannot be read, or any of its entries is
+/// invalid.
+pub fn read(path: &Path, aliases: &HashMap<String, Alias>) -> Result<Vec<Uri>, ListError> {
+    let content = std::fs::read_to_string(path)?;
+    if path.extension().is_some_and(|ext| ext == TOML_EXT) {
+        let list: TomlList = toml_edit::de::from_str(&content)?;
+        resolve(list.atom.into_iter().enumerate(), aliases)
+    } else {
+        parse(&content, aliases)
+    }
+}
+
+/// Parse the Atom URIs listed in plain text, expanding aliases from the given map.
+///
+/// # Errors
+///
+/// This function will return an error if any of the entries is invalid.
+pub fn parse(content: &str, aliases: &HashMap<String, Alias>) -> Result<Vec<Uri>, ListError> {
+    let mut entries = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.split_once('#').map_or(line, |(line, _)| line);
+        let mut fields = line.split_whitespace();
+        let Some(uri) = fields.next() else {
+            continue;
+        };
+        let mut entry = Entry {
+            uri: uri.to_owned(),
+            ..Entry::default()
+        };
+        for field in fields {
+            let (key, value) = field.split_once('=').unwrap_or((field, ""));
+            let slot = match key {
+                "version" => &mut entry.version,
+                "url" => &mut entry.url,
+                _ => {
+                    return Err(ListError::Override {
+                        line: i + 1,
+                        key: key.to_owned(),
+                    });
+                },
+            };
+            *slot = Some(value.to_owned());
+        }
+        entries.push((i, entry));
+    }
+    resolve(entries.into_iter(), aliases)
+}
+
+/// Resolve each entry into a URI, applying its overrides.
+fn resolve(
+    entries: impl Iterator<Item = (usize, Entry)>,
+    aliases: &HashMap<String, Alias>,
+) -> Result<Vec<Uri>, ListError> {
+    let aliases = Aliases(aliases);
+    entries
+        .map(|(i, entry)| {
+            entry.resolve(&aliases).map_err(|source| ListError::Uri {
+                line: i + 1,
+                source,
+            })
+        })
+        .collect()
+}
+
+impl Entry {
+    fn resolve(&self, aliases: &Aliases) -> Result<Uri, UriError> {
+        let mut uri = Uri::render(Ref::from(self.uri.as_str()), aliases)?;
+        if let Some(version) = &self.version {
+            uri.version = Some(version.parse()?);
+        }
+        if let Some(url) = &self.url {
+            // parsed as the url of a URI, so that it may use an alias
+            let store = format!("{url}::{}", uri.id);
+            uri.url = Uri::render(Ref::from(store.as_str()), aliases)?.url;
+        }
+        Ok(uri)
+    }
+}
@@ -7,12 +7,18 @@
 //!
 //! An `alias` is a user configurable URL shortener that must at least contain an FQDN or host,
 //! and as much of the url path as desirable. Aliases can be specified in the eka configuration
-//! file for the CLI program. See the Atom configuration crate for further detail.
+//! file for the CLI program, either as the fragment they expand to, or as a table describing
+//! their host, e.g. with the user to connect to it as over ssh. See the Atom configuration crate
+//! for further detail.
 //!
 //! ## Examples
 //! * `gh:owner/repo::my-atom` where `hub` is `github.com`
 //! * `work:repo::my-atom` where `work` is `github.com/my-work-org`
 //! * `repo::my-atom@^1` where `repo` is `example.com/some/repo`
+//!
+//! A [`Uri`] may also be built from its typed components with a [`UriBuilder`], and always
+//! renders to a string which parses back to an equal [`Uri`], with its aliases expanded.
+pub mod list;
 #[cfg(test)]
 mod tests;
 
@@ -20,7 +26,8 @@ use std::collections::HashMap;
 use std::ops::Deref;
 use std::str::FromStr;
 
-use gix_url::Url;
+use config::{Alias, HostAlias};
+use gix_url::{Scheme, Url};
 use semver::VersionReq;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -29,12 +36,13 @@ use super::id::Id;
 use crate::id::Error;
 
 #[derive(Debug)]
-struct Aliases(&'static HashMap<&'static str, &'static str>);
+struct Aliases<'a>(&'a HashMap<String, Alias>);
 
 /// Represents the parsed components of an Atom URI.
 ///
-/// It is typically created through the `FromStr` implementation, not constructed directly.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// It is typically created through [`Uri::parse_with`], or the `FromStr` implementation, which
+/// only expands the builtin aliases, or a [`UriBuilder`], not constructed directly.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Uri {
     /// The URL to the repository containing the Atom.
     url: Option<Url>,
@@ -227,6 +235,14 @@ impl<'a> From<&'a str> for Ref<'a> {
     }
 }
 
+/// Split an Atom URI into its components, without validating them or expanding aliases, to
+/// exercise the parser alone, e.g. from a fuzzer.
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub fn parse_ref(input: &str) -> impl std::fmt::Debug + '_ {
+    Ref::from(input)
+}
+
 /// A error encountered when constructing the concrete types from an Atom URI
 #[derive(Error, Debug)]
 pub enum UriError {
@@ -251,30 +267,36 @@ pub enum UriError {
 }
 
 use std::borrow::Cow;
-impl Aliases {
-    fn get_alias(&self, s: &str) -> Result<&str, UriError> {
-        self.get(s)
-            .map_or_else(|| Err(UriError::NoAlias(s.into())), |s| Ok(*s))
+impl<'a> Aliases<'a> {
+    fn get_alias(&self, s: &str) -> Result<&'a Alias, UriError> {
+        self.0.get(s).ok_or_else(|| UriError::NoAlias(s.into()))
+    }
+
+    fn resolve_alias(&self, s: &str) -> Result<Cow<'a, str>, UriError> {
+        self.resolve_host(s).map(|(res, _)| res)
     }
 
-    fn resolve_alias(&'static self, s: &str) -> Result<Cow<'static, str>, UriError> {
-        let res = self.get_alias(s)?;
+    /// Resolve the alias `s` as [`Self::resolve_alias`] does, along with the host it names, if
+    /// it is described as one, or the alias it refers to is.
+    fn resolve_host(&self, s: &str) -> Result<(Cow<'a, str>, Option<&'a HostAlias>), UriError> {
+        let alias = self.get_alias(s)?;
 
         // allow one level of indirection in alises, e.g. `org = gh:my-org`
-        let res = match res.split_once(':') {
+        let res = match alias.target().split_once(':') {
             Some((s, rest)) => {
-                let res = self.get_alias(s)?;
-                Cow::Owned(format!("{res}/{rest}"))
+                let alias = self.get_alias(s)?;
+                let res = alias.target();
+                (Cow::Owned(format!("{res}/{rest}")), alias.host())
             },
-            None => Cow::Borrowed(res),
+            None => (Cow::Borrowed(alias.target()), alias.host()),
         };
 
         Ok(res)
     }
 }
 
-impl Deref for Aliases {
-    type Target = HashMap<&'static str, &'static str>;
+impl Deref for Aliases<'_> {
+    type Target = HashMap<String, Alias>;
 
     fn deref(&self) -> &Self::Target {
         self.0
@@ -309,20 +331,32 @@ impl<'a> From<&'a str> for AtomRef<'a> {
     }
 }
 
+/// The url fragment an alias expands to, along with the host it names, if it is described as
+/// one.
+type Resolved<'a> = Option<(Cow<'a, str>, Option<&'a HostAlias>)>;
+
 use std::sync::LazyLock;
-static ALIASES: LazyLock<Aliases> = LazyLock::new(|| Aliases(config::CONFIG.aliases()));
+/// The builtin aliases, used when none are given explicitly, so that parsing never depends on
+/// configuration loaded behind the caller's back.
+static ALIASES: LazyLock<config::Aliases> =
+    LazyLock::new(|| config::Config::default().aliases().clone());
 
 impl<'a> UrlRef<'a> {
-    fn render_alias(&self) -> Option<(&str, Option<Cow<'static, str>>)> {
+    fn render_alias<'b>(&self, aliases: &Aliases<'b>) -> Option<(&str, Resolved<'b>)> {
         let (frag, alias) = parse_alias(self.frag?);
 
-        alias.and_then(|a| ALIASES.resolve_alias(a).ok().map(|a| (frag, Some(a))))
+        alias.and_then(|a| aliases.resolve_host(a).ok().map(|a| (frag, Some(a))))
     }
 
-    fn to_url(&self) -> Option<Url> {
-        use gix_url::Scheme;
-
-        let (frag, resolved) = self.render_alias().unwrap_or((self.frag?, None));
+    fn to_url(&self, aliases: &Aliases) -> Option<Url> {
+        let (frag, resolved) = self.render_alias(aliases).unwrap_or((self.frag?, None));
+        let (resolved, named) = resolved.unzip();
+        let named = named.flatten();
+        // the fragment following an alias extends the path the alias expands to
+        let resolved = resolved.map(|r| match frag {
+            "" => r,
+            _ => Cow::Owned(format!("{}/{frag}", r.trim_end_matches('/'))),
+        });
 
         #[allow(clippy::unnecessary_unwrap)]
         let (rest, (maybe_host, delim)) = if resolved.is_some() {
@@ -350,6 +384,12 @@ impl<'a> UrlRef<'a> {
             }
         });
 
+        // credentials are meaningless without a host, and a url with a user but no host cannot
+        // even be rendered
+        if host.is_none() && (self.user.is_some() || self.pass.is_some()) {
+            return None;
+        }
+
         let scheme: Scheme = self
             .scheme
             .unwrap_or_else(|| {
@@ -363,9 +403,6 @@ impl<'a> UrlRef<'a> {
             })
             .into();
 
-        // special case for empty fragments, e.g. foo::my-atom
-        let rest = if rest.is_empty() { frag } else { rest };
-
         let path = if host.is_none() {
             format!("{maybe_host}{delim}{rest}")
         } else if !rest.starts_with('/') {
@@ -386,6 +423,13 @@ impl<'a> UrlRef<'a> {
             ?resolved
         );
 
+        // the user the host an alias names is reached as over ssh, unless the URI gives one
+        let user = self.user.or_else(|| {
+            (host.is_some() && scheme == Scheme::Ssh)
+                .then(|| named.and_then(|named| named.ssh_user.as_deref()))
+                .flatten()
+        });
+
         let alternate_form = scheme == Scheme::File || scheme == Scheme::Ssh;
         let port = if scheme == Scheme::Ssh {
             tracing::warn!(
@@ -399,7 +443,7 @@ impl<'a> UrlRef<'a> {
 
         Url::from_parts(
             scheme,
-            self.user.map(Into::into),
+            user.map(Into::into),
             self.pass.map(Into::into),
             host.map(Into::into),
             port,
@@ -430,15 +474,7 @@ impl<'a> TryFrom<Ref<'a>> for Uri {
     type Error = UriError;
 
     fn try_from(refs: Ref<'a>) -> Result<Self, Self::Error> {
-        let Ref { url, atom } = refs;
-
-        let url = url.to_url();
-
-        let (id, version) = atom.render()?;
-
-        tracing::trace!(?url, %id, ?version);
-
-        Ok(Uri { url, id, version })
+        Uri::render(refs, &Aliases(&ALIASES))
     }
 }
 
@@ -446,7 +482,7 @@ impl<'a> TryFrom<UrlRef<'a>> for Url {
     type Error = UriError;
 
     fn try_from(refs: UrlRef<'a>) -> Result<Self, Self::Error> {
-        match refs.to_url() {
+        match refs.to_url(&Aliases(&ALIASES)) {
             Some(url) => Ok(url),
             None => Err(UriError::NoUrl),
         }
@@ -470,12 +506,14 @@ impl<'a> TryFrom<&'a str> for Uri {
     }
 }
 
+/// Renders the URI with its aliases expanded, and any password of its url included, so that
+/// parsing the result yields an equal [`Uri`].
 impl std::fmt::Display for Uri {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let url = self
             .url
             .as_ref()
-            .map(ToString::to_string)
+            .map(|url| url.to_bstring().to_string())
             .unwrap_or_default();
         let version = self
             .version
@@ -487,6 +525,34 @@ impl std::fmt::Display for Uri {
 }
 
 impl Uri {
+    fn render(refs: Ref, aliases: &Aliases) -> Result<Self, UriError> {
+        let Ref { url, atom } = refs;
+
+        let url = url.to_url(aliases);
+
+        let (id, version) = atom.render()?;
+
+        tracing::trace!(?url, %id, ?version);
+
+        Ok(Uri { url, id, version })
+    }
+
+    /// Parse an Atom URI, expanding aliases from the given map, e.g. those of a loaded
+    /// configuration, instead of the builtin ones.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error under the same conditions as [`Uri::from_str`].
+    pub fn parse_with(s: &str, aliases: &HashMap<String, Alias>) -> Result<Self, UriError> {
+        Uri::render(Ref::from(s), &Aliases(aliases))
+    }
+
+    /// Start building an Atom URI from its typed components.
+    #[must_use]
+    pub fn builder() -> UriBuilder {
+        UriBuilder::default()
+    }
+
     #[must_use]
     /// Returns a reference to the Url parsed out of the Atom URI.
     pub fn url(&self) -> Option<&Url> {
@@ -505,3 +571,107 @@ impl Uri {
         self.version.as_ref()
     }
 }
+
+/// A builder for an [`Uri`], from its typed components rather than a string, e.g. in tooling
+/// generating URIs from configuration.
+///
+/// The url of the store is either given as is, or as an alias and the fragment following it,
+/// expanded exactly as the parser would expand `alias:fragment::id`. Whichever of the two is
+/// set last is used.
+#[derive(Debug, Clone, Default)]
+pub struct UriBuilder {
+    scheme: Option<Scheme>,
+    alias: Option<(String, String)>,
+    url: Option<Url>,
+    id: Option<Id>,
+    version: Option<VersionReq>,
+}
+
+impl UriBuilder {
+    /// The scheme of the url an alias expands to, inferred as the parser would otherwise.
+    #[must_use]
+    pub fn scheme(mut self, scheme: Scheme) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    /// The alias the url of the store expands from, and the fragment extending it, e.g. `gh`
+    /// and `owner/repo`.
+    #[must_use]
+    pub fn alias(mut self, alias: impl Into<String>, fragment: impl Into<String>) -> Self {
+        self.alias = Some((alias.into(), fragment.into()));
+        self.url = None;
+        self
+    }
+
+    /// The url of the store the Atom is published to.
+    #[must_use]
+    pub fn url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self.alias = None;
+        self
+    }
+
+    /// The id of the Atom, which is required.
+    #[must_use]
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// The versions of the Atom requested.
+    #[must_use]
+    pub fn version(mut self, version: VersionReq) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Build the [`Uri`], expanding its alias, if any, from the builtin aliases.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error under the same conditions as
+    /// [`UriBuilder::build_with`].
+    pub fn build(self) -> Result<Uri, UriError> {
+        self.build_from(&Aliases(&ALIASES))
+    }
+
+    /// Build the [`Uri`], expanding its alias, if any, from the given map instead of the
+    /// builtin aliases.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no id was given, the alias is not in the map, or
+    /// the url it expands to is invalid.
+    pub fn build_with(self, aliases: &HashMap<String, Alias>) -> Result<Uri, UriError> {
+        self.build_from(&Aliases(aliases))
+    }
+
+    fn build_from(self, aliases: &Aliases) -> Result<Uri, UriError> {
+        let id = self.id.ok_or(UriError::NoAtom)?;
+        let url = match (self.url, self.alias) {
+            (Some(url), _) => Some(url),
+            (None, Some((alias, fragment))) => {
+                aliases.resolve_alias(&alias)?;
+                let frag = if fragment.is_empty() {
+                    alias
+                } else {
+                    format!("{alias}:{fragment}")
+                };
+                let url = UrlRef {
+                    scheme: self.scheme.as_ref().map(Scheme::as_str),
+                    frag: Some(&frag),
+                    ..Default::default()
+                };
+                Some(url.to_url(aliases).ok_or(UriError::NoUrl)?)
+            },
+            (None, None) => None,
+        };
+
+        Ok(Uri {
+            url,
+            id,
+            version: self.version,
+        })
+    }
+}
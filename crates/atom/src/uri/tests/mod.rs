@@ -1,3 +1,5 @@
+use anyhow::Context;
+
 use super::*;
 
 const ALIASES: &[&str] = &[
@@ -38,3 +40,168 @@ fn uri_snapshot() -> Result<(), UriError> {
     insta::assert_debug_snapshot!(results?);
     Ok(())
 }
+
+#[test]
+fn credentials_without_host() -> Result<(), UriError> {
+    use std::collections::HashMap;
+
+    // a user whose host is not a valid name yields no url, rather than one no one can render
+    let uri = Uri::parse_with("user@bad_host!/repo::foo", &HashMap::new())?;
+    assert!(uri.url().is_none());
+    assert_eq!(uri.id().as_str(), "foo");
+    Ok(())
+}
+
+#[test]
+fn builtin_aliases() -> Result<(), anyhow::Error> {
+    use std::collections::HashMap;
+
+    // parsing expands the builtin aliases alone, whatever the configuration on the machine
+    let builtin: Uri = "sh:repo::foo".parse()?;
+    let defaults = config::Config::default();
+    assert_eq!(
+        builtin,
+        Uri::parse_with("sh:repo::foo", defaults.aliases())?
+    );
+    assert_eq!(builtin.to_string(), "https://sr.ht/repo::foo");
+
+    let aliases = HashMap::from([("sh".to_owned(), "example.com/org".into())]);
+    assert_eq!(
+        Uri::parse_with("sh:repo::foo", &aliases)?.to_string(),
+        "https://example.com/org/repo::foo"
+    );
+    Ok(())
+}
+
+#[test]
+fn uri_lists() -> Result<(), anyhow::Error> {
+    use std::collections::HashMap;
+
+    use super::list::{self, ListError};
+
+    let aliases = HashMap::from([("work".to_owned(), "example.com/org".into())]);
+    let content = "\
+# the atoms mirrored nightly
+work:repo::foo@^1
+
+work:repo::bar@^1 version=^2.1   # pinned until bar 3 is vetted
+::baz url=work:tools
+";
+    let uris = list::parse(content, &aliases)?;
+    let listed: Vec<_> = uris.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        listed,
+        [
+            "https://example.com/org/repo::foo@^1",
+            "https://example.com/org/repo::bar@^2.1",
+            "https://example.com/org/tools::baz",
+        ]
+    );
+
+    assert!(matches!(
+        list::parse("::foo\n::bar rev=abc", &aliases),
+        Err(ListError::Override { line: 2, .. })
+    ));
+    assert!(matches!(
+        list::parse("\n::foo@not-a-version", &aliases),
+        Err(ListError::Uri { line: 2, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn display_round_trip() -> Result<(), UriError> {
+    for uri in ALIASES {
+        let parsed: Uri = uri.parse()?;
+        let rendered = parsed.to_string();
+        assert_eq!(
+            rendered.parse::<Uri>()?,
+            parsed,
+            "`{uri}` rendered as `{rendered}`"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn uri_builder() -> Result<(), anyhow::Error> {
+    use std::collections::HashMap;
+
+    let aliases = HashMap::from([("work".to_owned(), "example.com/org".into())]);
+    let foo = Id::try_from("foo")?;
+
+    let built = Uri::builder()
+        .alias("work", "repo")
+        .id(foo.clone())
+        .version(VersionReq::parse("^1")?)
+        .build_with(&aliases)?;
+    assert_eq!(built, Uri::parse_with("work:repo::foo@^1", &aliases)?);
+    assert_eq!(built.to_string(), "https://example.com/org/repo::foo@^1");
+
+    let ssh = Uri::builder()
+        .scheme(Scheme::Ssh)
+        .alias("work", "repo")
+        .id(foo.clone())
+        .build_with(&aliases)?;
+    assert_eq!(ssh, Uri::parse_with("ssh://work:repo::foo", &aliases)?);
+    assert_eq!(ssh.url().map(|url| url.scheme.clone()), Some(Scheme::Ssh));
+
+    let url = Uri::parse_with("https://example.com/repo::bar", &aliases)?
+        .url()
+        .cloned()
+        .context("no url")?;
+    let direct = Uri::builder()
+        .alias("work", "repo")
+        .url(url.clone())
+        .id(foo.clone())
+        .build_with(&aliases)?;
+    assert_eq!(direct.url(), Some(&url));
+    assert_eq!(direct.to_string().parse::<Uri>()?, direct);
+    assert_eq!(Uri::builder().id(foo.clone()).build()?.url(), None);
+
+    assert!(matches!(
+        Uri::builder().build_with(&aliases),
+        Err(UriError::NoAtom)
+    ));
+    assert!(matches!(
+        Uri::builder()
+            .alias("nope", "repo")
+            .id(foo)
+            .build_with(&aliases),
+        Err(UriError::NoAlias(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn host_alias_hints() -> Result<(), anyhow::Error> {
+    use std::collections::HashMap;
+
+    use config::{Alias, HostAlias};
+
+    let host = Alias::Host(HostAlias {
+        host: "example.com".into(),
+        api: None,
+        ssh_user: Some("git".into()),
+    });
+    let aliases = HashMap::from([
+        ("ex".to_owned(), host),
+        ("org".to_owned(), "ex:my-org".into()),
+    ]);
+
+    // the user an alias hints at is only used over ssh, unless the URI gives one
+    let user = |uri: &str| -> Result<_, anyhow::Error> {
+        let uri = Uri::parse_with(uri, &aliases)?;
+        let url = uri.url().context("no url")?;
+        Ok(url.user().map(ToOwned::to_owned))
+    };
+    assert_eq!(user("ssh://ex:repo::foo")?.as_deref(), Some("git"));
+    assert_eq!(user("ssh://org:repo::foo")?.as_deref(), Some("git"));
+    assert_eq!(user("ssh://me@ex:repo::foo")?.as_deref(), Some("me"));
+    assert_eq!(user("ex:repo::foo")?, None);
+    assert_eq!(
+        Uri::parse_with("org:repo::foo", &aliases)?.to_string(),
+        "https://example.com/my-org/repo::foo"
+    );
+    Ok(())
+}
@@ -0,0 +1,337 @@
+//! # Atom Verification
+//!
+//! An Atom is trivially verifiable from source: its commit records the commit it was published
+//! from, and the path of its content in it, as headers, and is otherwise fully reproducible.
+//! Re-creating the Atom from that source, exactly as the publisher does, must yield the very
+//! same commit, or the Atom does not hold what it claims to.
+//!
+//! [`verify`] checks an Atom commit already in the repository, while [`verify_published`]
+//! first fetches a published version of an Atom, along with the commit it claims to originate
+//! from, from a remote store. Neither trusts anything but the Atom commit itself, and the
+//! source it names.
+//!
+//! A [signed](crate::store::git::sign) Atom commit is reproducible but for its signature, and
+//! is compared with the commit re-created from its source once its signature is removed.
+//! Given an allowlist of key fingerprints, the signature must then have been made by one of
+//! them, and unsigned Atoms are refused.
+#[cfg(test)]
+mod test;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use bstr::{BString, ByteSlice};
+use gix::objs::Tree;
+use gix::{ObjectId, Repository};
+use semver::Version;
+use thiserror::Error as ThisError;
+
+use crate::id::Id;
+use crate::publish::error::git::Error as PublishError;
+use crate::publish::{ATOM, ATOM_ORIGIN, ATOM_REF_TOP_LEVEL};
+use crate::store::git;
+use crate::store::git::sign::Signed;
+
+/// An error encountered while verifying an Atom, which prevented reaching a verdict.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// The commit lacks the message or headers every Atom commit has.
+    #[error("`{0}` is not an Atom commit")]
+    NotAnAtom(ObjectId),
+    /// The commit the Atom claims to originate from is unavailable, e.g. as it was pruned.
+    #[error("The source `{0}` of the Atom is not available")]
+    NoSource(ObjectId),
+    /// A transparent wrapper for a [`git::Error`]
+    #[error(transparent)]
+    Store(#[from] git::Error),
+    /// A transparent wrapper for a [`PublishError`]
+    #[error(transparent)]
+    Recreate(#[from] PublishError),
+    /// A transparent wrapper for a [`Box<gix::object::find::existing::with_conversion::Error>`]
+    #[error(transparent)]
+    NoCommit(#[from] Box<gix::object::find::existing::with_conversion::Error>),
+    /// A transparent wrapper for a [`gix::object::commit::Error`]
+    #[error(transparent)]
+    NoTree(#[from] gix::object::commit::Error),
+    /// A transparent wrapper for a [`gix::objs::decode::Error`]
+    #[error(transparent)]
+    Decode(#[from] gix::objs::decode::Error),
+}
+
+/// A way in which an Atom differs from the one re-created from its claimed source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tampered {
+    /// The entry of the Atom's tree with the given name is missing from, added to, or differs
+    /// from the one re-created.
+    Entry(BString),
+    /// The Atom's tree is the one re-created, but its commit is not, e.g. as its metadata was
+    /// altered.
+    Commit,
+    /// There is no Atom at the claimed path in the source.
+    Source,
+    /// The Atom commit is not signed, but signatures are required.
+    Unsigned,
+    /// The signature of the Atom commit is invalid, or was made by a key not allowed.
+    Signature(String),
+}
+
+/// The verdict on an Atom, verified against the source it claims to originate from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    id: String,
+    version: String,
+    commit: ObjectId,
+    origin: ObjectId,
+    path: PathBuf,
+    recreated: Option<ObjectId>,
+    signer: Option<String>,
+    tampered: Vec<Tampered>,
+}
+
+/// What an Atom commit claims about itself.
+struct Claim {
+    id: String,
+    version: String,
+    origin: ObjectId,
+    dir: String,
+}
+
+impl Verification {
+    /// Return the id of the Atom, as recorded in its commit.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Return the version of the Atom, as recorded in its commit.
+    #[must_use]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Return the id of the Atom commit verified.
+    #[must_use]
+    pub fn commit(&self) -> ObjectId {
+        self.commit
+    }
+
+    /// Return the id of the commit the Atom claims to originate from.
+    #[must_use]
+    pub fn origin(&self) -> ObjectId {
+        self.origin
+    }
+
+    /// Return the path of the Atom's manifest in its source, relative to the repository root.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the id of the Atom commit re-created from the source, if there is an Atom at the
+    /// claimed path in it.
+    #[must_use]
+    pub fn recreated(&self) -> Option<ObjectId> {
+        self.recreated
+    }
+
+    /// Return the fingerprint of the allowed key the Atom commit was signed with, if its
+    /// signature was checked.
+    #[must_use]
+    pub fn signer(&self) -> Option<&str> {
+        self.signer.as_deref()
+    }
+
+    /// Return the ways the Atom differs from the one re-created from its source, if any.
+    #[must_use]
+    pub fn tampered(&self) -> &[Tampered] {
+        &self.tampered
+    }
+
+    /// Whether the Atom is exactly the one published from its claimed source.
+    #[must_use]
+    pub fn is_authentic(&self) -> bool {
+        self.tampered.is_empty()
+    }
+}
+
+impl fmt::Display for Tampered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tampered::Entry(name) => write!(f, "`{name}` differs from its source"),
+            Tampered::Commit => write!(f, "the commit differs from the one its source yields"),
+            Tampered::Source => write!(f, "the source holds no atom at the claimed path"),
+            Tampered::Unsigned => write!(f, "the commit is not signed"),
+            Tampered::Signature(reason) => write!(f, "the commit's signature is refused: {reason}"),
+        }
+    }
+}
+
+/// Parse the id and version of an Atom from the name of any of the refs it is published
+/// under, e.g. `refs/atoms/foo/1.0.0/atom`.
+#[must_use]
+pub fn parse_ref(name: &str) -> Option<(Id, Version)> {
+    let path = name
+        .strip_prefix("refs/")?
+        .strip_prefix(ATOM_REF_TOP_LEVEL)?
+        .strip_prefix('/')?;
+    let [id, version, _] = path.split('/').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    Some((Id::from_str(id).ok()?, git::decode_version(version).ok()?))
+}
+
+/// Verify the Atom commit `commit` against the source it claims to originate from, which
+/// must already be in `repo`, by re-creating the Atom from it and comparing the two.
+///
+/// Unless `keys` is empty, the commit must also be signed by the key with one of the given
+/// fingerprints.
+///
+/// # Errors
+///
+/// This function will return an error if `commit` is not an Atom commit, its source is not in
+/// the repository, or either cannot be read. Tampering is not an error, but the verdict.
+pub fn verify(repo: &Repository, commit: ObjectId, keys: &[String]) -> Result<Verification, Error> {
+    let atom = repo.find_commit(commit).map_err(Box::new)?;
+    let Claim {
+        id,
+        version,
+        origin,
+        dir,
+    } = claim(repo, &atom)?;
+    if repo.find_commit(origin).is_err() {
+        return Err(Error::NoSource(origin));
+    }
+
+    let tree: Tree = atom.tree()?.decode()?.into();
+    let manifest = tree
+        .entries
+        .iter()
+        .find(|e| e.mode.is_blob() && e.filename.ends_with(crate::ATOM_EXT.as_bytes()))
+        .and_then(|e| e.filename.to_str().ok())
+        .ok_or(Error::NotAnAtom(commit))?;
+    let path = Path::new(&dir).join(manifest);
+
+    // a signed commit is re-created as it was before it was signed
+    let signed = Signed::split(atom.decode()?);
+    let unsigned = signed
+        .as_ref()
+        .map_or(commit, |signed| signed.payload_id(repo));
+
+    let (recreated, mut tampered) = match crate::publish::git::recreate(repo, origin, &path) {
+        Ok((_, new)) if new == unsigned => (Some(new), Vec::new()),
+        Ok((recreated, new)) => {
+            // an entry differing on both sides is reported once
+            let mut tampered: Vec<_> = tree
+                .entries
+                .iter()
+                .filter(|e| !recreated.entries.contains(e))
+                .chain(
+                    recreated
+                        .entries
+                        .iter()
+                        .filter(|r| !tree.entries.contains(r)),
+                )
+                .map(|e| e.filename.clone())
+                .collect();
+            tampered.sort();
+            tampered.dedup();
+            let mut tampered: Vec<_> = tampered.into_iter().map(Tampered::Entry).collect();
+            if tampered.is_empty() {
+                tampered.push(Tampered::Commit);
+            }
+            (Some(new), tampered)
+        },
+        Err(PublishError::NotAnAtom(_)) => (None, vec![Tampered::Source]),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut signer = None;
+    if !keys.is_empty() {
+        match signed.map(|signed| signed.verify(repo, keys)) {
+            Some(Ok(key)) => signer = Some(key),
+            Some(Err(e)) => tampered.push(Tampered::Signature(e.to_string())),
+            None => tampered.push(Tampered::Unsigned),
+        }
+    }
+
+    if tampered.is_empty() {
+        tracing::debug!(%id, %version, %commit, %origin, "Verified the atom against its source");
+    }
+    Ok(Verification {
+        id,
+        version,
+        commit,
+        origin,
+        path,
+        recreated,
+        signer,
+        tampered,
+    })
+}
+
+/// Fetch the given version of an Atom from `remote`, along with the commit it claims to
+/// originate from, unless it is already in the repository, and [`verify`] it.
+///
+/// The source is fetched through the Atom's origin ref, which keeps it alive in the store, but
+/// the source is always the commit named by the Atom commit itself, wherever the ref points.
+///
+/// # Errors
+///
+/// This function will return an error if the version is not published to `remote`, or its
+/// source cannot be fetched, as well as for any reason [`verify`] would.
+pub fn verify_published(
+    remote: &gix::Remote,
+    id: &str,
+    version: &Version,
+    keys: &[String],
+) -> Result<Verification, Error> {
+    use crate::store::QueryStore;
+
+    let repo = remote.repo();
+    let name = |kind: &str| {
+        let version = git::encode_version(version);
+        format!("refs/{ATOM_REF_TOP_LEVEL}/{id}/{version}/{kind}")
+    };
+
+    let atom = name(ATOM);
+    git::validate_ref_name(&atom)?;
+    let commit = remote.get_ref(atom.as_str())?;
+
+    let Claim { origin, .. } = claim(repo, &repo.find_commit(commit).map_err(Box::new)?)?;
+    if repo.find_commit(origin).is_err() {
+        match remote.get_ref(name(ATOM_ORIGIN).as_str()) {
+            Ok(_) => {},
+            // the source may have been pruned, which `verify` reports
+            Err(git::Error::NoRef(..) | git::Error::Refs(_)) => {},
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    verify(repo, commit, keys)
+}
+
+/// Read what the Atom commit claims about itself from its message and headers.
+fn claim(repo: &Repository, atom: &gix::Commit) -> Result<Claim, Error> {
+    let not_an_atom = || Error::NotAnAtom(atom.id);
+
+    let commit = atom.decode()?;
+    let header = |key: &str| commit.extra_headers().find(key).map(|v| v.to_string());
+    let origin = header(ATOM_ORIGIN)
+        .and_then(|origin| git::parse_object_id(repo, origin.as_bytes()))
+        .ok_or_else(not_an_atom)?;
+    let dir = header("path").ok_or_else(not_an_atom)?;
+    let (id, version) = commit
+        .message
+        .to_str()
+        .ok()
+        .and_then(|message| message.split_once(": "))
+        .ok_or_else(not_an_atom)?;
+
+    Ok(Claim {
+        id: id.to_owned(),
+        version: version.trim_end().to_owned(),
+        origin,
+        dir: dir.trim_matches('/').to_owned(),
+    })
+}
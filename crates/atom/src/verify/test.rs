@@ -0,0 +1,100 @@
+use anyhow::Context;
+
+use super::*;
+use crate::publish::git::test::MockAtom;
+
+#[tokio::test]
+async fn verify_against_source() -> Result<(), anyhow::Error> {
+    use gix::objs::tree::{Entry, EntryKind};
+
+    use crate::Manifest;
+    use crate::publish::Publish;
+    use crate::publish::git::{Builder, GitPublisher, atom_commit};
+    use crate::store::{Init, QueryStore};
+    let (repo, _remote) = git::test::init_repo_and_remote()?;
+    let repo = gix::open(repo.as_ref())?;
+    let remote = repo.find_remote("origin")?;
+    remote.ekala_init()?;
+    remote.get_refs(Some("refs/heads/*:refs/heads/*"))?;
+
+    let (file, _) = repo.mock("foo", "0.1.0", "some atom")?;
+    let (paths, publisher) = GitPublisher::new(&repo, "origin", "HEAD")?.build()?;
+    for outcome in publisher.publish(paths.into_values()) {
+        assert!(matches!(outcome, Ok(Ok(_))));
+    }
+    let mut errors = Vec::new();
+    publisher.await_pushes(&mut errors).await;
+    (!errors.is_empty()).then_some(0).context("push errors")?;
+
+    let verified = verify_published(&remote, "foo", &Version::new(0, 1, 0), &[])?;
+    assert!(verified.is_authentic());
+    assert_eq!(verified.id(), "foo");
+    assert_eq!(verified.version(), "0.1.0");
+    assert_eq!(verified.recreated(), Some(verified.commit()));
+    let work_dir = repo.work_dir().context("no workdir")?;
+    assert_eq!(verified.path(), file.path().strip_prefix(work_dir)?);
+
+    // an Atom claiming the same source, with content it does not hold
+    let origin = verified.origin();
+    let manifest = std::fs::read_to_string(file.path())?;
+    let mut tree: Tree = repo.find_commit(origin)?.tree()?.decode()?.into();
+    tree.entries.push(Entry {
+        mode: EntryKind::Blob.into(),
+        filename: "evil.nix".into(),
+        oid: repo.write_blob(b"{ }")?.detach(),
+    });
+    tree.entries.sort();
+    let tree = repo.write_object(tree)?.detach();
+    let atom = Manifest::get_atom(&manifest)?;
+    let forged = repo
+        .write_object(atom_commit(&atom, tree, origin, Path::new("")))?
+        .detach();
+    let verdict = verify(&repo, forged, &[])?;
+    assert_eq!(verdict.tampered(), [Tampered::Entry("evil.nix".into())]);
+
+    // an Atom with the content of its source, under another version
+    let mut atom = Manifest::get_atom(&manifest)?;
+    atom.version = Version::new(0, 1, 1);
+    let tree = repo.find_commit(verified.commit())?.tree_id()?.detach();
+    let forged = repo
+        .write_object(atom_commit(&atom, tree, origin, Path::new("")))?
+        .detach();
+    let verdict = verify(&repo, forged, &[])?;
+    assert_eq!(verdict.tampered(), [Tampered::Commit]);
+
+    // a signed Atom is reproducible but for its signature, which is only checked given keys
+    let atom = Manifest::get_atom(&manifest)?;
+    let mut signed = atom_commit(&atom, tree, origin, Path::new(""));
+    signed.extra_headers.push((
+        crate::store::git::sign::SIGNATURE.into(),
+        "-----BEGIN SSH SIGNATURE-----\n-----END SSH SIGNATURE-----".into(),
+    ));
+    let signed = repo.write_object(signed)?.detach();
+    let verdict = verify(&repo, signed, &[])?;
+    assert!(verdict.is_authentic());
+    assert_eq!(verdict.recreated(), Some(verified.commit()));
+    let keys = ["SHA256:4lzuQ7Hdt1Kk0q4dQ6yK0ZDhhwW7bV5cpcqSd1b2P6M".to_owned()];
+    let verdict = verify(&repo, verified.commit(), &keys)?;
+    assert_eq!(verdict.tampered(), [Tampered::Unsigned]);
+
+    // only Atom commits can be verified
+    assert!(matches!(
+        verify(&repo, origin, &[]),
+        Err(Error::NotAnAtom(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn atom_refs() {
+    let parsed = |name| parse_ref(name).map(|(id, version)| (id.to_string(), version));
+    for kind in ["atom", "spec", "src"] {
+        assert_eq!(
+            parsed(&format!("refs/atoms/foo/1.0.0_build.5/{kind}")),
+            Some(("foo".into(), Version::parse("1.0.0+build.5").unwrap()))
+        );
+    }
+    assert_eq!(parsed("refs/atoms/foo/1.0.0"), None);
+    assert_eq!(parsed("refs/heads/foo/1.0.0/atom"), None);
+    assert_eq!(parsed("refs/atoms/foo/latest/atom"), None);
+}
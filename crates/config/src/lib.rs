@@ -1,5 +1,8 @@
+#[cfg(test)]
+mod test;
+
 use std::collections::HashMap;
-use std::sync::LazyLock;
+use std::path::{Path, PathBuf};
 
 use etcetera::BaseStrategy;
 use figment::providers::{Env, Format, Toml};
@@ -8,47 +11,685 @@ use figment::{Figment, Metadata, Provider};
 use gix::ThreadSafeRepository;
 use serde::{Deserialize, Serialize};
 
-/// Provide a lazyily instantiated static reference to
-/// a config object parsed from canonical locations
-/// so that applications have immutable access to it from
-/// anywhere without ever having to parse the config more
-/// than once.
+/// The aliases URIs may name stores by, e.g. `gh` for `github.com`.
+pub type Aliases = HashMap<String, Alias>;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Config {
+    aliases: Aliases,
+    #[serde(default)]
+    color: ColorChoice,
+    #[serde(default)]
+    store: Option<StoreKind>,
+    #[serde(default)]
+    publish: PublishConfig,
+    #[serde(default)]
+    eval: EvalConfig,
+    #[serde(default)]
+    develop: DevelopConfig,
+    #[serde(default)]
+    auth: HashMap<String, AuthConfig>,
+    #[serde(default)]
+    metrics: bool,
+    #[serde(default)]
+    gc: GcConfig,
+    #[serde(default)]
+    lint: LintConfig,
+    #[serde(default)]
+    proxy: ProxyConfig,
+    #[serde(default)]
+    fetch: FetchConfig,
+    #[serde(default)]
+    signing: SigningConfig,
+}
+
+/// When to emit ANSI color codes in terminal output.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorChoice {
+    /// Use color only when writing to a terminal, and `NO_COLOR` is unset.
+    #[default]
+    Auto,
+    /// Always use color.
+    Always,
+    /// Never use color.
+    Never,
+}
+
+/// A kind of Atom store.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum StoreKind {
+    /// A Git repository.
+    Git,
+}
+
+impl StoreKind {
+    /// The name of the kind, as written in the configuration.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StoreKind::Git => "git",
+        }
+    }
+}
+
+impl std::fmt::Display for StoreKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An alias for a url fragment, e.g. a host, or an organization on one, given either as the
+/// fragment it expands to, or as a table describing the host it names.
 ///
-/// For efficiency, all collections in the Config contain
-/// references to values owned by the deserializer instead
-/// of owned data, ensuring cheap copying where ownership
-/// is required.
-pub static CONFIG: LazyLock<Config> = LazyLock::new(load_config);
+/// ```toml
+/// [aliases]
+/// work = "gh:my-work-org"
+///
+/// [aliases.gh]
+/// host = "github.com"
+/// api = "rest"
+/// ssh-user = "git"
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Alias {
+    /// The url fragment the alias expands to, e.g. `github.com` or `gh:my-org`.
+    Plain(String),
+    /// A host, along with hints on how to reach it.
+    Host(HostAlias),
+}
 
-fn load_config() -> Config {
-    Config::figment().extract().unwrap_or_default()
+/// A host an alias names, along with hints on how to reach it.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct HostAlias {
+    /// The url fragment the alias expands to, i.e. the host, and as much of the path as
+    /// desired.
+    pub host: String,
+    /// The API the host offers to list the refs of stores, if any is known.
+    #[serde(default)]
+    pub api: Option<HostApi>,
+    /// The user to connect to the host as over ssh, unless a URI gives one.
+    #[serde(default)]
+    pub ssh_user: Option<String>,
 }
 
-type Aliases<'a> = HashMap<&'a str, &'a str>;
+/// The API a host offers to list the refs of stores.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostApi {
+    /// GitHub's REST API, served from `api.github.com` for `github.com`, or from `/api/v3` of
+    /// the host otherwise, as GitHub Enterprise serves it.
+    Rest,
+    /// No API, the refs are always listed over git.
+    Git,
+}
 
-#[derive(Deserialize, Serialize)]
-pub struct Config {
-    #[serde(borrow)]
-    aliases: Aliases<'static>,
+impl Alias {
+    /// The url fragment the alias expands to.
+    pub fn target(&self) -> &str {
+        match self {
+            Alias::Plain(target) => target,
+            Alias::Host(host) => &host.host,
+        }
+    }
+
+    /// The host the alias names, if it is described as one.
+    pub fn host(&self) -> Option<&HostAlias> {
+        match self {
+            Alias::Plain(_) => None,
+            Alias::Host(host) => Some(host),
+        }
+    }
+}
+
+impl From<&str> for Alias {
+    fn from(target: &str) -> Self {
+        Alias::Plain(target.to_owned())
+    }
+}
+
+impl From<String> for Alias {
+    fn from(target: String) -> Self {
+        Alias::Plain(target)
+    }
+}
+
+impl HostAlias {
+    /// The name of the host, without any path following it.
+    pub fn name(&self) -> &str {
+        self.host.split('/').next().unwrap_or_default()
+    }
+}
+
+/// Defaults for the `eka publish` subcommand.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PublishConfig {
+    /// Fail validation on any invalid Atom manifest instead of skipping it.
+    pub strict: bool,
+    /// Ask for confirmation in a terminal when publishing more than this many Atoms.
+    pub confirm_threshold: usize,
+    /// How Atom content is packed when pushed to any remote.
+    pub pack: PackConfig,
+    /// Packing settings for individual remotes, by name, taking precedence over `pack`.
+    pub remotes: HashMap<String, PackConfig>,
+    /// The remote to publish to when none is given, taking precedence over the push remote
+    /// configured in git.
+    pub default_remote: Option<String>,
+    /// Exit successfully when some Atoms failed to publish, as long as others were published.
+    pub allow_partial: bool,
+    /// Skip validating and publishing Atoms unchanged since they were last published.
+    pub incremental: bool,
+    /// The traits an Atom may declare beyond the builtin ones, which manifests declaring any
+    /// other are rejected for.
+    pub traits: Vec<String>,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        PublishConfig {
+            strict: false,
+            confirm_threshold: 1,
+            pack: PackConfig::default(),
+            remotes: HashMap::new(),
+            default_remote: None,
+            allow_partial: false,
+            incremental: false,
+            traits: vec!["devshell".to_owned()],
+        }
+    }
+}
+
+impl PublishConfig {
+    /// The packing settings for the given remote, falling back to the global ones.
+    pub fn pack(&self, remote: &str) -> PackConfig {
+        self.remotes
+            .get(remote)
+            .copied()
+            .unwrap_or_default()
+            .or(self.pack)
+    }
+}
+
+/// How Atom content is packed when pushed to a remote.
+///
+/// Unset values are left to the configuration of the underlying transport.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PackConfig {
+    /// The zlib compression level of the pack, from 0 (none) to 9 (best).
+    pub compression: Option<u8>,
+    /// How many objects are considered as delta bases for each object.
+    pub window: Option<u32>,
+    /// The maximum length of delta chains.
+    pub depth: Option<u32>,
+    /// Whether objects already packed locally may be sent as is, instead of being recompressed.
+    pub reuse: Option<bool>,
+    /// Optimize for low-bandwidth links.
+    ///
+    /// Defaults any unset value to [`PackConfig::THIN`], trading processing time for the
+    /// smallest transfer possible. Packs are pushed thin either way, as is git's default.
+    pub thin: bool,
+}
+
+impl PackConfig {
+    /// The most aggressive settings, used to fill in a [`PackConfig::thin`] profile.
+    pub const THIN: PackConfig = PackConfig {
+        compression: Some(9),
+        window: Some(250),
+        depth: Some(250),
+        reuse: Some(false),
+        thin: true,
+    };
+
+    /// Fill in the values unset in this profile from `fallback`.
+    #[must_use]
+    pub fn or(self, fallback: PackConfig) -> Self {
+        PackConfig {
+            compression: self.compression.or(fallback.compression),
+            window: self.window.or(fallback.window),
+            depth: self.depth.or(fallback.depth),
+            reuse: self.reuse.or(fallback.reuse),
+            thin: self.thin || fallback.thin,
+        }
+    }
+
+    /// The final settings of this profile, after applying the [`PackConfig::thin`] defaults.
+    #[must_use]
+    pub fn resolve(self) -> Self {
+        if self.thin {
+            self.or(PackConfig::THIN)
+        } else {
+            self
+        }
+    }
+}
+
+/// Defaults for the `eka eval` subcommand.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EvalConfig {
+    /// The command evaluating an Atom, which is passed the Atom's directory as its final
+    /// argument, and prints the output to cache.
+    pub command: Vec<String>,
+    /// Also look up and share evaluation outputs through the remote store.
+    pub share: bool,
+}
+
+impl Default for EvalConfig {
+    fn default() -> Self {
+        EvalConfig {
+            command: ["nix-instantiate", "--eval", "--strict", "--json"]
+                .map(ToOwned::to_owned)
+                .to_vec(),
+            share: false,
+        }
+    }
+}
+
+/// Defaults for the `eka develop` subcommand.
+///
+/// ```toml
+/// [develop.environments]
+/// deployment = "shell.nix"
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DevelopConfig {
+    /// The command instantiating an Atom's environment, which is passed the entry point of the
+    /// environment as its final argument, and prints the resulting derivation paths.
+    pub instantiate: Vec<String>,
+    /// The command launching a shell in the instantiated environment, which is passed the
+    /// derivation paths as its final arguments.
+    pub shell: Vec<String>,
+    /// The entry point defining the environment of an Atom, relative to its content, by the
+    /// trait it declares.
+    pub environments: HashMap<String, String>,
+}
+
+impl Default for DevelopConfig {
+    fn default() -> Self {
+        DevelopConfig {
+            instantiate: vec!["nixec".to_owned()],
+            shell: vec!["nix-shell".to_owned()],
+            environments: HashMap::from_iter(
+                [("package", "default.nix"), ("devshell", "shell.nix")]
+                    .map(|(k, v)| (k.to_owned(), v.to_owned())),
+            ),
+        }
+    }
+}
+
+impl DevelopConfig {
+    /// The entry point defining the environment of an Atom of the given trait, if any.
+    pub fn environment(&self, kind: &str) -> Option<&str> {
+        self.environments.get(kind).map(String::as_str)
+    }
+}
+
+/// Defaults for the `eka gc` subcommand.
+///
+/// With no rule set, every source is retained. With both set, a source is retained as long as
+/// either rule retains it.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct GcConfig {
+    /// Retain the sources of this many of the latest versions of each Atom.
+    pub keep_latest: Option<usize>,
+    /// Retain the sources of versions published within this many days.
+    pub keep_days: Option<u64>,
+}
+
+/// A read-through proxy, caching the Atoms fetched from remote stores in a local bare
+/// repository, e.g. one shared by the machines of a build farm.
+///
+/// ```toml
+/// [proxy]
+/// path = "/var/cache/eka/proxy"
+/// ttl = 300
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ProxyConfig {
+    /// The bare repository Atoms are cached in, which enables the proxy when set.
+    pub path: Option<PathBuf>,
+    /// How long the refs of a cached Atom are served before being revalidated, in seconds.
+    pub ttl: u64,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        ProxyConfig {
+            path: None,
+            ttl: 300,
+        }
+    }
+}
+
+/// Defaults for the `eka fetch` subcommand.
+///
+/// ```toml
+/// [fetch]
+/// shared = true
+/// shared-path = "/var/cache/eka/atoms"
+/// api = true
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FetchConfig {
+    /// Link the Atoms of a recursive fetch to a single checkout of each, shared by every
+    /// project on the machine, rather than checking each out anew.
+    pub shared: bool,
+    /// The directory of the shared checkouts, defaulting to `atoms` in eka's cache directory.
+    pub shared_path: Option<PathBuf>,
+    /// List the refs of stores hosted by a provider with a REST API through it, rather than over
+    /// git, for commands which only read metadata, e.g. `eka info`.
+    pub api: bool,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        FetchConfig {
+            shared: true,
+            shared_path: None,
+            api: true,
+        }
+    }
+}
+
+/// Signing of the Atom commits written by `eka publish`, and the keys `eka verify` accepts
+/// signatures from.
+///
+/// ```toml
+/// [signing]
+/// sign = true
+/// format = "ssh"
+/// key = "~/.ssh/id_ed25519.pub"
+/// allowed-keys = ["SHA256:4lzuQ7Hdt1Kk0q4dQ6yK0ZDhhwW7bV5cpcqSd1b2P6M"]
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SigningConfig {
+    /// Sign every Atom commit published, as with `eka publish --sign`.
+    pub sign: bool,
+    /// The format of the signatures, defaulting to Git's `gpg.format`.
+    pub format: Option<SigningFormat>,
+    /// The key to sign with, defaulting to Git's `user.signingKey`.
+    pub key: Option<String>,
+    /// The fingerprints of the keys whose signatures are accepted, OpenPGP fingerprints or
+    /// SSH `SHA256:` fingerprints. Once any are given, unsigned Atoms are refused.
+    pub allowed_keys: Vec<String>,
+}
+
+/// The format of the signatures of Atom commits.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SigningFormat {
+    /// OpenPGP signatures, made with `gpg`.
+    Openpgp,
+    /// SSH signatures, made with `ssh-keygen`.
+    Ssh,
+}
+
+/// Lints applied to Atom manifests by `eka check` and `eka publish`.
+///
+/// ```toml
+/// [lint]
+/// description-max-length = 120
+/// max-keywords = 5
+///
+/// [lint.id-conventions]
+/// "acme-" = "^acme-[a-z0-9]+(-[a-z0-9]+)*$"
+///
+/// [lint.levels]
+/// missing-description = "error"
+/// unstable-version = "warn"
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LintConfig {
+    /// The maximum length of a description, in characters.
+    pub description_max_length: Option<usize>,
+    /// The maximum number of keywords an Atom may declare.
+    pub max_keywords: Option<usize>,
+    /// The pattern the ids starting with each prefix, e.g. that of an organization, must match.
+    pub id_conventions: HashMap<String, String>,
+    /// The severity of each lint.
+    pub levels: LintLevels,
+}
+
+/// The severity of each manifest lint.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LintLevels {
+    /// An Atom declares no description.
+    pub missing_description: LintLevel,
+    /// The description of an Atom is longer than `description-max-length`.
+    pub description_too_long: LintLevel,
+    /// An Atom is at a version before 1.0.0.
+    pub unstable_version: LintLevel,
+    /// An Atom declares more than `max-keywords` keywords.
+    pub too_many_keywords: LintLevel,
+    /// The id of an Atom does not match the convention for its prefix.
+    pub id_convention: LintLevel,
+}
+
+impl Default for LintLevels {
+    fn default() -> Self {
+        LintLevels {
+            missing_description: LintLevel::Allow,
+            description_too_long: LintLevel::Warn,
+            unstable_version: LintLevel::Allow,
+            too_many_keywords: LintLevel::Warn,
+            id_convention: LintLevel::Error,
+        }
+    }
+}
+
+/// How a manifest violating a lint is treated.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintLevel {
+    /// The violation is ignored.
+    Allow,
+    /// The violation is reported, but the Atom is still published.
+    Warn,
+    /// The manifest is invalid, and the Atom is not published.
+    Error,
+}
+
+/// Credentials for a private store or index, applied to every request to a url it is
+/// configured for.
+///
+/// ```toml
+/// [auth."https://atoms.example.com/private"]
+/// token = "..."
+/// client-cert = "/etc/eka/client.pem"
+/// client-key = "/etc/eka/client.key"
+/// ```
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AuthConfig {
+    /// A bearer token, sent in the `Authorization` header.
+    pub token: Option<String>,
+    /// The user name for basic authentication, if no token is given.
+    pub username: Option<String>,
+    /// The password for basic authentication.
+    pub password: Option<String>,
+    /// The PEM encoded certificate presented to the server for mutual TLS.
+    pub client_cert: Option<PathBuf>,
+    /// The PEM encoded private key of the client certificate.
+    pub client_key: Option<PathBuf>,
+}
+
+/// How requests are authorized by an [`AuthConfig`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Authorization<'a> {
+    /// A bearer token.
+    Bearer(&'a str),
+    /// A user name, and optionally a password.
+    Basic(&'a str, Option<&'a str>),
+}
+
+impl AuthConfig {
+    /// How requests are authorized, preferring a token to basic authentication.
+    pub fn authorization(&self) -> Option<Authorization<'_>> {
+        if let Some(token) = &self.token {
+            return Some(Authorization::Bearer(token));
+        }
+        self.username
+            .as_deref()
+            .map(|user| Authorization::Basic(user, self.password.as_deref()))
+    }
+
+    /// The client certificate and its key, if both are configured for mutual TLS.
+    pub fn identity(&self) -> Option<(&Path, &Path)> {
+        Some((self.client_cert.as_deref()?, self.client_key.as_deref()?))
+    }
+}
+
+impl std::fmt::Debug for AuthConfig {
+    // secrets must never reach logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redact = |secret: &Option<String>| secret.as_ref().map(|_| "<redacted>");
+        f.debug_struct("AuthConfig")
+            .field("token", &redact(&self.token))
+            .field("username", &self.username)
+            .field("password", &redact(&self.password))
+            .field("client_cert", &self.client_cert)
+            .field("client_key", &self.client_key)
+            .finish()
+    }
 }
 
 impl Config {
     pub fn aliases(&self) -> &Aliases {
         &self.aliases
     }
+
+    pub fn color(&self) -> ColorChoice {
+        self.color
+    }
+
+    /// The kind of store to use when the working directory is within more than one.
+    pub fn store(&self) -> Option<StoreKind> {
+        self.store
+    }
+
+    pub fn publish(&self) -> &PublishConfig {
+        &self.publish
+    }
+
+    pub fn eval(&self) -> &EvalConfig {
+        &self.eval
+    }
+
+    pub fn develop(&self) -> &DevelopConfig {
+        &self.develop
+    }
+
+    pub fn gc(&self) -> &GcConfig {
+        &self.gc
+    }
+
+    pub fn lint(&self) -> &LintConfig {
+        &self.lint
+    }
+
+    pub fn proxy(&self) -> &ProxyConfig {
+        &self.proxy
+    }
+
+    pub fn fetch(&self) -> &FetchConfig {
+        &self.fetch
+    }
+
+    /// The directory of the checkouts shared by recursive fetches, if they are shared, and one
+    /// can be determined for the platform.
+    pub fn shared_store(&self) -> Option<PathBuf> {
+        if !self.fetch.shared {
+            return None;
+        }
+        self.fetch
+            .shared_path
+            .clone()
+            .or_else(|| self.cache_dir().map(|dir| dir.join("atoms")))
+    }
+
+    pub fn signing(&self) -> &SigningConfig {
+        &self.signing
+    }
+
+    /// The credentials configured for the given url, i.e. those of the longest url prefix
+    /// of it with any configured.
+    pub fn auth(&self, url: &str) -> Option<&AuthConfig> {
+        self.auth
+            .iter()
+            .filter(|(prefix, _)| {
+                // match whole path segments only
+                url.strip_prefix(prefix.trim_end_matches('/'))
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|(prefix, _)| prefix.trim_end_matches('/').len())
+            .map(|(_, auth)| auth)
+    }
+
+    /// The directory eka caches data in, if one can be determined for the platform.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        etcetera::choose_base_strategy()
+            .ok()
+            .map(|c| c.cache_dir().join("eka"))
+    }
+
+    /// Whether to record the commands run, and how long they took, in a local file. The
+    /// metrics never leave the machine.
+    pub fn metrics(&self) -> bool {
+        self.metrics
+    }
+
+    /// The directory eka keeps persistent data in, if one can be determined for the platform.
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        etcetera::choose_base_strategy()
+            .ok()
+            .map(|c| c.data_dir().join("eka"))
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
+        let host = |host: &str, api| {
+            Alias::Host(HostAlias {
+                host: host.to_owned(),
+                api,
+                ssh_user: Some("git".to_owned()),
+            })
+        };
         Config {
-            aliases: HashMap::from_iter([
-                ("gh", "github.com"),
-                ("gl", "gitlab.com"),
-                ("cb", "codeberg.org"),
-                ("bb", "bitbucket.org"),
-                ("sh", "sr.ht"),
-                ("pkgs", "gh:nixos/nixpkgs"),
-            ]),
+            aliases: HashMap::from_iter(
+                [
+                    ("gh", host("github.com", Some(HostApi::Rest))),
+                    ("gl", host("gitlab.com", Some(HostApi::Git))),
+                    ("cb", host("codeberg.org", None)),
+                    ("bb", host("bitbucket.org", None)),
+                    ("sh", "sr.ht".into()),
+                    ("pkgs", "gh:nixos/nixpkgs".into()),
+                ]
+                .map(|(k, v)| (k.to_owned(), v)),
+            ),
+            color: ColorChoice::default(),
+            store: None,
+            publish: PublishConfig::default(),
+            eval: EvalConfig::default(),
+            develop: DevelopConfig::default(),
+            auth: HashMap::new(),
+            metrics: false,
+            gc: GcConfig::default(),
+            lint: LintConfig::default(),
+            proxy: ProxyConfig::default(),
+            fetch: FetchConfig::default(),
+            signing: SigningConfig::default(),
         }
     }
 }
@@ -58,7 +699,18 @@ impl Config {
         Figment::from(provider).extract()
     }
 
+    /// Load the configuration from its canonical locations, discovering any repository
+    /// specific configuration from the given directory.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Config {
+        Config::figment_in(dir).extract().unwrap_or_default()
+    }
+
     pub fn figment() -> Figment {
+        Config::figment_in(".")
+    }
+
+    #[cfg_attr(not(feature = "git"), allow(unused_variables))]
+    pub fn figment_in<P: AsRef<Path>>(dir: P) -> Figment {
         let mut fig = Figment::from(Config::default());
 
         if let Ok(c) = etcetera::choose_base_strategy() {
@@ -67,7 +719,7 @@ impl Config {
         }
 
         #[cfg(feature = "git")]
-        if let Ok(r) = ThreadSafeRepository::discover(".") {
+        if let Ok(r) = ThreadSafeRepository::discover(dir.as_ref()) {
             let repo_config = r.git_dir().join("info/eka.toml");
             fig = fig.admerge(Toml::file(repo_config));
         };
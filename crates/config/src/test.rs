@@ -0,0 +1,115 @@
+use figment::providers::{Format, Toml};
+
+use super::*;
+
+/// The configuration in `toml`, over the defaults, as it would be loaded.
+fn load(toml: &str) -> Result<Config, figment::Error> {
+    Figment::from(Config::default())
+        .admerge(Toml::string(toml))
+        .extract()
+}
+
+#[test]
+fn develop_environments() -> Result<(), anyhow::Error> {
+    let config = Config::default();
+    assert_eq!(config.develop().environment("package"), Some("default.nix"));
+    assert_eq!(config.develop().environment("devshell"), Some("shell.nix"));
+    assert_eq!(config.develop().environment("deployment"), None);
+
+    // configured environments extend the defaults, or override them
+    let config = load(
+        r#"
+        [develop.environments]
+        deployment = "env.nix"
+        package = "build.nix"
+        "#,
+    )?;
+    assert_eq!(config.develop().environment("deployment"), Some("env.nix"));
+    assert_eq!(config.develop().environment("package"), Some("build.nix"));
+    assert_eq!(config.develop().environment("devshell"), Some("shell.nix"));
+    Ok(())
+}
+
+#[test]
+fn publish_traits() -> Result<(), anyhow::Error> {
+    // the trait `eka develop` defines an environment for by default is known
+    let config = Config::default();
+    assert_eq!(config.publish().traits, ["devshell"]);
+
+    // configured traits extend the defaults
+    let config = load(
+        r#"
+        [publish]
+        traits = ["service", "library"]
+        "#,
+    )?;
+    assert_eq!(config.publish().traits, ["devshell", "service", "library"]);
+    Ok(())
+}
+
+#[test]
+fn pack_precedence() -> Result<(), anyhow::Error> {
+    let given = PackConfig {
+        compression: Some(1),
+        ..PackConfig::default()
+    };
+    let fallback = PackConfig {
+        compression: Some(5),
+        window: Some(10),
+        ..PackConfig::default()
+    };
+    // set values take precedence over the fallback's, which fill in the others
+    let pack = given.or(fallback);
+    assert_eq!(pack.compression, Some(1));
+    assert_eq!(pack.window, Some(10));
+    assert_eq!(pack.depth, None);
+    assert_eq!(pack.resolve(), pack);
+
+    // a thin profile fills in what is left unset from the most aggressive settings
+    let thin = PackConfig {
+        thin: true,
+        ..fallback
+    }
+    .resolve();
+    assert_eq!(
+        thin,
+        PackConfig {
+            compression: Some(5),
+            window: Some(10),
+            ..PackConfig::THIN
+        }
+    );
+    // as it does when only the fallback is thin
+    assert_eq!(given.or(thin).resolve().depth, Some(250));
+
+    // the settings of a remote take precedence over the global ones
+    let config = load(
+        r#"
+        [publish.pack]
+        compression = 3
+        depth = 20
+
+        [publish.remotes.slow]
+        compression = 7
+        thin = true
+        "#,
+    )?;
+    let publish = config.publish();
+    assert_eq!(
+        publish.pack("origin"),
+        PackConfig {
+            compression: Some(3),
+            depth: Some(20),
+            ..PackConfig::default()
+        }
+    );
+    assert_eq!(
+        publish.pack("slow").resolve(),
+        PackConfig {
+            compression: Some(7),
+            depth: Some(20),
+            ..PackConfig::THIN
+        }
+    );
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! # Atom Artifacts
+//!
+//! Attaches auxiliary artifacts to published Atoms, and fetches them back, verified against
+//! the content hashes declared in the Atom's manifest.
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use semver::Version;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::GREEN;
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: ArtifactCommands,
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+/// Identifies a single artifact of a published Atom version.
+#[derive(Parser, Debug)]
+struct Artifact {
+    /// The id of the Atom
+    id: String,
+    /// The published version of the Atom
+    version: Version,
+    /// The name of the artifact, as declared in the manifest
+    name: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum ArtifactCommands {
+    /// Attach an artifact to a published Atom version.
+    ///
+    /// The artifact must be declared, along with the hash of its
+    /// content, in the `[artifacts]` of the Atom's manifest at the
+    /// time it was published.
+    #[command(verbatim_doc_comment)]
+    Attach {
+        #[command(flatten)]
+        artifact: Artifact,
+        /// The file holding the artifact's content
+        file: PathBuf,
+    },
+    /// Fetch an artifact of a published Atom version.
+    ///
+    /// The artifact is verified against the hash declared in the
+    /// Atom's manifest before it is written.
+    #[command(verbatim_doc_comment)]
+    Fetch {
+        #[command(flatten)]
+        artifact: Artifact,
+        /// Write the artifact to the given file instead of stdout
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::git::artifact;
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+
+            match args.command {
+                ArtifactCommands::Attach {
+                    artifact: Artifact { id, version, name },
+                    file,
+                } => {
+                    let content = std::fs::read(&file)?;
+                    let artifact_ref =
+                        artifact::attach(&repo, &remote, &id, &version, &name, &content)?;
+
+                    let mut sink = ctx.sink();
+                    sink.record(&Attached {
+                        name: &name,
+                        artifact_ref: &artifact_ref,
+                    });
+                    sink.finish()?;
+                },
+                ArtifactCommands::Fetch {
+                    artifact: Artifact { id, version, name },
+                    output,
+                } => {
+                    let store = repo.find_remote(remote.as_str())?;
+                    let content = artifact::fetch(&store, &id, &version, &name)?;
+                    match output {
+                        Some(path) => std::fs::write(path, content)?,
+                        None => io::stdout().write_all(&content)?,
+                    }
+                },
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// An artifact which was attached to a published Atom.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+struct Attached<'a> {
+    name: &'a str,
+    artifact_ref: &'a str,
+}
+
+impl Record for Attached<'_> {
+    fn row(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(msg!("status-attached")).color(GREEN),
+            Cell::new(self.name),
+            Cell::new(self.artifact_ref),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": "attached",
+            "name": self.name,
+            "ref": self.artifact_ref,
+        })
+    }
+}
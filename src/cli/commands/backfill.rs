@@ -0,0 +1,213 @@
+//! # Atom Backfilling
+//!
+//! Publishes every historical version of an Atom, each from the commit which introduced it,
+//! so that a freshly initialized store holds the Atom's full release history.
+use std::path::PathBuf;
+
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::exit::{ExitArgs, Outcome};
+use crate::cli::logging::ansi::{GREEN, RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the manifest of the atom to backfill
+    path: PathBuf,
+
+    /// The revision whose history is searched for versions of the atom
+    ///
+    /// Only its first-parent history is searched, and the atom is
+    /// identified by the id it has in this revision.
+    #[arg(
+        long,
+        short,
+        default_value = "HEAD",
+        name = "REVSPEC",
+        verbatim_doc_comment
+    )]
+    spec: String,
+
+    /// Only report which versions would be published, without publishing them
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(flatten)]
+    exit: ExitArgs,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("No version of an atom at `{0}` found in the history")]
+    NotAnAtom(PathBuf),
+    #[error("Failed to publish {0} version(s)")]
+    Failed(usize),
+}
+
+pub(super) async fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::publish::git::{GitPublisher, version_history};
+            use atom::publish::{Builder, Publish};
+            use atom::store::{NormalizeStorePath, git};
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            // the atom need not exist in the working directory anymore, nor need there be one
+            let path = match repo.normalize_from(ctx.cwd(), &args.path) {
+                Ok(path) => path,
+                Err(_) => repo.normalize_lexical(&args.path)?,
+            };
+
+            let history = version_history(&repo, &args.spec, &path)?;
+            let revisions: Vec<_> = history.iter().map(|(c, _)| c.to_string()).collect();
+            let Some(first) = revisions.first() else {
+                return Err(Error::NotAnAtom(args.path).into());
+            };
+
+            let existing = git::published(&repo, &remote)?;
+            let builder = GitPublisher::new(&repo, &remote, first)?
+                .pack(ctx.config().publish().pack(&remote))
+                .lexical(true);
+
+            let mut sink = ctx.sink();
+            let (mut published, mut skipped, mut failed) = (0, 0, 0);
+            for ((_, version), revision) in history.iter().zip(&revisions) {
+                let (_, publisher) = builder
+                    .clone()
+                    .spec(revision)
+                    .build()
+                    .inspect_err(super::publish::report)?;
+                let plan = publisher.plan([path.clone()]).remove(0)?;
+                let exists = plan.exists()
+                    || existing
+                        .get(plan.id().id())
+                        .is_some_and(|versions| versions.contains(version));
+
+                let status = if exists {
+                    Status::Skipped
+                } else if args.dry_run {
+                    Status::Planned
+                } else {
+                    let mut errors = Vec::new();
+                    let result = publisher.publish([path.clone()]).remove(0);
+                    publisher.await_pushes(&mut errors).await;
+                    match result {
+                        Err(e) => Status::Failed(e.to_string()),
+                        Ok(_) if !errors.is_empty() => {
+                            let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                            Status::Failed(errors.join("; "))
+                        },
+                        Ok(Ok(_)) => Status::Published,
+                        Ok(Err(_)) => Status::Skipped,
+                    }
+                };
+                publisher
+                    .take_warnings()
+                    .iter()
+                    .for_each(super::publish::warn);
+                match status {
+                    Status::Published | Status::Planned => published += 1,
+                    Status::Skipped => skipped += 1,
+                    Status::Failed(_) => failed += 1,
+                }
+
+                sink.record(&Backfilled {
+                    version,
+                    commit: revision,
+                    status,
+                });
+            }
+            sink.finish()?;
+
+            if failed > 0 && published > 0 {
+                let total = published + skipped + failed;
+                return Err(Outcome::Partial { failed, total }.into());
+            } else if failed > 0 {
+                return Err(Error::Failed(failed).into());
+            }
+            args.exit.check(published, skipped)?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// The outcome of backfilling a single version.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+enum Status {
+    Published,
+    Planned,
+    Skipped,
+    Failed(String),
+}
+
+/// A version of the Atom, the commit it was introduced in, and what became of it.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+struct Backfilled<'a> {
+    version: &'a semver::Version,
+    commit: &'a str,
+    status: Status,
+}
+
+impl Record for Backfilled<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let (status, color) = match self.status {
+            Status::Published => (msg!("status-published"), GREEN),
+            Status::Planned => (msg!("status-planned"), GREEN),
+            Status::Skipped => (msg!("status-skipped"), YELLOW),
+            Status::Failed(_) => (msg!("status-failed"), RED),
+        };
+        let mut row = vec![
+            Cell::new(status).color(color),
+            Cell::new(self.version),
+            Cell::new(&self.commit[..12]),
+        ];
+        if let Status::Failed(reason) = &self.status {
+            row.push(Cell::new(reason));
+        }
+        row
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let status = match self.status {
+            Status::Published => "published",
+            Status::Planned => "planned",
+            Status::Skipped => "skipped",
+            Status::Failed(_) => "failed",
+        };
+        let mut json = serde_json::json!({
+            "status": status,
+            "version": self.version.to_string(),
+            "commit": self.commit,
+        });
+        if let Status::Failed(reason) = &self.status {
+            json["reason"] = reason.as_str().into();
+        }
+        json
+    }
+}
@@ -0,0 +1,131 @@
+use atom::report::{Diagnostic, Report, Severity};
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::store::Detected;
+
+#[derive(Parser, Debug)]
+#[group(id = "check_args")]
+pub struct Args {
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("The store has {0} issue(s)")]
+    Invalid(usize),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::{QueryPolicy, git};
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+
+            let policy = repo.find_remote(remote.as_str())?.ekala_policy()?;
+            let mut summary = Report::default();
+            match policy {
+                Some(policy) => summary.extend(
+                    policy
+                        .check()
+                        .iter()
+                        .map(|issue| Diagnostic::error("policy", issue)),
+                ),
+                None => tracing::info!(%remote, "The store does not declare a policy"),
+            }
+            summary.extend(git::confusable_ids(&repo, &remote)?.iter().map(|ids| {
+                let ids: Vec<_> = ids.iter().map(|id| id.as_str()).collect();
+                let message = format!("Atom ids are confusable: {}", ids.join(", "));
+                Diagnostic::error("confusable", message)
+            }));
+            // a bare store has no Atoms of its own to lint
+            if repo.work_dir().is_some() {
+                lint(ctx, &repo, &remote, &mut summary)?;
+            }
+
+            let mut sink = ctx.sink();
+            for diagnostic in summary.diagnostics() {
+                sink.record(diagnostic);
+            }
+            sink.finish()?;
+
+            let issues = summary.raised(Severity::Error);
+            if issues > 0 {
+                return Err(Error::Invalid(issues).into());
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Lint the manifests of the Atoms at `HEAD`, raising an error for those refused by the
+/// configured lints, or otherwise invalid, and a warning for each lint violated at the `warn`
+/// level.
+#[cfg(feature = "git")]
+fn lint(
+    ctx: &Context,
+    repo: &gix::Repository,
+    remote: &str,
+    summary: &mut Report,
+) -> anyhow::Result<()> {
+    use atom::publish::error::git::Error;
+    use atom::publish::git::GitPublisher;
+    use atom::publish::{Builder, Warning};
+    use atom::{Linter, Manifest};
+
+    let linter = Linter::new(ctx.config().lint())?;
+    let (atoms, publisher) = GitPublisher::new(repo, remote, "HEAD")?
+        .lints(linter.clone())
+        .kinds(ctx.kinds()?)
+        .lexical(true)
+        .build()?;
+    summary.extend(
+        publisher
+            .take_warnings()
+            .iter()
+            .map(|warning| match warning {
+                Warning::Skipped(Error::Invalid(e, path)) => {
+                    Diagnostic::error("invalid", e).about(path.display())
+                },
+                _ => Diagnostic::error(warning.code(), warning),
+            }),
+    );
+
+    let head = repo.head_id()?.detach();
+    let workspace = atom::store::git::read_workspace(repo, head)?.unwrap_or_default();
+    for path in atoms.values() {
+        let Some(entry) = publisher.tree_search(path)? else {
+            continue;
+        };
+        let object = entry.object()?;
+        let atom = Manifest::get_member_atom(std::str::from_utf8(&object.data)?, &workspace)?;
+        summary.extend(
+            linter
+                .lint(&atom)
+                .into_iter()
+                .map(|(_, v)| Diagnostic::from(&Warning::Lint(v)).about(path.display())),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! # Debugging Aids
+//!
+//! Commands useful to developers of eka, rather than its users, such as generating synthetic
+//! repositories to test and profile validation and publishing at scale.
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::GREEN;
+use crate::cli::output::{Cell, Record};
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: DebugCommands,
+}
+
+#[derive(Subcommand, Debug)]
+enum DebugCommands {
+    /// Generate a synthetic repository of atoms.
+    ///
+    /// Creates a new git repository holding the given number of atoms,
+    /// optionally nested in directories, with duplicate ids, or with
+    /// broken manifests. The same options always generate the same
+    /// commit, so performance and correctness issues can be reproduced.
+    #[command(verbatim_doc_comment)]
+    GenRepo(GenRepoArgs),
+}
+
+#[derive(Parser, Debug)]
+struct GenRepoArgs {
+    /// The directory to create the repository in
+    path: PathBuf,
+
+    /// The number of atoms to generate
+    #[arg(long, default_value_t = 1_000)]
+    atoms: usize,
+
+    /// The most directories an atom is nested in
+    #[arg(long, default_value_t = 0)]
+    depth: usize,
+
+    /// The number of atoms given the id of another atom
+    #[arg(long, default_value_t = 0)]
+    duplicates: usize,
+
+    /// The number of atoms with a broken manifest
+    #[arg(long, default_value_t = 0)]
+    broken: usize,
+
+    /// Seed the random choices of the generator
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+#[derive(Error, Debug)]
+#[cfg_attr(feature = "git", allow(dead_code))]
+pub(super) enum Error {
+    #[error("Generating a repository requires eka to be built with the `git` feature")]
+    Unsupported,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match args.command {
+        DebugCommands::GenRepo(args) => gen_repo(ctx, args),
+    }
+}
+
+#[cfg(feature = "git")]
+fn gen_repo(ctx: &Context, args: GenRepoArgs) -> anyhow::Result<()> {
+    use atom::store::git::synthetic::Synthetic;
+
+    let path = ctx.cwd().join(&args.path);
+    let repo = gix::init(&path)?;
+    let generated = Synthetic::new(args.atoms)
+        .depth(args.depth)
+        .duplicates(args.duplicates)
+        .broken(args.broken)
+        .seed(args.seed)
+        .generate(&repo)?;
+
+    let mut sink = ctx.sink();
+    sink.record(&Generated {
+        path: &path,
+        commit: generated.commit().to_string(),
+        atoms: generated.atoms(),
+        valid: generated.valid(),
+        duplicates: generated.duplicates().len(),
+        broken: generated.broken().len(),
+    });
+    sink.finish()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "git"))]
+fn gen_repo(_ctx: &Context, _args: GenRepoArgs) -> anyhow::Result<()> {
+    Err(Error::Unsupported.into())
+}
+
+/// A synthetic repository which was generated.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+struct Generated<'a> {
+    path: &'a std::path::Path,
+    commit: String,
+    atoms: usize,
+    valid: usize,
+    duplicates: usize,
+    broken: usize,
+}
+
+impl Record for Generated<'_> {
+    fn row(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(msg!("status-generated")).color(GREEN),
+            Cell::new(self.path.display()),
+            Cell::new(&self.commit),
+            Cell::new(msg!(
+                "debug-generated",
+                atoms = self.atoms,
+                valid = self.valid,
+                duplicates = self.duplicates,
+                broken = self.broken,
+            )),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": "generated",
+            "path": self.path,
+            "commit": self.commit,
+            "atoms": self.atoms,
+            "valid": self.valid,
+            "duplicates": self.duplicates,
+            "broken": self.broken,
+        })
+    }
+}
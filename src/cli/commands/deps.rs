@@ -0,0 +1,272 @@
+//! # Dependency Queries
+//!
+//! Lists the distinct licenses and source hosts in the dependency closure of an Atom, as
+//! recorded in its lock, comparing each against the organization's policy, and naming the
+//! manifests which introduce them, so that a violation can be traced back to its origin.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use atom::policy::OrgPolicy;
+use atom::{Lockfile, Manifest};
+use clap::{Parser, Subcommand};
+use semver::Version;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, RED};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: DepsCommands,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum DepsCommands {
+    /// List the licenses declared in the dependency closure of an atom.
+    ///
+    /// Each distinct license is reported with the atoms declaring it,
+    /// and whether the `ekala-policy.toml` of the repository forbids it.
+    #[command(verbatim_doc_comment)]
+    Licenses {
+        /// Path to the manifest of the atom
+        path: PathBuf,
+    },
+    /// List the hosts sources are fetched from in the dependency closure.
+    ///
+    /// Each distinct host is reported with the dependencies fetched from
+    /// it, and the atoms declaring them, and whether the `ekala-policy.toml`
+    /// of the repository allows it.
+    #[command(verbatim_doc_comment)]
+    Hosts {
+        /// Path to the manifest of the atom
+        path: PathBuf,
+    },
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("`{}` is not resolved, run `eka resolve` first", .0.display())]
+    Unresolved(PathBuf),
+    #[error("The dependency closure violates the policy {0} time(s)")]
+    Violations(usize),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            let store = repo.find_remote(remote.as_str())?;
+            let policy = match repo.head_id() {
+                Ok(head) => atom::store::git::read_org_policy(&repo, head.detach())?,
+                Err(_) => None,
+            }
+            .unwrap_or_default();
+
+            let (DepsCommands::Licenses { path } | DepsCommands::Hosts { path }) = &args.command;
+            let path = ctx.cwd().join(path);
+            let manifest: Manifest = std::fs::read_to_string(&path)?.parse()?;
+            let lock: Lockfile = match std::fs::read_to_string(Lockfile::path(&path)) {
+                Ok(lock) => lock.parse()?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(Error::Unresolved(path).into());
+                },
+                Err(e) => return Err(e.into()),
+            };
+
+            let mut closure = vec![(Vec::new(), manifest)];
+            for atom in &lock.atoms {
+                if let Some(url) = &atom.store {
+                    tracing::warn!(id = %atom.id, store = %url, "Skipping an atom of another store");
+                    continue;
+                }
+                let (spec, _) =
+                    atom::store::git::fetch_spec(&store, atom.published_id(), &atom.version)?;
+                let chain = lock.chain(&atom.id).unwrap_or_default();
+                let via = chain[..chain.len().saturating_sub(1)]
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                closure.push((via, spec));
+            }
+
+            let found = match args.command {
+                DepsCommands::Licenses { .. } => licenses(&closure, &policy),
+                DepsCommands::Hosts { .. } => hosts(&closure, &policy),
+            };
+            report(ctx, &found)?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Group the Atoms of the closure by the license they declare.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn licenses(closure: &[(Vec<String>, Manifest)], policy: &OrgPolicy) -> Vec<Found> {
+    let mut found: BTreeMap<&str, Found> = BTreeMap::new();
+    for (via, manifest) in closure {
+        let Some(license) = &manifest.atom.license else {
+            continue;
+        };
+        found
+            .entry(license.as_str())
+            .or_insert_with(|| Found::new(license, !policy.forbids(license)))
+            .introducers
+            .push(Introducer::new(manifest, via, None));
+    }
+    found.into_values().collect()
+}
+
+/// Group the dependencies of the closure fetched from a url by its host, which is not allowed
+/// unless every url from it is.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn hosts(closure: &[(Vec<String>, Manifest)], policy: &OrgPolicy) -> Vec<Found> {
+    let mut found: BTreeMap<String, Found> = BTreeMap::new();
+    for (via, manifest) in closure {
+        for (dep, url) in manifest.deps.urls() {
+            let host = url.host_str().unwrap_or(url.as_str()).to_owned();
+            let entry = found
+                .entry(host)
+                .or_insert_with_key(|host| Found::new(host, true));
+            entry.allowed &= policy.allows(url);
+            entry
+                .introducers
+                .push(Introducer::new(manifest, via, Some((dep, url.to_string()))));
+        }
+    }
+    found.into_values().collect()
+}
+
+/// Emit what was found, failing if any of it violates the policy.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn report(ctx: &Context, found: &[Found]) -> anyhow::Result<()> {
+    let mut sink = ctx.sink();
+    for found in found {
+        sink.record(found);
+    }
+    sink.finish()?;
+
+    let violations: usize = found
+        .iter()
+        .filter(|found| !found.allowed)
+        .map(|found| found.introducers.len())
+        .sum();
+    if violations > 0 {
+        return Err(Error::Violations(violations).into());
+    }
+    Ok(())
+}
+
+/// A license or host found in the dependency closure of an Atom.
+struct Found {
+    value: String,
+    allowed: bool,
+    introducers: Vec<Introducer>,
+}
+
+/// The manifest of an Atom of the closure, which declares a license, or a dependency.
+struct Introducer {
+    id: String,
+    version: Version,
+    /// The ids of the Atoms through which the locking Atom depends on this one.
+    via: Vec<String>,
+    /// The name and url of the dependency declared, if any.
+    dep: Option<(String, String)>,
+}
+
+impl Found {
+    fn new(value: &str, allowed: bool) -> Self {
+        Found {
+            value: value.to_owned(),
+            allowed,
+            introducers: Vec::new(),
+        }
+    }
+}
+
+impl Introducer {
+    fn new(manifest: &Manifest, via: &[String], dep: Option<(String, String)>) -> Self {
+        Introducer {
+            id: manifest.atom.id.to_string(),
+            version: manifest.atom.version.clone(),
+            via: via.to_vec(),
+            dep,
+        }
+    }
+}
+
+impl Record for Found {
+    fn row(&self) -> Vec<Cell> {
+        let status = if self.allowed {
+            Cell::new(msg!("status-allowed")).color(GREEN)
+        } else {
+            Cell::new(msg!("status-forbidden")).color(RED)
+        };
+        let introducers: Vec<_> = self
+            .introducers
+            .iter()
+            .map(|i| {
+                let mut text = format!("{}@{}", i.id, i.version);
+                if let Some((dep, _)) = &i.dep {
+                    text = format!("{text}: {dep}");
+                }
+                if !i.via.is_empty() {
+                    text = format!("{text} ({})", msg!("deps-via", chain = i.via.join(" > ")));
+                }
+                text
+            })
+            .collect();
+        vec![
+            status,
+            Cell::new(&self.value),
+            Cell::new(introducers.join(", ")),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let introducers: Vec<_> = self
+            .introducers
+            .iter()
+            .map(|i| {
+                let (dep, url) = i.dep.clone().unzip();
+                serde_json::json!({
+                    "id": i.id,
+                    "version": i.version.to_string(),
+                    "via": i.via,
+                    "dep": dep,
+                    "url": url,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "status": if self.allowed { "allowed" } else { "forbidden" },
+            "value": self.value,
+            "introducers": introducers,
+        })
+    }
+}
@@ -0,0 +1,219 @@
+//! # Atom Development Environments
+//!
+//! Materializes an Atom's content outside of its repository, along with every Atom pinned by
+//! its lock, and launches a shell in the environment its trait defines, so that it can be worked
+//! on in isolation. The environment is instantiated by `nixec` by default, which confines
+//! evaluation to the materialized Atom and its dependencies:
+//!
+//! ```text
+//! <dir>/atom                     the content of the Atom
+//! <dir>/deps/<id>@<version>      each Atom pinned by its lock
+//! <dir>/deps/closure.json        the path of each, by id
+//! ```
+//!
+//! The directory is written through a temporary one, renamed once complete, and reused as long
+//! as neither the content nor the lock of the Atom changes. The dependencies are checked out
+//! to it rather than linked to the shared store, which lies outside of the sandbox.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::store::{Detected, RemoteArg};
+
+/// The directory the Atom's content is materialized to.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+const ATOM_DIR: &str = "atom";
+
+/// The directory the Atoms pinned by its lock are materialized to.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+const DEPS_DIR: &str = "deps";
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the manifest of the atom to develop
+    path: PathBuf,
+
+    /// The revision to materialize the atom from
+    ///
+    /// Specifies a revision using Git's extended SHA-1 syntax.
+    #[arg(
+        long,
+        short,
+        default_value = "HEAD",
+        name = "REVSPEC",
+        verbatim_doc_comment
+    )]
+    spec: String,
+
+    #[command(flatten)]
+    remote: RemoteArg,
+}
+
+#[derive(Error, Debug)]
+enum Error {
+    #[error("`{0}` is not an atom manifest in the given revision")]
+    NotAnAtom(PathBuf),
+    #[error("The atom declares no trait, which its environment is defined by")]
+    NoTrait,
+    #[error("The `{0}` trait defines no environment, configure one in `develop.environments`")]
+    NoEnvironment(String),
+    #[error("The atom has no `{0}`, which defines the environment of its trait")]
+    MissingEnvironment(String),
+    #[error("No `{0}` command configured")]
+    NoCommand(&'static str),
+    #[error("Instantiating the environment failed: {0}")]
+    Instantiate(String),
+    #[error("The shell exited with {0}")]
+    Shell(std::process::ExitStatus),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::fetch::git::GitFetcher;
+            use atom::store::{NormalizeStorePath, git};
+            use atom::{AtomId, ComputeHash, Lockfile, Manifest, eval};
+
+            let repo = repo.to_thread_local();
+            let manifest = repo.normalize_from(ctx.cwd(), &args.path)?;
+            let commit = repo
+                .rev_parse_single(args.spec.as_str())?
+                .object()?
+                .peel_to_commit()?;
+            let tree = commit.tree()?;
+
+            let mut buf = Vec::new();
+            let not_an_atom = || Error::NotAnAtom(args.path.clone());
+            let spec = tree
+                .lookup_entry_by_path(&manifest, &mut buf)?
+                .filter(|e| e.mode().is_blob())
+                .ok_or_else(not_an_atom)?
+                .object()?;
+            let workspace = git::read_workspace(&repo, commit.id)?.unwrap_or_default();
+            let atom = Manifest::get_member_atom(std::str::from_utf8(&spec.data)?, &workspace)?;
+            let kind = atom.kind.as_ref().ok_or(Error::NoTrait)?.to_string();
+            let entry = ctx
+                .config()
+                .develop()
+                .environment(&kind)
+                .ok_or_else(|| Error::NoEnvironment(kind.clone()))?;
+
+            let content = eval::content_dir(&manifest);
+            let content = tree
+                .lookup_entry_by_path(&content, &mut buf)?
+                .filter(|e| e.mode().is_tree())
+                .ok_or_else(not_an_atom)?
+                .object_id();
+            let lock = tree
+                .lookup_entry_by_path(Lockfile::path(&manifest), &mut buf)?
+                .filter(|e| e.mode().is_blob())
+                .map(|e| e.object())
+                .transpose()?;
+
+            // the directory is reused until either the content or the lock changes
+            let id = AtomId::compute(&commit, atom.id)?;
+            let key = match &lock {
+                Some(lock) => format!("{content}-{}", lock.id),
+                None => content.to_string(),
+            };
+            let dir = ctx
+                .config()
+                .cache_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("develop")
+                .join(id.compute_hash().to_string())
+                .join(atom.version.to_string())
+                .join(key);
+
+            if !dir.exists() {
+                let lock: Lockfile = match &lock {
+                    Some(lock) => std::str::from_utf8(&lock.data)?.parse()?,
+                    None => Lockfile::default(),
+                };
+                git::write_atomically(&dir, |tmp| -> anyhow::Result<()> {
+                    git::materialize(&repo, content, &tmp.join(ATOM_DIR))?;
+                    if lock.atoms.is_empty() {
+                        return Ok(());
+                    }
+
+                    let remote = ctx.remote(&repo, args.remote.as_deref())?;
+                    let fetcher =
+                        GitFetcher::new(repo.find_remote(remote.as_str())?).config(ctx.config());
+                    let deps = tmp.join(DEPS_DIR);
+                    let fetched = fetcher.fetch_lock(&lock, &deps)?;
+                    // the paths are recorded as they will be once renamed
+                    let paths = fetched.iter().map(|atom| {
+                        let name = atom.path.strip_prefix(&deps).unwrap_or(&atom.path);
+                        (&atom.id, dir.join(DEPS_DIR).join(name))
+                    });
+                    super::fetch::write_closure_map(&deps, paths)
+                })?;
+            }
+            tracing::debug!(atom.id = %id.id(), dir = %dir.display(), "Materialized atom");
+
+            let drvs = instantiate(ctx, &dir, entry)?;
+
+            let (program, rest) = ctx
+                .config()
+                .develop()
+                .shell
+                .split_first()
+                .ok_or(Error::NoCommand("develop.shell"))?;
+            let deps = dir.join(DEPS_DIR);
+            let status = Command::new(program)
+                .args(rest)
+                .args(drvs)
+                .current_dir(dir.join(ATOM_DIR))
+                .env("EKA_ATOM_ID", id.id().to_string())
+                .env("EKA_ATOM_VERSION", atom.version.to_string())
+                .env("EKA_ATOM_TRAIT", kind)
+                .envs(deps.is_dir().then_some(("EKA_ATOM_DEPS", deps)))
+                .status()?;
+
+            if !status.success() {
+                return Err(Error::Shell(status).into());
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Instantiate the environment defined by the `entry` of the Atom materialized to `dir`, from
+/// the directory itself, so that its dependencies are within reach, returning the paths of the
+/// resulting derivations.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn instantiate(ctx: &Context, dir: &Path, entry: &str) -> anyhow::Result<Vec<String>> {
+    let entry = Path::new(ATOM_DIR).join(entry);
+    if !dir.join(&entry).is_file() {
+        return Err(Error::MissingEnvironment(entry.display().to_string()).into());
+    }
+
+    let (program, rest) = ctx
+        .config()
+        .develop()
+        .instantiate
+        .split_first()
+        .ok_or(Error::NoCommand("develop.instantiate"))?;
+    let output = Command::new(program)
+        .args(rest)
+        .arg(&entry)
+        .current_dir(dir)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Instantiate(stderr.trim().to_owned()).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(ToOwned::to_owned)
+        .collect())
+}
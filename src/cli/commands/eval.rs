@@ -0,0 +1,142 @@
+//! # Atom Evaluation
+//!
+//! Evaluates an Atom with the configured evaluator, caching the output so that evaluating an
+//! unchanged Atom again, locally or in CI, is only a lookup.
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::store::Detected;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the manifest of the atom to evaluate
+    path: PathBuf,
+
+    /// Evaluate even if a cached output exists, and refresh the cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Also look up and share outputs through the remote store
+    ///
+    /// Defaults to the `eval.share` configuration value.
+    #[arg(long, verbatim_doc_comment)]
+    share: bool,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+#[derive(Error, Debug)]
+enum Error {
+    #[error("No evaluator configured, see the `eval.command` configuration value")]
+    NoEvaluator,
+    #[error("The evaluator failed: {0}")]
+    Failed(String),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::eval::{self, EvalCache, Key};
+            use atom::store::git;
+            use atom::{AtomId, Manifest, WORKSPACE_FILE, Workspace};
+
+            let repo = repo.to_thread_local();
+            let path = ctx.cwd().join(&args.path);
+            // the manifest is evaluated as it is in the worktree, and so is its workspace
+            let workspace = match repo
+                .work_dir()
+                .map(|d| std::fs::read(d.join(WORKSPACE_FILE)))
+            {
+                Some(Ok(data)) => Workspace::from_slice(&data)?,
+                _ => Workspace::default(),
+            };
+            let atom = Manifest::get_member_atom(&std::fs::read_to_string(&path)?, &workspace)?;
+            let dir = eval::content_dir(&path);
+
+            let id = AtomId::compute(&repo.head_commit()?, atom.id)?;
+            let key = Key::new(&id, &atom.version, eval::fingerprint(&dir)?);
+
+            let local = ctx
+                .config()
+                .cache_dir()
+                .map(|d| EvalCache::new(d.join("eval")));
+            let share = args.share || ctx.config().eval().share;
+            // only resolved when sharing, so that local evaluation needs no remote
+            let remote = || ctx.remote(&repo, args.git.remote.as_deref());
+
+            let cached = match (&local, args.no_cache) {
+                (Some(cache), false) => cache.get(&key)?,
+                _ => None,
+            };
+            if let Some(output) = cached {
+                tracing::debug!(atom.id = %id.id(), "Using cached evaluation");
+                io::stdout().write_all(&output)?;
+                return Ok(());
+            }
+
+            let shared = if share && !args.no_cache {
+                git::eval::fetch(&repo.find_remote(remote()?.as_str())?, &key)?
+            } else {
+                None
+            };
+            let output = match shared {
+                Some(output) => output,
+                None => {
+                    let output = evaluate(ctx, &dir)?;
+                    if share {
+                        git::eval::share(&repo, &remote()?, &key, &output)?;
+                    }
+                    output
+                },
+            };
+
+            if let Some(cache) = &local {
+                if let Err(e) = cache.put(&key, &output) {
+                    tracing::warn!(message = "Failed to cache evaluation", error = %e);
+                }
+            }
+
+            io::stdout().write_all(&output)?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Run the configured evaluator on the Atom's content directory, returning its output.
+fn evaluate(ctx: &Context, dir: &std::path::Path) -> anyhow::Result<Vec<u8>> {
+    let Some((program, args)) = ctx.config().eval().command.split_first() else {
+        return Err(Error::NoEvaluator.into());
+    };
+
+    let output = Command::new(program).args(args).arg(dir).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Failed(stderr.trim().to_owned()).into());
+    }
+
+    Ok(output.stdout)
+}
@@ -0,0 +1,100 @@
+//! # Exit Code Explanations
+//!
+//! Explains an exit code of eka, much like `rustc --explain`: what it stands for, its common
+//! causes, and how to remedy them. The explanations are part of the message catalogs compiled
+//! into the binary, so they are available offline, and in the language of the user.
+use std::io::{self, Write};
+
+use clap::Parser;
+
+use crate::cli::context::Context;
+use crate::cli::exit::Status;
+use crate::cli::i18n::message;
+use crate::cli::output::{Cell, Format, Record};
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The exit code to explain, by number or name, e.g. `3` or `conflict`
+    ///
+    /// Lists every exit code, and what it stands for, if none is given.
+    #[arg(value_name = "CODE", verbatim_doc_comment)]
+    code: Option<Status>,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    let Some(status) = args.code else {
+        let mut sink = ctx.sink();
+        for status in Status::ALL {
+            sink.record(&Explained(status));
+        }
+        sink.finish()?;
+        return Ok(());
+    };
+
+    if ctx.format() != Format::Human {
+        let mut sink = ctx.sink();
+        sink.record(&Explained(status));
+        sink.finish()?;
+        return Ok(());
+    }
+
+    let explained = Explained(status);
+    let mut stdout = io::stdout().lock();
+    let code = status as u8;
+    writeln!(stdout, "{code} {}: {}", status.name(), explained.summary())?;
+    let sections = [
+        (msg!("explain-causes"), explained.causes()),
+        (msg!("explain-remedies"), explained.remedies()),
+    ];
+    for (heading, lines) in sections {
+        writeln!(stdout, "\n{heading}:")?;
+        for line in lines {
+            writeln!(stdout, "  - {line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// The explanation of an exit code.
+struct Explained(Status);
+
+impl Explained {
+    fn summary(&self) -> String {
+        message(&format!("explain-{}", self.0.name()), None).into_owned()
+    }
+
+    fn causes(&self) -> Vec<String> {
+        self.lines("causes")
+    }
+
+    fn remedies(&self) -> Vec<String> {
+        self.lines("remedies")
+    }
+
+    /// The lines of the given section of the explanation, each of which is an item of it.
+    fn lines(&self, section: &str) -> Vec<String> {
+        let id = format!("explain-{}-{section}", self.0.name());
+        message(&id, None).lines().map(str::to_owned).collect()
+    }
+}
+
+impl Record for Explained {
+    fn row(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(self.0 as u8),
+            Cell::new(self.0.name()),
+            Cell::new(self.summary()),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "code": self.0 as u8,
+            "name": self.0.name(),
+            "summary": self.summary(),
+            "causes": self.causes(),
+            "remedies": self.remedies(),
+        })
+    }
+}
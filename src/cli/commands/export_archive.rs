@@ -0,0 +1,147 @@
+//! # Archive Export
+//!
+//! Exports the content of a published Atom as a byte-reproducible archive, and reports its
+//! hash, so that the archive can be declared in the Atom's `[artifacts]` and attached to it as
+//! an alternative means of distribution, e.g. for consumers without git.
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::GREEN;
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::{Detected, RemoteArg};
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The atom to export, e.g. `my-atom@^1` or `gh:owner/repo::my-atom@1.2.0`
+    ///
+    /// Without a url, the atom is looked up in the remote store given
+    /// by `--remote`. Without a version, the latest one is exported.
+    #[arg(verbatim_doc_comment)]
+    uri: String,
+
+    /// Write the archive to the given file instead of stdout
+    #[arg(long, short, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// The format of the archive
+    ///
+    /// Archives are written uncompressed, so an `--output` with any
+    /// extension but `.tar` or `.zip`, e.g. `.tar.zst`, needs one.
+    ///
+    /// [default: by the extension of `--output`, or `tar`]
+    #[arg(long, short, verbatim_doc_comment)]
+    format: Option<Format>,
+
+    #[command(flatten)]
+    remote: RemoteArg,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Format {
+    Tar,
+    Zip,
+}
+
+#[derive(Error, Debug)]
+enum Error {
+    #[error("No archive format is known by the extension of `{0}`, see `--format`")]
+    UnknownFormat(PathBuf),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::git::archive;
+            use atom::uri::Uri;
+
+            let repo = repo.to_thread_local();
+            let uri = Uri::parse_with(&args.uri, ctx.config().aliases())?;
+            let fetcher = ctx.fetcher(&repo, &uri, args.remote.as_deref())?;
+            let (version, content) = fetcher.content(&uri)?;
+            let id = uri.id().to_string();
+
+            let format = match args.format {
+                Some(Format::Tar) => archive::Format::Tar,
+                Some(Format::Zip) => archive::Format::Zip,
+                // an extension is never ignored, lest e.g. `.tar.zst` names a plain tar
+                None => match args.output.as_deref() {
+                    Some(path) if path.extension().is_some() => archive::Format::from_path(path)
+                        .ok_or_else(|| Error::UnknownFormat(path.to_owned()))?,
+                    _ => archive::Format::Tar,
+                },
+            };
+            let Some(path) = args.output else {
+                // the results would be mixed into the archive on stdout
+                let digest = archive::export(&repo, content, format, io::stdout().lock())?;
+                io::stdout().flush()?;
+                tracing::info!(
+                    "{}",
+                    msg!("export-archive-digest", digest = digest.to_string())
+                );
+                return Ok(());
+            };
+            let file = io::BufWriter::new(std::fs::File::create(&path)?);
+            let digest = archive::export(&repo, content, format, file)?;
+
+            let mut sink = ctx.sink();
+            sink.record(&Exported {
+                id: &id,
+                version: &version,
+                format,
+                digest: &digest,
+            });
+            sink.finish()?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// An archive exported from the content of a published atom version.
+#[cfg(feature = "git")]
+struct Exported<'a> {
+    id: &'a str,
+    version: &'a semver::Version,
+    format: atom::store::git::archive::Format,
+    digest: &'a atom::Digest,
+}
+
+#[cfg(feature = "git")]
+impl Exported<'_> {
+    fn format(&self) -> &'static str {
+        use atom::store::git::archive::Format;
+        match self.format {
+            Format::Tar => "tar",
+            Format::Zip => "zip",
+        }
+    }
+}
+
+#[cfg(feature = "git")]
+impl Record for Exported<'_> {
+    fn row(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(msg!("status-exported")).color(GREEN),
+            Cell::new(self.id),
+            Cell::new(self.version),
+            Cell::new(self.format()),
+            Cell::new(self.digest),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": "exported",
+            "id": self.id,
+            "version": self.version.to_string(),
+            "format": self.format(),
+            "digest": self.digest.to_string(),
+        })
+    }
+}
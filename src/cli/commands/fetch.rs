@@ -0,0 +1,179 @@
+//! # Atom Fetching
+//!
+//! Retrieves a published Atom by its uri, without cloning the repository it was published from,
+//! and checks its content out to a directory, e.g. to inspect or build a dependency in isolation.
+//!
+//! With `--recursive`, the Atom is fetched along with its entire locked closure, each Atom to a
+//! directory of its own, named after its id and version, and the path of each is written, by
+//! id, to a JSON map at the root of the layout, for build tooling to consume in one go. Unless
+//! `fetch.shared` is disabled, each of those directories is a link to a single checkout of the
+//! Atom, shared by every project on the machine, which is verified each time it is reused.
+use std::path::PathBuf;
+
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::GREEN;
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::{Detected, RemoteArg};
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The atom to fetch, e.g. `my-atom@^1` or `gh:owner/repo::my-atom@1.2.0`
+    ///
+    /// Without a url, the atom is looked up in the remote store given
+    /// by `--remote`. Without a version, the latest one is fetched.
+    #[arg(verbatim_doc_comment)]
+    uri: String,
+
+    /// The directory to check the atom out to, which must be empty
+    ///
+    /// [default: a directory named after the atom's id]
+    #[arg(verbatim_doc_comment)]
+    dest: Option<PathBuf>,
+
+    #[command(flatten)]
+    remote: RemoteArg,
+
+    /// Also fetch every atom pinned by the lock the atom was published with
+    ///
+    /// Each atom is checked out to `<DEST>/<id>@<version>`, and the path of
+    /// each is written, by id, to `<DEST>/closure.json`. Unless disabled
+    /// by `fetch.shared`, each directory links to a checkout shared by
+    /// every project, in `fetch.shared-path`.
+    #[arg(long, short, verbatim_doc_comment)]
+    recursive: bool,
+}
+
+/// The file the paths of the Atoms of a recursive fetch are written to, by id.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+const CLOSURE_MAP: &str = "closure.json";
+
+#[derive(Error, Debug)]
+#[cfg_attr(not(feature = "s3"), allow(dead_code))]
+enum Error {
+    #[error("Recursive fetches are only supported from a Git store")]
+    RecursiveObjectStore,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    // no repository is needed to fetch from an object store
+    #[cfg(feature = "s3")]
+    if let Some(store) = ctx.object_store() {
+        if args.recursive {
+            return Err(Error::RecursiveObjectStore.into());
+        }
+        let uri = atom::uri::Uri::parse_with(&args.uri, ctx.config().aliases())?;
+        let fetcher = atom::fetch::s3::S3Fetcher::new(store);
+        return checkout(ctx, &fetcher, &uri, args.dest);
+    }
+
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::uri::Uri;
+
+            let repo = repo.to_thread_local();
+            let uri = Uri::parse_with(&args.uri, ctx.config().aliases())?;
+            let fetcher = ctx.fetcher(&repo, &uri, args.remote.as_deref())?;
+            if args.recursive {
+                let dest = args
+                    .dest
+                    .unwrap_or_else(|| ctx.cwd().join(uri.id().to_string()));
+                closure(ctx, &fetcher, &uri, &dest)?;
+            } else {
+                checkout(ctx, &fetcher, &uri, args.dest)?;
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Check the atom requested by `uri` out to `dest`, or a directory named after its id, and
+/// record it.
+#[cfg(feature = "git")]
+fn checkout<F>(
+    ctx: &Context,
+    fetcher: &F,
+    uri: &atom::uri::Uri,
+    dest: Option<PathBuf>,
+) -> anyhow::Result<()>
+where
+    F: atom::fetch::Fetch,
+    F::Error: std::error::Error + Send + Sync + 'static,
+{
+    let dest = dest.unwrap_or_else(|| ctx.cwd().join(uri.id().to_string()));
+    let fetched = fetcher.fetch(uri, &dest)?;
+
+    let mut sink = ctx.sink();
+    sink.record(&Fetched(&fetched));
+    sink.finish()?;
+    Ok(())
+}
+
+/// Check the atom requested by `uri` out to `dest`, along with its locked closure, write the
+/// map of their paths, and record each.
+#[cfg(feature = "git")]
+fn closure(
+    ctx: &Context,
+    fetcher: &atom::fetch::git::GitFetcher,
+    uri: &atom::uri::Uri,
+    dest: &std::path::Path,
+) -> anyhow::Result<()> {
+    let fetched = fetcher.fetch_closure(uri, dest)?;
+    write_closure_map(
+        dest,
+        fetched.iter().map(|atom| (&atom.id, atom.path.clone())),
+    )?;
+
+    let mut sink = ctx.sink();
+    for atom in &fetched {
+        sink.record(&Fetched(atom));
+    }
+    sink.finish()?;
+    Ok(())
+}
+
+/// Write the paths of the given atoms, by id, to the closure map in `dir`.
+#[cfg(feature = "git")]
+pub(super) fn write_closure_map<'a>(
+    dir: &std::path::Path,
+    atoms: impl IntoIterator<Item = (&'a atom::Id, PathBuf)>,
+) -> anyhow::Result<()> {
+    let map: serde_json::Map<_, _> = atoms
+        .into_iter()
+        .map(|(id, path)| (id.to_string(), path.display().to_string().into()))
+        .collect();
+    let map = serde_json::to_string_pretty(&map)? + "\n";
+    std::fs::write(dir.join(CLOSURE_MAP), map)?;
+    Ok(())
+}
+
+/// An atom version checked out from the store.
+#[cfg(feature = "git")]
+struct Fetched<'a>(&'a atom::fetch::Fetched);
+
+#[cfg(feature = "git")]
+impl Record for Fetched<'_> {
+    fn row(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(msg!("status-fetched")).color(GREEN),
+            Cell::new(&self.0.id),
+            Cell::new(&self.0.version),
+            Cell::new(self.0.path.display()),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": "fetched",
+            "id": self.0.id.to_string(),
+            "version": self.0.version.to_string(),
+            "content": self.0.content.to_string(),
+            "path": self.0.path,
+        })
+    }
+}
@@ -0,0 +1,169 @@
+//! # Garbage Collection
+//!
+//! Prunes the refs pinning the source commit of each published Atom version, according to a
+//! retention policy, so that the history they keep alive can be reclaimed by `git gc`. As the
+//! affected versions can no longer be verified against their source, pruning is explicit, and
+//! confirmed before anything is deleted.
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Prune the source refs of published atom versions
+    ///
+    /// Only the refs pinning the commit each version was published from
+    /// are pruned, never the content or spec of a version, so published
+    /// atoms remain resolvable, but can no longer be verified against
+    /// their source once `git gc` reclaims it.
+    #[arg(long, required = true, verbatim_doc_comment)]
+    sources: bool,
+
+    /// Retain the sources of this many of the latest versions of each atom
+    ///
+    /// Defaults to the `gc.keep-latest` configuration value.
+    #[arg(long, value_name = "N", verbatim_doc_comment)]
+    keep_latest: Option<usize>,
+
+    /// Retain the sources of versions whose source commit is younger
+    /// than this many days
+    ///
+    /// Defaults to the `gc.keep-days` configuration value. With neither
+    /// rule set, every source is retained; with both, a source is
+    /// retained as long as either rule retains it.
+    #[arg(long, value_name = "DAYS", verbatim_doc_comment)]
+    keep_days: Option<u64>,
+
+    /// Only report which sources would be pruned, without deleting any refs
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Prune without asking for confirmation
+    ///
+    /// Required when not attached to a terminal.
+    #[arg(long, short = 'y', verbatim_doc_comment)]
+    yes: bool,
+}
+
+#[derive(Error, Debug)]
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+pub(super) enum Error {
+    #[error("Refusing to prune sources without confirmation, pass `--yes` to proceed")]
+    Unconfirmed,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+            use atom::store::git::gc::{self, Retention};
+
+            const DAY: u64 = 24 * 60 * 60;
+
+            let config = ctx.config().gc();
+            let retention = Retention {
+                latest: args.keep_latest.or(config.keep_latest),
+                max_age: args
+                    .keep_days
+                    .or(config.keep_days)
+                    .map(|days| Duration::from_secs(days.saturating_mul(DAY))),
+            };
+            if retention.keeps_all() {
+                tracing::info!("{}", msg!("gc-keep-all"));
+                return Ok(());
+            }
+
+            let repo = repo.to_thread_local();
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX));
+            let prunable = gc::prunable(gc::sources(&repo)?, retention, now);
+            if prunable.is_empty() {
+                return Ok(());
+            }
+
+            tracing::warn!("{}", msg!("gc-warning", count = prunable.len()));
+            if !args.dry_run && !args.yes && !confirm(ctx)? {
+                tracing::warn!("{}", msg!("gc-cancelled"));
+                return Ok(());
+            }
+            if !args.dry_run {
+                gc::prune(&repo, &prunable)?;
+            }
+
+            let mut sink = ctx.sink();
+            for source in &prunable {
+                sink.record(&Pruned {
+                    source,
+                    dry_run: args.dry_run,
+                });
+            }
+            sink.finish()?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Ask the user to confirm pruning, refusing when no user can answer.
+#[cfg(feature = "git")]
+fn confirm(ctx: &Context) -> anyhow::Result<bool> {
+    use std::io::{self, BufRead, Write};
+
+    if !ctx.output().interactive() {
+        return Err(Error::Unconfirmed.into());
+    }
+
+    let mut stderr = io::stderr().lock();
+    write!(stderr, "{} ", msg!("gc-confirm"))?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    let answer = answer.trim().to_lowercase();
+    let yes = msg!("publish-confirm-yes");
+
+    Ok(answer == "y" || answer == "yes" || (!answer.is_empty() && yes.starts_with(&answer)))
+}
+
+/// The source of a published atom version, pruned or planned to be.
+#[cfg(feature = "git")]
+struct Pruned<'a> {
+    source: &'a atom::store::git::gc::Source,
+    dry_run: bool,
+}
+
+#[cfg(feature = "git")]
+impl Record for Pruned<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let (status, color) = if self.dry_run {
+            (msg!("status-planned"), YELLOW)
+        } else {
+            (msg!("status-pruned"), RED)
+        };
+        vec![
+            Cell::new(status).color(color),
+            Cell::new(&self.source.id),
+            Cell::new(&self.source.version),
+            Cell::new(&self.source.origin),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": if self.dry_run { "planned" } else { "pruned" },
+            "id": self.source.id.to_string(),
+            "version": self.source.version.to_string(),
+            "ref": self.source.name,
+            "origin": self.source.origin.to_string(),
+        })
+    }
+}
@@ -0,0 +1,388 @@
+//! # Dependency Graphs
+//!
+//! Builds the graph of the dependencies among all the Atoms of the repository, by path, or by
+//! id on the Atoms of their own store, failing if they form a cycle. Also exports the
+//! dependency graph recorded in an Atom's lock file, both in the DOT language or the JSON
+//! Graph Format, so that it can be visualized and diffed, e.g. as an artifact of CI, and
+//! reports how the Atoms pinned by two lock files differ.
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use atom::{AtomGraph, Change, ChangeKind, Lockfile};
+use clap::{Parser, Subcommand, ValueEnum};
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, MAGENTA, RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Option<GraphCommands>,
+
+    /// The format to emit the graph of the repository's atoms in
+    #[arg(long, short, value_enum, default_value_t)]
+    emit: Emit,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+        /// The revision to build the graph as of
+        #[arg(long, short, default_value = "HEAD", name = "REVSPEC")]
+        pub(super) spec: String,
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum GraphCommands {
+    /// Export the dependency graph recorded in a lock file.
+    ///
+    /// Each atom is a node, carrying its locked version and the store it
+    /// was resolved from, and the locking atom itself is the root.
+    #[command(verbatim_doc_comment)]
+    Export {
+        /// Path to the lock file
+        lock: PathBuf,
+        /// The format to export the graph in
+        #[arg(long, short, value_enum, default_value_t)]
+        emit: Emit,
+    },
+    /// Report the atoms added, removed or changed between two lock files.
+    ///
+    /// Changes to the direct dependencies of the locking atom are listed
+    /// first, followed by those to its transitive dependencies.
+    #[command(verbatim_doc_comment)]
+    Diff {
+        /// Path to the old lock file
+        old: PathBuf,
+        /// Path to the new lock file
+        new: PathBuf,
+    },
+}
+
+/// The formats a graph can be exported in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+enum Emit {
+    /// The DOT language of Graphviz.
+    #[default]
+    Dot,
+    /// The JSON Graph Format.
+    Json,
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("`{0}` depends on `{1}`, which is not pinned by the lock")]
+    Dangling(String, String),
+    #[error("The atoms depend on one another in a cycle: {0}")]
+    Cycle(String),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    let Some(command) = args.command else {
+        return repo(ctx, args);
+    };
+    match command {
+        GraphCommands::Export { lock, emit } => {
+            let (root, lock) = read(&ctx.cwd().join(lock))?;
+            check(&root, &lock)?;
+            let graph = match emit {
+                Emit::Dot => dot(&root, &lock),
+                Emit::Json => serde_json::to_string_pretty(&json(&root, &lock))? + "\n",
+            };
+            io::stdout().write_all(graph.as_bytes())?;
+        },
+        GraphCommands::Diff { old, new } => {
+            let (_, old) = read(&ctx.cwd().join(old))?;
+            let (_, new) = read(&ctx.cwd().join(new))?;
+
+            let mut sink = ctx.sink();
+            for change in old.diff(&new) {
+                sink.record(&Diffed(change));
+            }
+            sink.finish()?;
+        },
+    }
+    Ok(())
+}
+
+/// Build the graph of the dependencies among the Atoms of the repository, and emit it.
+fn repo(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::publish::Builder;
+            use atom::publish::error::git::Error as GitError;
+            use atom::publish::git::GitPublisher;
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            let (atoms, publisher) = GitPublisher::new(&repo, &remote, &args.git.spec)?
+                .lexical(true)
+                .build()
+                .inspect_err(super::publish::report)?;
+            publisher
+                .take_warnings()
+                .iter()
+                .for_each(super::publish::warn);
+            if atoms.is_empty() {
+                return Err(GitError::NotFound.into());
+            }
+
+            let graph = publisher.graph(&atoms)?;
+            for missing in graph.missing() {
+                let path = missing
+                    .path
+                    .as_deref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default();
+                tracing::warn!(
+                    "{}",
+                    msg!(
+                        "graph-missing",
+                        id = missing.from.as_str(),
+                        name = missing.name.as_str(),
+                        path = path
+                    )
+                );
+            }
+            let cycles: Vec<_> = graph
+                .cycles()
+                .iter()
+                .map(|cycle| {
+                    let ids: Vec<_> = cycle.iter().map(|id| id.as_str()).collect();
+                    format!("[{}]", ids.join(", "))
+                })
+                .collect();
+            if !cycles.is_empty() {
+                return Err(Error::Cycle(cycles.join(", ")).into());
+            }
+
+            let out = match args.emit {
+                Emit::Dot => repo_dot(&graph),
+                Emit::Json => serde_json::to_string_pretty(&repo_json(&graph))? + "\n",
+            };
+            io::stdout().write_all(out.as_bytes())?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Render the graph of the Atoms of the repository in the DOT language.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn repo_dot(graph: &AtomGraph) -> String {
+    // ids are valid identifiers, which DOT requires no escaping of
+    let mut out = String::from("digraph {\n");
+    for (id, node) in graph.nodes() {
+        let _ = writeln!(out, "    \"{id}\" [label=\"{id}\\n{}\"];", node.version);
+    }
+    for (from, to) in graph.edges() {
+        let _ = writeln!(out, "    \"{from}\" -> \"{to}\";");
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Render the graph of the Atoms of the repository in the JSON Graph Format.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn repo_json(graph: &AtomGraph) -> serde_json::Value {
+    use serde_json::{Map, Value, json};
+
+    let nodes: Map<String, Value> = graph
+        .nodes()
+        .iter()
+        .map(|(id, node)| {
+            let node = json!({
+                "label": id.as_str(),
+                "metadata": {
+                    "version": node.version.to_string(),
+                    "path": node.path.display().to_string(),
+                },
+            });
+            (id.to_string(), node)
+        })
+        .collect();
+    let edges: Vec<Value> = graph
+        .edges()
+        .map(|(source, target)| json!({ "source": source.as_str(), "target": target.as_str() }))
+        .collect();
+
+    json!({
+        "graph": {
+            "directed": true,
+            "nodes": nodes,
+            "edges": edges,
+        }
+    })
+}
+
+/// Read the lock file at `path`, along with the name of the Atom it locks.
+fn read(path: &Path) -> anyhow::Result<(String, Lockfile)> {
+    let root = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let lock = std::fs::read_to_string(path)?.parse()?;
+    Ok((root, lock))
+}
+
+/// Each dependency in the lock, as the id of the dependent and that of its dependency, with the
+/// locking Atom named `root`.
+fn edges<'a>(root: &'a str, lock: &'a Lockfile) -> impl Iterator<Item = (&'a str, &'a str)> {
+    let direct = lock.deps.iter().map(move |dep| (root, dep.as_str()));
+    let transitive = lock.atoms.iter().flat_map(|atom| {
+        atom.deps
+            .iter()
+            .map(move |dep| (atom.id.as_str(), dep.as_str()))
+    });
+    direct.chain(transitive)
+}
+
+/// Ensure every dependency in the lock refers to an Atom it pins.
+fn check(root: &str, lock: &Lockfile) -> Result<(), Error> {
+    for (from, dep) in edges(root, lock) {
+        if lock.get(dep).is_none() {
+            return Err(Error::Dangling(from.to_owned(), dep.to_owned()));
+        }
+    }
+    Ok(())
+}
+
+/// Render the graph of the lock in the DOT language.
+fn dot(root: &str, lock: &Lockfile) -> String {
+    // ids are valid identifiers, but urls may contain characters DOT requires escaped
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let quote = |s: &str| format!("\"{}\"", escape(s));
+
+    let mut out = format!("digraph {} {{\n", quote(root));
+    let _ = writeln!(out, "    {} [shape=box];", quote(root));
+    for atom in &lock.atoms {
+        let mut label = vec![escape(&atom.id), atom.version.to_string()];
+        label.extend(atom.store.as_deref().map(escape));
+        let label = label.join("\\n");
+        let _ = writeln!(out, "    {} [label=\"{label}\"];", quote(&atom.id));
+    }
+    for (from, to) in edges(root, lock) {
+        let _ = writeln!(out, "    {} -> {};", quote(from), quote(to));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Render the graph of the lock in the JSON Graph Format.
+fn json(root: &str, lock: &Lockfile) -> serde_json::Value {
+    use serde_json::{Map, Value, json};
+
+    let mut nodes = Map::new();
+    nodes.insert(
+        root.to_owned(),
+        json!({ "label": root, "metadata": { "root": true } }),
+    );
+    for atom in &lock.atoms {
+        nodes.insert(
+            atom.id.to_string(),
+            json!({
+                "label": atom.id.as_str(),
+                "metadata": {
+                    "version": atom.version.to_string(),
+                    "rev": atom.rev,
+                    "store": atom.store,
+                    "root": atom.root,
+                },
+            }),
+        );
+    }
+
+    let edges: Vec<Value> = edges(root, lock)
+        .map(|(source, target)| json!({ "source": source, "target": target }))
+        .collect();
+
+    json!({
+        "graph": {
+            "directed": true,
+            "label": root,
+            "nodes": nodes,
+            "edges": edges,
+        }
+    })
+}
+
+/// A difference between two lock files.
+pub(super) struct Diffed<'a>(pub(super) Change<'a>);
+
+impl Record for Diffed<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let Diffed(change) = self;
+        let (status, color) = match change.kind {
+            ChangeKind::Added => (msg!("status-added"), GREEN),
+            ChangeKind::Removed => (msg!("status-removed"), RED),
+            ChangeKind::Upgraded => (msg!("status-upgraded"), GREEN),
+            ChangeKind::Downgraded => (msg!("status-downgraded"), YELLOW),
+            ChangeKind::Repinned => (msg!("status-repinned"), MAGENTA),
+        };
+        let dependency = if change.direct {
+            msg!("graph-direct")
+        } else {
+            msg!("graph-transitive")
+        };
+        let versions = match (change.old, change.new) {
+            (Some(old), Some(new)) => format!("{} -> {}", old.version, new.version),
+            (Some(atom), None) | (None, Some(atom)) => atom.version.to_string(),
+            (None, None) => String::new(),
+        };
+        vec![
+            Cell::new(status).color(color),
+            Cell::new(dependency),
+            Cell::new(change.id),
+            Cell::new(versions),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let Diffed(change) = self;
+        let status = match change.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Upgraded => "upgraded",
+            ChangeKind::Downgraded => "downgraded",
+            ChangeKind::Repinned => "repinned",
+        };
+        let pin = |atom: Option<&atom::LockedAtom>| {
+            atom.map(|atom| {
+                serde_json::json!({
+                    "version": atom.version.to_string(),
+                    "rev": atom.rev,
+                    "store": atom.store,
+                    "root": atom.root,
+                })
+            })
+        };
+        serde_json::json!({
+            "status": status,
+            "id": change.id,
+            "direct": change.direct,
+            "old": pin(change.old),
+            "new": pin(change.new),
+        })
+    }
+}
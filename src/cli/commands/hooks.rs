@@ -0,0 +1,208 @@
+//! # Server-Side Hooks
+//!
+//! Generates git hooks which let a store enforce the Atom format and its own policy at push
+//! time, and implements the check those hooks delegate to.
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::RED;
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+/// The variable the generated hooks pass the identity of the pusher through.
+const PUSHER: &str = "EKA_PUSHER";
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: HookCommands,
+}
+
+#[derive(Subcommand, Debug)]
+enum HookCommands {
+    /// Write a hook script enforcing the store's invariants.
+    ///
+    /// Install the script in the hooks directory of the repository
+    /// serving as the store, e.g. `hooks/pre-receive`, to have every
+    /// incoming Atom ref checked by `eka hooks check`.
+    #[command(verbatim_doc_comment)]
+    Generate(GenerateArgs),
+    /// Verify incoming ref updates, as invoked by a generated hook.
+    ///
+    /// Checks the format version and the reproducible fields of Atom
+    /// commits, that their sources share the root of the store, that
+    /// their manifests match their refs, that attached artifacts match
+    /// the hashes their manifests declare, that they follow the rules of
+    /// the `ekala-policy.toml` at the store's `HEAD`, if any, that the
+    /// store's policy permits the pusher to publish them, and that their
+    /// commits are signed by one of the keys it binds their versions to,
+    /// if any. Published Atoms may never be moved or deleted.
+    ///
+    /// Outside of `refs/atoms`, only the owners of the store's policy may
+    /// update `refs/ekala/policy`, the root tag of the store, or the
+    /// `ekala-policy.toml` on the branch at `HEAD`. Evaluation outputs
+    /// under `refs/ekala/eval` are shared once, for Atoms published to the
+    /// store, by those permitted to publish them, and only removed by the
+    /// owners. Other refs are not checked.
+    #[command(verbatim_doc_comment)]
+    Check(CheckArgs),
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+enum HookKind {
+    /// Checks every ref of a push at once, rejecting it as a whole
+    #[default]
+    PreReceive,
+    /// Checks each ref separately, rejecting only the offending ones
+    Update,
+}
+
+#[derive(Parser, Debug)]
+struct GenerateArgs {
+    /// The kind of hook to generate
+    #[arg(long, short, value_enum, default_value_t)]
+    kind: HookKind,
+
+    /// The environment variable holding the authenticated pusher
+    ///
+    /// Its value is matched against the identities of the store's
+    /// policy. Which variable is set depends on how the store is
+    /// served, e.g. `REMOTE_USER` for `git http-backend`.
+    #[arg(long, default_value = "REMOTE_USER", verbatim_doc_comment)]
+    pusher_var: String,
+
+    /// The eka executable the hook invokes
+    #[arg(long, default_value = "eka")]
+    program: String,
+
+    /// Write the hook to the given file and make it executable,
+    /// instead of printing it
+    #[arg(long, short, value_name = "PATH", verbatim_doc_comment)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct CheckArgs {
+    /// A single update to check, as given to an `update` hook
+    ///
+    /// Without it, updates are read from standard input, one
+    /// `<old> <new> <ref>` line each, as given to a `pre-receive` hook.
+    #[arg(num_args = 3, value_names = ["REF", "OLD", "NEW"], verbatim_doc_comment)]
+    update: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("{0} ref update(s) rejected")]
+    Rejected(usize),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match args.command {
+        HookCommands::Generate(args) => generate(args)?,
+        HookCommands::Check(args) => check(ctx, args)?,
+    }
+    Ok(())
+}
+
+fn generate(args: GenerateArgs) -> io::Result<()> {
+    let GenerateArgs {
+        kind,
+        pusher_var,
+        program,
+        output,
+    } = args;
+
+    let forward = match kind {
+        HookKind::PreReceive => "",
+        HookKind::Update => r#" "$1" "$2" "$3""#,
+    };
+    let script = format!(
+        "#!/bin/sh\n# Generated by `eka hooks generate`: rejects malformed or unauthorized Atom \
+         refs.\n{PUSHER}=\"${{{pusher_var}}}\" exec {program} hooks check{forward}\n"
+    );
+
+    let Some(path) = output else {
+        return io::stdout().write_all(script.as_bytes());
+    };
+
+    std::fs::write(&path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    tracing::info!(path = %path.display(), "Wrote hook");
+    Ok(())
+}
+
+fn check(ctx: &Context, args: CheckArgs) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::git::verify::{RefUpdate, Verifier};
+
+            let repo = repo.to_thread_local();
+            let pusher = std::env::var(PUSHER).ok().filter(|p| !p.is_empty());
+            let verifier = Verifier::new(&repo, pusher)?;
+
+            let updates: Vec<RefUpdate> = match args.update.as_slice() {
+                [name, old, new] => vec![format!("{old} {new} {name}").parse()?],
+                _ => io::stdin()
+                    .lock()
+                    .lines()
+                    .map(|line| Ok(line?.parse::<RefUpdate>()?))
+                    .collect::<anyhow::Result<_>>()?,
+            };
+
+            let mut sink = ctx.sink();
+            let mut rejected = 0;
+            for update in &updates {
+                if let Err(e) = verifier.verify(update) {
+                    rejected += 1;
+                    sink.record(&Rejected {
+                        name: &update.name,
+                        reason: &e,
+                    });
+                }
+            }
+            sink.finish()?;
+
+            if rejected > 0 {
+                return Err(Error::Rejected(rejected).into());
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// An incoming ref update which the store refused.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+struct Rejected<'a> {
+    name: &'a str,
+    reason: &'a dyn std::error::Error,
+}
+
+impl Record for Rejected<'_> {
+    fn row(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(msg!("status-rejected")).color(RED),
+            Cell::new(self.name),
+            Cell::new(self.reason),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": "rejected",
+            "ref": self.name,
+            "reason": self.reason.to_string(),
+        })
+    }
+}
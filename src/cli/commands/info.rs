@@ -0,0 +1,156 @@
+//! # Atom Info
+//!
+//! Describes a published atom by its uri, without checking it out: the fields of its manifest,
+//! read from its spec, the commit it originates from, the format it was published in, and the
+//! full name of each of its refs, so an atom can be inspected before depending on it. With
+//! `--versions`, every version published under its id is listed instead, including those
+//! yanked. An atom the store redirects to the id it was renamed to is described by its new id.
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::YELLOW;
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::{Detected, RemoteArg};
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The atom to describe, e.g. `my-atom@^1` or `gh:owner/repo::my-atom@1.2.0`
+    ///
+    /// Without a url, the atom is looked up in the remote store given
+    /// by `--remote`. Without a version, the latest one is described.
+    #[arg(verbatim_doc_comment)]
+    uri: String,
+
+    /// List every version of the atom satisfying the uri, including
+    /// those yanked, rather than describing the greatest one
+    #[arg(long, verbatim_doc_comment)]
+    versions: bool,
+
+    #[command(flatten)]
+    remote: RemoteArg,
+}
+
+#[derive(Error, Debug)]
+#[cfg_attr(not(feature = "s3"), allow(dead_code))]
+enum Error {
+    #[error("Atoms can only be described from a Git store")]
+    ObjectStore,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    #[cfg(feature = "s3")]
+    if ctx.object_store().is_some() {
+        return Err(Error::ObjectStore.into());
+    }
+
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::uri::Uri;
+
+            let repo = repo.to_thread_local();
+            let uri = Uri::parse_with(&args.uri, ctx.config().aliases())?;
+            let fetcher = ctx.fetcher(&repo, &uri, args.remote.as_deref())?;
+            let mut sink = ctx.sink();
+            if args.versions {
+                let (id, listed) = fetcher.versions(&uri)?;
+                if listed.is_empty() {
+                    tracing::info!(%id, "No version of the atom is published to the store");
+                }
+                for listed in &listed {
+                    sink.record(&Version { id: &id, listed });
+                }
+            } else {
+                let (id, info) = fetcher.describe(&uri)?;
+                for field in fields(&id, &info) {
+                    sink.record(&field);
+                }
+            }
+            sink.finish()?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// The fields describing the published atom `id`, in the order they are printed.
+#[cfg(feature = "git")]
+fn fields(id: &atom::Id, info: &atom::store::git::info::Info) -> Vec<Field> {
+    let atom = &info.manifest().atom;
+    let mut fields = vec![Field::new("id", id), Field::new("version", info.version())];
+    if let Some(description) = &atom.description {
+        fields.push(Field::new("description", description));
+    }
+    if let Some(license) = &atom.license {
+        fields.push(Field::new("license", license));
+    }
+    fields.push(Field::new("commit", info.commit()));
+    if let Some(origin) = info.origin() {
+        fields.push(Field::new("origin", origin));
+    }
+    if let Some(format) = info.format() {
+        fields.push(Field::new("format", format));
+    }
+    fields.extend(info.refs().iter().map(|(name, _)| Field::new("ref", name)));
+    fields
+}
+
+/// A field describing a published atom.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+struct Field {
+    key: &'static str,
+    value: String,
+}
+
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+impl Field {
+    fn new(key: &'static str, value: impl std::fmt::Display) -> Self {
+        Field {
+            key,
+            value: value.to_string(),
+        }
+    }
+}
+
+impl Record for Field {
+    fn row(&self) -> Vec<Cell> {
+        vec![Cell::new(self.key), Cell::new(&self.value)]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "field": self.key, "value": self.value })
+    }
+}
+
+/// A version of an atom published to the store.
+#[cfg(feature = "git")]
+struct Version<'a> {
+    id: &'a atom::Id,
+    listed: &'a atom::store::git::info::Listed,
+}
+
+#[cfg(feature = "git")]
+impl Record for Version<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let mut row = vec![
+            Cell::new(self.id),
+            Cell::new(&self.listed.version),
+            Cell::new(self.listed.commit),
+        ];
+        if self.listed.yanked {
+            row.push(Cell::new(msg!("status-yanked")).color(YELLOW));
+        }
+        row
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id.to_string(),
+            "version": self.listed.version.to_string(),
+            "commit": self.listed.commit.to_string(),
+            "yanked": self.listed.yanked,
+        })
+    }
+}
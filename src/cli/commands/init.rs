@@ -1,10 +1,22 @@
+use atom::store::EkalaStore;
 use clap::Parser;
+use thiserror::Error;
 
-use crate::cli::store::Detected;
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::msg;
 
 #[derive(Parser, Debug)]
 #[group(id = "init_args")]
 pub struct Args {
+    /// Only report whether the store is initialized, without modifying it
+    ///
+    /// Fails if the store is not initialized, or is initialized with a root
+    /// other than that of the local history.
+    #[arg(long, verbatim_doc_comment)]
+    check: bool,
+
     #[command(flatten)]
     #[cfg(feature = "git")]
     git: git::Args,
@@ -12,28 +24,139 @@ pub struct Args {
 
 #[cfg(feature = "git")]
 mod git {
-    use atom::store::git;
     use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
     #[derive(Parser, Debug)]
     #[command(next_help_heading = "Git Options")]
     #[group(id = "git_args")]
     pub(super) struct Args {
-        /// The target remote to initialize
-        #[arg(long, short = 't', default_value_t = git::default_remote().to_owned(), name = "TARGET")]
-        pub(super) remote: String,
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
     }
 }
 
-pub(super) fn run(store: Detected, args: Args) -> anyhow::Result<()> {
-    match store {
-        #[cfg(feature = "git")]
-        Detected::Git(repo) => {
-            use atom::store::Init;
-            let repo = repo.to_thread_local();
-            let remote = repo.find_remote(args.git.remote.as_str())?;
-            remote.ekala_init()?
-        },
-        _ => {},
+#[derive(Error, Debug)]
+enum Error {
+    #[error("The store `{0}` is not initialized, see `eka init`")]
+    Uninitialized(String),
+    #[error("The store `{0}` is initialized with a root the local history does not share")]
+    Mismatch(String),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    #[cfg(feature = "git")]
+    let remote = args.git.remote.as_deref();
+    #[cfg(not(feature = "git"))]
+    let remote = None;
+
+    let store = ctx.ekala_store(remote)?;
+    init(ctx, store.as_ref(), args.check)
+}
+
+/// Initialize the store, or only report whether it is initialized, when checking.
+fn init(ctx: &Context, store: &dyn EkalaStore, check: bool) -> anyhow::Result<()> {
+    let remote = store.name();
+    let root_ref = store.root_ref();
+    let mut sink = ctx.sink();
+    if check {
+        let status = store.status().map_err(|e| anyhow::anyhow!(e))?;
+        sink.record(&Checked {
+            remote,
+            root_ref,
+            initialized: status.remote.clone(),
+            head: status.head.clone(),
+            local: status.local.clone(),
+        });
+        sink.finish()?;
+
+        if !status.is_initialized() {
+            return Err(Error::Uninitialized(remote.to_owned()).into());
+        }
+        if !status.matches() {
+            return Err(Error::Mismatch(remote.to_owned()).into());
+        }
+        return Ok(());
     }
+
+    store.init().map_err(|e| anyhow::anyhow!(e))?;
+    sink.record(&Initialized { remote });
+    sink.finish()?;
     Ok(())
 }
+
+/// A remote which was initialized as an Ekala store.
+struct Initialized<'a> {
+    remote: &'a str,
+}
+
+impl Record for Initialized<'_> {
+    fn row(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(msg!("status-initialized")).color(GREEN),
+            Cell::new(self.remote),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "status": "initialized", "remote": self.remote })
+    }
+}
+
+/// The initialization state of a remote, as reported by `--check`.
+struct Checked<'a> {
+    remote: &'a str,
+    /// The ref recording the root of the store.
+    root_ref: &'a str,
+    /// The root the remote is initialized with, if it is.
+    initialized: Option<String>,
+    /// The root of the remote's history, which initializing it would record.
+    head: String,
+    /// The root of the local history.
+    local: String,
+}
+
+impl Record for Checked<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let (status, color, detail) = match &self.initialized {
+            Some(root) if *root == self.local => (msg!("status-initialized"), GREEN, root.clone()),
+            Some(root) => (
+                msg!("status-mismatched"),
+                RED,
+                format!("{root} ≠ {}", self.local),
+            ),
+            None => (
+                msg!("status-uninitialized"),
+                YELLOW,
+                format!("+ {} → {}", self.root_ref, self.head),
+            ),
+        };
+        vec![
+            Cell::new(status).color(color),
+            Cell::new(self.remote),
+            Cell::new(detail),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let status = match &self.initialized {
+            Some(root) if *root == self.local => "initialized",
+            Some(_) => "mismatched",
+            None => "uninitialized",
+        };
+        let mut json = serde_json::json!({
+            "status": status,
+            "remote": self.remote,
+            "root": self.initialized,
+            "local": self.local,
+        });
+        if self.initialized.is_none() {
+            json["would_create"] = serde_json::json!({
+                "ref": self.root_ref,
+                "target": self.head,
+            });
+        }
+        json
+    }
+}
@@ -0,0 +1,63 @@
+//! # Atom Listing
+//!
+//! Enumerates the Atoms published to a store, with each of their published versions, from the
+//! `refs/atoms/` namespace of the store, without fetching any of them.
+use std::collections::BTreeSet;
+
+use clap::Parser;
+use semver::Version;
+
+use crate::cli::context::Context;
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::RemoteArg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(flatten)]
+    remote: RemoteArg,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    let store = ctx.ekala_store(args.remote.as_deref())?;
+    let atoms = store.published().map_err(|e| anyhow::anyhow!(e))?;
+    if atoms.is_empty() {
+        tracing::info!(store = store.name(), "No atoms are published to the store");
+    }
+
+    let mut sink = ctx.sink();
+    for (id, versions) in atoms {
+        sink.record(&Listed {
+            id: id.to_string(),
+            versions,
+        });
+    }
+    sink.finish()?;
+    Ok(())
+}
+
+/// An Atom published to the store, with its published versions.
+struct Listed {
+    id: String,
+    versions: BTreeSet<Version>,
+}
+
+impl Record for Listed {
+    fn row(&self) -> Vec<Cell> {
+        let versions: Vec<_> = self.versions.iter().map(ToString::to_string).collect();
+        let latest = self.versions.last().map(ToString::to_string);
+        vec![
+            Cell::new(&self.id),
+            Cell::new(latest.unwrap_or_default()),
+            Cell::new(versions.join(", ")),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let versions: Vec<_> = self.versions.iter().map(ToString::to_string).collect();
+        serde_json::json!({
+            "id": self.id,
+            "latest": self.versions.last().map(ToString::to_string),
+            "versions": versions,
+        })
+    }
+}
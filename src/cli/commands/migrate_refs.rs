@@ -0,0 +1,156 @@
+//! # Legacy Ref Migration
+//!
+//! Rewrites Atoms a store holds under the legacy `refs/atom/<path>-<version>` layout into the
+//! current `refs/atoms/<id>/<version>` one, verified exactly as the store's hooks would.
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Only report what would be migrated, without writing or pushing any refs
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Delete each legacy ref from the store once it has been migrated
+    #[arg(long)]
+    prune: bool,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("Failed to migrate {0} legacy ref(s)")]
+    Failed(usize),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::Init;
+            use atom::store::git::migrate;
+            use atom::store::git::verify::Verifier;
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            let root = repo.find_remote(remote.as_str())?.ekala_root()?;
+            let pusher = repo
+                .committer()
+                .and_then(Result::ok)
+                .map(|sig| sig.email.to_string());
+            let verifier = Verifier::new(&repo, pusher)?.root(*root);
+
+            let mut sink = ctx.sink();
+            let mut failed = 0;
+            for (legacy, id) in migrate::fetch_legacy(&repo, &remote)? {
+                let outcome = migrate::prepare(&repo, &legacy, id).and_then(|migration| {
+                    migration.verify(&verifier)?;
+                    let existed = !args.dry_run && migration.apply(&repo, &remote, args.prune)?;
+                    Ok((migration, existed))
+                });
+
+                let status = match &outcome {
+                    Ok((_, true)) => Status::Skipped,
+                    Ok(_) if args.dry_run => Status::Verified,
+                    Ok(_) => Status::Migrated,
+                    Err(e) => {
+                        failed += 1;
+                        Status::Failed(e)
+                    },
+                };
+                let target = outcome.as_ref().ok().map(|(m, _)| m.refs()[0].0.clone());
+                sink.record(&Migrated {
+                    legacy: &legacy,
+                    target: target.as_deref(),
+                    status,
+                });
+            }
+            sink.finish()?;
+
+            if failed > 0 {
+                return Err(Error::Failed(failed).into());
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// The outcome of migrating a single legacy ref.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+enum Status<'a> {
+    Migrated,
+    Verified,
+    Skipped,
+    Failed(&'a dyn std::error::Error),
+}
+
+/// A legacy ref, and what became of it.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+struct Migrated<'a> {
+    legacy: &'a str,
+    target: Option<&'a str>,
+    status: Status<'a>,
+}
+
+impl Record for Migrated<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let (status, color) = match self.status {
+            Status::Migrated => (msg!("status-migrated"), GREEN),
+            Status::Verified => (msg!("status-verified"), GREEN),
+            Status::Skipped => (msg!("status-skipped"), YELLOW),
+            Status::Failed(_) => (msg!("status-invalid"), RED),
+        };
+        let detail = match self.status {
+            Status::Failed(e) => e.to_string(),
+            _ => self.target.unwrap_or_default().to_owned(),
+        };
+        vec![
+            Cell::new(status).color(color),
+            Cell::new(self.legacy),
+            Cell::new(detail),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let status = match self.status {
+            Status::Migrated => "migrated",
+            Status::Verified => "verified",
+            Status::Skipped => "skipped",
+            Status::Failed(_) => "invalid",
+        };
+        let mut json = serde_json::json!({
+            "status": status,
+            "legacy": self.legacy,
+            "ref": self.target,
+        });
+        if let Status::Failed(e) = self.status {
+            json["reason"] = e.to_string().into();
+        }
+        json
+    }
+}
@@ -0,0 +1,106 @@
+//! # Store Mirroring
+//!
+//! Syncs every atom ref of one store to another, e.g. a public store to an internal mirror,
+//! under the same names, once both are verified to be initialized with the same root. Refs of
+//! the mirror pointing elsewhere are only moved if they are channels or redirects, and are
+//! otherwise reported as conflicts and left alone. With `--prune`, the refs of the mirror the
+//! source no longer has are deleted.
+use clap::Parser;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The remote of the store to mirror
+    #[arg(value_name = "SOURCE")]
+    source: String,
+
+    /// The remote of the mirror to sync
+    #[arg(value_name = "DEST")]
+    dest: String,
+
+    /// Delete the refs of the mirror the source no longer has
+    #[arg(long)]
+    prune: bool,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::report::{Diagnostic, Report};
+            use atom::store::git::mirror::{Change, Mirror};
+
+            use crate::cli::report;
+
+            let repo = repo.to_thread_local();
+            let mirror = Mirror::plan(&repo, &args.source, &args.dest, args.prune)?;
+            mirror.apply(&repo)?;
+
+            let mut sink = ctx.sink();
+            let mut summary = Report::default();
+            for update in mirror.updates() {
+                let change = update.change();
+                summary.count(outcome(change));
+                if change == Change::Conflict {
+                    let conflict = Diagnostic::warning("mirror-conflict", msg!("mirror-conflict"));
+                    summary.push(conflict.about(&update.name));
+                }
+                sink.record(&Synced {
+                    name: &update.name,
+                    change,
+                });
+            }
+            if mirror.unchanged() > 0 {
+                tracing::info!(count = mirror.unchanged(), "Already in sync");
+            }
+            report::finish(&mut *sink, &summary)?;
+            report::summarize(&summary);
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// The outcome a change is counted as, named as in the results.
+#[cfg(feature = "git")]
+fn outcome(change: atom::store::git::mirror::Change) -> &'static str {
+    use atom::store::git::mirror::Change;
+
+    match change {
+        Change::Create => "created",
+        Change::Update => "changed",
+        Change::Delete => "deleted",
+        Change::Conflict => "skipped",
+    }
+}
+
+/// A ref of the mirror, and how it was synced.
+#[cfg(feature = "git")]
+struct Synced<'a> {
+    name: &'a str,
+    change: atom::store::git::mirror::Change,
+}
+
+#[cfg(feature = "git")]
+impl Record for Synced<'_> {
+    fn row(&self) -> Vec<Cell> {
+        use atom::store::git::mirror::Change;
+
+        let status = crate::cli::i18n::message(&format!("status-{}", outcome(self.change)), None);
+        let color = match self.change {
+            Change::Create | Change::Update => GREEN,
+            Change::Delete => RED,
+            Change::Conflict => YELLOW,
+        };
+        vec![Cell::new(status).color(color), Cell::new(self.name)]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "status": outcome(self.change), "ref": self.name })
+    }
+}
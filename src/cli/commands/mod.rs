@@ -1,10 +1,40 @@
+mod artifact;
+mod backfill;
+mod check;
+mod debug;
+mod deps;
+mod develop;
+mod eval;
+mod explain;
+mod export_archive;
+mod fetch;
+mod gc;
+mod graph;
+mod hooks;
+mod info;
 mod init;
+mod list;
+mod migrate_refs;
+mod mirror;
+mod new;
+mod plan;
 mod publish;
+mod rename;
+mod repl;
+mod resolve;
+mod show_ref;
+mod stats;
+mod status;
+mod verify;
+mod version;
+mod watch;
+mod yank;
 
 use clap::Subcommand;
 
 use super::Args;
-use crate::cli::store;
+use crate::cli::context::Context;
+use crate::cli::exit::{Outcome, Status};
 
 #[derive(Subcommand)]
 pub(super) enum Commands {
@@ -20,22 +50,490 @@ pub(super) enum Commands {
     /// future support of alternative storage backends as well.
     #[command(verbatim_doc_comment)]
     Publish(publish::PublishArgs),
+    /// Plan a publish, to be executed later with `eka publish --plan`.
+    ///
+    /// Validates the atoms and computes their commits exactly as
+    /// `eka publish` would, without publishing anything, and writes the
+    /// atoms, their versions, commits and target refs to a plan file,
+    /// signed with the signing key configured in Git. This allows plans
+    /// made in CI to be reviewed and approved before they are executed.
+    #[command(verbatim_doc_comment)]
+    Plan(plan::Args),
+    /// Compare the atoms of the working tree with the store.
+    ///
+    /// Reports whether the current version of each atom in and under
+    /// the current working directory, or at the given paths, is not yet
+    /// published, published with the very same content, or modified
+    /// since it was published, and so in need of a version bump. Use
+    /// `--format json` to process the report in scripts.
+    #[command(verbatim_doc_comment)]
+    Status(status::Args),
     /// Initialize the Ekala store.
     ///
     /// This command initializes the repository for use as an Ekala store
-    /// fit for publishing atoms to a remote location.
+    /// fit for publishing atoms to a remote location. With `--check`, it
+    /// only reports whether, and with which root, the store is initialized.
     #[command(verbatim_doc_comment)]
     Init(init::Args),
+    /// Validate the policy and Atoms of the Ekala store.
+    ///
+    /// Checks the team ownership declarations of the store's policy,
+    /// reporting teams without members or prefixes, and prefixes
+    /// claimed by more than one team. Also reports published Atoms
+    /// whose ids differ only by case or by confusable characters, and
+    /// lints the manifests of the Atoms at `HEAD` as configured in the
+    /// `[lint]` section of the configuration, and against the rules of
+    /// the `ekala-policy.toml` committed at the root of the repository.
+    #[command(verbatim_doc_comment)]
+    Check(check::Args),
+    /// Enforce the Atom format and the store's policy at push time.
+    ///
+    /// Generates server-side git hooks for the repository serving as
+    /// the store, rejecting malformed or unauthorized Atom refs before
+    /// they are ever published.
+    #[command(verbatim_doc_comment)]
+    Hooks(hooks::Args),
+    /// Attach auxiliary artifacts to published atoms, or fetch them.
+    ///
+    /// Artifacts, such as a prebuilt tarball or an evaluation cache,
+    /// are stored beside an atom's refs and verified against content
+    /// hashes declared in its manifest, keeping the atom's own content
+    /// reproducible while allowing optional binary distribution.
+    #[command(verbatim_doc_comment)]
+    Artifact(artifact::Args),
+    /// Evaluate an atom, caching the output.
+    ///
+    /// Outputs are cached by the atom's id hash, version and content,
+    /// locally and optionally in the store, so evaluating an unchanged
+    /// atom again is only a lookup. The evaluator is configured with
+    /// the `eval.command` configuration value.
+    #[command(verbatim_doc_comment)]
+    Eval(eval::Args),
+    /// Enter the development environment of an atom.
+    ///
+    /// The atom's content is materialized outside of the repository,
+    /// along with every atom pinned by its lock, and the environment
+    /// its trait defines, by the entry point configured for it in
+    /// `develop.environments`, is instantiated in a sandbox confined
+    /// to them, before launching a shell in it. This allows working on
+    /// an atom in isolation from the rest of the repository.
+    #[command(verbatim_doc_comment)]
+    Develop(develop::Args),
+    /// Publish every historical version of an atom.
+    ///
+    /// Walks the history of the given revision, finds each commit in
+    /// which the atom's manifest introduced a new version, and publishes
+    /// that version from it, oldest first. Versions already in the store
+    /// are skipped, so a freshly initialized store can be populated with
+    /// an atom's full release history.
+    #[command(verbatim_doc_comment)]
+    Backfill(backfill::Args),
+    /// Inspect the dependency graph of the repository, or of an atom.
+    ///
+    /// Without a subcommand, builds the graph of the dependencies among
+    /// all the atoms of the repository, failing on cycles and warning
+    /// about path dependencies on no atom. Also exports the graph recorded
+    /// in an atom's lock file, with the version and store of each atom, in
+    /// the DOT language or the JSON Graph Format, e.g. to visualize it in
+    /// CI, and reports the atoms added, removed or changed between two
+    /// lock files.
+    #[command(verbatim_doc_comment)]
+    Graph(graph::Args),
+    /// Query the licenses and hosts in the dependency closure of an atom.
+    ///
+    /// Lists each distinct license declared, or host sources are fetched
+    /// from, by the atoms pinned in an atom's lock file, with the manifests
+    /// introducing it and the dependency chain leading to them, and fails
+    /// if any is forbidden by the `ekala-policy.toml` of the repository.
+    #[command(verbatim_doc_comment)]
+    Deps(deps::Args),
+    /// Migrate atoms published under the legacy ref layout.
+    ///
+    /// Detects refs of the form `refs/atom/<path>-<version>` in the
+    /// store, rewrites each into the current `refs/atoms/<id>/<version>`
+    /// layout with a reproducible atom commit, verifies the result as
+    /// the store's hooks would, and pushes it.
+    #[command(verbatim_doc_comment)]
+    MigrateRefs(migrate_refs::Args),
+    /// Show the headers of a published atom commit.
+    ///
+    /// Prints the extra headers (origin, path and format) recorded in the
+    /// atom commit a ref points to. With `--verify-headers`, they are also
+    /// checked against those the current publisher would write, which is
+    /// useful when debugging stores written by older versions of eka or
+    /// by third-party publishers.
+    #[command(verbatim_doc_comment)]
+    ShowRef(show_ref::Args),
+    /// Summarize the commands run on this machine.
+    ///
+    /// With `--self`, reports how often each command was run and how
+    /// long it took, from the metrics recorded locally when the opt-in
+    /// `metrics` configuration value is set. The metrics are never sent
+    /// anywhere; they help find the slowest workflows.
+    #[command(verbatim_doc_comment)]
+    Stats(stats::Args),
+    /// Report the atom refs changed in a store since the last poll.
+    ///
+    /// Lists the atom refs added, moved or removed since the previous
+    /// run, from a cursor persisted between runs, so bots and mirrors
+    /// need not diff the entire ref list of the store each time. With
+    /// `--interval`, keeps polling the store until interrupted.
+    #[command(verbatim_doc_comment)]
+    Watch(watch::Args),
+    /// Prune the source refs of published atoms.
+    ///
+    /// With `--sources`, deletes the refs pinning the commit each atom
+    /// version was published from, unless retained by the `--keep-latest`
+    /// or `--keep-days` policy, so upstream history can be reclaimed by
+    /// `git gc`. The content of every version is kept, but the pruned
+    /// versions can no longer be verified against their source.
+    #[command(verbatim_doc_comment)]
+    Gc(gc::Args),
+    /// Verify published atoms against their sources.
+    ///
+    /// Fetches the given atom and the commit it claims to be published
+    /// from, re-creates the atom from the claimed path in that commit,
+    /// and fails unless the two are identical, but for the signature of
+    /// a signed atom. With `signing.allowed-keys` configured, also fails
+    /// unless the atom was signed by one of the keys it lists.
+    ///
+    /// With `--all`, re-checks every atom version in the store as the
+    /// store's hooks checked it when it was pushed, and recomputes its
+    /// content from its source, on several threads at once. Reports the
+    /// outcome for each version and a summary, and fails if any of them
+    /// could not be verified, e.g. for a nightly job of store operators.
+    ///
+    /// With `--lock`, checks that every atom a lock pins is still
+    /// fetchable from the store it pins it from, as the very commit it
+    /// pins. With `--attest`, also writes the outcome as an attestation
+    /// document, signed with the configured key, to be kept with the
+    /// artifacts of a release for later audits.
+    #[command(verbatim_doc_comment)]
+    Verify(verify::Args),
+    /// Withdraw a published atom version from resolution.
+    ///
+    /// Marks the version yanked in the store, with a `_yanked` ref
+    /// beside its own, so that no requirement resolves to it anymore,
+    /// while it remains in the store, and can never be republished. With
+    /// `--force`, deletes the version's refs from the store outright,
+    /// keeping its source ref if another version shares it.
+    #[command(verbatim_doc_comment)]
+    Yank(yank::Args),
+    /// Redirect the former id of a renamed atom to its new one.
+    ///
+    /// Once the atom is published under its new id, writes a `_redirect`
+    /// ref beside the versions of its former id, pointing to the latest
+    /// version of the new one. Dependencies on the former id are then
+    /// resolved against the new one, with a warning that the atom was
+    /// renamed, so that dependents keep working until they are updated.
+    #[command(verbatim_doc_comment)]
+    Rename(rename::Args),
+    /// Sync the atoms of a store to a mirror of it.
+    ///
+    /// Fetches every ref under `refs/atoms/` of the source store, and
+    /// pushes it to the mirror under the same name, once both stores are
+    /// verified to be initialized with the same root. Refs of the mirror
+    /// pointing elsewhere are only moved if they are channels or
+    /// redirects, and are otherwise reported as conflicts. With
+    /// `--prune`, the refs the source no longer has are deleted.
+    #[command(verbatim_doc_comment)]
+    Mirror(mirror::Args),
+    /// Export the content of a published atom as an archive.
+    ///
+    /// Writes a byte-reproducible tar or zip archive of the atom's
+    /// content, with its entries in a fixed order, and no timestamps or
+    /// owners, and reports its hash, so that it may be declared in the
+    /// atom's `[artifacts]` and attached with `eka artifact attach`.
+    /// Archives are uncompressed; pipe them through `zstd`, or similar,
+    /// for a compressed archive.
+    #[command(verbatim_doc_comment)]
+    ExportArchive(export_archive::Args),
+    /// Fetch a published atom and check out its content.
+    ///
+    /// Resolves the version requirement of the atom's uri against the
+    /// versions published to the store, fetches only the content of the
+    /// greatest one satisfying it, without the history of the repository
+    /// it was published from, and checks it out to an empty directory.
+    /// With a `[proxy]` configured, atoms are served from its local
+    /// cache, and only fetched from the store on a miss, or once they
+    /// are older than its `ttl`. With `--recursive`, every atom pinned
+    /// by the lock it was published with is fetched too, each to its
+    /// own directory, with a map of their paths for build tooling.
+    #[command(verbatim_doc_comment)]
+    Fetch(fetch::Args),
+    /// Describe a published atom without checking it out.
+    ///
+    /// Resolves the version requirement of the atom's uri against the
+    /// versions published to the store, fetches only the spec of the
+    /// greatest one satisfying it, along with its atom commit, and prints
+    /// the id, version and description from its manifest, the commit it
+    /// originates from, the format it was published in, and the full name
+    /// of each of its refs. With `--versions`, every version published
+    /// under its id is listed instead, marking those yanked. An atom the
+    /// store redirects to a new id is described by the new one.
+    #[command(verbatim_doc_comment)]
+    Info(info::Args),
+    /// List the atoms published to the store.
+    ///
+    /// Reports the id of each atom published to the remote store, or the
+    /// object store given by `--store`, with its latest and all of its
+    /// published versions, without fetching any of them. Use `--format
+    /// json` to process the list in scripts.
+    #[command(verbatim_doc_comment)]
+    List(list::Args),
+    /// Resolve the dependencies of an atom into its lock file.
+    ///
+    /// Pins each atom the manifest depends on in its own store, and
+    /// transitively their dependencies, to the greatest version published
+    /// to the remote store which satisfies its requirement, and writes the
+    /// pins to the `.lock` file beside the manifest, reporting how the
+    /// lock changed. Dependencies on other stores are not yet resolved.
+    #[command(verbatim_doc_comment)]
+    Resolve(resolve::Args),
+    /// Create a new atom.
+    ///
+    /// Creates a directory with a manifest declaring the id, version
+    /// and description of the atom, once its id is found valid, or lays
+    /// it out from a template directory with `--template`. With
+    /// `--workspace`, also declares the atom in the workspace manifest
+    /// of the repository.
+    #[command(verbatim_doc_comment)]
+    New(new::Args),
+    /// Bump the version of an atom in its manifest.
+    ///
+    /// Bumps the major, minor or patch version declared by the
+    /// manifest, or sets it with `--set`, editing the manifest in place
+    /// so that its formatting and comments are preserved. With
+    /// `--check`, fails if the new version is already published to the
+    /// store, before writing anything.
+    #[command(verbatim_doc_comment)]
+    Version(version::Args),
+    /// Explain an exit code of eka.
+    ///
+    /// Prints what the exit code stands for, its common causes, and how
+    /// to remedy them, given its number or name, e.g. `3` or `conflict`.
+    /// Lists every exit code, if none is given.
+    #[command(verbatim_doc_comment)]
+    Explain(explain::Args),
+    /// Execute a sequence of commands in a single process.
+    ///
+    /// Commands are read line by line from a file, or from standard input
+    /// if none is given, and share the configuration and the discovered
+    /// store, avoiding repeated startup and discovery costs when driving
+    /// eka from scripts. Global options apply to every command.
+    #[command(verbatim_doc_comment)]
+    Repl(repl::Args),
+    /// Tools for developing and profiling eka itself.
+    ///
+    /// `gen-repo` generates a synthetic repository of thousands of
+    /// atoms, optionally nested, with duplicate ids or broken manifests,
+    /// to test and profile validation and publishing at scale.
+    #[command(verbatim_doc_comment)]
+    Debug(debug::Args),
 }
 
-pub async fn run(args: Args) -> anyhow::Result<()> {
-    let store = store::detect();
+pub async fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
     match args.command {
-        Commands::Publish(args) => {
-            publish::run(store.await?, args).await?;
-        },
-
-        Commands::Init(args) => init::run(store.await?, args)?,
+        Commands::Repl(args) => repl::run(ctx, args).await?,
+        command => execute(ctx, command).await?,
     }
     Ok(())
 }
+
+impl Commands {
+    /// The name the command is invoked by, as recorded in the usage metrics.
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::Publish(_) => "publish",
+            Commands::Plan(_) => "plan",
+            Commands::Status(_) => "status",
+            Commands::Init(_) => "init",
+            Commands::Check(_) => "check",
+            Commands::Hooks(_) => "hooks",
+            Commands::Artifact(_) => "artifact",
+            Commands::Eval(_) => "eval",
+            Commands::Develop(_) => "develop",
+            Commands::Backfill(_) => "backfill",
+            Commands::Graph(_) => "graph",
+            Commands::Deps(_) => "deps",
+            Commands::MigrateRefs(_) => "migrate-refs",
+            Commands::ShowRef(_) => "show-ref",
+            Commands::Stats(_) => "stats",
+            Commands::Watch(_) => "watch",
+            Commands::Gc(_) => "gc",
+            Commands::Verify(_) => "verify",
+            Commands::Yank(_) => "yank",
+            Commands::Rename(_) => "rename",
+            Commands::Mirror(_) => "mirror",
+            Commands::ExportArchive(_) => "export-archive",
+            Commands::Fetch(_) => "fetch",
+            Commands::Info(_) => "info",
+            Commands::List(_) => "list",
+            Commands::Resolve(_) => "resolve",
+            Commands::New(_) => "new",
+            Commands::Version(_) => "version",
+            Commands::Explain(_) => "explain",
+            Commands::Repl(_) => "repl",
+            Commands::Debug(_) => "debug",
+        }
+    }
+}
+
+async fn execute(ctx: &Context, command: Commands) -> anyhow::Result<()> {
+    use tracing::Instrument;
+
+    use crate::cli::metrics::COMMAND_SPAN;
+
+    let span = tracing::info_span!(COMMAND_SPAN, name = command.name());
+    async move {
+        match command {
+            Commands::Publish(args) => {
+                publish::run(ctx, args).await?;
+            },
+
+            Commands::Plan(args) => plan::run(ctx, args)?,
+
+            Commands::Status(args) => status::run(ctx, args)?,
+
+            Commands::Init(args) => init::run(ctx, args)?,
+
+            Commands::Check(args) => check::run(ctx, args)?,
+
+            Commands::Hooks(args) => hooks::run(ctx, args)?,
+
+            Commands::Artifact(args) => artifact::run(ctx, args)?,
+
+            Commands::Eval(args) => eval::run(ctx, args)?,
+
+            Commands::Develop(args) => develop::run(ctx, args)?,
+
+            Commands::Backfill(args) => backfill::run(ctx, args).await?,
+
+            Commands::Graph(args) => graph::run(ctx, args)?,
+
+            Commands::Deps(args) => deps::run(ctx, args)?,
+
+            Commands::MigrateRefs(args) => migrate_refs::run(ctx, args)?,
+
+            Commands::ShowRef(args) => show_ref::run(ctx, args)?,
+
+            Commands::Stats(args) => stats::run(ctx, args)?,
+
+            Commands::Watch(args) => watch::run(ctx, args).await?,
+
+            Commands::Gc(args) => gc::run(ctx, args)?,
+
+            Commands::Verify(args) => verify::run(ctx, args)?,
+
+            Commands::Yank(args) => yank::run(ctx, args)?,
+
+            Commands::Rename(args) => rename::run(ctx, args)?,
+
+            Commands::Mirror(args) => mirror::run(ctx, args)?,
+
+            Commands::ExportArchive(args) => export_archive::run(ctx, args)?,
+
+            Commands::Fetch(args) => fetch::run(ctx, args)?,
+
+            Commands::Info(args) => info::run(ctx, args)?,
+
+            Commands::List(args) => list::run(ctx, args)?,
+
+            Commands::Resolve(args) => resolve::run(ctx, args)?,
+
+            Commands::New(args) => new::run(ctx, args)?,
+
+            Commands::Version(args) => version::run(ctx, args)?,
+
+            Commands::Explain(args) => explain::run(ctx, args)?,
+
+            Commands::Debug(args) => debug::run(ctx, args)?,
+
+            Commands::Repl(_) => return Err(repl::Error::Nested.into()),
+        }
+        Ok(())
+    }
+    .instrument(span)
+    .await
+}
+
+/// Classify the failure of a command into the [`Status`] eka exits with.
+pub fn status(error: &anyhow::Error) -> Status {
+    for cause in error.chain() {
+        if let Some(outcome) = cause.downcast_ref::<Outcome>() {
+            return match outcome {
+                Outcome::NothingToDo => Status::NothingToDo,
+                Outcome::Partial { .. } => Status::Partial,
+            };
+        }
+        if let Some(repl::Error::Failed { error, .. }) = cause.downcast_ref() {
+            return status(error);
+        }
+        if cause.is::<clap::Error>() {
+            return Status::Usage;
+        }
+        if cause.is::<backfill::Error>() || cause.is::<migrate_refs::Error>() {
+            return Status::Publish;
+        }
+        if cause.is::<check::Error>()
+            || cause.is::<deps::Error>()
+            || cause.is::<graph::Error>()
+            || cause.is::<hooks::Error>()
+            || cause.is::<show_ref::Error>()
+            || cause.is::<verify::Error>()
+        {
+            return Status::Verification;
+        }
+        #[cfg(feature = "git")]
+        if let Some(status) = git_status(cause) {
+            return status;
+        }
+    }
+    Status::Failure
+}
+
+/// Classify the errors of the Git store, which are transparent wrappers of one another, so
+/// only the outermost is found in the chain of causes.
+#[cfg(feature = "git")]
+fn git_status(cause: &(dyn std::error::Error + 'static)) -> Option<Status> {
+    use atom::publish::error::{PublishError, git as publish};
+    use atom::store::git::{self as store, verify};
+
+    fn published(error: &publish::Error) -> Option<Status> {
+        use publish::Error;
+        match error {
+            Error::Failed | Error::SomePushFailed => Some(Status::Publish),
+            Error::Invalid(..)
+            | Error::Strict(_)
+            | Error::Duplicates(_)
+            | Error::Protected { .. }
+            | Error::Trespass { .. } => Some(Status::Verification),
+            Error::PathDependency(..)
+            | Error::MismatchedPathDependency { .. }
+            | Error::UnsatisfiedPathDependency { .. }
+            | Error::Cycle(_)
+            | Error::Concurrent { .. } => Some(Status::Conflict),
+            Error::StoreError(e) => stored(e),
+            _ => None,
+        }
+    }
+
+    fn stored(error: &store::Error) -> Option<Status> {
+        matches!(
+            error,
+            store::Error::Unsatisfied(..) | store::Error::Conflict(..)
+        )
+        .then_some(Status::Conflict)
+    }
+
+    if let Some(PublishError::Git(e)) = cause.downcast_ref() {
+        return published(e);
+    }
+    if let Some(e) = cause.downcast_ref() {
+        return published(e);
+    }
+    if let Some(e) = cause.downcast_ref() {
+        return stored(e);
+    }
+    cause.is::<verify::Error>().then_some(Status::Verification)
+}
@@ -0,0 +1,168 @@
+//! # Atom Scaffolding
+//!
+//! Creates the directory of a new Atom, with a valid manifest declaring its id, version and
+//! optional description, or laid out from a template directory. With `--workspace`, the new
+//! Atom is also declared in the workspace manifest at the root of the repository, so that the
+//! other Atoms of the repository can depend on it with `workspace = true`.
+use std::path::PathBuf;
+
+use atom::{INITIAL_VERSION, Id, Scaffold, WORKSPACE_FILE};
+use clap::Parser;
+use semver::Version;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::GREEN;
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct Args {
+    /// The id of the new atom
+    id: Id,
+
+    /// The directory to create the atom in
+    ///
+    /// [default: a directory named after the id]
+    #[arg(long, short, value_name = "DIR", verbatim_doc_comment)]
+    path: Option<PathBuf>,
+
+    /// The initial version of the atom
+    #[arg(long, default_value_t = INITIAL_VERSION)]
+    version: Version,
+
+    /// A description of the atom, declared in its manifest
+    #[arg(long, short)]
+    description: Option<String>,
+
+    /// A directory to lay the atom out from
+    ///
+    /// Its files are copied, with `{{id}}`, `{{version}}` and
+    /// `{{description}}` replaced in their names and content. A
+    /// manifest is written as without a template, unless it has one.
+    #[arg(long, value_name = "DIR", verbatim_doc_comment)]
+    template: Option<PathBuf>,
+
+    /// Declare the atom in the workspace manifest of the repository
+    ///
+    /// Adds the atom to the `[deps.atoms]` of the `ekala.toml` at the
+    /// root of the repository, creating it if needed, so other atoms can
+    /// depend on it with `workspace = true`.
+    #[arg(long, verbatim_doc_comment)]
+    workspace: bool,
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("No repository was found to declare the atom in the workspace of")]
+    NoWorkspace,
+    #[error("`{0}` is outside of the repository")]
+    Outside(PathBuf),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    let dir = ctx
+        .cwd()
+        .join(args.path.unwrap_or_else(|| PathBuf::from(args.id.as_str())));
+    let template = args.template.map(|t| ctx.cwd().join(t));
+    let scaffold = Scaffold {
+        version: args.version,
+        description: args.description,
+        ..Scaffold::new(args.id)
+    };
+
+    // the workspace is edited before anything is written, so that it is never left behind
+    let workspace = if args.workspace {
+        let (path, relative) = workspace(ctx, &dir)?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Some((path, scaffold.register(&content, &relative)?))
+    } else {
+        None
+    };
+
+    let existed = dir.exists();
+    let written = scaffold.write(&dir, template.as_deref())?;
+    if let Some((path, registered)) = &workspace {
+        if let Err(e) = std::fs::write(path, registered) {
+            // the atom is not left behind undeclared, nor is its directory if it was created
+            let _ = std::fs::remove_dir_all(&dir);
+            if existed {
+                let _ = std::fs::create_dir(&dir);
+            }
+            return Err(e.into());
+        }
+    }
+
+    let mut sink = ctx.sink();
+    sink.record(&Created {
+        scaffold: &scaffold,
+        manifest: &written[0],
+        files: written.len(),
+        workspace: workspace.is_some(),
+    });
+    sink.finish()?;
+    Ok(())
+}
+
+/// The workspace manifest of the repository `dir` is in, and the path of `dir` relative to
+/// the root of the repository.
+fn workspace(ctx: &Context, dir: &std::path::Path) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let root = match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            let repo = repo.to_thread_local();
+            repo.work_dir().map(std::fs::canonicalize).transpose()?
+        },
+        _ => None,
+    };
+    let root = root.ok_or(Error::NoWorkspace)?;
+    // the directory need not exist yet, but its parent must
+    let parent = dir.parent().unwrap_or(dir);
+    let relative = std::fs::canonicalize(parent)?
+        .join(dir.file_name().unwrap_or_default())
+        .strip_prefix(&root)
+        .map_err(|_| Error::Outside(dir.to_owned()))?
+        .to_owned();
+    Ok((root.join(WORKSPACE_FILE), relative))
+}
+
+/// A new atom, as scaffolded.
+struct Created<'a> {
+    scaffold: &'a Scaffold,
+    manifest: &'a std::path::Path,
+    files: usize,
+    workspace: bool,
+}
+
+impl Record for Created<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let mut row = vec![
+            Cell::new(msg!("status-created")).color(GREEN),
+            Cell::new(&self.scaffold.id),
+            Cell::new(&self.scaffold.version),
+            Cell::new(self.manifest.display()),
+        ];
+        if self.workspace {
+            let registered = msg!("new-registered", workspace = WORKSPACE_FILE);
+            row.push(Cell::new(registered));
+        }
+        row
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": "created",
+            "id": self.scaffold.id.to_string(),
+            "version": self.scaffold.version.to_string(),
+            "manifest": self.manifest,
+            "files": self.files,
+            "workspace": self.workspace,
+        })
+    }
+}
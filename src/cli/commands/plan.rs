@@ -0,0 +1,256 @@
+//! # Publish Plans
+//!
+//! Plans a publish without executing it: the atoms are validated, and their commits computed,
+//! as `eka publish` would, but instead of being published, they are recorded in a plan file,
+//! signed with the signing key configured in Git. The plan can then be reviewed, and executed
+//! exactly with `eka publish --plan`, e.g. planned in CI and executed once a human approved it.
+use std::path::PathBuf;
+
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct Args {
+    /// Plan to publish all the atoms in and under the current working directory
+    #[arg(long, short, conflicts_with = "path")]
+    recursive: bool,
+
+    /// Plan to publish all the atoms in the repository, in dependency order
+    #[arg(long, conflicts_with_all = ["path", "recursive"])]
+    workspace: bool,
+
+    /// Path(s) to the atom(s) to plan
+    #[arg(required_unless_present_any = ["recursive", "workspace"])]
+    path: Vec<PathBuf>,
+
+    /// The file to write the plan to
+    #[arg(long, short, value_name = "FILE", default_value = "plan.json")]
+    output: PathBuf,
+
+    /// Write the plan without signing it
+    ///
+    /// Unsigned plans are only executed with `--allow-unsigned`.
+    #[arg(long, verbatim_doc_comment)]
+    no_sign: bool,
+
+    /// Plan to sign the atom commits with the configured signing key
+    ///
+    /// The plan is then executed with signed atom commits, and only then.
+    /// Defaults to the `signing.sign` configuration value.
+    #[arg(long, verbatim_doc_comment)]
+    sign_atoms: bool,
+
+    /// Fail if any invalid Atom manifest is encountered during validation
+    ///
+    /// Defaults to the `publish.strict` configuration value.
+    #[arg(long, overrides_with = "no_strict", verbatim_doc_comment)]
+    strict: bool,
+
+    /// Skip invalid Atom manifests, even if `publish.strict` is set
+    #[arg(long, overrides_with = "strict")]
+    no_strict: bool,
+
+    /// Plan to publish Atom versions protected by the store's policy
+    #[arg(long)]
+    allow_protected: bool,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+        /// The revision to plan to publish the atom(s) from
+        #[arg(long, short, default_value = "HEAD", name = "REVSPEC")]
+        pub(super) spec: String,
+    }
+}
+
+#[derive(Error, Debug)]
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+pub(super) enum Error {
+    #[error("The plan was written by another version of eka, plan it again")]
+    Format,
+    #[error("The plan is not signed, pass `--allow-unsigned` to execute it anyway")]
+    Unsigned,
+    #[error("The plan is for unsigned atom commits, plan it with `--sign-atoms` to sign them")]
+    UnsignedAtoms,
+    #[error("The plan targets `{0}`, but `{1}` was given")]
+    Remote(String, String),
+    #[error("Publish plans can only be executed against a Git store")]
+    ObjectStore,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::Linter;
+            use atom::publish::Builder;
+            use atom::publish::error::git::Error as GitError;
+            use atom::publish::git::GitPublisher;
+            use atom::publish::git::plan::{self, PLAN_FORMAT, PlannedAtom, PublishPlan};
+            use atom::store::NormalizeStorePath;
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            let revision = repo.rev_parse_single(args.git.spec.as_str())?;
+            let revision = revision.object()?.peel_to_commit()?.id.to_string();
+
+            let strict = args.strict || (!args.no_strict && ctx.config().publish().strict);
+            let (atoms, publisher) = GitPublisher::new(&repo, &remote, &revision)?
+                .strict(strict)
+                .allow_protected(args.allow_protected)
+                .lints(Linter::new(ctx.config().lint())?)
+                .kinds(ctx.kinds()?)
+                .current_dir(ctx.cwd())
+                .lexical(args.recursive || args.workspace)
+                .build()
+                .inspect_err(super::publish::report)?;
+            publisher
+                .take_warnings()
+                .iter()
+                .for_each(super::publish::warn);
+
+            let paths: Vec<_> = if args.recursive || args.workspace {
+                let cwd = if args.recursive && !repo.is_bare() {
+                    Some(repo.normalize_from(ctx.cwd(), ctx.cwd())?)
+                } else {
+                    None
+                };
+                atoms
+                    .into_values()
+                    .filter(|path| cwd.as_ref().map_or(true, |cwd| path.starts_with(cwd)))
+                    .collect()
+            } else {
+                args.path
+            };
+            if paths.is_empty() {
+                return Err(GitError::NotFound.into());
+            }
+            // record the order the Atoms are published in, after those they depend on by path
+            let levels = if args.workspace {
+                publisher.levels(paths)?
+            } else {
+                vec![paths]
+            };
+
+            let mut sink = ctx.sink();
+            let mut planned = Vec::new();
+            for (level, paths) in levels.into_iter().enumerate() {
+                for plan in publisher.plan(paths) {
+                    let plan = plan.inspect_err(super::publish::report)?;
+                    if plan.exists() {
+                        sink.record(&Planned::Skipped(plan.id().id().to_string()));
+                        continue;
+                    }
+                    planned.push(PlannedAtom::new(&plan, level));
+                }
+            }
+            for atom in &planned {
+                sink.record(&Planned::Atom(atom));
+            }
+
+            let mut plan = PublishPlan {
+                format: PLAN_FORMAT,
+                remote: remote.clone(),
+                revision: revision.clone(),
+                atoms: planned,
+                signed: args.sign_atoms || ctx.config().signing().sign,
+                signature: None,
+            };
+            if !args.no_sign {
+                let payload = serde_json::to_vec(&plan)?;
+                plan.signature = Some(plan::sign(&repo, &payload)?);
+            }
+            std::fs::write(&args.output, serde_json::to_string_pretty(&plan)?)?;
+            sink.finish()?;
+            tracing::info!(
+                "{}",
+                msg!("plan-written", path = args.output.display().to_string())
+            );
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Read the plan at `path`, and verify its signature with the keys Git trusts, unless
+/// `allow_unsigned` and it is not signed.
+#[cfg(feature = "git")]
+pub(super) fn load(
+    repo: &gix::Repository,
+    path: &std::path::Path,
+    allow_unsigned: bool,
+) -> anyhow::Result<atom::publish::git::plan::PublishPlan> {
+    use atom::publish::git::plan::{self, PLAN_FORMAT, PublishPlan};
+
+    let mut plan: PublishPlan = serde_json::from_slice(&std::fs::read(path)?)?;
+    if plan.format != PLAN_FORMAT {
+        return Err(Error::Format.into());
+    }
+    match plan.signature.take() {
+        Some(signature) => plan::verify(repo, &serde_json::to_vec(&plan)?, &signature)?,
+        None if allow_unsigned => tracing::warn!("{}", msg!("plan-unsigned")),
+        None => return Err(Error::Unsigned.into()),
+    }
+    Ok(plan)
+}
+
+/// An atom recorded in the plan, or skipped as it was already published.
+#[cfg(feature = "git")]
+enum Planned<'a> {
+    Atom(&'a atom::publish::git::plan::PlannedAtom),
+    Skipped(String),
+}
+
+#[cfg(feature = "git")]
+impl Record for Planned<'_> {
+    fn row(&self) -> Vec<Cell> {
+        match self {
+            Planned::Atom(atom) => vec![
+                Cell::new(msg!("status-planned")).color(GREEN),
+                Cell::new(&atom.id),
+                Cell::new(&atom.version),
+                Cell::new(&atom.commit),
+            ],
+            Planned::Skipped(id) => vec![
+                Cell::new(msg!("status-skipped")).color(YELLOW),
+                Cell::new(id),
+            ],
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        use serde_json::json;
+        match self {
+            Planned::Atom(atom) => json!({
+                "status": "planned",
+                "id": atom.id,
+                "version": atom.version.to_string(),
+                "path": atom.path.display().to_string(),
+                "commit": atom.commit,
+                "refs": atom.refs,
+            }),
+            Planned::Skipped(id) => json!({ "status": "skipped", "id": id }),
+        }
+    }
+}
@@ -1,25 +1,39 @@
 use std::collections::HashSet;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
+use atom::publish::Warnings;
 use atom::publish::error::git::Error;
-use atom::publish::git::{GitOutcome, GitResult};
+use atom::publish::git::plan::PublishPlan;
+use atom::publish::git::{GitContext, GitOutcome, GitResult};
 use atom::store::git;
+use atom::store::git::sign::Signer;
 use clap::Parser;
+use config::PackConfig;
 use gix::ThreadSafeRepository;
+use semver::Version;
 
-use super::PublishArgs;
+use super::{PublishArgs, report};
+use crate::cli::context::Context;
+use crate::cli::dashboard::{Dashboard, Status};
+use crate::cli::store::RemoteArg;
+use crate::msg;
 
 #[derive(Parser, Debug)]
 #[command(next_help_heading = "Git Options")]
 pub(super) struct GitArgs {
-    /// The target remote to publish the atom(s) to
-    #[arg(long, short = 't', default_value_t = git::default_remote().to_owned(), name = "TARGET")]
-    remote: String,
-    /// The revision to publish the atom(s) from
+    #[command(flatten)]
+    remote: RemoteArg,
+    /// The revision(s) to publish the atom(s) from
     ///
     /// Specifies a revision using Git's extended SHA-1 syntax.
     /// This can be a commit hash, branch name, tag, or a relative
     /// reference like HEAD~3 or master@{yesterday}.
+    ///
+    /// May be given more than once, or as a range like v1.0..v2.0,
+    /// to publish the atom(s) as they were at each of the commits, in
+    /// order. Each version is then published from the first commit it
+    /// appears in, and versions already in the store are skipped.
     #[arg(
         long,
         short,
@@ -27,47 +41,453 @@ pub(super) struct GitArgs {
         verbatim_doc_comment,
         name = "REVSPEC"
     )]
-    spec: String,
+    pub(super) spec: Vec<String>,
+    /// The compression level of pushed Atom content, from 0 to 9
+    ///
+    /// Defaults to the `publish.remotes.<TARGET>.compression`, or
+    /// the `publish.pack.compression` configuration value.
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        value_parser = clap::value_parser!(u8).range(0..=9),
+        verbatim_doc_comment
+    )]
+    compression: Option<u8>,
+    /// Minimize the size of pushes, for low-bandwidth links
+    ///
+    /// Packs at compression level 9, with a delta window and depth of
+    /// 250, recompressing objects already packed locally rather than
+    /// reusing them, at the expense of processing time. Individual
+    /// settings, such as `--compression`, still take precedence. May
+    /// also be enabled per remote with `publish.remotes.<TARGET>.thin`.
+    #[arg(long, verbatim_doc_comment)]
+    thin: bool,
+    /// Skip the atoms unchanged since they were last published
+    ///
+    /// Records the manifests found valid, and the atoms published, in
+    /// `.git/ekala/cache`, so that neither is validated, nor has its
+    /// tree re-created, again until its content changes.
+    ///
+    /// Defaults to the `publish.incremental` configuration value.
+    #[arg(long, verbatim_doc_comment)]
+    incremental: bool,
+    /// Sign the atom commits with the configured signing key
+    ///
+    /// Signs with the `signing.key` configuration value, or Git's
+    /// `user.signingKey`, in the `signing.format`, or Git's `gpg.format`,
+    /// either `openpgp` or `ssh`. The signature is stored in the atom
+    /// commit, which remains reproducible but for its signature.
+    ///
+    /// Defaults to the `signing.sign` configuration value. With `--plan`,
+    /// the atom commits are signed if the plan says so, and only then.
+    #[arg(long, verbatim_doc_comment)]
+    sign: bool,
+    /// The number of atoms to prepare at once
+    ///
+    /// Atoms are found, checked, and have their objects written on up
+    /// to this many threads, while their refs are still written, and
+    /// pushed, one atom at a time.
+    ///
+    /// [default: the available parallelism]
+    #[arg(long, short = 'j', value_name = "N", verbatim_doc_comment)]
+    jobs: Option<NonZeroUsize>,
 }
 
 pub(super) async fn run(
+    ctx: &Context,
     repo: &ThreadSafeRepository,
     args: PublishArgs,
-) -> GitResult<(Vec<GitResult<GitOutcome>>, Vec<Error>)> {
-    use std::path::Path;
-
+) -> GitResult<(Vec<GitResult<GitOutcome>>, Vec<Error>, Warnings)> {
+    use atom::Linter;
     use atom::publish::git::GitPublisher;
     use atom::publish::{Builder, Publish};
     use atom::store::NormalizeStorePath;
     let repo = repo.to_thread_local();
 
-    let GitArgs { remote, spec } = args.store.git;
+    let GitArgs {
+        remote,
+        spec,
+        compression,
+        thin,
+        incremental,
+        sign,
+        jobs,
+    } = args.store.git;
+    let remote = ctx.remote(&repo, remote.as_deref())?;
+
+    let strict = args.strict || (!args.no_strict && ctx.config().publish().strict);
+    let pack = PackConfig {
+        compression,
+        thin,
+        ..PackConfig::default()
+    }
+    .or(ctx.config().publish().pack(&remote));
+
+    let revisions = git::expand_revisions(&repo, &spec)?;
+    let Some(first) = revisions.first() else {
+        return Err(Error::NotFound);
+    };
+    let builder = GitPublisher::new(&repo, &remote, first)?
+        .strict(strict)
+        .allow_protected(args.allow_protected)
+        .pack(pack)
+        .lints(Linter::new(ctx.config().lint())?)
+        .kinds(ctx.kinds()?)
+        .current_dir(ctx.cwd())
+        .lexical(args.recursive || args.workspace)
+        .incremental(incremental || ctx.config().publish().incremental)
+        .jobs(jobs.unwrap_or_else(available_parallelism));
+    let builder = match signer(ctx, &repo, sign)? {
+        Some(signer) => builder.sign(signer),
+        None => builder,
+    };
+    // the refs of the store as planned against, which every push is conditioned on, so that
+    // an Atom published concurrently in the meantime is never overwritten
+    let snapshot = git::Snapshot::take(&repo, &remote)?;
+    let builder = builder.snapshot(snapshot.clone());
+
+    // when publishing from several revisions, each version is published from the first
+    // revision it appears in, and versions already in the store are skipped
+    let existing: HashSet<_> = if revisions.len() > 1 {
+        snapshot
+            .published()
+            .into_iter()
+            .flat_map(|(id, versions)| versions.into_iter().map(move |v| (id.to_string(), v)))
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let mut seen = HashSet::new();
 
-    let (atoms, publisher) = GitPublisher::new(&repo, &remote, &spec)?.build()?;
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    let mut warnings = Warnings::new();
+    let mut batches = Vec::with_capacity(revisions.len());
+    let cwd = if args.recursive && !repo.is_bare() {
+        Some(repo.normalize_from(ctx.cwd(), ctx.cwd())?)
+    } else {
+        None
+    };
+    // filter redundant paths
+    let given: HashSet<_> = args.path.into_iter().collect();
 
-    let mut errors = Vec::with_capacity(args.path.len());
-    let results = if args.recursive {
-        let paths: HashSet<_> = if !repo.is_bare() {
-            let cwd = repo.normalize(repo.current_dir())?;
+    let mut found = false;
+    for revision in &revisions {
+        let (atoms, publisher) = builder.clone().spec(revision).build().inspect_err(report)?;
+        warnings.extend(publisher.take_warnings());
+        let paths: Vec<_> = if args.recursive || args.workspace {
+            // keep paths relative to the root, as found in the tree, so they need not exist on disk
             atoms
                 .into_values()
-                .filter_map(|path| path.strip_prefix(&cwd).map(Path::to_path_buf).ok())
+                .filter(|path| cwd.as_ref().map_or(true, |cwd| path.starts_with(cwd)))
                 .collect()
         } else {
-            atoms.into_values().collect()
+            given.iter().cloned().collect()
         };
+        found |= !paths.is_empty();
 
-        if paths.is_empty() {
-            return Err(Error::NotFound);
-        }
-        publisher.publish(paths)
+        let paths = if revisions.len() > 1 {
+            dedup(&publisher, paths, &existing, &mut seen, &mut results)
+        } else {
+            paths
+        };
+        // no Atom may reach the store before the Atoms it depends on by path
+        let levels = if args.workspace {
+            publisher.levels(paths)?
+        } else {
+            vec![paths]
+        };
+        batches.push((publisher, levels));
+    }
+
+    if !found {
+        return Err(Error::NotFound);
+    }
+
+    if !args.yes && !confirm(ctx, &batches, &remote)? {
+        tracing::warn!("{}", msg!("publish-cancelled"));
+        return Ok((Vec::new(), errors, warnings));
+    }
+
+    let total = batches.iter().flat_map(|(_, levels)| levels).map(Vec::len);
+    let title = msg!("dashboard-publish", remote = remote.as_str());
+    let dashboard = ctx.dashboard(&title, total.sum());
+    // publish in order, so older versions reach the store first
+    for (publisher, levels) in batches {
+        publish_levels(
+            &publisher,
+            levels,
+            dashboard.as_ref(),
+            &mut results,
+            &mut errors,
+        )
+        .await;
+        warnings.extend(publisher.take_warnings());
+    }
+
+    Ok((results, errors, warnings))
+}
+
+/// Publish exactly the Atoms of a plan, from the revision and to the remote it was made for,
+/// failing before anything is published if any of them would no longer be published as planned.
+pub(super) async fn execute(
+    ctx: &Context,
+    repo: &ThreadSafeRepository,
+    args: PublishArgs,
+    plan: PublishPlan,
+) -> anyhow::Result<(Vec<GitResult<GitOutcome>>, Vec<Error>, Warnings)> {
+    use std::collections::BTreeMap;
+
+    use atom::Linter;
+    use atom::publish::git::GitPublisher;
+    use atom::publish::{Builder, Publish};
+    let repo = repo.to_thread_local();
+
+    let GitArgs {
+        remote,
+        compression,
+        thin,
+        sign,
+        jobs,
+        ..
+    } = args.store.git;
+    if let Some(remote) = remote.as_deref().filter(|remote| *remote != plan.remote) {
+        return Err(super::super::plan::Error::Remote(plan.remote, remote.to_owned()).into());
+    }
+    if sign && !plan.signed {
+        return Err(super::super::plan::Error::UnsignedAtoms.into());
+    }
+
+    let strict = args.strict || (!args.no_strict && ctx.config().publish().strict);
+    let pack = PackConfig {
+        compression,
+        thin,
+        ..PackConfig::default()
+    }
+    .or(ctx.config().publish().pack(&plan.remote));
+    let snapshot = git::Snapshot::take(&repo, &plan.remote)?;
+    let builder = GitPublisher::new(&repo, &plan.remote, &plan.revision)?
+        .strict(strict)
+        .allow_protected(args.allow_protected)
+        .pack(pack)
+        .lints(Linter::new(ctx.config().lint())?)
+        .kinds(ctx.kinds()?)
+        .lexical(true)
+        .snapshot(snapshot)
+        .jobs(jobs.unwrap_or_else(available_parallelism));
+    // the Atom commits are signed as planned, as signing them changes the commits published
+    let builder = if plan.signed {
+        builder.sign(Signer::from_config(&repo, ctx.config().signing())?)
     } else {
-        // filter redundant paths
-        let paths: HashSet<PathBuf> = args.path.into_iter().collect();
-        publisher.publish(paths)
+        builder
+    };
+    let (_, publisher) = builder.build().inspect_err(report)?;
+    let mut warnings = publisher.take_warnings();
+
+    let mut levels: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for atom in &plan.atoms {
+        levels.entry(atom.level).or_default().push(atom);
+    }
+    // every Atom is checked against the plan before any is published
+    for atoms in levels.values() {
+        let recreated = publisher.plan(atoms.iter().map(|atom| atom.path.clone()));
+        for (atom, recreated) in atoms.iter().zip(recreated) {
+            atom.check(&recreated.inspect_err(report)?)?;
+        }
+    }
+    let levels = levels
+        .into_values()
+        .map(|atoms| atoms.into_iter().map(|atom| atom.path.clone()).collect())
+        .collect();
+
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    let batches = [(publisher, levels)];
+    if !args.yes && !confirm(ctx, &batches, &plan.remote)? {
+        tracing::warn!("{}", msg!("publish-cancelled"));
+        return Ok((results, errors, warnings));
+    }
+
+    let [(publisher, levels)] = batches;
+    let title = msg!("dashboard-publish", remote = plan.remote.as_str());
+    let dashboard = ctx.dashboard(&title, plan.atoms.len());
+    publish_levels(
+        &publisher,
+        levels,
+        dashboard.as_ref(),
+        &mut results,
+        &mut errors,
+    )
+    .await;
+    warnings.extend(publisher.take_warnings());
+
+    Ok((results, errors, warnings))
+}
+
+/// Publish each level of Atoms in turn, awaiting the pushes of a level before the next one is
+/// published, and drawing the progress of each Atom on the dashboard, if one is shown.
+async fn publish_levels(
+    publisher: &GitContext<'_>,
+    levels: Vec<Vec<PathBuf>>,
+    dashboard: Option<&Dashboard>,
+    results: &mut Vec<GitResult<GitOutcome>>,
+    errors: &mut Vec<Error>,
+) {
+    use atom::publish::Publish;
+
+    let Some(dashboard) = dashboard else {
+        for paths in levels {
+            results.extend(publisher.publish(paths));
+            publisher.await_pushes(errors).await;
+        }
+        return;
     };
 
-    publisher.await_pushes(&mut errors).await;
+    for paths in levels {
+        // the Atoms of a level are prepared at once, so they are all in progress until done
+        let atoms: Vec<_> = paths.iter().map(|p| p.display().to_string()).collect();
+        for atom in &atoms {
+            dashboard.begin(atom);
+        }
+        let outcomes = publisher.publish(paths);
+        for (atom, outcome) in atoms.iter().zip(&outcomes) {
+            let status = match outcome {
+                Ok(Ok(_)) => Status::Done,
+                Ok(Err(_)) => Status::Skipped,
+                Err(e) => {
+                    dashboard.error(format_args!("{atom}: {e}"));
+                    Status::Failed
+                },
+            };
+            dashboard.end(atom, status);
+        }
+        results.extend(outcomes);
+        let failed = errors.len();
+        publisher
+            .await_pushes_with(errors, |_| dashboard.pushed())
+            .await;
+        for e in &errors[failed..] {
+            dashboard.error(e);
+        }
+    }
+}
+
+/// The number of threads which may run at once, or a single one if it is unknown.
+fn available_parallelism() -> NonZeroUsize {
+    std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// The signer of the Atom commits, if they are signed, per `--sign` or the configuration.
+fn signer(ctx: &Context, repo: &gix::Repository, sign: bool) -> GitResult<Option<Signer>> {
+    let config = ctx.config().signing();
+    if !(sign || config.sign) {
+        return Ok(None);
+    }
+    Ok(Some(Signer::from_config(repo, config)?))
+}
+
+/// Filter out the paths of Atom versions which are already in the store, or were planned from
+/// an earlier revision, recording the former as skipped.
+fn dedup(
+    publisher: &GitContext,
+    paths: Vec<PathBuf>,
+    existing: &HashSet<(String, Version)>,
+    seen: &mut HashSet<(String, Version)>,
+    results: &mut Vec<GitResult<GitOutcome>>,
+) -> Vec<PathBuf> {
+    let plans = publisher.plan(paths.iter().cloned());
+    paths
+        .into_iter()
+        .zip(plans)
+        .filter_map(|(path, plan)| {
+            let plan = match plan {
+                Ok(plan) => plan,
+                // an Atom need not exist in every revision
+                Err(Error::NotAnAtom(_)) => return None,
+                Err(e) => {
+                    results.push(Err(e));
+                    return None;
+                },
+            };
+            let key = (plan.id().id().to_string(), plan.version().clone());
+            if !seen.insert(key.clone()) {
+                return None;
+            }
+            if existing.contains(&key) {
+                results.push(Ok(Err(plan.id().id().clone())));
+                return None;
+            }
+            Some(path)
+        })
+        .collect()
+}
+
+/// Preview the planned Atoms and ask the user to confirm publishing them.
+///
+/// Confirmation is only requested when attached to a terminal and publishing more Atoms than
+/// the configured threshold, otherwise publishing proceeds unconditionally.
+fn confirm(
+    ctx: &Context,
+    batches: &[(GitContext, Vec<Vec<PathBuf>>)],
+    remote: &str,
+) -> GitResult<bool> {
+    use std::io::{self, BufRead, Write};
+
+    use crate::cli::logging::ansi::{GREEN, RED, YELLOW};
+
+    let threshold = ctx.config().publish().confirm_threshold;
+    let count: usize = batches
+        .iter()
+        .flat_map(|(_, levels)| levels)
+        .map(Vec::len)
+        .sum();
+    if count <= threshold || !ctx.output().interactive() {
+        return Ok(true);
+    }
+
+    let output = ctx.output();
+
+    let mut stderr = io::stderr().lock();
+
+    writeln!(stderr, "{}", msg!("publish-plan-header", remote = remote))?;
+    for (publisher, levels) in batches {
+        let paths = levels.concat();
+        let plans = publisher.plan(paths.iter().cloned());
+        for (path, plan) in paths.iter().zip(plans) {
+            match plan {
+                Ok(plan) if plan.exists() => writeln!(
+                    stderr,
+                    "  {:<24} {:<12} {}",
+                    plan.id().id(),
+                    plan.version(),
+                    output.paint(YELLOW, msg!("publish-plan-skip"))
+                )?,
+                Ok(plan) => writeln!(
+                    stderr,
+                    "  {} {:<12} refs/{}/{}",
+                    output.paint(GREEN, format_args!("{:<24}", plan.id().id())),
+                    plan.version(),
+                    plan.ref_prefix(),
+                    git::encode_version(plan.version())
+                )?,
+                Err(e) => writeln!(
+                    stderr,
+                    "  {:<37} {}",
+                    path.display(),
+                    output.paint(RED, msg!("publish-plan-invalid", error = e.to_string()))
+                )?,
+            }
+        }
+    }
+
+    write!(stderr, "{} ", msg!("publish-confirm"))?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    let answer = answer.trim().to_lowercase();
+    let yes = msg!("publish-confirm-yes");
 
-    Ok((results, errors))
+    Ok(answer == "y" || answer == "yes" || (!answer.is_empty() && yes.starts_with(&answer)))
 }
@@ -1,13 +1,21 @@
 #[cfg(feature = "git")]
 mod git;
+#[cfg(feature = "s3")]
+mod s3;
 
 use std::path::PathBuf;
 
 use atom::publish::error::PublishError;
-use atom::publish::{self};
+use atom::publish::{self, Warning};
+use atom::report::Report;
 use clap::Parser;
 
+use crate::cli::context::Context;
+use crate::cli::exit::{ExitArgs, Outcome};
+use crate::cli::logging::ansi::{GREEN, RED, YELLOW};
+use crate::cli::output::{Cell, Record};
 use crate::cli::store::Detected;
+use crate::msg;
 
 #[derive(Parser, Debug)]
 #[command(arg_required_else_help = true)]
@@ -16,9 +24,78 @@ pub(in super::super) struct PublishArgs {
     #[arg(long, short, conflicts_with = "path")]
     recursive: bool,
 
+    /// Publish all the atoms in the repository, in dependency order
+    ///
+    /// Atoms are published only after the atoms they depend on by path,
+    /// and path dependencies are rewritten to version requirements in
+    /// the published manifests, so they can be consumed from the store.
+    #[arg(long, conflicts_with_all = ["path", "recursive"], verbatim_doc_comment)]
+    workspace: bool,
+
     /// Path(s) to the atom(s) to publish
-    #[arg(required_unless_present = "recursive")]
+    #[arg(required_unless_present_any = ["recursive", "workspace", "plan"])]
     path: Vec<PathBuf>,
+
+    /// Publish exactly the atoms of a plan written by `eka plan`
+    ///
+    /// The atoms are published from the revision, and to the remote, the
+    /// plan was made for. Publishing fails if the plan's signature is not
+    /// valid, or any atom would no longer be published as planned. The atom
+    /// commits are signed if the plan says so, see `eka plan --sign-atoms`.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["path", "recursive", "workspace"],
+        verbatim_doc_comment
+    )]
+    plan: Option<PathBuf>,
+
+    /// Execute a plan even if it is not signed
+    #[arg(long, requires = "plan")]
+    allow_unsigned: bool,
+
+    /// Fail if any invalid Atom manifest is encountered during validation
+    ///
+    /// By default, invalid manifests are reported and skipped. In strict
+    /// mode they abort publishing entirely, so typos can never silently
+    /// prevent an intended Atom from being published.
+    ///
+    /// Defaults to the `publish.strict` configuration value.
+    #[arg(long, overrides_with = "no_strict", verbatim_doc_comment)]
+    strict: bool,
+
+    /// Skip invalid Atom manifests, even if `publish.strict` is set
+    #[arg(long, overrides_with = "strict")]
+    no_strict: bool,
+
+    /// Publish without asking for confirmation
+    ///
+    /// When attached to a terminal and publishing more Atoms than the
+    /// `publish.confirm-threshold` configuration value (default: 1),
+    /// the planned Atoms are previewed and confirmation is requested.
+    #[arg(long, short = 'y', verbatim_doc_comment)]
+    yes: bool,
+
+    /// Publish Atom versions protected by the store's policy
+    ///
+    /// Without this, publishing a protected version fails unless the
+    /// configured Git identity (`user.email`) is one of its signers, and
+    /// the atom commits are signed with `--sign` if it binds the version
+    /// to keys. The store's hooks check the pusher and the signing key
+    /// regardless.
+    #[arg(long, verbatim_doc_comment)]
+    allow_protected: bool,
+
+    /// Exit successfully if at least one atom was published, even if others failed
+    ///
+    /// Failures are still reported, and included in the results. Without
+    /// this, publishing only some of the atoms exits with 6.
+    ///
+    /// Defaults to the `publish.allow-partial` configuration value.
+    #[arg(long, verbatim_doc_comment)]
+    allow_partial: bool,
+    #[command(flatten)]
+    exit: ExitArgs,
     #[command(flatten)]
     store: StoreArgs,
 }
@@ -30,52 +107,216 @@ struct StoreArgs {
     git: git::GitArgs,
 }
 
-use publish::Stats;
-pub(super) async fn run(store: Detected, args: PublishArgs) -> Result<Stats, PublishError> {
+pub(super) async fn run(ctx: &Context, args: PublishArgs) -> anyhow::Result<Report> {
     #[cfg_attr(not(feature = "stores"), allow(unused_mut))]
-    let mut stats = Stats::default();
-    match store {
+    let mut summary = Report::default();
+    match ctx.store()? {
         #[cfg(feature = "git")]
         Detected::Git(repo) => {
-            use atom::publish::{Content, error};
-            use {Err as Skipped, Ok as Published};
-            let (results, mut errors) = git::run(repo, args).await?;
-
-            for res in results {
-                match res {
-                    Ok(Published(atom)) => {
-                        stats.published += 1;
-                        let Content::Git(content) = atom.content();
-                        tracing::info!(
-                            atom.id = %atom.id().id(),
-                            path = %content.path().display(),
-                            "Atom successfully published"
-                        );
-                        tracing::debug!("published under: {}", content.ref_prefix());
-                    },
-                    Ok(Skipped(id)) => {
-                        stats.skipped += 1;
-                        tracing::info!(atom.id = %id, "Skipping existing atom")
-                    },
-                    Err(e) => {
-                        stats.failed += 1;
-                        errors.push(e)
-                    },
+            let exit = args.exit;
+            let allow_partial = args.allow_partial || ctx.config().publish().allow_partial;
+            if let Some(path) = args.plan.clone() {
+                #[cfg(feature = "s3")]
+                if ctx.object_store().is_some() {
+                    return Err(super::plan::Error::ObjectStore.into());
                 }
+                let plan = super::plan::load(&repo.to_thread_local(), &path, args.allow_unsigned)?;
+                let outcome = git::execute(ctx, repo, args, plan).await?;
+                return conclude(ctx, outcome, exit, allow_partial, report);
             }
-
-            for err in &errors {
-                err.warn()
+            #[cfg(feature = "s3")]
+            if let Some(store) = ctx.object_store() {
+                let (results, warnings) = s3::run(ctx, repo, store, args)?;
+                let outcome = (results, Vec::new(), warnings);
+                return conclude(ctx, outcome, exit, allow_partial, s3::report);
             }
+            let outcome = git::run(ctx, repo, args).await?;
+            summary = conclude(ctx, outcome, exit, allow_partial, report)?;
+        },
+        _ => {},
+    }
 
-            tracing::info!(stats.published, stats.skipped, stats.failed);
+    Ok(summary)
+}
 
-            if !errors.is_empty() {
-                return Err(PublishError::Git(error::git::Error::Failed));
-            }
+/// The results of publishing each Atom, the errors encountered beyond them, e.g. while
+/// pushing, and the warnings collected.
+#[cfg(feature = "git")]
+type Outcomes<S, E> = (
+    Vec<Result<Result<publish::Record<atom::store::git::Root>, S>, E>>,
+    Vec<E>,
+    publish::Warnings,
+);
+
+/// Record the outcome of publishing each Atom, and report the errors encountered, failing if
+/// any Atom failed to publish, unless partial success is allowed, and failing whenever an error
+/// was encountered beyond the Atoms.
+#[cfg(feature = "git")]
+fn conclude<S: std::fmt::Display, E: std::fmt::Display>(
+    ctx: &Context,
+    (results, mut errors, warnings): Outcomes<S, E>,
+    exit: ExitArgs,
+    allow_partial: bool,
+    explain: impl Fn(&E),
+) -> anyhow::Result<Report> {
+    use atom::publish::{Content, error};
+    use {Err as Skipped, Ok as Published};
+
+    let mut summary = Report::default();
+    for warning in &warnings {
+        match warning {
+            // skipped atoms are explained along with the error which skipped them
+            Warning::Skipped(e) => report(e),
+            _ => summary.push(warning.into()),
+        }
+    }
+    let mut sink = ctx.sink();
+
+    for res in results {
+        match res {
+            Ok(Published(atom)) => {
+                summary.count("published");
+                let (path, ref_prefix) = match atom.content() {
+                    Content::Git(content) => (content.path(), content.ref_prefix()),
+                    #[cfg(feature = "s3")]
+                    Content::S3(content) => (content.path(), content.ref_prefix()),
+                };
+                tracing::debug!("published under: {}", ref_prefix);
+                sink.record(&AtomRecord::Published {
+                    id: atom.id().id().to_string(),
+                    path: path.display().to_string(),
+                    ref_prefix,
+                });
+            },
+            Ok(Skipped(id)) => {
+                summary.count("skipped");
+                sink.record(&AtomRecord::Skipped { id: id.to_string() });
+            },
+            Err(e) => {
+                summary.count("failed");
+                errors.push(e)
+            },
+        }
+    }
+
+    for err in &errors {
+        sink.record(&AtomRecord::Failed {
+            reason: err.to_string(),
+        });
+        explain(err)
+    }
+
+    crate::cli::report::finish(&mut *sink, &summary)?;
+    crate::cli::report::summarize(&summary);
+
+    let (published, skipped) = (summary.tally("published"), summary.tally("skipped"));
+    // only the Atoms which failed count towards a partial success, as the errors encountered
+    // beyond them, e.g. while pushing, tell nothing of how many Atoms reached the store
+    let failed = summary.tally("failed");
+    if failed > 0 && published > 0 && failed == errors.len() {
+        let total = published + skipped + failed;
+        let partial = Outcome::Partial { failed, total };
+        if !allow_partial {
+            return Err(partial.into());
+        }
+        tracing::warn!("{partial}");
+    } else if !errors.is_empty() {
+        return Err(PublishError::Git(error::git::Error::Failed).into());
+    }
+    exit.check(published, skipped)?;
+
+    Ok(summary)
+}
+
+/// Render a warning collected while publishing.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+pub(super) fn warn(warning: &Warning) {
+    match warning {
+        #[cfg(feature = "git")]
+        Warning::Skipped(e) => report(e),
+        _ => crate::cli::report::log(&warning.into()),
+    }
+}
+
+/// Render an error encountered while publishing, along with any details which help the user
+/// address it. The warnings it carries, e.g. for the duplicate Atoms which prevented
+/// publishing, are rendered in its place.
+#[cfg(feature = "git")]
+pub(super) fn report(err: &atom::publish::error::git::Error) {
+    use atom::publish::error::git::Error;
+    const INCONSISTENT_ROOT_SUGGESTION: &str =
+        "You may need to reinitalize the remote if the issue persists";
+
+    match err {
+        Error::InconsistentRoot { remote, atom } => {
+            tracing::warn!(
+                message = %err,
+                atom_root = %**atom,
+                remote_root = %**remote,
+                suggest = INCONSISTENT_ROOT_SUGGESTION
+            );
         },
-        _ => {},
+        Error::Invalid(e, path) => {
+            tracing::warn!(message = %err, path = %path.display(), message = format!("\n{}", e));
+        },
+        Error::NotAnAtom(path) => {
+            tracing::warn!(message = %err, path = %path.display());
+        },
+        Error::Duplicates(warnings) | Error::Strict(warnings) => warnings.iter().for_each(warn),
+        Error::Failed => (),
+        _ => tracing::warn!(message = %err),
     }
+}
 
-    Ok(stats)
+/// The outcome of publishing a single Atom.
+enum AtomRecord<'a> {
+    Published {
+        id: String,
+        path: String,
+        ref_prefix: &'a str,
+    },
+    Skipped {
+        id: String,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+impl Record for AtomRecord<'_> {
+    fn row(&self) -> Vec<Cell> {
+        match self {
+            AtomRecord::Published { id, path, .. } => vec![
+                Cell::new(msg!("status-published")).color(GREEN),
+                Cell::new(id),
+                Cell::new(path),
+            ],
+            AtomRecord::Skipped { id } => vec![
+                Cell::new(msg!("status-skipped")).color(YELLOW),
+                Cell::new(id),
+            ],
+            AtomRecord::Failed { reason } => vec![
+                Cell::new(msg!("status-failed")).color(RED),
+                Cell::new(reason),
+            ],
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        use serde_json::json;
+        match self {
+            AtomRecord::Published {
+                id,
+                path,
+                ref_prefix,
+            } => json!({
+                "status": "published",
+                "id": id,
+                "path": path,
+                "ref_prefix": ref_prefix,
+            }),
+            AtomRecord::Skipped { id } => json!({ "status": "skipped", "id": id }),
+            AtomRecord::Failed { reason } => json!({ "status": "failed", "reason": reason }),
+        }
+    }
 }
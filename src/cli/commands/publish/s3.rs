@@ -0,0 +1,94 @@
+//! # Publishing to an Object Store
+//!
+//! Atoms are published from a single revision of the repository to the S3 store given by
+//! `--store s3://<bucket>/<prefix>`, rather than pushed to one of its remotes.
+use std::collections::HashSet;
+
+use atom::publish::Warnings;
+use atom::publish::error::git;
+use atom::publish::error::s3::Error;
+use atom::publish::s3::{S3Outcome, S3Result};
+use atom::store::s3::S3Store;
+use gix::ThreadSafeRepository;
+use thiserror::Error as ThisError;
+
+use super::PublishArgs;
+use crate::cli::context::Context;
+
+#[derive(ThisError, Debug)]
+enum RevisionError {
+    #[error("An object store can only be published to from a single revision, not {0}")]
+    Several(usize),
+}
+
+pub(super) fn run(
+    ctx: &Context,
+    repo: &ThreadSafeRepository,
+    store: &S3Store,
+    args: PublishArgs,
+) -> anyhow::Result<(Vec<S3Result<S3Outcome>>, Warnings)> {
+    use atom::Linter;
+    use atom::publish::s3::S3Publisher;
+    use atom::publish::{Builder, Publish};
+    use atom::store::NormalizeStorePath;
+    let repo = repo.to_thread_local();
+
+    let revisions = atom::store::git::expand_revisions(&repo, &args.store.git.spec)?;
+    let revision = match &revisions[..] {
+        [revision] => revision,
+        [] => return Err(Error::Git(git::Error::NotFound).into()),
+        several => return Err(RevisionError::Several(several.len()).into()),
+    };
+
+    let strict = args.strict || (!args.no_strict && ctx.config().publish().strict);
+    let (atoms, publisher) = S3Publisher::new(&repo, store, revision)?
+        .strict(strict)
+        .lints(Linter::new(ctx.config().lint())?)
+        .kinds(ctx.kinds()?)
+        .current_dir(ctx.cwd())
+        .lexical(args.recursive || args.workspace)
+        .build()
+        .inspect_err(report)?;
+    let mut warnings = publisher.take_warnings();
+
+    let paths: Vec<_> = if args.recursive || args.workspace {
+        let cwd = if args.recursive && !repo.is_bare() {
+            Some(repo.normalize_from(ctx.cwd(), ctx.cwd())?)
+        } else {
+            None
+        };
+        atoms
+            .into_values()
+            .filter(|path| cwd.as_ref().map_or(true, |cwd| path.starts_with(cwd)))
+            .collect()
+    } else {
+        let given: HashSet<_> = args.path.into_iter().collect();
+        given.into_iter().collect()
+    };
+    if paths.is_empty() {
+        return Err(Error::Git(git::Error::NotFound).into());
+    }
+
+    // no Atom may reach the store before the Atoms it depends on by path
+    let levels = if args.workspace {
+        publisher.levels(paths)?
+    } else {
+        vec![paths]
+    };
+
+    let results = levels
+        .into_iter()
+        .flat_map(|paths| publisher.publish(paths))
+        .collect();
+    warnings.extend(publisher.take_warnings());
+
+    Ok((results, warnings))
+}
+
+/// Render an error encountered while publishing to an object store, as for a Git store.
+pub(super) fn report(err: &Error) {
+    match err {
+        Error::Git(e) => super::report(e),
+        _ => tracing::warn!(message = %err),
+    }
+}
@@ -0,0 +1,100 @@
+//! # Renaming
+//!
+//! Redirects the former id of a renamed atom to its new one, once the atom is published under
+//! it, with a `_redirect` ref beside the versions of the former id. Dependents still naming
+//! the atom by its former id are then resolved against the new one, with a warning that it was
+//! renamed, rather than breaking outright.
+use clap::Parser;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The former id of the atom
+    #[arg(value_name = "FROM")]
+    from: String,
+
+    /// The id the atom was renamed to, and is published under
+    #[arg(value_name = "TO")]
+    to: String,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::Id;
+            use atom::store::git::redirect::Redirect;
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            let (from, to) = (Id::try_from(args.from)?, Id::try_from(args.to)?);
+            let redirect = Redirect::plan(&repo, &remote, &from, &to)?;
+            redirect.apply(&repo.find_remote(remote.as_str())?)?;
+
+            let mut sink = ctx.sink();
+            sink.record(&Redirected(&redirect));
+            sink.finish()?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// A renamed atom, redirected to its new id.
+#[cfg(feature = "git")]
+struct Redirected<'a>(&'a atom::store::git::redirect::Redirect);
+
+#[cfg(feature = "git")]
+impl Record for Redirected<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let Redirected(redirect) = self;
+        let status = if redirect.is_unchanged() {
+            Cell::new(msg!("status-skipped")).color(YELLOW)
+        } else {
+            Cell::new(msg!("status-redirected")).color(GREEN)
+        };
+        vec![
+            status,
+            Cell::new(redirect.from()),
+            Cell::new(msg!(
+                "rename-target",
+                id = redirect.to().to_string(),
+                version = redirect.version().to_string(),
+            )),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let Redirected(redirect) = self;
+        serde_json::json!({
+            "status": if redirect.is_unchanged() { "skipped" } else { "redirected" },
+            "from": redirect.from().to_string(),
+            "to": redirect.to().to_string(),
+            "version": redirect.version().to_string(),
+        })
+    }
+}
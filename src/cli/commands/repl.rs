@@ -0,0 +1,108 @@
+//! # Batch Execution
+//!
+//! Runs a sequence of commands within a single process, so that the execution context, and
+//! everything it has already resolved (configuration, the detected store), is shared between
+//! them instead of being rediscovered for every invocation.
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use clap::Parser;
+use thiserror::Error;
+
+use super::Commands;
+use crate::cli::context::Context;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Read commands from the given file instead of standard input
+    ///
+    /// Each line holds a single command without the leading `eka`,
+    /// e.g. `publish --recursive`. Arguments are split as they would
+    /// be by a POSIX shell. Blank lines and lines starting with `#`
+    /// are ignored.
+    #[arg(value_name = "FILE", verbatim_doc_comment)]
+    file: Option<PathBuf>,
+
+    /// Continue with the remaining commands after one fails
+    ///
+    /// By default, the first failing command aborts the batch. When
+    /// reading from an interactive terminal, failures never abort.
+    #[arg(long, short, verbatim_doc_comment)]
+    keep_going: bool,
+}
+
+/// A single command, as read from one line of input.
+#[derive(Parser)]
+#[command(no_binary_name = true, disable_version_flag = true)]
+struct Line {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("line {line}: {error}")]
+    Failed { line: usize, error: anyhow::Error },
+    #[error("{0} command(s) failed")]
+    Failures(usize),
+    #[error("unbalanced quotes or trailing escape")]
+    Quotes,
+    #[error("`repl` cannot be run from within a batch")]
+    Nested,
+    /// A transparent wrapper for a [`std::io::Error`]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub(super) async fn run(ctx: &Context, args: Args) -> Result<(), Error> {
+    let interactive = args.file.is_none() && ctx.output().interactive();
+    let mut input: Box<dyn BufRead + Send> = match &args.file {
+        Some(path) => Box::new(BufReader::new(std::fs::File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+    let keep_going = args.keep_going || interactive;
+
+    let mut failed = 0;
+    let mut buf = String::new();
+    for line in 1.. {
+        if interactive {
+            eprint!("eka> ");
+            io::stderr().flush()?;
+        }
+
+        buf.clear();
+        if input.read_line(&mut buf)? == 0 {
+            break;
+        }
+
+        let cmd = buf.trim();
+        if cmd.is_empty() || cmd.starts_with('#') {
+            continue;
+        }
+
+        if let Err(error) = execute(ctx, cmd).await {
+            if !keep_going {
+                return Err(Error::Failed { line, error });
+            }
+            failed += 1;
+            tracing::error!(line, "{error}");
+        }
+    }
+
+    if failed > 0 && !interactive {
+        return Err(Error::Failures(failed));
+    }
+
+    Ok(())
+}
+
+async fn execute(ctx: &Context, cmd: &str) -> anyhow::Result<()> {
+    let words = shlex::split(cmd).ok_or(Error::Quotes)?;
+    let Line { command } = match Line::try_parse_from(words) {
+        Ok(line) => line,
+        // help output was requested, so there is nothing to run
+        Err(e) if !e.use_stderr() => return Ok(e.print()?),
+        Err(e) => return Err(e.into()),
+    };
+    super::execute(ctx, command).await
+}
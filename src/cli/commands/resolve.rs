@@ -0,0 +1,95 @@
+//! # Dependency Resolution
+//!
+//! Resolves the dependencies an Atom's manifest declares on other Atoms of its store against
+//! the versions published to it, and writes the result to the lock beside the manifest,
+//! reporting how the lock changed. Versions yanked from the store are only kept where the
+//! existing lock already pins them, with a warning. Atoms of other stores are locked from the
+//! store at their url, or the first of its mirrors to be reached, which the lock records.
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::context::Context;
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path to the manifest of the atom to resolve
+    path: PathBuf,
+
+    /// Only report how the lock would change, without writing it
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::git;
+            use atom::{Lockfile, Manifest};
+
+            use super::graph::Diffed;
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            let store = repo.find_remote(remote.as_str())?;
+
+            let path = ctx.cwd().join(&args.path);
+            let manifest: Manifest = std::fs::read_to_string(&path)?.parse()?;
+
+            let lock_path = Lockfile::path(&path);
+            let old = match std::fs::read_to_string(&lock_path) {
+                Ok(old) => old.parse()?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Lockfile::default(),
+                Err(e) => return Err(e.into()),
+            };
+            let lock = git::resolve(&store, &manifest, &old)?;
+
+            let yanked = git::Snapshot::query(&repo, &remote, ctx.config())?.yanked();
+            for atom in lock.atoms.iter().filter(|atom| atom.store.is_none()) {
+                if yanked
+                    .get(atom.published_id())
+                    .is_some_and(|versions| versions.contains(&atom.version))
+                {
+                    let version = atom.version.to_string();
+                    tracing::warn!(
+                        "{}",
+                        msg!("yank-locked", id = atom.id.as_str(), version = version)
+                    );
+                }
+            }
+            if !args.dry_run {
+                std::fs::write(&lock_path, lock.to_toml()?)?;
+            }
+
+            let mut sink = ctx.sink();
+            for change in old.diff(&lock) {
+                sink.record(&Diffed(change));
+            }
+            sink.finish()?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
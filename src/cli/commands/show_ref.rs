@@ -0,0 +1,98 @@
+//! # Atom Commit Introspection
+//!
+//! Prints the extra headers of a published Atom commit and, on request, checks them against
+//! those the current publisher would write, to debug stores written by older or third-party
+//! publishers.
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, RED};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The Atom ref, or any revision naming an Atom commit, to inspect
+    #[arg(name = "REF")]
+    reference: String,
+
+    /// Check the headers against those the current publisher would write
+    #[arg(long)]
+    verify_headers: bool,
+}
+
+#[derive(Error, Debug)]
+pub(super) enum Error {
+    #[error("Found {0} issue(s) with the headers of the Atom commit")]
+    Invalid(usize),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::git::verify;
+
+            let repo = repo.to_thread_local();
+            let id = repo.rev_parse_single(args.reference.as_str())?.detach();
+            let headers = verify::inspect_headers(&repo, &id)?;
+
+            let mut sink = ctx.sink();
+            for (key, value) in &headers.entries {
+                sink.record(&Line::Header { key, value });
+            }
+            if args.verify_headers {
+                for issue in &headers.issues {
+                    sink.record(&Line::Issue(issue));
+                }
+                if headers.issues.is_empty() {
+                    sink.record(&Line::Verified);
+                }
+            }
+            sink.finish()?;
+
+            if args.verify_headers && !headers.issues.is_empty() {
+                return Err(Error::Invalid(headers.issues.len()).into());
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// A header of the Atom commit, or the outcome of checking them.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+enum Line<'a> {
+    Header { key: &'a str, value: &'a str },
+    Issue(&'a str),
+    Verified,
+}
+
+impl Record for Line<'_> {
+    fn row(&self) -> Vec<Cell> {
+        match self {
+            Line::Header { key, value } => vec![Cell::new(key), Cell::new(value)],
+            Line::Issue(reason) => vec![
+                Cell::new(msg!("status-invalid")).color(RED),
+                Cell::new(reason),
+            ],
+            Line::Verified => vec![Cell::new(msg!("status-verified")).color(GREEN)],
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Line::Header { key, value } => serde_json::json!({
+                "header": key,
+                "value": value,
+            }),
+            Line::Issue(reason) => serde_json::json!({
+                "status": "invalid",
+                "reason": reason,
+            }),
+            Line::Verified => serde_json::json!({ "status": "verified" }),
+        }
+    }
+}
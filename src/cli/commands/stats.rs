@@ -0,0 +1,95 @@
+//! # Usage Statistics
+//!
+//! Summarizes the usage metrics recorded locally by [`crate::cli::metrics`], per command, so
+//! that teams can see which of their workflows are the slowest.
+use std::collections::BTreeMap;
+
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::metrics::Metrics;
+use crate::cli::output::{Cell, Record};
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Summarize the metrics recorded for eka itself on this machine
+    // required, as the metrics of this machine are the only ones to summarize for now
+    #[arg(long = "self", required = true)]
+    #[allow(dead_code)]
+    own: bool,
+}
+
+#[derive(Error, Debug)]
+enum Error {
+    #[error("No data directory could be determined for this platform")]
+    NoDataDir,
+}
+
+/// The runs of a single command.
+#[derive(Default)]
+struct Summary<'a> {
+    command: &'a str,
+    count: u64,
+    total_ms: f64,
+    max_ms: f64,
+}
+
+pub(super) fn run(ctx: &Context, _args: Args) -> anyhow::Result<()> {
+    let dir = ctx.config().data_dir().ok_or(Error::NoDataDir)?;
+    let runs = Metrics::read(&dir)?;
+
+    if runs.is_empty() {
+        tracing::warn!("{}", msg!("stats-empty"));
+    }
+
+    let mut summaries: BTreeMap<&str, Summary> = BTreeMap::new();
+    for run in &runs {
+        let summary = summaries.entry(run.command.as_str()).or_default();
+        summary.command = &run.command;
+        summary.count += 1;
+        summary.total_ms += run.ms;
+        summary.max_ms = summary.max_ms.max(run.ms);
+    }
+
+    // the commands taking the most time overall first
+    let mut summaries: Vec<_> = summaries.into_values().collect();
+    summaries.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+
+    let mut sink = ctx.sink();
+    for summary in &summaries {
+        sink.record(summary);
+    }
+    sink.finish()?;
+    Ok(())
+}
+
+impl Summary<'_> {
+    fn mean_ms(&self) -> f64 {
+        self.total_ms / self.count as f64
+    }
+}
+
+impl Record for Summary<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let ms = |ms: f64| format!("{ms:.0}ms");
+        vec![
+            Cell::new(self.command),
+            Cell::new(msg!("stats-runs", count = self.count)),
+            Cell::new(msg!("stats-total", time = ms(self.total_ms))),
+            Cell::new(msg!("stats-mean", time = ms(self.mean_ms()))),
+            Cell::new(msg!("stats-max", time = ms(self.max_ms))),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "command": self.command,
+            "count": self.count,
+            "total_ms": self.total_ms,
+            "mean_ms": self.mean_ms(),
+            "max_ms": self.max_ms,
+        })
+    }
+}
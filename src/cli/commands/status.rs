@@ -0,0 +1,184 @@
+//! # Atom Status
+//!
+//! Compares the atoms of the working tree with the store: each atom is re-created exactly as
+//! `eka publish` would publish it, and its current version is looked up among the refs of the
+//! remote store. An atom is then either unpublished, published with the very same content, or
+//! modified since its current version was published, i.e. in need of a version bump.
+//!
+//! Published atoms are told apart by the tree of their atom commit, which neither the revision
+//! they were published from, nor their signature, changes. The atom commits the repository
+//! lacks are fetched from the store, without the history of the repository they were
+//! published from.
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Path(s) to the atom(s) to report the status of
+    ///
+    /// [default: all the atoms in and under the current working directory]
+    #[arg(verbatim_doc_comment)]
+    path: Vec<PathBuf>,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+        /// The revision to compare the atom(s) as of
+        #[arg(long, short, default_value = "HEAD", name = "REVSPEC")]
+        pub(super) spec: String,
+    }
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::Linter;
+            use atom::publish::Builder;
+            use atom::publish::error::git::Error as GitError;
+            use atom::publish::git::GitPublisher;
+            use atom::store::{NormalizeStorePath, git};
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            let all = args.path.is_empty();
+            let (atoms, publisher) = GitPublisher::new(&repo, &remote, &args.git.spec)?
+                .lints(Linter::new(ctx.config().lint())?)
+                .kinds(ctx.kinds()?)
+                .current_dir(ctx.cwd())
+                .lexical(all)
+                .build()
+                .inspect_err(super::publish::report)?;
+            publisher
+                .take_warnings()
+                .iter()
+                .for_each(super::publish::warn);
+
+            let mut paths: Vec<_> = if all {
+                let cwd = if repo.is_bare() {
+                    None
+                } else {
+                    Some(repo.normalize_from(ctx.cwd(), ctx.cwd())?)
+                };
+                atoms
+                    .into_values()
+                    .filter(|path| cwd.as_ref().map_or(true, |cwd| path.starts_with(cwd)))
+                    .collect()
+            } else {
+                args.path
+            };
+            if paths.is_empty() {
+                return Err(GitError::NotFound.into());
+            }
+            paths.sort_unstable();
+
+            let snapshot = git::Snapshot::query(&repo, &remote, ctx.config())?;
+            let store = repo.find_remote(remote.as_str())?;
+            let mut sink = ctx.sink();
+            for plan in publisher.plan(paths) {
+                let plan = plan.inspect_err(super::publish::report)?;
+                let [name, ..] = plan.refs();
+                let state = match snapshot.get(&name) {
+                    None => State::Unpublished,
+                    Some(commit) if commit == plan.commit() => State::Published,
+                    Some(commit) => {
+                        let id = plan.id().id().as_str();
+                        let tree = match repo.find_commit(commit) {
+                            Ok(commit) => commit.tree_id()?.detach(),
+                            Err(_) => git::fetch_content(&store, id, plan.version())?,
+                        };
+                        if tree == plan.tree() {
+                            State::Published
+                        } else {
+                            State::Modified
+                        }
+                    },
+                };
+                sink.record(&Status {
+                    state,
+                    id: plan.id().id().to_string(),
+                    version: plan.version().to_string(),
+                    path: plan.path().display().to_string(),
+                    published: snapshot.get(&name).map(|c| c.to_string()),
+                });
+            }
+            sink.finish()?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// How an atom of the working tree compares with the store.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+enum State {
+    /// Its current version is not published to the store.
+    Unpublished,
+    /// Its current version is published to the store, with the very same content.
+    Published,
+    /// Its current version is published to the store, but with other content.
+    Modified,
+}
+
+/// The status of an atom of the working tree.
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+struct Status {
+    state: State,
+    id: String,
+    version: String,
+    path: String,
+    /// The atom commit its current version is published as, if it is.
+    published: Option<String>,
+}
+
+impl Record for Status {
+    fn row(&self) -> Vec<Cell> {
+        let state = match self.state {
+            State::Unpublished => Cell::new(msg!("status-unpublished")).color(YELLOW),
+            State::Published => Cell::new(msg!("status-published")).color(GREEN),
+            State::Modified => Cell::new(msg!("status-modified")).color(RED),
+        };
+        vec![
+            state,
+            Cell::new(&self.id),
+            Cell::new(&self.version),
+            Cell::new(&self.path),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let status = match self.state {
+            State::Unpublished => "unpublished",
+            State::Published => "published",
+            State::Modified => "modified",
+        };
+        serde_json::json!({
+            "status": status,
+            "id": self.id,
+            "version": self.version,
+            "path": self.path,
+            "published": self.published,
+        })
+    }
+}
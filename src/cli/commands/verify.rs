@@ -0,0 +1,449 @@
+//! # Atom Verification
+//!
+//! Verifies a single published Atom from the client's side, by re-creating it from the source
+//! it claims to originate from, and comparing the two, so anyone can check an Atom before
+//! trusting it. Once `signing.allowed-keys` is configured, the atom must also be signed by
+//! one of the keys it lists.
+//!
+//! With `--all`, re-verifies the Atoms already in a store instead, as its hooks verified them
+//! when they were pushed, and recomputes their content from their sources, so operators can
+//! catch corruption or tampering at rest, e.g. from a nightly job.
+//!
+//! With `--lock`, verifies that every atom a lock pins is still fetchable right now, from the
+//! store it pins it from, as the very atom commit it pins, so a release can be checked for
+//! reproducibility long after it was cut. With `--attest`, the outcome is also written as an
+//! attestation document, along with a detached signature by the configured signing key, to be
+//! stored beside the release's artifacts for later audits. Pins and sources are not locked to
+//! any hash yet, so they are listed in the attestation as unlocked, rather than verified.
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use atom::report::{Diagnostic, Report};
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::dashboard::Status;
+use crate::cli::logging::ansi::{GREEN, RED};
+use crate::cli::output::{Cell, Record};
+use crate::cli::report;
+use crate::cli::store::{Detected, RemoteArg};
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The atom to verify against its source, as a uri, e.g. `my-atom@^1`,
+    /// or the name of one of its refs, e.g. `refs/atoms/my-atom/1.0.0/atom`
+    #[arg(
+        value_name = "ATOM",
+        required_unless_present_any = ["all", "lock"],
+        conflicts_with_all = ["all", "lock"],
+        verbatim_doc_comment
+    )]
+    atom: Option<String>,
+
+    /// Verify every atom version published to the store
+    #[arg(long, conflicts_with_all = ["lock", "TARGET"])]
+    all: bool,
+
+    /// Verify every atom pinned by a lock, given by its path, or that of the manifest it locks
+    #[arg(long, value_name = "PATH")]
+    lock: Option<PathBuf>,
+
+    /// Write an attestation of the lock's verification to the given path, signed with the
+    /// configured key, whose detached signature is written beside it with a `.sig` extension
+    #[arg(long, value_name = "PATH", requires = "lock", verbatim_doc_comment)]
+    attest: Option<PathBuf>,
+
+    /// The number of atoms to verify at once
+    ///
+    /// [default: the available parallelism]
+    #[arg(
+        long,
+        short = 'j',
+        value_name = "N",
+        requires = "all",
+        verbatim_doc_comment
+    )]
+    jobs: Option<NonZeroUsize>,
+
+    #[command(flatten)]
+    remote: RemoteArg,
+}
+
+#[derive(Error, Debug)]
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+pub(super) enum Error {
+    #[error("Failed to verify {0} of {1} atom version(s)")]
+    Failed(usize, usize),
+    #[error("`{0}@{1}` does not match the source it claims to be published from")]
+    Tampered(String, String),
+    #[error("No lock was found at `{}`, resolve it with `eka resolve`", .0.display())]
+    NoLock(PathBuf),
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::git::verify;
+
+            if let Some(atom) = &args.atom {
+                let repo = repo.to_thread_local();
+                return verify_atom(ctx, &repo, atom, args.remote.as_deref());
+            }
+            if let Some(lock) = &args.lock {
+                let repo = repo.to_thread_local();
+                return verify_lock(
+                    ctx,
+                    &repo,
+                    lock,
+                    args.attest.as_deref(),
+                    args.remote.as_deref(),
+                );
+            }
+
+            let jobs = args
+                .jobs
+                .or_else(|| std::thread::available_parallelism().ok())
+                .unwrap_or(NonZeroUsize::MIN);
+            let atoms = verify::published(&repo.to_thread_local())?;
+            let outcomes = match ctx.dashboard(&msg!("dashboard-verify"), atoms.len()) {
+                Some(dashboard) => verify::verify_each(&repo, atoms, jobs, |atom, outcome| {
+                    let name = format!("{}@{}", atom.id, atom.version);
+                    let status = match outcome {
+                        Ok(()) => Status::Done,
+                        Err(e) => {
+                            dashboard.error(format_args!("{name}: {e}"));
+                            Status::Failed
+                        },
+                    };
+                    dashboard.end(name, status);
+                })?,
+                // the atoms are listed again, but their progress is reported on stderr
+                None => verify::verify_all(&repo, jobs)?,
+            };
+
+            let mut sink = ctx.sink();
+            let mut summary = Report::default();
+            for (atom, outcome) in &outcomes {
+                let status = if outcome.is_ok() {
+                    "verified"
+                } else {
+                    "invalid"
+                };
+                summary.count(status);
+                sink.record(&Verified {
+                    atom,
+                    error: outcome.as_ref().err(),
+                });
+            }
+            report::finish(&mut *sink, &summary)?;
+            conclude(&summary)?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Verify the atom given by a uri or ref against its source, fetching both from the remote.
+#[cfg(feature = "git")]
+fn verify_atom(
+    ctx: &Context,
+    repo: &gix::Repository,
+    atom: &str,
+    remote: Option<&str>,
+) -> anyhow::Result<()> {
+    use atom::fetch::Fetch;
+    use atom::uri::Uri;
+    use atom::verify;
+
+    let keys = &ctx.config().signing().allowed_keys;
+    let verification = match verify::parse_ref(atom) {
+        Some((id, version)) => {
+            let remote = repo.find_remote(ctx.remote(repo, remote)?.as_str())?;
+            verify::verify_published(&remote, &id.to_string(), &version, keys)?
+        },
+        None => {
+            let uri = Uri::parse_with(atom, ctx.config().aliases())?;
+            let fetcher = ctx.fetcher(repo, &uri, remote)?;
+            let version = fetcher.resolve(&uri)?;
+            verify::verify_published(fetcher.remote(), &uri.id().to_string(), &version, keys)?
+        },
+    };
+
+    let mut sink = ctx.sink();
+    sink.record(&Recreated(&verification));
+    sink.finish()?;
+
+    if !verification.is_authentic() {
+        return Err(Error::Tampered(
+            verification.id().to_owned(),
+            verification.version().to_owned(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// The format of the attestation documents written by `--attest`.
+#[cfg(feature = "git")]
+const ATTESTATION_FORMAT: &str = "eka-lock-attestation/v1";
+
+/// Verify every atom pinned by the lock at `path`, or beside the manifest at `path`, and
+/// attest the outcome to `attest`, if given.
+#[cfg(feature = "git")]
+fn verify_lock(
+    ctx: &Context,
+    repo: &gix::Repository,
+    path: &std::path::Path,
+    attest: Option<&std::path::Path>,
+    remote: Option<&str>,
+) -> anyhow::Result<()> {
+    use atom::store::git;
+    use atom::{Lockfile, Manifest};
+
+    let path = ctx.cwd().join(path);
+    let (lock_path, manifest) = if path.extension().is_some_and(|ext| ext == "lock") {
+        (path, None)
+    } else {
+        (Lockfile::path(&path), Some(path))
+    };
+    let content = match std::fs::read(&lock_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::NoLock(lock_path).into());
+        },
+        Err(e) => return Err(e.into()),
+    };
+    let lock: Lockfile = std::str::from_utf8(&content)?.parse()?;
+    let manifest: Option<Manifest> = match &manifest {
+        Some(path) => Some(std::fs::read_to_string(path)?.parse()?),
+        None => None,
+    };
+
+    let store = repo.find_remote(ctx.remote(repo, remote)?.as_str())?;
+    let mut sink = ctx.sink();
+    let mut summary = Report::default();
+    let mut entries = Vec::new();
+    for atom in &lock.atoms {
+        let outcome = git::verify_locked(&store, atom);
+        let status = if outcome.is_ok() {
+            "verified"
+        } else {
+            "invalid"
+        };
+        summary.count(status);
+        let checked = Checked {
+            atom,
+            outcome: outcome.as_ref(),
+        };
+        sink.record(&checked);
+        entries.push(checked.to_json());
+    }
+
+    // pins and sources are not locked to any hash, so there is nothing to verify them against
+    let mut unlocked = Vec::new();
+    if let Some(manifest) = &manifest {
+        let pins = manifest
+            .deps
+            .pins
+            .iter()
+            .map(|(name, pin)| ("pin", name, &pin.url));
+        let srcs = manifest
+            .deps
+            .srcs
+            .iter()
+            .map(|(name, src)| ("src", name, &src.url));
+        for (kind, name, url) in pins.chain(srcs) {
+            let note = format!("Skipping a {kind} from `{url}`, which is not locked to any hash");
+            summary.push(Diagnostic::note("unlocked", note).about(name));
+            unlocked.push(serde_json::json!({ "kind": kind, "name": name, "url": url.as_str() }));
+        }
+    }
+    report::finish(&mut *sink, &summary)?;
+
+    if let Some(attest) = attest {
+        let verified_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let digest = gix::objs::compute_hash(repo.object_hash(), gix::object::Kind::Blob, &content);
+        let document = serde_json::json!({
+            "format": ATTESTATION_FORMAT,
+            "lock": {
+                "path": lock_path.display().to_string(),
+                "blob": digest.to_string(),
+            },
+            "verified-at": verified_at,
+            "verified": summary.tally("invalid") == 0,
+            "atoms": entries,
+            "unlocked": unlocked,
+        });
+        let mut document = serde_json::to_string_pretty(&document)?;
+        document.push('\n');
+
+        let signer = git::sign::Signer::from_config(repo, ctx.config().signing())?;
+        let signature = signer.sign(document.as_bytes())?;
+        let mut sig_path = attest.as_os_str().to_owned();
+        sig_path.push(".sig");
+        let attest = ctx.cwd().join(attest);
+        let sig_path = ctx.cwd().join(sig_path);
+        std::fs::write(&attest, document)?;
+        std::fs::write(&sig_path, signature)?;
+        tracing::info!(
+            "{}",
+            msg!(
+                "verify-attested",
+                path = attest.display().to_string(),
+                signature = sig_path.display().to_string(),
+            )
+        );
+    }
+
+    conclude(&summary)
+}
+
+/// Summarize the atoms verified, failing if any of them is invalid.
+#[cfg(feature = "git")]
+fn conclude(summary: &Report) -> Result<(), Error> {
+    let (verified, failed) = (summary.tally("verified"), summary.tally("invalid"));
+    tracing::info!(
+        "{}",
+        msg!("verify-summary", verified = verified, failed = failed)
+    );
+    if failed > 0 {
+        return Err(Error::Failed(failed, verified + failed));
+    }
+    Ok(())
+}
+
+/// An atom pinned by a lock, checked against the store it is pinned from.
+#[cfg(feature = "git")]
+struct Checked<'a> {
+    atom: &'a atom::LockedAtom,
+    outcome: Result<&'a gix::ObjectId, &'a atom::store::git::Error>,
+}
+
+#[cfg(feature = "git")]
+impl Record for Checked<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let mut row = match self.outcome {
+            Ok(_) => vec![Cell::new(msg!("status-verified")).color(GREEN)],
+            Err(_) => vec![Cell::new(msg!("status-invalid")).color(RED)],
+        };
+        row.extend([
+            Cell::new(&self.atom.id),
+            Cell::new(&self.atom.version),
+            Cell::new(self.atom.store.as_deref().unwrap_or_default()),
+        ]);
+        if let Err(e) = self.outcome {
+            row.push(Cell::new(e));
+        }
+        row
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "status": if self.outcome.is_ok() { "verified" } else { "invalid" },
+            "id": self.atom.id.to_string(),
+            "version": self.atom.version.to_string(),
+            "rev": self.atom.rev.to_string(),
+            "store": self.atom.store,
+            "root": self.atom.root.map(|root| root.to_string()),
+        });
+        match self.outcome {
+            Ok(content) => json["content"] = content.to_string().into(),
+            Err(e) => json["reason"] = e.to_string().into(),
+        }
+        json
+    }
+}
+
+/// An atom verified against the source it claims to be published from.
+#[cfg(feature = "git")]
+struct Recreated<'a>(&'a atom::verify::Verification);
+
+#[cfg(feature = "git")]
+impl Record for Recreated<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let Recreated(verification) = self;
+        let status = if verification.is_authentic() {
+            Cell::new(msg!("status-verified")).color(GREEN)
+        } else {
+            Cell::new(msg!("status-tampered")).color(RED)
+        };
+        let mut row = vec![
+            status,
+            Cell::new(verification.id()),
+            Cell::new(verification.version()),
+            Cell::new(msg!(
+                "verify-source",
+                origin = verification.origin().to_string(),
+                path = verification.path().display().to_string(),
+            )),
+        ];
+        let tampered: Vec<_> = verification
+            .tampered()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if !tampered.is_empty() {
+            row.push(Cell::new(tampered.join("; ")));
+        }
+        row
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let Recreated(verification) = self;
+        let tampered: Vec<_> = verification
+            .tampered()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        serde_json::json!({
+            "status": if verification.is_authentic() { "verified" } else { "tampered" },
+            "id": verification.id(),
+            "version": verification.version(),
+            "commit": verification.commit().to_string(),
+            "origin": verification.origin().to_string(),
+            "path": verification.path(),
+            "recreated": verification.recreated().map(|id| id.to_string()),
+            "signer": verification.signer(),
+            "tampered": tampered,
+        })
+    }
+}
+
+/// The outcome of verifying a single published atom version.
+#[cfg(feature = "git")]
+struct Verified<'a> {
+    atom: &'a atom::store::git::verify::Published,
+    error: Option<&'a atom::store::git::verify::Error>,
+}
+
+#[cfg(feature = "git")]
+impl Record for Verified<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let mut row = match self.error {
+            None => vec![Cell::new(msg!("status-verified")).color(GREEN)],
+            Some(_) => vec![Cell::new(msg!("status-invalid")).color(RED)],
+        };
+        row.extend([Cell::new(&self.atom.id), Cell::new(&self.atom.version)]);
+        if let Some(e) = self.error {
+            row.push(Cell::new(e));
+        }
+        row
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "status": if self.error.is_some() { "invalid" } else { "verified" },
+            "id": self.atom.id.to_string(),
+            "version": self.atom.version.to_string(),
+        });
+        if let Some(e) = self.error {
+            json["reason"] = e.to_string().into();
+        }
+        json
+    }
+}
@@ -0,0 +1,153 @@
+//! # Version Bumps
+//!
+//! Bumps the version of an Atom in its manifest, or sets it outright, editing the manifest in
+//! place so that the rest of it, including its formatting and comments, is left untouched.
+//! With `--check`, the new version is first checked not to be published to the store already.
+use std::path::PathBuf;
+
+use atom::{Bump, Manifest};
+use clap::{Parser, ValueEnum};
+use semver::Version;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct Args {
+    /// Path to the manifest of the atom to bump
+    path: PathBuf,
+
+    /// The component of the version to bump
+    #[arg(long, value_enum, required_unless_present = "set")]
+    bump: Option<Part>,
+
+    /// Set the version outright, rather than bumping it
+    #[arg(long, value_name = "VERSION", conflicts_with = "bump")]
+    set: Option<Version>,
+
+    /// Fail if the new version is already published to the store
+    #[arg(long)]
+    check: bool,
+
+    /// Only report the new version, without writing the manifest
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Part {
+    Major,
+    Minor,
+    Patch,
+}
+
+#[derive(Error, Debug)]
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+pub(super) enum Error {
+    #[error("`{0}@{1}` is already published to the store")]
+    Published(String, Version),
+    #[error("No Git store was found to check the new version against")]
+    NoStore,
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    let bump = match (args.bump, args.set) {
+        (_, Some(version)) => Bump::To(version),
+        (Some(Part::Major), None) => Bump::Major,
+        (Some(Part::Minor), None) => Bump::Minor,
+        (Some(Part::Patch) | None, None) => Bump::Patch,
+    };
+
+    let path = ctx.cwd().join(&args.path);
+    let bumped = Manifest::bump_version(&std::fs::read_to_string(&path)?, &bump)?;
+    let id = Manifest::get_atom(&bumped.content)?.id;
+
+    if args.check {
+        match ctx.store()? {
+            #[cfg(feature = "git")]
+            Detected::Git(repo) => {
+                let repo = repo.to_thread_local();
+                let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+                let published =
+                    atom::store::git::Snapshot::query(&repo, &remote, ctx.config())?.published();
+                if published.get(&id).is_some_and(|v| v.contains(&bumped.new)) {
+                    return Err(Error::Published(id.to_string(), bumped.new).into());
+                }
+            },
+            _ => return Err(Error::NoStore.into()),
+        }
+    }
+
+    if !args.dry_run {
+        std::fs::write(&path, &bumped.content)?;
+    }
+
+    let mut sink = ctx.sink();
+    sink.record(&Bumped {
+        id: id.to_string(),
+        bumped: &bumped,
+        dry_run: args.dry_run,
+    });
+    sink.finish()?;
+    Ok(())
+}
+
+/// The version of an atom, as bumped in its manifest.
+struct Bumped<'a> {
+    id: String,
+    bumped: &'a atom::Bumped,
+    dry_run: bool,
+}
+
+impl Record for Bumped<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let status = if self.bumped.old == self.bumped.new {
+            Cell::new(msg!("status-skipped")).color(YELLOW)
+        } else {
+            Cell::new(msg!("status-bumped")).color(GREEN)
+        };
+        vec![
+            status,
+            Cell::new(&self.id),
+            Cell::new(msg!(
+                "version-bumped",
+                old = self.bumped.old.to_string(),
+                new = self.bumped.new.to_string()
+            )),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": if self.bumped.old == self.bumped.new { "skipped" } else { "bumped" },
+            "id": self.id,
+            "old": self.bumped.old.to_string(),
+            "new": self.bumped.new.to_string(),
+            "written": !self.dry_run,
+        })
+    }
+}
@@ -0,0 +1,115 @@
+//! # Store Watching
+//!
+//! Reports the Atom refs added, moved or removed in a store since the last poll, from a cursor
+//! persisted between runs, so bots and mirrors need not diff the entire ref list each time.
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{GREEN, RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The store to watch
+    ///
+    /// [default: `publish.default-remote`, the push remote configured in git,
+    /// a remote named `ekala`, the only remote, or `origin`]
+    #[arg(name = "STORE", verbatim_doc_comment)]
+    store: Option<String>,
+
+    /// The file the position of the watch is persisted in between runs
+    ///
+    /// [default: `ekala/watch/<STORE>.toml` in the git directory]
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    cursor: Option<PathBuf>,
+
+    /// Keep polling the store, waiting this many seconds between polls
+    ///
+    /// Without this, the store is polled once, reporting the changes
+    /// since the last run, e.g. for a periodic job.
+    #[arg(long, value_name = "SECONDS", verbatim_doc_comment)]
+    interval: Option<u64>,
+}
+
+pub(super) async fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::git::watch::{self, Cursor};
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.store.as_deref())?;
+            let path = args.cursor.unwrap_or_else(|| {
+                repo.git_dir()
+                    .join("ekala/watch")
+                    .join(format!("{remote}.toml"))
+            });
+            let mut cursor = Cursor::load(&path)?;
+
+            loop {
+                let events = watch::poll(&repo, &remote, &mut cursor)?;
+                cursor.save(&path)?;
+
+                let mut sink = ctx.sink();
+                for event in &events {
+                    sink.record(&Change(event));
+                }
+                sink.finish()?;
+
+                let Some(interval) = args.interval else {
+                    break;
+                };
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// A change to an Atom ref of the store.
+#[cfg(feature = "git")]
+struct Change<'a>(&'a atom::store::git::watch::Event);
+
+#[cfg(feature = "git")]
+impl Record for Change<'_> {
+    fn row(&self) -> Vec<Cell> {
+        use atom::store::git::watch::Event;
+        match self.0 {
+            Event::Added { name, id } => vec![
+                Cell::new(msg!("status-added")).color(GREEN),
+                Cell::new(name),
+                Cell::new(id),
+            ],
+            Event::Changed { name, new, .. } => vec![
+                Cell::new(msg!("status-changed")).color(YELLOW),
+                Cell::new(name),
+                Cell::new(new),
+            ],
+            Event::Removed { name, .. } => vec![
+                Cell::new(msg!("status-removed")).color(RED),
+                Cell::new(name),
+            ],
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        use atom::store::git::watch::Event;
+        use serde_json::json;
+        match self.0 {
+            Event::Added { name, id } => json!({ "status": "added", "ref": name, "new": id }),
+            Event::Changed { name, old, new } => json!({
+                "status": "changed",
+                "ref": name,
+                "old": old,
+                "new": new,
+            }),
+            Event::Removed { name, old } => json!({ "status": "removed", "ref": name, "old": old }),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! # Yanking
+//!
+//! Withdraws a published atom version from resolution, by marking it yanked in the store, so
+//! that no requirement resolves to it anymore, while it remains in the store. With `--force`,
+//! the version is deleted from the store outright instead, which is confirmed first.
+use clap::Parser;
+use semver::Version;
+use thiserror::Error;
+
+use crate::cli::context::Context;
+use crate::cli::logging::ansi::{RED, YELLOW};
+use crate::cli::output::{Cell, Record};
+use crate::cli::store::Detected;
+use crate::msg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The atom version to yank, e.g. `my-atom@1.0.0`
+    #[arg(value_name = "ATOM@VERSION", value_parser = parse_atom)]
+    atom: (String, Version),
+
+    /// Delete the version's refs from the store, rather than marking it yanked
+    ///
+    /// The version is marked yanked first, as the store's hooks only
+    /// accept the deletion of yanked versions. Its source ref is kept
+    /// as long as another published version was published from the
+    /// same commit.
+    #[arg(long, verbatim_doc_comment)]
+    force: bool,
+
+    /// Delete without asking for confirmation
+    ///
+    /// Required with `--force` when not attached to a terminal.
+    #[arg(long, short = 'y', requires = "force", verbatim_doc_comment)]
+    yes: bool,
+
+    #[command(flatten)]
+    #[cfg(feature = "git")]
+    git: git::Args,
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use clap::Parser;
+
+    use crate::cli::store::RemoteArg;
+
+    #[derive(Parser, Debug)]
+    #[command(next_help_heading = "Git Options")]
+    #[group(id = "git_args")]
+    pub(super) struct Args {
+        #[command(flatten)]
+        pub(super) remote: RemoteArg,
+    }
+}
+
+#[derive(Error, Debug)]
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+pub(super) enum Error {
+    #[error("`{0}` is not an atom version, expected `<atom-id>@<version>`")]
+    Malformed(String),
+    #[error("Refusing to delete without confirmation, pass `--yes` to proceed")]
+    Unconfirmed,
+}
+
+/// Parse an atom version of the form `<atom-id>@<version>`.
+fn parse_atom(atom: &str) -> Result<(String, Version), Error> {
+    let malformed = || Error::Malformed(atom.to_owned());
+    let (id, version) = atom.split_once('@').ok_or_else(malformed)?;
+    let version = Version::parse(version).map_err(|_| malformed())?;
+    Ok((id.to_owned(), version))
+}
+
+pub(super) fn run(ctx: &Context, args: Args) -> anyhow::Result<()> {
+    match ctx.store()? {
+        #[cfg(feature = "git")]
+        Detected::Git(repo) => {
+            use atom::store::git::yank::Yank;
+
+            let repo = repo.to_thread_local();
+            let remote = ctx.remote(&repo, args.git.remote.as_deref())?;
+            let (id, version) = &args.atom;
+            let yank = Yank::plan(&repo, &remote, id, version, args.force)?;
+
+            if yank.is_deletion() {
+                let version = version.to_string();
+                tracing::warn!(
+                    "{}",
+                    msg!("yank-warning", id = id.as_str(), version = version)
+                );
+                if !args.yes && !confirm(ctx)? {
+                    tracing::warn!("{}", msg!("yank-cancelled"));
+                    return Ok(());
+                }
+            }
+            yank.apply(&repo.find_remote(remote.as_str())?)?;
+
+            let mut sink = ctx.sink();
+            sink.record(&Yanked(&yank));
+            sink.finish()?;
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Ask the user to confirm deleting the version, refusing when no user can answer.
+#[cfg(feature = "git")]
+fn confirm(ctx: &Context) -> anyhow::Result<bool> {
+    use std::io::{self, BufRead, Write};
+
+    if !ctx.output().interactive() {
+        return Err(Error::Unconfirmed.into());
+    }
+
+    let mut stderr = io::stderr().lock();
+    write!(stderr, "{} ", msg!("yank-confirm"))?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    let answer = answer.trim().to_lowercase();
+    let yes = msg!("publish-confirm-yes");
+
+    Ok(answer == "y" || answer == "yes" || (!answer.is_empty() && yes.starts_with(&answer)))
+}
+
+/// A yanked atom version.
+#[cfg(feature = "git")]
+struct Yanked<'a>(&'a atom::store::git::yank::Yank);
+
+#[cfg(feature = "git")]
+impl Record for Yanked<'_> {
+    fn row(&self) -> Vec<Cell> {
+        let Yanked(yank) = self;
+        let (status, color) = match (yank.is_deletion(), yank.was_yanked()) {
+            (true, _) => (msg!("status-deleted"), RED),
+            (false, true) => (msg!("status-skipped"), YELLOW),
+            (false, false) => (msg!("status-yanked"), YELLOW),
+        };
+        let mut row = vec![
+            Cell::new(status).color(color),
+            Cell::new(yank.id()),
+            Cell::new(yank.version()),
+        ];
+        if let Some(kept) = yank.kept() {
+            row.push(Cell::new(msg!("yank-kept", name = kept)));
+        }
+        row
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let Yanked(yank) = self;
+        let status = match (yank.is_deletion(), yank.was_yanked()) {
+            (true, _) => "deleted",
+            (false, true) => "skipped",
+            (false, false) => "yanked",
+        };
+        serde_json::json!({
+            "status": status,
+            "id": yank.id(),
+            "version": yank.version().to_string(),
+            "deleted": yank.deleted().collect::<Vec<_>>(),
+            "kept": yank.kept(),
+        })
+    }
+}
@@ -0,0 +1,205 @@
+//! # Execution Context
+//!
+//! State which is resolved once per invocation and threaded through command execution,
+//! rather than being stored in process-wide globals, so that multiple logical invocations
+//! can run within a single process without leaking state between them.
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use atom::store::EkalaStore;
+use config::{Config, StoreKind};
+
+use super::Args;
+use super::dashboard::Dashboard;
+use super::output::{Format, Output, OutputSink};
+use super::store::{self, Detected, StoreArg};
+
+/// The context a command is executed in.
+#[derive(Debug)]
+pub struct Context {
+    cwd: PathBuf,
+    config: Config,
+    output: Output,
+    format: Format,
+    tui: bool,
+    store_kind: Option<StoreKind>,
+    store: OnceLock<Result<Detected, store::Error>>,
+    #[cfg(feature = "s3")]
+    object_store: Option<atom::store::s3::S3Store>,
+}
+
+impl Context {
+    /// Construct a new context from the given arguments.
+    ///
+    /// The working directory is taken from `-C` if given, or the current working directory of
+    /// the process otherwise, and the configuration is discovered relative to it.
+    pub fn new(args: &Args) -> io::Result<Self> {
+        let cwd = match &args.working_directory {
+            Some(dir) => dir.to_owned(),
+            None => std::env::current_dir()?,
+        };
+        let config = Config::load(&cwd);
+        let output = Output::new(args.log.color.unwrap_or_else(|| config.color()));
+        let store_kind = match &args.store {
+            Some(StoreArg::Kind(kind)) => Some(*kind),
+            // Atoms are still published from a Git repository
+            #[cfg(feature = "s3")]
+            Some(StoreArg::S3(_)) => Some(StoreKind::Git),
+            None => config.store(),
+        };
+        #[cfg(feature = "s3")]
+        let object_store = match &args.store {
+            Some(StoreArg::S3(url)) => {
+                let store = atom::store::s3::S3Store::new(url.clone());
+                let auth = config.auth(store.endpoint()).cloned();
+                Some(store.auth(auth))
+            },
+            _ => None,
+        };
+
+        Ok(Context {
+            cwd,
+            config,
+            output,
+            format: args.format,
+            tui: args.tui,
+            store_kind,
+            store: OnceLock::new(),
+            #[cfg(feature = "s3")]
+            object_store,
+        })
+    }
+
+    /// The effective working directory for this invocation.
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// The configuration in effect for this invocation.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The output settings for this invocation.
+    pub fn output(&self) -> Output {
+        self.output
+    }
+
+    /// The format command results are written in for this invocation.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// A sink for the results of a command, in the format requested for this invocation.
+    pub fn sink(&self) -> Box<dyn OutputSink> {
+        self.output.sink(self.format)
+    }
+
+    /// A live dashboard of an operation over `total` atoms, if one was requested with `--tui`
+    /// and stderr is a terminal.
+    pub fn dashboard(&self, title: &str, total: usize) -> Option<Dashboard> {
+        Dashboard::start(self.output, self.tui, title, total)
+    }
+
+    /// The kind of store chosen for this invocation, by `--store` or the configuration.
+    pub(super) fn store_kind(&self) -> Option<StoreKind> {
+        self.store_kind
+    }
+
+    /// The object store given by `--store`, if any, which Atoms are published to and fetched
+    /// from instead of a remote of the repository.
+    #[cfg(feature = "s3")]
+    pub(super) fn object_store(&self) -> Option<&atom::store::s3::S3Store> {
+        self.object_store.as_ref()
+    }
+
+    /// The store detected from the working directory, which is only searched for on first use.
+    pub(super) fn store(&self) -> Result<&Detected, store::Error> {
+        self.store
+            .get_or_init(|| store::detect(self))
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// The store to operate on, through the backend selected at runtime: the object store
+    /// given by `--store`, if any, or else the remote of the detected repository selected as by
+    /// [`Context::remote`].
+    #[cfg_attr(not(feature = "git"), allow(unused_variables))]
+    pub(super) fn ekala_store(
+        &self,
+        given: Option<&str>,
+    ) -> anyhow::Result<Box<dyn EkalaStore + '_>> {
+        #[cfg(feature = "s3")]
+        if let Some(store) = self.object_store() {
+            // no repository is needed to query an object store, only to publish to it
+            let repo = match self.store() {
+                Ok(Detected::Git(repo)) => Some(repo.to_thread_local()),
+                _ => None,
+            };
+            return Ok(Box::new(atom::store::s3::S3Backend::new(repo, store)));
+        }
+
+        match self.store()? {
+            #[cfg(feature = "git")]
+            Detected::Git(repo) => {
+                let repo = repo.to_thread_local();
+                let remote = self.remote(&repo, given)?;
+                Ok(Box::new(atom::store::git::backend::GitBackend::new(
+                    repo, remote,
+                )))
+            },
+            _ => Err(store::Error::FailedDetection.into()),
+        }
+    }
+
+    /// Select the remote to operate on, from the one given, the configured default, or the
+    /// push remote configured in git.
+    #[cfg(feature = "git")]
+    pub(super) fn remote(
+        &self,
+        repo: &gix::Repository,
+        given: Option<&str>,
+    ) -> Result<String, atom::store::git::Error> {
+        let configured = self.config.publish().default_remote.as_deref();
+        atom::store::git::select_remote(repo, given, configured).map(|(name, _)| name)
+    }
+
+    /// The traits Atoms may declare, the builtin ones along with those configured in
+    /// `publish.traits`.
+    pub(super) fn kinds(&self) -> Result<atom::KindRegistry, atom::KindError> {
+        let mut kinds = atom::KindRegistry::default();
+        for name in &self.config.publish().traits {
+            kinds = kinds.register(name.parse()?);
+        }
+        Ok(kinds)
+    }
+
+    /// A fetcher for the store in the url of `uri`, if it has one, or the remote selected as by
+    /// [`Context::remote`] otherwise, querying it as configured, going through the read-through
+    /// proxy, if one is configured, and checking the Atoms of a closure out to the shared
+    /// store, unless `fetch.shared` is disabled.
+    #[cfg(feature = "git")]
+    pub(super) fn fetcher<'a>(
+        &'a self,
+        repo: &'a gix::Repository,
+        uri: &atom::uri::Uri,
+        given: Option<&str>,
+    ) -> anyhow::Result<atom::fetch::git::GitFetcher<'a>> {
+        let remote = match uri.url() {
+            Some(url) => repo.remote_at(url.clone())?,
+            None => repo.find_remote(self.remote(repo, given)?.as_str())?,
+        };
+        let mut fetcher = atom::fetch::git::GitFetcher::new(remote).config(&self.config);
+        if let Some(path) = self.config.shared_store() {
+            fetcher = fetcher.shared(atom::store::git::shared::SharedStore::new(path));
+        }
+        let proxy = self.config.proxy();
+        let Some(path) = &proxy.path else {
+            return Ok(fetcher);
+        };
+        let ttl = std::time::Duration::from_secs(proxy.ttl);
+        let proxy = atom::store::git::proxy::Proxy::open(path, ttl)?;
+        Ok(fetcher.proxy(proxy))
+    }
+}
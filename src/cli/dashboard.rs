@@ -0,0 +1,284 @@
+//! # Live Dashboard
+//!
+//! Recursive publishes and store-wide verifications may run for minutes over thousands of
+//! atoms, during which the usual line of progress says little about what is going on. With
+//! `--tui`, such operations draw a live dashboard on the alternate screen of the terminal
+//! instead, redrawn a few times a second: the progress over all atoms, their throughput and
+//! that of pushes, the atoms in flight and those last finished, and every error so far.
+//!
+//! The dashboard is drawn with plain ANSI escapes, and is only shown when stderr is a
+//! terminal, so that CI logs and pipes keep the usual line progress and logs. Once the
+//! operation ends, the terminal is restored, and the results are written as usual.
+use std::collections::VecDeque;
+use std::fmt::{Display, Write as _};
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::logging::ansi::{GREEN, RED, RESET, YELLOW};
+use super::output::Output;
+use crate::msg;
+
+/// How often the dashboard is redrawn.
+const REFRESH: Duration = Duration::from_millis(250);
+
+/// The number of finished atoms, and of errors, kept on screen.
+const RECENT: usize = 8;
+
+/// How an atom shown on the dashboard finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The atom was published, or verified.
+    Done,
+    /// The atom was skipped, e.g. as it was already published.
+    Skipped,
+    /// The atom failed.
+    Failed,
+}
+
+/// A live dashboard of a long operation over many atoms, drawn on stderr until dropped.
+pub struct Dashboard {
+    shared: Arc<Shared>,
+    render: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    stopped: AtomicBool,
+}
+
+struct State {
+    title: String,
+    total: usize,
+    started: Instant,
+    active: Vec<String>,
+    recent: VecDeque<(String, Status)>,
+    done: usize,
+    skipped: usize,
+    failed: usize,
+    pushes: usize,
+    errors: Vec<String>,
+    ansi: bool,
+}
+
+impl Dashboard {
+    /// Start drawing a dashboard titled `title`, of an operation over `total` atoms, if `tui`
+    /// is requested and stderr is a terminal, or return `None` otherwise.
+    pub fn start(output: Output, tui: bool, title: &str, total: usize) -> Option<Self> {
+        if !tui || !io::stderr().is_terminal() {
+            return None;
+        }
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                title: title.to_owned(),
+                total,
+                started: Instant::now(),
+                active: Vec::new(),
+                recent: VecDeque::with_capacity(RECENT),
+                done: 0,
+                skipped: 0,
+                failed: 0,
+                pushes: 0,
+                errors: Vec::new(),
+                ansi: output.ansi(),
+            }),
+            stopped: AtomicBool::new(false),
+        });
+
+        // enter the alternate screen, and hide the cursor
+        let _ = io::stderr().write_all(b"\x1b[?1049h\x1b[?25l");
+        let render = {
+            let shared = Arc::clone(&shared);
+            std::thread::spawn(move || {
+                while !shared.stopped.load(Ordering::Relaxed) {
+                    shared.draw();
+                    std::thread::sleep(REFRESH);
+                }
+            })
+        };
+        Some(Dashboard {
+            shared,
+            render: Some(render),
+        })
+    }
+
+    /// Record that work on `atom` started.
+    pub fn begin(&self, atom: impl Display) {
+        self.shared
+            .update(|state| state.active.push(atom.to_string()));
+    }
+
+    /// Record that work on `atom` finished, with the given status.
+    pub fn end(&self, atom: impl Display, status: Status) {
+        let atom = atom.to_string();
+        self.shared.update(|state| {
+            state.active.retain(|active| active != &atom);
+            match status {
+                Status::Done => state.done += 1,
+                Status::Skipped => state.skipped += 1,
+                Status::Failed => state.failed += 1,
+            }
+            if state.recent.len() == RECENT {
+                state.recent.pop_back();
+            }
+            state.recent.push_front((atom, status));
+        });
+    }
+
+    /// Record an error, which is kept on screen until the operation ends.
+    pub fn error(&self, error: impl Display) {
+        self.shared
+            .update(|state| state.errors.push(error.to_string()));
+    }
+
+    /// Record that a push completed, whether it succeeded or not.
+    pub fn pushed(&self) {
+        self.shared.update(|state| state.pushes += 1);
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Relaxed);
+        if let Some(render) = self.render.take() {
+            let _ = render.join();
+        }
+        // show the cursor, and leave the alternate screen
+        let _ = io::stderr().write_all(b"\x1b[?25h\x1b[?1049l");
+    }
+}
+
+impl Shared {
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        f(&mut self.state.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    fn draw(&self) {
+        let screen = self
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .render();
+        let mut stderr = io::stderr().lock();
+        let _ = stderr.write_all(screen.as_bytes());
+        let _ = stderr.flush();
+    }
+}
+
+impl State {
+    /// Render the whole screen, from its top left corner.
+    fn render(&self) -> String {
+        let (width, height) = size();
+        let elapsed = self.started.elapsed();
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let finished = self.done + self.skipped + self.failed;
+
+        let mut lines = vec![
+            format!(" {} · {}s", self.title, elapsed.as_secs()),
+            String::new(),
+        ];
+        let bar = width.saturating_sub(40).clamp(10, 60);
+        let filled = if self.total == 0 {
+            bar
+        } else {
+            bar * finished.min(self.total) / self.total
+        };
+        lines.push(format!(
+            " [{}{}] {}",
+            "#".repeat(filled),
+            "-".repeat(bar - filled),
+            msg!(
+                "dashboard-progress",
+                finished = finished,
+                total = self.total,
+                rate = format!("{:.1}", finished as f64 / secs),
+            ),
+        ));
+        lines.push(format!(
+            " {}",
+            msg!(
+                "dashboard-counts",
+                done = self.paint(GREEN, self.done),
+                skipped = self.paint(YELLOW, self.skipped),
+                failed = self.paint(RED, self.failed),
+                pushes = self.pushes,
+                rate = format!("{:.1}", self.pushes as f64 / secs),
+            ),
+        ));
+
+        lines.push(String::new());
+        lines.push(format!(" {}", msg!("dashboard-active")));
+        lines.extend(self.active.iter().map(|atom| format!("   {atom}")));
+        lines.push(String::new());
+        lines.push(format!(" {}", msg!("dashboard-recent")));
+        lines.extend(self.recent.iter().map(|(atom, status)| {
+            let mark = match status {
+                Status::Done => self.paint(GREEN, "✓"),
+                Status::Skipped => self.paint(YELLOW, "-"),
+                Status::Failed => self.paint(RED, "✗"),
+            };
+            format!("   {mark} {atom}")
+        }));
+        if !self.errors.is_empty() {
+            lines.push(String::new());
+            let count = self.errors.len();
+            lines.push(format!(" {}", msg!("dashboard-errors", count = count)));
+            let shown = self.errors.len().saturating_sub(RECENT);
+            lines.extend(
+                self.errors[shown..]
+                    .iter()
+                    .map(|e| format!("   {}", self.paint(RED, e))),
+            );
+        }
+
+        let mut screen = String::from("\x1b[H");
+        for line in lines.iter().take(height) {
+            // clear the rest of each line, rather than the whole screen, so it does not flicker
+            let _ = write!(screen, "{}\x1b[K\r\n", truncate(line, width));
+        }
+        screen.push_str("\x1b[J");
+        screen
+    }
+
+    fn paint(&self, color: &str, text: impl Display) -> String {
+        if self.ansi {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// The size of the terminal, as exported by the shell, or the conventional 80x24.
+fn size() -> (usize, usize) {
+    let var = |name, default| {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    (var("COLUMNS", 80), var("LINES", 24))
+}
+
+/// Truncate `line` to `width` visible characters, skipping over ANSI escapes, so that no line
+/// wraps and scrolls the screen.
+fn truncate(line: &str, width: usize) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut visible = 0;
+    let mut escape = false;
+    for c in line.chars() {
+        if escape {
+            escape = !c.is_ascii_alphabetic();
+        } else if c == '\x1b' {
+            escape = true;
+        } else if visible == width {
+            continue;
+        } else {
+            visible += 1;
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,135 @@
+//! # Exit Codes
+//!
+//! The exit code of eka is a stable contract, so that scripts and CI can tell the classes of
+//! failure apart without parsing diagnostics. Every command fails with an [`anyhow::Error`],
+//! which is classified into a [`Status`] by the errors in its chain.
+use std::process::ExitCode;
+
+use thiserror::Error;
+
+/// The exit codes, as documented in the help of the `eka` command.
+pub const HELP: &str = "\
+Exit Codes:
+  0  Success
+  1  Failure not covered by any other code
+  2  Invalid usage
+  3  Dependencies could not be resolved, or conflict with one another
+  4  Publishing failed
+  5  Verification failed, e.g. of a manifest, a ref or the store's policy
+  6  Partial success, some work failed while the rest succeeded
+  7  Nothing to do, e.g. every atom is already published,
+     unless `--exit-zero-on-skip` is given
+
+Run `eka explain <CODE>` for the common causes of a code, and their remedies.";
+
+/// The class of outcome an exit code stands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Status {
+    /// The command succeeded.
+    Success = 0,
+    /// The command failed for a reason not covered by any other status.
+    Failure = 1,
+    /// The command line was invalid, as reported by the argument parser itself.
+    Usage = 2,
+    /// Dependencies could not be resolved, or conflict with one another.
+    Conflict = 3,
+    /// Publishing failed.
+    Publish = 4,
+    /// Verification failed.
+    Verification = 5,
+    /// Some of the work failed, while the rest succeeded.
+    Partial = 6,
+    /// There was nothing to do.
+    NothingToDo = 7,
+}
+
+/// An exit code which is neither the number nor the name of a [`Status`].
+#[derive(Error, Debug)]
+#[error("`{0}` is not an exit code of eka, see `eka explain` for each of them")]
+pub struct UnknownStatus(String);
+
+impl Status {
+    /// Every status, in the order of their exit codes.
+    pub const ALL: [Status; 8] = [
+        Status::Success,
+        Status::Failure,
+        Status::Usage,
+        Status::Conflict,
+        Status::Publish,
+        Status::Verification,
+        Status::Partial,
+        Status::NothingToDo,
+    ];
+
+    /// The stable name of the status, as accepted by `eka explain` in place of its code.
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Success => "success",
+            Status::Failure => "failure",
+            Status::Usage => "usage",
+            Status::Conflict => "conflict",
+            Status::Publish => "publish",
+            Status::Verification => "verification",
+            Status::Partial => "partial",
+            Status::NothingToDo => "nothing-to-do",
+        }
+    }
+}
+
+impl std::str::FromStr for Status {
+    type Err = UnknownStatus;
+
+    /// Parse a status from its exit code, or its name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Status::ALL
+            .into_iter()
+            .find(|status| s == status.name() || s.parse() == Ok(*status as u8))
+            .ok_or_else(|| UnknownStatus(s.to_owned()))
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
+
+/// Outcomes which are not errors as such, but are still reported with a dedicated exit code.
+#[derive(Error, Debug)]
+pub enum Outcome {
+    /// Nothing was done, as everything was already done before.
+    #[error("Nothing to do, everything was skipped")]
+    NothingToDo,
+    /// Some of the work failed, while the rest succeeded.
+    #[error("{failed} of {total} failed, the rest succeeded")]
+    Partial {
+        /// How many items failed.
+        failed: usize,
+        /// How many items were processed in total.
+        total: usize,
+    },
+}
+
+/// Options for the exit code of commands which may have nothing to do.
+#[derive(clap::Args, Clone, Copy, Debug)]
+pub struct ExitArgs {
+    /// Exit with 0 instead of 7 when there is nothing to do
+    ///
+    /// By default, a command which skipped everything, e.g. because
+    /// every atom was already published, exits with 7, so CI can tell
+    /// "nothing to do" apart from both success and failure.
+    #[arg(long, verbatim_doc_comment)]
+    pub exit_zero_on_skip: bool,
+}
+
+impl ExitArgs {
+    /// The outcome of a command which `did` some work and skipped some other.
+    pub fn check(self, did: usize, skipped: usize) -> Result<(), Outcome> {
+        if did == 0 && skipped > 0 && !self.exit_zero_on_skip {
+            Err(Outcome::NothingToDo)
+        } else {
+            Ok(())
+        }
+    }
+}
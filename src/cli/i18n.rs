@@ -0,0 +1,89 @@
+//! # Localizable Messages
+//!
+//! User-facing strings are looked up by id in [Fluent](https://projectfluent.org) catalogs
+//! compiled into the binary, rather than hard-coded at their call sites. The locale is taken
+//! from `EKA_LANG`, or the usual POSIX locale variables, falling back to English when no
+//! matching catalog exists.
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use fluent::concurrent::FluentBundle;
+use fluent::{FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+/// The catalogs compiled into the binary, the first of which is the fallback.
+const CATALOGS: &[(&str, &str)] = &[("en-US", include_str!("i18n/en-US.ftl"))];
+
+static BUNDLE: LazyLock<FluentBundle<FluentResource>> =
+    LazyLock::new(|| bundle(requested_locale().as_ref()));
+
+fn requested_locale() -> Option<LanguageIdentifier> {
+    ["EKA_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.is_empty())
+        .and_then(|v| {
+            // strip the encoding and modifier, e.g. `de_DE.UTF-8@euro`
+            let tag = v.split(['.', '@']).next().unwrap_or_default();
+            tag.replace('_', "-").parse().ok()
+        })
+}
+
+fn bundle(requested: Option<&LanguageIdentifier>) -> FluentBundle<FluentResource> {
+    let (locale, source) = requested
+        .and_then(|req| {
+            CATALOGS.iter().find(|(locale, _)| {
+                locale
+                    .parse::<LanguageIdentifier>()
+                    .is_ok_and(|l| l.language == req.language)
+            })
+        })
+        .unwrap_or(&CATALOGS[0]);
+
+    let locale = locale.parse().expect("catalog locales are valid");
+    let resource = FluentResource::try_new((*source).to_owned()).expect("catalogs are valid");
+
+    let mut bundle = FluentBundle::new_concurrent(vec![locale]);
+    // unicode isolation marks only add noise to terminal output
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .expect("catalogs contain no duplicate messages");
+    bundle
+}
+
+/// Format the message with the given id from the active catalog.
+///
+/// Missing messages are not fatal; the id itself is returned so output degrades gracefully.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> Cow<'static, str> {
+    let bundle: &'static FluentBundle<FluentResource> = &BUNDLE;
+
+    let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) else {
+        tracing::debug!(id, "message missing from catalog");
+        return Cow::Owned(id.to_owned());
+    };
+
+    let mut errors = Vec::new();
+    let message = bundle.format_pattern(pattern, args, &mut errors);
+    for error in errors {
+        tracing::debug!(id, %error, "failed to format message");
+    }
+    message
+}
+
+/// Format a localized message by id, with optional named arguments.
+///
+/// ```ignore
+/// msg!("publish-plan-header", remote = "origin");
+/// ```
+#[macro_export]
+macro_rules! msg {
+    ($id:literal) => {
+        $crate::cli::i18n::message($id, None)
+    };
+    ($id:literal, $($key:ident = $value:expr),+ $(,)?) => {{
+        let mut args = ::fluent::FluentArgs::new();
+        $(args.set(stringify!($key), $value);)+
+        $crate::cli::i18n::message($id, Some(&args))
+    }};
+}
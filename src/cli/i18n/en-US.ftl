@@ -0,0 +1,198 @@
+## Publishing
+
+publish-plan-header = Planning to publish to `{ $remote }`:
+publish-plan-skip = (already published, will skip)
+publish-plan-invalid = (invalid: { $error })
+publish-confirm = Proceed? [y/N]
+publish-confirm-yes = yes
+publish-cancelled = Publishing cancelled, nothing was published
+plan-written = Wrote the publish plan to `{ $path }`
+plan-unsigned = Executing an unsigned publish plan
+
+## Garbage Collection
+
+gc-keep-all = No retention rule is set, every source is retained
+gc-warning = { $count ->
+    [one] The source of { $count } published version will be pruned.
+   *[other] The sources of { $count } published versions will be pruned.
+} Once `git gc` reclaims them, these versions can no longer be verified against the commits they were published from.
+gc-confirm = Prune? [y/N]
+gc-cancelled = Pruning cancelled, no source was pruned
+
+## Yanking
+
+yank-warning = `{ $id }@{ $version }` will be deleted from the store. Anything locked to it can no longer be fetched.
+yank-confirm = Delete? [y/N]
+yank-cancelled = Deletion cancelled, nothing was yanked
+yank-kept = kept `{ $name }`, another version was published from the same commit
+yank-locked = `{ $id }@{ $version }` is locked, but was yanked from the store. It is kept, but new resolutions will not select it.
+
+## Renaming
+
+rename-target = to `{ $id }`, as of { $version }
+
+## Mirroring
+
+mirror-conflict = The mirror's ref points elsewhere than the source's, and is left alone, as it may never be moved
+
+## Verification
+
+verify-summary = Verified { $verified ->
+    [one] { $verified } atom version
+   *[other] { $verified } atom versions
+}, { $failed } failed
+verify-source = from `{ $path }` at { $origin }
+verify-attested = Attested the lock to `{ $path }`, signed in `{ $signature }`
+
+## Archive Export
+
+export-archive-digest = Exported an archive with the digest `{ $digest }`
+
+## Version Bumps
+
+version-bumped = { $old } → { $new }
+
+## New Atoms
+
+new-registered = declared in { $workspace }
+
+## Dashboard
+
+dashboard-publish = Publishing to { $remote }
+dashboard-verify = Verifying the store
+dashboard-progress = { $finished }/{ $total } atoms  { $rate } atoms/s
+dashboard-counts = { $done } done  { $skipped } skipped  { $failed } failed  { $pushes } pushes  { $rate } pushes/s
+dashboard-active = In progress
+dashboard-recent = Recent
+dashboard-errors = Errors ({ $count })
+
+## Exit Code Explanations
+## Each cause and remedy is on a line of its own.
+
+explain-causes = Common causes
+explain-remedies = Remediation
+explain-success = The command succeeded.
+explain-success-causes =
+    Everything the command was asked to do was done.
+explain-success-remedies =
+    Nothing to remedy.
+explain-failure = The command failed for a reason not covered by any other exit code.
+explain-failure-causes =
+    The working directory, or a file given, could not be read or written.
+    No store was found, or its remote could not be reached.
+    The configuration, or a manifest, is not valid TOML.
+explain-failure-remedies =
+    Rerun with `-v`, or `-vv`, to log what failed, and why.
+    Check that the store is initialized with `eka init --check`.
+    Check the configuration and manifests with `eka check`.
+explain-usage = The command line is invalid, as reported by the argument parser.
+explain-usage-causes =
+    An unknown command or option was given, or a required argument is missing.
+    Options which conflict with one another were given together.
+    The value of an option is not valid, e.g. a version which is not semver.
+explain-usage-remedies =
+    See the usage of the command with `eka <command> --help`.
+explain-conflict = Dependencies could not be resolved, or conflict with one another.
+explain-conflict-causes =
+    No version published to the store satisfies the requirement of a dependency.
+    Two atoms require versions of the same atom which no single version satisfies.
+    The store the dependency is published to could not be reached.
+explain-conflict-remedies =
+    List the versions published to the store with `eka list`.
+    Show why each atom is depended on with `eka deps`.
+    Relax the requirement in the manifest, or publish a version which satisfies it.
+explain-publish = Publishing failed.
+explain-publish-causes =
+    The store rejected a push, e.g. by its hooks, or as the version was published concurrently.
+    The version is already published with other content, which is never overwritten.
+    The atom commit could not be signed with the configured key.
+explain-publish-remedies =
+    Bump the version of the atom with `eka version`, rather than republishing it.
+    Rerun with `-v` to log the reason the store gave for rejecting the push.
+    Check the signing configuration, or publish without `--sign`.
+explain-verification = Verification failed, e.g. of a manifest, a ref or the store's policy.
+explain-verification-causes =
+    A manifest is invalid, or violates a lint configured at the `error` level.
+    A published atom does not match the source it claims to be published from.
+    An atom breaks a rule of the store's policy, or is not signed by an allowed key.
+explain-verification-remedies =
+    Check the manifests with `eka check`, which reports each violation.
+    Verify the atom against its source with `eka verify`, which reports what was tampered with.
+    Review the store's policy, and `signing.allowed-keys` in the configuration.
+explain-partial = Some of the work failed, while the rest succeeded.
+explain-partial-causes =
+    Some of the atoms of a recursive publish, or fetch, failed, while the others succeeded.
+explain-partial-remedies =
+    Review the failures reported, fix them, and rerun the command, which skips the work already done.
+explain-nothing-to-do = There was nothing to do, as everything was already done before.
+explain-nothing-to-do-causes =
+    Every atom was already published, or fetched.
+explain-nothing-to-do-remedies =
+    Bump the version of the atoms changed with `eka version`, if they are meant to be published.
+    Pass `--exit-zero-on-skip` to exit with 0 instead.
+
+## Results
+
+status-published = published
+status-skipped = skipped
+status-planned = planned
+status-failed = failed
+status-initialized = initialized
+status-uninitialized = uninitialized
+status-mismatched = mismatched
+status-invalid = invalid
+status-rejected = rejected
+status-attached = attached
+status-migrated = migrated
+status-verified = verified
+status-added = added
+status-removed = removed
+status-upgraded = upgraded
+status-downgraded = downgraded
+status-repinned = repinned
+status-changed = changed
+status-pruned = pruned
+status-exported = exported
+status-fetched = fetched
+status-allowed = allowed
+status-forbidden = forbidden
+status-generated = generated
+status-tampered = tampered
+status-yanked = yanked
+status-redirected = redirected
+status-deleted = deleted
+status-bumped = bumped
+status-created = created
+status-unpublished = unpublished
+status-modified = modified
+
+## Diagnostics
+
+severity-note = note
+severity-warning = warning
+severity-error = error
+
+## Graphs
+
+graph-direct = direct
+graph-transitive = transitive
+graph-missing = `{ $id }` depends on `{ $name }` at `{ $path }`, which is not an atom of the repository
+
+## Dependency Queries
+
+deps-via = via { $chain }
+
+## Debugging
+
+debug-generated = { $atoms } atoms, { $valid } valid, { $duplicates } duplicates, { $broken } broken
+
+## Statistics
+
+stats-empty = No usage metrics recorded yet, set `metrics = true` in the configuration to record them
+stats-runs = { $count ->
+    [one] { $count } run
+   *[other] { $count } runs
+}
+stats-total = { $time } total
+stats-mean = { $time } mean
+stats-max = { $time } max
@@ -2,11 +2,14 @@ use std::str::FromStr;
 
 use clap::Parser;
 use tracing_error::ErrorLayer;
-use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter, filter_fn};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{Layer, fmt};
 
+use super::metrics::Metrics;
+use super::output::Output;
+use super::profile::Profiler;
 use super::{Args, LogArgs};
 
 fn get_log_level(args: LogArgs) -> LevelFilter {
@@ -32,21 +35,28 @@ use std::sync::atomic::{AtomicBool, Ordering};
 pub static ANSI: AtomicBool = AtomicBool::new(true);
 
 use tracing_appender::non_blocking::WorkerGuard;
-pub fn init_global_subscriber(args: LogArgs) -> WorkerGuard {
+pub fn init_global_subscriber(
+    args: LogArgs,
+    output: Output,
+    profiler: Option<Profiler>,
+    metrics: Option<Metrics>,
+) -> WorkerGuard {
     let log_level = get_log_level(args);
 
     let env_filter = EnvFilter::from_default_env().add_directive(log_level.into());
 
     let (non_blocking, guard) = tracing_appender::non_blocking(std::io::stderr());
 
+    ANSI.store(output.ansi(), Ordering::SeqCst);
+
     use std::io::IsTerminal;
     let fmt = if std::io::stderr().is_terminal() {
         fmt::layer()
+            .with_ansi(ANSI.load(Ordering::SeqCst))
             .without_time()
             .with_writer(non_blocking)
             .boxed()
     } else {
-        ANSI.store(false, Ordering::SeqCst);
         fmt::layer()
             .with_ansi(ANSI.load(Ordering::SeqCst))
             .json()
@@ -54,9 +64,14 @@ pub fn init_global_subscriber(args: LogArgs) -> WorkerGuard {
             .boxed()
     };
 
+    // the profiler must observe every span regardless of the log level, so the level filter
+    // only applies to the formatting layer
+    let profiler = profiler.map(|p| p.with_filter(filter_fn(|meta| meta.is_span())));
+
     tracing_subscriber::registry()
-        .with(fmt)
-        .with(env_filter)
+        .with(fmt.with_filter(env_filter))
+        .with(profiler)
+        .with(metrics)
         .with(ErrorLayer::default())
         .init();
 
@@ -68,7 +83,10 @@ pub fn init_global_subscriber(args: LogArgs) -> WorkerGuard {
 }
 
 pub mod ansi {
+    pub const GREEN: &str = "\x1b[32m";
     pub const MAGENTA: &str = "\x1b[35m";
+    pub const RED: &str = "\x1b[31m";
+    pub const YELLOW: &str = "\x1b[33m";
     pub const RESET: &str = "\x1b[0m";
 }
 
@@ -0,0 +1,150 @@
+//! # Usage Metrics
+//!
+//! An opt-in tracing [`Layer`] which records each command run, and how long it took, to a file
+//! in the local data directory, so teams can find their slow workflows with `eka stats --self`.
+//! It is enabled with the `metrics` configuration value, and nothing it records is ever sent
+//! anywhere.
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tracing::field::{Field, Visit};
+use tracing::{Subscriber, span};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// The name of the span each command is executed in, with its name as the `name` field.
+pub const COMMAND_SPAN: &str = "command";
+
+/// The file, in the data directory, the metrics are appended to, one run per line.
+const METRICS_FILE: &str = "metrics.jsonl";
+
+/// Collects the commands run over the lifetime of the process.
+#[derive(Clone)]
+pub struct Metrics {
+    path: PathBuf,
+    runs: Arc<Mutex<Vec<Run>>>,
+}
+
+/// A single run of a command.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Run {
+    /// The name of the command.
+    pub command: String,
+    /// When the command started, in seconds since the Unix epoch.
+    pub at: u64,
+    /// How long the command took, in milliseconds.
+    pub ms: f64,
+}
+
+struct Started {
+    command: String,
+    at: SystemTime,
+    instant: Instant,
+}
+
+/// Extracts the `name` field of a command span.
+struct CommandName(Option<String>);
+
+impl Visit for CommandName {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+impl Metrics {
+    /// Create a collector which will append its runs to the metrics file in `dir`.
+    pub fn new(dir: &Path) -> Self {
+        Metrics {
+            path: dir.join(METRICS_FILE),
+            runs: Arc::default(),
+        }
+    }
+
+    /// Append the runs recorded so far to the metrics file.
+    pub fn write(&self) -> io::Result<()> {
+        let runs = self.runs.lock().unwrap_or_else(PoisonError::into_inner);
+        if runs.is_empty() {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // a single write per process, so concurrent runs never interleave within a line
+        let mut lines = String::new();
+        for run in runs.iter() {
+            lines.push_str(&serde_json::to_string(run)?);
+            lines.push('\n');
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(lines.as_bytes())
+    }
+
+    /// Read every run recorded in the metrics file in `dir`, skipping any malformed line.
+    pub fn read(dir: &Path) -> io::Result<Vec<Run>> {
+        let content = match fs::read_to_string(dir.join(METRICS_FILE)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+impl<S> Layer<S> for Metrics
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != COMMAND_SPAN {
+            return;
+        }
+        let mut name = CommandName(None);
+        attrs.record(&mut name);
+        if let (Some(span), Some(command)) = (ctx.span(id), name.0) {
+            span.extensions_mut().insert(Started {
+                command,
+                at: SystemTime::now(),
+                instant: Instant::now(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(started) = span.extensions_mut().remove::<Started>() else {
+            return;
+        };
+        let at = started
+            .at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let mut runs = self.runs.lock().unwrap_or_else(PoisonError::into_inner);
+        runs.push(Run {
+            command: started.command,
+            at,
+            ms: started.instant.elapsed().as_secs_f64() * 1e3,
+        });
+    }
+}
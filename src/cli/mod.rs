@@ -1,26 +1,75 @@
 #![cfg_attr(not(feature = "stores"), allow(unused_variables))]
 mod commands;
+pub mod context;
+pub mod dashboard;
+pub mod exit;
+pub mod i18n;
 pub mod logging;
+pub mod metrics;
+pub mod output;
+pub mod profile;
+pub mod report;
 mod store;
 
 use std::path::PathBuf;
 
 use clap::Parser;
-pub use commands::run;
+pub use commands::{run, status};
+use config::ColorChoice;
 pub use logging::init_global_subscriber;
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_long_help = exit::HELP)]
 pub struct Args {
-    #[arg(short = 'C', value_name = "DIR", global = true, verbatim_doc_comment, value_parser = validate_path)]
-
-    /// Change the current working directory
+    /// Run as if started in the given directory
     ///
-    /// If specified, changes the current working directory to the given
-    /// path before executing any commands. This affects all file system
-    /// operations performed by the program.
+    /// If specified, all relative paths, as well as the discovery of the
+    /// store and its configuration, are resolved from the given directory
+    /// instead of the current working directory.
+    #[arg(short = 'C', long, value_name = "DIR", global = true, verbatim_doc_comment, value_parser = validate_path)]
     working_directory: Option<PathBuf>,
 
+    /// Write the time spent in each stage of execution as JSON to FILE
+    ///
+    /// A relative path is taken from the directory given by `-C`. Use
+    /// `-` to write the report to stdout.
+    #[arg(long, value_name = "FILE", global = true, verbatim_doc_comment)]
+    pub profile_json: Option<PathBuf>,
+
+    /// The format to write command results to stdout in
+    ///
+    /// Diagnostics are always logged to stderr, independent of this.
+    #[arg(long, global = true, value_enum, default_value_t, verbatim_doc_comment)]
+    format: output::Format,
+
+    /// The kind of store to operate on, or the url of an object store
+    ///
+    /// A kind (`git`) is only needed when the directory is within more
+    /// than one kind of store. Defaults to the `store` configuration
+    /// value, if set.
+    ///
+    /// Atoms are published to, and fetched from, an S3-compatible object
+    /// store given as `s3://<bucket>/<prefix>`, rather than a remote of
+    /// the repository. Requests are made with `curl`, using the standard
+    /// `AWS_*` environment variables for credentials, region and endpoint.
+    #[arg(
+        long,
+        global = true,
+        value_name = "STORE",
+        value_parser = store::parse_store,
+        verbatim_doc_comment
+    )]
+    store: Option<store::StoreArg>,
+
+    /// Show a live dashboard of long operations on the terminal
+    ///
+    /// Recursive publishes and `verify --all` draw the progress over all
+    /// atoms, the throughput of pushes, the atoms in flight and the errors
+    /// so far on the full terminal, until they end. Ignored when stderr is
+    /// not a terminal, which keeps the usual line progress and logs.
+    #[arg(long, global = true, verbatim_doc_comment)]
+    tui: bool,
+
     #[command(flatten)]
     pub log: LogArgs,
 
@@ -64,27 +113,17 @@ pub struct LogArgs {
     /// typically in non-interactive or automated environments.
     #[arg(short, long, global = true, verbatim_doc_comment)]
     quiet: bool,
+
+    /// Control when to use colored output
+    ///
+    /// `auto` uses color only when stderr is a terminal and the `NO_COLOR`
+    /// environment variable is unset.
+    ///
+    /// Defaults to the `color` configuration value, or `auto` if unset.
+    #[arg(long, global = true, value_name = "WHEN", verbatim_doc_comment)]
+    color: Option<ColorChoice>,
 }
 
 fn validate_path(path: &str) -> Result<PathBuf, std::io::Error> {
     std::fs::canonicalize(path)
 }
-
-pub fn change_directory() -> Vec<String> {
-    let mut seen: Option<bool> = None;
-    std::env::args()
-        .map(|arg| {
-            if seen.is_none() && arg == "-C" {
-                seen = Some(true);
-                return arg;
-            }
-            if let Some(cd) = seen {
-                if cd {
-                    std::env::set_current_dir(&arg).ok();
-                    seen = Some(false);
-                }
-            }
-            arg
-        })
-        .collect()
-}
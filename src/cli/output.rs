@@ -0,0 +1,203 @@
+//! # Command Output
+//!
+//! The results of a command are emitted as [`Record`]s to an [`OutputSink`], selected with
+//! `--format`, rather than being logged ad hoc, so that every command renders its results
+//! consistently, whether for humans or for machines.
+use std::fmt::Display;
+use std::io::{self, IsTerminal, Write};
+
+use atom::report::Diagnostic;
+use clap::ValueEnum;
+use config::ColorChoice;
+use serde_json::Value;
+
+use super::logging::ansi;
+
+/// Settings describing how output should be rendered for the user.
+#[derive(Debug, Clone, Copy)]
+pub struct Output {
+    ansi: bool,
+    stdout_ansi: bool,
+    interactive: bool,
+}
+
+/// The format command results are written to stdout in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// An aligned table, intended to be read by humans.
+    #[default]
+    Human,
+    /// A JSON array containing one object per result.
+    Json,
+    /// No results at all, only diagnostics.
+    Quiet,
+}
+
+/// A single cell of a row in the human readable table, optionally colored.
+#[derive(Debug)]
+pub struct Cell {
+    text: String,
+    color: Option<&'static str>,
+}
+
+/// A single result of a command.
+pub trait Record {
+    /// The cells of this record when rendered as a row of the human readable table.
+    fn row(&self) -> Vec<Cell>;
+    /// The machine readable representation of this record.
+    fn to_json(&self) -> Value;
+}
+
+/// A destination for the results of a command.
+pub trait OutputSink {
+    /// Emit a single result.
+    fn record(&mut self, record: &dyn Record);
+    /// Raise a diagnostic beside the results, which is logged to stderr in every format.
+    fn diagnose(&mut self, diagnostic: &Diagnostic) {
+        super::report::log(diagnostic);
+    }
+    /// Flush any buffered results, once the command has completed.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+impl Output {
+    pub(super) fn new(color: ColorChoice) -> Self {
+        // each stream is colored by whether it is a terminal, so that e.g. a table piped
+        // elsewhere is not, while the logs beside it still are
+        let ansi = |terminal: bool| match color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => terminal && std::env::var_os("NO_COLOR").is_none(),
+        };
+        let interactive = io::stdin().is_terminal() && io::stderr().is_terminal();
+        Output {
+            ansi: ansi(io::stderr().is_terminal()),
+            stdout_ansi: ansi(io::stdout().is_terminal()),
+            interactive,
+        }
+    }
+
+    /// Whether ANSI color codes should be emitted to stderr.
+    pub fn ansi(&self) -> bool {
+        self.ansi
+    }
+
+    /// Whether a user is attached to the terminal and can answer prompts.
+    pub fn interactive(&self) -> bool {
+        self.interactive
+    }
+
+    /// Wrap `text` in the given color code, if colored output to stderr is enabled.
+    pub fn paint(&self, color: &str, text: impl Display) -> String {
+        if self.ansi {
+            format!("{color}{text}{}", ansi::RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Construct the sink for results in the given format.
+    pub fn sink(&self, format: Format) -> Box<dyn OutputSink> {
+        match format {
+            Format::Human => Box::new(Table {
+                output: *self,
+                rows: Vec::new(),
+            }),
+            Format::Json => Box::new(Json(Vec::new())),
+            Format::Quiet => Box::new(Quiet),
+        }
+    }
+}
+
+impl Cell {
+    /// Create an uncolored cell.
+    pub fn new(text: impl Display) -> Self {
+        Cell {
+            text: text.to_string(),
+            color: None,
+        }
+    }
+
+    /// Render this cell in the given color, if colored output is enabled.
+    #[must_use]
+    pub fn color(mut self, color: &'static str) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    fn width(&self) -> usize {
+        self.text.chars().count()
+    }
+}
+
+struct Table {
+    output: Output,
+    rows: Vec<Vec<Cell>>,
+}
+
+struct Json(Vec<Value>);
+
+struct Quiet;
+
+impl OutputSink for Table {
+    fn record(&mut self, record: &dyn Record) {
+        self.rows.push(record.row());
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let rows = std::mem::take(&mut self.rows);
+
+        let mut widths: Vec<usize> = Vec::new();
+        for row in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                match widths.get_mut(i) {
+                    Some(w) => *w = (*w).max(cell.width()),
+                    None => widths.push(cell.width()),
+                }
+            }
+        }
+
+        let mut stdout = io::stdout().lock();
+        for row in rows {
+            let last = row.len().saturating_sub(1);
+            for (i, cell) in row.into_iter().enumerate() {
+                // pad using the width of the plain text, since color codes take no space
+                let pad = if i == last {
+                    0
+                } else {
+                    widths[i] - cell.width() + 2
+                };
+                let text = match cell.color {
+                    Some(color) if self.output.stdout_ansi => {
+                        format!("{color}{}{}", cell.text, ansi::RESET)
+                    },
+                    _ => cell.text,
+                };
+                write!(stdout, "{text}{:pad$}", "")?;
+            }
+            writeln!(stdout)?;
+        }
+        Ok(())
+    }
+}
+
+impl OutputSink for Json {
+    fn record(&mut self, record: &dyn Record) {
+        self.0.push(record.to_json());
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let records = std::mem::take(&mut self.0);
+        let mut stdout = io::stdout().lock();
+        serde_json::to_writer_pretty(&mut stdout, &records)?;
+        writeln!(stdout)
+    }
+}
+
+impl OutputSink for Quiet {
+    fn record(&mut self, _record: &dyn Record) {}
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! # Profiling
+//!
+//! A tracing [`Layer`] which records the wall-clock time spent in each span, so that the time
+//! taken by the individual stages of a command can be reported as JSON via `--profile-json`.
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use serde::Serialize;
+use tracing::{Subscriber, span};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Collects span timings over the lifetime of the process.
+#[derive(Clone)]
+pub struct Profiler {
+    path: PathBuf,
+    start: Instant,
+    spans: Arc<Mutex<BTreeMap<&'static str, Timing>>>,
+}
+
+#[derive(Default, Serialize)]
+struct Timing {
+    count: u64,
+    total_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    total_ms: f64,
+    spans: &'a BTreeMap<&'static str, Timing>,
+}
+
+#[derive(Clone, Copy)]
+struct Started(Instant);
+
+impl Profiler {
+    /// Create a profiler which will write its report to `path`, or to stdout if it is `-`.
+    pub fn new(path: PathBuf) -> Self {
+        Profiler {
+            path,
+            start: Instant::now(),
+            spans: Arc::default(),
+        }
+    }
+
+    /// Write the timings recorded so far as JSON.
+    pub fn write(&self) -> io::Result<()> {
+        let spans = self.spans.lock().unwrap_or_else(PoisonError::into_inner);
+        let report = Report {
+            total_ms: self.start.elapsed().as_secs_f64() * 1e3,
+            spans: &spans,
+        };
+        let json = serde_json::to_string_pretty(&report)?;
+
+        if self.path == Path::new("-") {
+            println!("{json}");
+            Ok(())
+        } else {
+            std::fs::write(&self.path, json)
+        }
+    }
+}
+
+impl<S> Layer<S> for Profiler
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(&Started(start)) = span.extensions().get::<Started>() else {
+            return;
+        };
+        let elapsed = start.elapsed().as_secs_f64() * 1e3;
+
+        let mut spans = self.spans.lock().unwrap_or_else(PoisonError::into_inner);
+        let timing = spans.entry(span.name()).or_default();
+        timing.count += 1;
+        timing.total_ms += elapsed;
+        timing.max_ms = timing.max_ms.max(elapsed);
+    }
+}
@@ -0,0 +1,74 @@
+//! # Reports
+//!
+//! Renders the [`Report`] of a command acting on many atoms. Its diagnostics are results of
+//! their own when they are what the command looks for, as for `eka check`, and are otherwise
+//! raised through [`OutputSink::diagnose`], beside the results. Its tallies may be summarized
+//! once the results are written, with each kind of outcome named as in the results.
+use atom::report::{Diagnostic, Report, Severity};
+
+use super::logging::ansi::{MAGENTA, RED, YELLOW};
+use super::output::{Cell, OutputSink, Record};
+use crate::msg;
+
+impl Record for Diagnostic {
+    fn row(&self) -> Vec<Cell> {
+        let severity = match self.severity() {
+            Severity::Note => Cell::new(msg!("severity-note")).color(MAGENTA),
+            Severity::Warning => Cell::new(msg!("severity-warning")).color(YELLOW),
+            Severity::Error => Cell::new(msg!("severity-error")).color(RED),
+        };
+        vec![
+            severity,
+            Cell::new(self.code()),
+            Cell::new(self.subject().unwrap_or_default()),
+            Cell::new(self.message()),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "severity": self.severity().as_str(),
+            "code": self.code(),
+            "subject": self.subject(),
+            "message": self.message(),
+        })
+    }
+}
+
+/// Log a diagnostic to stderr, at the level matching its severity.
+pub fn log(diagnostic: &Diagnostic) {
+    let (code, subject) = (diagnostic.code(), diagnostic.subject());
+    match diagnostic.severity() {
+        Severity::Note => tracing::info!(code, subject, message = %diagnostic),
+        Severity::Warning => tracing::warn!(code, subject, message = %diagnostic),
+        Severity::Error => tracing::error!(code, subject, message = %diagnostic),
+    }
+}
+
+/// Raise the diagnostics of `report` through `sink`, then write out its results.
+pub fn finish(sink: &mut dyn OutputSink, report: &Report) -> std::io::Result<()> {
+    for diagnostic in report.diagnostics() {
+        sink.diagnose(diagnostic);
+    }
+    sink.finish()
+}
+
+/// Log a summary of the tallies of `report`, e.g. `2 published, 1 skipped`, if it counted any
+/// outcome.
+pub fn summarize(report: &Report) {
+    if let Some(summary) = summary(report) {
+        tracing::info!("{summary}");
+    }
+}
+
+fn summary(report: &Report) -> Option<String> {
+    let tallies: Vec<_> = report
+        .tallies()
+        .iter()
+        .map(|(outcome, n)| {
+            let outcome = super::i18n::message(&format!("status-{outcome}"), None);
+            format!("{n} {outcome}")
+        })
+        .collect();
+    (!tallies.is_empty()).then(|| tallies.join(", "))
+}
@@ -1,42 +1,153 @@
 #[cfg(feature = "git")]
 use atom::store::git;
+use clap::Args;
+use config::StoreKind;
 #[cfg(feature = "git")]
 use gix::ThreadSafeRepository;
 use thiserror::Error;
 
+use super::context::Context;
+
+/// The store chosen by `--store`, either a kind of store to detect, or an object store.
+#[derive(Clone, Debug)]
+pub(super) enum StoreArg {
+    Kind(StoreKind),
+    #[cfg(feature = "s3")]
+    S3(atom::store::s3::S3Url),
+}
+
+/// The `--remote` argument of every command operating on a remote store, whose default is
+/// selected as by [`Context::remote`].
+#[derive(Args, Clone, Debug, Default)]
+pub(super) struct RemoteArg {
+    /// The remote store to operate on
+    ///
+    /// [default: `publish.default-remote`, the push remote configured in git,
+    /// a remote named `ekala`, the only remote, or `origin`]
+    #[arg(
+        long,
+        short = 't',
+        name = "TARGET",
+        global = true,
+        verbatim_doc_comment
+    )]
+    remote: Option<String>,
+}
+
+impl RemoteArg {
+    /// The remote given, if any.
+    pub(super) fn as_deref(&self) -> Option<&str> {
+        self.remote.as_deref()
+    }
+}
+
+/// Parse the argument of `--store`, as a url if it has the scheme of an object store, or as
+/// a kind of store otherwise.
+pub(super) fn parse_store(arg: &str) -> Result<StoreArg, String> {
+    #[cfg(feature = "s3")]
+    if arg.starts_with("s3://") {
+        return arg
+            .parse()
+            .map(StoreArg::S3)
+            .map_err(|e: atom::store::s3::Error| e.to_string());
+    }
+    <StoreKind as clap::ValueEnum>::from_str(arg, true).map(StoreArg::Kind)
+}
+
 #[non_exhaustive]
 #[derive(Clone, Debug)]
 pub(super) enum Detected {
     #[cfg(feature = "git")]
-    Git(&'static ThreadSafeRepository),
+    Git(ThreadSafeRepository),
     #[allow(dead_code)]
     None,
 }
 
-pub(super) async fn detect() -> Result<Detected, Error> {
+/// Find every store the working directory is within, in order of precedence, along with why
+/// each store found could not be opened.
+fn candidates(ctx: &Context) -> (Vec<(StoreKind, Detected)>, Vec<(StoreKind, String)>) {
+    #[allow(unused_mut)]
+    let mut found = Vec::new();
+    #[allow(unused_mut)]
+    let mut failed = Vec::new();
+
     #[cfg(feature = "git")]
-    if let Ok(Some(repo)) = git::repo() {
-        use std::fs;
-        let git_dir = fs::canonicalize(repo.path())
-            .ok()
-            .map(|p| p.display().to_string());
-        let work_dir = repo
-            .work_dir()
-            .and_then(|dir| fs::canonicalize(dir).ok())
-            .map(|p| p.display().to_string());
-
-        tracing::debug!(message = "Detected Git repository", git_dir, work_dir);
-        return Ok(Detected::Git(repo));
+    match git::discover(ctx.cwd()) {
+        Ok(repo) => {
+            use std::fs;
+            let git_dir = fs::canonicalize(repo.path())
+                .ok()
+                .map(|p| p.display().to_string());
+            let work_dir = repo
+                .work_dir()
+                .and_then(|dir| fs::canonicalize(dir).ok())
+                .map(|p| p.display().to_string());
+
+            tracing::debug!(message = "Detected Git repository", git_dir, work_dir);
+            found.push((StoreKind::Git, Detected::Git(repo)));
+        },
+        Err(e) if !no_repository(&e) => failed.push((StoreKind::Git, e.to_string())),
+        Err(_) => {},
+    }
+
+    (found, failed)
+}
+
+/// Whether Git discovery failed as there is no repository to find, rather than as the one found
+/// could not be opened, e.g. as it is not trusted.
+#[cfg(feature = "git")]
+fn no_repository(error: &gix::discover::Error) -> bool {
+    use gix::discover::upwards::Error;
+    matches!(
+        error,
+        gix::discover::Error::Discover(
+            Error::NoGitRepository { .. }
+                | Error::NoGitRepositoryWithinCeiling { .. }
+                | Error::NoGitRepositoryWithinFs { .. }
+        )
+    )
+}
+
+/// Detect the store to operate on, which is the one of the kind chosen by `--store` or the
+/// configuration if given, or else the only store found.
+pub(super) fn detect(ctx: &Context) -> Result<Detected, Error> {
+    let (mut found, failed) = candidates(ctx);
+    // a store which could not be opened is reported as such, rather than as missing
+    let unusable = |kind: Option<StoreKind>| {
+        failed
+            .iter()
+            .find(|(k, _)| kind.map_or(true, |kind| *k == kind))
+            .map(|(k, reason)| Error::Unusable(*k, reason.clone()))
+    };
+
+    if let Some(kind) = ctx.store_kind() {
+        return found
+            .into_iter()
+            .find_map(|(k, store)| (k == kind).then_some(store))
+            .ok_or_else(|| unusable(Some(kind)).unwrap_or(Error::NotFound(kind)));
     }
 
-    Err(Error::FailedDetection)
+    match found.len() {
+        0 => Err(unusable(None).unwrap_or(Error::FailedDetection)),
+        1 => Ok(found.remove(0).1),
+        _ => {
+            let kinds: Vec<_> = found.iter().map(|(k, _)| k.as_str()).collect();
+            Err(Error::Ambiguous(kinds.join(", ")))
+        },
+    }
 }
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub(crate) enum Error {
     #[error("No supported repository found in this directory or its parents")]
     FailedDetection,
-    #[cfg(feature = "git")]
-    #[error(transparent)]
-    Discover(#[from] Box<gix::discover::Error>),
+    #[error("No {0} store found in this directory or its parents")]
+    NotFound(StoreKind),
+    #[error("The {0} store found in this directory or its parents could not be opened: {1}")]
+    Unusable(StoreKind, String),
+    #[error(
+        "This directory is within multiple stores ({0}), choose one with `--store` or the `store` \
+         configuration value"
+    )]
+    Ambiguous(String),
 }
@@ -1,19 +1,65 @@
 use std::process::ExitCode;
 
 use clap::Parser;
+use eka::cli::context::Context;
+use eka::cli::exit::Status;
+use eka::cli::metrics::Metrics;
+use eka::cli::profile::Profiler;
 use eka::cli::{self, Args};
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    let args = Args::parse_from(cli::change_directory());
-    let Args { log, .. } = args;
+    let mut args = Args::parse();
 
-    let _guard = cli::init_global_subscriber(log);
+    let ctx = match Context::new(&args) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            eprintln!("FATAL failed to determine the working directory: {e}");
+            return ExitCode::FAILURE;
+        },
+    };
 
-    if let Err(e) = cli::run(args).await {
-        eka::fatal!(e);
-        ExitCode::FAILURE
-    } else {
-        ExitCode::SUCCESS
+    // a relative path is taken from the directory given by `-C`, as any other path is
+    let profiler = args
+        .profile_json
+        .take()
+        .map(|path| {
+            if path.as_os_str() == "-" {
+                path
+            } else {
+                ctx.cwd().join(path)
+            }
+        })
+        .map(Profiler::new);
+    let metrics = ctx
+        .config()
+        .metrics()
+        .then(|| ctx.config().data_dir())
+        .flatten()
+        .map(|dir| Metrics::new(&dir));
+    let _guard =
+        cli::init_global_subscriber(args.log, ctx.output(), profiler.clone(), metrics.clone());
+
+    let result = cli::run(&ctx, args).await;
+
+    if let Some(Err(e)) = profiler.map(|p| p.write()) {
+        tracing::warn!(message = "Failed to write the profile", error = %e);
+    }
+
+    if let Some(Err(e)) = metrics.map(|m| m.write()) {
+        tracing::warn!(message = "Failed to record usage metrics", error = %e);
+    }
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let status = cli::status(&e);
+            if status == Status::NothingToDo {
+                tracing::info!("{e}");
+            } else {
+                eka::fatal!(e);
+            }
+            status.into()
+        },
     }
 }